@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::commands::Runner;
+use crate::{filelists, i18n, names, prefix, PackageManager};
+
+// What `E` names the file list (see exports::write) unless it goes to the
+// clipboard.
+pub const FILES_NAME: &str = "tpi-files.txt";
+
+// The union of the packages' files, for a backup include-list.
+pub struct Export {
+    pub paths: Vec<String>,
+    pub packages: usize,       // Packages whose files were read
+    pub missing: usize,        // Listed but not on disk, when only existing files count
+    pub unlisted: Vec<String>, // Packages without a file list (other managers, or gone)
+}
+
+enum Progress {
+    Package(usize), // Packages done so far
+    Done(Result<Export, String>),
+}
+
+enum State {
+    Choosing,
+    Running {
+        done: usize,
+        receiver: Receiver<Progress>,
+        cancel: Arc<AtomicBool>,
+    },
+}
+
+// Every file dpkg or pip installed for `packages`, sorted and deduplicated.
+// Directories are left out, since an include-list naming one takes all of
+// it. `subtree` keeps only paths under it; `existing_only` drops (and counts)
+// paths that are no longer there.
+fn collect(
+    runner: &dyn Runner,
+    packages: &[(PackageManager, String)],
+    existing_only: bool,
+    subtree: Option<&str>,
+    cancel: &AtomicBool,
+    progress: &dyn Fn(usize),
+) -> Result<Export, String> {
+    let mut lists: HashMap<PackageManager, Option<HashMap<String, Vec<String>>>> = HashMap::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut export = Export {
+        paths: Vec::new(),
+        packages: 0,
+        missing: 0,
+        unlisted: Vec::new(),
+    };
+    for (done, (manager, name)) in packages.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        // pip's RECORDs spell names their own way; key both sides the same
+        let key = |name: &str| names::canonical(*manager, name);
+        let files = lists
+            .entry(*manager)
+            .or_insert_with(|| match filelists::load(*manager, runner, cancel) {
+                Some(Ok(lists)) => Some(
+                    lists
+                        .iter()
+                        .map(|(name, paths)| (key(name), paths.clone()))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .as_ref()
+            .and_then(|lists| lists.get(&key(name)));
+        let Some(files) = files else {
+            export
+                .unlisted
+                .push(format!("{} ({})", name, manager.name()));
+            progress(done + 1);
+            continue;
+        };
+        export.packages += 1;
+        for path in files {
+            if subtree.is_some_and(|dir| !under(path, dir)) || !seen.insert(path.clone()) {
+                continue;
+            }
+            match fs::symlink_metadata(path) {
+                Ok(meta) if meta.is_dir() => continue,
+                Ok(_) => {}
+                Err(_) if existing_only => {
+                    export.missing += 1;
+                    continue;
+                }
+                Err(_) => {}
+            }
+            export.paths.push(path.clone());
+        }
+        progress(done + 1);
+    }
+    export.paths.sort();
+    Ok(export)
+}
+
+fn under(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches('/');
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+// The export popup: what to keep and where it goes, then progress while the
+// file lists are read in the background.
+pub struct ExportView {
+    pub packages: Vec<(PackageManager, String)>,
+    pub existing_only: bool,
+    pub subtree: String,         // Empty for everything; $PREFIX is expanded
+    pub editing: Option<String>, // Subtree being typed
+    pub to_clipboard: bool,
+    state: State,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ExportView {
+    pub fn new(packages: Vec<(PackageManager, String)>) -> Self {
+        Self {
+            packages,
+            existing_only: false,
+            subtree: String::new(),
+            editing: None,
+            to_clipboard: false,
+            state: State::Choosing,
+            worker: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, State::Running { .. })
+    }
+
+    pub fn start(&mut self, runner: Arc<dyn Runner>) {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let packages = self.packages.clone();
+        let existing_only = self.existing_only;
+        let subtree = match self.subtree.trim() {
+            "" => None,
+            dir => Some(dir.replacen("$PREFIX", &prefix(), 1)),
+        };
+        let flag = cancel.clone();
+        self.worker = Some(thread::spawn(move || {
+            let progress = |done| {
+                let _ = sender.send(Progress::Package(done));
+            };
+            let export = collect(
+                runner.as_ref(),
+                &packages,
+                existing_only,
+                subtree.as_deref(),
+                &flag,
+                &progress,
+            );
+            let _ = sender.send(Progress::Done(export));
+        }));
+        self.state = State::Running {
+            done: 0,
+            receiver,
+            cancel,
+        };
+    }
+
+    // The thread of the last export, finished or not, for the app to join
+    // before it exits.
+    pub fn into_worker(self) -> Option<JoinHandle<()>> {
+        self.worker
+    }
+
+    pub fn cancel(&mut self) {
+        if let State::Running { cancel, .. } = &self.state {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.state = State::Choosing;
+    }
+
+    // Takes in progress from the background thread. Returns whether anything
+    // changed, and the export once it is finished.
+    pub fn poll(&mut self) -> (bool, Option<Result<Export, String>>) {
+        let State::Running { done, receiver, .. } = &mut self.state else {
+            return (false, None);
+        };
+        let mut changed = false;
+        while let Ok(progress) = receiver.try_recv() {
+            changed = true;
+            match progress {
+                Progress::Package(count) => *done = count,
+                Progress::Done(export) => {
+                    self.state = State::Choosing;
+                    return (true, Some(export));
+                }
+            }
+        }
+        (changed, None)
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let count = self.packages.len();
+        let what = if count == 1 { "package" } else { "packages" };
+        let block = Block::default()
+            .title(format!("Export the files of {} {}", count, what))
+            .borders(Borders::ALL);
+        let check = |on: bool| if on { "[x]" } else { "[ ]" };
+        let subtree = match &self.editing {
+            Some(dir) => format!("{}▏", dir),
+            None if self.subtree.trim().is_empty() => "everything".to_string(),
+            None => self.subtree.clone(),
+        };
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines = vec![
+            Line::from(vec![
+                Span::raw(format!(
+                    "{} Existing files only  ",
+                    check(self.existing_only)
+                )),
+                Span::styled("e", dim),
+            ]),
+            Line::from(vec![
+                Span::raw(format!("Under: {}  ", subtree)),
+                Span::styled("t to type a directory, e.g. $PREFIX/etc", dim),
+            ]),
+            Line::from(vec![
+                Span::raw(format!(
+                    "To: {}  ",
+                    if self.to_clipboard {
+                        "the clipboard"
+                    } else {
+                        FILES_NAME
+                    }
+                )),
+                Span::styled("c", dim),
+            ]),
+            Line::from(""),
+        ];
+        lines.push(match &self.state {
+            State::Running { done, .. } => Line::from(format!(
+                "Reading file lists… {}/{} packages (Esc cancels)",
+                done, count
+            )),
+            State::Choosing if self.editing.is_some() => {
+                Line::styled("Enter keeps the directory, Esc drops it", dim)
+            }
+            State::Choosing => Line::styled("Enter export, Esc close", dim),
+        });
+        f.render_widget(Clear, area);
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+// Outcome line for the status bar.
+pub fn summary(export: &Export, destination: &str) -> String {
+    let key = if export.packages == 1 {
+        "message.paths_of_one"
+    } else {
+        "message.paths_of"
+    };
+    let mut text = i18n::fill(
+        key,
+        &[
+            ("paths", &export.paths.len()),
+            ("count", &export.packages),
+            ("destination", &destination),
+        ],
+    );
+    if export.missing > 0 {
+        text.push_str(&i18n::fill(
+            "message.missing_skipped",
+            &[("count", &export.missing)],
+        ));
+    }
+    if !export.unlisted.is_empty() {
+        text.push_str(&i18n::fill(
+            "message.no_file_list",
+            &[("names", &export.unlisted.join(", "))],
+        ));
+    }
+    text
+}
+
+// One path per line, ready for `tar -T` or `rsync --files-from`. Returns
+// the file written.
+pub fn write(export: &Export) -> std::io::Result<std::path::PathBuf> {
+    let mut text = export.paths.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    crate::exports::write(FILES_NAME, &text)
+}
@@ -0,0 +1,297 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::commands::{self, Action, Runner};
+use crate::config;
+use crate::{format_date, parse_package_list, prefix, PackageManager};
+
+// Packages that come with the interpreter and must not be reinstalled by pip.
+const PIP_BUNDLED: [&str; 3] = ["pip", "setuptools", "wheel"];
+
+// Global npm packages that come with Termux's nodejs.
+const NPM_BUNDLED: [&str; 2] = ["npm", "corepack"];
+
+pub struct Section {
+    pub manager: PackageManager,
+    pub names: Vec<String>,
+}
+
+// Every manually installed package across the enabled managers.
+pub fn full_inventory(runner: &dyn Runner) -> Vec<Section> {
+    let enabled = config::managers().enabled;
+    let mut sections = Vec::new();
+
+    // pkg and apt share one dpkg database, so they share one section
+    let dpkg = if enabled.contains(&PackageManager::Apt) {
+        PackageManager::Apt
+    } else {
+        PackageManager::Pkg
+    };
+    if let Some(stdout) = query(runner, &enabled, dpkg, Action::Manual) {
+        let mut names: Vec<String> = stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        names.sort();
+        sections.push(Section {
+            manager: PackageManager::Pkg,
+            names,
+        });
+    }
+
+    // --not-required leaves out packages that are only there as dependencies
+    if let Some(stdout) = query(runner, &enabled, PackageManager::Pip, Action::TopLevel) {
+        let names = parse_package_list(PackageManager::Pip, &stdout)
+            .unwrap_or_default()
+            .packages
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .filter(|name| !PIP_BUNDLED.contains(&name.as_str()))
+            .collect();
+        sections.push(Section {
+            manager: PackageManager::Pip,
+            names,
+        });
+    }
+
+    // luarocks has no notion of manual installs, so every rock is listed once
+    // and its dependencies are pulled in again by `luarocks install`.
+    if let Some(stdout) = query(runner, &enabled, PackageManager::Luarocks, Action::List) {
+        let mut names: Vec<String> = parse_package_list(PackageManager::Luarocks, &stdout)
+            .unwrap_or_default()
+            .packages
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .collect();
+        names.sort();
+        names.dedup();
+        if !names.is_empty() {
+            sections.push(Section {
+                manager: PackageManager::Luarocks,
+                names,
+            });
+        }
+    }
+
+    // --direct leaves out packages composer pulled in as dependencies
+    if let Some(stdout) = query(runner, &enabled, PackageManager::Composer, Action::TopLevel) {
+        let names: Vec<String> = parse_package_list(PackageManager::Composer, &stdout)
+            .unwrap_or_default()
+            .packages
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .collect();
+        if !names.is_empty() {
+            sections.push(Section {
+                manager: PackageManager::Composer,
+                names,
+            });
+        }
+    }
+
+    // --roots leaves out packages opam installed as dependencies; only the
+    // current switch is looked at.
+    if let Some(stdout) = query(runner, &enabled, PackageManager::Opam, Action::TopLevel) {
+        let names: Vec<String> = parse_package_list(PackageManager::Opam, &stdout)
+            .unwrap_or_default()
+            .packages
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .collect();
+        if !names.is_empty() {
+            sections.push(Section {
+                manager: PackageManager::Opam,
+                names,
+            });
+        }
+    }
+
+    // Everything `cargo install` and `npm -g` list was asked for by name.
+    // Crates built from a git checkout or a local path can't be fetched again
+    // by name, so they are left out.
+    for manager in [PackageManager::Cargo, PackageManager::Npm] {
+        if let Some(stdout) = query(runner, &enabled, manager, Action::List) {
+            let names: Vec<String> = parse_package_list(manager, &stdout)
+                .unwrap_or_default()
+                .packages
+                .into_iter()
+                .filter(|pkg| match manager {
+                    PackageManager::Npm => !NPM_BUNDLED.contains(&pkg.name.as_str()),
+                    _ => pkg.repo.is_none(),
+                })
+                .map(|pkg| pkg.name)
+                .collect();
+            if !names.is_empty() {
+                sections.push(Section { manager, names });
+            }
+        }
+    }
+
+    sections
+}
+
+// Output of a successful command, or None when the manager is disabled or the
+// command failed.
+fn query(
+    runner: &dyn Runner,
+    enabled: &[PackageManager],
+    manager: PackageManager,
+    action: Action,
+) -> Option<String> {
+    if !enabled.contains(&manager) {
+        return None;
+    }
+    commands::run_with(runner, manager, action, "")
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Shell script that reinstalls the given packages on a fresh Termux.
+pub fn script(sections: &[Section]) -> String {
+    let mut system: Vec<String> = Vec::new();
+    let mut pip: Vec<String> = Vec::new();
+    let mut rocks: Vec<String> = Vec::new();
+    let mut composer: Vec<String> = Vec::new();
+    let mut opam: Vec<String> = Vec::new();
+    let mut crates: Vec<String> = Vec::new();
+    let mut npm: Vec<String> = Vec::new();
+    for section in sections {
+        match section.manager {
+            PackageManager::Pkg | PackageManager::Apt => {
+                system.extend(section.names.iter().cloned())
+            }
+            PackageManager::Pip => pip.extend(section.names.iter().cloned()),
+            PackageManager::Luarocks => rocks.extend(section.names.iter().cloned()),
+            PackageManager::Composer => composer.extend(section.names.iter().cloned()),
+            PackageManager::Opam => opam.extend(section.names.iter().cloned()),
+            PackageManager::Cargo => crates.extend(section.names.iter().cloned()),
+            PackageManager::Npm => npm.extend(section.names.iter().cloned()),
+        }
+    }
+
+    // Language runtimes go into the system line so they exist before their packages.
+    if !pip.is_empty() && !system.iter().any(|name| name.starts_with("python")) {
+        system.push("python".to_string());
+    }
+    if !rocks.is_empty() && !system.iter().any(|name| name == "luarocks") {
+        system.push("luarocks".to_string());
+    }
+    rocks.sort();
+    rocks.dedup();
+    if !composer.is_empty() && !system.iter().any(|name| name == "composer") {
+        system.push("composer".to_string());
+    }
+    if !crates.is_empty() && !system.iter().any(|name| name == "rust") {
+        system.push("rust".to_string());
+    }
+    if !npm.is_empty() && !system.iter().any(|name| name.starts_with("nodejs")) {
+        system.push("nodejs".to_string());
+    }
+    system.sort();
+    system.dedup();
+
+    let mut out = format!("#!{}/bin/sh\n", prefix());
+    out.push_str(&format!(
+        "# Generated by tpi on {} ({})\n",
+        device_name(),
+        today()
+    ));
+    out.push_str("set -e\n");
+
+    // Names come from the package managers and are quoted as needed, so
+    // none can be taken for more than one word or for shell syntax.
+    let words = commands::join_words;
+
+    if !system.is_empty() {
+        out.push_str(&format!("\npkg install -y {}\n", words(&system)));
+    }
+    if !pip.is_empty() {
+        out.push_str(&format!("\npip install {}\n", words(&pip)));
+    }
+    // luarocks installs one rock per invocation
+    if !rocks.is_empty() {
+        out.push('\n');
+        for rock in &rocks {
+            out.push_str(&format!(
+                "luarocks install {}\n",
+                words(std::slice::from_ref(rock))
+            ));
+        }
+    }
+    if !composer.is_empty() {
+        out.push_str(&format!("\ncomposer global require {}\n", words(&composer)));
+    }
+    // Termux doesn't package opam, so it has to be set up by hand first
+    if !opam.is_empty() {
+        out.push_str(&format!(
+            "\n# Needs opam with an initialized switch (opam init)\nopam install -y {}\n",
+            words(&opam)
+        ));
+    }
+
+    if !crates.is_empty() {
+        out.push_str(&format!("\ncargo install {}\n", words(&crates)));
+    }
+    if !npm.is_empty() {
+        out.push_str(&format!("\nnpm install -g {}\n", words(&npm)));
+    }
+
+    out
+}
+
+pub fn write_script(path: &Path, text: &str) -> io::Result<()> {
+    fs::write(path, text)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+// As `name` in the export directory, next to any script written before
+// rather than over it (see exports::write). Returns the path written.
+pub fn write_export(name: &str, text: &str) -> io::Result<PathBuf> {
+    let path = crate::exports::write(name, text)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
+
+fn command_lines(program: &str, args: &[&str]) -> Option<Vec<String>> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+fn device_name() -> String {
+    for (program, args) in [
+        ("getprop", &["ro.product.model"][..]),
+        ("uname", &["-n"][..]),
+    ] {
+        if let Some(name) = command_lines(program, args).and_then(|lines| lines.into_iter().next())
+        {
+            return name;
+        }
+    }
+    "unknown device".to_string()
+}
+
+// Current UTC date as YYYY-MM-DD.
+fn today() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_date(secs)
+}
@@ -0,0 +1,902 @@
+#[cfg(feature = "reports")]
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::commands::{self, Action, Runner, SystemRunner};
+use crate::config;
+use crate::doctor;
+use crate::health::{self, Check};
+use crate::history;
+use crate::inspect;
+use crate::inventory;
+use crate::schema;
+use crate::serve;
+use crate::stats;
+#[cfg(feature = "reports")]
+use crate::{bootstrap, report};
+use crate::{Package, PackageList, PackageManager};
+
+const USAGE: &str = "\
+Usage: tpi [OPTIONS] [COMMAND]
+
+Without a command, starts the interactive browser.
+
+Options:
+  --read-only
+      Disable every action that changes installed packages
+      (also enabled by TPI_READ_ONLY=1)
+  --manager NAME
+      Start with this package manager instead of the first enabled one
+  --watch SECS
+      Re-list the packages every SECS seconds and highlight what changed
+  --fast
+      List names and versions only, skipping holds, sizes, why-installed
+      and version history (also TPI_PROFILE=fast)
+  --full
+      Do all of that work even when TPI_PROFILE=fast
+  --no-alt-screen
+      Draw in the bottom TPI_INLINE_HEIGHT rows (20) of the terminal instead
+      of the alternate screen, leaving the last frame in the scrollback on
+      exit (also TPI_NO_ALT_SCREEN=1)
+  --export FILE
+      Write the installed packages to FILE instead of starting the browser,
+      as JSON or CSV by its extension: the --manager one's (or the first
+      enabled manager that lists)
+  --export-all
+      With --export, every enabled manager's packages
+  --tour
+      Start with the tour of the screen, even when it was taken before
+  --config FILE
+      Read startup defaults and keys from FILE instead of
+      ~/.config/termux-package-inspector/config.toml (also TPI_CONFIG)
+  --list-if-not-tty
+      When there is no terminal to draw on (output piped or redirected, a
+      cron job), print what `tpi list` does instead of exiting with status 3
+  --startup-trace
+      Log how long each startup step took (terminal setup, config load,
+      cache read, first parse, first full list paint) to TPI_LOG, or to
+      $TMPDIR/tpi.log when it isn't set
+
+Commands:
+  list [--manager NAME] [--format plain|json|tsv]
+      Print the installed packages: plain `name version` lines, JSON, or
+      tab-separated name, version, architecture, repository and description
+  show NAME [--manager NAME]
+      Print what the package manager shows about an installed package
+  inspect-file PATH
+      Print the fields and files of a .deb (with dpkg-deb) or a wheel (with
+      python) without installing it; exits 1 when it can't be read
+  count [--manager NAME] [--format plain|json|tsv]
+      Print how many packages each installed package manager lists
+  stats [--manager NAME]
+      Print the statistics of the L view as JSON: package counts per
+      manager, sizes, install reasons, repositories, install dates, and
+      upgradable, held and broken packages
+  health [--json]
+      Run every health check; exits 1 when any check finds problems
+  doctor [--json]
+      Check the environment tpi runs in (package managers, dpkg database,
+      python, termux-api, settings, files, terminal); exits 1 when tpi
+      can't work here
+  snapshot
+      Record the installed versions of every available package manager in
+      the version history (a no-op when nothing changed)
+  serve [--port PORT] [--once DIR]
+      Serve read-only JSON on 127.0.0.1:PORT (TPI_SERVE_PORT, 7377):
+      /packages?manager=NAME, /package/MANAGER/NAME, /outdated, /health.
+      With TPI_SERVE_TOKEN set, requests need it in an X-Tpi-Token header.
+      --once writes packages.json, outdated.json and health.json to DIR
+      and exits instead
+  help
+      Show this message";
+
+#[cfg(feature = "reports")]
+const REPORTS_USAGE: &str = "
+  report --markdown --packages a,b,c [--manager NAME] [--output FILE]
+      Write a Markdown report of the given packages
+  bootstrap [--packages a,b,c [--manager NAME]] [--output FILE]
+      Write a shell script that reinstalls every manually installed package,
+      or only the given ones";
+
+// Subcommands of optional features are only listed when compiled in.
+fn usage() -> String {
+    #[allow(unused_mut)]
+    let mut text = USAGE.to_string();
+    #[cfg(feature = "reports")]
+    text.push_str(REPORTS_USAGE);
+    text
+}
+
+// Options that apply to the TUI and to every subcommand.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub read_only: bool,
+    pub watch: Option<u64>, // Refresh interval in seconds
+    pub manager: Option<PackageManager>,
+    pub profile: config::Profile,
+    pub no_alt_screen: bool, // Inline viewport instead of the alternate screen
+    pub export: Option<String>, // Write the inventory here and exit
+    pub export_all: bool,    // Of every enabled manager
+    pub tour: bool,          // Show the first-run tour again
+    pub config: Option<String>, // Config file instead of the usual one
+    pub list_if_not_tty: bool, // Fall back to `list` without a terminal
+    pub startup_trace: bool, // Log how long each startup step took
+}
+
+impl Options {
+    // Takes the leading options off `args`, leaving the subcommand (if any).
+    pub fn parse(args: &mut Vec<String>) -> Result<Self, String> {
+        let mut options = Options {
+            read_only: matches!(
+                std::env::var("TPI_READ_ONLY").as_deref(),
+                Ok("1" | "true" | "yes")
+            ),
+            watch: None,
+            manager: None,
+            profile: config::profile(),
+            no_alt_screen: config::no_alt_screen(),
+            export: None,
+            export_all: false,
+            tour: false,
+            config: None,
+            list_if_not_tty: false,
+            startup_trace: false,
+        };
+
+        while let Some(arg) = args.first() {
+            match arg.as_str() {
+                "--read-only" => options.read_only = true,
+                "--fast" => options.profile = config::Profile::Fast,
+                "--full" => options.profile = config::Profile::Full,
+                "--no-alt-screen" => options.no_alt_screen = true,
+                "--export-all" => options.export_all = true,
+                "--tour" => options.tour = true,
+                "--list-if-not-tty" => options.list_if_not_tty = true,
+                "--startup-trace" => options.startup_trace = true,
+                "--config" => {
+                    match args.get(1).filter(|path| !path.starts_with('-')) {
+                        Some(path) => options.config = Some(path.clone()),
+                        None => return Err("tpi: --config needs a file name".to_string()),
+                    }
+                    args.remove(0);
+                }
+                "--export" => {
+                    match args.get(1).filter(|path| !path.starts_with('-')) {
+                        Some(path) => options.export = Some(path.clone()),
+                        None => return Err("tpi: --export needs a file name".to_string()),
+                    }
+                    args.remove(0);
+                }
+                "--manager" => {
+                    match args.get(1).and_then(|name| PackageManager::from_name(name)) {
+                        Some(manager) => options.manager = Some(manager),
+                        None => return Err(format!("tpi: --manager: {}", manager_names())),
+                    }
+                    args.remove(0);
+                }
+                "--watch" => {
+                    let secs = args.get(1).and_then(|secs| secs.parse::<u64>().ok());
+                    match secs {
+                        Some(secs) if secs > 0 => options.watch = Some(secs),
+                        _ => return Err("tpi: --watch needs a number of seconds".to_string()),
+                    }
+                    args.remove(0);
+                }
+                "-h" | "--help" => break,
+                flag if flag.starts_with('-') => {
+                    return Err(format!("tpi: unknown option '{}'\n\n{}", flag, usage()))
+                }
+                _ => break,
+            }
+            args.remove(0);
+        }
+
+        if options.export_all && options.export.is_none() {
+            return Err("tpi: --export-all goes with --export FILE".to_string());
+        }
+        Ok(options)
+    }
+}
+
+// Runs a headless subcommand and returns the process exit code. None of the
+// current subcommands change installed packages, so read-only mode needs no
+// special handling here.
+pub fn run(args: &[String], options: &Options) -> i32 {
+    match args[0].as_str() {
+        #[cfg(feature = "reports")]
+        "report" => report(&args[1..]),
+        #[cfg(feature = "reports")]
+        "bootstrap" => bootstrap(&args[1..]),
+        "list" => list(&args[1..], options),
+        "show" => show(&args[1..], options),
+        "count" => count(&args[1..], options),
+        "inspect-file" => inspect_file(&args[1..]),
+        "stats" => stats(&args[1..], options),
+        "health" => health(&args[1..]),
+        "doctor" => doctor(&args[1..]),
+        "snapshot" => snapshot(),
+        "serve" => serve(&args[1..], options),
+        "-h" | "--help" | "help" => {
+            println!("{}", usage());
+            0
+        }
+        other => {
+            eprintln!("tpi: unknown command '{}'\n\n{}", other, usage());
+            2
+        }
+    }
+}
+
+// Why the browser can't run here, if it can't: it draws on stdout and reads
+// keys from stdin, or from /dev/tty when stdin is redirected (crossterm
+// falls back to it), so stdout must be a terminal and one of those too.
+pub fn no_terminal() -> Option<&'static str> {
+    if !io::stdout().is_terminal() {
+        return Some("output is not a terminal");
+    }
+    let tty = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty");
+    if !io::stdin().is_terminal() && tty.is_err() {
+        return Some("there is no terminal to read keys from");
+    }
+    None
+}
+
+// Without a terminal and without a command: `tpi list` with
+// --list-if-not-tty, else a pointer to the commands and status 3, so a
+// script can tell this from a failing command.
+pub fn without_terminal(reason: &str, options: &Options) -> i32 {
+    if options.list_if_not_tty {
+        return list(&[], options);
+    }
+    eprintln!(
+        "tpi: {}, so the browser can't start. In scripts and pipes, use a command:
+  tpi list [--format plain|json|tsv]
+  tpi show NAME
+  tpi count
+or --list-if-not-tty to print the list in this case. `tpi help` lists them all.",
+        reason
+    );
+    3
+}
+
+// `--export FILE`: the inventory without starting the TUI.
+pub fn export(options: &Options) -> i32 {
+    let Some(path) = &options.export else {
+        return 2;
+    };
+    if let Err(err) = inventory::Format::from_path(path) {
+        eprintln!("tpi: --export {}", err);
+        return 2;
+    }
+    let runner: Arc<dyn Runner> = Arc::new(SystemRunner);
+    let enabled = config::managers().enabled;
+    let lists: Vec<PackageList> = if options.export_all {
+        let mut lists = Vec::new();
+        for manager in enabled {
+            if !commands::is_available(manager) {
+                continue;
+            }
+            let list = PackageList::load(manager, runner.clone(), config::Profile::Fast);
+            match &list.load_error {
+                Some(err) => eprintln!("tpi: skipping {}: {}", manager.name(), err),
+                None => lists.push(list),
+            }
+        }
+        lists
+    } else {
+        let (list, errors) =
+            crate::first_list(runner, &enabled, options.manager, config::Profile::Fast);
+        if list.load_error.is_some() {
+            for err in &errors {
+                eprintln!("tpi: {}", err);
+            }
+            return 1;
+        }
+        vec![list]
+    };
+    let packages: Vec<(PackageManager, &Package)> = lists
+        .iter()
+        .flat_map(|list| list.items.iter().map(|pkg| (list.package_manager, pkg)))
+        .collect();
+    match inventory::write(path, &packages) {
+        Ok(written) => {
+            println!(
+                "Exported {} packages to {}",
+                packages.len(),
+                written.display()
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("tpi: {}", err);
+            1
+        }
+    }
+}
+
+// How `list` and `count` print. No format colours its output.
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Plain,
+    Json,
+    Tsv,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plain" => Some(Format::Plain),
+            "json" => Some(Format::Json),
+            "tsv" => Some(Format::Tsv),
+            _ => None,
+        }
+    }
+}
+
+// `--manager` and `--format` after a headless listing command, and any
+// other arguments in order. The leading --manager is the default.
+struct ListArgs {
+    manager: Option<PackageManager>,
+    format: Format,
+    rest: Vec<String>,
+}
+
+fn list_args(command: &str, args: &[String], options: &Options) -> Result<ListArgs, i32> {
+    let mut parsed = ListArgs {
+        manager: options.manager,
+        format: Format::Plain,
+        rest: Vec::new(),
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--manager" => match iter.next().map(|name| PackageManager::from_name(name)) {
+                Some(Some(manager)) => parsed.manager = Some(manager),
+                _ => {
+                    eprintln!("tpi {}: --manager: {}", command, manager_names());
+                    return Err(2);
+                }
+            },
+            "--format" => match iter.next().and_then(|name| Format::from_name(name)) {
+                Some(format) => parsed.format = format,
+                None => {
+                    eprintln!("tpi {}: --format: expected plain, json or tsv", command);
+                    return Err(2);
+                }
+            },
+            flag if flag.starts_with('-') => {
+                eprintln!("tpi {}: unexpected argument '{}'", command, flag);
+                return Err(2);
+            }
+            _ => parsed.rest.push(arg.clone()),
+        }
+    }
+    Ok(parsed)
+}
+
+// The --manager one, else the first enabled manager.
+fn default_manager(asked: Option<PackageManager>) -> PackageManager {
+    asked.unwrap_or_else(|| {
+        config::managers()
+            .enabled
+            .first()
+            .copied()
+            .unwrap_or(PackageManager::Pkg)
+    })
+}
+
+// Tabs and line breaks would split a TSV field.
+fn tsv_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+// `list`: the parsed list, as the TUI would show it, without the TUI.
+fn list(args: &[String], options: &Options) -> i32 {
+    let parsed = match list_args("list", args, options) {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    if let Some(arg) = parsed.rest.first() {
+        eprintln!("tpi list: unexpected argument '{}'", arg);
+        return 2;
+    }
+    let manager = default_manager(parsed.manager);
+    let listing = match crate::fetch_listing(&SystemRunner, manager, None) {
+        Ok(listing) => listing,
+        Err(err) => {
+            eprintln!("tpi list: {}", err);
+            return 1;
+        }
+    };
+    match parsed.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&schema::list_cache(manager, &listing.packages))
+                .unwrap_or_default()
+        ),
+        Format::Tsv => {
+            for pkg in &listing.packages {
+                let field = |value: &Option<String>| tsv_field(value.as_deref().unwrap_or(""));
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    tsv_field(&pkg.name),
+                    tsv_field(&pkg.version),
+                    field(&pkg.arch),
+                    field(&pkg.repo),
+                    field(&pkg.description)
+                );
+            }
+        }
+        Format::Plain => {
+            for pkg in &listing.packages {
+                println!("{} {}", pkg.name, pkg.version);
+            }
+        }
+    }
+    0
+}
+
+// `show NAME`: the raw `show` output. The package is looked up in the list
+// first, since luarocks and npm need its version.
+fn show(args: &[String], options: &Options) -> i32 {
+    let parsed = match list_args("show", args, options) {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    let [name] = &parsed.rest[..] else {
+        eprintln!("tpi show: expected one package name");
+        return 2;
+    };
+    let manager = default_manager(parsed.manager);
+    let listing = match crate::fetch_listing(&SystemRunner, manager, None) {
+        Ok(listing) => listing,
+        Err(err) => {
+            eprintln!("tpi show: {}", err);
+            return 1;
+        }
+    };
+    let Some(pkg) = listing.packages.iter().find(|pkg| &pkg.name == name) else {
+        eprintln!(
+            "tpi show: '{}' is not installed via {}",
+            name,
+            manager.name()
+        );
+        return 1;
+    };
+    match commands::run_for_package(&SystemRunner, manager, Action::Show, name, &pkg.version) {
+        Ok(output) if output.status.success() => {
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+            0
+        }
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match stderr.trim() {
+                "" => eprintln!("tpi show: {} show {}", manager.name(), output.status),
+                stderr => eprintln!("tpi show: {}", stderr),
+            }
+            1
+        }
+        Err(err) => {
+            eprintln!("tpi show: {}", err);
+            1
+        }
+    }
+}
+
+// `inspect-file`: what Ctrl+o shows, as text.
+fn inspect_file(args: &[String]) -> i32 {
+    let [path] = args else {
+        eprintln!("tpi inspect-file: expected one .deb or .whl file");
+        return 2;
+    };
+    match inspect::inspect(&SystemRunner, path) {
+        Ok(inspected) => {
+            print!("{}", inspect::text(&inspected));
+            0
+        }
+        Err(err) => {
+            eprintln!("tpi inspect-file: {}", err);
+            1
+        }
+    }
+}
+
+// `count`: packages per manager. Without --manager, every enabled manager
+// that is installed; one that fails to list makes the exit code 1.
+fn count(args: &[String], options: &Options) -> i32 {
+    let parsed = match list_args("count", args, options) {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    if let Some(arg) = parsed.rest.first() {
+        eprintln!("tpi count: unexpected argument '{}'", arg);
+        return 2;
+    }
+    let managers = match parsed.manager {
+        Some(manager) => vec![manager],
+        None => config::managers()
+            .enabled
+            .into_iter()
+            .filter(|manager| commands::is_available(*manager))
+            .collect(),
+    };
+    let counts: Vec<(PackageManager, Result<usize, String>)> = managers
+        .into_iter()
+        .map(|manager| {
+            let count = crate::fetch_listing(&SystemRunner, manager, None)
+                .map(|listing| listing.packages.len())
+                .map_err(|err| err.to_string());
+            (manager, count)
+        })
+        .collect();
+    match parsed.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&schema::counts(&counts)).unwrap_or_default()
+        ),
+        Format::Plain | Format::Tsv => {
+            let separator = if parsed.format == Format::Tsv {
+                "\t"
+            } else {
+                " "
+            };
+            for (manager, count) in &counts {
+                match count {
+                    Ok(count) => println!("{}{}{}", manager.name(), separator, count),
+                    Err(err) => eprintln!("tpi count: {}", err),
+                }
+            }
+        }
+    }
+    if counts.iter().any(|(_, count)| count.is_err()) {
+        1
+    } else {
+        0
+    }
+}
+
+// `stats`: what the L view shows, as JSON. The manager's list is read in
+// full, with sizes, holds and the why-installed graph; the others are only
+// counted, and for dpkg the broken-state health check is run. Upgrades
+// aren't checked, so `upgradable` is null.
+fn stats(args: &[String], options: &Options) -> i32 {
+    let parsed = match list_args("stats", args, options) {
+        Ok(parsed) => parsed,
+        Err(code) => return code,
+    };
+    if let Some(arg) = parsed.rest.first() {
+        eprintln!("tpi stats: unexpected argument '{}'", arg);
+        return 2;
+    }
+    if parsed.format != Format::Json && args.iter().any(|arg| arg == "--format") {
+        eprintln!("tpi stats: --format: only json");
+        return 2;
+    }
+    let manager = default_manager(parsed.manager);
+    let list = PackageList::load(manager, Arc::new(SystemRunner), config::Profile::Full);
+    if let Some(err) = &list.load_error {
+        eprintln!("tpi stats: {}", err);
+        return 1;
+    }
+    let others = config::managers()
+        .enabled
+        .into_iter()
+        .filter(|&other| other != manager && commands::is_available(other))
+        .filter_map(|other| {
+            let listing = crate::fetch_listing(&SystemRunner, other, None).ok()?;
+            Some((other, listing.packages.len(), false))
+        })
+        .collect();
+    let broken = matches!(manager, PackageManager::Pkg | PackageManager::Apt)
+        .then(|| Check::BrokenState.run(&SystemRunner, &AtomicBool::new(false)))
+        .and_then(|outcome| outcome.ok())
+        .map(|findings| findings.len());
+    let stats = stats::Stats::collect(&list, others, broken);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema::stats(&stats)).unwrap_or_default()
+    );
+    0
+}
+
+fn snapshot() -> i32 {
+    let Some(path) = history::path() else {
+        eprintln!("tpi snapshot: no history location (set HOME or TPI_HISTORY)");
+        return 1;
+    };
+    let mut status = 0;
+    for manager in config::managers().enabled {
+        if !commands::is_available(manager) {
+            eprintln!("tpi snapshot: skipping {}: not installed", manager.name());
+            continue;
+        }
+        let list = PackageList::load(manager, Arc::new(SystemRunner), config::Profile::Full);
+        if let Some(err) = &list.load_error {
+            eprintln!("tpi snapshot: {}", err);
+            status = 1;
+        }
+    }
+    println!("History: {}", path.display());
+    status
+}
+
+fn serve(args: &[String], options: &Options) -> i32 {
+    let mut port = config::serve_port();
+    let mut once = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--port" => match iter.next().and_then(|port| port.parse().ok()) {
+                Some(value) if value > 0 => port = value,
+                _ => {
+                    eprintln!("tpi serve: --port needs a port number");
+                    return 2;
+                }
+            },
+            "--once" => match iter.next() {
+                Some(dir) => once = Some(dir.clone()),
+                None => {
+                    eprintln!("tpi serve: --once needs a directory");
+                    return 2;
+                }
+            },
+            other => {
+                eprintln!("tpi serve: unexpected argument '{}'", other);
+                return 2;
+            }
+        }
+    }
+
+    let server = serve::Server::new(Arc::new(SystemRunner), options.profile);
+    let result = match once {
+        Some(dir) => server.dump(Path::new(&dir)).map(|written| {
+            for path in written {
+                println!("{}", path);
+            }
+        }),
+        None => server.listen(port),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("tpi serve: {}", err);
+            1
+        }
+    }
+}
+
+fn health(args: &[String]) -> i32 {
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            other => {
+                eprintln!("tpi health: unexpected argument '{}'", other);
+                return 2;
+            }
+        }
+    }
+
+    let results = health::run_all(Arc::new(SystemRunner), &Check::ALL);
+    let healthy = results
+        .iter()
+        .all(|(_, outcome)| matches!(outcome, Ok(findings) if findings.is_empty()));
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&schema::health(&results)).unwrap_or_default()
+        );
+    } else {
+        for (check, outcome) in &results {
+            match outcome {
+                Ok(findings) if findings.is_empty() => println!("pass   {}", check.label()),
+                Ok(findings) => {
+                    println!("FAIL   {} ({})", check.label(), findings.len());
+                    for finding in findings {
+                        println!("         {}: {}", finding.package, finding.detail);
+                    }
+                }
+                Err(err) => println!("error  {}: {}", check.label(), err),
+            }
+        }
+    }
+
+    if healthy {
+        0
+    } else {
+        1
+    }
+}
+
+fn doctor(args: &[String]) -> i32 {
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            other => {
+                eprintln!("tpi doctor: unexpected argument '{}'", other);
+                return 2;
+            }
+        }
+    }
+
+    let items = doctor::run(&SystemRunner);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&schema::doctor(&items)).unwrap_or_default()
+        );
+    } else {
+        let mut section = "";
+        for item in &items {
+            if item.section != section {
+                section = item.section;
+                println!("{}", section);
+            }
+            let status = match item.level {
+                doctor::Level::Ok => "ok",
+                doctor::Level::Warn => "warn",
+                doctor::Level::Fail => "FAIL",
+            };
+            println!("  {:<5} {}: {}", status, item.name, item.detail);
+        }
+    }
+
+    if items.iter().any(|item| item.level == doctor::Level::Fail) {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(feature = "reports")]
+fn report(args: &[String]) -> i32 {
+    let mut manager = PackageManager::Pkg;
+    let mut packages: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--markdown" => {} // The only format for now
+            "--packages" => match iter.next() {
+                Some(list) => packages.extend(
+                    list.split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(String::from),
+                ),
+                None => return missing_value(arg),
+            },
+            "--manager" => match iter.next().map(|name| PackageManager::from_name(name)) {
+                Some(Some(m)) => manager = m,
+                Some(None) => {
+                    eprintln!("tpi report: unknown package manager ({})", manager_names());
+                    return 2;
+                }
+                None => return missing_value(arg),
+            },
+            "--output" | "-o" => match iter.next() {
+                Some(path) => output = Some(path.clone()),
+                None => return missing_value(arg),
+            },
+            other => {
+                eprintln!("tpi report: unexpected argument '{}'", other);
+                return 2;
+            }
+        }
+    }
+
+    if packages.is_empty() {
+        eprintln!("tpi report: --packages is required");
+        return 2;
+    }
+
+    let list = PackageList::load(manager, Arc::new(SystemRunner), config::Profile::Full);
+    let mut entries = Vec::new();
+    for name in &packages {
+        match list.items.iter().find(|pkg| &pkg.name == name) {
+            Some(pkg) => entries.push(list.report_entry(pkg)),
+            None => {
+                eprintln!(
+                    "tpi report: '{}' is not installed via {}",
+                    name,
+                    manager.name()
+                );
+                return 1;
+            }
+        }
+    }
+
+    let text = report::markdown(&entries);
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, text) {
+                eprintln!("tpi report: cannot write {}: {}", path, err);
+                return 1;
+            }
+        }
+        None => print!("{}", text),
+    }
+    0
+}
+
+#[cfg(feature = "reports")]
+fn bootstrap(args: &[String]) -> i32 {
+    let mut manager = PackageManager::Pkg;
+    let mut packages: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--packages" => match iter.next() {
+                Some(list) => packages.extend(
+                    list.split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(String::from),
+                ),
+                None => return missing_value(arg),
+            },
+            "--manager" => match iter.next().map(|name| PackageManager::from_name(name)) {
+                Some(Some(m)) => manager = m,
+                Some(None) => {
+                    eprintln!(
+                        "tpi bootstrap: unknown package manager ({})",
+                        manager_names()
+                    );
+                    return 2;
+                }
+                None => return missing_value(arg),
+            },
+            "--output" | "-o" => match iter.next() {
+                Some(path) => output = Some(path.clone()),
+                None => return missing_value(arg),
+            },
+            other => {
+                eprintln!("tpi bootstrap: unexpected argument '{}'", other);
+                return 2;
+            }
+        }
+    }
+
+    let sections = if packages.is_empty() {
+        bootstrap::full_inventory(&SystemRunner)
+    } else {
+        vec![bootstrap::Section {
+            manager,
+            names: packages,
+        }]
+    };
+
+    let text = bootstrap::script(&sections);
+    match output {
+        Some(path) => {
+            if let Err(err) = bootstrap::write_script(Path::new(&path), &text) {
+                eprintln!("tpi bootstrap: cannot write {}: {}", path, err);
+                return 1;
+            }
+        }
+        None => print!("{}", text),
+    }
+    0
+}
+
+fn manager_names() -> String {
+    let names: Vec<&str> = PackageManager::ALL.iter().map(|m| m.name()).collect();
+    format!("expected one of {}", names.join(", "))
+}
+
+#[cfg(feature = "reports")]
+fn missing_value(flag: &str) -> i32 {
+    eprintln!("tpi: {} needs a value", flag);
+    2
+}
@@ -0,0 +1,130 @@
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::i18n;
+
+// Copy and paste commands, tried in order: termux-api's on Android, then
+// wl-clipboard and xclip on desktops.
+const COPY: [&[&str]; 3] = [
+    &["termux-clipboard-set"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+];
+const PASTE: [&[&str]; 3] = [
+    &["termux-clipboard-get"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-o"],
+];
+
+// Puts `text` on the clipboard. Returns what did it: the first command that
+// takes it, else an OSC 52 sequence to the terminal, which copies on the
+// machine the terminal runs on, so over SSH too (if the terminal allows
+// it; nothing says whether it did).
+pub fn copy(text: &str) -> Result<&'static str, String> {
+    for tool in COPY {
+        let Ok(mut child) = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        if child.wait().is_ok_and(|status| status.success()) && written {
+            return Ok(tool[0]);
+        }
+    }
+    let mut stdout = io::stdout();
+    if !stdout.is_terminal() {
+        return Err(i18n::text("message.no_clipboard").to_string());
+    }
+    stdout
+        .write_all(osc52(text).as_bytes())
+        .and_then(|()| stdout.flush())
+        .map_err(|err| i18n::fill("message.terminal_failed", &[("error", &err)]))?;
+    Ok("OSC 52")
+}
+
+pub fn paste() -> Option<String> {
+    PASTE.iter().find_map(|tool| {
+        let output = Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    })
+}
+
+// "Set the clipboard selection to" the base64 of the text, ended by BEL.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+// Standard alphabet, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 63) as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_to_whole_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn base64_uses_the_whole_alphabet() {
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(base64(&[0, 0, 0]), "AAAA");
+        assert_eq!(base64(&[0xff]), "/w==");
+    }
+
+    #[test]
+    fn base64_of_non_ascii_text() {
+        // UTF-8 bytes, as the terminal decodes them
+        assert_eq!(base64("ñ".as_bytes()), "w7E=");
+        assert_eq!(base64("café".as_bytes()), "Y2Fmw6k=");
+        assert_eq!(base64("✓ bash".as_bytes()), "4pyTIGJhc2g=");
+    }
+
+    #[test]
+    fn osc52_wraps_the_base64() {
+        assert_eq!(osc52(""), "\x1b]52;c;\x07");
+        assert_eq!(osc52("bash 5.2"), "\x1b]52;c;YmFzaCA1LjI=\x07");
+    }
+}
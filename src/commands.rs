@@ -0,0 +1,635 @@
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use crate::log;
+use crate::PackageManager;
+
+// Everything we ask a package manager to do. Each (manager, action) pair has a
+// default command template that can be replaced with $TPI_<MANAGER>_<ACTION>_CMD,
+// e.g. TPI_APT_SHOW_CMD="apt-cache show {name}".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    List,
+    Show,
+    Manual,
+    TopLevel,
+    Held,
+    Hold,
+    Unhold,
+    Arch,
+    Home,
+    Status,
+    Autoremove,
+    Ldd,
+    Owner,
+    ImportCheck,
+    Depends,
+    Rdepends,
+    Policy,
+    Sizes,
+    Files,
+    PyVersion,
+    Metadata,
+    Records,
+    Version,
+    SimulateRemove,
+    Switch,
+    Switches,
+    ListSwitch,
+    Upgradable,
+    Install,
+    Remove,
+}
+
+impl Action {
+    pub const ALL: [Action; 30] = [
+        Action::List,
+        Action::Show,
+        Action::Manual,
+        Action::TopLevel,
+        Action::Held,
+        Action::Hold,
+        Action::Unhold,
+        Action::Arch,
+        Action::Home,
+        Action::Status,
+        Action::Autoremove,
+        Action::Ldd,
+        Action::Owner,
+        Action::ImportCheck,
+        Action::Depends,
+        Action::Rdepends,
+        Action::Policy,
+        Action::Sizes,
+        Action::Files,
+        Action::PyVersion,
+        Action::Metadata,
+        Action::Records,
+        Action::Version,
+        Action::SimulateRemove,
+        Action::Switch,
+        Action::Switches,
+        Action::ListSwitch,
+        Action::Upgradable,
+        Action::Install,
+        Action::Remove,
+    ];
+
+    // Actions that change the package database; refused in read-only mode.
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Action::Hold | Action::Unhold | Action::Install | Action::Remove
+        )
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Action::List => "LIST",
+            Action::Show => "SHOW",
+            Action::Manual => "MANUAL",
+            Action::TopLevel => "TOPLEVEL",
+            Action::Held => "HELD",
+            Action::Hold => "HOLD",
+            Action::Unhold => "UNHOLD",
+            Action::Arch => "ARCH",
+            Action::Home => "HOME",
+            Action::Status => "STATUS",
+            Action::Autoremove => "AUTOREMOVE",
+            Action::Ldd => "LDD",
+            Action::Owner => "OWNER",
+            Action::ImportCheck => "IMPORTCHECK",
+            Action::Depends => "DEPENDS",
+            Action::Rdepends => "RDEPENDS",
+            Action::Policy => "POLICY",
+            Action::Sizes => "SIZES",
+            Action::Files => "FILES",
+            Action::PyVersion => "PYVERSION",
+            Action::Metadata => "METADATA",
+            Action::Records => "RECORDS",
+            Action::Version => "VERSION",
+            Action::SimulateRemove => "SIMULATEREMOVE",
+            Action::Switch => "SWITCH",
+            Action::Switches => "SWITCHES",
+            Action::ListSwitch => "LISTSWITCH",
+            Action::Upgradable => "UPGRADABLE",
+            Action::Install => "INSTALL",
+            Action::Remove => "REMOVE",
+        }
+    }
+}
+
+fn default_template(manager: PackageManager, action: Action) -> Option<&'static str> {
+    match (manager, action) {
+        (PackageManager::Pkg, Action::List) => Some("pkg list-installed"),
+        (PackageManager::Apt, Action::List) => Some("apt list --installed"),
+        (PackageManager::Pip, Action::List) => Some("pip list"),
+        (PackageManager::Pkg, Action::Show) => Some("pkg show {name}"),
+        (PackageManager::Apt, Action::Show) => Some("apt show {name}"),
+        (PackageManager::Pip, Action::Show) => Some("pip show {name}"),
+        (PackageManager::Luarocks, Action::List) => Some("luarocks list --porcelain"),
+        (PackageManager::Luarocks, Action::Show) => Some("luarocks show {name} {version}"),
+        (PackageManager::Composer, Action::List) => Some("composer global show --format=json"),
+        (PackageManager::Composer, Action::TopLevel) => {
+            Some("composer global show --direct --format=json")
+        }
+        (PackageManager::Composer, Action::Show) => Some("composer global show {name}"),
+        (PackageManager::Composer, Action::Home) => Some("composer config --global home"),
+        // opam lists the current switch; LISTSWITCH takes a switch name as `{name}`
+        (PackageManager::Opam, Action::List) => Some(
+            "opam list --installed --columns=name,installed-version '--separator=\t' --color=never",
+        ),
+        (PackageManager::Opam, Action::ListSwitch) => Some(
+            "opam list --installed --switch={name} --columns=name,installed-version '--separator=\t' --color=never",
+        ),
+        (PackageManager::Opam, Action::TopLevel) => Some(
+            "opam list --installed --roots --columns=name,installed-version '--separator=\t' --color=never",
+        ),
+        (PackageManager::Opam, Action::Show) => Some("opam show {name}"),
+        (PackageManager::Cargo, Action::List) => Some("cargo install --list"),
+        // crates.io's entry for the crate; cargo keeps nothing else about it locally
+        (PackageManager::Cargo, Action::Show) => Some("cargo search {name} --limit 1"),
+        (PackageManager::Npm, Action::List) => Some("npm list -g --depth=0 --json"),
+        (PackageManager::Npm, Action::Show) => Some("npm view {name}@{version}"),
+        (PackageManager::Opam, Action::Switch) => Some("opam switch show"),
+        (PackageManager::Opam, Action::Switches) => Some("opam switch list --short"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Manual) => Some("apt-mark showmanual"),
+        (PackageManager::Pip, Action::TopLevel) => Some("pip list --not-required"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Held) => Some("apt-mark showhold"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Hold) => Some("apt-mark hold {name}"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Unhold) => {
+            Some("apt-mark unhold {name}")
+        }
+        (PackageManager::Pkg | PackageManager::Apt, Action::Policy) => {
+            Some("apt-cache policy {name}")
+        }
+        (PackageManager::Pkg | PackageManager::Apt, Action::Sizes) => {
+            Some("dpkg-query -W '-f=${Package}\t${Installed-Size}\n'")
+        }
+        (PackageManager::Pip, Action::Sizes) => Some(PIP_SIZES),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Files) => Some("dpkg -L {name}"),
+        (PackageManager::Pip, Action::Files) => Some("pip show -f {name}"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Arch) => {
+            Some("dpkg --print-architecture")
+        }
+        // Health checks; `{name}` is a file path for LDD and OWNER
+        (PackageManager::Pkg | PackageManager::Apt, Action::Status) => {
+            Some("dpkg-query -W '-f=${Package}\t${db:Status-Abbrev}\n'")
+        }
+        (PackageManager::Pkg | PackageManager::Apt, Action::Autoremove) => {
+            Some("apt-get --simulate autoremove")
+        }
+        (PackageManager::Pkg | PackageManager::Apt, Action::Ldd) => Some("ldd {name}"),
+        (PackageManager::Pkg | PackageManager::Apt, Action::Owner) => Some("dpkg -S {name}"),
+        (PackageManager::Pip, Action::ImportCheck) => Some(IMPORT_CHECK),
+        (PackageManager::Pip, Action::Records) => Some(RECORDS),
+        // Dependency graph for "why is this installed?"
+        (PackageManager::Pkg | PackageManager::Apt, Action::Depends) => Some(
+            "dpkg-query -W '-f=${Package}\t${db:Status-Abbrev}\t${Pre-Depends}, ${Depends}\t${Recommends}\t${Provides}\t${Essential}\t${Priority}\n'",
+        ),
+        (PackageManager::Pip, Action::Depends) => Some(REQUIRES),
+        // Run once per automatically installed package by the orphan scan (`c`)
+        (PackageManager::Pkg | PackageManager::Apt, Action::Rdepends) => {
+            Some("apt-cache rdepends --installed {name}")
+        }
+        (PackageManager::Pip, Action::Metadata) => Some(METADATA),
+        // Interpreter pip installs for, e.g. "3.12"
+        (PackageManager::Pip, Action::PyVersion) => {
+            Some("python -c \"import sys; print('%d.%d' % sys.version_info[:2])\"")
+        }
+        // Reported by `tpi doctor`; pkg is a script around apt
+        (PackageManager::Pkg | PackageManager::Apt, Action::Version) => Some("apt --version"),
+        (PackageManager::Pip, Action::Version) => Some("pip --version"),
+        // The authoritative answer to the removal impact estimate
+        (PackageManager::Pkg | PackageManager::Apt, Action::SimulateRemove) => {
+            Some("apt-get --simulate remove {name}")
+        }
+        // Newer versions known to the manager, for `V` and `tpi serve`'s /outdated
+        (PackageManager::Pkg | PackageManager::Apt, Action::Upgradable) => {
+            Some("apt list --upgradable")
+        }
+        (PackageManager::Pip, Action::Upgradable) => Some("pip list --outdated"),
+        // Run in the foreground on the terminal, so apt can ask its questions
+        (PackageManager::Pkg, Action::Install) => Some("pkg install {name}"),
+        (PackageManager::Apt, Action::Install) => Some("apt install {name}"),
+        (PackageManager::Pip, Action::Install) => Some("pip install {name}"),
+        (PackageManager::Pkg, Action::Remove) => Some("pkg uninstall {name}"),
+        (PackageManager::Apt, Action::Remove) => Some("apt remove {name}"),
+        (PackageManager::Pip, Action::Remove) => Some("pip uninstall -y {name}"),
+        (PackageManager::Luarocks, Action::Version) => Some("luarocks --version"),
+        (PackageManager::Composer, Action::Version) => Some("composer --version"),
+        (PackageManager::Opam, Action::Version) => Some("opam --version"),
+        (PackageManager::Cargo, Action::Version) => Some("cargo --version"),
+        (PackageManager::Npm, Action::Version) => Some("npm --version"),
+        _ => None,
+    }
+}
+
+// Loads every native extension of every installed distribution and prints
+// `name<TAB>error` for the ones the dynamic linker rejects.
+const IMPORT_CHECK: &str = "python -c \"
+import ctypes, importlib.metadata as md
+for dist in md.distributions():
+    for file in dist.files or []:
+        if file.name.endswith('.so'):
+            try:
+                ctypes.CDLL(str(file.locate()))
+            except OSError as err:
+                print(dist.metadata['Name'], err, sep=chr(9))
+\"";
+
+// Prints `name<TAB>path` for every file in the RECORD of every installed
+// distribution, as absolute paths.
+const RECORDS: &str = "python -c \"
+import os, importlib.metadata as md
+for dist in md.distributions():
+    name = dist.metadata['Name']
+    for file in dist.files or []:
+        print(name, os.path.normpath(file.locate()), sep=chr(9))
+\"";
+
+// Prints `name<TAB>KiB` for every installed distribution, adding up the
+// file sizes its RECORD lists, like dpkg's Installed-Size.
+const PIP_SIZES: &str = "python -c \"
+import importlib.metadata as md
+for dist in md.distributions():
+    size = sum(file.size or 0 for file in dist.files or [])
+    print(dist.metadata['Name'], (size + 1023) // 1024, sep=chr(9))
+\"";
+
+// Prints the interpreter version, then `name<TAB>dist` for every installed
+// distribution with its Provides-Extra, raw Requires-Dist and WHEEL lines.
+const METADATA: &str = "python -c \"
+import sys, importlib.metadata as md
+print('%d.%d' % sys.version_info[:2])
+for dist in md.distributions():
+    name = dist.metadata['Name']
+    if name:
+        print(name, 'dist', sep=chr(9))
+        for extra in dist.metadata.get_all('Provides-Extra') or []:
+            print(name, 'extra', extra, sep=chr(9))
+        for req in dist.requires or []:
+            print(name, 'requires', req, sep=chr(9))
+        for line in (dist.read_text('WHEEL') or '').splitlines():
+            print(name, 'wheel', line, sep=chr(9))
+\"";
+
+// Prints `name<TAB>requirement,...` for every installed distribution, names
+// normalized as in PEP 503. Requirements that only apply to extras are left out.
+const REQUIRES: &str = "python -c \"
+import re, importlib.metadata as md
+key = lambda name: re.sub('[-_.]+', '-', name).lower()
+for dist in md.distributions():
+    if dist.metadata['Name']:
+        requires = [re.match('[A-Za-z0-9._-]+', req).group() for req in dist.requires or [] if 'extra' not in req.partition(';')[2]]
+        print(key(dist.metadata['Name']), ','.join(map(key, requires)), sep=chr(9))
+\"";
+
+pub fn env_var(manager: PackageManager, action: Action) -> String {
+    format!("TPI_{}_{}_CMD", manager.name().to_uppercase(), action.key())
+}
+
+fn template(manager: PackageManager, action: Action) -> Option<String> {
+    match std::env::var(env_var(manager, action)) {
+        Ok(value) if !value.trim().is_empty() => Some(value),
+        _ => default_template(manager, action).map(String::from),
+    }
+}
+
+// (variable, value) for every override currently set in the environment.
+pub fn active_overrides() -> Vec<(String, String)> {
+    let mut overrides = Vec::new();
+    for manager in PackageManager::ALL {
+        for action in Action::ALL {
+            let var = env_var(manager, action);
+            if let Ok(value) = std::env::var(&var) {
+                if !value.trim().is_empty() {
+                    overrides.push((var, value));
+                }
+            }
+        }
+    }
+    overrides
+}
+
+pub fn resolve(manager: PackageManager, action: Action, name: &str) -> Option<Vec<String>> {
+    resolve_package(manager, action, name, "")
+}
+
+// Program and arguments with `{name}` and `{version}` substituted. Placeholders
+// are replaced inside a single word, so a package name never turns into extra
+// arguments; a word that was only a placeholder for an empty value is dropped.
+pub fn resolve_package(
+    manager: PackageManager,
+    action: Action,
+    name: &str,
+    version: &str,
+) -> Option<Vec<String>> {
+    substitute(&template(manager, action)?, name, version)
+}
+
+fn substitute(template: &str, name: &str, version: &str) -> Option<Vec<String>> {
+    let words: Vec<String> = split_words(template)
+        .into_iter()
+        .filter_map(|word| {
+            let replaced = word.replace("{name}", name).replace("{version}", version);
+            if replaced.is_empty() && !word.is_empty() {
+                None
+            } else {
+                Some(replaced)
+            }
+        })
+        .collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words)
+    }
+}
+
+// The command run once for several packages: a word that is only `{name}`
+// becomes one word per name. None when the template has no such word (a name
+// embedded in another word can't take a list), so batches fall back to
+// refusing rather than guessing.
+pub fn resolve_names(
+    manager: PackageManager,
+    action: Action,
+    names: &[String],
+) -> Option<Vec<String>> {
+    expand_names(&template(manager, action)?, names)
+}
+
+// resolve_names for a given template, so overrides can be tried without
+// setting variables other threads read.
+fn expand_names(template: &str, names: &[String]) -> Option<Vec<String>> {
+    if let [name] = names {
+        return substitute(template, name, "");
+    }
+    let template = split_words(template);
+    if !template.iter().any(|word| word == "{name}") {
+        return None;
+    }
+    let words: Vec<String> = template
+        .into_iter()
+        .flat_map(|word| {
+            if word == "{name}" {
+                names.to_vec()
+            } else {
+                vec![word.replace("{version}", "")]
+            }
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words)
+    }
+}
+
+// Whether the program behind the manager's list command is on PATH (the
+// equivalent of `which`), honoring overrides.
+pub fn is_available(manager: PackageManager) -> bool {
+    resolve(manager, Action::List, "").is_some_and(|words| find_program(&words[0]).is_some())
+}
+
+// Where a program would be run from: itself when given as a path, else the
+// first match on $PATH.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Some(PathBuf::from(program)).filter(|path| path.is_file());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+// Executes resolved command lines. The app takes one of these so it can be run
+// against canned output instead of the real package managers.
+pub trait Runner: Send + Sync {
+    fn output(&self, words: &[String]) -> io::Result<Output>;
+}
+
+pub struct SystemRunner;
+
+// Output is parsed, so it is asked for in the C locale whatever the user's
+// is: dpkg and apt translate their headers and messages.
+impl Runner for SystemRunner {
+    fn output(&self, words: &[String]) -> io::Result<Output> {
+        Command::new(&words[0])
+            .args(&words[1..])
+            .env("LC_ALL", "C")
+            .output()
+    }
+}
+
+pub fn supports(manager: PackageManager, action: Action) -> bool {
+    default_template(manager, action).is_some()
+}
+
+pub fn run_with(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+    name: &str,
+) -> io::Result<Output> {
+    run_for_package(runner, manager, action, name, "")
+}
+
+pub fn run_for_package(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+    name: &str,
+    version: &str,
+) -> io::Result<Output> {
+    let words = resolve_package(manager, action, name, version)
+        .ok_or_else(|| no_command(manager, action))?;
+
+    log::debug(&format!("run: {}", join_words(&words)));
+    runner.output(&words)
+}
+
+// For commands about several packages at once, as resolve_names builds them.
+pub fn run_with_names(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+    names: &[String],
+) -> io::Result<Output> {
+    let template = template(manager, action).ok_or_else(|| no_command(manager, action))?;
+    let words = expand_names(&template, names).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} takes one package at a time: no word of it is only {{name}}",
+                env_var(manager, action)
+            ),
+        )
+    })?;
+
+    log::debug(&format!("run: {}", join_words(&words)));
+    runner.output(&words)
+}
+
+fn no_command(manager: PackageManager, action: Action) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "{} has no {} command",
+            manager.name(),
+            action.key().to_lowercase()
+        ),
+    )
+}
+
+// Quote words so the line can be pasted into a shell, or written into a
+// script, and run as the same words: anything but letters, digits and
+// punctuation no shell treats specially is single-quoted.
+pub fn join_words(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|word| {
+            if !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.+,:@/=%".contains(c))
+            {
+                word.clone()
+            } else {
+                format!("'{}'", word.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// POSIX-shell-like word splitting: whitespace separates words, single quotes
+// are literal, double quotes and backslashes escape.
+pub fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    current.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(next) = chars.next() {
+                                current.push(next);
+                            }
+                        }
+                        _ => current.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Option<Vec<String>> {
+        Some(line.split(' ').map(String::from).collect())
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn one_command_for_several_names() {
+        assert_eq!(
+            resolve_names(
+                PackageManager::Apt,
+                Action::Remove,
+                &names(&["curl", "zlib"])
+            ),
+            words("apt remove curl zlib")
+        );
+        assert_eq!(
+            resolve_names(
+                PackageManager::Pip,
+                Action::Remove,
+                &names(&["six", "toml"])
+            ),
+            words("pip uninstall -y six toml")
+        );
+        assert_eq!(
+            resolve_names(PackageManager::Apt, Action::Remove, &names(&["curl"])),
+            resolve(PackageManager::Apt, Action::Remove, "curl")
+        );
+    }
+
+    #[test]
+    fn overrides_without_a_name_word_refuse_batches() {
+        let embedded = "sh -c 'yes | pkg uninstall {name}'";
+        let two = names(&["curl", "zlib"]);
+        assert_eq!(expand_names(embedded, &two), None);
+        // One name still goes into the word
+        assert_eq!(
+            expand_names(embedded, &names(&["curl"])),
+            Some(names(&["sh", "-c", "yes | pkg uninstall curl"]))
+        );
+        // {version} has no value for a batch, or for one name
+        let versioned = "pkg uninstall -y {name} {version}";
+        assert_eq!(
+            expand_names(versioned, &two),
+            words("pkg uninstall -y curl zlib")
+        );
+        assert_eq!(
+            expand_names(versioned, &names(&["curl"])),
+            words("pkg uninstall -y curl")
+        );
+    }
+
+    #[test]
+    fn batches_run_what_resolve_names_builds() {
+        let runner = crate::tests::Mock::new();
+        let two = names(&["curl", "zlib"]);
+        run_with_names(&runner, PackageManager::Apt, Action::SimulateRemove, &two).unwrap();
+        assert_eq!(
+            Some(runner.ran.lock().unwrap()[0].clone()),
+            resolve_names(PackageManager::Apt, Action::SimulateRemove, &two)
+                .map(|words| join_words(&words))
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::commands;
+use crate::PackageManager;
+
+// Which package managers are probed and offered. $TPI_MANAGERS (comma
+// separated, e.g. "pkg,pip") names them explicitly and in Tab order; managers
+// named there stay listed even when their command is missing, so a broken
+// install is noticed rather than silently hidden. Without it every manager
+// whose command is installed is used.
+pub struct Managers {
+    pub enabled: Vec<PackageManager>,
+    pub explicit: bool,
+    pub unknown: Vec<String>, // Names in $TPI_MANAGERS that aren't managers
+}
+
+pub fn managers() -> Managers {
+    let Some(value) = std::env::var("TPI_MANAGERS")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+    else {
+        return Managers {
+            enabled: PackageManager::ALL
+                .into_iter()
+                .filter(|m| commands::is_available(*m))
+                .collect(),
+            explicit: false,
+            unknown: Vec::new(),
+        };
+    };
+
+    let mut enabled = Vec::new();
+    let mut unknown = Vec::new();
+    for name in value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        match PackageManager::from_name(name) {
+            Some(manager) if !enabled.contains(&manager) => enabled.push(manager),
+            Some(_) => {}
+            None => unknown.push(name.to_string()),
+        }
+    }
+    Managers {
+        enabled,
+        explicit: true,
+        unknown,
+    }
+}
+
+// Held packages more than this many versions behind are flagged in the hold
+// view ($TPI_HOLD_WARN, 3 by default).
+pub fn hold_warn_versions() -> usize {
+    std::env::var("TPI_HOLD_WARN")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(3)
+}
+
+// After how long a manager's list is shown as stale: $TPI_STALE_<MANAGER>
+// in minutes, e.g. TPI_STALE_PIP=5. dpkg's database only changes when you
+// install something, so pkg and apt get an hour; pip packages also come and
+// go with every `pip install` in a project, so pip gets 15 minutes.
+pub fn stale_after(manager: PackageManager) -> Duration {
+    let minutes = std::env::var(stale_var(manager))
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(match manager {
+            PackageManager::Pkg | PackageManager::Apt => 60,
+            PackageManager::Pip => 15,
+            _ => 30,
+        });
+    Duration::from_secs(minutes * 60)
+}
+
+pub fn stale_var(manager: PackageManager) -> String {
+    format!("TPI_STALE_{}", manager.name().to_uppercase())
+}
+
+// Whether Tab drops the search and repository filter rather than applying
+// them to the next manager's list ($TPI_CLEAR_SEARCH_ON_SWITCH=1).
+pub fn clear_search_on_switch() -> bool {
+    matches!(
+        std::env::var("TPI_CLEAR_SEARCH_ON_SWITCH").as_deref(),
+        Ok("1" | "true" | "yes")
+    )
+}
+
+// Render inline below the prompt instead of on the alternate screen
+// ($TPI_NO_ALT_SCREEN=1); --no-alt-screen does the same.
+pub fn no_alt_screen() -> bool {
+    matches!(
+        std::env::var("TPI_NO_ALT_SCREEN").as_deref(),
+        Ok("1" | "true" | "yes")
+    )
+}
+
+// Height of the inline viewport ($TPI_INLINE_HEIGHT, 20 rows by default).
+// The terminal's own height caps it.
+pub fn inline_height() -> u16 {
+    std::env::var("TPI_INLINE_HEIGHT")
+        .ok()
+        .and_then(|rows| rows.trim().parse().ok())
+        .filter(|rows| *rows >= 10)
+        .unwrap_or(20)
+}
+
+// Port `tpi serve` listens on ($TPI_SERVE_PORT, 7377 by default); --port
+// overrides it.
+pub fn serve_port() -> u16 {
+    std::env::var("TPI_SERVE_PORT")
+        .ok()
+        .and_then(|port| port.trim().parse().ok())
+        .filter(|port| *port > 0)
+        .unwrap_or(7377)
+}
+
+// When set, `tpi serve` answers only requests carrying it in an X-Tpi-Token
+// header ($TPI_SERVE_TOKEN).
+pub fn serve_token() -> Option<String> {
+    std::env::var("TPI_SERVE_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+// Where reports, scripts and file lists are written ($TPI_EXPORT_DIR, else
+// the home directory), whatever directory tpi was started in.
+pub fn export_dir() -> PathBuf {
+    std::env::var_os("TPI_EXPORT_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(home)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+// Bootstrap script `d` compares the installed packages with ($TPI_MANIFEST,
+// by default the tpi-bootstrap.sh `B` writes first in export_dir()).
+pub fn manifest_path() -> PathBuf {
+    std::env::var_os("TPI_MANIFEST")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| export_dir().join("tpi-bootstrap.sh"))
+}
+
+// How much optional work a load does besides listing names and versions.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Profile {
+    Fast, // Names and versions only: no holds, sizes, why-installed or history
+    #[default]
+    Full,
+}
+
+impl Profile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Fast => "fast",
+            Profile::Full => "full",
+        }
+    }
+}
+
+// Install commands copied with `I` are broken with backslashes to fit this
+// many columns ($TPI_INSTALL_WIDTH, 80 by default; 0 never breaks them).
+pub fn install_width() -> usize {
+    std::env::var("TPI_INSTALL_WIDTH")
+        .ok()
+        .and_then(|width| width.trim().parse().ok())
+        .unwrap_or(80)
+}
+
+// $TPI_TERMINAL_TITLE=1: set the terminal's title to the manager and its
+// counts. Off by default, as some terminals show title changes loudly.
+pub fn terminal_title() -> bool {
+    matches!(
+        std::env::var("TPI_TERMINAL_TITLE").as_deref(),
+        Ok("1" | "true" | "yes")
+    )
+}
+
+// $TPI_ALLOW_ROOT=1: run as root, still offer actions that change packages.
+pub fn allow_root() -> bool {
+    matches!(
+        std::env::var("TPI_ALLOW_ROOT").as_deref(),
+        Ok("1" | "true" | "yes")
+    )
+}
+
+// $HOME, for where state files go by default. Not as root: that is root's
+// home, not the Termux user's, so those files are only used where a TPI_ or
+// XDG_ variable names them.
+pub fn home() -> Option<PathBuf> {
+    if crate::root::detected() {
+        return None;
+    }
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+// $TPI_PROFILE ("fast" or "full"); --fast and --full override it.
+pub fn profile() -> Profile {
+    match std::env::var("TPI_PROFILE").as_deref().map(str::trim) {
+        Ok("fast") => Profile::Fast,
+        _ => Profile::Full,
+    }
+}
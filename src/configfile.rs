@@ -0,0 +1,428 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::keymap::{self, Binding, Keymap};
+use crate::{config, preview, PackageManager};
+
+// Startup defaults and keys from a small TOML file:
+//
+//     manager = "pip"
+//     details_height = 40
+//     details = "dwell"
+//     details_delay = 400
+//     prefetch = true
+//
+//     [highlight]
+//     fg = "yellow"
+//     modifiers = ["bold"]
+//     symbol = "> "
+//
+//     [keys]
+//     next = ["n", "Down"]
+//
+// Only the subset of TOML these need is understood: tables, strings,
+// integers, booleans and one-line arrays.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub manager: Option<PackageManager>, // Opened first when it is enabled
+    pub details_height: u16,             // Percent of the screen for the detail pane
+    pub details: preview::Fetch,         // When the detail pane runs its commands
+    pub details_delay: Duration,         // How long the selection rests before they run
+    pub prefetch: bool,                  // Fetch the details of the packages on screen when idle
+    pub highlight: Style,                // Of the selected row
+    pub symbol: String,                  // In front of the selected row
+    pub keys: Keymap,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            manager: None,
+            details_height: 30,
+            details: preview::Fetch::Eager,
+            details_delay: Duration::from_millis(400),
+            prefetch: false,
+            highlight: Style::default().add_modifier(Modifier::ITALIC),
+            symbol: ">> ".to_string(),
+            keys: Keymap::default(),
+        }
+    }
+}
+
+// --config, else $TPI_CONFIG, else
+// $XDG_CONFIG_HOME/termux-package-inspector/config.toml (~/.config by
+// default).
+pub fn path(explicit: Option<&str>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(path) = std::env::var_os("TPI_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".config")))?;
+    Some(config.join("termux-package-inspector").join("config.toml"))
+}
+
+// The config, or the defaults and why the file couldn't be used. A missing
+// file is no problem unless it was asked for with --config.
+pub fn load(explicit: Option<&str>) -> (Config, Option<String>) {
+    let Some(path) = path(explicit) else {
+        return (Config::default(), None);
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound && explicit.is_none() => {
+            return (Config::default(), None);
+        }
+        Err(err) => {
+            return (
+                Config::default(),
+                Some(format!("{}: {}", path.display(), err)),
+            )
+        }
+    };
+    match parse(&text) {
+        Ok(config) => (config, None),
+        // Only the file's name: the message has to fit a phone's status line
+        Err(err) => {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            (
+                Config::default(),
+                Some(format!("{} {}", name.to_string_lossy(), err)),
+            )
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Value {
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Text(_) => "a string",
+            Value::Integer(_) => "a number",
+            Value::Boolean(_) => "true or false",
+            Value::Array(_) => "a list",
+        }
+    }
+}
+
+// Errors read "line 4: keys.next: …", naming the setting at fault.
+pub fn parse(text: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    let mut table = String::new();
+    let mut keys: Vec<(Binding, Vec<keymap::Key>)> = Vec::new();
+    let mut modifiers = None;
+    let mut colors = (None, None);
+    for (number, line) in text.lines().enumerate() {
+        let at = |key: &str, err: String| {
+            let key = match table.as_str() {
+                "" => key.to_string(),
+                table => format!("{}.{}", table, key),
+            };
+            format!("line {}: {}: {}", number + 1, key, err)
+        };
+        let line = strip_comment(line);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            let name = name.trim();
+            if !matches!(name, "highlight" | "keys") {
+                return Err(format!(
+                    "line {}: [{}]: not a table tpi knows",
+                    number + 1,
+                    name
+                ));
+            }
+            table = name.to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected key = value", number + 1));
+        };
+        let key = key.trim().trim_matches('"');
+        let value = parse_value(value.trim()).map_err(|err| at(key, err))?;
+        match (table.as_str(), key) {
+            ("", "manager") => {
+                let name = text_of(&value).map_err(|err| at(key, err))?;
+                let manager = PackageManager::from_name(name)
+                    .ok_or_else(|| at(key, format!("\"{}\" is not a package manager", name)))?;
+                config.manager = Some(manager);
+            }
+            ("", "details_height") => match value {
+                Value::Integer(percent @ 10..=80) => config.details_height = percent as u16,
+                Value::Integer(_) => return Err(at(key, "must be from 10 to 80".to_string())),
+                other => return Err(at(key, format!("expected a number, not {}", other.kind()))),
+            },
+            ("", "details") => {
+                let name = text_of(&value).map_err(|err| at(key, err))?;
+                config.details = preview::Fetch::from_name(name).ok_or_else(|| {
+                    at(
+                        key,
+                        format!("\"{}\" is not eager, debounced or dwell", name),
+                    )
+                })?;
+            }
+            ("", "details_delay") => match value {
+                Value::Integer(ms @ 0..=5000) => {
+                    config.details_delay = Duration::from_millis(ms as u64)
+                }
+                Value::Integer(_) => {
+                    return Err(at(key, "must be from 0 to 5000 milliseconds".to_string()))
+                }
+                other => return Err(at(key, format!("expected a number, not {}", other.kind()))),
+            },
+            ("", "prefetch") => match value {
+                Value::Boolean(on) => config.prefetch = on,
+                other => {
+                    return Err(at(
+                        key,
+                        format!("expected true or false, not {}", other.kind()),
+                    ))
+                }
+            },
+            ("highlight", "fg" | "bg") => {
+                let name = text_of(&value).map_err(|err| at(key, err))?;
+                let color = Color::from_str(name)
+                    .map_err(|_| at(key, format!("\"{}\" is not a color", name)))?;
+                if key == "fg" {
+                    colors.0 = Some(color);
+                } else {
+                    colors.1 = Some(color);
+                }
+            }
+            ("highlight", "modifiers") => {
+                let mut all = Modifier::empty();
+                for name in texts_of(&value).map_err(|err| at(key, err))? {
+                    all |= modifier(name)
+                        .ok_or_else(|| at(key, format!("\"{}\" is not a text style", name)))?;
+                }
+                modifiers = Some(all);
+            }
+            ("highlight", "symbol") => {
+                config.symbol = text_of(&value).map_err(|err| at(key, err))?.to_string();
+            }
+            ("keys", name) => {
+                let binding = Binding::from_name(name)
+                    .ok_or_else(|| at(key, "not an action that can be rebound".to_string()))?;
+                let bound = texts_of(&value)
+                    .map_err(|err| at(key, err))?
+                    .into_iter()
+                    .map(keymap::parse_key)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| at(key, err))?;
+                if bound.is_empty() {
+                    return Err(at(key, "needs at least one key".to_string()));
+                }
+                keys.retain(|(other, _)| *other != binding);
+                keys.push((binding, bound));
+                config.keys = Keymap::with(&keys).map_err(|err| at(key, err))?;
+            }
+            _ => return Err(at(key, "not a setting tpi knows".to_string())),
+        }
+    }
+    // Colors and modifiers replace the default italics only when given
+    if colors != (None, None) || modifiers.is_some() {
+        let mut style = Style::default().add_modifier(modifiers.unwrap_or(Modifier::empty()));
+        if let Some(fg) = colors.0 {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = colors.1 {
+            style = style.bg(bg);
+        }
+        config.highlight = style;
+    }
+    Ok(config)
+}
+
+// A file of strings only, e.g. a message catalog: each `key = "text"` under
+// a `[table]` comes back as ("table.key", text), in file order.
+pub fn parse_strings(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut strings = Vec::new();
+    let mut table = String::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            table = name.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {}: expected key = value", number + 1));
+        };
+        let key = key.trim().trim_matches('"');
+        let key = match table.as_str() {
+            "" => key.to_string(),
+            table => format!("{}.{}", table, key),
+        };
+        let value = parse_value(value.trim())
+            .and_then(|value| text_of(&value).map(str::to_string))
+            .map_err(|err| format!("line {}: {}: {}", number + 1, key, err))?;
+        strings.push((key, value));
+    }
+    Ok(strings)
+}
+
+// Everything from a `#` that isn't inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let (value, rest) = parse_one(text)?;
+    if rest.trim().is_empty() {
+        Ok(value)
+    } else {
+        Err(format!("unexpected \"{}\" after the value", rest.trim()))
+    }
+}
+
+// One value off the front of `text`, and what follows it.
+fn parse_one(text: &str) -> Result<(Value, &str), String> {
+    let text = text.trim_start();
+    if let Some(rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        let mut rest = rest.trim_start();
+        loop {
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_one(rest)?;
+            items.push(item);
+            let after = after.trim_start();
+            rest = match after.strip_prefix(',') {
+                Some(after) => after.trim_start(),
+                None if after.starts_with(']') => after,
+                None => return Err("expected , or ] in the list".to_string()),
+            };
+        }
+    }
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Text(value), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => return Err(format!("unknown escape \\{}", other)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("string without its closing \"".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        return match rest.split_once('\'') {
+            Some((value, after)) => Ok((Value::Text(value.to_string()), after)),
+            None => Err("string without its closing '".to_string()),
+        };
+    }
+    let end = text.find([',', ']']).unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let word = word.trim();
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => Value::Integer(
+            word.replace('_', "")
+                .parse()
+                .map_err(|_| format!("\"{}\" is not a value (strings need quotes)", word))?,
+        ),
+    };
+    Ok((value, rest))
+}
+
+fn text_of(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::Text(text) => Ok(text),
+        other => Err(format!("expected a string, not {}", other.kind())),
+    }
+}
+
+// A string, or a list of them.
+fn texts_of(value: &Value) -> Result<Vec<&str>, String> {
+    match value {
+        Value::Array(items) => items.iter().map(text_of).collect(),
+        value => Ok(vec![text_of(value)?]),
+    }
+}
+
+fn modifier(name: &str) -> Option<Modifier> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "bold" => Modifier::BOLD,
+        "dim" => Modifier::DIM,
+        "italic" => Modifier::ITALIC,
+        "underlined" | "underline" => Modifier::UNDERLINED,
+        "reversed" | "reverse" => Modifier::REVERSED,
+        "crossed_out" => Modifier::CROSSED_OUT,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefetch_takes_true_or_false() {
+        assert!(!parse("").unwrap().prefetch);
+        assert!(parse("prefetch = true").unwrap().prefetch);
+        assert!(
+            !parse("prefetch = true\nprefetch = false # off")
+                .unwrap()
+                .prefetch
+        );
+        assert_eq!(
+            parse("\nprefetch = 1").unwrap_err(),
+            "line 2: prefetch: expected true or false, not a number"
+        );
+        assert_eq!(
+            parse("prefetch = \"yes\"").unwrap_err(),
+            "line 1: prefetch: expected true or false, not a string"
+        );
+    }
+}
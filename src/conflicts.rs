@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands::Runner;
+use crate::filelists;
+use crate::health::{Finding, Outcome};
+use crate::names::{self, Confidence};
+use crate::PackageManager;
+
+type Owner = (PackageManager, String);
+
+// Paths that more than one installed package ships, from dpkg's file lists
+// and pip's RECORDs, one finding per pair of owners. dpkg refuses such
+// overwrites unless a package declares Replaces, but pip doesn't know about
+// dpkg, and whichever installed last wins. Directories are shared freely and
+// don't count.
+pub fn find(runner: &dyn Runner, cancel: &AtomicBool) -> Outcome {
+    let dpkg = filelists::dpkg(cancel);
+    if cancel.load(Ordering::Relaxed) {
+        return Err("cancelled".to_string());
+    }
+    let pip = filelists::pip(runner);
+    if let (Err(err), Err(_)) = (&dpkg, &pip) {
+        return Err(err.clone());
+    }
+
+    let mut owners: HashMap<&str, Vec<Owner>> = HashMap::new();
+    for (manager, lists) in [(PackageManager::Apt, &dpkg), (PackageManager::Pip, &pip)] {
+        let Ok(lists) = lists else {
+            continue;
+        };
+        for (name, paths) in lists.iter() {
+            let owner = (manager, name.clone());
+            for path in paths {
+                let claimed = owners.entry(path).or_default();
+                if !claimed.contains(&owner) {
+                    claimed.push(owner.clone());
+                }
+            }
+        }
+    }
+
+    let mut pairs: HashMap<(Owner, Owner), Vec<String>> = HashMap::new();
+    for (path, mut claimed) in owners {
+        if claimed.len() < 2 || fs::symlink_metadata(path).map_or(true, |meta| meta.is_dir()) {
+            continue;
+        }
+        claimed.sort_by(|a, b| (a.0.name(), &a.1).cmp(&(b.0.name(), &b.1)));
+        for (i, first) in claimed.iter().enumerate() {
+            for second in &claimed[i + 1..] {
+                pairs
+                    .entry((first.clone(), second.clone()))
+                    .or_default()
+                    .push(path.to_string());
+            }
+        }
+    }
+
+    let mut pairs: Vec<((Owner, Owner), Vec<String>)> = pairs.into_iter().collect();
+    pairs.sort_by(|(a, a_paths), (b, b_paths)| {
+        b_paths
+            .len()
+            .cmp(&a_paths.len())
+            .then_with(|| (&a.0 .1, &a.1 .1).cmp(&(&b.0 .1, &b.1 .1)))
+    });
+    let findings: Vec<Finding> = pairs
+        .into_iter()
+        .map(|((first, second), mut paths)| {
+            paths.sort();
+            let files = if paths.len() == 1 { "file" } else { "files" };
+            // python-numpy from pkg and numpy from pip: one thing installed
+            // twice, where removing either copy is the fix
+            let twice = match names::correlate((first.0, &first.1), (second.0, &second.1)) {
+                Some(confidence) if confidence >= Confidence::Medium => {
+                    format!("; {} the same software installed twice", confidence.label())
+                }
+                _ => String::new(),
+            };
+            Finding {
+                manager: first.0,
+                package: first.1,
+                detail: format!(
+                    "{} {} also in {} ({}), e.g. {}{}",
+                    paths.len(),
+                    files,
+                    second.1,
+                    second.0.name(),
+                    paths[0],
+                    twice
+                ),
+                other: Some(second),
+            }
+        })
+        .collect();
+
+    Ok(findings)
+}
@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, ListState},
+};
+
+use crate::commands::Runner;
+use crate::health::{Check, Finding, Outcome};
+use crate::PackageManager;
+
+pub enum State {
+    NotRun,
+    Running(Arc<AtomicBool>), // Set to cancel
+    Cancelled,
+    Done(Outcome),
+}
+
+pub struct Entry {
+    pub check: Check,
+    pub state: State,
+    pub expanded: bool,
+    run_id: u64, // Results of earlier, cancelled runs are dropped
+}
+
+// What the selected row of the dashboard is.
+pub enum Selection<'a> {
+    Check,
+    Finding(&'a Finding),
+}
+
+// The "Health" screen: every check with its result, expandable to the
+// affected packages. Checks run on background threads and report back over a
+// channel that the app drains with `poll`.
+pub struct Dashboard {
+    pub entries: Vec<Entry>,
+    pub state: ListState,
+    runner: Arc<dyn Runner>,
+    sender: Sender<(usize, u64, Outcome)>,
+    receiver: Receiver<(usize, u64, Outcome)>,
+    next_run_id: u64,
+    workers: Vec<JoinHandle<()>>, // Threads of checks, cancelled ones included
+}
+
+enum Row {
+    Check(usize),
+    Finding(usize, usize),
+}
+
+impl Dashboard {
+    // Opens the dashboard and starts every check that is cheap to run.
+    pub fn open(runner: Arc<dyn Runner>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let mut dashboard = Self {
+            entries: Check::ALL
+                .into_iter()
+                .map(|check| Entry {
+                    check,
+                    state: State::NotRun,
+                    expanded: false,
+                    run_id: 0,
+                })
+                .collect(),
+            state: ListState::default().with_selected(Some(0)),
+            runner,
+            sender,
+            receiver,
+            next_run_id: 1,
+            workers: Vec::new(),
+        };
+        for i in 0..dashboard.entries.len() {
+            if !dashboard.entries[i].check.is_expensive() {
+                dashboard.start(i);
+            }
+        }
+        dashboard
+    }
+
+    fn start(&mut self, index: usize) {
+        self.cancel(index);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let run_id = self.next_run_id;
+        self.next_run_id += 1;
+
+        let entry = &mut self.entries[index];
+        entry.state = State::Running(cancel.clone());
+        entry.run_id = run_id;
+
+        let check = entry.check;
+        let runner = self.runner.clone();
+        let sender = self.sender.clone();
+        self.workers.retain(|worker| !worker.is_finished());
+        self.workers.push(thread::spawn(move || {
+            let outcome = check.run(runner.as_ref(), &cancel);
+            let _ = sender.send((index, run_id, outcome));
+        }));
+    }
+
+    // Runs (or re-runs) the check the selection is in. Its findings go away
+    // while it runs, so the selection moves up to the check itself.
+    pub fn run_selected(&mut self) {
+        if let Some(index) = self.selected_entry() {
+            self.select_check(index);
+            self.start(index);
+        }
+    }
+
+    pub fn run_all(&mut self) {
+        if let Some(index) = self.selected_entry() {
+            self.select_check(index);
+        }
+        for i in 0..self.entries.len() {
+            self.start(i);
+        }
+    }
+
+    fn cancel(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        if let State::Running(flag) = &entry.state {
+            flag.store(true, Ordering::Relaxed);
+            entry.state = State::Cancelled;
+        }
+    }
+
+    pub fn cancel_selected(&mut self) {
+        if let Some(index) = self.selected_entry() {
+            self.cancel(index);
+        }
+    }
+
+    pub fn cancel_all(&mut self) {
+        for i in 0..self.entries.len() {
+            self.cancel(i);
+        }
+    }
+
+    // Every package a finished check found something wrong with. Orphans
+    // aren't broken, just unneeded, so they don't count.
+    pub fn flagged(&self) -> HashSet<(PackageManager, String)> {
+        let mut flagged = HashSet::new();
+        for entry in &self.entries {
+            let State::Done(Ok(findings)) = &entry.state else {
+                continue;
+            };
+            if entry.check == Check::Orphans {
+                continue;
+            }
+            for finding in findings {
+                flagged.insert((finding.manager, finding.package.clone()));
+                flagged.extend(finding.other.clone());
+            }
+        }
+        flagged
+    }
+
+    // Checks still running, for the quit dialog.
+    pub fn running(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| matches!(entry.state, State::Running(_)))
+            .count()
+    }
+
+    // Whether a thread is still busy, cancelled or not: a cancelled check
+    // stops at its next step, but the command it is running finishes first.
+    pub fn has_workers(&self) -> bool {
+        self.workers.iter().any(|worker| !worker.is_finished())
+    }
+
+    // Waits for every thread, so no command of ours outlives the app.
+    pub fn join(&mut self) {
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| matches!(entry.state, State::Running(_)))
+    }
+
+    // Collects finished checks. Returns whether anything changed.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((index, run_id, outcome)) = self.receiver.try_recv() {
+            let entry = &mut self.entries[index];
+            if entry.run_id == run_id && matches!(entry.state, State::Running(_)) {
+                entry.state = State::Done(outcome);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn findings(entry: &Entry) -> &[Finding] {
+        match &entry.state {
+            State::Done(Ok(findings)) => findings,
+            _ => &[],
+        }
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            rows.push(Row::Check(i));
+            if entry.expanded {
+                rows.extend((0..Self::findings(entry).len()).map(|j| Row::Finding(i, j)));
+            }
+        }
+        rows
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.state
+            .selected()
+            .and_then(|i| self.rows().into_iter().nth(i))
+    }
+
+    fn selected_entry(&self) -> Option<usize> {
+        match self.selected_row()? {
+            Row::Check(i) | Row::Finding(i, _) => Some(i),
+        }
+    }
+
+    pub fn selection(&self) -> Option<Selection<'_>> {
+        match self.selected_row()? {
+            Row::Check(_) => Some(Selection::Check),
+            Row::Finding(i, j) => Self::findings(&self.entries[i])
+                .get(j)
+                .map(Selection::Finding),
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.rows().len();
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1).min(len - 1)));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    // Expands or collapses the selected check; collapsing from a finding
+    // moves the selection back to its check.
+    pub fn set_expanded(&mut self, expanded: bool) {
+        let Some(index) = self.selected_entry() else {
+            return;
+        };
+        self.entries[index].expanded = expanded;
+        if !expanded {
+            self.select_check(index);
+        }
+    }
+
+    fn select_check(&mut self, index: usize) {
+        let row = self
+            .rows()
+            .iter()
+            .position(|row| matches!(row, Row::Check(i) if *i == index));
+        self.state.select(row);
+    }
+
+    pub fn toggle_expanded(&mut self) {
+        if let Some(index) = self.selected_entry() {
+            let expanded = self.entries[index].expanded;
+            self.set_expanded(!expanded);
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .rows()
+            .into_iter()
+            .map(|row| match row {
+                Row::Check(i) => {
+                    let entry = &self.entries[i];
+                    let (symbol, status, color) = match &entry.state {
+                        State::NotRun => ("·", "not run (r to run)".to_string(), Color::DarkGray),
+                        State::Running(_) => ("…", "running".to_string(), Color::Cyan),
+                        State::Cancelled => ("-", "cancelled".to_string(), Color::DarkGray),
+                        State::Done(Err(err)) => ("?", format!("error: {}", err), Color::Magenta),
+                        State::Done(Ok(findings)) if findings.is_empty() => {
+                            ("✓", "pass".to_string(), Color::Green)
+                        }
+                        State::Done(Ok(findings)) => {
+                            ("✗", format!("{} affected", findings.len()), Color::Red)
+                        }
+                    };
+                    let arrow = match (&entry.state, entry.expanded) {
+                        (State::Done(Ok(findings)), _) if findings.is_empty() => " ",
+                        (State::Done(Ok(_)), true) => "▾",
+                        (State::Done(Ok(_)), false) => "▸",
+                        _ => " ",
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} ", symbol), Style::default().fg(color)),
+                        Span::raw(format!("{} {:<32} ", arrow, entry.check.label())),
+                        Span::styled(status, Style::default().fg(color)),
+                    ]))
+                }
+                Row::Finding(i, j) => {
+                    let finding = &Self::findings(&self.entries[i])[j];
+                    ListItem::new(Line::from(vec![
+                        Span::raw(format!("      {} ", finding.package)),
+                        Span::styled(
+                            format!("({}) {}", finding.manager.name(), finding.detail),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ]))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("Health — r run, R run all, c cancel, Enter expand/jump, o jump to other, Esc close")
+                    .borders(Borders::ALL),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
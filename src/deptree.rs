@@ -0,0 +1,410 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+};
+
+use crate::details::Details;
+use crate::PackageManager;
+
+// Branches deeper than this aren't offered for expanding; with apt's
+// dependency web a tree can otherwise go on for pages.
+const MAX_DEPTH: usize = 12;
+
+// A dependency as `show` lists it.
+pub struct Dep {
+    pub name: String,
+    pub constraint: String, // "(>= 1.2)", alternatives "| b", or empty
+    pub recommended: bool,  // From Recommends rather than Depends
+    pub installed: bool,
+}
+
+// Hard dependencies, then recommendations, from a package's `show` fields.
+// Of alternatives (`a | b`) the first installed one is followed and the
+// others are kept in the constraint.
+pub fn parse(
+    manager: PackageManager,
+    details: &Details,
+    installed: impl Fn(&str) -> bool,
+) -> Vec<Dep> {
+    let fields: &[(&str, bool)] = match manager {
+        PackageManager::Pkg | PackageManager::Apt => &[
+            ("Pre-Depends", false),
+            ("Depends", false),
+            ("Recommends", true),
+        ],
+        PackageManager::Pip => &[("Requires", false)],
+        _ => &[],
+    };
+    let mut deps: Vec<Dep> = Vec::new();
+    for (field, recommended) in fields {
+        let Some(value) = details.get(field) else {
+            continue;
+        };
+        for clause in value.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            let alternatives: Vec<(&str, &str)> = clause
+                .split('|')
+                .map(|alternative| {
+                    let alternative = alternative.trim();
+                    let (name, constraint) =
+                        alternative.split_once(' ').unwrap_or((alternative, ""));
+                    // An architecture qualifier, e.g. python3:any
+                    let name = name.split(':').next().unwrap_or(name);
+                    (name, constraint.trim())
+                })
+                .collect();
+            let picked = alternatives
+                .iter()
+                .position(|(name, _)| installed(name))
+                .unwrap_or(0);
+            let (name, constraint) = alternatives[picked];
+            if deps.iter().any(|dep| dep.name == name) {
+                continue;
+            }
+            let others: Vec<&str> = alternatives
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != picked)
+                .map(|(_, (name, _))| *name)
+                .collect();
+            let mut constraint = constraint.to_string();
+            if !others.is_empty() {
+                constraint = format!("{} | {}", constraint, others.join(" | "))
+                    .trim()
+                    .to_string();
+            }
+            deps.push(Dep {
+                name: name.to_string(),
+                constraint,
+                recommended: *recommended,
+                installed: installed(name),
+            });
+        }
+    }
+    deps
+}
+
+struct Node {
+    dep: Dep,
+    depth: usize,
+    expanded: bool,
+    cycle: bool, // Already an ancestor: shown, never expanded
+}
+
+// Dependencies of one package as a tree in the detail area. Branches are
+// read when first expanded, so cycles only ever show up as a marked leaf.
+pub struct DepTree {
+    pub root: String,
+    nodes: Vec<Node>, // Visible nodes, depth-first
+    pub state: ListState,
+}
+
+impl DepTree {
+    pub fn new(root: String, deps: Vec<Dep>) -> Self {
+        let mut tree = Self {
+            root,
+            nodes: Vec::new(),
+            state: ListState::default(),
+        };
+        tree.insert(0, 0, deps);
+        tree.state.select((!tree.nodes.is_empty()).then_some(0));
+        tree
+    }
+
+    fn insert(&mut self, at: usize, depth: usize, deps: Vec<Dep>) {
+        let ancestors = self.ancestors(at, depth);
+        let nodes: Vec<Node> = deps
+            .into_iter()
+            .map(|dep| Node {
+                cycle: dep.name == self.root || ancestors.contains(&dep.name),
+                dep,
+                depth,
+                expanded: false,
+            })
+            .collect();
+        self.nodes.splice(at..at, nodes);
+    }
+
+    // Names above a node inserted at `at` with this depth.
+    fn ancestors(&self, at: usize, depth: usize) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut want = depth;
+        for node in self.nodes[..at].iter().rev() {
+            if want == 0 {
+                break;
+            }
+            if node.depth == want - 1 {
+                names.push(node.dep.name.clone());
+                want -= 1;
+            }
+        }
+        names
+    }
+
+    pub fn selected(&self) -> Option<&Dep> {
+        self.nodes.get(self.state.selected()?).map(|node| &node.dep)
+    }
+
+    // The selected dependency, when it can be expanded and isn't yet.
+    pub fn expandable(&self) -> Option<&str> {
+        let node = self.nodes.get(self.state.selected()?)?;
+        (node.dep.installed && !node.expanded && !node.cycle && node.depth + 1 < MAX_DEPTH)
+            .then_some(node.dep.name.as_str())
+    }
+
+    pub fn expand(&mut self, deps: Vec<Dep>) {
+        let Some(i) = self.state.selected() else {
+            return;
+        };
+        self.nodes[i].expanded = true;
+        let depth = self.nodes[i].depth + 1;
+        self.insert(i + 1, depth, deps);
+    }
+
+    // Collapses the selected branch, or from inside one its parent.
+    pub fn collapse(&mut self) {
+        let Some(mut i) = self.state.selected() else {
+            return;
+        };
+        if !self.nodes[i].expanded {
+            let depth = self.nodes[i].depth;
+            match self.nodes[..i].iter().rposition(|node| node.depth < depth) {
+                Some(parent) => i = parent,
+                None => return,
+            }
+        }
+        let depth = self.nodes[i].depth;
+        let end = self.nodes[i + 1..]
+            .iter()
+            .position(|node| node.depth <= depth)
+            .map_or(self.nodes.len(), |len| i + 1 + len);
+        self.nodes.drain(i + 1..end);
+        self.nodes[i].expanded = false;
+        self.state.select(Some(i));
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state
+                .select(Some((i + 1).min(self.nodes.len().saturating_sub(1))));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .title(format!(
+                "Dependencies of {} — l expand, h collapse, Enter go to, t close",
+                self.root
+            ))
+            .borders(Borders::ALL);
+        if self.nodes.is_empty() {
+            let empty = List::new([ListItem::new("No dependencies listed")]).block(block);
+            f.render_widget(empty, area);
+            return;
+        }
+        let dim = Style::default().fg(Color::DarkGray);
+        let items: Vec<ListItem> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let arrow = if node.cycle {
+                    "↻"
+                } else if node.expanded {
+                    "▾"
+                } else if node.dep.installed && node.depth + 1 < MAX_DEPTH {
+                    "▸"
+                } else {
+                    "·"
+                };
+                let name_style = if node.dep.installed {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default().fg(Color::Red)
+                };
+                let mut spans = vec![
+                    Span::raw(format!("{}{} ", "  ".repeat(node.depth), arrow)),
+                    Span::styled(node.dep.name.clone(), name_style),
+                ];
+                if !node.dep.constraint.is_empty() {
+                    spans.push(Span::styled(format!(" {}", node.dep.constraint), dim));
+                }
+                if node.dep.recommended {
+                    spans.push(Span::styled(" recommended", dim));
+                }
+                if !node.dep.installed {
+                    spans.push(Span::styled(" not installed", dim));
+                }
+                if node.cycle {
+                    spans.push(Span::styled(" (cycle)", dim));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(fields: &[(&str, &str)]) -> Details {
+        Details {
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn dep(name: &str) -> Dep {
+        Dep {
+            name: name.to_string(),
+            constraint: String::new(),
+            recommended: false,
+            installed: true,
+        }
+    }
+
+    fn deps(names: &[&str]) -> Vec<Dep> {
+        names.iter().map(|name| dep(name)).collect()
+    }
+
+    // Each visible node as "<indent><arrow> name".
+    fn rows(tree: &DepTree) -> Vec<String> {
+        tree.nodes
+            .iter()
+            .map(|node| {
+                let arrow = match (node.cycle, node.expanded) {
+                    (true, _) => "↻",
+                    (_, true) => "▾",
+                    _ => "▸",
+                };
+                format!("{}{} {}", "  ".repeat(node.depth), arrow, node.dep.name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn apt_fields_in_order_with_installed_alternatives() {
+        let installed = |name: &str| matches!(name, "libc" | "mawk" | "ca-certificates");
+        let details = details(&[
+            ("Pre-Depends", "libc (>= 2.0)"),
+            (
+                "Depends",
+                "gawk | mawk, libc, python3:any (>= 3.11), ncurses | ncurses-ui",
+            ),
+            ("Recommends", "ca-certificates, libc"),
+        ]);
+        let parsed = parse(PackageManager::Apt, &details, installed);
+        let summary: Vec<(&str, &str, bool, bool)> = parsed
+            .iter()
+            .map(|dep| {
+                (
+                    dep.name.as_str(),
+                    dep.constraint.as_str(),
+                    dep.recommended,
+                    dep.installed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("libc", "(>= 2.0)", false, true),
+                ("mawk", "| gawk", false, true),
+                ("python3", "(>= 3.11)", false, false),
+                ("ncurses", "| ncurses-ui", false, false),
+                ("ca-certificates", "", true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn pip_requires_and_nothing_elsewhere() {
+        let details = details(&[("Requires", "certifi, idna"), ("Depends", "libc")]);
+        let names: Vec<String> = parse(PackageManager::Pip, &details, |_| true)
+            .into_iter()
+            .map(|dep| dep.name)
+            .collect();
+        assert_eq!(names, ["certifi", "idna"]);
+        assert!(parse(PackageManager::Npm, &details, |_| true).is_empty());
+    }
+
+    #[test]
+    fn expand_and_collapse_branches() {
+        let mut tree = DepTree::new("curl".to_string(), deps(&["libcurl", "zlib"]));
+        assert_eq!(tree.expandable(), Some("libcurl"));
+        tree.expand(deps(&["libssl3", "zlib"]));
+        tree.select_next();
+        tree.expand(deps(&["ca-certificates"]));
+        assert_eq!(
+            rows(&tree),
+            [
+                "▾ libcurl",
+                "  ▾ libssl3",
+                "    ▸ ca-certificates",
+                "  ▸ zlib",
+                "▸ zlib"
+            ]
+        );
+        // From inside a branch, h closes its parent
+        tree.select_next();
+        tree.collapse();
+        assert_eq!(tree.selected().unwrap().name, "libssl3");
+        assert_eq!(
+            rows(&tree),
+            ["▾ libcurl", "  ▸ libssl3", "  ▸ zlib", "▸ zlib"]
+        );
+        tree.select_previous();
+        tree.collapse();
+        assert_eq!(rows(&tree), ["▸ libcurl", "▸ zlib"]);
+        // A top-level leaf has no parent to close
+        tree.select_next();
+        tree.collapse();
+        assert_eq!(tree.selected().unwrap().name, "zlib");
+        tree.select_next();
+        assert_eq!(tree.selected().unwrap().name, "zlib");
+    }
+
+    #[test]
+    fn cycles_and_missing_packages_are_leaves() {
+        let mut missing = dep("libgcc");
+        missing.installed = false;
+        let mut tree = DepTree::new("python".to_string(), vec![dep("libffi"), missing]);
+        tree.expand(deps(&["python", "libffi-dev"]));
+        tree.select_next();
+        assert_eq!(rows(&tree)[1], "  ↻ python");
+        assert_eq!(tree.expandable(), None);
+        tree.select_next();
+        tree.expand(deps(&["libffi"]));
+        // libffi is two levels up
+        assert_eq!(rows(&tree)[3], "    ↻ libffi");
+        tree.select_next();
+        assert_eq!(tree.expandable(), None);
+        tree.select_next();
+        assert_eq!(tree.selected().unwrap().name, "libgcc");
+        assert_eq!(tree.expandable(), None);
+    }
+
+    #[test]
+    fn branches_stop_at_the_depth_limit() {
+        let mut tree = DepTree::new("a".to_string(), deps(&["d0"]));
+        for depth in 1..MAX_DEPTH {
+            assert!(tree.expandable().is_some(), "depth {}", depth);
+            tree.expand(deps(&[&format!("d{}", depth)]));
+            tree.select_next();
+        }
+        assert_eq!(tree.nodes.len(), MAX_DEPTH);
+        assert_eq!(tree.expandable(), None);
+    }
+}
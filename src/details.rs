@@ -0,0 +1,285 @@
+// Structured view of `pkg show` / `apt show` / `pip show` / `opam show` output.
+//
+// The first three are `Key: value` lines, split at the first colon so URLs
+// stay whole; apt additionally folds long values onto continuation lines that
+// start with a space.
+use crate::PackageManager;
+
+#[derive(Debug, Default, Clone)]
+pub struct Details {
+    pub fields: Vec<(String, String)>,
+}
+
+impl Details {
+    pub fn parse(text: &str) -> Self {
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        for line in text.lines() {
+            if line.starts_with(' ') || line.starts_with('\t') {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                let key = key.trim();
+                if key.is_empty() || key.contains(' ') {
+                    continue; // Not a field, e.g. a stray warning line
+                }
+                fields.push((key.to_string(), value.trim().to_string()));
+            }
+        }
+
+        Self { fields }
+    }
+
+    // `opam show`: `<><> section <><>` banners, then `key   value` columns,
+    // with strings in double quotes.
+    pub fn parse_opam(text: &str) -> Self {
+        let mut fields: Vec<(String, String)> = Vec::new();
+
+        for line in text.lines() {
+            if line.starts_with("<><>") || line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with(' ') {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push('\n');
+                    value.push_str(line);
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .filter(|v| !v.contains('"'))
+                .unwrap_or(value);
+            fields.push((key.to_string(), value.to_string()));
+        }
+
+        Self { fields }
+    }
+
+    // `pip show`: `Key: value` lines too, but `-v` puts lists (Classifiers,
+    // Entry-points, Project-URLs) on indented lines under an empty field, a
+    // field may repeat (a Classifier per line), which makes one field with a
+    // value per line, and a License is copied as written, unindented, so a
+    // line that isn't a field belongs to the one above.
+    pub fn parse_pip(text: &str) -> Self {
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut last: Option<usize> = None;
+
+        for line in text.lines() {
+            let field = line
+                .split_once(':')
+                .filter(|(key, _)| !line.starts_with([' ', '\t']) && is_pip_key(key));
+            match (field, last) {
+                (Some((key, value)), _) => {
+                    let value = value.trim();
+                    let i = match fields.iter().position(|(k, _)| k == key) {
+                        Some(i) if value.is_empty() => i,
+                        Some(i) => {
+                            let existing = &mut fields[i].1;
+                            if !existing.is_empty() {
+                                existing.push('\n');
+                            }
+                            existing.push_str(value);
+                            i
+                        }
+                        None => {
+                            fields.push((key.to_string(), value.to_string()));
+                            fields.len() - 1
+                        }
+                    };
+                    last = Some(i);
+                }
+                (None, Some(i)) => {
+                    let value = &mut fields[i].1;
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(line);
+                }
+                (None, None) => {} // Before the first field, e.g. a warning
+            }
+        }
+        for (_, value) in &mut fields {
+            value.truncate(value.trim_end().len());
+        }
+
+        Self { fields }
+    }
+
+    // opam show lines up `key value` columns instead of `Key: value` fields,
+    // and pip's values need more care.
+    pub fn parse_for(manager: PackageManager, text: &str) -> Self {
+        match manager {
+            PackageManager::Opam => Self::parse_opam(text),
+            PackageManager::Pip => Self::parse_pip(text),
+            _ => Self::parse(text),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    // One-line summary: pip's Summary, or the synopsis line of apt's Description.
+    #[cfg(feature = "reports")]
+    pub fn summary(&self) -> Option<&str> {
+        self.get("Summary")
+            .or_else(|| self.get("Description").and_then(|d| d.lines().next()))
+            .map(str::trim)
+    }
+
+    // Long description with apt's continuation indent and ` .` paragraph markers removed.
+    #[cfg(feature = "reports")]
+    pub fn description(&self) -> Option<String> {
+        let raw = self.get("Description")?;
+        let body: Vec<&str> = raw
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let line = line.strip_prefix(' ').unwrap_or(line);
+                if line.trim() == "." {
+                    ""
+                } else {
+                    line
+                }
+            })
+            .collect();
+
+        let text = body.join("\n").trim().to_string();
+        if text.is_empty() {
+            None
+        } else {
+            Some(text)
+        }
+    }
+
+    pub fn homepage(&self) -> Option<&str> {
+        self.get("Homepage").or_else(|| self.get("Home-page"))
+    }
+
+    #[cfg(feature = "reports")]
+    pub fn installed_size(&self) -> Option<&str> {
+        self.get("Installed-Size")
+    }
+}
+
+// pip's field names: `Home-page`, `Required-by`, and the one with spaces.
+// A License line such as "Copyright (c) 2024: Someone" isn't one.
+fn is_pip_key(key: &str) -> bool {
+    key == "Editable project location"
+        || key.starts_with(|c: char| c.is_ascii_uppercase())
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUESTS: &str = include_str!("../tests/fixtures/pip-show-requests.txt");
+    const EDITABLE: &str = include_str!("../tests/fixtures/pip-show-editable.txt");
+
+    fn keys(details: &Details) -> Vec<&str> {
+        details.fields.iter().map(|(key, _)| key.as_str()).collect()
+    }
+
+    #[test]
+    fn pip_lists_start_at_their_first_entry() {
+        let details = Details::parse_for(PackageManager::Pip, REQUESTS);
+        assert_eq!(details.homepage(), Some("https://requests.readthedocs.io"));
+        assert_eq!(
+            details.get("Classifiers"),
+            Some(
+                "  Development Status :: 5 - Production/Stable\n  \
+                 License :: OSI Approved :: Apache Software License\n  \
+                 Programming Language :: Python :: 3"
+            )
+        );
+        assert_eq!(
+            details.get("Project-URLs"),
+            Some(
+                "  Documentation, https://requests.readthedocs.io\n  \
+                 Source, https://github.com/psf/requests"
+            )
+        );
+        // Empty fields stay, but read as missing
+        assert!(keys(&details).contains(&"Entry-points"));
+        assert_eq!(details.get("Entry-points"), None);
+        assert_eq!(details.get("Required-by"), None);
+    }
+
+    #[test]
+    fn pip_unindented_lines_belong_to_the_field_above() {
+        let details = Details::parse_pip(REQUESTS);
+        assert_eq!(
+            details.get("Requires"),
+            Some("certifi, charset-normalizer, idna,\nurllib3")
+        );
+        let details = Details::parse_pip(EDITABLE);
+        assert_eq!(
+            details.get("License"),
+            Some(
+                "MIT License\n\nCopyright (c) 2019: Someone\n\n\
+                 Permission is hereby granted, free of charge, to any person obtaining a copy"
+            )
+        );
+        assert_eq!(
+            keys(&details),
+            [
+                "Name",
+                "Version",
+                "Summary",
+                "Home-page",
+                "Author",
+                "License",
+                "Location",
+                "Editable project location",
+                "Requires",
+                "Required-by",
+                "Classifier",
+            ]
+        );
+    }
+
+    #[test]
+    fn pip_repeated_fields_merge() {
+        let details = Details::parse_pip(EDITABLE);
+        assert_eq!(
+            details.get("Classifier"),
+            Some("Programming Language :: Python :: 3\nOperating System :: Android")
+        );
+        assert_eq!(
+            details.get("Editable project location"),
+            Some("/data/data/com.termux/files/home/src/mytool")
+        );
+    }
+
+    #[test]
+    fn apt_splits_at_the_first_colon_and_folds_continuations() {
+        let text = "Package: curl\n\
+                    Homepage: https://curl.se/\n\
+                    Description: Command line URL tool\n \
+                    Transfers data with URLs.\n \
+                    .\n \
+                    Supports HTTP: and more\n";
+        let details = Details::parse_for(PackageManager::Apt, text);
+        assert_eq!(keys(&details), ["Package", "Homepage", "Description"]);
+        assert_eq!(details.homepage(), Some("https://curl.se/"));
+        assert_eq!(
+            details.get("Description"),
+            Some("Command line URL tool\n Transfers data with URLs.\n .\n Supports HTTP: and more")
+        );
+    }
+}
@@ -0,0 +1,621 @@
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::commands::{self, Action, Runner};
+use crate::{
+    config, configfile, hidden, history, i18n, installed_stanzas, lock, log, noupgrade, prefix,
+    python, root, sessions, PackageManager,
+};
+
+// Environment variables tpi reads, besides the TPI_<MANAGER>_<ACTION>_CMD
+// overrides.
+const SETTINGS: [&str; 21] = [
+    "TPI_MANAGERS",
+    "TPI_PROFILE",
+    "TPI_READ_ONLY",
+    "TPI_HOLD_WARN",
+    "TPI_CLEAR_SEARCH_ON_SWITCH",
+    "TPI_NO_ALT_SCREEN",
+    "TPI_INLINE_HEIGHT",
+    "TPI_LOG",
+    "TPI_HISTORY",
+    "TPI_PIP_NO_UPGRADE",
+    "TPI_SESSIONS",
+    "TPI_LOCK",
+    "TPI_SERVE_PORT",
+    "TPI_SERVE_TOKEN",
+    "TPI_MANIFEST",
+    "TPI_ALLOW_ROOT",
+    "TPI_TERMINAL_TITLE",
+    "TPI_INSTALL_WIDTH",
+    "TPI_CACHE_DIR",
+    "TPI_CONFIG",
+    "TPI_HIDDEN",
+];
+
+// Smallest terminal the list and detail pane are usable in.
+const MIN_SIZE: (u16, u16) = (40, 12);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Ok,
+    Warn, // Some feature won't work
+    Fail, // tpi itself won't work; `tpi doctor` exits 1
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Ok => "ok",
+            Level::Warn => "warn",
+            Level::Fail => "fail",
+        }
+    }
+}
+
+pub struct Item {
+    pub section: &'static str,
+    pub name: String,
+    pub level: Level,
+    pub detail: String,
+}
+
+// Everything `tpi doctor` looks at, in report order.
+pub fn run(runner: &dyn Runner) -> Vec<Item> {
+    let mut items = Vec::new();
+    managers(runner, &mut items);
+    dpkg_status(&mut items);
+    site_packages(&mut items);
+    termux_api(&mut items);
+    settings(&mut items);
+    user(&mut items);
+    files(&mut items);
+    terminal(&mut items);
+    language(&mut items);
+    items
+}
+
+fn item(section: &'static str, name: impl Into<String>, level: Level, detail: String) -> Item {
+    Item {
+        section,
+        name: name.into(),
+        level,
+        detail,
+    }
+}
+
+// Which list commands resolve, and what version each manager reports. Having
+// none at all is the only failure here.
+fn managers(runner: &dyn Runner, items: &mut Vec<Item>) {
+    const SECTION: &str = "Package managers";
+    let configured = config::managers();
+    let mut found = 0;
+    for manager in PackageManager::ALL {
+        let program = commands::resolve(manager, Action::List, "")
+            .map(|words| words[0].clone())
+            .unwrap_or_default();
+        let Some(path) = commands::find_program(&program) else {
+            let level = if configured.explicit && configured.enabled.contains(&manager) {
+                Level::Warn // Named in $TPI_MANAGERS, so it was expected
+            } else {
+                Level::Ok
+            };
+            items.push(item(
+                SECTION,
+                manager.name(),
+                level,
+                format!("{} not found on PATH", program),
+            ));
+            continue;
+        };
+        found += 1;
+        let version = commands::run_with(runner, manager, Action::Version, "")
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                stdout.lines().next().map(|line| line.trim().to_string())
+            })
+            .unwrap_or_else(|| "version unknown".to_string());
+        items.push(item(
+            SECTION,
+            manager.name(),
+            Level::Ok,
+            format!("{} ({})", version, path.display()),
+        ));
+    }
+    if found == 0 {
+        items.push(item(
+            SECTION,
+            "any",
+            Level::Fail,
+            "no supported package manager found on PATH".to_string(),
+        ));
+    }
+}
+
+// pkg and apt are listed from dpkg's database in the fast profile, and
+// priorities always come from it.
+fn dpkg_status(items: &mut Vec<Item>) {
+    const SECTION: &str = "dpkg database";
+    let path = format!("{}/var/lib/dpkg/status", prefix());
+    let needed =
+        commands::is_available(PackageManager::Pkg) || commands::is_available(PackageManager::Apt);
+    let Some(stanzas) = installed_stanzas() else {
+        let level = if needed { Level::Fail } else { Level::Ok };
+        items.push(item(
+            SECTION,
+            "status",
+            level,
+            format!("{} is not readable", path),
+        ));
+        return;
+    };
+    let unnamed = stanzas
+        .iter()
+        .filter(|details| details.get("Package").is_none() || details.get("Version").is_none())
+        .count();
+    let (level, detail) = match (stanzas.len(), unnamed) {
+        (0, _) if needed => (Level::Fail, format!("{}: no installed packages", path)),
+        (count, 0) => (Level::Ok, format!("{}: {} installed packages", path, count)),
+        (count, unnamed) => (
+            Level::Warn,
+            format!(
+                "{}: {} installed packages, {} without Package or Version",
+                path, count, unnamed
+            ),
+        ),
+    };
+    items.push(item(SECTION, "status", level, detail));
+}
+
+// Where python upgrades are looked for (`$PREFIX/lib/python3.*`).
+fn site_packages(items: &mut Vec<Item>) {
+    const SECTION: &str = "Python";
+    let lib = Path::new(&prefix()).join("lib");
+    let mut found: Vec<(String, usize)> = fs::read_dir(&lib)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_str()?.to_string();
+                    name.starts_with("python3.").then(|| {
+                        let count =
+                            python::read_dist_info(&entry.path().join("site-packages")).len();
+                        (name, count)
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    found.sort();
+    if found.is_empty() {
+        let level = if commands::is_available(PackageManager::Pip) {
+            Level::Warn
+        } else {
+            Level::Ok
+        };
+        items.push(item(
+            SECTION,
+            "site-packages",
+            level,
+            format!("no python3.* directories in {}", lib.display()),
+        ));
+        return;
+    }
+    for (name, count) in found {
+        items.push(item(
+            SECTION,
+            format!("{}/site-packages", name),
+            Level::Ok,
+            format!("{} distributions", count),
+        ));
+    }
+}
+
+// Used to copy to the clipboard and open home pages; without them copying
+// falls back to desktop tools or OSC 52, and opening fails.
+fn termux_api(items: &mut Vec<Item>) {
+    for program in ["termux-clipboard-set", "termux-open-url"] {
+        items.push(match commands::find_program(program) {
+            Some(path) => item("termux-api", program, Level::Ok, path.display().to_string()),
+            None => item(
+                "termux-api",
+                program,
+                Level::Warn,
+                "not found (pkg install termux-api)".to_string(),
+            ),
+        });
+    }
+}
+
+// tpi is configured through the environment; this catches typos and values
+// that are silently ignored.
+fn settings(items: &mut Vec<Item>) {
+    const SECTION: &str = "Configuration";
+    let managers = config::managers();
+    if !managers.unknown.is_empty() {
+        items.push(item(
+            SECTION,
+            "TPI_MANAGERS",
+            Level::Warn,
+            format!("unknown managers: {}", managers.unknown.join(", ")),
+        ));
+    }
+    let number = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .filter(|value| value.trim().parse::<u64>().is_err())
+    };
+    let stale: Vec<String> = PackageManager::ALL
+        .into_iter()
+        .map(config::stale_var)
+        .collect();
+    for name in ["TPI_HOLD_WARN", "TPI_INLINE_HEIGHT", "TPI_SERVE_PORT"]
+        .into_iter()
+        .chain(stale.iter().map(String::as_str))
+    {
+        if let Some(value) = number(name) {
+            items.push(item(
+                SECTION,
+                name,
+                Level::Warn,
+                format!("'{}' is not a number, the default is used", value),
+            ));
+        }
+    }
+    if let Ok(value) = std::env::var("TPI_PROFILE") {
+        if !matches!(value.trim(), "fast" | "full") {
+            items.push(item(
+                SECTION,
+                "TPI_PROFILE",
+                Level::Warn,
+                format!("'{}' is neither fast nor full, full is used", value),
+            ));
+        }
+    }
+
+    let overrides: Vec<String> = PackageManager::ALL
+        .into_iter()
+        .flat_map(|manager| Action::ALL.map(|action| commands::env_var(manager, action)))
+        .collect();
+    let mut set: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("TPI_"))
+        .collect();
+    set.sort();
+    for (name, value) in set {
+        if overrides.contains(&name) {
+            items.push(item(
+                SECTION,
+                name.as_str(),
+                Level::Ok,
+                format!("command overridden: {}", value),
+            ));
+        } else if SETTINGS.contains(&name.as_str()) || stale.contains(&name) {
+            if !items.iter().any(|item| item.name == name) {
+                // Doctor output goes into bug reports; the token stays out of it
+                let value = if name == "TPI_SERVE_TOKEN" {
+                    "set".to_string()
+                } else {
+                    value
+                };
+                items.push(item(SECTION, name.as_str(), Level::Ok, value));
+            }
+        } else {
+            items.push(item(
+                SECTION,
+                name.as_str(),
+                Level::Warn,
+                "not a setting tpi knows".to_string(),
+            ));
+        }
+    }
+    if !items.iter().any(|item| item.section == SECTION) {
+        items.push(item(
+            SECTION,
+            "environment",
+            Level::Ok,
+            "no TPI_ settings, defaults apply".to_string(),
+        ));
+    }
+}
+
+// Files tpi reads and writes: the config file, the version history, the
+// do-not-upgrade list and, when enabled, the debug log.
+fn files(items: &mut Vec<Item>) {
+    const SECTION: &str = "Files";
+    items.push(match (configfile::path(None), configfile::load(None)) {
+        (_, (_, Some(err))) => item(SECTION, "config", Level::Warn, err),
+        (Some(path), _) if path.exists() => {
+            item(SECTION, "config", Level::Ok, path.display().to_string())
+        }
+        (Some(path), _) => item(
+            SECTION,
+            "config",
+            Level::Ok,
+            format!("{} (none, defaults apply)", path.display()),
+        ),
+        (None, _) => item(
+            SECTION,
+            "config",
+            Level::Ok,
+            "no location: defaults apply".to_string(),
+        ),
+    });
+    match history::path() {
+        Some(path) => {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let writable = fs::create_dir_all(dir).is_ok()
+                && fs::metadata(dir).is_ok_and(|meta| !meta.permissions().readonly());
+            let (level, note) = if writable {
+                (Level::Ok, "")
+            } else {
+                (Level::Warn, " (directory not writable)")
+            };
+            items.push(item(
+                SECTION,
+                "history",
+                level,
+                format!("{}{}", path.display(), note),
+            ));
+        }
+        None => items.push(item(
+            SECTION,
+            "history",
+            Level::Warn,
+            "no location: set TPI_HISTORY, XDG_STATE_HOME or HOME".to_string(),
+        )),
+    }
+    items.push(match noupgrade::path() {
+        Some(path) if path.exists() => item(
+            SECTION,
+            "pip do-not-upgrade list",
+            Level::Ok,
+            path.display().to_string(),
+        ),
+        Some(path) => item(
+            SECTION,
+            "pip do-not-upgrade list",
+            Level::Ok,
+            format!("{} (not written yet, defaults apply)", path.display()),
+        ),
+        None => item(
+            SECTION,
+            "pip do-not-upgrade list",
+            Level::Warn,
+            "no location: defaults apply and U can't save".to_string(),
+        ),
+    });
+    items.push(match hidden::path() {
+        Some(path) if path.exists() => item(
+            SECTION,
+            "hidden packages",
+            Level::Ok,
+            path.display().to_string(),
+        ),
+        Some(path) => item(
+            SECTION,
+            "hidden packages",
+            Level::Ok,
+            format!("{} (none hidden yet)", path.display()),
+        ),
+        None => item(
+            SECTION,
+            "hidden packages",
+            Level::Warn,
+            "no location: z can't save; set TPI_HIDDEN, XDG_DATA_HOME or HOME".to_string(),
+        ),
+    });
+    items.push(match sessions::path() {
+        Some(path) => match sessions::load() {
+            Ok(saved) => item(
+                SECTION,
+                "sessions",
+                Level::Ok,
+                format!("{} ({} saved)", path.display(), saved.len()),
+            ),
+            Err(err) => item(SECTION, "sessions", Level::Warn, err),
+        },
+        None => item(
+            SECTION,
+            "sessions",
+            Level::Warn,
+            "no location: set TPI_SESSIONS, XDG_DATA_HOME or HOME".to_string(),
+        ),
+    });
+    items.push(match lock::path() {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(pid) if pid.trim() == std::process::id().to_string() => item(
+                SECTION,
+                "lock",
+                Level::Ok,
+                format!("{} (held by this tpi)", path.display()),
+            ),
+            Ok(pid) => match lock::holder() {
+                Some(holder) => item(
+                    SECTION,
+                    "lock",
+                    Level::Warn,
+                    format!(
+                        "{} held by PID {}: this tpi won't save",
+                        path.display(),
+                        holder
+                    ),
+                ),
+                None => item(
+                    SECTION,
+                    "lock",
+                    Level::Ok,
+                    format!("{} (held by PID {})", path.display(), pid.trim()),
+                ),
+            },
+            Err(_) => item(
+                SECTION,
+                "lock",
+                Level::Ok,
+                format!("{} (free)", path.display()),
+            ),
+        },
+        None => item(
+            SECTION,
+            "lock",
+            Level::Warn,
+            "no location: set TPI_LOCK, XDG_STATE_HOME or HOME".to_string(),
+        ),
+    });
+    items.push(item(
+        SECTION,
+        "log",
+        Level::Ok,
+        log::path().unwrap_or_else(|| "off (set TPI_LOG to a file)".to_string()),
+    ));
+}
+
+// Termux is meant to run as its app's user; as root tpi changes nothing and
+// keeps its state files out of root's home.
+fn user(items: &mut Vec<Item>) {
+    const SECTION: &str = "User";
+    items.push(if !root::detected() {
+        item(SECTION, "root", Level::Ok, "not running as root".to_string())
+    } else if config::allow_root() {
+        item(
+            SECTION,
+            "root",
+            Level::Warn,
+            "running as root, changes allowed by TPI_ALLOW_ROOT; state files under $HOME are not used".to_string(),
+        )
+    } else {
+        item(
+            SECTION,
+            "root",
+            Level::Warn,
+            "running as root: changing packages is disabled and state files under $HOME are not used".to_string(),
+        )
+    });
+}
+
+// The TUI needs a terminal on stdin and stdout that is big enough; colours
+// are used for badges but nothing depends on them.
+fn terminal(items: &mut Vec<Item>) {
+    const SECTION: &str = "Terminal";
+    let tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    items.push(if tty {
+        item(
+            SECTION,
+            "tty",
+            Level::Ok,
+            "stdin and stdout are a terminal".to_string(),
+        )
+    } else {
+        item(
+            SECTION,
+            "tty",
+            Level::Warn,
+            "not run from a terminal, the TUI can't start here".to_string(),
+        )
+    });
+    if let Ok((width, height)) = crossterm::terminal::size() {
+        let level = if width >= MIN_SIZE.0 && height >= MIN_SIZE.1 {
+            Level::Ok
+        } else {
+            Level::Warn
+        };
+        items.push(item(
+            SECTION,
+            "size",
+            level,
+            format!(
+                "{}x{} (at least {}x{} needed)",
+                width, height, MIN_SIZE.0, MIN_SIZE.1
+            ),
+        ));
+    }
+    let term = std::env::var("TERM").unwrap_or_default();
+    let colors = match std::env::var("COLORTERM").as_deref() {
+        Ok("truecolor" | "24bit") => "24-bit colour",
+        _ if term.contains("256color") => "256 colours",
+        _ => "basic colours",
+    };
+    items.push(if term.is_empty() || term == "dumb" {
+        item(
+            SECTION,
+            "TERM",
+            Level::Warn,
+            format!("'{}' can't position the cursor", term),
+        )
+    } else {
+        item(SECTION, "TERM", Level::Ok, format!("{} ({})", term, colors))
+    });
+}
+
+// The UI's language, and whether every catalog has the messages English has
+// and no others (a misspelt key would never be shown).
+fn language(items: &mut Vec<Item>) {
+    const SECTION: &str = "Language";
+    let language = i18n::messages().language;
+    items.push(match i18n::requested() {
+        Some((name, value)) if i18n::language_of(&value) == language => item(
+            SECTION,
+            "messages",
+            Level::Ok,
+            format!("{} ({}={})", language, name, value),
+        ),
+        Some((name, value)) => item(
+            SECTION,
+            "messages",
+            Level::Ok,
+            format!("en ({}={} has no catalog)", name, value),
+        ),
+        None => item(
+            SECTION,
+            "messages",
+            Level::Ok,
+            "en (no LC_ALL, LC_MESSAGES or LANG)".to_string(),
+        ),
+    });
+    let english: Vec<String> = match i18n::catalog("en") {
+        Ok(strings) => strings.into_iter().map(|(key, _)| key).collect(),
+        Err(err) => {
+            items.push(item(SECTION, "en", Level::Warn, err));
+            return;
+        }
+    };
+    for (name, _) in i18n::CATALOGS.iter().skip(1) {
+        let keys: Vec<String> = match i18n::catalog(name) {
+            Ok(strings) => strings.into_iter().map(|(key, _)| key).collect(),
+            Err(err) => {
+                items.push(item(SECTION, *name, Level::Warn, err));
+                continue;
+            }
+        };
+        let missing: Vec<&str> = english
+            .iter()
+            .filter(|key| !keys.contains(key))
+            .map(String::as_str)
+            .collect();
+        let unknown: Vec<&str> = keys
+            .iter()
+            .filter(|key| !english.contains(key))
+            .map(String::as_str)
+            .collect();
+        items.push(match (missing.is_empty(), unknown.is_empty()) {
+            (true, true) => item(
+                SECTION,
+                *name,
+                Level::Ok,
+                format!("all {} messages", keys.len()),
+            ),
+            _ => {
+                let mut problems = Vec::new();
+                if !missing.is_empty() {
+                    problems.push(format!("lacks {} (English shown)", missing.join(", ")));
+                }
+                if !unknown.is_empty() {
+                    problems.push(format!("has unknown {}", unknown.join(", ")));
+                }
+                item(SECTION, *name, Level::Warn, problems.join("; "))
+            }
+        });
+    }
+}
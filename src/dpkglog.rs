@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::commands::Runner;
+use crate::{civil_from_days, days_from_civil, prefix};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Install,
+    Upgrade,
+    Remove,
+    Purge,
+}
+
+impl Kind {
+    fn verb(self) -> &'static str {
+        match self {
+            Kind::Install => "installed",
+            Kind::Upgrade => "upgraded",
+            Kind::Remove => "removed",
+            Kind::Purge => "purged",
+        }
+    }
+}
+
+// One action line of dpkg.log:
+//
+//     2024-05-01 10:20:30 upgrade openssl:aarch64 1.1.1w 3.2.1
+//
+// Versions are None where dpkg writes `<none>`.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub date: String, // YYYY-MM-DD, local time as dpkg wrote it
+    pub days: i64,    // The date as days since 1970-01-01
+    pub kind: Kind,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// Every install, upgrade and removal in dpkg's logs, oldest first:
+// $PREFIX/var/log/dpkg.log and its rotations (dpkg.log.1, dpkg.log.2.gz, …).
+// Compressed rotations are unpacked with `gzip -dc`. Indexed by package, so
+// a package's timeline is a lookup however many years the logs span.
+#[derive(Default)]
+pub struct Log {
+    events: Vec<Event>,
+    by_package: HashMap<String, Vec<usize>>,
+    pub files: usize,  // Log files read
+    pub unread: usize, // Compressed rotations gzip couldn't unpack
+}
+
+impl Log {
+    pub fn load(runner: &dyn Runner) -> Self {
+        Self::load_in(runner, &format!("{}/var/log", prefix()))
+    }
+
+    fn load_in(runner: &dyn Runner, dir: &str) -> Self {
+        let mut rotated: Vec<(u32, String)> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let rest = name.strip_prefix("dpkg.log.")?;
+                let n = rest.strip_suffix(".gz").unwrap_or(rest).parse().ok()?;
+                Some((n, name))
+            })
+            .collect();
+        // The highest number is the oldest
+        rotated.sort_by_key(|(n, _)| std::cmp::Reverse(*n));
+        let mut log = Self::default();
+        let names = rotated
+            .into_iter()
+            .map(|(_, name)| name)
+            .chain(["dpkg.log".to_string()]);
+        for name in names {
+            let path = format!("{}/{}", dir, name);
+            let text = if name.ends_with(".gz") {
+                let words = ["gzip", "-dc", &path].map(String::from);
+                match runner.output(&words) {
+                    Ok(output) if output.status.success() => {
+                        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+                    }
+                    _ => {
+                        log.unread += 1;
+                        None
+                    }
+                }
+            } else {
+                fs::read_to_string(&path).ok()
+            };
+            if let Some(text) = text {
+                log.add(&text);
+                log.files += 1;
+            }
+        }
+        log
+    }
+
+    // Lines other than install, upgrade, remove and purge (status changes,
+    // configure, trigproc, startup) are skipped.
+    pub fn add(&mut self, text: &str) {
+        for line in text.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let [date, _time, action, package, from, to] = words[..] else {
+                continue;
+            };
+            let kind = match action {
+                "install" => Kind::Install,
+                "upgrade" => Kind::Upgrade,
+                "remove" => Kind::Remove,
+                "purge" => Kind::Purge,
+                _ => continue,
+            };
+            let Some(days) = parse_date(date) else {
+                continue;
+            };
+            let version = |version: &str| (version != "<none>").then(|| version.to_string());
+            let name = package.split(':').next().unwrap_or(package);
+            self.by_package
+                .entry(name.to_string())
+                .or_default()
+                .push(self.events.len());
+            self.events.push(Event {
+                date: date.to_string(),
+                days,
+                kind,
+                from: version(from),
+                to: version(to),
+            });
+        }
+    }
+
+    pub fn events(&self, name: &str) -> Vec<&Event> {
+        self.by_package
+            .get(name)
+            .map(|indexes| indexes.iter().map(|&i| &self.events[i]).collect())
+            .unwrap_or_default()
+    }
+
+    // Date of the package's most recent install.
+    pub fn last_install(&self, name: &str) -> Option<&str> {
+        self.events(name)
+            .into_iter()
+            .rev()
+            .find(|event| event.kind == Kind::Install)
+            .map(|event| event.date.as_str())
+    }
+
+    // Installs and upgrades per calendar month, the last `months` of them up
+    // to this one, oldest first.
+    pub fn monthly(&self, months: usize) -> Vec<u64> {
+        let index = |days| {
+            let (year, month, _) = civil_from_days(days);
+            year * 12 + i64::from(month)
+        };
+        let current = index(today());
+        let mut counts = vec![0; months];
+        for event in &self.events {
+            if !matches!(event.kind, Kind::Install | Kind::Upgrade) {
+                continue;
+            }
+            let ago = current - index(event.days);
+            if (0..months as i64).contains(&ago) {
+                counts[months - 1 - ago as usize] += 1;
+            }
+        }
+        counts
+    }
+}
+
+// dpkg's logs for one listing, read on a background thread the first time
+// something asks for them: years of rotations take a while on a phone, and
+// the UI keeps drawing meanwhile.
+#[derive(Default)]
+pub struct Lazy {
+    log: Arc<OnceLock<Log>>,
+    started: AtomicBool,
+    arrived: Arc<AtomicBool>, // Set by the thread, taken by `poll`
+}
+
+impl Lazy {
+    // The logs once read; None while they are, starting the read if it
+    // hasn't been.
+    pub fn get_or_start(&self, runner: &Arc<dyn Runner>) -> Option<&Log> {
+        if let Some(log) = self.log.get() {
+            return Some(log);
+        }
+        if !self.started.swap(true, Ordering::Relaxed) {
+            let log = self.log.clone();
+            let arrived = self.arrived.clone();
+            let runner = runner.clone();
+            thread::spawn(move || {
+                let _ = log.set(Log::load(runner.as_ref()));
+                arrived.store(true, Ordering::Relaxed);
+            });
+        }
+        None
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.log.get().is_some()
+    }
+
+    // Whether the logs arrived since the last call.
+    pub fn poll(&self) -> bool {
+        self.arrived.swap(false, Ordering::Relaxed)
+    }
+
+    #[cfg(test)]
+    pub fn set(&self, log: Log) -> Result<(), Log> {
+        self.log.set(log)
+    }
+}
+
+fn today() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() / 86_400) as i64
+}
+
+fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    Some(days_from_civil(year, month, day))
+}
+
+// The package's events as a vertical timeline, each gap labelled with how
+// long the package stayed as it was: "stayed at 1.1.1w for 14 months".
+pub fn timeline(log: &Log, name: &str) -> String {
+    let events = log.events(name);
+    if events.is_empty() {
+        return format!(
+            "{} doesn't appear in dpkg's logs ({})",
+            name,
+            files_label(log)
+        );
+    }
+    let today = today();
+    let mut text = format!("dpkg log timeline ({}):\n\n", files_label(log));
+    let mut removed = false;
+    for (i, event) in events.iter().enumerate() {
+        let what = match (event.kind, &event.from, &event.to) {
+            (Kind::Install, _, Some(to)) if removed => format!("reinstalled {}", to),
+            (Kind::Install, _, Some(to)) => format!("installed {}", to),
+            (Kind::Upgrade, Some(from), Some(to)) if from == to => format!("reinstalled {}", to),
+            (Kind::Upgrade, Some(from), Some(to)) => format!("upgraded {} → {}", from, to),
+            (Kind::Remove, Some(from), _) => format!("removed {}", from),
+            (Kind::Purge, _, _) => "purged its configuration".to_string(),
+            (kind, _, _) => format!("{} (version not logged)", kind.verb()),
+        };
+        text.push_str(&format!("{}  ● {}\n", event.date, what));
+        removed = matches!(event.kind, Kind::Remove | Kind::Purge);
+
+        let next = events.get(i + 1);
+        let days = next.map_or(today, |next| next.days) - event.days;
+        let state = match (&event.to, removed) {
+            (Some(version), false) => format!("at {}", version),
+            _ => "not installed".to_string(),
+        };
+        let gap = match next {
+            Some(_) if removed => format!("{} for {}", state, duration(days)),
+            Some(_) => format!("stayed {} for {}", state, duration(days)),
+            None => format!("{} for {}, to this day", state, duration(days)),
+        };
+        let rail = if next.is_some() { '│' } else { '╵' };
+        text.push_str(&format!("{:10}  {}   {}\n", "", rail, gap));
+    }
+    text
+}
+
+// "3 log files read", and how many compressed ones were left out.
+fn files_label(log: &Log) -> String {
+    let read = match log.files {
+        1 => "1 log file read".to_string(),
+        n => format!("{} log files read", n),
+    };
+    match log.unread {
+        0 => read,
+        n => format!(
+            "{}; {} compressed left out, gzip couldn't unpack {}",
+            read,
+            n,
+            if n == 1 { "it" } else { "them" }
+        ),
+    }
+}
+
+// Days, roughly, in the largest unit that fits: "3 weeks", "14 months".
+fn duration(days: i64) -> String {
+    let plural = |n: i64, unit: &str| match n {
+        1 => format!("1 {}", unit),
+        n => format!("{} {}s", n, unit),
+    };
+    match days {
+        i64::MIN..=0 => "less than a day".to_string(),
+        1..=13 => plural(days, "day"),
+        14..=59 => plural(days / 7, "week"),
+        60..=729 => plural(days / 30, "month"),
+        _ => match days % 365 / 30 {
+            0 => plural(days / 365, "year"),
+            months => format!(
+                "{}, {}",
+                plural(days / 365, "year"),
+                plural(months, "month")
+            ),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{sandbox, Mock};
+
+    #[test]
+    fn compressed_rotations_through_gzip() {
+        let dir = sandbox().join("dpkglogs");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("dpkg.log"),
+            "2024-05-01 10:00:00 upgrade gzip:aarch64 1.12 1.13\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("dpkg.log.1"),
+            "2023-02-22 10:00:00 upgrade gzip:aarch64 1.11 1.12\n",
+        )
+        .unwrap();
+        for name in ["dpkg.log.2.gz", "dpkg.log.3.gz"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let dir = dir.to_string_lossy().into_owned();
+        let mock = Mock::new()
+            .reply(
+                &format!("gzip -dc {}/dpkg.log.2.gz", dir),
+                "2023-02-01 10:00:00 install gzip:aarch64 <none> 1.11\n",
+            )
+            .fail(&format!("gzip -dc {}/dpkg.log.3.gz", dir), "unexpected end");
+        let log = Log::load_in(&mock, &dir);
+
+        let versions: Vec<_> = log
+            .events("gzip")
+            .iter()
+            .map(|event| event.to.as_deref().unwrap_or(""))
+            .collect();
+        assert_eq!(versions, ["1.11", "1.12", "1.13"]);
+        assert_eq!((log.files, log.unread), (3, 1));
+        let text = timeline(&log, "gzip");
+        assert!(
+            text.starts_with(
+                "dpkg log timeline (3 log files read; \
+                 1 compressed left out, gzip couldn't unpack it):"
+            ),
+            "{}",
+            text
+        );
+    }
+}
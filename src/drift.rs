@@ -0,0 +1,235 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::commands::{self, Action, Runner};
+use crate::{i18n, names, parse_package_list, Package, PackageManager};
+
+// How far the device has drifted from a manifest: a bootstrap script as `B`
+// or `tpi bootstrap` write it, read back as the packages each manager was
+// asked to install.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    InManifest,
+    Added,   // Installed on request since, not in the manifest
+    Missing, // In the manifest, not installed
+}
+
+impl State {
+    pub const ALL: [State; 3] = [State::InManifest, State::Added, State::Missing];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            State::InManifest => i18n::text("drift.in_manifest"),
+            State::Added => i18n::text("drift.added"),
+            State::Missing => i18n::text("drift.missing"),
+        }
+    }
+}
+
+pub struct Manifest {
+    pub path: PathBuf,
+    sections: HashMap<PackageManager, BTreeSet<String>>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let sections = parse(&text);
+        if sections.is_empty() {
+            return Err(io::Error::other("no install lines in it"));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            sections,
+        })
+    }
+
+    // pkg and apt install from the same line.
+    fn names(&self, manager: PackageManager) -> Option<&BTreeSet<String>> {
+        let manager = match manager {
+            PackageManager::Apt => PackageManager::Pkg,
+            other => other,
+        };
+        self.sections.get(&manager)
+    }
+}
+
+// Install lines of a script, by manager. Options are skipped and versions
+// dropped (`a==1.2`, `vendor/pkg:^2`, `name@1.0`), so only names are compared.
+fn parse(text: &str) -> HashMap<PackageManager, BTreeSet<String>> {
+    let mut sections: HashMap<PackageManager, BTreeSet<String>> = HashMap::new();
+    for line in text.replace("\\\n", " ").lines() {
+        // Quoted as the shell would read them, as `B` quotes odd names
+        let words = commands::split_words(line);
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        let (manager, skip) = match words.as_slice() {
+            ["pkg" | "apt" | "apt-get", "install", ..] => (PackageManager::Pkg, 2),
+            ["pip" | "pip3", "install", ..] => (PackageManager::Pip, 2),
+            ["luarocks", "install", ..] => (PackageManager::Luarocks, 2),
+            ["composer", "global", "require", ..] => (PackageManager::Composer, 3),
+            ["opam", "install", ..] => (PackageManager::Opam, 2),
+            ["cargo", "install", ..] => (PackageManager::Cargo, 2),
+            ["npm", "install" | "i", ..] => (PackageManager::Npm, 2),
+            _ => continue,
+        };
+        let mut names = words[skip..]
+            .iter()
+            .filter(|word| !word.starts_with('-'))
+            .map(|word| bare_name(manager, word));
+        let entry = sections.entry(manager).or_default();
+        // The second word of `luarocks install` is the rock's version
+        if manager == PackageManager::Luarocks {
+            entry.extend(names.next());
+        } else {
+            entry.extend(names);
+        }
+    }
+    sections
+}
+
+fn bare_name(manager: PackageManager, word: &str) -> String {
+    let word = word.trim_matches(['"', '\'']);
+    let name = match manager {
+        PackageManager::Pip => word.split(['=', '<', '>', '~', '!', '[', ';']).next(),
+        PackageManager::Composer => word.split(':').next(),
+        PackageManager::Opam => word.split(['.', '=']).next(),
+        // A leading @ is the scope, a later one the version
+        PackageManager::Npm => Some(match word.get(1..).and_then(|rest| rest.find('@')) {
+            Some(at) => &word[..at + 1],
+            None => word,
+        }),
+        _ => word.split('=').next(),
+    };
+    name.unwrap_or(word).to_string()
+}
+
+// The manifest as it applies to one manager's list.
+pub struct Drift {
+    pub manifest: Arc<Manifest>,
+    requested: Option<HashSet<String>>, // Installed by name rather than as a dependency, when the manager tells
+    pub missing: Vec<String>,           // In the manifest, not installed
+}
+
+impl Drift {
+    pub fn new(
+        manifest: Arc<Manifest>,
+        runner: &dyn Runner,
+        manager: PackageManager,
+        items: &[Package],
+    ) -> Self {
+        // `pip install Pillow` in the script and `pillow` in the list
+        let installed: HashSet<String> = items
+            .iter()
+            .map(|pkg| names::canonical(manager, &pkg.name))
+            .collect();
+        let missing = manifest
+            .names(manager)
+            .map(|listed| {
+                listed
+                    .iter()
+                    .filter(|name| !installed.contains(&names::canonical(manager, name)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            manifest,
+            requested: requested(runner, manager),
+            missing,
+        }
+    }
+
+    // For a list still loading: nothing is missing or added yet.
+    pub fn pending(manifest: Arc<Manifest>) -> Self {
+        Self {
+            manifest,
+            requested: Some(HashSet::new()),
+            missing: Vec::new(),
+        }
+    }
+
+    // None for a dependency that isn't in the manifest either: it came with
+    // something, which is not drift of its own.
+    pub fn state(&self, manager: PackageManager, name: &str) -> Option<State> {
+        let key = names::canonical(manager, name);
+        if self.manifest.names(manager).is_some_and(|listed| {
+            listed
+                .iter()
+                .any(|listed| names::canonical(manager, listed) == key)
+        }) {
+            return Some(State::InManifest);
+        }
+        match &self.requested {
+            Some(requested) if !requested.contains(&key) => None,
+            _ => Some(State::Added),
+        }
+    }
+}
+
+// The packages that were asked for by name, as the bootstrap script picks
+// them: apt-mark's manual ones, and pip, composer and opam's top level.
+// None where every package counts.
+fn requested(runner: &dyn Runner, manager: PackageManager) -> Option<HashSet<String>> {
+    let action = match manager {
+        PackageManager::Pkg | PackageManager::Apt => Action::Manual,
+        PackageManager::Pip | PackageManager::Composer | PackageManager::Opam => Action::TopLevel,
+        _ => return None,
+    };
+    if !commands::supports(manager, action) {
+        return None;
+    }
+    let output = commands::run_with(runner, manager, action, "")
+        .ok()
+        .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(match action {
+        Action::Manual => stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| names::canonical(manager, line))
+            .collect(),
+        _ => parse_package_list(manager, &stdout)
+            .ok()?
+            .packages
+            .into_iter()
+            .map(|pkg| names::canonical(manager, &pkg.name))
+            .collect(),
+    })
+}
+
+// The scripts come from `B`, which the reports feature builds
+#[cfg(all(test, feature = "reports"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::{self, Section};
+
+    #[test]
+    fn bootstrap_scripts_read_back_whatever_the_names() {
+        let odd = ["a;b", "$(id)", "it's", "x y"];
+        let sections = [
+            Section {
+                manager: PackageManager::Pkg,
+                names: vec!["curl".to_string(), odd[0].to_string()],
+            },
+            Section {
+                manager: PackageManager::Pip,
+                names: odd[1..].iter().map(|name| name.to_string()).collect(),
+            },
+        ];
+        let script = bootstrap::script(&sections);
+        assert!(script.contains("\npip install '$(id)' 'it'\\''s' 'x y'\n"));
+        let parsed = parse(&script);
+        assert_eq!(
+            parsed[&PackageManager::Pkg],
+            BTreeSet::from(["a;b", "curl", "python"].map(String::from))
+        );
+        assert_eq!(
+            parsed[&PackageManager::Pip],
+            BTreeSet::from(["$(id)", "it's", "x y"].map(String::from))
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+// Writes a file the user asked for (report, script, file list) as `name` in
+// config::export_dir(). One already there is never replaced: the name gets a
+// number instead, tpi-report-2.md, tpi-report-3.md and so on. Returns the
+// path written.
+pub fn write(name: &str, text: &str) -> io::Result<PathBuf> {
+    write_in(&config::export_dir(), name, text)
+}
+
+fn write_in(dir: &Path, name: &str, text: &str) -> io::Result<PathBuf> {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, format!(".{}", extension)),
+        None => (name, String::new()),
+    };
+    for n in 1.. {
+        let path = match n {
+            1 => dir.join(name),
+            n => dir.join(format!("{}-{}{}", stem, n, extension)),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(text.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && n < 1000 => {}
+            Err(err) => return Err(err),
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn numbered_rather_than_replaced() {
+        let dir = crate::tests::sandbox().join("exports");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (text, name) in [
+            ("one", "tpi-report.md"),
+            ("two", "tpi-report-2.md"),
+            ("three", "tpi-report-3.md"),
+        ] {
+            let path = write_in(&dir, "tpi-report.md", text).unwrap();
+            assert_eq!(path, dir.join(name));
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join("tpi-report.md")).unwrap(),
+            "one"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("tpi-report-3.md")).unwrap(),
+            "three"
+        );
+        let path = write_in(&dir, "notes", "").unwrap();
+        assert_eq!(path, dir.join("notes"));
+        assert_eq!(write_in(&dir, "notes", "").unwrap(), dir.join("notes-2"));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::names;
+
+// An extra a distribution declares, with the extra-gated requirements that
+// aren't installed.
+pub struct Extra {
+    pub name: String,
+    pub missing: Vec<String>,
+}
+
+// Declared extras of every pip distribution, from Provides-Extra and the
+// `extra == "..."` markers of Requires-Dist, keyed by canonical name.
+pub struct Extras {
+    by_package: HashMap<String, Vec<Extra>>,
+}
+
+impl Extras {
+    // "Extras: socks ✓, security ✗ (missing pysocks)"
+    pub fn text(&self, name: &str) -> Option<String> {
+        let extras = self.by_package.get(&names::pip(name))?;
+        let parts: Vec<String> = extras
+            .iter()
+            .map(|extra| match extra.missing.as_slice() {
+                [] => format!("{} ✓", extra.name),
+                missing => format!("{} ✗ (missing {})", extra.name, missing.join(", ")),
+            })
+            .collect();
+        Some(format!("Extras: {}\n", parts.join(", ")))
+    }
+}
+
+// From the output of the pip METADATA command: the interpreter version, then
+// `name<TAB>dist`, `name<TAB>extra<TAB>extra` and `name<TAB>requires<TAB>req`
+// lines. Its WHEEL lines are for wheels.rs.
+pub fn parse(stdout: &str) -> Extras {
+    let mut lines = stdout.lines();
+    let python = lines.next().and_then(parse_version).unwrap_or((3, 0));
+
+    let mut installed = HashSet::new();
+    let mut declared: Vec<(String, String)> = Vec::new();
+    let mut requires: Vec<(String, String)> = Vec::new();
+    for line in lines {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(name), Some(kind)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let name = names::pip(name);
+        match (kind, fields.next()) {
+            ("dist", _) => {
+                installed.insert(name);
+            }
+            ("extra", Some(extra)) => declared.push((name, names::pip(extra))),
+            ("requires", Some(req)) => requires.push((name, req.to_string())),
+            _ => {}
+        }
+    }
+
+    let mut gated: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for (name, req) in &requires {
+        let (spec, marker) = req.split_once(';').unwrap_or((req, ""));
+        let Some(dep) = spec
+            .trim()
+            .split(|c: char| !(c.is_ascii_alphanumeric() || "._-".contains(c)))
+            .next()
+            .filter(|dep| !dep.is_empty())
+        else {
+            continue;
+        };
+        for extra in extras_needing(marker, python) {
+            declared.push((name.clone(), extra.clone()));
+            let missing = gated.entry((name.clone(), extra)).or_default();
+            let dep = names::pip(dep);
+            if !installed.contains(&dep) && !missing.contains(&dep) {
+                missing.push(dep);
+            }
+        }
+    }
+
+    let mut by_package: HashMap<String, Vec<Extra>> = HashMap::new();
+    declared.sort();
+    declared.dedup();
+    for (name, extra) in declared {
+        let missing = gated
+            .remove(&(name.clone(), extra.clone()))
+            .unwrap_or_default();
+        by_package.entry(name).or_default().push(Extra {
+            name: extra,
+            missing,
+        });
+    }
+    Extras { by_package }
+}
+
+// Extras under which a requirement applies, e.g. `extra == "socks"`.
+// Alternatives are split on `or`, clauses on `and`; besides `extra`, only
+// python_version and the platform of Termux (sys_platform, platform_system,
+// os_name) are evaluated, and any other clause is taken as true.
+fn extras_needing(marker: &str, python: (u32, u32)) -> Vec<String> {
+    let mut extras = Vec::new();
+    for alternative in marker.split(" or ") {
+        let mut extra = None;
+        let mut applies = true;
+        for clause in alternative.split(" and ") {
+            let clause = clause.trim().trim_matches(|c| c == '(' || c == ')').trim();
+            let Some((variable, op, value)) = parse_clause(clause) else {
+                continue;
+            };
+            match variable {
+                "extra" if op == "==" => extra = Some(names::pip(value)),
+                "python_version" => {
+                    applies &= parse_version(value).is_none_or(|v| compare(python, op, v))
+                }
+                "sys_platform" => applies &= compare_str("linux", op, value),
+                "platform_system" => applies &= compare_str("Linux", op, value),
+                "os_name" => applies &= compare_str("posix", op, value),
+                _ => {}
+            }
+        }
+        if let (Some(extra), true) = (extra, applies) {
+            extras.push(extra);
+        }
+    }
+    extras
+}
+
+// `variable op "value"`, quotes single or double.
+fn parse_clause(clause: &str) -> Option<(&str, &str, &str)> {
+    let ops = ["==", "!=", "<=", ">=", "<", ">"];
+    let (at, op) = ops
+        .iter()
+        .filter_map(|op| clause.find(op).map(|at| (at, *op)))
+        .min_by_key(|(at, op)| (*at, std::cmp::Reverse(op.len())))?;
+    let variable = clause[..at].trim();
+    let value = clause[at + op.len()..]
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'');
+    Some((variable, op, value))
+}
+
+fn parse_version(text: &str) -> Option<(u32, u32)> {
+    let mut parts = text.trim().split('.');
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+fn compare(left: (u32, u32), op: &str, right: (u32, u32)) -> bool {
+    match op {
+        "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        "<=" => left <= right,
+        ">" => left > right,
+        ">=" => left >= right,
+        _ => true,
+    }
+}
+
+fn compare_str(left: &str, op: &str, right: &str) -> bool {
+    match op {
+        "==" => left == right,
+        "!=" => left != right,
+        _ => true,
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io;
+use std::process::Output;
+
+use ratatui::{
+    prelude::*,
+    widgets::{block, Block, Borders, Paragraph, Wrap},
+};
+
+use crate::commands::{self, Action};
+use crate::doctor;
+use crate::{prefix, PackageManager};
+
+// Why a manager's packages couldn't be listed.
+#[derive(Debug, Clone)]
+pub enum Failure {
+    NotInstalled {
+        program: String,
+    },
+    // Found, but not ours to run
+    Denied {
+        program: String,
+    },
+    Spawn(String), // Any other reason it didn't start
+    Exited {
+        command: String,
+        code: Option<i32>,      // None when killed by a signal
+        stderr: Option<String>, // Its first non-empty line
+    },
+    Unparsable(String), // Succeeded with output we don't understand
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    pub manager: PackageManager,
+    pub failure: Failure,
+}
+
+impl LoadError {
+    pub fn new(manager: PackageManager, failure: Failure) -> Self {
+        Self { manager, failure }
+    }
+
+    // The list command couldn't be started at all, most often because the
+    // manager isn't installed.
+    pub fn from_io(manager: PackageManager, err: &io::Error) -> Self {
+        let program = list_command(manager)
+            .and_then(|words| words.into_iter().next())
+            .unwrap_or_default();
+        let failure = match err.kind() {
+            io::ErrorKind::NotFound => Failure::NotInstalled { program },
+            io::ErrorKind::PermissionDenied => Failure::Denied { program },
+            _ => Failure::Spawn(err.to_string()),
+        };
+        Self::new(manager, failure)
+    }
+
+    // The list command ran and failed without printing packages.
+    pub fn from_output(manager: PackageManager, output: &Output) -> Self {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let failure = Failure::Exited {
+            command: list_command(manager)
+                .map(|words| commands::join_words(&words))
+                .unwrap_or_default(),
+            code: output.status.code(),
+            stderr: stderr
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(String::from),
+        };
+        Self::new(manager, failure)
+    }
+
+    // What to try about it.
+    pub fn next_step(&self) -> String {
+        let name = self.manager.name();
+        match &self.failure {
+            Failure::NotInstalled { .. } => match self.manager {
+                PackageManager::Pkg | PackageManager::Apt => format!(
+                    "{} comes with Termux: check that $PREFIX ({}) is Termux's and its bin directory is on PATH",
+                    name,
+                    prefix()
+                ),
+                PackageManager::Pip => {
+                    "pkg install python, or leave pip out of $TPI_MANAGERS".to_string()
+                }
+                PackageManager::Cargo => {
+                    "pkg install rust, or leave cargo out of $TPI_MANAGERS".to_string()
+                }
+                PackageManager::Npm => {
+                    "pkg install nodejs, or leave npm out of $TPI_MANAGERS".to_string()
+                }
+                _ => format!(
+                    "pkg install {}, or leave it out of $TPI_MANAGERS",
+                    name
+                ),
+            },
+            Failure::Denied { program } => {
+                format!("check the permissions of {} and of $PREFIX", program)
+            }
+            Failure::Spawn(_) => "check $PREFIX and PATH; d shows the doctor report".to_string(),
+            Failure::Exited { command, .. } => match self.manager {
+                PackageManager::Pkg | PackageManager::Apt => {
+                    "run pkg update, then r to try again".to_string()
+                }
+                _ => format!("run {} yourself to see the whole error", command),
+            },
+            Failure::Unparsable(_) => {
+                "press r to try again; if it persists, report it with tpi doctor's output"
+                    .to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.manager.name();
+        match &self.failure {
+            Failure::NotInstalled { program } => {
+                write!(
+                    f,
+                    "{} is not installed ({} not found on PATH)",
+                    name, program
+                )
+            }
+            Failure::Denied { program } => {
+                write!(
+                    f,
+                    "Permission denied running {} to list {} packages",
+                    program, name
+                )
+            }
+            Failure::Spawn(reason) => {
+                write!(f, "Failed to run the {} list command: {}", name, reason)
+            }
+            Failure::Exited { code, stderr, .. } => {
+                write!(f, "Failed to list {} packages", name)?;
+                if let Some(code) = code {
+                    write!(f, " (exit {})", code)?;
+                }
+                write!(f, ": {}", stderr.as_deref().unwrap_or("command failed"))
+            }
+            Failure::Unparsable(reason) => f.write_str(reason),
+        }
+    }
+}
+
+fn list_command(manager: PackageManager) -> Option<Vec<String>> {
+    commands::resolve(manager, Action::List, "")
+}
+
+// Shown instead of the package list when no manager could be listed: each
+// one tried with what went wrong and what to do about it, or the doctor
+// report.
+pub struct Failures {
+    pub errors: Vec<LoadError>,
+    pub doctor: Option<Vec<doctor::Item>>, // While the doctor report is shown
+    pub scroll: u16,
+}
+
+impl Failures {
+    pub fn new(errors: Vec<LoadError>) -> Self {
+        Self {
+            errors,
+            doctor: None,
+            scroll: 0,
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines = Vec::new();
+        let (title, keys) = match &self.doctor {
+            None => {
+                lines.push(Line::from(
+                    "tpi couldn't list packages from any package manager it tried:",
+                ));
+                for err in &self.errors {
+                    lines.push(Line::default());
+                    lines.push(Line::from(vec![
+                        Span::styled(
+                            err.manager.name(),
+                            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(format!("  {}", err)),
+                    ]));
+                    lines.push(Line::styled(format!("  → {}", err.next_step()), dim));
+                }
+                (
+                    "Nothing could be loaded",
+                    " r retry · d doctor report · q quit ",
+                )
+            }
+            Some(items) => {
+                for item in items {
+                    let color = match item.level {
+                        doctor::Level::Ok => Color::Green,
+                        doctor::Level::Warn => Color::Yellow,
+                        doctor::Level::Fail => Color::Red,
+                    };
+                    lines.push(Line::from(vec![
+                        Span::styled(
+                            format!("{:<5}", item.level.name()),
+                            Style::default().fg(color),
+                        ),
+                        Span::styled(format!("{} · ", item.section), dim),
+                        Span::raw(format!("{}: {}", item.name, item.detail)),
+                    ]));
+                }
+                ("Doctor report", " d or Esc back · r retry · q quit ")
+            }
+        };
+        let block = Block::default()
+            .title(title)
+            .title(block::Title::from(keys).position(block::Position::Bottom))
+            .borders(Borders::ALL);
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        f.render_widget(paragraph, area);
+    }
+}
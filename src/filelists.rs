@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::commands::{self, Action, Runner};
+use crate::{prefix, PackageManager};
+
+// Installed files by package name, as absolute paths.
+pub type Lists = HashMap<String, Vec<String>>;
+
+// Modification times of a package database, to tell when a read is stale.
+type Stamp = Vec<(PathBuf, Option<SystemTime>)>;
+
+// The last read of each database and what it saw; any install, upgrade or
+// removal touches one of the stamped paths.
+static DPKG: Mutex<Option<(Stamp, Arc<Lists>)>> = Mutex::new(None);
+static PIP: Mutex<Option<(Stamp, Arc<Lists>)>> = Mutex::new(None);
+
+// Every file list of the manager's database: dpkg's for pkg and apt, pip's
+// RECORDs for pip. None for managers without file lists.
+pub fn load(
+    manager: PackageManager,
+    runner: &dyn Runner,
+    cancel: &AtomicBool,
+) -> Option<Result<Arc<Lists>, String>> {
+    match manager {
+        PackageManager::Pkg | PackageManager::Apt => Some(dpkg(cancel)),
+        PackageManager::Pip => Some(pip(runner)),
+        _ => None,
+    }
+}
+
+// `$PREFIX/var/lib/dpkg/info/<package>.list`, one path per line. Multi-arch
+// packages have a list per architecture (`libfoo:arm.list`) whose files are
+// the same package's.
+pub fn dpkg(cancel: &AtomicBool) -> Result<Arc<Lists>, String> {
+    let root = PathBuf::from(prefix());
+    let stamp = stamp(vec![
+        root.join("var/lib/dpkg/status"),
+        root.join("var/lib/dpkg/info"),
+    ]);
+    if let Some(lists) = cached(&DPKG, &stamp) {
+        return Ok(lists);
+    }
+
+    let info = root.join("var/lib/dpkg/info");
+    let entries = fs::read_dir(&info).map_err(|err| format!("{}: {}", info.display(), err))?;
+    let mut lists = Lists::new();
+    for entry in entries.flatten() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        let file_name = entry.file_name();
+        let Some(stem) = file_name.to_str().and_then(|n| n.strip_suffix(".list")) else {
+            continue;
+        };
+        let name = stem.split(':').next().unwrap_or(stem);
+        let Ok(text) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        lists.entry(name.to_string()).or_default().extend(
+            text.lines()
+                .filter(|line| !line.is_empty() && *line != "/.")
+                .map(String::from),
+        );
+    }
+    for files in lists.values_mut() {
+        files.sort();
+        files.dedup();
+    }
+    Ok(store(&DPKG, stamp, lists))
+}
+
+// `name<TAB>path` for every file of every installed distribution.
+pub fn pip(runner: &dyn Runner) -> Result<Arc<Lists>, String> {
+    let stamp = stamp(site_packages());
+    if let Some(lists) = cached(&PIP, &stamp) {
+        return Ok(lists);
+    }
+
+    let output = commands::run_with(runner, PackageManager::Pip, Action::Records, "")
+        .map_err(|err| format!("python: {}", err))?;
+    if !output.status.success() {
+        return Err("listing pip RECORDs failed".to_string());
+    }
+    let mut lists = Lists::new();
+    for (name, path) in String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+    {
+        lists
+            .entry(name.to_string())
+            .or_default()
+            .push(path.to_string());
+    }
+    Ok(store(&PIP, stamp, lists))
+}
+
+fn cached(cache: &Mutex<Option<(Stamp, Arc<Lists>)>>, stamp: &Stamp) -> Option<Arc<Lists>> {
+    let cache = cache.lock().ok()?;
+    let (seen, lists) = cache.as_ref()?;
+    (seen == stamp).then(|| lists.clone())
+}
+
+fn store(cache: &Mutex<Option<(Stamp, Arc<Lists>)>>, stamp: Stamp, lists: Lists) -> Arc<Lists> {
+    let lists = Arc::new(lists);
+    if let Ok(mut cache) = cache.lock() {
+        *cache = Some((stamp, lists.clone()));
+    }
+    lists
+}
+
+fn site_packages() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(Path::new(&prefix()).join("lib")) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("python"))
+        .map(|entry| entry.path().join("site-packages"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn stamp(paths: Vec<PathBuf>) -> Stamp {
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+            (path, modified)
+        })
+        .collect()
+}
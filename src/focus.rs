@@ -0,0 +1,88 @@
+use crate::i18n;
+
+// Where keys go. Worked out from what is open, in one place, so the key
+// dispatcher and the status bar can't disagree; the first thing open in the
+// order below takes every key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Focus {
+    Failures, // No manager could be listed
+    Tour,
+    Help,
+    Search, // Typing the query; once it is kept the list has the keys again
+    OwnerPrompt,
+    InstallPrompt,
+    InventoryPrompt,
+    MarkPrompt,
+    FilePrompt,
+    Confirm,
+    QuitMenu,
+    ActionMenu,
+    GroupMenu,
+    SwitchMenu,
+    PriorityMenu,
+    SectionMenu,
+    DriftMenu,
+    DepTree,
+    Rdepends,
+    Health,
+    Holds,
+    Repos,
+    Sessions,
+    Export,
+    Install,
+    Impact,
+    Upgrades,
+    Stats,
+    Growth,
+    Inspect,
+    Usage,
+    Rollback, // `<` with nothing cached to go back to
+    Python,
+    Diagnostics,
+    DetailsPage, // The details on a screen of their own (short screens)
+    List,
+}
+
+// The coarse kind of focus, for the status bar.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    List,
+    Detail,
+    Search, // Text goes into a field: the search and the prompts
+    Dialog,
+    Op, // Checks or an export running in the background, with their view
+}
+
+impl Focus {
+    pub fn mode(self) -> Mode {
+        match self {
+            Self::List => Mode::List,
+            Self::DetailsPage | Self::DepTree | Self::Rdepends => Mode::Detail,
+            Self::Search
+            | Self::OwnerPrompt
+            | Self::InstallPrompt
+            | Self::InventoryPrompt
+            | Self::MarkPrompt
+            | Self::FilePrompt => Mode::Search,
+            Self::Health | Self::Export => Mode::Op,
+            _ => Mode::Dialog,
+        }
+    }
+
+    // Whether the main-list keys apply: nothing open over the list.
+    pub fn on_list(self) -> bool {
+        matches!(self, Self::List | Self::DetailsPage)
+    }
+}
+
+impl Mode {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::List => i18n::text("status.list"),
+            Self::Detail => i18n::text("status.detail"),
+            Self::Search => i18n::text("status.search"),
+            Self::Dialog => i18n::text("status.dialog"),
+            Self::Op => i18n::text("status.op"),
+        }
+    }
+}
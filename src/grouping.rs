@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{i18n, sections, Package, PackageManager};
+
+// dpkg priorities, from the baseline every system has down to the rest.
+pub const PRIORITIES: [&str; 5] = ["required", "important", "standard", "optional", "extra"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    None,
+    Repo,
+    FirstLetter,
+    Priority,
+    Section,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 5] = [
+        GroupBy::None,
+        GroupBy::Repo,
+        GroupBy::FirstLetter,
+        GroupBy::Priority,
+        GroupBy::Section,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupBy::None => i18n::text("group.none"),
+            GroupBy::Repo => i18n::text("group.repo"),
+            GroupBy::FirstLetter => i18n::text("group.first_letter"),
+            GroupBy::Priority => i18n::text("group.priority"),
+            GroupBy::Section => i18n::text("group.section"),
+        }
+    }
+
+    // Stable identifier for saved sessions.
+    pub fn id(&self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Repo => "repo",
+            GroupBy::FirstLetter => "first-letter",
+            GroupBy::Priority => "priority",
+            GroupBy::Section => "section",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        GroupBy::ALL
+            .into_iter()
+            .find(|group_by| group_by.id() == id)
+    }
+
+    pub fn applies_to(&self, manager: PackageManager) -> bool {
+        match self {
+            GroupBy::Repo => manager != PackageManager::Pip,
+            GroupBy::Priority | GroupBy::Section => {
+                matches!(manager, PackageManager::Pkg | PackageManager::Apt)
+            }
+            GroupBy::None | GroupBy::FirstLetter => true,
+        }
+    }
+
+    fn key(&self, pkg: &Package) -> String {
+        match self {
+            GroupBy::None => String::new(),
+            GroupBy::Repo => pkg.repo.clone().unwrap_or_else(|| "(unknown)".to_string()),
+            GroupBy::FirstLetter => match pkg.name.chars().next() {
+                Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string(),
+                Some(_) => "#".to_string(),
+                None => "(unnamed)".to_string(),
+            },
+            GroupBy::Priority => pkg
+                .priority
+                .clone()
+                .unwrap_or_else(|| "(unknown)".to_string()),
+            GroupBy::Section => sections::title(sections::of(pkg)),
+        }
+    }
+
+    // Groups are sorted by name, except priorities, which go from required
+    // down to extra, and packages without a section, which come last.
+    fn rank(&self, key: &str) -> usize {
+        match self {
+            GroupBy::Priority => PRIORITIES
+                .iter()
+                .position(|priority| *priority == key)
+                .unwrap_or(PRIORITIES.len()),
+            GroupBy::Section => usize::from(key == sections::title(sections::UNSECTIONED)),
+            GroupBy::None | GroupBy::Repo | GroupBy::FirstLetter => 0,
+        }
+    }
+}
+
+// One line of the package list: either a group header or an index into the
+// package vector.
+pub enum Row {
+    Header {
+        name: String,
+        count: usize,
+        collapsed: bool,
+    },
+    Package(usize),
+    Missing(usize), // In the manifest but not installed, an index into Drift::missing
+}
+
+// Rows for the given packages (indices into `items`). Groups are sorted (see
+// GroupBy::rank); packages keep their order within a group.
+pub fn build_rows(
+    items: &[Package],
+    visible: impl Iterator<Item = usize>,
+    group_by: GroupBy,
+    collapsed: &HashSet<String>,
+) -> Vec<Row> {
+    if group_by == GroupBy::None {
+        return visible.map(Row::Package).collect();
+    }
+
+    let mut groups: BTreeMap<(usize, String), Vec<usize>> = BTreeMap::new();
+    for i in visible {
+        let key = group_by.key(&items[i]);
+        groups
+            .entry((group_by.rank(&key), key))
+            .or_default()
+            .push(i);
+    }
+
+    let mut rows = Vec::new();
+    for ((_, name), members) in groups {
+        let is_collapsed = collapsed.contains(&name);
+        rows.push(Row::Header {
+            count: members.len(),
+            collapsed: is_collapsed,
+            name,
+        });
+        if !is_collapsed {
+            rows.extend(members.into_iter().map(Row::Package));
+        }
+    }
+    rows
+}
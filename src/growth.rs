@@ -0,0 +1,353 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ratatui::{
+    prelude::*,
+    symbols,
+    widgets::{
+        Axis, Block, Borders, Chart, Clear, Dataset, GraphType, List, ListItem, ListState,
+        Paragraph,
+    },
+};
+
+use crate::history::{self, Change};
+use crate::{format_date, format_size, format_time, PackageManager};
+
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
+// One manager's packages as the version history saw them.
+struct Series {
+    manager: PackageManager,
+    history: HashMap<String, Vec<Change>>,
+}
+
+impl Series {
+    // Installed size of a package at a snapshot: Some(0) when it wasn't
+    // installed, None when it was but no size had been recorded yet.
+    fn size_at(changes: &[Change], time: u64) -> Option<u64> {
+        match changes.iter().rev().find(|change| change.time <= time) {
+            None => Some(0),
+            Some(change) if change.version.is_none() => Some(0),
+            Some(change) => change.size,
+        }
+    }
+
+    // Total installed size at a snapshot. Packages the manager never reported
+    // a size for don't count; any other package without one makes the total
+    // unknown, and so do snapshots from before sizes were recorded at all.
+    fn total_at(&self, time: u64) -> Option<u64> {
+        let first_size = self
+            .history
+            .values()
+            .flatten()
+            .filter(|change| change.size.is_some())
+            .map(|change| change.time)
+            .min()?;
+        if time < first_size {
+            return None;
+        }
+        let mut total = 0;
+        for changes in self.history.values() {
+            if changes.iter().all(|change| change.size.is_none()) {
+                continue;
+            }
+            total += Self::size_at(changes, time)?;
+        }
+        Some(total)
+    }
+
+    fn has_sizes(&self) -> bool {
+        self.history
+            .values()
+            .flatten()
+            .any(|change| change.size.is_some())
+    }
+}
+
+// A package whose installed size changed between two snapshots.
+struct Delta {
+    manager: PackageManager,
+    name: String,
+    kib: i64,
+}
+
+// "Disk usage over time": the total installed size per manager at every
+// snapshot of the version history, and which packages grew or shrank between
+// two of them.
+pub struct GrowthView {
+    series: Vec<Series>,
+    times: Vec<u64>,               // Every snapshot, oldest first
+    totals: Vec<Vec<Option<u64>>>, // Per series, per snapshot
+    from: usize,                   // The snapshot compared against
+    pub state: ListState,          // The snapshot compared, `to`
+    deltas: Vec<Delta>,            // Largest growth first
+    unknown: usize,                // Packages without sizes at either end
+}
+
+impl GrowthView {
+    pub fn load(managers: &[PackageManager]) -> Self {
+        let series: Vec<Series> = managers
+            .iter()
+            .map(|&manager| Series {
+                manager,
+                history: history::load(manager),
+            })
+            .filter(|series| !series.history.is_empty())
+            .collect();
+        let times: Vec<u64> = series
+            .iter()
+            .flat_map(|series| series.history.values().flatten().map(|change| change.time))
+            .collect::<BTreeSet<u64>>()
+            .into_iter()
+            .collect();
+        let totals = series
+            .iter()
+            .map(|series| times.iter().map(|&time| series.total_at(time)).collect())
+            .collect();
+
+        let mut view = Self {
+            series,
+            times,
+            totals,
+            from: 0,
+            state: ListState::default(),
+            deltas: Vec::new(),
+            unknown: 0,
+        };
+        if !view.times.is_empty() {
+            // From the first snapshot with any size to the latest one
+            view.from = (0..view.times.len())
+                .find(|&i| view.totals.iter().any(|totals| totals[i].is_some()))
+                .unwrap_or(0);
+            view.state.select(Some(view.times.len() - 1));
+            view.compare();
+        }
+        view
+    }
+
+    fn to(&self) -> usize {
+        self.state.selected().unwrap_or(0)
+    }
+
+    fn compare(&mut self) {
+        let (from, to) = (self.times[self.from], self.times[self.to()]);
+        self.deltas.clear();
+        self.unknown = 0;
+        for series in &self.series {
+            for (name, changes) in &series.history {
+                match (Series::size_at(changes, from), Series::size_at(changes, to)) {
+                    (Some(before), Some(after)) if before != after => self.deltas.push(Delta {
+                        manager: series.manager,
+                        name: name.clone(),
+                        kib: after as i64 - before as i64,
+                    }),
+                    (Some(_), Some(_)) => {}
+                    _ => self.unknown += 1,
+                }
+            }
+        }
+        self.deltas
+            .sort_by(|a, b| b.kib.cmp(&a.kib).then_with(|| a.name.cmp(&b.name)));
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1).min(self.times.len() - 1)));
+            self.compare();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+            self.compare();
+        }
+    }
+
+    // Compares against the selected snapshot from now on.
+    pub fn set_from(&mut self) {
+        if self.state.selected().is_some() {
+            self.from = self.to();
+            self.compare();
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title("Disk usage over time — j/k pick snapshot, Space compare from it, Esc close")
+            .borders(Borders::ALL);
+        f.render_widget(Clear, area);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        if self.times.is_empty() {
+            f.render_widget(
+                Paragraph::new(
+                    "No snapshots yet: they are taken whenever a package list is loaded",
+                ),
+                inner,
+            );
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(inner);
+        self.render_chart(f, layout[0]);
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(layout[1]);
+        self.render_snapshots(f, bottom[0]);
+        self.render_deltas(f, bottom[1]);
+    }
+
+    fn render_chart(&self, f: &mut Frame, area: Rect) {
+        // Snapshots without a known total are left out rather than drawn as 0
+        let points: Vec<Vec<(f64, f64)>> = self
+            .totals
+            .iter()
+            .map(|totals| {
+                self.times
+                    .iter()
+                    .zip(totals)
+                    .filter_map(|(&time, total)| Some((time as f64, (*total)? as f64)))
+                    .collect()
+            })
+            .collect();
+        let mut datasets = Vec::new();
+        let mut missing = Vec::new();
+        for (i, (series, points)) in self.series.iter().zip(&points).enumerate() {
+            if points.is_empty() || !series.has_sizes() {
+                missing.push(series.manager.name());
+                continue;
+            }
+            let graph_type = if points.len() == 1 {
+                GraphType::Scatter
+            } else {
+                GraphType::Line
+            };
+            datasets.push(
+                Dataset::default()
+                    .name(series.manager.name())
+                    .marker(symbols::Marker::Braille)
+                    .graph_type(graph_type)
+                    .style(Style::default().fg(COLORS[i % COLORS.len()]))
+                    .data(points),
+            );
+        }
+
+        let first = self.times[0];
+        let last = self.times[self.times.len() - 1].max(first + 1);
+        let max = points
+            .iter()
+            .flatten()
+            .map(|(_, kib)| *kib)
+            .fold(0.0, f64::max)
+            .max(1.0)
+            * 1.1;
+        let title = if missing.is_empty() {
+            "Installed size".to_string()
+        } else {
+            format!("Installed size (no size data: {})", missing.join(", "))
+        };
+        let chart = Chart::new(datasets)
+            .block(Block::default().title(title))
+            .x_axis(
+                Axis::default()
+                    .bounds([first as f64, last as f64])
+                    .labels(vec![
+                        Span::raw(format_date(first)),
+                        Span::raw(format_date(last)),
+                    ]),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, max])
+                    .labels(vec![Span::raw("0"), Span::raw(format_size(max as u64))]),
+            );
+        f.render_widget(chart, area);
+    }
+
+    fn render_snapshots(&mut self, f: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .times
+            .iter()
+            .enumerate()
+            .map(|(i, &time)| {
+                let mark = if i == self.from { "from " } else { "     " };
+                let mut spans = vec![Span::raw(format!(
+                    "{}{} {}",
+                    mark,
+                    format_date(time),
+                    &format_time(time)[..5]
+                ))];
+                for (j, (series, totals)) in self.series.iter().zip(&self.totals).enumerate() {
+                    if !series.has_sizes() {
+                        continue;
+                    }
+                    let total = totals[i].map_or("?".to_string(), format_size);
+                    spans.push(Span::styled(
+                        format!("  {} {}", series.manager.name(), total),
+                        Style::default().fg(COLORS[j % COLORS.len()]),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().title("Snapshots (UTC)"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+
+    fn render_deltas(&self, f: &mut Frame, area: Rect) {
+        let (from, to) = (self.times[self.from], self.times[self.to()]);
+        let growth: i64 = self.deltas.iter().map(|delta| delta.kib).sum();
+        let mut title = format!(
+            "{} → {}: {}{}",
+            format_date(from),
+            format_date(to),
+            if growth < 0 { "-" } else { "+" },
+            format_size(growth.unsigned_abs())
+        );
+        if self.unknown > 0 {
+            title.push_str(&format!(", {} without size data", self.unknown));
+        }
+        let width = self
+            .deltas
+            .iter()
+            .map(|delta| delta.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .deltas
+            .iter()
+            .map(|delta| {
+                let (sign, color) = if delta.kib < 0 {
+                    ("-", Color::Green)
+                } else {
+                    ("+", Color::Red)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<width$} ", delta.name, width = width)),
+                    Span::styled(
+                        format!("{}{}", sign, format_size(delta.kib.unsigned_abs())),
+                        Style::default().fg(color),
+                    ),
+                    Span::styled(
+                        format!(" ({})", delta.manager.name()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        f.render_widget(List::new(items).block(Block::default().title(title)), area);
+    }
+}
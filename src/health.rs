@@ -0,0 +1,346 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::commands::{self, Action, Runner};
+use crate::conflicts;
+use crate::shadowing;
+use crate::strays;
+use crate::{parse_package_list, prefix, Package, PackageManager};
+
+// Packages built for another architecture than the system's. On Termux these
+// are almost always leftovers from restoring a backup made on another device.
+// `all` packages are architecture independent and never foreign.
+pub fn foreign_architecture<'a>(packages: &'a [Package], primary: &str) -> Vec<&'a Package> {
+    packages
+        .iter()
+        .filter(|pkg| is_foreign_arch(pkg, primary))
+        .collect()
+}
+
+pub fn is_foreign_arch(pkg: &Package, primary: &str) -> bool {
+    match pkg.arch.as_deref() {
+        Some(arch) => arch != "all" && arch != primary,
+        None => false,
+    }
+}
+
+// Everything the health dashboard and `tpi health` look at. All checks only
+// query the package databases, so any number of them can run at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Check {
+    BrokenState,
+    ResidualConfig,
+    ForeignArch,
+    Obsolete,
+    Orphans,
+    MissingLibraries,
+    NativeExtensions,
+    FileConflicts,
+    StrayFiles,
+    ShadowedCommands,
+}
+
+// One affected package. `detail` says what is wrong with it.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub manager: PackageManager,
+    pub package: String,
+    pub detail: String,
+    pub other: Option<(PackageManager, String)>, // A second package involved, e.g. in a file conflict
+}
+
+pub type Outcome = Result<Vec<Finding>, String>;
+
+impl Check {
+    pub const ALL: [Check; 10] = [
+        Check::BrokenState,
+        Check::ResidualConfig,
+        Check::ForeignArch,
+        Check::Obsolete,
+        Check::Orphans,
+        Check::MissingLibraries,
+        Check::NativeExtensions,
+        Check::FileConflicts,
+        Check::StrayFiles,
+        Check::ShadowedCommands,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Check::BrokenState => "Broken dpkg state",
+            Check::ResidualConfig => "Residual configuration",
+            Check::ForeignArch => "Foreign architecture",
+            Check::Obsolete => "Obsolete or local packages",
+            Check::Orphans => "Orphaned dependencies",
+            Check::MissingLibraries => "Missing shared libraries",
+            Check::NativeExtensions => "Broken Python native extensions",
+            Check::FileConflicts => "Files shipped by two packages",
+            Check::StrayFiles => "Files outside the usual places",
+            Check::ShadowedCommands => "Commands shadowed on PATH",
+        }
+    }
+
+    // Stable identifier for `tpi health --json`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Check::BrokenState => "broken-state",
+            Check::ResidualConfig => "residual-config",
+            Check::ForeignArch => "foreign-arch",
+            Check::Obsolete => "obsolete",
+            Check::Orphans => "orphans",
+            Check::MissingLibraries => "missing-libraries",
+            Check::NativeExtensions => "native-extensions",
+            Check::FileConflicts => "file-conflicts",
+            Check::StrayFiles => "stray-files",
+            Check::ShadowedCommands => "shadowed-commands",
+        }
+    }
+
+    // Checks that inspect every binary, extension or file list; the dashboard
+    // only runs them on request.
+    pub fn is_expensive(&self) -> bool {
+        matches!(
+            self,
+            Check::MissingLibraries
+                | Check::NativeExtensions
+                | Check::FileConflicts
+                | Check::StrayFiles
+                | Check::ShadowedCommands
+        )
+    }
+
+    // `cancel` is polled between steps of the long-running checks; a cancelled
+    // check returns an error that callers are expected to discard.
+    pub fn run(&self, runner: &dyn Runner, cancel: &AtomicBool) -> Outcome {
+        match self {
+            Check::BrokenState => dpkg_status(runner, |status| match status.as_bytes() {
+                [_, b'c', b' '] | [_, b'i', b' '] | [_, b'n', b' '] => None,
+                [_, state, flag] => Some(describe_status(*state, *flag)),
+                _ => None,
+            }),
+            Check::ResidualConfig => dpkg_status(runner, |status| {
+                (status.as_bytes().get(1) == Some(&b'c'))
+                    .then(|| "removed, configuration files left".to_string())
+            }),
+            Check::ForeignArch => {
+                let primary = stdout(runner, PackageManager::Pkg, Action::Arch, "")?;
+                let primary = primary.trim();
+                Ok(installed(runner)?
+                    .iter()
+                    .filter(|pkg| is_foreign_arch(pkg, primary))
+                    .map(|pkg| Finding {
+                        manager: PackageManager::Apt,
+                        package: pkg.name.clone(),
+                        detail: format!(
+                            "built for {}, system is {}",
+                            pkg.arch.as_deref().unwrap_or("?"),
+                            primary
+                        ),
+                        other: None,
+                    })
+                    .collect())
+            }
+            // `apt list` shows only the pseudo-suite "now" for packages no
+            // configured repository provides
+            Check::Obsolete => Ok(installed(runner)?
+                .into_iter()
+                .filter(|pkg| pkg.repo.is_none())
+                .map(|pkg| Finding {
+                    manager: PackageManager::Apt,
+                    package: pkg.name,
+                    detail: "not available from any configured repository".to_string(),
+                    other: None,
+                })
+                .collect()),
+            // Inst/Remv lines of the simulation: "Remv libfoo [1.2]"
+            Check::Orphans => Ok(stdout(runner, PackageManager::Pkg, Action::Autoremove, "")?
+                .lines()
+                .filter_map(|line| line.strip_prefix("Remv "))
+                .filter_map(|rest| rest.split_whitespace().next())
+                .map(|name| Finding {
+                    manager: PackageManager::Apt,
+                    package: name.to_string(),
+                    detail: "automatically installed, nothing depends on it".to_string(),
+                    other: None,
+                })
+                .collect()),
+            Check::MissingLibraries => missing_libraries(runner, cancel),
+            Check::FileConflicts => conflicts::find(runner, cancel),
+            Check::StrayFiles => strays::find(runner, cancel),
+            Check::ShadowedCommands => shadowing::find(runner, cancel),
+            Check::NativeExtensions => {
+                let output =
+                    commands::run_with(runner, PackageManager::Pip, Action::ImportCheck, "")
+                        .map_err(|err| format!("python: {}", err))?;
+                if !output.status.success() {
+                    return Err(first_line(&output.stderr, "import check failed"));
+                }
+                Ok(String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .filter_map(|line| line.split_once('\t'))
+                    .map(|(name, error)| Finding {
+                        manager: PackageManager::Pip,
+                        package: name.to_string(),
+                        detail: error.to_string(),
+                        other: None,
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+// Runs the given checks side by side and returns their outcomes in order.
+pub fn run_all(runner: Arc<dyn Runner>, checks: &[Check]) -> Vec<(Check, Outcome)> {
+    let cancel = AtomicBool::new(false);
+    thread::scope(|scope| {
+        let handles: Vec<_> = checks
+            .iter()
+            .map(|check| {
+                let runner = runner.clone();
+                let cancel = &cancel;
+                scope.spawn(move || check.run(runner.as_ref(), cancel))
+            })
+            .collect();
+        checks
+            .iter()
+            .zip(handles)
+            .map(|(check, handle)| {
+                let outcome = handle
+                    .join()
+                    .unwrap_or_else(|_| Err("check panicked".to_string()));
+                (*check, outcome)
+            })
+            .collect()
+    })
+}
+
+fn stdout(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+    name: &str,
+) -> Result<String, String> {
+    let output =
+        commands::run_with(runner, manager, action, name).map_err(|err| err.to_string())?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(first_line(&output.stderr, "command failed"))
+    }
+}
+
+fn first_line(bytes: &[u8], fallback: &str) -> String {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or(fallback)
+        .to_string()
+}
+
+// Listed through apt, which is there wherever dpkg is (pkg is a Termux-only
+// wrapper around it); findings from dpkg point at the apt list for the same reason.
+fn installed(runner: &dyn Runner) -> Result<Vec<Package>, String> {
+    let text = stdout(runner, PackageManager::Apt, Action::List, "")?;
+    parse_package_list(PackageManager::Apt, &text).map(|listing| listing.packages)
+}
+
+// `dpkg-query` lines are `name<TAB>XYZ` with the desired action, the package
+// state and an error flag; `judge` turns a status into a problem description.
+fn dpkg_status(runner: &dyn Runner, judge: impl Fn(&str) -> Option<String>) -> Outcome {
+    Ok(stdout(runner, PackageManager::Pkg, Action::Status, "")?
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(name, status)| {
+            judge(&format!("{:<3}", status)).map(|detail| Finding {
+                manager: PackageManager::Apt,
+                package: name.to_string(),
+                detail,
+                other: None,
+            })
+        })
+        .collect())
+}
+
+fn describe_status(state: u8, flag: u8) -> String {
+    let state = match state {
+        b'i' => "installed",
+        b'c' => "configuration files only",
+        b'n' => "not installed",
+        b'H' => "half-installed",
+        b'U' => "unpacked but not configured",
+        b'F' => "half-configured",
+        b'W' => "awaiting triggers",
+        b't' => "triggers pending",
+        _ => "in an unknown state",
+    };
+    if flag == b'R' {
+        format!("{}, reinstall required", state)
+    } else {
+        state.to_string()
+    }
+}
+
+// Every ELF executable in $PREFIX/bin whose libraries `ldd` cannot resolve,
+// attributed to the package that owns it.
+fn missing_libraries(runner: &dyn Runner, cancel: &AtomicBool) -> Outcome {
+    let bin = Path::new(&prefix()).join("bin");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&bin)
+        .map_err(|err| format!("{}: {}", bin.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_elf(path))
+        .collect();
+    paths.sort();
+
+    let mut findings = Vec::new();
+    for path in paths {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("cancelled".to_string());
+        }
+        let path_text = path.to_string_lossy();
+        let Ok(report) = stdout(runner, PackageManager::Pkg, Action::Ldd, &path_text) else {
+            continue; // Static binaries make ldd fail
+        };
+        let missing: Vec<&str> = report
+            .lines()
+            .filter(|line| line.contains("not found"))
+            .filter_map(|line| line.split_whitespace().next())
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+
+        // "dpkg -S" prints "package: path"
+        let owner = stdout(runner, PackageManager::Pkg, Action::Owner, &path_text)
+            .ok()
+            .and_then(|text| {
+                text.split_once(':')
+                    .map(|(name, _)| name.trim().to_string())
+            })
+            .unwrap_or_else(|| "(no package)".to_string());
+        findings.push(Finding {
+            manager: PackageManager::Apt,
+            package: owner,
+            detail: format!(
+                "{}: {} not found",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                missing.join(", ")
+            ),
+            other: None,
+        });
+    }
+    Ok(findings)
+}
+
+fn is_elf(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| &magic == b"\x7fELF")
+        .unwrap_or(false)
+}
@@ -0,0 +1,194 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::i18n;
+use crate::keymap::{Binding, Keymap};
+
+// `?`: every key of the main list, grouped. The rebindable ones are read
+// from the active keymap, so the text follows the config file.
+pub struct Help {
+    lines: Vec<Line<'static>>,
+    pub scroll: u16,
+}
+
+impl Help {
+    // Keys that change packages are left out in read-only mode, where they
+    // do nothing.
+    pub fn new(keys: &Keymap, read_only: bool) -> Self {
+        let bound = |binding| keys.label(binding);
+        let mut sections: Vec<(&str, Vec<(String, &str)>)> = vec![
+            (
+                "help.navigation",
+                vec![
+                    (bound(Binding::Next), "help.next_package"),
+                    (bound(Binding::Previous), "help.previous_package"),
+                    (bound(Binding::First), "help.first_package"),
+                    (bound(Binding::Last), "help.last_package"),
+                    ("h/l".to_string(), "help.fold_group"),
+                    ("{ }".to_string(), "help.jump_group"),
+                    ("/".to_string(), "help.search"),
+                    ("n/N".to_string(), "help.next_match"),
+                    ("Alt+n/N".to_string(), "help.next_detail_match"),
+                ],
+            ),
+            (
+                "help.detail_pane",
+                vec![
+                    ("J/K".to_string(), "help.scroll_details"),
+                    ("PgDn/PgUp".to_string(), "help.scroll_page"),
+                    ("[ ]".to_string(), "help.detail_view"),
+                    ("f".to_string(), "help.files"),
+                    ("+/-".to_string(), "help.pane_height"),
+                ],
+            ),
+            (
+                "help.package_managers",
+                vec![
+                    (bound(Binding::SwitchManager), "help.next_manager"),
+                    (bound(Binding::PreviousManager), "help.previous_manager"),
+                    ("S".to_string(), "help.opam_switch"),
+                    ("r/R".to_string(), "help.refresh"),
+                    ("W".to_string(), "help.watch"),
+                ],
+            ),
+            (
+                "help.marking",
+                vec![
+                    ("Space".to_string(), "help.mark"),
+                    ("a/A".to_string(), "help.mark_all"),
+                    ("~".to_string(), "help.invert_marks"),
+                    ("m".to_string(), "help.mark_pattern"),
+                    ("z".to_string(), "help.hide"),
+                    ("Z".to_string(), "help.show_hidden"),
+                    ("u".to_string(), "help.undo"),
+                    ("Ctrl+r".to_string(), "help.redo"),
+                ],
+            ),
+            (
+                "help.actions",
+                vec![
+                    ("Enter".to_string(), "help.action_menu"),
+                    ("i".to_string(), "help.install"),
+                    ("x".to_string(), "help.remove"),
+                    ("H".to_string(), "help.hold"),
+                    ("<".to_string(), "help.rollback"),
+                    ("U".to_string(), "help.no_upgrade"),
+                    ("V".to_string(), "help.upgrades"),
+                    ("e".to_string(), "help.export"),
+                    ("E".to_string(), "help.export_files"),
+                    ("I".to_string(), "help.copy_install"),
+                    ("Y".to_string(), "help.copy_name"),
+                    ("Ctrl+y".to_string(), "help.copy_details"),
+                    ("Ctrl+o".to_string(), "help.inspect_file"),
+                    ("w".to_string(), "help.homepage"),
+                    ("s".to_string(), "help.sessions"),
+                ],
+            ),
+            (
+                "help.views",
+                vec![
+                    ("o".to_string(), "help.sort"),
+                    ("v".to_string(), "help.grouping"),
+                    ("b".to_string(), "help.source_builds"),
+                    ("c".to_string(), "help.orphans"),
+                    ("T".to_string(), "help.priorities"),
+                    ("#".to_string(), "help.sections"),
+                    ("t".to_string(), "help.dep_tree"),
+                    ("y".to_string(), "help.rdepends"),
+                    ("X".to_string(), "help.impact"),
+                    ("F".to_string(), "help.owner"),
+                    ("P".to_string(), "help.held"),
+                    ("O".to_string(), "help.repos"),
+                    ("d".to_string(), "help.drift"),
+                    ("C".to_string(), "help.health"),
+                    ("%".to_string(), "help.usage"),
+                    ("D".to_string(), "help.growth"),
+                    ("L".to_string(), "help.stats"),
+                    ("!".to_string(), "help.diagnostics"),
+                ],
+            ),
+            (
+                "help.general",
+                vec![
+                    (bound(Binding::Quit), "help.quit"),
+                    ("Ctrl+Z".to_string(), "help.suspend"),
+                    ("?".to_string(), "help.help"),
+                ],
+            ),
+        ];
+        #[cfg(feature = "reports")]
+        if let Some((_, actions)) = sections
+            .iter_mut()
+            .find(|(name, _)| *name == "help.actions")
+        {
+            actions.push(("M".to_string(), "help.markdown_report"));
+            actions.push(("B".to_string(), "help.bootstrap_script"));
+        }
+        if read_only {
+            for (_, rows) in &mut sections {
+                rows.retain(|(key, _)| !matches!(key.as_str(), "i" | "x" | "H" | "<"));
+            }
+        }
+
+        let heading = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        let width = sections
+            .iter()
+            .flat_map(|(_, rows)| rows.iter().map(|(key, _)| key.chars().count()))
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for (name, rows) in sections {
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::styled(i18n::text(name), heading));
+            for (key, what) in rows {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}  ", key), Style::default().bold()),
+                    Span::raw(i18n::text(what)),
+                ]));
+            }
+        }
+        Self { lines, scroll: 0 }
+    }
+
+    pub fn scroll_by(&mut self, down: bool, lines: u16) {
+        self.scroll = if down {
+            self.scroll.saturating_add(lines)
+        } else {
+            self.scroll.saturating_sub(lines)
+        };
+    }
+
+    // Scrolling stops with the last line at the bottom.
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let popup = Paragraph::new(self.lines.clone())
+            .block(
+                Block::default()
+                    .title(i18n::text("help.title"))
+                    .borders(Borders::ALL),
+            )
+            .wrap(Wrap { trim: false });
+        let lines = popup.line_count(area.width.saturating_sub(2)) as u16;
+        self.scroll = self
+            .scroll
+            .min(lines.saturating_sub(area.height.saturating_sub(2)));
+        f.render_widget(Clear, area);
+        f.render_widget(popup.scroll((self.scroll, 0)), area);
+    }
+}
+
+// The one-line reminder under the main screen, so the help is found at all.
+pub fn footer(keys: &Keymap) -> String {
+    i18n::fill(
+        "help.footer",
+        &[
+            ("quit", &keys.label(Binding::Quit)),
+            ("switch", &keys.label(Binding::SwitchManager)),
+        ],
+    )
+}
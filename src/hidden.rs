@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{config, lock, PackageManager};
+
+// Packages left out of the list (`z`), per manager: one `manager<TAB>name`
+// line each, `#` starting a comment. Only the list hides them; exports,
+// health checks and everything else still see every package.
+#[derive(Default)]
+pub struct Hidden {
+    entries: HashSet<(PackageManager, String)>,
+}
+
+// $TPI_HIDDEN, or $XDG_DATA_HOME/tpi/hidden.tsv (~/.local/share by default).
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TPI_HIDDEN").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".local/share")))?;
+    Some(data.join("tpi").join("hidden.tsv"))
+}
+
+impl Hidden {
+    // Lines naming an unknown manager are skipped.
+    pub fn load() -> Self {
+        let Some(text) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return Self::default();
+        };
+        let entries = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (manager, name) = line.split_once('\t')?;
+                let manager = PackageManager::from_name(manager.trim())?;
+                Some((manager, name.trim().to_string()))
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn contains(&self, manager: PackageManager, name: &str) -> bool {
+        self.entries.contains(&(manager, name.to_string()))
+    }
+
+    // Hides the packages, or shows them again when every one of them is
+    // hidden, and saves. Returns whether they are hidden now.
+    pub fn toggle(&mut self, manager: PackageManager, names: &[String]) -> io::Result<bool> {
+        let hide = !names.iter().all(|name| self.contains(manager, name));
+        self.set(manager, names, hide)?;
+        Ok(hide)
+    }
+
+    // Hides the packages or shows them again, and saves.
+    pub fn set(&mut self, manager: PackageManager, names: &[String], hide: bool) -> io::Result<()> {
+        for name in names {
+            let entry = (manager, name.clone());
+            if hide {
+                self.entries.insert(entry);
+            } else {
+                self.entries.remove(&entry);
+            }
+        }
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no location: set TPI_HIDDEN, XDG_DATA_HOME or HOME",
+            ));
+        };
+        let mut text = String::from("# Packages tpi leaves out of the list: manager<TAB>name\n");
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(manager, name)| (manager.name(), name));
+        for (manager, name) in entries {
+            text.push_str(&format!("{}\t{}\n", manager.name(), name));
+        }
+        lock::write_atomic(&path, &text)
+    }
+}
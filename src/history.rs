@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{config, lock, Package, PackageManager};
+
+// Version history on this device, built from our own snapshots of the
+// installed packages. Only changes are stored, one tab-separated line each:
+//
+//     <unix seconds>  <manager>  <name>  <version, or - when removed>  <KiB>
+//
+// so a snapshot identical to the previous one adds nothing and the file grows
+// with the number of upgrades, not with the number of runs. The installed
+// size is optional: files written before it was recorded, managers that don't
+// report sizes and removals have four fields.
+
+// A version as first seen in a snapshot.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub time: u64,
+    pub version: Option<String>, // None: the package was removed
+    pub size: Option<u64>,       // Installed size in KiB, when known
+}
+
+// $TPI_HISTORY, or $XDG_STATE_HOME/tpi/history.tsv (~/.local/state by default).
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TPI_HISTORY").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".local/state")))?;
+    Some(state.join("tpi").join("history.tsv"))
+}
+
+// Per-package changes of one manager, oldest first.
+pub fn load(manager: PackageManager) -> HashMap<String, Vec<Change>> {
+    let mut history: HashMap<String, Vec<Change>> = HashMap::new();
+    let Some(text) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return history;
+    };
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let (time, owner, name, version, size) = match fields[..] {
+            [time, owner, name, version] => (time, owner, name, version, None),
+            [time, owner, name, version, size] => (time, owner, name, version, size.parse().ok()),
+            _ => continue,
+        };
+        let Ok(time) = time.parse() else {
+            continue;
+        };
+        if owner != manager.name() {
+            continue;
+        }
+        history.entry(name.to_string()).or_default().push(Change {
+            time,
+            version: (version != "-").then(|| version.to_string()),
+            size,
+        });
+    }
+    history
+}
+
+// The name→(version, size) map as of the last snapshot.
+fn latest(history: &HashMap<String, Vec<Change>>) -> BTreeMap<&str, (&str, Option<u64>)> {
+    history
+        .iter()
+        .filter_map(|(name, changes)| {
+            let last = changes.last()?;
+            Some((name.as_str(), (last.version.as_deref()?, last.size)))
+        })
+        .collect()
+}
+
+// Appends what changed since the last snapshot of this manager. Returns the
+// number of lines written. Several versions of one package (luarocks) are
+// stored as a single comma-separated version. A package whose size becomes
+// known or changes gets a line too, so older files pick sizes up once.
+pub fn record(
+    manager: PackageManager,
+    packages: &[Package],
+    sizes: &HashMap<String, u64>,
+) -> io::Result<usize> {
+    let Some(path) = path() else {
+        return Ok(0);
+    };
+
+    let mut current: BTreeMap<&str, String> = BTreeMap::new();
+    for pkg in packages {
+        current
+            .entry(pkg.name.as_str())
+            .and_modify(|versions| {
+                versions.push(',');
+                versions.push_str(&pkg.version);
+            })
+            .or_insert_with(|| pkg.version.clone());
+    }
+
+    let history = load(manager);
+    let previous = latest(&history);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut lines = String::new();
+    for (name, version) in &current {
+        let size = sizes.get(*name).copied();
+        let changed = match previous.get(name) {
+            Some((seen, seen_size)) => {
+                *seen != version.as_str() || (size.is_some() && size != *seen_size)
+            }
+            None => true,
+        };
+        if changed {
+            lines.push_str(&format!(
+                "{}\t{}\t{}\t{}",
+                time,
+                manager.name(),
+                name,
+                version
+            ));
+            if let Some(size) = size {
+                lines.push_str(&format!("\t{}", size));
+            }
+            lines.push('\n');
+        }
+    }
+    for name in previous.keys() {
+        if !current.contains_key(name) {
+            lines.push_str(&format!("{}\t{}\t{}\t-\n", time, manager.name(), name));
+        }
+    }
+    if lines.is_empty() {
+        return Ok(0);
+    }
+
+    // Rewritten whole rather than appended to, so a crash can't leave half a line
+    let mut text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(&lines);
+    lock::write_atomic(&path, &text)?;
+    Ok(lines.lines().count())
+}
@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+
+use ratatui::{
+    prelude::*,
+    widgets::{block, Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::commands::{self, Action, Runner};
+use crate::{i18n, PackageManager};
+
+// A held package with the version it is pinned at and what apt would install
+// instead.
+pub struct Held {
+    pub name: String,
+    pub installed: Option<String>,
+    pub candidate: Option<String>,
+    pub behind: usize, // Newer versions available in the configured repositories
+}
+
+// Every held package, queried one `apt-cache policy` at a time.
+pub fn load(runner: &dyn Runner, manager: PackageManager, names: &HashSet<String>) -> Vec<Held> {
+    let mut names: Vec<&String> = names.iter().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let policy = commands::run_with(runner, manager, Action::Policy, name)
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+                .unwrap_or_default();
+            let (installed, candidate, behind) = parse_policy(&policy);
+            Held {
+                name: name.clone(),
+                installed,
+                candidate,
+                behind,
+            }
+        })
+        .collect()
+}
+
+// Installed and candidate versions from `apt-cache policy`, and how many
+// versions in its table (newest first) come before the installed one:
+//
+//     curl:
+//       Installed: 8.0
+//       Candidate: 8.5
+//       Version table:
+//          8.5 500
+//             500 https://... stable/main aarch64 Packages
+//      *** 8.0 100
+//             100 /data/data/com.termux/files/usr/var/lib/dpkg/status
+fn parse_policy(stdout: &str) -> (Option<String>, Option<String>, usize) {
+    let mut installed = None;
+    let mut candidate = None;
+    let mut versions = Vec::new();
+    let mut in_table = false;
+    for line in stdout.lines() {
+        let trimmed = line.trim();
+        if let Some(version) = trimmed.strip_prefix("Installed:") {
+            installed = Some(version.trim().to_string()).filter(|v| v != "(none)");
+        } else if let Some(version) = trimmed.strip_prefix("Candidate:") {
+            candidate = Some(version.trim().to_string()).filter(|v| v != "(none)");
+        } else if trimmed == "Version table:" {
+            in_table = true;
+        } else if in_table {
+            // Versions are indented less than the sources listed under them
+            let indent = line.len() - line.trim_start().len();
+            if indent < 8 {
+                let version = trimmed.trim_start_matches("***").split_whitespace().next();
+                versions.extend(version.map(String::from));
+            }
+        }
+    }
+    let behind = match &installed {
+        Some(installed) => versions
+            .iter()
+            .position(|version| version == installed)
+            .unwrap_or(0),
+        None => 0,
+    };
+    (installed, candidate, behind)
+}
+
+// Screen listing every held package, with marks for bulk unholding.
+pub struct HoldView {
+    pub entries: Vec<Held>,
+    pub marked: HashSet<String>,
+    pub state: ListState,
+    warn_after: usize, // Holds more versions behind than this are flagged
+}
+
+impl HoldView {
+    pub fn new(entries: Vec<Held>, warn_after: usize) -> Self {
+        let mut state = ListState::default();
+        if !entries.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            entries,
+            marked: HashSet::new(),
+            state,
+            warn_after,
+        }
+    }
+
+    // Replaces the entries after a change, dropping marks of packages that
+    // are no longer held.
+    pub fn reload(&mut self, entries: Vec<Held>) {
+        self.marked
+            .retain(|name| entries.iter().any(|held| &held.name == name));
+        let selected = self.state.selected().unwrap_or(0);
+        self.state.select(match entries.len() {
+            0 => None,
+            len => Some(selected.min(len - 1)),
+        });
+        self.entries = entries;
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.entries.len()));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            let len = self.entries.len();
+            self.state.select(Some((i + len - 1) % len));
+        }
+    }
+
+    pub fn toggle_mark(&mut self) {
+        let Some(held) = self.state.selected().and_then(|i| self.entries.get(i)) else {
+            return;
+        };
+        if !self.marked.remove(&held.name) {
+            self.marked.insert(held.name.clone());
+        }
+        self.select_next();
+    }
+
+    // The marked packages, or the selected one when nothing is marked.
+    pub fn targets(&self) -> Vec<String> {
+        if !self.marked.is_empty() {
+            let mut names: Vec<String> = self.marked.iter().cloned().collect();
+            names.sort();
+            return names;
+        }
+        self.state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .map(|held| vec![held.name.clone()])
+            .unwrap_or_default()
+    }
+
+    // `message` is the outcome of the last change, shown in the bottom border.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, message: Option<&str>) {
+        let mut block = Block::default()
+            .title(i18n::text("holds.title"))
+            .borders(Borders::ALL);
+        if let Some(message) = message {
+            block = block.title(block::Title::from(message).position(block::Position::Bottom));
+        }
+        f.render_widget(Clear, area);
+        if self.entries.is_empty() {
+            f.render_widget(Paragraph::new(i18n::text("holds.none")).block(block), area);
+            return;
+        }
+
+        let name_width = self
+            .entries
+            .iter()
+            .map(|held| held.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|held| {
+                let mark = if self.marked.contains(&held.name) {
+                    "* "
+                } else {
+                    "  "
+                };
+                let installed = held.installed.as_deref().unwrap_or("?");
+                let mut spans = vec![Span::raw(format!(
+                    "{}{:<width$}  {}",
+                    mark,
+                    held.name,
+                    installed,
+                    width = name_width
+                ))];
+                match held.candidate.as_deref() {
+                    Some(candidate) if candidate != installed => {
+                        spans.push(Span::raw(format!(" → {}", candidate)));
+                    }
+                    _ => spans.push(Span::styled(
+                        i18n::text("holds.up_to_date"),
+                        Style::default().fg(Color::DarkGray),
+                    )),
+                }
+                if held.behind > self.warn_after {
+                    spans.push(Span::styled(
+                        i18n::fill("holds.far_behind", &[("count", &held.behind)]),
+                        Style::default().fg(Color::Red),
+                    ));
+                } else if held.behind > 0 {
+                    spans.push(Span::styled(
+                        i18n::fill("holds.behind", &[("count", &held.behind)]),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
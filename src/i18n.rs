@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use crate::configfile;
+
+// The catalogs, compiled in. English has every key; a key another language
+// lacks is shown in English.
+pub const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("locales/en.toml")),
+    ("es", include_str!("locales/es.toml")),
+];
+
+// The locale variables, in the order setlocale reads them for messages.
+const VARIABLES: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+static MESSAGES: OnceLock<Messages> = OnceLock::new();
+
+// tpi's own words in one language: the main screen, its dialogs, the help
+// and the status bar. What the package managers print is shown as they
+// print it, and they run with LC_ALL=C so that it parses.
+pub struct Messages {
+    pub language: &'static str,
+    texts: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+impl Messages {
+    // A language without a catalog gets English.
+    pub fn load(language: &str) -> Self {
+        let language = CATALOGS
+            .iter()
+            .map(|(name, _)| *name)
+            .find(|name| *name == language)
+            .unwrap_or("en");
+        Self {
+            language,
+            texts: catalog(language).unwrap_or_default().into_iter().collect(),
+            english: catalog("en").unwrap_or_default().into_iter().collect(),
+        }
+    }
+
+    // A key in no catalog shows as itself, which is easy to spot.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.texts
+            .get(key)
+            .or_else(|| self.english.get(key))
+            .map_or(key, String::as_str)
+    }
+}
+
+// The catalog's strings, or what is wrong with it.
+pub fn catalog(language: &str) -> Result<Vec<(String, String)>, String> {
+    let (_, text) = CATALOGS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .ok_or_else(|| format!("no catalog for {}", language))?;
+    configfile::parse_strings(text)
+}
+
+// The first locale variable that is set, and its value.
+pub fn requested() -> Option<(&'static str, String)> {
+    VARIABLES.iter().find_map(|name| {
+        env::var(name)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map(|value| (*name, value))
+    })
+}
+
+// "es_ES.UTF-8" is "es"; C and POSIX are English.
+pub fn language_of(locale: &str) -> &str {
+    match locale.split(['_', '.', '@']).next().unwrap_or("") {
+        "C" | "POSIX" | "" => "en",
+        language => language,
+    }
+}
+
+pub fn messages() -> &'static Messages {
+    MESSAGES.get_or_init(|| {
+        let locale = requested().map(|(_, value)| value).unwrap_or_default();
+        Messages::load(language_of(&locale))
+    })
+}
+
+pub fn text(key: &str) -> &str {
+    messages().get(key)
+}
+
+// The text with each `{name}` in it filled in.
+pub fn fill(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = text(key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn keys(language: &str) -> BTreeSet<String> {
+        catalog(language)
+            .unwrap_or_else(|err| panic!("{}: {}", language, err))
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    // The `{name}`s a message fills in.
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn every_catalog_has_every_key() {
+        let english = keys("en");
+        for (language, _) in CATALOGS {
+            let keys = keys(language);
+            let missing: Vec<_> = english.difference(&keys).collect();
+            let unknown: Vec<_> = keys.difference(&english).collect();
+            assert!(missing.is_empty(), "{} lacks {:?}", language, missing);
+            assert!(unknown.is_empty(), "{} has unknown {:?}", language, unknown);
+        }
+    }
+
+    #[test]
+    fn translations_fill_in_the_same_names() {
+        let english: HashMap<String, String> = catalog("en").unwrap().into_iter().collect();
+        for (language, _) in CATALOGS {
+            for (key, text) in catalog(language).unwrap() {
+                assert_eq!(
+                    placeholders(&text),
+                    placeholders(&english[&key]),
+                    "{} {}",
+                    language,
+                    key
+                );
+            }
+        }
+    }
+
+    // Every "table.key" literal in the source, where the table is one of the
+    // catalog's, is a message that must exist.
+    #[test]
+    fn keys_in_the_source_exist() {
+        let english = keys("en");
+        let tables: BTreeSet<&str> = english
+            .iter()
+            .filter_map(|key| key.split_once('.').map(|(table, _)| table))
+            .collect();
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let (mut checked, mut unknown) = (0, Vec::new());
+        for entry in fs::read_dir(src).unwrap().flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("rs") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            for literal in source.split('"').skip(1).step_by(2) {
+                let Some((table, name)) = literal.split_once('.') else {
+                    continue;
+                };
+                let is_key = tables.contains(table)
+                    && !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+                if !is_key {
+                    continue;
+                }
+                checked += 1;
+                if !english.contains(literal) {
+                    unknown.push(format!("{}: {}", path.display(), literal));
+                }
+            }
+        }
+        assert!(checked > 100, "only {} keys found", checked);
+        assert!(unknown.is_empty(), "not in the catalog: {:?}", unknown);
+    }
+
+    #[test]
+    fn locales_pick_a_catalog() {
+        assert_eq!(language_of("es_ES.UTF-8"), "es");
+        assert_eq!(language_of("de_DE@euro"), "de");
+        assert_eq!(language_of("C.UTF-8"), "en");
+        assert_eq!(language_of("POSIX"), "en");
+        assert_eq!(Messages::load("de").language, "en");
+        assert_eq!(Messages::load("es").language, "es");
+        assert_eq!(Messages::load("es").get("no.such_key"), "no.such_key");
+    }
+}
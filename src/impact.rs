@@ -0,0 +1,390 @@
+use std::collections::{HashSet, VecDeque};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::commands::{self, Action, Runner};
+use crate::why::{Explainer, Graph};
+use crate::PackageManager;
+
+// What removing some packages would do to the rest, estimated from the
+// dependency graph alone.
+pub struct Impact {
+    pub broken: Vec<String>,   // Would have unsatisfied dependencies
+    pub orphaned: Vec<String>, // Only needed by what goes away
+    pub unknown: Vec<String>,  // Targets the graph doesn't have
+}
+
+// Packages depending on a removed one break, and so does whatever depends on
+// those in turn, until nothing changes; a dependency with alternatives only
+// breaks once every installed alternative is gone. Then whatever the roots no
+// longer reach (and did before) is orphaned.
+pub fn estimate(graph: &Graph, roots: &HashSet<String>, targets: &[String]) -> Impact {
+    let (known, unknown): (Vec<&String>, Vec<&String>) = targets
+        .iter()
+        .partition(|name| graph.edges.contains_key(*name));
+    let mut gone: HashSet<&str> = known.into_iter().map(String::as_str).collect();
+
+    let mut broken = Vec::new();
+    loop {
+        let newly: Vec<&str> = graph
+            .clauses
+            .iter()
+            .filter(|(name, _)| !gone.contains(name.as_str()))
+            .filter(|(_, clauses)| {
+                clauses
+                    .iter()
+                    .any(|clause| clause.iter().all(|dep| gone.contains(dep.as_str())))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if newly.is_empty() {
+            break;
+        }
+        broken.extend(newly.iter().map(|name| name.to_string()));
+        gone.extend(newly);
+    }
+
+    let before = reachable(graph, roots, &HashSet::new());
+    let after = reachable(graph, roots, &gone);
+    let mut orphaned: Vec<String> = before
+        .into_iter()
+        .filter(|name| !after.contains(name) && !gone.contains(name))
+        .map(String::from)
+        .collect();
+
+    broken.sort();
+    orphaned.sort();
+    Impact {
+        broken,
+        orphaned,
+        unknown: unknown.into_iter().cloned().collect(),
+    }
+}
+
+fn reachable<'a>(
+    graph: &'a Graph,
+    roots: &'a HashSet<String>,
+    gone: &HashSet<&str>,
+) -> HashSet<&'a str> {
+    let mut seen: HashSet<&str> = roots
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !gone.contains(name))
+        .collect();
+    let mut queue: VecDeque<&str> = seen.iter().copied().collect();
+    while let Some(name) = queue.pop_front() {
+        for dep in graph.edges.get(name).into_iter().flatten() {
+            if !gone.contains(dep.as_str()) && seen.insert(dep) {
+                queue.push_back(dep);
+            }
+        }
+    }
+    seen
+}
+
+// The "what would break?" popup: the estimate first, and apt's own dry run of
+// the same removal on request.
+pub struct ImpactView {
+    pub manager: PackageManager,
+    pub names: Vec<String>,
+    pub impact: Impact,
+    pub autoremoves: bool, // Whether the orphans go too (apt's autoremove)
+    simulated: Option<Vec<String>>, // apt's answer, once asked for
+    text: Text<'static>,
+    dry_run: bool, // Showing apt's answer instead of the estimate
+    pub scroll: u16,
+}
+
+impl ImpactView {
+    pub fn new(explainer: &Explainer, manager: PackageManager, names: Vec<String>) -> Self {
+        let keys: Vec<String> = names.iter().map(|name| explainer.key(name)).collect();
+        let impact = estimate(&explainer.graph, &explainer.roots, &keys);
+        let autoremoves = explainer.autoremoves();
+        let orphaned = if autoremoves {
+            "Would be removed with it (no longer needed, autoremove)"
+        } else {
+            "Would be left with nothing requiring them (pip doesn't remove these)"
+        };
+
+        let mut lines = vec![Line::from(format!("Removing: {}", names.join(", ")))];
+        if !impact.unknown.is_empty() {
+            lines.push(Line::styled(
+                format!("Not in the dependency graph: {}", impact.unknown.join(", ")),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        for (title, packages, color) in [
+            (
+                "Would have unsatisfied dependencies",
+                &impact.broken,
+                Color::Red,
+            ),
+            (orphaned, &impact.orphaned, Color::Yellow),
+        ] {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(
+                format!("{} ({}):", title, packages.len()),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ));
+            if packages.is_empty() {
+                lines.push(Line::styled("  none", Style::default().fg(Color::DarkGray)));
+            }
+            lines.extend(
+                packages
+                    .iter()
+                    .map(|name| Line::from(format!("  {}", name))),
+            );
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "Estimated from the dependency graph; nothing was run.",
+            Style::default().fg(Color::DarkGray),
+        ));
+        Self {
+            manager,
+            names,
+            impact,
+            autoremoves,
+            simulated: None,
+            text: Text::from(lines),
+            dry_run: false,
+            scroll: 0,
+        }
+    }
+
+    // What `apt-get --simulate remove` says about the same packages, run the
+    // first time it is asked for.
+    pub fn simulated(&mut self, runner: &dyn Runner) -> Result<&[String], String> {
+        if !commands::supports(self.manager, Action::SimulateRemove) {
+            return Err(format!(
+                "{} has no dry run for removals",
+                self.manager.name()
+            ));
+        }
+        if self.simulated.is_none() {
+            let output =
+                commands::run_with_names(runner, self.manager, Action::SimulateRemove, &self.names)
+                    .map_err(|err| format!("Dry run failed: {}", err))?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // The Remv/Inst/Conf lines and apt's summary; the rest is progress
+            self.simulated = Some(
+                stdout
+                    .lines()
+                    .chain(stderr.lines())
+                    .filter(|line| {
+                        !line.starts_with("Reading ")
+                            && !line.starts_with("Building ")
+                            && !line.trim().is_empty()
+                    })
+                    .map(String::from)
+                    .collect(),
+            );
+        }
+        Ok(self.simulated.as_deref().unwrap_or_default())
+    }
+
+    // Replaces the estimate with what `apt-get --simulate remove` says.
+    pub fn run_dry_run(&mut self, runner: &dyn Runner) -> Result<(), String> {
+        let mut lines: Vec<Line> = self
+            .simulated(runner)?
+            .iter()
+            .map(|line| {
+                let color = match line.split_whitespace().next() {
+                    Some("Remv" | "Purg") => Color::Red,
+                    Some("Inst" | "Conf") => Color::Yellow,
+                    Some("E:" | "W:") => Color::Magenta,
+                    _ => Color::Reset,
+                };
+                Line::styled(line.to_string(), Style::default().fg(color))
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            "Simulated by apt; nothing was removed.",
+            Style::default().fg(Color::DarkGray),
+        ));
+        self.text = Text::from(lines);
+        self.dry_run = true;
+        self.scroll = 0;
+        Ok(())
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let title = if self.dry_run {
+            "Removal dry run (apt) — p/c plan to file/clipboard, j/k scroll, Esc close"
+        } else if commands::supports(self.manager, Action::SimulateRemove) {
+            "What would break? (estimate) — a apt dry run, p/c plan to file/clipboard, Esc close"
+        } else {
+            "What would break? (estimate) — p/c plan to file/clipboard, j/k scroll, Esc close"
+        };
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(self.text.clone())
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0)),
+            area,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Mock;
+
+    // A graph from each package's hard dependencies, alternatives joined by
+    // `|`, and what it only recommends.
+    fn graph(packages: &[(&str, &[&str], &[&str])]) -> Graph {
+        let mut graph = Graph::default();
+        for (name, depends, recommends) in packages {
+            let clauses: Vec<Vec<String>> = depends
+                .iter()
+                .map(|clause| clause.split('|').map(String::from).collect())
+                .collect();
+            let mut edges: Vec<String> = clauses.iter().flatten().cloned().collect();
+            edges.extend(recommends.iter().map(|name| name.to_string()));
+            graph.edges.insert(name.to_string(), edges);
+            graph.clauses.insert(name.to_string(), clauses);
+        }
+        graph
+    }
+
+    fn roots(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn targets(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    // curl and wget need libcurl's TLS; git needs curl; the editor pulls in
+    // its own runtime; ssh works with either libssl3 or libressl.
+    fn termux() -> Graph {
+        graph(&[
+            ("git", &["curl", "zlib"], &["less"]),
+            ("curl", &["libcurl"], &[]),
+            ("libcurl", &["libssl3", "zlib"], &[]),
+            ("wget", &["libssl3"], &[]),
+            ("openssh", &["libssl3|libressl"], &[]),
+            ("libssl3", &[], &[]),
+            ("libressl", &[], &[]),
+            ("zlib", &[], &[]),
+            ("less", &["ncurses"], &[]),
+            ("ncurses", &[], &[]),
+            ("neovim", &["libuv", "luajit"], &[]),
+            ("libuv", &[], &[]),
+            ("luajit", &[], &[]),
+        ])
+    }
+
+    #[test]
+    fn breakage_follows_reverse_dependencies() {
+        let impact = estimate(
+            &termux(),
+            &roots(&["git", "wget", "openssh", "neovim"]),
+            &targets(&["libcurl"]),
+        );
+        assert_eq!(impact.broken, ["curl", "git"]);
+        // Everything only the broken git kept, down the chain
+        assert_eq!(impact.orphaned, ["less", "ncurses", "zlib"]);
+        assert!(impact.unknown.is_empty());
+    }
+
+    #[test]
+    fn alternatives_break_once_all_are_gone() {
+        let roots = roots(&["openssh", "wget"]);
+        // openssh still has libressl
+        let impact = estimate(&termux(), &roots, &targets(&["libssl3"]));
+        assert_eq!(impact.broken, ["curl", "git", "libcurl", "wget"]);
+        let impact = estimate(&termux(), &roots, &targets(&["libssl3", "libressl"]));
+        assert_eq!(impact.broken, ["curl", "git", "libcurl", "openssh", "wget"]);
+    }
+
+    #[test]
+    fn leaves_orphan_what_only_they_needed() {
+        let impact = estimate(&termux(), &roots(&["git", "neovim"]), &targets(&["neovim"]));
+        assert!(impact.broken.is_empty());
+        assert_eq!(impact.orphaned, ["libuv", "luajit"]);
+
+        // Still needed elsewhere, so not orphaned
+        let impact = estimate(&termux(), &roots(&["git", "wget"]), &targets(&["git"]));
+        assert_eq!(
+            impact.orphaned,
+            ["curl", "less", "libcurl", "ncurses", "zlib"]
+        );
+        assert!(!impact.orphaned.contains(&"libssl3".to_string()));
+    }
+
+    #[test]
+    fn already_unreachable_isnt_newly_orphaned() {
+        // ncurses was left behind before anything was removed
+        let impact = estimate(&termux(), &roots(&["neovim"]), &targets(&["neovim"]));
+        assert_eq!(impact.orphaned, ["libuv", "luajit"]);
+    }
+
+    #[test]
+    fn a_root_depending_on_a_target_breaks_but_stays_out_of_orphans() {
+        let impact = estimate(&termux(), &roots(&["git"]), &targets(&["zlib"]));
+        assert_eq!(impact.broken, ["curl", "git", "libcurl"]);
+        assert_eq!(impact.orphaned, ["less", "libssl3", "ncurses"]);
+    }
+
+    #[test]
+    fn unknown_targets_are_reported_and_change_nothing() {
+        let impact = estimate(
+            &termux(),
+            &roots(&["git"]),
+            &targets(&["nonexistent", "zlib1g"]),
+        );
+        assert_eq!(impact.unknown, ["nonexistent", "zlib1g"]);
+        assert!(impact.broken.is_empty());
+        assert!(impact.orphaned.is_empty());
+    }
+
+    #[test]
+    fn cycles_end() {
+        let graph = graph(&[("a", &["b"], &[]), ("b", &["a"], &[]), ("c", &["a"], &[])]);
+        let impact = estimate(&graph, &roots(&["c"]), &targets(&["a"]));
+        assert_eq!(impact.broken, ["b", "c"]);
+        assert!(impact.orphaned.is_empty());
+    }
+
+    #[test]
+    fn dry_run_keeps_apt_lines_only() {
+        let mock = Mock::new().reply(
+            "apt-get --simulate remove",
+            "Reading package lists...\nBuilding dependency tree...\n\nRemv git [2.45.2]\nRemv curl [8.9.1]\n",
+        );
+        let graph = termux();
+        let impact = estimate(&graph, &roots(&["git"]), &targets(&["curl"]));
+        let mut view = ImpactView {
+            manager: PackageManager::Apt,
+            names: targets(&["curl"]),
+            impact,
+            autoremoves: true,
+            simulated: None,
+            text: Text::default(),
+            dry_run: false,
+            scroll: 0,
+        };
+        assert_eq!(
+            view.simulated(&mock).unwrap(),
+            ["Remv git [2.45.2]", "Remv curl [8.9.1]"]
+        );
+        // Asked once
+        view.simulated(&mock).unwrap();
+        assert_eq!(mock.ran("apt-get --simulate remove curl"), 1);
+
+        view.manager = PackageManager::Pip;
+        view.simulated = None;
+        assert_eq!(
+            view.simulated(&mock).unwrap_err(),
+            "pip has no dry run for removals"
+        );
+    }
+}
@@ -0,0 +1,495 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::commands::Runner;
+use crate::details::Details;
+use crate::{format_size, i18n, renderers, tabs, PackageManager};
+
+// Where the Ctrl+o prompt starts: Termux's link to the phone's downloads,
+// after termux-setup-storage.
+pub const DEFAULT_DIR: &str = "~/storage/downloads/";
+
+// Prints the wheel's METADATA, a NUL line, then `size path` for each file in
+// the archive (what its RECORD lists, with sizes for every entry). Python
+// reads the zip, so a deflated one needs nothing else installed.
+const WHEEL_SCRIPT: &str = r#"
+import sys, zipfile
+try:
+    z = zipfile.ZipFile(sys.argv[1])
+    meta = [n for n in z.namelist() if n.count('/') == 1 and n.endswith('.dist-info/METADATA')]
+    if not meta:
+        sys.exit('no .dist-info/METADATA in it, so not a wheel')
+    sys.stdout.write(z.read(meta[0]).decode('utf-8', 'replace'))
+    sys.stdout.write('\n\0\n')
+    for info in z.infolist():
+        if not info.is_dir():
+            print(info.file_size, info.filename)
+except zipfile.BadZipFile as e:
+    sys.exit('not a zip archive (%s)' % e)
+except Exception as e:
+    sys.exit(str(e))
+"#;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Deb,
+    Wheel,
+}
+
+impl Kind {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("deb") => Ok(Kind::Deb),
+            Some("whl") => Ok(Kind::Wheel),
+            _ => Err(i18n::fill(
+                "inspect.wrong_kind",
+                &[("file", &path.display())],
+            )),
+        }
+    }
+
+    // What installs it, and whose field renderers suit its fields.
+    pub fn manager(self) -> PackageManager {
+        match self {
+            Kind::Deb => PackageManager::Apt,
+            Kind::Wheel => PackageManager::Pip,
+        }
+    }
+}
+
+// A package file read without installing it.
+pub struct Inspected {
+    pub path: PathBuf, // Absolute: apt takes a bare name for a package in a repository
+    pub kind: Kind,
+    pub details: Details,
+    pub files: Vec<(String, Option<u64>)>, // With the size in bytes, when the archive says
+}
+
+impl Inspected {
+    pub fn name(&self) -> String {
+        let file = self.path.file_name().unwrap_or_default();
+        file.to_string_lossy().into_owned()
+    }
+
+    // Unpacked, in bytes: what the archive lists.
+    pub fn unpacked(&self) -> u64 {
+        self.files.iter().filter_map(|(_, size)| *size).sum()
+    }
+}
+
+// `~/` is the shell's, not the kernel's, so it is expanded here.
+pub fn expand(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+pub fn inspect(runner: &dyn Runner, path: &str) -> Result<Inspected, String> {
+    let path = expand(path.trim());
+    let kind = Kind::from_path(&path)?;
+    let path = path
+        .canonicalize()
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    if !path.is_file() {
+        return Err(i18n::fill(
+            "inspect.not_a_file",
+            &[("file", &path.display())],
+        ));
+    }
+    let file = path.to_string_lossy().into_owned();
+    let (details, files) = match kind {
+        Kind::Deb => {
+            let control = run(runner, &["dpkg-deb", "--field", &file])?;
+            let contents = run(runner, &["dpkg-deb", "--contents", &file])?;
+            (Details::parse(&control), parse_contents(&contents))
+        }
+        Kind::Wheel => {
+            let output = run(runner, &["python", "-c", WHEEL_SCRIPT, &file])?;
+            parse_wheel(&output)
+        }
+    };
+    if details.fields.is_empty() {
+        return Err(i18n::fill(
+            "inspect.no_fields",
+            &[("file", &path.display())],
+        ));
+    }
+    Ok(Inspected {
+        path,
+        kind,
+        details,
+        files,
+    })
+}
+
+// stdout, or stderr as the error: dpkg-deb and the script both say what is
+// wrong with the archive there.
+fn run(runner: &dyn Runner, words: &[&str]) -> Result<String, String> {
+    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    let output = runner.output(&words).map_err(|err| {
+        i18n::fill(
+            "inspect.run_failed",
+            &[("command", &words[0]), ("error", &err)],
+        )
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stderr = stderr.trim();
+        let stderr = stderr.strip_prefix("dpkg-deb: error: ").unwrap_or(stderr);
+        return Err(match stderr {
+            "" => i18n::fill(
+                "inspect.failed",
+                &[("command", &words[0]), ("status", &output.status)],
+            ),
+            stderr => stderr.to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `dpkg-deb --contents`: tar's long listing,
+// `-rwxr-xr-x root/root 1234 2024-01-01 00:00 ./data/…/bin/x`. Directories
+// are left out; the files get the `/` that dpkg installs them under.
+pub fn parse_contents(text: &str) -> Vec<(String, Option<u64>)> {
+    let mut files = Vec::new();
+    for line in text.lines() {
+        if line.starts_with('d') {
+            continue;
+        }
+        let mut rest = line;
+        let mut columns = Vec::new();
+        for _ in 0..5 {
+            let trimmed = rest.trim_start();
+            let end = trimmed.find(' ').unwrap_or(trimmed.len());
+            columns.push(&trimmed[..end]);
+            rest = &trimmed[end..];
+        }
+        let name = rest.trim_start();
+        if name.is_empty() {
+            continue;
+        }
+        let name = name.strip_prefix('.').unwrap_or(name);
+        files.push((name.to_string(), columns[2].parse().ok()));
+    }
+    files
+}
+
+// The script's output: METADATA is pip show's header format, with the long
+// description after the first blank line, which becomes the Description.
+pub fn parse_wheel(text: &str) -> (Details, Vec<(String, Option<u64>)>) {
+    let (metadata, listing) = text.split_once("\n\0\n").unwrap_or((text, ""));
+    let (headers, body) = metadata.split_once("\n\n").unwrap_or((metadata, ""));
+    let mut details = Details::parse_pip(headers);
+    if !body.trim().is_empty() {
+        details
+            .fields
+            .push(("Description".to_string(), body.trim().to_string()));
+    }
+    let files = listing
+        .lines()
+        .filter_map(|line| {
+            let (size, name) = line.split_once(' ')?;
+            Some((name.to_string(), size.parse().ok()))
+        })
+        .collect();
+    (details, files)
+}
+
+// The same fields and file list, for `tpi inspect-file`.
+pub fn text(inspected: &Inspected) -> String {
+    let manager = inspected.kind.manager();
+    let mut text = tabs::fields_text(&inspected.details, manager, &context());
+    text.push_str(&format!(
+        "\nFiles ({}, {} unpacked):\n",
+        inspected.files.len(),
+        format_size(inspected.unpacked().div_ceil(1024))
+    ));
+    for (name, _) in &inspected.files {
+        text.push_str(name);
+        text.push('\n');
+    }
+    text
+}
+
+fn context() -> renderers::Context {
+    renderers::Context {
+        largest: None,
+        now: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+    }
+}
+
+// Ctrl+o: a .deb or wheel's fields or files, as the detail pane would show
+// an installed package's, with `i` to install it.
+pub struct InspectView {
+    pub inspected: Inspected,
+    pub files: bool, // The Files view instead of Fields
+    scroll: u16,
+    max_scroll: u16, // As of the last draw
+}
+
+impl InspectView {
+    pub fn new(inspected: Inspected) -> Self {
+        Self {
+            inspected,
+            files: false,
+            scroll: 0,
+            max_scroll: 0,
+        }
+    }
+
+    pub fn toggle_files(&mut self) {
+        self.files = !self.files;
+        self.scroll = 0;
+    }
+
+    pub fn scroll_by(&mut self, lines: i32) {
+        let scroll = i32::from(self.scroll) + lines;
+        self.scroll = u16::try_from(scroll.max(0))
+            .unwrap_or(u16::MAX)
+            .min(self.max_scroll);
+    }
+
+    // The command `i` runs, e.g. "apt install /…/x.deb".
+    pub fn install_words(&self) -> Vec<String> {
+        let manager = self.inspected.kind.manager();
+        let path = self.inspected.path.to_string_lossy().into_owned();
+        crate::commands::resolve_names(manager, crate::commands::Action::Install, &[path])
+            .unwrap_or_default()
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect, read_only: bool) {
+        let inspected = &self.inspected;
+        let view = if self.files {
+            i18n::fill(
+                "inspect.files",
+                &[
+                    ("count", &inspected.files.len()),
+                    ("size", &format_size(inspected.unpacked().div_ceil(1024))),
+                ],
+            )
+        } else {
+            i18n::text("inspect.fields").to_string()
+        };
+        let install = if read_only {
+            ""
+        } else {
+            i18n::text("inspect.install")
+        };
+        let other = if self.files {
+            i18n::text("inspect.to_fields")
+        } else {
+            i18n::text("inspect.to_files")
+        };
+        let title = i18n::fill(
+            "inspect.title",
+            &[
+                ("name", &inspected.name()),
+                ("view", &view),
+                ("other", &other),
+                ("install", &install),
+            ],
+        );
+        let lines: Vec<Line> = if self.files {
+            inspected
+                .files
+                .iter()
+                .map(|(name, _)| Line::from(name.clone()))
+                .collect()
+        } else {
+            let mut lines =
+                tabs::field_lines(&inspected.details, inspected.kind.manager(), &context());
+            tabs::emphasize_synopsis(&mut lines);
+            lines
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let inner = block.inner(area);
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        let count = u16::try_from(paragraph.line_count(inner.width)).unwrap_or(u16::MAX);
+        self.max_scroll = count.saturating_sub(inner.height);
+        self.scroll = self.scroll.min(self.max_scroll);
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph.block(block).scroll((self.scroll, 0)), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{self, SystemRunner};
+
+    // Built with dpkg-deb and zip; the broken ones are cut short or aren't
+    // archives at all.
+    fn fixture(file: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), file)
+    }
+
+    // Reading a file takes dpkg-deb or python, as on the device. Without
+    // them the archive tests have nothing to check.
+    fn have(program: &str) -> bool {
+        let found = commands::find_program(program).is_some();
+        if !found {
+            eprintln!("{} not on PATH; skipped", program);
+        }
+        found
+    }
+
+    fn fails(file: &str) -> String {
+        match inspect(&SystemRunner, &fixture(file)) {
+            Ok(_) => panic!("{} was read", file),
+            Err(err) => err,
+        }
+    }
+
+    fn field<'a>(inspected: &'a Inspected, name: &str) -> Option<&'a str> {
+        inspected.details.get(name)
+    }
+
+    #[test]
+    fn reads_a_deb() {
+        if !have("dpkg-deb") {
+            return;
+        }
+        let inspected = inspect(&SystemRunner, &fixture("hello_1.0-1_all.deb")).unwrap();
+        assert_eq!(inspected.kind, Kind::Deb);
+        assert_eq!(inspected.name(), "hello_1.0-1_all.deb");
+        assert_eq!(field(&inspected, "Package"), Some("hello"));
+        assert_eq!(field(&inspected, "Version"), Some("1.0-1"));
+        assert_eq!(field(&inspected, "Depends"), Some("bash (>= 5.0)"));
+        assert_eq!(
+            inspected.files,
+            [
+                (
+                    "/data/data/com.termux/files/usr/bin/hello".to_string(),
+                    Some(52)
+                ),
+                (
+                    "/data/data/com.termux/files/usr/share/doc/hello/README".to_string(),
+                    Some(22)
+                ),
+            ]
+        );
+        assert_eq!(inspected.unpacked(), 74);
+        assert!(inspected.path.is_absolute());
+    }
+
+    #[test]
+    fn reads_a_wheel() {
+        if !have("python") {
+            return;
+        }
+        let inspected = inspect(&SystemRunner, &fixture("hello-1.0-py3-none-any.whl")).unwrap();
+        assert_eq!(inspected.kind, Kind::Wheel);
+        assert_eq!(field(&inspected, "Name"), Some("hello"));
+        assert_eq!(field(&inspected, "Version"), Some("1.0"));
+        assert_eq!(
+            field(&inspected, "Description"),
+            Some("A tiny wheel used as a test fixture.")
+        );
+        // In archive order
+        let names: Vec<&str> = inspected
+            .files
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "hello/__init__.py",
+                "hello-1.0.dist-info/RECORD",
+                "hello-1.0.dist-info/METADATA",
+                "hello-1.0.dist-info/WHEEL",
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_debs_say_why() {
+        if !have("dpkg-deb") {
+            return;
+        }
+        let err = fails("not-a-deb_1.0_all.deb");
+        assert!(err.ends_with("is not a Debian format archive"), "{}", err);
+        assert!(!err.starts_with("dpkg-deb: error:"), "{}", err);
+        let err = fails("truncated_1.0_all.deb");
+        assert!(err.contains("unexpected end of file"), "{}", err);
+    }
+
+    #[test]
+    fn broken_wheels_say_why() {
+        if !have("python") {
+            return;
+        }
+        let err = fails("truncated-1.0-py3-none-any.whl");
+        assert!(err.starts_with("not a zip archive"), "{}", err);
+        let err = fails("no-metadata-1.0-py3-none-any.whl");
+        assert_eq!(err, "no .dist-info/METADATA in it, so not a wheel");
+    }
+
+    #[test]
+    fn other_paths_are_refused_before_running_anything() {
+        let err = fails("apt-list.txt");
+        assert!(
+            err.ends_with("apt-list.txt: expected a .deb or .whl file"),
+            "{}",
+            err
+        );
+        let err = fails("missing_1.0_all.deb");
+        assert!(err.contains("missing_1.0_all.deb: No such file"), "{}", err);
+    }
+
+    #[test]
+    fn contents_listing() {
+        let listing = "\
+drwxr-xr-x root/root         0 2024-05-01 10:00 ./
+drwxr-xr-x root/root         0 2024-05-01 10:00 ./data/data/com.termux/files/usr/bin/
+-rwxr-xr-x root/root     12345 2024-05-01 10:00 ./data/data/com.termux/files/usr/bin/hello
+lrwxrwxrwx root/root         0 2024-05-01 10:00 ./data/data/com.termux/files/usr/bin/hi -> hello
+-rw-r--r-- root/root        22 2024-05-01 10:00 ./data/data/com.termux/files/usr/share/a b.txt
+";
+        assert_eq!(
+            parse_contents(listing),
+            [
+                (
+                    "/data/data/com.termux/files/usr/bin/hello".to_string(),
+                    Some(12345)
+                ),
+                (
+                    "/data/data/com.termux/files/usr/bin/hi -> hello".to_string(),
+                    Some(0)
+                ),
+                (
+                    "/data/data/com.termux/files/usr/share/a b.txt".to_string(),
+                    Some(22)
+                ),
+            ]
+        );
+        assert!(parse_contents("").is_empty());
+    }
+
+    #[test]
+    fn wheel_script_output() {
+        let output = "Name: hello\nVersion: 1.0\n\nLong text.\n\n More.\n\n\0\n10 hello/__init__.py\nx odd line\n";
+        let (details, files) = parse_wheel(output);
+        assert_eq!(details.get("Name"), Some("hello"));
+        assert_eq!(details.get("Description"), Some("Long text.\n\n More."));
+        assert_eq!(
+            files,
+            [
+                ("hello/__init__.py".to_string(), Some(10)),
+                ("odd line".to_string(), None),
+            ]
+        );
+        // Without the separator, all of it is METADATA
+        let (details, files) = parse_wheel("Name: hello\nVersion: 1.0\n");
+        assert_eq!(details.get("Version"), Some("1.0"));
+        assert!(files.is_empty());
+    }
+}
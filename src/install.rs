@@ -0,0 +1,183 @@
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::commands;
+use crate::{i18n, PackageManager};
+
+// Continuation lines of a wrapped command start with this.
+const INDENT: &str = "  ";
+
+// `I`: commands that install the marked packages somewhere else, for pasting
+// into a chat or another device's shell. Nothing is run here.
+pub struct InstallCommands {
+    packages: Vec<(PackageManager, String, Option<String>)>, // With the installed version, when known
+    pub versions: bool, // Pin each package to its installed version
+    width: usize,       // Wrap with backslashes past this; 0 never wraps
+}
+
+impl InstallCommands {
+    pub fn new(mut packages: Vec<(PackageManager, String, Option<String>)>, width: usize) -> Self {
+        packages.sort_by_key(|(manager, name, _)| {
+            let order = PackageManager::ALL.iter().position(|m| m == manager);
+            (order, name.clone())
+        });
+        Self {
+            packages,
+            versions: false,
+            width,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.packages.len()
+    }
+
+    // One command per manager, in Tab order; luarocks installs one rock per
+    // invocation, so it gets one per rock.
+    pub fn commands(&self) -> Vec<String> {
+        let mut commands = Vec::new();
+        for manager in PackageManager::ALL {
+            let packages: Vec<(&str, Option<&str>)> = self
+                .packages
+                .iter()
+                .filter(|(m, _, _)| *m == manager)
+                .map(|(_, name, version)| {
+                    let version = version.as_deref().filter(|_| self.versions);
+                    (name.as_str(), version)
+                })
+                .collect();
+            if packages.is_empty() {
+                continue;
+            }
+            if manager == PackageManager::Luarocks {
+                for (name, version) in packages {
+                    let mut words = words(&["luarocks", "install", name]);
+                    words.extend(version.map(String::from));
+                    commands.push(wrap(&words, self.width));
+                }
+                continue;
+            }
+            let mut words = words(program(manager));
+            words.extend(
+                packages
+                    .into_iter()
+                    .map(|(name, version)| pinned(manager, name, version)),
+            );
+            commands.push(wrap(&words, self.width));
+        }
+        commands
+    }
+
+    // E.g. "2 install commands for 5 packages", for the status bar.
+    pub fn summary(&self) -> String {
+        let key = match (self.commands().len(), self.count()) {
+            (1, 1) => "message.install_commands_one",
+            (1, _) => "message.install_command",
+            (_, 1) => "message.install_commands_for_one",
+            _ => "message.install_commands",
+        };
+        i18n::fill(
+            key,
+            &[
+                ("commands", &self.commands().len()),
+                ("count", &self.count()),
+            ],
+        )
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let check = if self.versions { "[x]" } else { "[ ]" };
+        let mut lines = vec![
+            Line::from(vec![
+                Span::raw(format!("{} With versions  ", check)),
+                Span::styled("v", dim),
+            ]),
+            Line::from(""),
+        ];
+        for command in self.commands() {
+            lines.extend(command.lines().map(|line| Line::from(line.to_string())));
+            lines.push(Line::from(""));
+        }
+        let unknown = self
+            .packages
+            .iter()
+            .filter(|(_, _, version)| version.is_none())
+            .count();
+        if self.versions && unknown > 0 {
+            lines.push(Line::styled(
+                format!("{} not installed any more, left unpinned", unknown),
+                dim,
+            ));
+        }
+        let popup = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "Install {} package{} (y to copy, Esc to close)",
+                        self.count(),
+                        if self.count() == 1 { "" } else { "s" }
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+}
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+// Each manager's install command, as bootstrap scripts use them but without
+// -y, since the command is read before it is run.
+fn program(manager: PackageManager) -> &'static [&'static str] {
+    match manager {
+        PackageManager::Pkg => &["pkg", "install"],
+        PackageManager::Apt => &["apt", "install"],
+        PackageManager::Pip => &["pip", "install"],
+        PackageManager::Luarocks => &["luarocks", "install"],
+        PackageManager::Composer => &["composer", "global", "require"],
+        PackageManager::Opam => &["opam", "install"],
+        PackageManager::Cargo => &["cargo", "install"],
+        PackageManager::Npm => &["npm", "install", "-g"],
+    }
+}
+
+// The package as its manager's install command takes a version.
+fn pinned(manager: PackageManager, name: &str, version: Option<&str>) -> String {
+    let Some(version) = version.filter(|version| !version.is_empty()) else {
+        return name.to_string();
+    };
+    match manager {
+        PackageManager::Pkg | PackageManager::Apt => format!("{}={}", name, version),
+        PackageManager::Pip => format!("{}=={}", name, version),
+        PackageManager::Composer => format!("{}:{}", name, version),
+        PackageManager::Opam => format!("{}.{}", name, version),
+        PackageManager::Cargo | PackageManager::Npm => format!("{}@{}", name, version),
+        PackageManager::Luarocks => name.to_string(), // Version is its own word
+    }
+}
+
+// Quoted words joined with spaces, broken with a backslash before a line
+// would go past `width` (counting the " \" that ends it).
+fn wrap(words: &[String], width: usize) -> String {
+    let mut lines: Vec<String> = vec![String::new()];
+    for word in words {
+        let word = commands::join_words(std::slice::from_ref(word));
+        let line = lines.last_mut().expect("starts with a line");
+        let started = !line.trim().is_empty();
+        if width > 0 && started && line.chars().count() + 1 + word.chars().count() + 2 > width {
+            lines.push(format!("{}{}", INDENT, word));
+        } else {
+            if started {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+    }
+    lines.join(" \\\n")
+}
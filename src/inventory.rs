@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::schema;
+use crate::{Package, PackageManager};
+
+// Where `e` offers to write unless another path is typed.
+pub const DEFAULT_PATH: &str = "tpi-packages.json";
+
+// The package inventory as a file for asset tracking: name, version and
+// manager of each package, in the format the file's extension asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("json") => Ok(Format::Json),
+            Some("csv") => Ok(Format::Csv),
+            _ => Err(format!("{}: the name must end in .json or .csv", path)),
+        }
+    }
+}
+
+pub fn render(format: Format, packages: &[(PackageManager, &Package)]) -> String {
+    match format {
+        Format::Json => {
+            let mut text =
+                serde_json::to_string_pretty(&schema::inventory(packages)).unwrap_or_default();
+            text.push('\n');
+            text
+        }
+        Format::Csv => {
+            let mut text = String::from("name,version,manager\n");
+            for (manager, pkg) in packages {
+                text.push_str(&format!(
+                    "{},{},{}\n",
+                    csv_field(&pkg.name),
+                    csv_field(&pkg.version),
+                    manager.name()
+                ));
+            }
+            text
+        }
+    }
+}
+
+// Quoted as RFC 4180 asks, only when it has to be.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Writes the inventory and returns the absolute path written, or what went
+// wrong as a sentence for the status line.
+pub fn write(path: &str, packages: &[(PackageManager, &Package)]) -> Result<PathBuf, String> {
+    let format = Format::from_path(path)?;
+    fs::write(path, render(format, packages))
+        .map_err(|err| format!("Couldn't write {}: {}", path, err))?;
+    Ok(fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)))
+}
@@ -0,0 +1,193 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::menu;
+
+// Main-list actions whose keys the config file can change. Every other key
+// stays where it is; a key bound here takes over whatever it did before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Quit,
+    Next,
+    Previous,
+    First,
+    Last,
+    SwitchManager,
+    PreviousManager,
+}
+
+impl Binding {
+    pub const ALL: [Binding; 7] = [
+        Binding::Quit,
+        Binding::Next,
+        Binding::Previous,
+        Binding::First,
+        Binding::Last,
+        Binding::SwitchManager,
+        Binding::PreviousManager,
+    ];
+
+    // As written under [keys] in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Binding::Quit => "quit",
+            Binding::Next => "next",
+            Binding::Previous => "previous",
+            Binding::First => "first",
+            Binding::Last => "last",
+            Binding::SwitchManager => "switch_manager",
+            Binding::PreviousManager => "previous_manager",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|binding| binding.name() == name)
+    }
+
+    fn defaults(self) -> &'static [&'static str] {
+        match self {
+            Binding::Quit => &["q", "Esc"],
+            Binding::Next => &["j", "Down"],
+            Binding::Previous => &["k", "Up"],
+            Binding::First => &["g", "Home"],
+            Binding::Last => &["G", "End"],
+            Binding::SwitchManager => &["Tab"],
+            Binding::PreviousManager => &["BackTab"],
+        }
+    }
+}
+
+// A key with the modifiers that matter: Ctrl and Alt. Shift is part of the
+// character itself ("G").
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        event.code == self.code && modifiers == self.modifiers
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt+");
+        }
+        label.push_str(&menu::key_label(self.code));
+        label
+    }
+}
+
+// "q", "G", "Esc", "Shift+Tab", "PageDown", "F5", "Ctrl+d", "Alt+n". Names other than
+// single characters are case-insensitive.
+pub fn parse_key(text: &str) -> Result<Key, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        if lower.starts_with("ctrl+") && rest.len() > 5 {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = &rest[5..];
+        } else if lower.starts_with("alt+") && rest.len() > 4 {
+            modifiers |= KeyModifiers::ALT;
+            rest = &rest[4..];
+        } else {
+            break;
+        }
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backtab" | "shift+tab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("\"{}\" is not a key", text)),
+            },
+        },
+    };
+    Ok(Key { code, modifiers })
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    keys: Vec<(Binding, Key)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = Binding::ALL
+            .into_iter()
+            .flat_map(|binding| {
+                binding.defaults().iter().map(move |name| {
+                    let key = parse_key(name).expect("default keys parse");
+                    (binding, key)
+                })
+            })
+            .collect();
+        Self { keys }
+    }
+}
+
+impl Keymap {
+    // The defaults with the configured bindings' keys replaced. A default
+    // key that a configured binding takes is dropped from its old binding, so
+    // swapping two keys only needs both written down; two configured
+    // bindings can't share a key.
+    pub fn with(configured: &[(Binding, Vec<Key>)]) -> Result<Self, String> {
+        let mut keys: Vec<(Binding, Key)> = Vec::new();
+        for (binding, bound) in configured {
+            for key in bound {
+                if let Some((other, _)) = keys.iter().find(|(_, taken)| taken == key) {
+                    return Err(format!("{} is also bound to {}", key.label(), other.name()));
+                }
+                keys.push((*binding, *key));
+            }
+        }
+        for (binding, key) in Self::default().keys {
+            let configured = configured.iter().any(|(bound, _)| *bound == binding);
+            let taken = keys.iter().any(|(_, taken)| *taken == key);
+            if !configured && !taken {
+                keys.push((binding, key));
+            }
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn lookup(&self, event: &KeyEvent) -> Option<Binding> {
+        self.keys
+            .iter()
+            .find(|(_, key)| key.matches(event))
+            .map(|(binding, _)| *binding)
+    }
+
+    // E.g. "q/Esc", for the help.
+    pub fn label(&self, binding: Binding) -> String {
+        self.keys
+            .iter()
+            .filter(|(bound, _)| *bound == binding)
+            .map(|(_, key)| key.label())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
@@ -0,0 +1,51 @@
+use ratatui::prelude::*;
+
+// Fewer rows than this and a detail pane below the list would be a sliver:
+// the list takes the whole screen and the details are a page of their own.
+pub const COMPACT_HEIGHT: u16 = 20;
+// This many columns or more and the details go beside the list.
+pub const SIDE_BY_SIDE_WIDTH: u16 = 140;
+
+// How the list and the detail pane share the screen, from its size at the
+// last draw.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Arrangement {
+    Stacked,    // Details below the list
+    SideBySide, // Details to the right of it
+    Compact,    // One at a time; Enter and Esc go between them
+}
+
+impl Arrangement {
+    pub fn for_screen(screen: Rect) -> Self {
+        if screen.height < COMPACT_HEIGHT {
+            Self::Compact
+        } else if screen.width >= SIDE_BY_SIDE_WIDTH {
+            Self::SideBySide
+        } else {
+            Self::Stacked
+        }
+    }
+}
+
+// The list's area and the detail pane's. `share` is the detail pane's
+// percentage of the height (the `+`/`-` setting); beside the list it gets 20
+// points more of the width, so the default split is even. In compact mode
+// whichever isn't shown gets an empty area at the top.
+pub fn split(area: Rect, arrangement: Arrangement, share: u16, page: bool) -> (Rect, Rect) {
+    let (direction, share) = match arrangement {
+        Arrangement::Compact => {
+            let hidden = Rect { height: 0, ..area };
+            return if page { (hidden, area) } else { (area, hidden) };
+        }
+        Arrangement::Stacked => (Direction::Vertical, share),
+        Arrangement::SideBySide => (Direction::Horizontal, (share + 20).min(80)),
+    };
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints([
+            Constraint::Percentage(100 - share),
+            Constraint::Percentage(share),
+        ])
+        .split(area);
+    (chunks[0], chunks[1])
+}
@@ -0,0 +1,68 @@
+// Package types and the parsers for each manager's list output, kept apart
+// from the TUI so they don't depend on the terminal, the environment or
+// running anything.
+pub mod parsers;
+
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub arch: Option<String>, // dpkg architecture, e.g. aarch64 or all
+    pub repo: Option<String>, // apt suite (stable, x11), luarocks tree (system, user), composer vendor, npm scope or cargo source (git, path)
+    pub description: Option<String>, // Shown as a second line in the list when the manager reports one
+    pub priority: Option<String>, // dpkg Priority: required, important, standard, optional, extra
+    pub section: Option<String>,  // dpkg Section: libs, net, editors, maybe "contrib/net"
+    pub auto_installed: bool,     // apt installed it as a dependency ("[installed,automatic]")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackageManager {
+    Pkg,
+    Apt,
+    Pip,
+    Luarocks,
+    Composer,
+    Opam,
+    Cargo,
+    Npm,
+}
+
+impl PackageManager {
+    pub const ALL: [PackageManager; 8] = [
+        PackageManager::Pkg,
+        PackageManager::Apt,
+        PackageManager::Pip,
+        PackageManager::Luarocks,
+        PackageManager::Composer,
+        PackageManager::Opam,
+        PackageManager::Cargo,
+        PackageManager::Npm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackageManager::Pkg => "pkg",
+            PackageManager::Apt => "apt",
+            PackageManager::Pip => "pip",
+            PackageManager::Luarocks => "luarocks",
+            PackageManager::Composer => "composer",
+            PackageManager::Opam => "opam",
+            PackageManager::Cargo => "cargo",
+            PackageManager::Npm => "npm",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pkg" => Some(PackageManager::Pkg),
+            "apt" => Some(PackageManager::Apt),
+            "pip" => Some(PackageManager::Pip),
+            "luarocks" => Some(PackageManager::Luarocks),
+            "composer" => Some(PackageManager::Composer),
+            "opam" => Some(PackageManager::Opam),
+            "cargo" => Some(PackageManager::Cargo),
+            "npm" => Some(PackageManager::Npm),
+            _ => None,
+        }
+    }
+}
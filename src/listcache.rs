@@ -0,0 +1,223 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::{config, lock, log, schema, Package, PackageManager};
+
+// The last list of each manager on disk, shown while the real one loads so
+// tpi starts (and Tab switches) with something on screen. It is only ever an
+// optimization: a cache that can't be read is a miss, never an error.
+
+// $TPI_CACHE_DIR, or $XDG_CACHE_HOME/tpi (~/.cache by default).
+pub fn dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("TPI_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".cache")))?;
+    Some(cache.join("tpi"))
+}
+
+fn path(manager: PackageManager) -> Option<PathBuf> {
+    Some(dir()?.join(format!("list-{}.json", manager.name())))
+}
+
+// The cached packages, or None on a miss. A file that doesn't parse, as a
+// power loss mid-write on flaky storage can leave it, is logged and moved
+// aside to `<file>.bad` so it is looked at once rather than on every start.
+pub fn load(manager: PackageManager) -> Option<Vec<Package>> {
+    let path = path(manager)?;
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            log::debug(&format!("list cache {}: {}", path.display(), err));
+            return None;
+        }
+    };
+    let parsed = String::from_utf8(bytes)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse(manager, &text));
+    match parsed {
+        Ok(Some(packages)) => Some(packages),
+        Ok(None) => None, // Written by another version; the next save replaces it
+        Err(err) => {
+            quarantine(&path, &err);
+            None
+        }
+    }
+}
+
+// Saved after every successful listing; failures only go to the log.
+pub fn save(manager: PackageManager, packages: &[Package]) {
+    let Some(path) = path(manager) else {
+        return;
+    };
+    let text = serde_json::to_string(&schema::list_cache(manager, packages)).unwrap_or_default();
+    if let Err(err) = lock::write_atomic(&path, &text) {
+        log::debug(&format!(
+            "list cache {}: not saved: {}",
+            path.display(),
+            err
+        ));
+    }
+}
+
+// Ok(None) for a readable cache of another schema version.
+fn parse(manager: PackageManager, text: &str) -> Result<Option<Vec<Package>>, String> {
+    let json: Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+    let version = json
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .ok_or("no schema_version")?;
+    if version != schema::VERSION {
+        return Ok(None);
+    }
+    if json.get("manager").and_then(Value::as_str) != Some(manager.name()) {
+        return Err("cached for another manager".to_string());
+    }
+    json.get("packages")
+        .and_then(Value::as_array)
+        .ok_or("no \"packages\" list")?
+        .iter()
+        .map(|entry| {
+            let text = |key: &str| entry.get(key).and_then(Value::as_str).map(String::from);
+            Ok(Package {
+                name: text("name").ok_or("a package without a name")?,
+                version: text("version").ok_or("a package without a version")?,
+                arch: text("arch"),
+                repo: text("repo"),
+                description: text("description"),
+                priority: text("priority"),
+                section: text("section"),
+                auto_installed: entry.get("auto_installed").and_then(Value::as_bool) == Some(true),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(Some)
+}
+
+fn quarantine(path: &Path, err: &str) {
+    let mut bad = path.as_os_str().to_os_string();
+    bad.push(".bad");
+    let outcome = match fs::rename(path, &bad) {
+        Ok(()) => format!("moved to {}", Path::new(&bad).display()),
+        Err(_) => match fs::remove_file(path) {
+            Ok(()) => "deleted".to_string(),
+            Err(err) => format!("couldn't remove it: {}", err),
+        },
+    };
+    log::debug(&format!(
+        "list cache {} unreadable ({}); {}",
+        path.display(),
+        err,
+        outcome
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The app's tests list apt and pip through the same cache directory,
+    // so these use managers they don't.
+    fn cache_file(manager: PackageManager) -> PathBuf {
+        crate::tests::sandbox();
+        let path = path(manager).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        path
+    }
+
+    fn bad_file(path: &Path) -> PathBuf {
+        let mut bad = path.as_os_str().to_os_string();
+        bad.push(".bad");
+        PathBuf::from(bad)
+    }
+
+    fn packages() -> Vec<Package> {
+        ["busted", "luafilesystem", "penlight"]
+            .iter()
+            .map(|name| Package {
+                name: name.to_string(),
+                version: "1.0-1".to_string(),
+                arch: None,
+                repo: Some("user".to_string()),
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: false,
+            })
+            .collect()
+    }
+
+    // Writes `contents` as the cache, loads it, and returns what was
+    // loaded and whether the file was moved aside.
+    fn load_from(manager: PackageManager, contents: &[u8]) -> (Option<Vec<Package>>, bool) {
+        let path = cache_file(manager);
+        let _ = fs::remove_file(bad_file(&path));
+        fs::write(&path, contents).unwrap();
+        let loaded = load(manager);
+        let moved = bad_file(&path).exists();
+        assert_eq!(path.exists(), !moved);
+        (loaded, moved)
+    }
+
+    #[test]
+    fn saved_list_loads_back() {
+        let manager = PackageManager::Luarocks;
+        cache_file(manager);
+        save(manager, &packages());
+        let loaded = load(manager).unwrap();
+        let names: Vec<&str> = loaded.iter().map(|pkg| pkg.name.as_str()).collect();
+        assert_eq!(names, ["busted", "luafilesystem", "penlight"]);
+        assert_eq!(loaded[0].repo.as_deref(), Some("user"));
+    }
+
+    #[test]
+    fn unreadable_caches_are_misses_and_moved_aside() {
+        let manager = PackageManager::Opam;
+        let whole = serde_json::to_string(&schema::list_cache(manager, &packages())).unwrap();
+        let cases: [(&str, Vec<u8>); 6] = [
+            ("truncated", whole.as_bytes()[..whole.len() / 2].to_vec()),
+            ("empty", Vec::new()),
+            ("garbage", b"\x00\x9f\x92\x96\xff binary junk".to_vec()),
+            ("not json", b"list-opam.json".to_vec()),
+            (
+                "no packages",
+                format!(
+                    r#"{{"schema_version":{},"manager":"opam"}}"#,
+                    schema::VERSION
+                )
+                .into_bytes(),
+            ),
+            (
+                "another manager's",
+                serde_json::to_vec(&schema::list_cache(PackageManager::Cargo, &packages()))
+                    .unwrap(),
+            ),
+        ];
+        for (case, contents) in cases {
+            let (loaded, moved) = load_from(manager, &contents);
+            assert!(loaded.is_none(), "{}", case);
+            assert!(moved, "{} should be moved aside", case);
+            // And the next start is a plain miss
+            assert!(load(manager).is_none(), "{}", case);
+        }
+    }
+
+    #[test]
+    fn other_schema_version_is_a_miss_left_in_place() {
+        let manager = PackageManager::Cargo;
+        let text = format!(
+            r#"{{"schema_version":{},"manager":"cargo","packages":[]}}"#,
+            schema::VERSION + 1
+        );
+        let (loaded, moved) = load_from(manager, text.as_bytes());
+        assert!(loaded.is_none());
+        assert!(!moved);
+    }
+}
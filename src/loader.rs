@@ -0,0 +1,147 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::failures::LoadError;
+use crate::{PackageList, PackageManager};
+
+// A list read in the background, with why any manager tried before it
+// failed (only at startup, where several may be tried).
+pub type Loaded = (PackageList, Vec<LoadError>);
+
+// Reads package lists on a background thread so the UI keeps drawing and
+// taking keys while a manager lists, which for apt and pip takes seconds.
+// Only the latest load counts: pressing Tab again before a list arrives
+// drops the earlier one when it does.
+pub struct Loader {
+    sender: Sender<(u64, Loaded)>,
+    receiver: Receiver<(u64, Loaded)>,
+    latest: u64,
+    pending: Option<PackageManager>, // Being listed
+}
+
+impl Loader {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            latest: 0,
+            pending: None,
+        }
+    }
+
+    // Threads aren't joined: one still listing when tpi quits only runs a
+    // read-only command, and quitting shouldn't wait for it.
+    pub fn start(
+        &mut self,
+        manager: PackageManager,
+        load: impl FnOnce() -> Loaded + Send + 'static,
+    ) {
+        self.latest += 1;
+        self.pending = Some(manager);
+        let id = self.latest;
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send((id, load()));
+        });
+    }
+
+    // For a list loaded some other way meanwhile: whatever arrives is dropped.
+    pub fn cancel(&mut self) {
+        self.latest += 1;
+        self.pending = None;
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // The latest list, once it has arrived.
+    pub fn poll(&mut self) -> Option<Loaded> {
+        let mut arrived = None;
+        while let Ok((id, loaded)) = self.receiver.try_recv() {
+            if id == self.latest {
+                arrived = Some(loaded);
+            }
+        }
+        if arrived.is_some() {
+            self.pending = None;
+        }
+        arrived
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::SyncSender;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::tests::Mock;
+    use crate::Listing;
+
+    // Starts loading `manager`'s (empty) list, held back until the returned
+    // sender is used.
+    fn start_held(loader: &mut Loader, manager: PackageManager) -> SyncSender<()> {
+        let (release, released) = mpsc::sync_channel(0);
+        loader.start(manager, move || {
+            released.recv().unwrap();
+            let list =
+                PackageList::with_listing(manager, Arc::new(Mock::new()), Listing::default(), None);
+            (list, Vec::new())
+        });
+        release
+    }
+
+    // Polls for a while, as the event loop would.
+    fn poll_for(loader: &mut Loader) -> Option<PackageManager> {
+        let began = Instant::now();
+        while began.elapsed() < Duration::from_millis(300) {
+            if let Some((list, _)) = loader.poll() {
+                return Some(list.package_manager);
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        None
+    }
+
+    #[test]
+    fn only_the_latest_counts_when_they_arrive_in_reverse() {
+        let mut loader = Loader::new();
+        let apt = start_held(&mut loader, PackageManager::Apt);
+        let pip = start_held(&mut loader, PackageManager::Pip);
+        assert!(loader.is_loading());
+
+        pip.send(()).unwrap();
+        assert_eq!(poll_for(&mut loader), Some(PackageManager::Pip));
+        assert!(!loader.is_loading());
+        // The earlier one, arriving late, is dropped
+        apt.send(()).unwrap();
+        assert_eq!(poll_for(&mut loader), None);
+        assert!(!loader.is_loading());
+    }
+
+    #[test]
+    fn an_earlier_one_arriving_first_is_dropped() {
+        let mut loader = Loader::new();
+        let apt = start_held(&mut loader, PackageManager::Apt);
+        let pip = start_held(&mut loader, PackageManager::Pip);
+
+        apt.send(()).unwrap();
+        assert_eq!(poll_for(&mut loader), None);
+        assert!(loader.is_loading());
+        pip.send(()).unwrap();
+        assert_eq!(poll_for(&mut loader), Some(PackageManager::Pip));
+    }
+
+    #[test]
+    fn cancelled_loads_are_dropped() {
+        let mut loader = Loader::new();
+        let apt = start_held(&mut loader, PackageManager::Apt);
+        loader.cancel();
+        assert!(!loader.is_loading());
+        apt.send(()).unwrap();
+        assert_eq!(poll_for(&mut loader), None);
+    }
+}
@@ -0,0 +1,323 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::log;
+
+// Only one tpi at a time writes the state files (the version history,
+// sessions, hidden packages, the pip do-not-upgrade list and the cached
+// package lists). The first instance creates `tpi.lock` with its PID; one
+// started while it runs still reads the files but saves nothing, until the
+// holder exits. A lock whose PID is gone was left by a crash and is taken
+// over.
+
+static HOLDER: AtomicU32 = AtomicU32::new(0); // PID of the other instance; 0 when we may write
+static HELD: Mutex<Option<PathBuf>> = Mutex::new(None); // Our lock file, removed on exit
+static FORBIDDEN: AtomicBool = AtomicBool::new(false); // Set by processes that must not write
+static TEMPS: AtomicU64 = AtomicU64::new(0); // Numbers temporary files, one per call
+
+// $TPI_LOCK, or $XDG_STATE_HOME/tpi/tpi.lock (~/.local/state by default).
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TPI_LOCK").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| crate::config::home().map(|home| home.join(".local/state")))?;
+    Some(state.join("tpi").join("tpi.lock"))
+}
+
+// Takes the lock for this process. Returns the PID of another live instance
+// that already has it. Without a location, or when the directory can't be
+// written, there is nothing to guard and saving fails on its own.
+pub fn acquire() -> Option<u32> {
+    let path = path()?;
+    for _ in 0..3 {
+        match create(&path) {
+            Ok(()) => {
+                if let Ok(mut held) = HELD.lock() {
+                    *held = Some(path);
+                }
+                HOLDER.store(0, Ordering::Relaxed);
+                return None;
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => match read_pid(&path) {
+                Some(pid) if pid != std::process::id() && alive(pid) => {
+                    HOLDER.store(pid, Ordering::Relaxed);
+                    return Some(pid);
+                }
+                pid => take_over(&path, pid),
+            },
+            Err(err) => {
+                log::debug(&format!("lock: {}: {}", path.display(), err));
+                HOLDER.store(0, Ordering::Relaxed);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+// Moves a stale lock out of the way. Another instance starting at the same
+// time may have judged it stale too and already replaced it with its own,
+// so what was moved is checked, and put back unless it is the stale one.
+fn take_over(path: &Path, stale: Option<u32>) {
+    log::debug(&format!("lock: taking over stale lock of PID {:?}", stale));
+    let aside = temp_path(path, "stale");
+    if fs::rename(path, &aside).is_err() {
+        return; // Gone already; creating it is tried again
+    }
+    let moved = read_pid(&aside);
+    if moved != stale && moved.is_some_and(alive) {
+        if let Err(err) = fs::hard_link(&aside, path) {
+            log::debug(&format!("lock: restoring PID {:?}'s lock: {}", moved, err));
+        }
+    }
+    let _ = fs::remove_file(&aside);
+}
+
+// A name beside `path` that no other call, in this process or another, uses.
+fn temp_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}.{}.{}",
+        std::process::id(),
+        TEMPS.fetch_add(1, Ordering::Relaxed),
+        suffix
+    ));
+    path.with_file_name(name)
+}
+
+// The PID goes into a temporary file that is then linked into place, so the
+// lock never exists without it: another instance reading an empty lock
+// would take it for one left by a crash.
+fn create(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = temp_path(path, "tmp");
+    let linked = fs::File::create(&temp)
+        .and_then(|mut file| writeln!(file, "{}", std::process::id()))
+        .and_then(|()| fs::hard_link(&temp, path));
+    let _ = fs::remove_file(&temp);
+    match linked {
+        // Shared storage has no hard links
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+            log::debug(&format!("lock: linking {}: {}", path.display(), err));
+            let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+            writeln!(file, "{}", std::process::id())
+        }
+        linked => linked,
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn alive(pid: u32) -> bool {
+    // Signal 0 only checks; EPERM means it exists but belongs to someone else
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    let found = unsafe { libc::kill(pid, 0) } == 0;
+    found || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn alive(_pid: u32) -> bool {
+    true
+}
+
+// The other instance that keeps this one from saving, if any. Once it has
+// exited the lock is taken over here.
+pub fn holder() -> Option<u32> {
+    match HOLDER.load(Ordering::Relaxed) {
+        0 => None,
+        pid if alive(pid) => Some(pid),
+        _ => acquire(),
+    }
+}
+
+// Keeps this process from writing any state file, e.g. `tpi serve`, which
+// promises to only read.
+pub fn forbid_writes() {
+    FORBIDDEN.store(true, Ordering::Relaxed);
+}
+
+// Ok when this instance may write the state files.
+pub fn writable() -> io::Result<()> {
+    if FORBIDDEN.load(Ordering::Relaxed) {
+        return Err(io::Error::other("read-only process, not saving"));
+    }
+    match holder() {
+        Some(pid) => Err(io::Error::other(format!(
+            "another tpi (PID {}) is running, not saving",
+            pid
+        ))),
+        None => Ok(()),
+    }
+}
+
+// Removes our lock file, unless it has been taken over meanwhile.
+pub fn release() {
+    let Some(path) = HELD.lock().ok().and_then(|mut held| held.take()) else {
+        return;
+    };
+    if read_pid(&path) == Some(std::process::id()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Replaces `path` with `text` through a temporary file in the same directory,
+// so a crash mid-write leaves the old contents rather than a truncated file.
+pub fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    writable()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = temp_path(path, "tmp");
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(text.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(err) = written.and_then(|()| fs::rename(&temp, path)) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    fn lock_path(test: &str) -> PathBuf {
+        let path = crate::tests::sandbox().join("locks").join(test);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn created_with_our_pid_once() {
+        let path = lock_path("once");
+        create(&path).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        let err = create(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        // Nothing left beside it
+        let dir = path.parent().unwrap();
+        let names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("once"))
+            .collect();
+        assert_eq!(names, ["once"]);
+    }
+
+    #[test]
+    fn one_of_many_at_once_takes_it() {
+        let path = lock_path("many");
+        let start = Arc::new(Barrier::new(8));
+        let created: Vec<bool> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                let start = start.clone();
+                thread::spawn(move || {
+                    start.wait();
+                    create(&path).is_ok()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+        assert_eq!(created.iter().filter(|created| **created).count(), 1);
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+    }
+
+    // The race that made a starting instance take over a live lock: it read
+    // the file between its creation and the PID being written.
+    #[test]
+    fn never_read_without_its_pid() {
+        let path = lock_path("race");
+        let done = Arc::new(AtomicBool::new(false));
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let path = path.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    while !done.load(Ordering::Relaxed) {
+                        // Even one removed meanwhile still has its PID
+                        if let Ok(text) = fs::read_to_string(&path) {
+                            assert_eq!(text.trim().parse().ok(), Some(std::process::id()));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..2000 {
+            create(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    // A PID no process has: beyond pid_t, so alive() rules it out at once
+    const DEAD: u32 = u32::MAX;
+
+    #[test]
+    fn stale_locks_are_taken_over_fresh_ones_kept() {
+        let path = lock_path("stale");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("{}\n", DEAD)).unwrap();
+        take_over(&path, Some(DEAD));
+        assert!(!path.exists());
+
+        // Judged stale, but replaced by a live instance before being moved
+        create(&path).unwrap();
+        take_over(&path, Some(DEAD));
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+        let names: Vec<_> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with("stale"))
+            .collect();
+        assert_eq!(names, ["stale"]);
+    }
+
+    #[test]
+    fn threads_write_one_file_at_once() {
+        let path = lock_path("state.tsv");
+        let start = Arc::new(Barrier::new(8));
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                let start = start.clone();
+                thread::spawn(move || {
+                    start.wait();
+                    for _ in 0..50 {
+                        write_atomic(&path, &format!("{}\n", i).repeat(1000)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        // One writer's text whole, never two interleaved or a truncated one
+        let text = fs::read_to_string(&path).unwrap();
+        let first = text.lines().next().unwrap();
+        assert_eq!(text, format!("{}\n", first).repeat(1000));
+    }
+}
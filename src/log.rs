@@ -0,0 +1,27 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Appends a timestamped line to the file named by $TPI_LOG; a no-op when unset.
+pub fn debug(message: &str) {
+    let Some(path) = std::env::var_os("TPI_LOG") else {
+        return;
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(
+            file,
+            "[{}.{:03}] {}",
+            now.as_secs(),
+            now.subsec_millis(),
+            message
+        );
+    }
+}
+
+pub fn path() -> Option<String> {
+    std::env::var("TPI_LOG").ok()
+}
@@ -1,338 +1,6772 @@
-use std::io::{self, stdout};
-use std::process::Command;
+mod backup;
+#[cfg(feature = "reports")]
+mod bootstrap;
+mod cli;
+mod clipboard;
+mod commands;
+mod config;
+mod configfile;
+mod conflicts;
+mod dashboard;
+mod deptree;
+mod details;
+mod doctor;
+mod dpkglog;
+mod drift;
+mod exports;
+mod extras;
+mod failures;
+mod filelists;
+mod focus;
+mod grouping;
+mod growth;
+mod health;
+mod help;
+mod hidden;
+mod history;
+mod holds;
+mod i18n;
+mod impact;
+mod inspect;
+mod install;
+mod inventory;
+mod keymap;
+mod layout;
+mod listcache;
+mod loader;
+mod lock;
+mod log;
+mod menu;
+mod names;
+mod noupgrade;
+mod orphans;
+mod outdated;
+mod pattern;
+mod pins;
+mod plan;
+mod prefetch;
+mod preview;
+mod python;
+mod rdepends;
+mod renderers;
+#[cfg(feature = "reports")]
+mod report;
+mod repos;
+mod rollback;
+mod root;
+mod schema;
+mod search;
+mod sections;
+mod serve;
+mod sessions;
+mod shadowing;
+mod showcache;
+mod sorting;
+mod spotlight;
+mod startup;
+mod stats;
+mod status;
+mod strays;
+mod tabs;
+mod termtitle;
+mod tour;
+mod undo;
+mod upgrades;
+mod usage;
+mod watch;
+mod wheels;
+mod which;
+mod why;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, stdout, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use commands::{Action, Runner, SystemRunner};
+use failures::LoadError;
+use focus::Focus;
+use grouping::{GroupBy, Row};
+use sorting::SortBy;
+use termux_pkg_inspector::parsers::{self, Listing, Rejection};
+use termux_pkg_inspector::{Package, PackageManager};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers, MouseButton, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        block, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
+    },
+    TerminalOptions, Viewport,
 };
 
 fn main() -> io::Result<()> {
+    let launched = Instant::now();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match cli::Options::parse(&mut args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args, &options));
+    }
+    if options.export.is_some() {
+        std::process::exit(cli::export(&options));
+    }
+    if let Some(reason) = cli::no_terminal() {
+        std::process::exit(cli::without_terminal(reason, &options));
+    }
+    let trace_file = if options.startup_trace {
+        startup::trace(launched)
+    } else {
+        None
+    };
+
     enable_raw_mode()?;
+    restore_on_panic(options.no_alt_screen);
     let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    let viewport = if options.no_alt_screen {
+        execute!(stdout, EnableMouseCapture)?;
+        Viewport::Inline(config::inline_height())
+    } else {
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        Viewport::Fullscreen
+    };
 
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = Terminal::with_options(backend, TerminalOptions { viewport })?;
+    startup::step("terminal setup", launched);
+    // Before anything is read from $PREFIX or $HOME
+    let drawing = Instant::now();
+    terminal.draw(startup::first_frame)?;
+    startup::step("first frame", drawing);
 
-    let mut app = App::new();
+    // Before the first list is loaded, since that records the version history
+    let shared = lock::acquire();
+    let mut app = App::new(&options);
+    if let Some(pid) = shared {
+        app.message = Some(i18n::fill("message.not_saving", &[("pid", &pid)]));
+    }
     let res = app.run(&mut terminal);
+    lock::release();
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    if options.no_alt_screen {
+        // The last frame stays on screen; the prompt goes below it
+        let area = terminal.get_frame().size();
+        terminal.set_cursor(0, area.bottom().saturating_sub(1))?;
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+        terminal.show_cursor()?;
+        println!();
+    } else {
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        )?;
+        terminal.show_cursor()?;
+    }
+    termtitle::restore();
+    if let Some(path) = trace_file {
+        println!("Startup trace written to {}", path);
+    }
 
     res
 }
 
-#[derive(Debug)]
-struct Package {
-    name: String,
-    version: String,
+// A panic anywhere in the TUI would otherwise leave the shell in raw mode on
+// the alternate screen, with the message lost on it. The terminal is handed
+// back first, then the usual panic message is printed.
+fn restore_on_panic(no_alt_screen: bool) {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        if no_alt_screen {
+            let _ = execute!(stdout(), DisableMouseCapture, cursor::Show);
+            println!();
+        } else {
+            let _ = execute!(
+                stdout(),
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                cursor::Show
+            );
+        }
+        termtitle::restore();
+        lock::release();
+        default(info);
+    }));
+}
+
+// Hands the terminal back to the shell and stops like any job sent SIGTSTP.
+// `raise` returns once `fg` continues us; the screen is then taken over again
+// and cleared, so the next draw repaints everything the shell left behind.
+#[cfg(unix)]
+fn suspend<B: Backend>(terminal: &mut Terminal<B>, alt_screen: bool) -> io::Result<()> {
+    release_terminal(terminal, alt_screen)?;
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    retake_terminal(terminal, alt_screen)
+}
+
+// Leaves the screen as the shell had it, for suspending or a command run in
+// the foreground.
+fn release_terminal<B: Backend>(terminal: &mut Terminal<B>, alt_screen: bool) -> io::Result<()> {
+    disable_raw_mode()?;
+    if alt_screen {
+        execute!(stdout(), LeaveAlternateScreen)?;
+    }
+    execute!(stdout(), DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    termtitle::restore();
+    Ok(())
+}
+
+fn retake_terminal<B: Backend>(terminal: &mut Terminal<B>, alt_screen: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    if alt_screen {
+        execute!(stdout(), EnterAlternateScreen)?;
+    }
+    execute!(stdout(), EnableMouseCapture)?;
+    terminal.hide_cursor()?;
+    terminal.clear()
+}
+
+// Runs an install or removal on the real terminal, so apt's prompts and
+// progress show as in the shell, and waits for Enter before tpi comes back
+// so its output can be read. The outer error is the terminal's.
+fn run_in_foreground<B: Backend>(
+    terminal: &mut Terminal<B>,
+    alt_screen: bool,
+    words: &[String],
+) -> io::Result<Result<std::process::ExitStatus, String>> {
+    release_terminal(terminal, alt_screen)?;
+    println!("$ {}", commands::join_words(words));
+    let status = Command::new(&words[0])
+        .args(&words[1..])
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|err| format!("{}: {}", words[0], err));
+    print!("\nPress Enter to go back to tpi ");
+    io::stdout().flush()?;
+    io::stdin().read_line(&mut String::new())?;
+    retake_terminal(terminal, alt_screen)?;
+    Ok(status)
+}
+
+// No job control elsewhere; Ctrl+Z is ignored.
+#[cfg(not(unix))]
+fn suspend<B: Backend>(_terminal: &mut Terminal<B>, _alt_screen: bool) -> io::Result<()> {
+    Ok(())
+}
+
+const DIAGNOSTIC_REJECTIONS: usize = 20; // Shown in the popup; `y` copies all
+
+// A list command's output as packages, with luarocks trees labelled.
+fn parse_package_list(package_manager: PackageManager, stdout: &str) -> Result<Listing, String> {
+    let mut listing = parsers::parse_package_list(package_manager, stdout)?;
+    if package_manager == PackageManager::Luarocks {
+        for pkg in &mut listing.packages {
+            pkg.repo = pkg.repo.as_deref().map(luarocks_tree_label);
+        }
+    }
+    Ok(listing)
+}
+
+// Termux installation prefix, e.g. /data/data/com.termux/files/usr.
+fn prefix() -> String {
+    std::env::var("PREFIX").unwrap_or_else(|_| "/data/data/com.termux/files/usr".to_string())
+}
+
+// Seconds since the epoch as a UTC date, YYYY-MM-DD.
+fn format_date(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Kibibytes as dpkg counts them, in the largest fitting unit.
+fn format_size(kib: u64) -> String {
+    match kib {
+        0..=1023 => format!("{} KiB", kib),
+        1024..=1_048_575 => format!("{:.1} MiB", kib as f64 / 1024.0),
+        _ => format!("{:.1} GiB", kib as f64 / 1_048_576.0),
+    }
+}
+
+// Seconds since the epoch as a UTC time of day, HH:MM:SS.
+fn format_time(secs: u64) -> String {
+    let day = secs % 86_400;
+    format!("{:02}:{:02}:{:02}", day / 3600, day / 60 % 60, day % 60)
+}
+
+// How long ago, roughly: "just now", "5m ago", "3h ago", "2d ago".
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=59 => i18n::text("age.now").to_string(),
+        60..=3599 => i18n::fill("age.minutes", &[("count", &(secs / 60))]),
+        3600..=86_399 => i18n::fill("age.hours", &[("count", &(secs / 3600))]),
+        _ => i18n::fill("age.days", &[("count", &(secs / 86_400))]),
+    }
+}
+
+// A proleptic Gregorian date to days since 1970-01-01.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Stanzas of dpkg's status file for the packages that are installed.
+fn installed_stanzas() -> Option<Vec<details::Details>> {
+    let status = fs::read_to_string(format!("{}/var/lib/dpkg/status", prefix())).ok()?;
+    Some(
+        status
+            .split("\n\n")
+            .map(details::Details::parse)
+            .filter(|details| {
+                details
+                    .get("Status")
+                    .is_some_and(|status| status.ends_with(" installed"))
+            })
+            .collect(),
+    )
+}
+
+// Packages apt's extended_states marks `Auto-Installed: 1`.
+fn auto_installed_names() -> HashSet<String> {
+    let Ok(text) = fs::read_to_string(format!("{}/var/lib/apt/extended_states", prefix())) else {
+        return HashSet::new();
+    };
+    text.split("\n\n")
+        .map(details::Details::parse)
+        .filter(|details| details.get("Auto-Installed") == Some("1"))
+        .filter_map(|details| details.get("Package").map(String::from))
+        .collect()
+}
+
+// Installed pkg/apt packages straight from dpkg's status file, which is much
+// quicker than `apt list` but knows nothing about suites. Only used by the fast
+// profile, and not when the list command is overridden.
+fn read_dpkg_status(manager: PackageManager) -> Option<Listing> {
+    if !matches!(manager, PackageManager::Pkg | PackageManager::Apt)
+        || std::env::var_os(commands::env_var(manager, Action::List)).is_some()
+    {
+        return None;
+    }
+    let mut packages: Vec<Package> = installed_stanzas()?
+        .into_iter()
+        .filter_map(|details| {
+            Some(Package {
+                name: details.get("Package")?.to_string(),
+                version: details.get("Version")?.to_string(),
+                arch: details.get("Architecture").map(String::from),
+                repo: None,
+                description: None,
+                priority: details.get("Priority").map(String::from),
+                section: details.get("Section").map(String::from),
+                auto_installed: false,
+            })
+        })
+        .collect();
+    // dpkg doesn't know what apt installed as a dependency; apt keeps that apart
+    let auto = auto_installed_names();
+    for pkg in &mut packages {
+        pkg.auto_installed = auto.contains(&pkg.name);
+    }
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(Listing {
+        packages,
+        ..Listing::default()
+    })
+}
+
+// Lists and parses a manager's packages: what the TUI, `tpi list` and the
+// other headless commands all start from.
+fn fetch_listing(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    switch: Option<&str>,
+) -> Result<Listing, LoadError> {
+    list_output(runner, manager, switch)
+        .map_err(|err| LoadError::from_io(manager, &err))
+        .and_then(|output| parse_listing(manager, &output))
+}
+
+// Runs the list command, or the opam one for a switch picked in the TUI.
+fn list_output(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    switch: Option<&str>,
+) -> io::Result<Output> {
+    match switch {
+        Some(switch) => commands::run_with(runner, manager, Action::ListSwitch, switch),
+        None => commands::run_with(runner, manager, Action::List, ""),
+    }
+}
+
+// The list to start with: the manager asked for, else the first enabled one
+// that lists, with why each one before it failed. When none does, the last
+// one tried, empty.
+fn first_list(
+    runner: Arc<dyn Runner>,
+    enabled: &[PackageManager],
+    asked: Option<PackageManager>,
+    profile: config::Profile,
+) -> (PackageList, Vec<LoadError>) {
+    let tried = match asked {
+        Some(manager) => vec![manager],
+        None if enabled.is_empty() => vec![PackageManager::Pkg],
+        None => enabled.to_vec(),
+    };
+    let mut errors = Vec::new();
+    let mut last = None;
+    for manager in tried {
+        let list = PackageList::open(manager, runner.clone(), profile);
+        match &list.load_error {
+            Some(err) => errors.push(err.clone()),
+            None => return (list, errors),
+        }
+        last = Some(list);
+    }
+    (last.expect("at least one manager is tried"), errors)
+}
+
+// Non-empty output lines of a command the manager supports and that succeeded.
+fn command_lines(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+) -> Option<Vec<String>> {
+    if !commands::supports(manager, action) {
+        return None;
+    }
+    let output = commands::run_with(runner, manager, action, "")
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+// Rocks installed with --local live under the user's home, everything else
+// in the system tree under $PREFIX.
+fn luarocks_tree_label(tree: &str) -> String {
+    match config::home() {
+        Some(home) if std::path::Path::new(tree).starts_with(&home) => "user".to_string(),
+        _ => "system".to_string(),
+    }
+}
+
+// Packages from a list command's output. A failing command that still printed
+// packages (pip warnings, say) is fine; one that printed nothing usable
+// explains itself on stderr.
+fn parse_listing(manager: PackageManager, output: &Output) -> Result<Listing, LoadError> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match parse_package_list(manager, &stdout) {
+        Ok(listing) if !listing.packages.is_empty() || output.status.success() => Ok(listing),
+        Err(err) if output.status.success() => {
+            Err(LoadError::new(manager, failures::Failure::Unparsable(err)))
+        }
+        _ => Err(LoadError::from_output(manager, output)),
+    }
+}
+
+// What the package list is narrowed down to, besides collapsed groups.
+#[derive(Clone)]
+enum ListFilter {
+    Repo {
+        label: String,
+        suites: HashSet<String>, // Suites `apt list` may show for it
+    },
+    Built(wheels::Build), // pip packages installed this way
+    Priority(&'static str),
+    Section(String), // As sections::of gives it, "unsectioned" included
+    Drift(drift::State),
+    Upgradable, // Once `V` has fetched what is
+    Orphans,    // Found so far by the scan `c` starts
+}
+
+impl ListFilter {
+    fn label(&self) -> String {
+        match self {
+            ListFilter::Repo { label, .. } => i18n::fill("filter.repo", &[("repo", label)]),
+            ListFilter::Built(wheels::Build::Source) => i18n::text("filter.source").to_string(),
+            ListFilter::Built(wheels::Build::Wheel) => i18n::text("filter.wheels").to_string(),
+            ListFilter::Built(wheels::Build::Pure) => i18n::text("filter.pure").to_string(),
+            ListFilter::Priority(priority) => {
+                i18n::fill("filter.priority", &[("priority", priority)])
+            }
+            ListFilter::Section(section) => {
+                i18n::fill("filter.section", &[("section", &sections::title(section))])
+            }
+            ListFilter::Drift(state) => state.label().to_string(),
+            ListFilter::Upgradable => i18n::text("filter.upgradable").to_string(),
+            ListFilter::Orphans => i18n::text("filter.orphans").to_string(),
+        }
+    }
+
+    // pkg and apt read the same sources, so a repository filter carries over
+    // between them.
+    fn applies_to(&self, manager: PackageManager) -> bool {
+        match self {
+            ListFilter::Repo { .. } | ListFilter::Priority(_) | ListFilter::Section(_) => {
+                matches!(manager, PackageManager::Pkg | PackageManager::Apt)
+            }
+            ListFilter::Built(_) => manager == PackageManager::Pip,
+            ListFilter::Drift(_) => true,
+            ListFilter::Upgradable => commands::supports(manager, Action::Upgradable),
+            ListFilter::Orphans => commands::supports(manager, Action::Rdepends),
+        }
+    }
+}
+
+// Entries of the quit dialog shown while background operations run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum QuitChoice {
+    Wait,
+    Cancel,
+    Abandon, // Exit without waiting; their commands keep running
+}
+
+// Entries of the priority menu (T).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PriorityChoice {
+    All,
+    Only(&'static str),
+    Column, // Show or hide each package's priority in the list
+}
+
+// Entries of the sections popup (#).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SectionChoice {
+    All,
+    Only(usize), // Index into the sections the popup lists
+    Column,      // Show or hide each package's section in the list
+}
+
+// Entries of the manifest drift menu (d).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DriftChoice {
+    All,
+    Only(drift::State),
+    #[cfg(feature = "reports")]
+    Update, // Write the manifest again from what is installed now
+    Stop,
 }
 
-#[derive(Debug, PartialEq)]
-enum PackageManager {
-    Pkg,
-    Apt,
-    Pip,
+// What a list hands on to the one shown in its place: marks, the search,
+// the order, and the filter and grouping where they still apply.
+struct Carried {
+    group_by: GroupBy,
+    sort_by: SortBy,
+    manifest: Option<Arc<drift::Manifest>>,
+    marked: HashSet<(PackageManager, String)>,
+    upgradable: HashMap<PackageManager, HashMap<String, outdated::Outdated>>,
+    hidden: hidden::Hidden,
+    flagged: HashSet<(PackageManager, String)>,
+    show_hidden: bool,
+    filter: Option<ListFilter>,
+    query: String,
 }
 
 struct PackageList {
     items: Vec<Package>,
-    state: ListState,
+    rows: Vec<Row>,   // What is displayed: packages, or group headers and packages
+    state: ListState, // Selected row
+    group_by: GroupBy,
+    sort_by: SortBy,
+    collapsed: HashSet<String>,
     package_manager: PackageManager,
+    marked: HashSet<(PackageManager, String)>, // By identity, so marks survive reordering and Tab
+    sizes: HashMap<String, u64>,               // Installed size in KiB, where the manager says
+    held: HashSet<String>,
+    pins: Vec<pins::Pin>,          // apt preferences records, pkg and apt only
+    primary_arch: Option<String>,  // From `dpkg --print-architecture`
+    home: Option<String>,          // Where the manager installs global packages, if it says
+    switch: Option<String>,        // opam switch listed
+    switch_picked: bool,           // `switch` was picked rather than opam's current one
+    switches: Vec<String>,         // Every opam switch, for the picker
+    load_error: Option<LoadError>, // Why `items` is empty when listing failed
+    rejected: Vec<Rejection>,      // Listing lines that didn't parse
+    rejected_count: usize,         // Including those beyond parsers::MAX_REJECTIONS
+    history: HashMap<String, Vec<history::Change>>, // Versions seen on this device, by name
+    changes: HashMap<String, watch::Change>, // What the last watch refresh changed
+    why: Option<why::Explainer>,   // Dependency chains, when the manager exposes its graph
+    extras: Option<extras::Extras>, // pip extras and whether their dependencies are installed
+    builds: HashMap<String, wheels::BuildInfo>, // pip wheel or source build, by canonical name
+    essential: HashSet<String>,    // Essential: yes in dpkg's status file
+    strays: HashMap<String, Vec<String>>, // Files outside where the manager puts them, by name
+    shadows: HashMap<String, Vec<String>>, // Commands hidden by or hiding another package's, by name
+    shown: showcache::ShowCache,           // `show` output, fetched once per package
+    orphans: Option<orphans::Scan>,        // Started by `c`, dropped with the listing
+    requires: Option<why::Graph>,          // pip's Requires read by `y` without the why graph
+    dpkg_log: dpkglog::Lazy, // Read in the background when first needed, dropped with the listing
+    drift: Option<drift::Drift>, // Comparison with a manifest, while `d` is on
+    upgradable: HashMap<PackageManager, HashMap<String, outdated::Outdated>>, // Fetched by `V`, per manager, kept across Tab
+    hidden: hidden::Hidden, // Left out of the rows (`z`), kept across Tab
+    show_hidden: bool,      // `Z`: hidden packages are listed too, dimmed
+    flagged: HashSet<(PackageManager, String)>, // Named by a finished health check
+    loaded_at: Instant,     // When the list was last read from the manager
+    profile: config::Profile, // Fast skips everything but names and versions
+    filter: Option<ListFilter>,
+    query: String, // Lowercased search query; only names containing it are listed
+    runner: Arc<dyn Runner>,
 }
 
 impl PackageList {
-    fn load(package_manager: PackageManager) -> Self {
-        let output = match package_manager {
-            PackageManager::Pkg => Command::new("pkg")
-                .arg("list-installed")
-                .output()
-                .expect("Failed to execute pkg list-installed"),
-            PackageManager::Apt => Command::new("apt")
-                .arg("list")
-                .arg("--installed")
-                .output()
-                .expect("Failed to execute apt list --installed"),
-            PackageManager::Pip => Command::new("pip")
-                .arg("list")
-                .output()
-                .expect("Failed to execute pip list"),
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let items: Vec<Package> = stdout
-            .lines()
-            .filter_map(|line| {
-                match package_manager {
-                    PackageManager::Pkg => {
-                        let parts: Vec<&str> = line.split('/').collect();
-                        if parts.len() >= 2 {
-                            Some(Package {
-                                name: parts[0].to_string(),
-                                version: parts[1].to_string(),
-                            })
-                        } else {
-                            None
-                        }
-                    }
-                    PackageManager::Apt => {
-                        let parts: Vec<&str> = line.split('/').collect();
-                        if parts.len() >= 2 {
-                            let version_parts: Vec<&str> = parts[1].split(' ').collect();
-                            if version_parts.len() >= 1 {
-                                Some(Package {
-                                    name: parts[0].to_string(),
-                                    version: version_parts[0].to_string(),
-                                })
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    }
-                    PackageManager::Pip => {
-                        if line.contains("Package") || line.contains("---") {
-                            return None; // Skip header lines
-                        }
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() >= 2 {
-                            Some(Package {
-                                name: parts[0].to_string(),
-                                version: parts[1].to_string(),
-                            })
-                        } else {
-                            None
-                        }
-                    }
+    fn load(
+        package_manager: PackageManager,
+        runner: Arc<dyn Runner>,
+        profile: config::Profile,
+    ) -> Self {
+        Self::load_in(package_manager, runner, profile, None)
+    }
+
+    // Like load, but lists the given opam switch instead of the current one.
+    fn load_in(
+        package_manager: PackageManager,
+        runner: Arc<dyn Runner>,
+        profile: config::Profile,
+        switch: Option<String>,
+    ) -> Self {
+        let started = Instant::now();
+        let switch_picked = switch.is_some();
+        let switch = switch.or_else(|| {
+            command_lines(runner.as_ref(), package_manager, Action::Switch)?
+                .into_iter()
+                .next()
+        });
+        let fast_listing = match profile {
+            config::Profile::Fast => read_dpkg_status(package_manager),
+            config::Profile::Full => None,
+        };
+        let (listing, load_error) = match fast_listing {
+            Some(listing) => (listing, None),
+            None => {
+                let picked = switch.as_deref().filter(|_| switch_picked);
+                match fetch_listing(runner.as_ref(), package_manager, picked) {
+                    Ok(listing) => (listing, None),
+                    Err(err) => (Listing::default(), Some(err)),
                 }
+            }
+        };
+
+        let mut list = Self::with_listing(package_manager, runner, listing, load_error);
+        startup::step("first parse", started);
+        list.profile = profile;
+        list.switches = command_lines(list.runner.as_ref(), package_manager, Action::Switches)
+            .unwrap_or_default();
+        list.switch = switch;
+        list.switch_picked = switch_picked;
+        log::debug(&format!(
+            "listed {}: {} packages in {} ms",
+            package_manager.name(),
+            list.items.len(),
+            started.elapsed().as_millis()
+        ));
+        if profile == config::Profile::Full {
+            list.load_held();
+            list.load_pins();
+            list.load_status_fields();
+            list.load_primary_arch();
+            list.load_home();
+            list.load_sizes();
+            list.load_history();
+            list.load_why();
+            list.load_strays();
+            list.load_shadows();
+            log::debug(&format!(
+                "loaded {} with extras in {} ms",
+                package_manager.name(),
+                started.elapsed().as_millis()
+            ));
+        }
+        list
+    }
+
+    // Lists the packages again, keeping selection, marks and grouping, and
+    // remembers what changed. On failure the current items stay.
+    // Returns the packages whose version changed, compared with what was
+    // listed and shown before.
+    fn refresh(&mut self) -> Result<Vec<upgrades::Upgrade>, String> {
+        let picked = self.switch.as_deref().filter(|_| self.switch_picked);
+        let fresh = fetch_listing(self.runner.as_ref(), self.package_manager, picked)
+            .map_err(|err| err.to_string())?;
+        let (items, changes) = watch::diff(&self.items, &self.changes, fresh.packages);
+        self.changes = changes;
+        let old = self.set_items(items);
+        let old_sizes = self.sizes.clone();
+        let shown: HashMap<(String, String), details::Details> = self
+            .shown
+            .take()
+            .into_iter()
+            .map(|(key, text)| {
+                (
+                    key,
+                    details::Details::parse_for(self.package_manager, &text),
+                )
             })
             .collect();
-
-        let mut state = ListState::default();
-        if !items.is_empty() {
-            state.select(Some(0));
+        self.rejected = fresh.rejected;
+        self.rejected_count = fresh.rejected_count;
+        self.load_error = None;
+        self.requires = None;
+        self.loaded_at = Instant::now();
+        if self.profile == config::Profile::Full {
+            self.load_held();
+            self.load_pins();
+            self.load_status_fields();
+            self.load_sizes();
+            self.load_history();
+            self.load_why();
+            self.load_strays();
+            self.load_shadows();
+            if self.sort_by.column() == sorting::Column::Size {
+                self.rebuild_rows();
+            }
         }
+        Ok(upgrades::collect(
+            &old,
+            &old_sizes,
+            &shown,
+            &self.items,
+            &self.sizes,
+        ))
+    }
 
-        Self {
-            items,
-            state,
-            package_manager,
+    // Every successful listing is also a snapshot for the version history,
+    // with the sizes loaded just before it.
+    fn load_history(&mut self) {
+        if self.load_error.is_none() {
+            if let Err(err) = history::record(self.package_manager, &self.items, &self.sizes) {
+                log::debug(&format!("history: {}", err));
+            }
         }
+        self.history = history::load(self.package_manager);
     }
 
-    fn toggle_package_manager(&mut self) {
-        let new_manager = match self.package_manager {
-            PackageManager::Pkg => PackageManager::Apt,
-            PackageManager::Apt => PackageManager::Pip,
-            PackageManager::Pip => PackageManager::Pkg,
+    // Priority, Essential and Section come from dpkg's status file; `apt
+    // list` shows none of them.
+    fn load_status_fields(&mut self) {
+        if !matches!(
+            self.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            return;
+        }
+        let Some(stanzas) = installed_stanzas() else {
+            return;
         };
-        *self = Self::load(new_manager);
+        let mut priorities = HashMap::new();
+        let mut sections = HashMap::new();
+        self.essential.clear();
+        for details in &stanzas {
+            let Some(name) = details.get("Package") else {
+                continue;
+            };
+            if details.get("Essential") == Some("yes") {
+                self.essential.insert(name.to_string());
+            }
+            if let Some(priority) = details.get("Priority") {
+                priorities.insert(name, priority);
+            }
+            if let Some(section) = details.get("Section") {
+                sections.insert(name, section);
+            }
+        }
+        for item in &mut self.items {
+            item.priority = priorities.get(item.name.as_str()).map(|p| p.to_string());
+            item.section = sections.get(item.name.as_str()).map(|s| s.to_string());
+        }
     }
 
-    fn select_next(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.items.len().saturating_sub(1) {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.state.select(Some(i));
+    fn load_sizes(&mut self) {
+        if !commands::supports(self.package_manager, Action::Sizes) {
+            return;
+        }
+        if let Ok(output) = commands::run_with(
+            self.runner.as_ref(),
+            self.package_manager,
+            Action::Sizes,
+            "",
+        ) {
+            self.sizes = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| {
+                    let (name, size) = line.split_once('\t')?;
+                    Some((name.to_string(), size.trim().parse().ok()?))
+                })
+                .collect();
+        }
     }
 
-    fn select_previous(&mut self) {
-        let i = match self.state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.items.len().saturating_sub(1)
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
+    fn load_why(&mut self) {
+        self.why = why::Explainer::load(self.runner.as_ref(), self.package_manager);
+        if self.package_manager != PackageManager::Pip {
+            return;
+        }
+        let metadata = commands::run_with(
+            self.runner.as_ref(),
+            self.package_manager,
+            Action::Metadata,
+            "",
+        )
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+        if let Some(metadata) = metadata {
+            self.extras = Some(extras::parse(&metadata));
+            self.builds = wheels::parse(&metadata);
+        }
+    }
+
+    // From the same file lists as the health checks, cached until the
+    // package database changes.
+    fn load_strays(&mut self) {
+        self.strays = strays::scan(self.package_manager, self.runner.as_ref());
+    }
+
+    fn stray_files(&self, pkg: &Package) -> Option<&Vec<String>> {
+        self.strays
+            .get(&names::canonical(self.package_manager, &pkg.name))
+    }
+
+    // Also from the cached file lists, for the managers that have them and
+    // cargo.
+    fn load_shadows(&mut self) {
+        self.shadows = match self.package_manager {
+            PackageManager::Pkg
+            | PackageManager::Apt
+            | PackageManager::Pip
+            | PackageManager::Cargo => shadowing::find_all(
+                self.runner.as_ref(),
+                &std::sync::atomic::AtomicBool::new(false),
+            )
+            .map(|found| shadowing::notes(&found, self.package_manager))
+            .unwrap_or_default(),
+            _ => HashMap::new(),
         };
-        self.state.select(Some(i));
     }
 
-    fn select_first(&mut self) {
-        if !self.items.is_empty() {
-            self.state.select(Some(0));
+    fn shadow_notes(&self, pkg: &Package) -> Option<&Vec<String>> {
+        self.shadows
+            .get(&names::canonical(self.package_manager, &pkg.name))
+    }
+
+    fn shadow_text(&self, pkg: &Package) -> Option<String> {
+        let notes = self.shadow_notes(pkg)?;
+        Some(format!("Commands on PATH:\n  {}\n", notes.join("\n  ")))
+    }
+
+    fn load_home(&mut self) {
+        if !commands::supports(self.package_manager, Action::Home) {
+            return;
+        }
+        if let Ok(output) =
+            commands::run_with(self.runner.as_ref(), self.package_manager, Action::Home, "")
+        {
+            let home = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            self.home = Some(home).filter(|home| output.status.success() && !home.is_empty());
         }
     }
 
-    fn select_last(&mut self) {
-        if !self.items.is_empty() {
-            self.state.select(Some(self.items.len().saturating_sub(1)));
-        }
-    }
-
-    fn fetch_package_details(&self, package_name: &str) -> String {
-        let output = match self.package_manager {
-            PackageManager::Pkg => Command::new("pkg")
-                .arg("show")
-                .arg(package_name)
-                .output(),
-            PackageManager::Apt => Command::new("apt")
-                .arg("show")
-                .arg(package_name)
-                .output(),
-            PackageManager::Pip => Command::new("pip")
-                .arg("show")
-                .arg(package_name)
-                .output(),
-        };
-
-        match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                if stdout.is_empty() {
-                    "No details available".to_string()
-                } else {
-                    stdout.to_string()
-                }
-            }
-            Err(_) => "Failed to fetch package details".to_string(),
+    // Loads the manager if its command is installed; otherwise an empty list
+    // that says why.
+    fn open(
+        package_manager: PackageManager,
+        runner: Arc<dyn Runner>,
+        profile: config::Profile,
+    ) -> Self {
+        if commands::is_available(package_manager) {
+            return Self::load(package_manager, runner, profile);
         }
+        let program = commands::resolve(package_manager, Action::List, "")
+            .map(|words| words[0].clone())
+            .unwrap_or_default();
+        let reason = LoadError::new(package_manager, failures::Failure::NotInstalled { program });
+        let mut list =
+            Self::with_listing(package_manager, runner, Listing::default(), Some(reason));
+        list.profile = profile;
+        list
     }
-}
 
-struct App {
-    should_exit: bool,
-    package_list: PackageList,
-    details_scroll: u16, // Track scroll position for details
-    details_height_percentage: u16, // Percentage for details section (30% by default)
-}
+    fn with_listing(
+        package_manager: PackageManager,
+        runner: Arc<dyn Runner>,
+        listing: Listing,
+        load_error: Option<LoadError>,
+    ) -> Self {
+        let mut list = Self {
+            items: listing.packages,
+            rejected: listing.rejected,
+            rejected_count: listing.rejected_count,
+            rows: Vec::new(),
+            state: ListState::default(),
+            group_by: GroupBy::None,
+            sort_by: SortBy::Name,
+            collapsed: HashSet::new(),
+            package_manager,
+            marked: HashSet::new(),
+            sizes: HashMap::new(),
+            held: HashSet::new(),
+            pins: Vec::new(),
+            primary_arch: None,
+            home: None,
+            switch: None,
+            switch_picked: false,
+            switches: Vec::new(),
+            load_error,
+            history: HashMap::new(),
+            changes: HashMap::new(),
+            why: None,
+            extras: None,
+            builds: HashMap::new(),
+            essential: HashSet::new(),
+            strays: HashMap::new(),
+            shadows: HashMap::new(),
+            shown: showcache::ShowCache::default(),
+            orphans: None,
+            requires: None,
+            dpkg_log: dpkglog::Lazy::default(),
+            drift: None,
+            upgradable: HashMap::new(),
+            hidden: hidden::Hidden::default(),
+            show_hidden: false,
+            flagged: HashSet::new(),
+            loaded_at: Instant::now(),
+            profile: config::Profile::Full,
+            filter: None,
+            query: String::new(),
+            runner,
+        };
+        list.rebuild_rows();
+        list
+    }
 
-impl App {
-    fn new() -> Self {
-        Self {
-            should_exit: false,
-            package_list: PackageList::load(PackageManager::Pkg),
-            details_scroll: 0,
-            details_height_percentage: 30, // Initial split: 70% list, 30% details
+    fn load_primary_arch(&mut self) {
+        if !commands::supports(self.package_manager, Action::Arch) {
+            return;
+        }
+        if let Ok(output) =
+            commands::run_with(self.runner.as_ref(), self.package_manager, Action::Arch, "")
+        {
+            let arch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            self.primary_arch = Some(arch).filter(|arch| !arch.is_empty());
         }
     }
 
-    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> io::Result<()> {
-        loop {
-            terminal.draw(|f| self.ui(f))?;
+    fn is_foreign(&self, pkg: &Package) -> bool {
+        match &self.primary_arch {
+            Some(primary) => health::is_foreign_arch(pkg, primary),
+            None => false,
+        }
+    }
 
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            self.should_exit = true;
-                        }
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            self.package_list.select_next();
-                            self.details_scroll = 0; // Reset scroll when selecting a new package
-                        }
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            self.package_list.select_previous();
-                            self.details_scroll = 0; // Reset scroll when selecting a new package
-                        }
-                        KeyCode::Home | KeyCode::Char('g') => {
-                            self.package_list.select_first();
-                            self.details_scroll = 0;
-                        }
-                        KeyCode::End | KeyCode::Char('G') => {
-                            self.package_list.select_last();
-                            self.details_scroll = 0;
-                        }
-                        KeyCode::Tab => {
-                            self.package_list.toggle_package_manager();
-                            self.details_scroll = 0;
-                        }
-                        KeyCode::Char('J') => {
-                            // Scroll details down
-                            self.details_scroll = self.details_scroll.saturating_add(1);
-                        }
-                        KeyCode::Char('K') => {
-                            // Scroll details up
-                            self.details_scroll = self.details_scroll.saturating_sub(1);
-                        }
-                        KeyCode::Char('+') => {
-                            // Increase details section size (up to 80%)
-                            if self.details_height_percentage < 80 {
-                                self.details_height_percentage =
-                                    (self.details_height_percentage + 5).min(80);
-                            }
-                        }
-                        KeyCode::Char('-') => {
-                            // Decrease details section size (down to 10%)
-                            if self.details_height_percentage > 10 {
-                                self.details_height_percentage =
-                                    (self.details_height_percentage - 5).max(10);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
+    fn load_pins(&mut self) {
+        if matches!(
+            self.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            self.pins = pins::load();
+        }
+    }
 
-            if self.should_exit {
-                return Ok(());
-            }
+    fn load_held(&mut self) {
+        if !commands::supports(self.package_manager, Action::Held) {
+            return;
+        }
+        if let Ok(output) =
+            commands::run_with(self.runner.as_ref(), self.package_manager, Action::Held, "")
+        {
+            self.held = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from)
+                .collect();
         }
     }
 
-    fn ui(&mut self, f: &mut Frame) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(100 - self.details_height_percentage),
-                Constraint::Percentage(self.details_height_percentage),
-            ])
-            .split(f.size());
+    fn selected(&self) -> Option<&Package> {
+        match self.state.selected().and_then(|i| self.rows.get(i)) {
+            Some(Row::Package(index)) => self.items.get(*index),
+            _ => None,
+        }
+    }
 
-        let list_area = chunks[0];
-        let detail_area = chunks[1];
+    // Name of the selected manifest entry that isn't installed.
+    fn selected_missing(&self) -> Option<&str> {
+        match self.state.selected().and_then(|i| self.rows.get(i)) {
+            Some(Row::Missing(index)) => self
+                .drift
+                .as_ref()
+                .and_then(|drift| drift.missing.get(*index))
+                .map(String::as_str),
+            _ => None,
+        }
+    }
 
-        // Render package list
-        let items: Vec<ListItem> = self
-            .package_list
-            .items
+    // Name of the group the selected row belongs to (its header's name).
+    fn selected_group(&self) -> Option<String> {
+        let selected = self.state.selected()?;
+        self.rows[..=selected.min(self.rows.len().checked_sub(1)?)]
             .iter()
-            .map(|pkg| ListItem::new(format!("{} {}", pkg.name, pkg.version)))
-            .collect();
+            .rev()
+            .find_map(|row| match row {
+                Row::Header { name, .. } => Some(name.clone()),
+                Row::Package(_) | Row::Missing(_) => None,
+            })
+    }
 
-        let title = match self.package_list.package_manager {
-            PackageManager::Pkg => "Installed Packages (pkg)",
-            PackageManager::Apt => "Installed Packages (apt)",
-            PackageManager::Pip => "Installed Packages (pip)",
-        };
+    // Replaces the packages, keeping the selection on the same package or,
+    // when that one is gone, on the nearest one left, and dropping marks of
+    // packages that went away. Every change of `items` goes through here so
+    // the rows never point past its end. Returns the packages it had.
+    fn set_items(&mut self, items: Vec<Package>) -> Vec<Package> {
+        let (selected_package, selected_group) = self.selection();
+        let row = self.state.selected();
+        let old = std::mem::replace(&mut self.items, items);
+        self.rows.clear(); // Indices into the old items
 
-        let list = List::new(items)
-            .block(Block::default().title(title).borders(Borders::ALL))
-            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-            .highlight_symbol(">> ");
+        let manager = self.package_manager;
+        let names: HashSet<&str> = self.items.iter().map(|pkg| pkg.name.as_str()).collect();
+        self.marked
+            .retain(|(marked, name)| *marked != manager || names.contains(name.as_str()));
+        if let Some(drift) = self.drift.take() {
+            self.drift = Some(drift::Drift::new(
+                drift.manifest,
+                self.runner.as_ref(),
+                self.package_manager,
+                &self.items,
+            ));
+        }
+
+        let kept = self.rebuild_rows_keeping(selected_package.clone(), selected_group);
+        if let (false, Some(_), Some(row)) = (kept, selected_package, row) {
+            self.select_near(row);
+        }
+        old
+    }
 
-        f.render_stateful_widget(list, list_area, &mut self.package_list.state);
+    // The selected package or manifest entry, and group.
+    fn selection(&self) -> (Option<String>, Option<String>) {
+        let package = self
+            .selected()
+            .map(|pkg| pkg.name.as_str())
+            .or(self.selected_missing())
+            .map(String::from);
+        (package, self.selected_group())
+    }
 
-        // Render selected package details with scrolling
-        let detail = if let Some(i) = self.package_list.state.selected() {
-            let pkg = &self.package_list.items[i];
-            self.package_list.fetch_package_details(&pkg.name)
-        } else {
-            "No package selected".to_string()
+    // Rebuilds the visible rows, keeping the selection on the same package
+    // (or group header) where possible.
+    fn rebuild_rows(&mut self) {
+        let (selected_package, selected_group) = self.selection();
+        self.rebuild_rows_keeping(selected_package, selected_group);
+    }
+
+    // Whether the selection stayed where it was; else it is on the first row.
+    fn rebuild_rows_keeping(
+        &mut self,
+        selected_package: Option<String>,
+        selected_group: Option<String>,
+    ) -> bool {
+        let mut visible: Vec<usize> = (0..self.items.len())
+            .filter(|i| self.keeps(&self.items[*i]) && self.shows(&self.items[*i]))
+            .collect();
+        self.sort_by.sort(&self.items, &self.sizes, &mut visible);
+        self.rows = grouping::build_rows(
+            &self.items,
+            visible.into_iter(),
+            self.group_by,
+            &self.collapsed,
+        );
+        self.add_missing_rows();
+
+        let position = self
+            .rows
+            .iter()
+            .position(|row| match row {
+                Row::Package(i) => Some(&self.items[*i].name) == selected_package.as_ref(),
+                Row::Missing(i) => {
+                    self.drift.as_ref().map(|drift| &drift.missing[*i]) == selected_package.as_ref()
+                }
+                Row::Header { .. } => false,
+            })
+            .or_else(|| self.header_position(selected_group.as_deref()?));
+        match position {
+            Some(i) => self.state.select(Some(i)),
+            None => self.select_first(),
+        }
+        position.is_some()
+    }
+
+    // Keeps the selection on a row, or none when there are no rows.
+    fn clamp_selection(&mut self) {
+        match self.state.selected() {
+            _ if self.rows.is_empty() => self.state.select(None),
+            Some(i) if i >= self.rows.len() => self.state.select(Some(self.rows.len() - 1)),
+            _ => {}
+        }
+    }
+
+    // Packages of the manifest that aren't installed, after the installed
+    // ones (under a header of their own when the list is grouped), unless a
+    // filter other than `missing` is on.
+    fn add_missing_rows(&mut self) {
+        let Some(drift) = &self.drift else {
+            return;
         };
+        if !matches!(
+            self.filter,
+            None | Some(ListFilter::Drift(drift::State::Missing))
+        ) {
+            return;
+        }
+        let missing: Vec<usize> = (0..drift.missing.len())
+            .filter(|i| drift.missing[*i].to_lowercase().contains(&self.query))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        if self.group_by != GroupBy::None {
+            let name = MISSING_GROUP.to_string();
+            let collapsed = self.collapsed.contains(&name);
+            self.rows.push(Row::Header {
+                name,
+                count: missing.len(),
+                collapsed,
+            });
+            if collapsed {
+                return;
+            }
+        }
+        self.rows.extend(missing.into_iter().map(Row::Missing));
+    }
 
-        let paragraph = Paragraph::new(detail)
-            .block(Block::default().title("Package Details").borders(Borders::ALL))
-            .wrap(Wrap { trim: true })
-            .scroll((self.details_scroll, 0)); // Apply scroll offset
+    fn keeps(&self, pkg: &Package) -> bool {
+        if !self.query.is_empty() && !pkg.name.to_lowercase().contains(&self.query) {
+            return false;
+        }
+        match &self.filter {
+            None => true,
+            Some(ListFilter::Repo { suites, .. }) => {
+                pkg.repo.as_ref().is_some_and(|repo| suites.contains(repo))
+            }
+            Some(ListFilter::Built(build)) => self
+                .build_info(pkg)
+                .is_some_and(|info| info.build == *build),
+            Some(ListFilter::Priority(priority)) => pkg.priority.as_deref() == Some(*priority),
+            Some(ListFilter::Section(section)) => sections::of(pkg) == section,
+            Some(ListFilter::Drift(state)) => self.drift_state(pkg) == Some(*state),
+            Some(ListFilter::Upgradable) => self.candidate(pkg).is_some(),
+            Some(ListFilter::Orphans) => self.is_orphan(pkg),
+        }
+    }
 
-        f.render_widget(paragraph, detail_area);
+    fn is_orphan(&self, pkg: &Package) -> bool {
+        pkg.auto_installed
+            && self
+                .orphans
+                .as_ref()
+                .is_some_and(|scan| scan.found.contains(&pkg.name))
     }
+
+    // Scans the automatically installed packages for orphans, unless this
+    // listing was scanned already.
+    fn scan_orphans(&mut self) {
+        if self.orphans.is_some() {
+            return;
+        }
+        let names = self
+            .items
+            .iter()
+            .filter(|pkg| pkg.auto_installed)
+            .map(|pkg| pkg.name.clone())
+            .collect();
+        self.orphans = Some(orphans::Scan::start(
+            self.runner.clone(),
+            self.package_manager,
+            names,
+        ));
+    }
+
+    // Takes in the scan's progress, listing orphans as they are found.
+    fn poll_orphans(&mut self) -> bool {
+        let changed = self.orphans.as_mut().is_some_and(|scan| scan.poll());
+        if changed && matches!(self.filter, Some(ListFilter::Orphans)) {
+            self.rebuild_rows();
+        }
+        changed
+    }
+
+    // The newer version `V` found, while the installed one is still the one
+    // it was found for.
+    fn candidate(&self, pkg: &Package) -> Option<&str> {
+        let outdated = self.upgradable.get(&self.package_manager)?.get(&pkg.name)?;
+        (outdated.installed == pkg.version).then_some(outdated.candidate.as_str())
+    }
+
+    fn drift_state(&self, pkg: &Package) -> Option<drift::State> {
+        self.drift.as_ref()?.state(self.package_manager, &pkg.name)
+    }
+
+    // Compares the list with `manifest`, or stops comparing.
+    fn set_manifest(&mut self, manifest: Option<Arc<drift::Manifest>>) {
+        self.drift = manifest.map(|manifest| {
+            drift::Drift::new(
+                manifest,
+                self.runner.as_ref(),
+                self.package_manager,
+                &self.items,
+            )
+        });
+        if self.drift.is_none() && matches!(self.filter, Some(ListFilter::Drift(_))) {
+            self.filter = None;
+        }
+        self.rebuild_rows();
+    }
+
+    fn is_hidden(&self, pkg: &Package) -> bool {
+        self.hidden.contains(self.package_manager, &pkg.name)
+    }
+
+    // Why a hidden package is listed anyway: hiding must never keep
+    // something that needs attention out of sight.
+    fn hiding_overridden(&self, pkg: &Package) -> Option<&'static str> {
+        if !self.is_hidden(pkg) {
+            None
+        } else if self.candidate(pkg).is_some() {
+            Some(i18n::text("badge.reason_upgradable"))
+        } else if self
+            .flagged
+            .contains(&(self.package_manager, pkg.name.clone()))
+        {
+            Some(i18n::text("badge.reason_health"))
+        } else if self.stray_files(pkg).is_some() {
+            Some(i18n::text("badge.reason_strays"))
+        } else if self.is_misprioritized(pkg) {
+            Some(i18n::text("badge.reason_priority"))
+        } else {
+            None
+        }
+    }
+
+    // Whether the package gets a row, as far as hiding goes. Unlike `keeps`,
+    // this doesn't narrow exports.
+    fn shows(&self, pkg: &Package) -> bool {
+        self.show_hidden || !self.is_hidden(pkg) || self.hiding_overridden(pkg).is_some()
+    }
+
+    fn hidden_count(&self) -> usize {
+        self.items.iter().filter(|pkg| self.is_hidden(pkg)).count()
+    }
+
+    // Counts for the status bar.
+    fn summary(&self) -> status::Summary {
+        let is_package = |row: &Row| matches!(row, Row::Package(_));
+        let selected = self
+            .state
+            .selected()
+            .filter(|row| self.rows.get(*row).is_some_and(is_package))
+            .map(|row| {
+                self.rows[..row]
+                    .iter()
+                    .filter(|row| is_package(row))
+                    .count()
+            });
+        let sizes: Vec<u64> = self
+            .items
+            .iter()
+            .filter_map(|pkg| self.sizes.get(&pkg.name).copied())
+            .collect();
+        status::Summary {
+            manager: self.package_manager,
+            total: self.items.len(),
+            shown: self.rows.iter().filter(|row| is_package(row)).count(),
+            selected,
+            size: sizes.iter().sum(),
+            sized: sizes.len(),
+        }
+    }
+
+    // Essential to dpkg yet below important: apt will refuse to remove it,
+    // but nothing about its priority says so.
+    fn is_misprioritized(&self, pkg: &Package) -> bool {
+        self.essential.contains(&pkg.name)
+            && !matches!(pkg.priority.as_deref(), Some("required" | "important"))
+    }
+
+    fn build_info(&self, pkg: &Package) -> Option<&wheels::BuildInfo> {
+        self.builds.get(&names::pip(&pkg.name))
+    }
+
+    fn header_position(&self, group: &str) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|row| matches!(row, Row::Header { name, .. } if name == group))
+    }
+
+    fn set_sort(&mut self, sort_by: SortBy) {
+        self.sort_by = sort_by;
+        self.rebuild_rows();
+    }
+
+    fn set_group_by(&mut self, group_by: GroupBy) {
+        self.group_by = group_by;
+        self.collapsed.clear();
+        self.rebuild_rows();
+    }
+
+    // Next (or previous) of the enabled managers (see config::managers),
+    // unless that is this one.
+    fn next_manager(&self, managers: &[PackageManager], backwards: bool) -> Option<PackageManager> {
+        let step = if backwards { managers.len() - 1 } else { 1 };
+        let next = match managers.iter().position(|m| *m == self.package_manager) {
+            Some(i) => managers[(i + step) % managers.len()],
+            None => *managers.first()?,
+        };
+        (next != self.package_manager).then_some(next)
+    }
+
+    // What to show of `manager` while its packages load: the last list
+    // cached for it, if any, else an empty one, with what replace() would
+    // keep of this one.
+    fn loading(&self, manager: PackageManager) -> Self {
+        let cached = Listing {
+            packages: listcache::load(manager).unwrap_or_default(),
+            ..Listing::default()
+        };
+        let mut list = Self::with_listing(manager, self.runner.clone(), cached, None);
+        list.profile = self.profile;
+        list.marked = self.marked.clone();
+        list.upgradable = self.upgradable.clone();
+        list.query = self.query.clone();
+        list.sort_by = self.sort_by;
+        list.filter = self
+            .filter
+            .clone()
+            .filter(|filter| filter.applies_to(manager));
+        if self.group_by.applies_to(manager) {
+            list.group_by = self.group_by;
+        }
+        list.drift = self
+            .drift
+            .as_ref()
+            .map(|drift| drift::Drift::pending(drift.manifest.clone()));
+        list.rebuild_rows();
+        list
+    }
+
+    fn switch_to(&mut self, manager: PackageManager) {
+        let fresh = Self::open(manager, self.runner.clone(), self.profile);
+        self.replace(fresh);
+    }
+
+    // Lists another opam switch; opam's own current switch stays as it is.
+    fn pick_switch(&mut self, switch: String) {
+        let fresh = Self::load_in(
+            self.package_manager,
+            self.runner.clone(),
+            self.profile,
+            Some(switch),
+        );
+        self.replace(fresh);
+    }
+
+    // Takes over a freshly loaded list, keeping marks, the search, the order,
+    // and the filter and grouping where they still apply.
+    fn replace(&mut self, fresh: Self) {
+        let carried = self.hand_on();
+        *self = fresh;
+        self.take_on(carried);
+    }
+
+    // What this list passes to the one shown in its place.
+    fn hand_on(&mut self) -> Carried {
+        Carried {
+            group_by: self.group_by,
+            sort_by: self.sort_by,
+            manifest: self.drift.take().map(|drift| drift.manifest),
+            marked: std::mem::take(&mut self.marked),
+            upgradable: std::mem::take(&mut self.upgradable),
+            hidden: std::mem::take(&mut self.hidden),
+            flagged: std::mem::take(&mut self.flagged),
+            show_hidden: self.show_hidden,
+            filter: self.filter.take(),
+            query: std::mem::take(&mut self.query),
+        }
+    }
+
+    fn take_on(&mut self, carried: Carried) {
+        let manager = self.package_manager;
+        let Carried {
+            group_by,
+            sort_by,
+            manifest,
+            marked,
+            upgradable,
+            hidden,
+            flagged,
+            show_hidden,
+            filter,
+            query,
+        } = carried;
+        self.marked = marked;
+        self.upgradable = upgradable;
+        self.hidden = hidden;
+        self.flagged = flagged;
+        self.show_hidden = show_hidden;
+        self.query = query;
+        self.sort_by = sort_by;
+        self.filter = filter.filter(|filter| filter.applies_to(manager));
+        self.drift = manifest.map(|manifest| {
+            drift::Drift::new(manifest, self.runner.as_ref(), manager, &self.items)
+        });
+        // A reloaded list is scanned again
+        if matches!(self.filter, Some(ListFilter::Orphans)) {
+            self.scan_orphans();
+        }
+        if group_by.applies_to(manager) {
+            self.set_group_by(group_by);
+        } else {
+            self.rebuild_rows();
+        }
+    }
+
+    // Lists only the packages whose name contains `query`, any case.
+    fn set_query(&mut self, query: &str) {
+        let query = query.to_lowercase();
+        if query != self.query {
+            self.query = query;
+            self.rebuild_rows();
+        }
+    }
+
+    // Selects the package with this name, expanding its group if needed.
+    fn select_package(&mut self, name: &str) -> bool {
+        let Some(index) = self.items.iter().position(|pkg| pkg.name == name) else {
+            return false;
+        };
+        if !self
+            .rows
+            .iter()
+            .any(|row| matches!(row, Row::Package(i) if *i == index))
+        {
+            self.collapsed.clear();
+            self.rebuild_rows();
+        }
+        let row = self
+            .rows
+            .iter()
+            .position(|row| matches!(row, Row::Package(i) if *i == index));
+        self.state.select(row);
+        row.is_some()
+    }
+
+    // Rows j/k can land on: packages, or headers when every group is collapsed.
+    fn is_stop(&self, row: &Row) -> bool {
+        let entry = |row: &Row| matches!(row, Row::Package(_) | Row::Missing(_));
+        entry(row) || !self.rows.iter().any(entry)
+    }
+
+    fn select_next(&mut self) {
+        let len = self.rows.len();
+        let start = self.state.selected().unwrap_or(len.saturating_sub(1));
+        let next = (1..=len)
+            .map(|offset| (start + offset) % len)
+            .find(|&i| self.is_stop(&self.rows[i]));
+        if next.is_some() {
+            self.state.select(next);
+        }
+    }
+
+    fn select_previous(&mut self) {
+        let len = self.rows.len();
+        let start = self.state.selected().unwrap_or(0);
+        let previous = (1..=len)
+            .map(|offset| (start + len - offset) % len)
+            .find(|&i| self.is_stop(&self.rows[i]));
+        if previous.is_some() {
+            self.state.select(previous);
+        }
+    }
+
+    // The first package at or after `row`, else the last one before it, for
+    // when the package that was there went away.
+    fn select_near(&mut self, row: usize) {
+        let near = (row..self.rows.len())
+            .find(|&i| self.is_stop(&self.rows[i]))
+            .or_else(|| {
+                self.rows[..row.min(self.rows.len())]
+                    .iter()
+                    .rposition(|r| self.is_stop(r))
+            });
+        self.state.select(near);
+    }
+
+    fn select_first(&mut self) {
+        let first = self.rows.iter().position(|row| self.is_stop(row));
+        self.state.select(first);
+    }
+
+    fn select_last(&mut self) {
+        let last = self.rows.iter().rposition(|row| self.is_stop(row));
+        self.state.select(last);
+    }
+
+    // `{` / `}`: jump to the previous / next group header.
+    fn select_group(&mut self, forward: bool) {
+        let Some(current) = self.state.selected() else {
+            return;
+        };
+        let headers: Vec<usize> = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| matches!(row, Row::Header { .. }))
+            .map(|(i, _)| i)
+            .collect();
+        let target = if forward {
+            headers.iter().find(|&&i| i > current)
+        } else {
+            let own_header = headers.iter().rev().find(|&&i| i <= current);
+            headers
+                .iter()
+                .rev()
+                .find(|&&i| Some(&i) != own_header && i < current)
+        };
+        if let Some(&i) = target {
+            self.state.select(Some(i));
+        }
+    }
+
+    fn set_collapsed(&mut self, collapse: bool) {
+        let Some(group) = self.selected_group() else {
+            return;
+        };
+        if collapse {
+            self.collapsed.insert(group.clone());
+        } else {
+            self.collapsed.remove(&group);
+        }
+        self.rebuild_rows();
+        if let Some(header) = self.header_position(&group) {
+            // Collapsing parks the selection on the header, expanding moves
+            // into the group.
+            let target = if collapse { header } else { header + 1 };
+            self.state.select(Some(target.min(self.rows.len() - 1)));
+        }
+    }
+
+    fn selected_header(&self) -> Option<bool> {
+        match self.state.selected().and_then(|i| self.rows.get(i)) {
+            Some(Row::Header { collapsed, .. }) => Some(*collapsed),
+            _ => None,
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(name) = self.selected().map(|pkg| pkg.name.clone()) {
+            let marked = self.is_marked(&name);
+            self.set_mark(&name, !marked);
+        }
+    }
+
+    fn is_marked(&self, name: &str) -> bool {
+        self.marked
+            .contains(&(self.package_manager, name.to_string()))
+    }
+
+    fn set_mark(&mut self, name: &str, mark: bool) {
+        let key = (self.package_manager, name.to_string());
+        if mark {
+            self.marked.insert(key);
+        } else {
+            self.marked.remove(&key);
+        }
+    }
+
+    // What `e` writes: the marked packages of this manager when there are
+    // any, regardless of search and filter, else the packages in view. True
+    // with the marked ones.
+    fn export_items(&self) -> (Vec<&Package>, bool) {
+        let marked: Vec<&Package> = self
+            .items
+            .iter()
+            .filter(|pkg| self.is_marked(&pkg.name))
+            .collect();
+        if !marked.is_empty() {
+            return (marked, true);
+        }
+        let shown = self.items.iter().filter(|pkg| self.keeps(pkg)).collect();
+        (shown, false)
+    }
+
+    // Marked packages of the active manager, sorted.
+    fn marked_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .marked
+            .iter()
+            .filter(|(manager, _)| *manager == self.package_manager)
+            .map(|(_, name)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    // Packages in the list as it is shown: outside collapsed groups and, while
+    // a search is active, matching it.
+    fn visible_names(&self, search: Option<&search::Search>) -> Vec<String> {
+        self.rows
+            .iter()
+            .filter_map(|row| match row {
+                Row::Package(i) => self.items.get(*i),
+                Row::Header { .. } | Row::Missing(_) => None,
+            })
+            .filter(|pkg| match search {
+                Some(search) if !search.query.is_empty() => search.matches(&pkg.name),
+                _ => true,
+            })
+            .map(|pkg| pkg.name.clone())
+            .collect()
+    }
+
+    #[cfg(feature = "reports")]
+    fn report_entry(&self, pkg: &Package) -> report::ReportEntry {
+        report::ReportEntry {
+            manager: self.package_manager,
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            details: self.package_details(pkg),
+        }
+    }
+
+    fn fetch_package_details(&self, pkg: &Package) -> String {
+        self.shown.get(
+            self.runner.as_ref(),
+            self.package_manager,
+            &pkg.name,
+            &pkg.version,
+        )
+    }
+
+    fn package_details(&self, pkg: &Package) -> details::Details {
+        details::Details::parse_for(self.package_manager, &self.fetch_package_details(pkg))
+    }
+
+    // The details if `show` has run for the package; otherwise None, with
+    // `show` started in the background. For the UI thread, which a slow
+    // `apt show` must not hold up.
+    fn loaded_details(&self, pkg: &Package) -> Option<details::Details> {
+        let text =
+            self.shown
+                .get_or_start(&self.runner, self.package_manager, &pkg.name, &pkg.version)?;
+        Some(details::Details::parse_for(self.package_manager, &text))
+    }
+
+    // What an installed package depends on, each marked installed or not.
+    // None while its `show` runs in the background.
+    fn dependencies(&self, name: &str) -> Option<Vec<deptree::Dep>> {
+        let Some(pkg) = self.items.iter().find(|pkg| pkg.name == name) else {
+            return Some(Vec::new());
+        };
+        let details = self.loaded_details(pkg)?;
+        let key = |name: &str| names::canonical(self.package_manager, name);
+        let installed: HashSet<String> = self.items.iter().map(|pkg| key(&pkg.name)).collect();
+        Some(deptree::parse(self.package_manager, &details, |name| {
+            installed.contains(&key(name))
+        }))
+    }
+
+    // The list's own spelling of a dependency's name: pip's Requires may
+    // differ in case or separators.
+    fn installed_name(&self, name: &str) -> Option<String> {
+        let key = names::canonical(self.package_manager, name);
+        self.items
+            .iter()
+            .find(|pkg| names::canonical(self.package_manager, &pkg.name) == key)
+            .map(|pkg| pkg.name.clone())
+    }
+
+    // What the field renderers compare values with.
+    fn render_context(&self) -> renderers::Context {
+        renderers::Context {
+            largest: self.sizes.values().max().copied(),
+            now: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        }
+    }
+
+    // "Why installed" line for the detail pane.
+    fn why_text(&self, pkg: &Package) -> Option<String> {
+        let explanation = self.why.as_ref()?.explain(&pkg.name)?;
+        Some(format!("Why installed: {}\n", explanation))
+    }
+
+    // "Version history on this device" table for the detail pane.
+    fn history_text(&self, pkg: &Package) -> Option<String> {
+        let changes = self
+            .history
+            .get(&pkg.name)
+            .filter(|changes| !changes.is_empty())?;
+        let mut text = "Version history on this device:\nFirst seen  Version\n".to_string();
+        let mut previous = None;
+        for change in changes {
+            // Lines that only recorded a new size
+            if previous == Some(&change.version) {
+                continue;
+            }
+            previous = Some(&change.version);
+            text.push_str(&format!(
+                "{}  {}\n",
+                format_date(change.time),
+                change.version.as_deref().unwrap_or("(removed)")
+            ));
+        }
+        Some(text)
+    }
+}
+
+// What the cached detail text was built for.
+#[derive(PartialEq)]
+struct DetailKey {
+    manager: PackageManager,
+    name: String,
+    version: String,
+    view: tabs::View,
+    details_version: u64,
+}
+
+#[cfg(feature = "reports")]
+const REPORT_NAME: &str = "tpi-report.md";
+#[cfg(feature = "reports")]
+const BOOTSTRAP_NAME: &str = "tpi-bootstrap.sh";
+
+// Header of the manifest's packages that aren't installed, in a grouped list.
+const MISSING_GROUP: &str = "(not installed)";
+
+struct App {
+    should_exit: bool,
+    should_suspend: bool, // Ctrl+Z was pressed; handled by `run`
+    inline: bool,         // --no-alt-screen: drawn in a viewport below the prompt
+    package_list: PackageList,
+    details_scroll: u16,                     // Track scroll position for details
+    details_rows: (u16, u16), // Wrapped lines of the detail text and rows showing them, as last drawn
+    sort_hits: Vec<(Rect, sorting::Column)>, // Column headers of the list, as last drawn
+    details_version: u64,     // Bumped when the detail text of any package may change
+    detail_cache: Option<(DetailKey, String)>, // The `show`-backed view last drawn
+    details_height_percentage: u16, // Percentage for details section (30% by default)
+    arrangement: layout::Arrangement, // Of the list and details, as last drawn
+    details_page: bool,       // Compact arrangement: the details fill the screen
+    resized: bool,            // Clear the screen before the next draw
+    details_fetch: preview::Fetch, // When the detail pane runs its commands
+    details_delay: Duration,  // How long the selection rests before then
+    rest: preview::Rest,      // Of the selection, for the delay
+    prefetch: prefetch::Prefetch, // Details of the packages on screen, fetched when idle
+    keys: keymap::Keymap,     // Rebindable main-list keys
+    help: Option<help::Help>, // The `?` popup
+    highlight: (Style, String), // Style and symbol of the selected row
+    message: Option<String>,  // Feedback from the last action, on the status bar
+    message_expiry: status::Expiry, // Clears the message after a few seconds
+    show_diagnostics: bool,
+    action_menu: Option<menu::ActionMenu>,
+    group_menu: Option<menu::Menu<GroupBy>>,
+    priority_menu: Option<menu::Menu<PriorityChoice>>,
+    section_menu: Option<(menu::Menu<SectionChoice>, Vec<String>)>, // With the sections it lists
+    drift_menu: Option<menu::Menu<DriftChoice>>,
+    dep_tree: Option<deptree::DepTree>, // Over the detail pane, from `t`
+    dep_wait: Option<String>,           // Package whose dependencies the tree waits for
+    rdepends: Option<rdepends::Lookup>, // Over the detail pane, from `y`
+    failures: Option<failures::Failures>, // Instead of everything else when no manager lists
+    loader: loader::Loader,             // Lists read in the background, at startup and on Tab
+    switch_menu: Option<menu::Menu<usize>>, // Index into package_list.switches
+    show_priority: bool,                // Priority column, toggled from the priority menu
+    show_section: bool,                 // Section column, toggled from the sections popup
+    read_only: bool,                    // --read-only: no action may change installed packages
+    root_banner: bool,                  // Run as root and the warning not dismissed
+    terminal_title: Option<String>,     // As last set, with $TPI_TERMINAL_TITLE
+    watch_interval: Option<Duration>,   // From --watch; W uses the default otherwise
+    health: Option<dashboard::Dashboard>, // Kept while hidden so results survive a jump to the list
+    show_health: bool,
+    watch: Option<watch::Watch>, // Periodic refresh, toggled with W or started by --watch
+    managers: config::Managers,  // What Tab cycles through
+    search: Option<search::Search>, // `/` query highlighted in both panes
+    holds: Option<holds::HoldView>,
+    growth: Option<growth::GrowthView>,
+    stats: Option<stats::StatsView>, // From `L`, collected again when its source changes
+    upgrades: Option<upgrades::UpgradesView>, // What the last refresh upgraded
+    impact: Option<impact::ImpactView>,
+    sessions: Option<sessions::SessionsView>,
+    owner_prompt: Option<String>, // Command typed after F, to find its package
+    mark_prompt: Option<(bool, String)>, // Marking (or unmarking) by pattern, and the pattern
+    history: undo::History,       // Mark, hide and do-not-upgrade changes, for `u` and Ctrl+r
+    export: Option<backup::ExportView>,
+    install: Option<install::InstallCommands>,
+    quit_menu: Option<menu::Menu<QuitChoice>>,
+    quit_when_done: bool,         // Exit once no background operation runs
+    abandon: bool,                // Exit without joining worker threads
+    workers: Vec<JoinHandle<()>>, // Of closed exports, joined on exit
+    drawn_age: String,            // List age as last drawn, redrawn when it changes
+    repos: Option<repos::RepoView>,
+    confirm: Option<menu::Confirm<(Action, Vec<String>)>>, // Pending hold, unhold or removal
+    install_prompt: Option<String>,                        // Name typed after i, to install
+    foreground: Option<(PackageManager, Action, Vec<String>)>, // Installs or removes once the screen is handed over
+    parked: HashMap<PackageManager, PackageList>, // Lists Tab switched away from, shown as they were on return
+    inventory_prompt: Option<String>,             // Path typed after e, to export the list to
+    file_prompt: Option<String>, // Path typed after Ctrl+o, of a .deb or wheel to inspect
+    inspect: Option<inspect::InspectView>,
+    usage: Option<usage::UsageView>, // `%`, over the list until closed
+    rollback: Option<rollback::Plan>, // Confirmed `<`, until the install is back and a hold offered
+    no_rollback: Option<rollback::NoCache>, // `<` with no older .deb cached
+    tabs: tabs::DetailTabs,          // Which view the detail pane shows
+    tour: Option<tour::Tour>,        // Until finished or ended with Esc
+    started: Instant,                // For the first-paint time in the debug log
+    python: Option<python::Upgrade>, // pip packages left behind by a python upgrade
+    python_check: Option<Receiver<Option<python::Upgrade>>>, // The startup one, until it is done
+    show_python: bool,
+    no_upgrade: noupgrade::NoUpgrade,
+    include_listed: bool, // The python reinstall command overrides the do-not-upgrade list
+}
+
+impl App {
+    fn new(options: &cli::Options) -> Self {
+        Self::with_runner(Arc::new(SystemRunner), options)
+    }
+
+    // Loads only the manager shown first, in the background: --manager, else
+    // the first enabled one that can be listed, trying the config file's
+    // manager first. Until it arrives the list is the one cached last time,
+    // if any, of the manager most likely to be shown.
+    fn with_runner(runner: Arc<dyn Runner>, options: &cli::Options) -> Self {
+        let started = Instant::now();
+        let (settings, problem) = configfile::load(options.config.as_deref());
+        startup::step("config load", started);
+        let managers = config::managers();
+        let mut tried = managers.enabled.clone();
+        if let Some(preferred) = settings.manager.filter(|m| tried.contains(m)) {
+            tried.retain(|m| *m != preferred);
+            tried.insert(0, preferred);
+        }
+        let manager = options
+            .manager
+            .or(tried.first().copied())
+            .unwrap_or(PackageManager::Pkg);
+        let reading = Instant::now();
+        let cached = Listing {
+            packages: listcache::load(manager).unwrap_or_default(),
+            ..Listing::default()
+        };
+        let mut package_list = PackageList::with_listing(manager, runner.clone(), cached, None);
+        package_list.profile = options.profile;
+        package_list.hidden = hidden::Hidden::load();
+        startup::step("cache read", reading);
+        let mut loader = loader::Loader::new();
+        let (enabled, asked, profile) = (tried, options.manager, options.profile);
+        let load_runner = runner.clone();
+        loader.start(manager, move || {
+            first_list(load_runner, &enabled, asked, profile)
+        });
+        let watch_interval = options.watch.map(Duration::from_secs);
+        // It runs python, so it waits its turn like the list does
+        let python_check = (managers.enabled.contains(&PackageManager::Pip)
+            && options.profile == config::Profile::Full)
+            .then(|| {
+                let (sender, receiver) = mpsc::channel();
+                let runner = runner.clone();
+                thread::spawn(move || {
+                    let _ = sender.send(python::check(runner.as_ref()));
+                });
+                receiver
+            });
+        Self {
+            should_exit: false,
+            should_suspend: false,
+            inline: options.no_alt_screen,
+            message: problem.map(|err| i18n::fill("message.config_not_used", &[("error", &err)])),
+            message_expiry: status::Expiry::new(),
+            failures: None,
+            loader,
+            package_list,
+            managers,
+            details_scroll: 0,
+            details_rows: (0, 0),
+            sort_hits: Vec::new(),
+            details_version: 0,
+            detail_cache: None,
+            details_height_percentage: settings.details_height,
+            arrangement: layout::Arrangement::Stacked,
+            details_page: false,
+            resized: false,
+            details_fetch: settings.details,
+            details_delay: settings.details_delay,
+            rest: preview::Rest::new(),
+            prefetch: prefetch::Prefetch::new(settings.prefetch),
+            keys: settings.keys,
+            help: None,
+            highlight: (settings.highlight, settings.symbol),
+            show_diagnostics: false,
+            action_menu: None,
+            group_menu: None,
+            priority_menu: None,
+            section_menu: None,
+            drift_menu: None,
+            dep_tree: None,
+            dep_wait: None,
+            rdepends: None,
+            switch_menu: None,
+            show_priority: false,
+            show_section: false,
+            read_only: options.read_only || (root::detected() && !config::allow_root()),
+            root_banner: root::detected() && !root::dismissed(),
+            terminal_title: None,
+            watch_interval,
+            health: None,
+            show_health: false,
+            watch: watch_interval.map(watch::Watch::new),
+            search: None,
+            holds: None,
+            growth: None,
+            stats: None,
+            upgrades: None,
+            impact: None,
+            sessions: None,
+            owner_prompt: None,
+            mark_prompt: None,
+            history: undo::History::default(),
+            export: None,
+            install: None,
+            quit_menu: None,
+            quit_when_done: false,
+            abandon: false,
+            workers: Vec::new(),
+            drawn_age: String::new(),
+            repos: None,
+            confirm: None,
+            install_prompt: None,
+            inventory_prompt: None,
+            foreground: None,
+            file_prompt: None,
+            inspect: None,
+            usage: None,
+            rollback: None,
+            no_rollback: None,
+            parked: HashMap::new(),
+            tour: (options.tour || !tour::completed()).then(tour::Tour::new),
+            tabs: tabs::DetailTabs::new(),
+            started,
+            python: None,
+            python_check,
+            show_python: false,
+            no_upgrade: noupgrade::NoUpgrade::load(),
+            include_listed: false,
+        }
+    }
+
+    // Actions that make sense for the selected package in its current state,
+    // each with the key that runs it directly.
+    fn package_actions(&self, pkg: &Package) -> Vec<(String, KeyCode)> {
+        let list = &self.package_list;
+        let mut entries = Vec::new();
+
+        let mark = if list.is_marked(&pkg.name) {
+            i18n::text("action.unmark")
+        } else {
+            i18n::text("action.mark")
+        };
+        entries.push((mark.to_string(), KeyCode::Char(' ')));
+        entries.push((
+            i18n::text("action.mark_all").to_string(),
+            KeyCode::Char('a'),
+        ));
+        entries.push((
+            i18n::text("action.unmark_all").to_string(),
+            KeyCode::Char('A'),
+        ));
+        entries.push((i18n::text("action.invert").to_string(), KeyCode::Char('~')));
+        entries.push((
+            i18n::text("action.mark_pattern").to_string(),
+            KeyCode::Char('m'),
+        ));
+        if let Some(change) = self.history.last() {
+            entries.push((
+                i18n::fill("action.undo", &[("change", &change.label())]),
+                KeyCode::Char('u'),
+            ));
+        }
+        let hide = if !list.marked_names().is_empty() {
+            i18n::text("action.hide_marked")
+        } else if list.is_hidden(pkg) {
+            i18n::text("action.unhide")
+        } else {
+            i18n::text("action.hide")
+        };
+        entries.push((hide.to_string(), KeyCode::Char('z')));
+        let reveal = if list.show_hidden {
+            i18n::text("action.leave_hidden")
+        } else {
+            i18n::text("action.show_hidden")
+        };
+        entries.push((reveal.to_string(), KeyCode::Char('Z')));
+
+        // Offered while the details load; `w` says so if there is none
+        if list
+            .loaded_details(pkg)
+            .is_none_or(|details| details.homepage().is_some())
+        {
+            entries.push((
+                i18n::text("action.homepage").to_string(),
+                KeyCode::Char('w'),
+            ));
+        }
+
+        if self.allows(Action::Hold) {
+            let hold = if !list.marked_names().is_empty() {
+                i18n::text("action.hold_marked")
+            } else if list.held.contains(&pkg.name) {
+                i18n::text("action.unhold")
+            } else {
+                i18n::text("action.hold")
+            };
+            entries.push((hold.to_string(), KeyCode::Char('H')));
+        }
+        if commands::supports(list.package_manager, Action::Held) {
+            entries.push((i18n::text("action.held").to_string(), KeyCode::Char('P')));
+        }
+        if list.switches.len() > 1 {
+            entries.push((i18n::text("action.switch").to_string(), KeyCode::Char('S')));
+        }
+        if commands::supports(list.package_manager, Action::Upgradable) {
+            let upgradable = if !list.upgradable.contains_key(&list.package_manager) {
+                i18n::text("action.check_upgrades")
+            } else if matches!(list.filter, Some(ListFilter::Upgradable)) {
+                i18n::text("action.list_all")
+            } else {
+                i18n::text("action.list_upgradable")
+            };
+            entries.push((upgradable.to_string(), KeyCode::Char('V')));
+        }
+        if commands::supports(list.package_manager, Action::Rdepends) {
+            let orphans = if matches!(list.filter, Some(ListFilter::Orphans)) {
+                i18n::text("action.list_all")
+            } else {
+                i18n::text("action.list_orphans")
+            };
+            entries.push((orphans.to_string(), KeyCode::Char('c')));
+        }
+        if list.package_manager == PackageManager::Pip {
+            let label = if self.no_upgrade.contains(&pkg.name) {
+                i18n::text("action.allow_pip")
+            } else {
+                i18n::text("action.stop_pip")
+            };
+            entries.push((label.to_string(), KeyCode::Char('U')));
+        }
+        if list.why.is_some() {
+            let impact = if !list.marked_names().is_empty() {
+                i18n::text("action.impact_marked")
+            } else {
+                i18n::text("action.impact")
+            };
+            entries.push((impact.to_string(), KeyCode::Char('X')));
+        }
+        if matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt | PackageManager::Pip
+        ) {
+            entries.push((
+                i18n::text("action.rdepends").to_string(),
+                KeyCode::Char('y'),
+            ));
+        }
+        entries.push((
+            i18n::text("action.statistics").to_string(),
+            KeyCode::Char('L'),
+        ));
+
+        let export = if list.marked.is_empty() {
+            i18n::text("action.export_files")
+        } else {
+            i18n::text("action.export_marked_files")
+        };
+        entries.push((export.to_string(), KeyCode::Char('E')));
+        let marked = list.marked_names().len();
+        let inventory = if marked == 0 {
+            i18n::text("action.export_list")
+        } else {
+            i18n::text("action.export_marked")
+        };
+        entries.push((inventory.to_string(), KeyCode::Char('e')));
+        if self.allows(Action::Remove) && !self.root_banner {
+            let remove = match marked {
+                0 => i18n::fill("action.remove", &[("name", &pkg.name)]),
+                1 => i18n::text("action.remove_marked_one").to_string(),
+                _ => i18n::fill("action.remove_marked", &[("count", &marked)]),
+            };
+            entries.push((remove, KeyCode::Char('x')));
+        }
+        if self.allows(Action::Install) {
+            entries.push((i18n::text("action.install").to_string(), KeyCode::Char('i')));
+        }
+        if let Some(rollback) = self.rollback_label(pkg) {
+            entries.push((rollback, KeyCode::Char('<')));
+        }
+        let install = if list.marked.is_empty() {
+            i18n::text("action.install_command")
+        } else {
+            i18n::text("action.install_commands")
+        };
+        entries.push((install.to_string(), KeyCode::Char('I')));
+        entries.push((
+            i18n::text("action.copy_name").to_string(),
+            KeyCode::Char('Y'),
+        ));
+        entries.push((i18n::text("action.session").to_string(), KeyCode::Char('s')));
+        entries.push((
+            i18n::fill("action.sort", &[("order", &list.sort_by.next().label())]),
+            KeyCode::Char('o'),
+        ));
+        let drift = if list.drift.is_some() {
+            i18n::text("action.drift")
+        } else {
+            i18n::text("action.compare")
+        };
+        entries.push((drift.to_string(), KeyCode::Char('d')));
+        entries.push((i18n::text("action.owner").to_string(), KeyCode::Char('F')));
+
+        #[cfg(feature = "reports")]
+        {
+            if !list.marked_names().is_empty() {
+                entries.push((
+                    i18n::text("action.report_marked").to_string(),
+                    KeyCode::Char('M'),
+                ));
+            }
+            entries.push((
+                i18n::text("action.bootstrap").to_string(),
+                KeyCode::Char('B'),
+            ));
+        }
+
+        entries
+    }
+
+    fn open_action_menu(&mut self) {
+        let Some(pkg) = self.package_list.selected() else {
+            self.message = Some(i18n::text("message.no_selection").to_string());
+            return;
+        };
+        let title = format!("{} {}", pkg.name, pkg.version);
+        let entries = self.package_actions(pkg);
+        self.action_menu = Some(menu::ActionMenu::actions(title, entries));
+    }
+
+    fn open_group_menu(&mut self) {
+        let list = &self.package_list;
+        let entries = GroupBy::ALL
+            .into_iter()
+            .filter(|group_by| group_by.applies_to(list.package_manager))
+            .map(|group_by| menu::MenuEntry {
+                label: group_by.label().to_string(),
+                hint: if group_by == list.group_by {
+                    i18n::text("menu.current").to_string()
+                } else {
+                    String::new()
+                },
+                value: group_by,
+            })
+            .collect();
+        let mut group_menu = menu::Menu::new(i18n::text("menu.group_by").to_string(), entries);
+        group_menu.select_value(list.group_by);
+        self.group_menu = Some(group_menu);
+    }
+
+    fn open_switch_menu(&mut self) {
+        let list = &self.package_list;
+        if list.package_manager != PackageManager::Opam {
+            self.message = Some(i18n::text("message.switches_opam").to_string());
+            return;
+        }
+        if list.switches.len() < 2 {
+            self.message = Some(i18n::text("message.one_switch").to_string());
+            return;
+        }
+        let entries = list
+            .switches
+            .iter()
+            .enumerate()
+            .map(|(i, switch)| menu::MenuEntry {
+                label: switch.clone(),
+                hint: if list.switch.as_ref() == Some(switch) {
+                    i18n::text("menu.current").to_string()
+                } else {
+                    String::new()
+                },
+                value: i,
+            })
+            .collect();
+        let mut switch_menu = menu::Menu::new(i18n::text("menu.switch").to_string(), entries);
+        if let Some(i) = list
+            .switches
+            .iter()
+            .position(|switch| list.switch.as_ref() == Some(switch))
+        {
+            switch_menu.select_value(i);
+        }
+        self.switch_menu = Some(switch_menu);
+    }
+
+    fn open_priority_menu(&mut self) {
+        let list = &self.package_list;
+        if !matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            self.message = Some(i18n::text("message.priorities_dpkg").to_string());
+            return;
+        }
+        if list.items.iter().all(|pkg| pkg.priority.is_none()) {
+            self.message = Some(i18n::text("message.no_priorities").to_string());
+            return;
+        }
+        let current = match list.filter {
+            Some(ListFilter::Priority(priority)) => PriorityChoice::Only(priority),
+            _ => PriorityChoice::All,
+        };
+        let hint = |choice: PriorityChoice, count: usize| {
+            if choice == current {
+                i18n::fill("menu.current_count", &[("count", &count)])
+            } else {
+                count.to_string()
+            }
+        };
+        let mut entries = vec![menu::MenuEntry {
+            label: i18n::text("menu.all_priorities").to_string(),
+            hint: hint(PriorityChoice::All, list.items.len()),
+            value: PriorityChoice::All,
+        }];
+        for priority in grouping::PRIORITIES {
+            let count = list
+                .items
+                .iter()
+                .filter(|pkg| pkg.priority.as_deref() == Some(priority))
+                .count();
+            let choice = PriorityChoice::Only(priority);
+            entries.push(menu::MenuEntry {
+                label: priority.to_string(),
+                hint: hint(choice, count),
+                value: choice,
+            });
+        }
+        entries.push(menu::MenuEntry {
+            label: if self.show_priority {
+                i18n::text("menu.hide_priority").to_string()
+            } else {
+                i18n::text("menu.show_priority").to_string()
+            },
+            hint: String::new(),
+            value: PriorityChoice::Column,
+        });
+        let mut priority_menu = menu::Menu::new(i18n::text("menu.priority").to_string(), entries);
+        priority_menu.select_value(current);
+        self.priority_menu = Some(priority_menu);
+    }
+
+    fn pick_priority(&mut self, choice: PriorityChoice) {
+        let list = &mut self.package_list;
+        match choice {
+            PriorityChoice::Column => self.show_priority = !self.show_priority,
+            PriorityChoice::All => {
+                if matches!(list.filter, Some(ListFilter::Priority(_))) {
+                    list.filter = None;
+                }
+            }
+            PriorityChoice::Only(priority) => list.filter = Some(ListFilter::Priority(priority)),
+        }
+        list.rebuild_rows();
+    }
+
+    // `#`: the sections of the installed packages, most packages first, to
+    // narrow the list to one.
+    fn open_section_menu(&mut self) {
+        let list = &self.package_list;
+        if !matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            self.message = Some(i18n::text("message.sections_dpkg").to_string());
+            return;
+        }
+        if list.items.iter().all(|pkg| pkg.section.is_none()) {
+            self.message = Some(i18n::text("message.no_sections").to_string());
+            return;
+        }
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for pkg in &list.items {
+            *counts.entry(sections::of(pkg)).or_default() += 1;
+        }
+        let mut counted: Vec<(&str, usize)> = counts.into_iter().collect();
+        counted.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        let sections: Vec<String> = counted.iter().map(|(name, _)| name.to_string()).collect();
+
+        let current = match &list.filter {
+            Some(ListFilter::Section(section)) => sections
+                .iter()
+                .position(|name| name == section)
+                .map_or(SectionChoice::All, SectionChoice::Only),
+            _ => SectionChoice::All,
+        };
+        let hint = |choice: SectionChoice, count: usize| {
+            if choice == current {
+                i18n::fill("menu.current_count", &[("count", &count)])
+            } else {
+                count.to_string()
+            }
+        };
+        let mut entries = vec![menu::MenuEntry {
+            label: i18n::text("menu.all_sections").to_string(),
+            hint: hint(SectionChoice::All, list.items.len()),
+            value: SectionChoice::All,
+        }];
+        for (i, (name, count)) in counted.iter().enumerate() {
+            entries.push(menu::MenuEntry {
+                label: sections::title(name),
+                hint: hint(SectionChoice::Only(i), *count),
+                value: SectionChoice::Only(i),
+            });
+        }
+        entries.push(menu::MenuEntry {
+            label: if self.show_section {
+                i18n::text("menu.hide_section").to_string()
+            } else {
+                i18n::text("menu.show_section").to_string()
+            },
+            hint: String::new(),
+            value: SectionChoice::Column,
+        });
+        let mut section_menu = menu::Menu::new(i18n::text("menu.sections").to_string(), entries);
+        section_menu.select_value(current);
+        self.section_menu = Some((section_menu, sections));
+    }
+
+    fn pick_section(&mut self, choice: SectionChoice, sections: Vec<String>) {
+        let list = &mut self.package_list;
+        match choice {
+            SectionChoice::Column => self.show_section = !self.show_section,
+            SectionChoice::All => {
+                if matches!(list.filter, Some(ListFilter::Section(_))) {
+                    list.filter = None;
+                }
+            }
+            SectionChoice::Only(i) => {
+                if let Some(section) = sections.into_iter().nth(i) {
+                    list.filter = Some(ListFilter::Section(section));
+                }
+            }
+        }
+        list.rebuild_rows();
+    }
+
+    fn handle_failures_key(&mut self, key: KeyEvent) {
+        let Some(failures) = &mut self.failures else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('q') => self.should_exit = true,
+            KeyCode::Esc if failures.doctor.is_none() => self.should_exit = true,
+            KeyCode::Esc | KeyCode::Char('d') if failures.doctor.is_some() => {
+                failures.doctor = None;
+                failures.scroll = 0;
+            }
+            KeyCode::Char('d') => {
+                failures.doctor = Some(doctor::run(self.package_list.runner.as_ref()));
+                failures.scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                failures.scroll = failures.scroll.saturating_add(1)
+            }
+            KeyCode::Up | KeyCode::Char('k') => failures.scroll = failures.scroll.saturating_sub(1),
+            KeyCode::Char('r') => self.retry_detection(),
+            _ => {}
+        }
+    }
+
+    // Tries every enabled manager again, as at startup; with $TPI_MANAGERS
+    // and PATH read afresh.
+    fn retry_detection(&mut self) {
+        self.managers = config::managers();
+        let (list, errors) = first_list(
+            self.package_list.runner.clone(),
+            &self.managers.enabled,
+            None,
+            self.package_list.profile,
+        );
+        if list.load_error.is_some() {
+            self.failures = Some(failures::Failures::new(errors));
+            return;
+        }
+        self.failures = None;
+        self.message = Some(i18n::fill(
+            "message.listed",
+            &[("manager", &list.package_manager.name())],
+        ));
+        self.loader.cancel();
+        self.package_list = list;
+        self.details_scroll = 0;
+    }
+
+    // The first `V` fetches what the manager can upgrade and shows the newer
+    // versions in the list; later ones toggle listing only those.
+    fn toggle_upgradable(&mut self) {
+        let list = &mut self.package_list;
+        let manager = list.package_manager;
+        if list.upgradable.contains_key(&manager) {
+            list.filter = match list.filter {
+                Some(ListFilter::Upgradable) => None,
+                _ => Some(ListFilter::Upgradable),
+            };
+            list.rebuild_rows();
+            return;
+        }
+        self.message = Some(match outdated::load(list.runner.as_ref(), manager) {
+            None => i18n::fill(
+                "message.upgradable_unknown",
+                &[("manager", &manager.name())],
+            ),
+            Some(Err(err)) => i18n::fill(
+                "message.upgradable_failed",
+                &[("manager", &manager.name()), ("error", &err)],
+            ),
+            Some(Ok(found)) => {
+                let found: HashMap<String, outdated::Outdated> = found
+                    .into_iter()
+                    .map(|pkg| (pkg.name.clone(), pkg))
+                    .collect();
+                let count = list
+                    .items
+                    .iter()
+                    .filter(|pkg| {
+                        found
+                            .get(&pkg.name)
+                            .is_some_and(|outdated| outdated.installed == pkg.version)
+                    })
+                    .count();
+                list.upgradable.insert(manager, found);
+                self.details_version += 1;
+                match count {
+                    0 => i18n::fill("message.up_to_date", &[("manager", &manager.name())]),
+                    1 => i18n::text("message.upgradable_one").to_string(),
+                    n => i18n::fill("message.upgradable", &[("count", &n)]),
+                }
+            }
+        });
+    }
+
+    // Lists only the likely orphans, scanning for them the first time.
+    fn toggle_orphans(&mut self) {
+        let list = &mut self.package_list;
+        if !commands::supports(list.package_manager, Action::Rdepends) {
+            self.message = Some(i18n::fill(
+                "message.no_auto",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        }
+        if matches!(list.filter, Some(ListFilter::Orphans)) {
+            list.filter = None;
+        } else {
+            list.scan_orphans();
+            list.filter = Some(ListFilter::Orphans);
+        }
+        list.rebuild_rows();
+    }
+
+    // Dependencies of the selected package as a tree over the detail pane.
+    fn open_dep_tree(&mut self) {
+        let list = &self.package_list;
+        let manager = list.package_manager;
+        if !matches!(
+            manager,
+            PackageManager::Pkg | PackageManager::Apt | PackageManager::Pip
+        ) {
+            self.message = Some(i18n::fill(
+                "message.no_dep_tree",
+                &[("manager", &manager.name())],
+            ));
+            return;
+        }
+        let Some(pkg) = list.selected() else {
+            return;
+        };
+        let name = pkg.name.clone();
+        match list.dependencies(&name) {
+            Some(deps) => self.dep_tree = Some(deptree::DepTree::new(name, deps)),
+            None => self.wait_for_dependencies(name),
+        }
+    }
+
+    fn wait_for_dependencies(&mut self, name: String) {
+        self.message = Some(i18n::fill("message.loading_deps", &[("name", &name)]));
+        self.dep_wait = Some(name);
+    }
+
+    // Opens or expands the tree once the package it waits for has loaded,
+    // unless the selection (or the tree's) has moved on meanwhile.
+    fn poll_dep_wait(&mut self) -> bool {
+        let Some(name) = &self.dep_wait else {
+            return false;
+        };
+        let Some(deps) = self.package_list.dependencies(name) else {
+            return false;
+        };
+        let name = self.dep_wait.take().unwrap_or_default();
+        self.message = None;
+        match &mut self.dep_tree {
+            Some(tree) if tree.expandable() == Some(name.as_str()) => tree.expand(deps),
+            Some(_) => {}
+            None if self
+                .package_list
+                .selected()
+                .is_some_and(|pkg| pkg.name == name) =>
+            {
+                self.dep_tree = Some(deptree::DepTree::new(name, deps));
+            }
+            None => {}
+        }
+        true
+    }
+
+    fn handle_dep_tree_key(&mut self, key: KeyEvent) {
+        let Some(tree) = &mut self.dep_tree else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 't') => self.dep_tree = None,
+            KeyCode::Down | KeyCode::Char('j') => tree.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => tree.select_previous(),
+            KeyCode::Right | KeyCode::Char('l' | ' ') => {
+                if let Some(name) = tree.expandable().map(String::from) {
+                    match self.package_list.dependencies(&name) {
+                        Some(deps) => {
+                            if let Some(tree) = &mut self.dep_tree {
+                                tree.expand(deps);
+                            }
+                        }
+                        None => self.wait_for_dependencies(name),
+                    }
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => tree.collapse(),
+            KeyCode::Enter => {
+                let Some(dep) = tree.selected() else {
+                    return;
+                };
+                match self.package_list.installed_name(&dep.name) {
+                    Some(name) => {
+                        self.dep_tree = None;
+                        let manager = self.package_list.package_manager;
+                        self.jump_to(manager, &name);
+                    }
+                    None => {
+                        self.message =
+                            Some(i18n::fill("message.not_installed", &[("name", &dep.name)]))
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // What the statistics come from. Broken packages are known once the
+    // health dashboard has run its check.
+    fn stats_source(&self) -> stats::Source {
+        let list = &self.package_list;
+        let broken = self.health.as_ref().and_then(|health| {
+            health.entries.iter().find_map(|entry| match &entry.state {
+                dashboard::State::Done(Ok(findings))
+                    if entry.check == health::Check::BrokenState =>
+                {
+                    Some(findings.len())
+                }
+                _ => None,
+            })
+        });
+        (
+            list.package_manager,
+            list.loaded_at,
+            list.upgradable.get(&list.package_manager).map(HashMap::len),
+            broken,
+            list.dpkg_log.is_read(),
+        )
+    }
+
+    // The other managers are counted from their cached lists, so nothing
+    // is listed again.
+    fn collect_stats(&self, source: stats::Source) -> stats::StatsView {
+        let others = self
+            .managers
+            .enabled
+            .iter()
+            .filter(|&&manager| manager != self.package_list.package_manager)
+            .filter_map(|&manager| Some((manager, listcache::load(manager)?.len(), true)))
+            .collect();
+        stats::StatsView {
+            stats: stats::Stats::collect(&self.package_list, others, source.3),
+            source,
+            scroll: 0,
+        }
+    }
+
+    fn open_stats(&mut self) {
+        self.stats = Some(self.collect_stats(self.stats_source()));
+    }
+
+    // Collects the statistics again if the list they came from changed.
+    fn refresh_stats(&mut self) -> bool {
+        let Some(view) = &self.stats else {
+            return false;
+        };
+        let source = self.stats_source();
+        if view.source == source {
+            return false;
+        }
+        let scroll = view.scroll;
+        let mut view = self.collect_stats(source);
+        view.scroll = scroll;
+        self.stats = Some(view);
+        true
+    }
+
+    // `Y`: the selected package's name, to paste into another app.
+    fn copy_name(&mut self) {
+        let Some(name) = self.package_list.selected().map(|pkg| pkg.name.clone()) else {
+            return;
+        };
+        self.message = Some(match clipboard::copy(&name) {
+            Ok(via) => i18n::fill("message.copied_name", &[("name", &name), ("via", &via)]),
+            Err(err) => err,
+        });
+    }
+
+    // Ctrl+y: the text of the detail pane's current view.
+    fn copy_details(&mut self) {
+        let Some(name) = self.package_list.selected().map(|pkg| pkg.name.clone()) else {
+            return;
+        };
+        let text = self.detail_content();
+        if self.placeholders().contains(&text) {
+            self.message = Some(i18n::fill("message.details_loading", &[("name", &name)]));
+            return;
+        }
+        self.message = Some(match clipboard::copy(&text) {
+            Ok(via) => i18n::fill(
+                "message.copied_view",
+                &[
+                    ("view", &self.tabs.view.label()),
+                    ("name", &name),
+                    ("count", &text.lines().count()),
+                    ("via", &via),
+                ],
+            ),
+            Err(err) => err,
+        });
+    }
+
+    // What installed packages depend on the selected one, over the detail pane.
+    fn open_rdepends(&mut self) {
+        let list = &self.package_list;
+        let manager = list.package_manager;
+        if !matches!(
+            manager,
+            PackageManager::Pkg | PackageManager::Apt | PackageManager::Pip
+        ) {
+            self.message = Some(i18n::fill(
+                "message.no_rdepends",
+                &[("manager", &manager.name())],
+            ));
+            return;
+        }
+        let Some(pkg) = list.selected() else {
+            return;
+        };
+        let graph = list
+            .why
+            .as_ref()
+            .map(|explainer| &explainer.graph)
+            .or(list.requires.as_ref());
+        self.rdepends = Some(rdepends::Lookup::start(
+            list.runner.clone(),
+            manager,
+            pkg.name.clone(),
+            graph,
+        ));
+    }
+
+    // Takes in the lookup's answer, keeping pip's Requires for the next one.
+    fn poll_rdepends(&mut self) -> bool {
+        let Some(lookup) = &mut self.rdepends else {
+            return false;
+        };
+        let changed = lookup.poll();
+        if let Some(graph) = lookup.take_graph() {
+            self.package_list.requires = Some(graph);
+        }
+        changed
+    }
+
+    fn handle_rdepends_key(&mut self, key: KeyEvent) {
+        let Some(lookup) = &mut self.rdepends else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'y') => self.rdepends = None,
+            KeyCode::Down | KeyCode::Char('j') => lookup.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => lookup.select_previous(),
+            KeyCode::Enter => {
+                let Some(dependent) = lookup.selected() else {
+                    return;
+                };
+                match self.package_list.installed_name(dependent) {
+                    Some(name) => {
+                        self.rdepends = None;
+                        let manager = self.package_list.package_manager;
+                        self.jump_to(manager, &name);
+                    }
+                    None => {
+                        self.message =
+                            Some(i18n::fill("message.not_listed", &[("name", &dependent)]))
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The first `d` compares the list with the manifest; later ones offer its
+    // filters, rewriting it and stopping.
+    fn open_drift_menu(&mut self) {
+        let list = &self.package_list;
+        let Some(drift) = &list.drift else {
+            self.compare_with_manifest();
+            return;
+        };
+        let current = match list.filter {
+            Some(ListFilter::Drift(state)) => DriftChoice::Only(state),
+            _ => DriftChoice::All,
+        };
+        let hint = |choice: DriftChoice, count: usize| {
+            if choice == current {
+                i18n::fill("menu.current_count", &[("count", &count)])
+            } else {
+                count.to_string()
+            }
+        };
+        let mut entries = vec![menu::MenuEntry {
+            label: i18n::text("drift.everything").to_string(),
+            hint: hint(DriftChoice::All, list.items.len() + drift.missing.len()),
+            value: DriftChoice::All,
+        }];
+        for state in drift::State::ALL {
+            let count = match state {
+                drift::State::Missing => drift.missing.len(),
+                _ => list
+                    .items
+                    .iter()
+                    .filter(|pkg| list.drift_state(pkg) == Some(state))
+                    .count(),
+            };
+            let choice = DriftChoice::Only(state);
+            entries.push(menu::MenuEntry {
+                label: i18n::fill("drift.only", &[("state", &state.label())]),
+                hint: hint(choice, count),
+                value: choice,
+            });
+        }
+        let file = drift.manifest.path.display().to_string();
+        #[cfg(feature = "reports")]
+        entries.push(menu::MenuEntry {
+            label: i18n::fill("drift.update", &[("file", &file)]),
+            hint: String::new(),
+            value: DriftChoice::Update,
+        });
+        entries.push(menu::MenuEntry {
+            label: i18n::text("drift.stop").to_string(),
+            hint: String::new(),
+            value: DriftChoice::Stop,
+        });
+        let mut drift_menu =
+            menu::Menu::new(i18n::fill("drift.title", &[("file", &file)]), entries);
+        drift_menu.select_value(current);
+        self.drift_menu = Some(drift_menu);
+    }
+
+    fn compare_with_manifest(&mut self) {
+        let path = config::manifest_path();
+        match drift::Manifest::load(&path) {
+            Ok(manifest) => {
+                let list = &mut self.package_list;
+                list.set_manifest(Some(Arc::new(manifest)));
+                let added = list
+                    .items
+                    .iter()
+                    .filter(|pkg| list.drift_state(pkg) == Some(drift::State::Added))
+                    .count();
+                let missing = list.drift.as_ref().map_or(0, |drift| drift.missing.len());
+                self.message = Some(i18n::fill(
+                    "message.compared",
+                    &[
+                        ("file", &path.display()),
+                        ("added", &added),
+                        ("missing", &missing),
+                    ],
+                ));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.message = Some(i18n::fill(
+                    "message.no_manifest",
+                    &[("file", &path.display())],
+                ));
+            }
+            Err(err) => {
+                self.message = Some(i18n::fill(
+                    "message.compare_failed",
+                    &[("file", &path.display()), ("error", &err)],
+                ));
+            }
+        }
+    }
+
+    fn pick_drift(&mut self, choice: DriftChoice) {
+        let list = &mut self.package_list;
+        match choice {
+            DriftChoice::All => {
+                if matches!(list.filter, Some(ListFilter::Drift(_))) {
+                    list.filter = None;
+                }
+            }
+            DriftChoice::Only(state) => list.filter = Some(ListFilter::Drift(state)),
+            #[cfg(feature = "reports")]
+            DriftChoice::Update => {
+                self.update_manifest();
+                return;
+            }
+            DriftChoice::Stop => {
+                list.set_manifest(None);
+                return;
+            }
+        }
+        list.rebuild_rows();
+    }
+
+    // Writes the manifest again as `tpi bootstrap` would (every manually
+    // installed package of the enabled managers), then compares with that.
+    #[cfg(feature = "reports")]
+    fn update_manifest(&mut self) {
+        let Some(path) = self
+            .package_list
+            .drift
+            .as_ref()
+            .map(|drift| drift.manifest.path.clone())
+        else {
+            return;
+        };
+        let sections = bootstrap::full_inventory(self.package_list.runner.as_ref());
+        let written = bootstrap::write_script(&path, &bootstrap::script(&sections))
+            .and_then(|()| drift::Manifest::load(&path));
+        self.message = Some(match written {
+            Ok(manifest) => {
+                self.package_list.set_manifest(Some(Arc::new(manifest)));
+                i18n::fill("message.manifest_updated", &[("file", &path.display())])
+            }
+            Err(err) => i18n::fill(
+                "message.manifest_failed",
+                &[("file", &path.display()), ("error", &err)],
+            ),
+        });
+    }
+
+    // Whether the action can be offered for the active manager in this mode.
+    fn allows(&self, action: Action) -> bool {
+        !(self.read_only && action.is_mutating())
+            && commands::supports(self.package_list.package_manager, action)
+    }
+
+    // Hides the selected or marked packages, or shows them again. Hiding only
+    // changes the list, so it works in read-only mode too.
+    fn toggle_hidden(&mut self) {
+        let list = &mut self.package_list;
+        let mut names = list.marked_names();
+        if names.is_empty() {
+            match list.selected() {
+                Some(pkg) => names.push(pkg.name.clone()),
+                None => return,
+            }
+        }
+        let what = match names.as_slice() {
+            [name] => name.clone(),
+            names => i18n::fill("message.packages", &[("count", &names.len())]),
+        };
+        let row = list.state.selected().unwrap_or(0);
+        let manager = list.package_manager;
+        let before: Vec<bool> = names
+            .iter()
+            .map(|name| list.hidden.contains(manager, name))
+            .collect();
+        let toggled = list.hidden.toggle(manager, &names);
+        if let Ok(hidden) = toggled {
+            let changed = names
+                .iter()
+                .zip(before)
+                .filter(|(_, was)| *was != hidden)
+                .map(|(name, _)| name.clone())
+                .collect();
+            self.history.record(undo::Change::Hidden {
+                manager,
+                names: changed,
+                hidden,
+            });
+        }
+        let list = &mut self.package_list;
+        self.message = Some(match toggled {
+            Ok(true) if list.show_hidden => i18n::fill("message.hid", &[("what", &what)]),
+            Ok(true) => i18n::fill("message.hid_shown_by_z", &[("what", &what)]),
+            Ok(false) => i18n::fill("message.unhid", &[("what", &what)]),
+            Err(err) => i18n::fill("message.hidden_failed", &[("error", &err)]),
+        });
+        let (package, group) = list.selection();
+        if !list.rebuild_rows_keeping(package, group) {
+            list.select_near(row);
+        }
+    }
+
+    fn toggle_hold(&mut self) {
+        if !self.package_list.marked_names().is_empty() {
+            self.hold_marked();
+            return;
+        }
+        let list = &mut self.package_list;
+        let Some(name) = list.selected().map(|pkg| pkg.name.clone()) else {
+            return;
+        };
+        if !commands::supports(list.package_manager, Action::Hold) {
+            self.message = Some(i18n::fill(
+                "message.no_holds",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        }
+
+        let (action, done) = if list.held.contains(&name) {
+            (Action::Unhold, "message.unheld")
+        } else {
+            (Action::Hold, "message.held")
+        };
+        if self.read_only && action.is_mutating() {
+            self.message = Some(i18n::text("message.read_only_hold").to_string());
+            return;
+        }
+        self.message = Some(
+            match commands::run_with(list.runner.as_ref(), list.package_manager, action, &name) {
+                Ok(output) if output.status.success() => {
+                    list.load_held();
+                    i18n::fill(done, &[("name", &name)])
+                }
+                Ok(output) => i18n::fill(
+                    "message.apt_mark_failed",
+                    &[("error", &String::from_utf8_lossy(&output.stderr).trim())],
+                ),
+                Err(err) => i18n::fill("message.apt_mark_failed", &[("error", &err)]),
+            },
+        );
+    }
+
+    // Asks to hold every marked package that isn't held yet.
+    fn hold_marked(&mut self) {
+        let list = &self.package_list;
+        if !commands::supports(list.package_manager, Action::Hold) {
+            self.message = Some(i18n::fill(
+                "message.no_holds",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        }
+        if self.read_only {
+            self.message = Some(i18n::text("message.read_only_hold").to_string());
+            return;
+        }
+        let names: Vec<String> = list
+            .marked_names()
+            .into_iter()
+            .filter(|name| !list.held.contains(name))
+            .collect();
+        if names.is_empty() {
+            self.message = Some(i18n::text("message.all_held").to_string());
+            return;
+        }
+        self.confirm_holds(Action::Hold, names);
+    }
+
+    fn confirm_holds(&mut self, action: Action, names: Vec<String>) {
+        let verb = if action == Action::Hold {
+            "Hold"
+        } else {
+            "Unhold"
+        };
+        let title = match (action, names.len()) {
+            (Action::Hold, 1) => i18n::text("confirm.hold_one").to_string(),
+            (Action::Hold, n) => i18n::fill("confirm.hold", &[("count", &n)]),
+            (_, 1) => i18n::text("confirm.unhold_one").to_string(),
+            (_, n) => i18n::fill("confirm.unhold", &[("count", &n)]),
+        };
+        let lines = names
+            .iter()
+            .map(|name| {
+                let version = self
+                    .package_list
+                    .items
+                    .iter()
+                    .find(|pkg| &pkg.name == name)
+                    .map(|pkg| format!(" {}", pkg.version))
+                    .unwrap_or_default();
+                format!("apt-mark {} {}{}", verb.to_lowercase(), name, version)
+            })
+            .collect();
+        self.confirm = Some(menu::Confirm {
+            title,
+            lines,
+            value: (action, names),
+        });
+    }
+
+    // Runs the confirmed apt-mark calls and reloads everything showing holds.
+    fn apply_holds(&mut self, action: Action, names: Vec<String>) {
+        let list = &mut self.package_list;
+        let mut failures = Vec::new();
+        for name in &names {
+            match commands::run_with(list.runner.as_ref(), list.package_manager, action, name) {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => failures.push(format!(
+                    "{}: {}",
+                    name,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )),
+                Err(err) => failures.push(format!("{}: {}", name, err)),
+            }
+        }
+        list.load_held();
+        if action == Action::Hold {
+            let manager = list.package_manager;
+            list.marked.retain(|(marked, _)| *marked != manager);
+        }
+        if let Some(view) = &mut self.holds {
+            view.reload(holds::load(
+                list.runner.as_ref(),
+                list.package_manager,
+                &list.held,
+            ));
+        }
+
+        let done = if action == Action::Hold {
+            "message.held_count"
+        } else {
+            "message.unheld_count"
+        };
+        self.message = Some(if failures.is_empty() {
+            i18n::fill(done, &[("count", &names.len())])
+        } else {
+            i18n::fill(
+                "message.apt_mark_failed_for",
+                &[("failures", &failures.join("; "))],
+            )
+        });
+    }
+
+    // Why the active manager can't install or remove from here, if it can't.
+    fn refuse(&self, action: Action) -> Option<String> {
+        let manager = self.package_list.package_manager.name();
+        let (read_only, unsupported) = if action == Action::Install {
+            ("message.read_only_install", "message.cant_install")
+        } else {
+            ("message.read_only_remove", "message.cant_remove")
+        };
+        if !commands::supports(self.package_list.package_manager, action) {
+            Some(i18n::fill(unsupported, &[("manager", &manager)]))
+        } else if self.read_only {
+            Some(i18n::text(read_only).to_string())
+        } else {
+            None
+        }
+    }
+
+    // `x`: asks before removing the marked packages of this manager in one
+    // command, or the selected package when none are marked.
+    fn confirm_remove(&mut self) {
+        let list = &self.package_list;
+        let names = match list.marked_names() {
+            marked if !marked.is_empty() => marked,
+            _ => match list.selected() {
+                Some(pkg) => vec![pkg.name.clone()],
+                None => return,
+            },
+        };
+        if let Some(refused) = self.refuse(Action::Remove) {
+            self.message = Some(refused);
+            return;
+        }
+        let Some(words) = commands::resolve_names(list.package_manager, Action::Remove, &names)
+        else {
+            self.message = Some(i18n::fill(
+                "message.remove_one_at_a_time",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        };
+        let title = match names.as_slice() {
+            [name] => i18n::fill("confirm.remove_one", &[("name", name)]),
+            _ => i18n::fill("confirm.remove", &[("count", &names.len())]),
+        };
+        self.confirm = Some(menu::Confirm {
+            title,
+            lines: vec![commands::join_words(&words)],
+            value: (Action::Remove, names),
+        });
+    }
+
+    // Every other key is swallowed, so nothing changes behind the spotlight.
+    // Every other key is swallowed, so nothing changes behind the spotlight.
+    fn handle_tour_key(&mut self, key: KeyEvent) {
+        let Some(tour) = &mut self.tour else {
+            return;
+        };
+        let going = match key.code {
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l' | ' ') => tour.next(),
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') => {
+                tour.back();
+                true
+            }
+            KeyCode::Esc | KeyCode::Char('q') => false,
+            _ => true,
+        };
+        if !going {
+            self.end_tour();
+        }
+    }
+
+    // Only closing, scrolling and the tour; nothing underneath changes.
+    fn handle_help_key(&mut self, key: KeyEvent) {
+        let Some(help) = &mut self.help else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('?' | 'q') | KeyCode::Esc => self.help = None,
+            KeyCode::Down | KeyCode::Char('j') => help.scroll_by(true, 1),
+            KeyCode::Up | KeyCode::Char('k') => help.scroll_by(false, 1),
+            KeyCode::PageDown => help.scroll_by(true, 10),
+            KeyCode::PageUp => help.scroll_by(false, 10),
+            KeyCode::Char('t') => {
+                self.help = None;
+                self.tour = Some(tour::Tour::new());
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tour(&mut self) {
+        self.tour = None;
+        tour::complete();
+    }
+
+    fn handle_inventory_prompt_key(&mut self, key: KeyEvent) {
+        let Some(path) = &mut self.inventory_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.inventory_prompt = None,
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) => path.push(c),
+            KeyCode::Enter => {
+                let path = path.trim().to_string();
+                self.inventory_prompt = None;
+                if !path.is_empty() {
+                    self.export_inventory(&path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The marked packages, or else the ones in view, so a search or filter
+    // narrows the export.
+    fn export_inventory(&mut self, path: &str) {
+        let list = &self.package_list;
+        let (items, marked) = list.export_items();
+        let packages: Vec<(PackageManager, &Package)> = items
+            .into_iter()
+            .map(|pkg| (list.package_manager, pkg))
+            .collect();
+        let count = packages.len();
+        let key = if marked {
+            "message.exported_marked"
+        } else {
+            "message.exported"
+        };
+        self.message = Some(match inventory::write(path, &packages) {
+            Ok(written) => i18n::fill(key, &[("count", &count), ("file", &written.display())]),
+            Err(err) => err,
+        });
+    }
+
+    fn handle_install_prompt_key(&mut self, key: KeyEvent) {
+        let Some(name) = &mut self.install_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.install_prompt = None,
+            KeyCode::Backspace => {
+                name.pop();
+            }
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::Enter => {
+                let name = name.trim().to_string();
+                self.install_prompt = None;
+                if !name.is_empty() {
+                    let manager = self.package_list.package_manager;
+                    self.foreground = Some((manager, Action::Install, vec![name]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_file_prompt_key(&mut self, key: KeyEvent) {
+        let Some(path) = &mut self.file_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.file_prompt = None,
+            KeyCode::Backspace => {
+                path.pop();
+            }
+            KeyCode::Char(c) => path.push(c),
+            KeyCode::Enter => {
+                let path = path.clone();
+                self.file_prompt = None;
+                match inspect::inspect(self.package_list.runner.as_ref(), &path) {
+                    Ok(inspected) => self.inspect = Some(inspect::InspectView::new(inspected)),
+                    Err(err) => self.message = Some(err),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_inspect_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.inspect else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.inspect = None,
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Char('f' | '[' | ']') => {
+                view.toggle_files();
+            }
+            KeyCode::Down | KeyCode::Char('j') => view.scroll_by(1),
+            KeyCode::Up | KeyCode::Char('k') => view.scroll_by(-1),
+            KeyCode::PageDown => view.scroll_by(10),
+            KeyCode::PageUp => view.scroll_by(-10),
+            KeyCode::Char('i') if self.read_only => {
+                self.message = Some(i18n::text("message.read_only_install").to_string());
+            }
+            KeyCode::Char('i') => {
+                let manager = view.inspected.kind.manager();
+                let path = view.inspected.path.to_string_lossy().into_owned();
+                if view.install_words().is_empty() {
+                    self.message = Some(i18n::fill(
+                        "message.cant_install",
+                        &[("manager", &manager.name())],
+                    ));
+                } else {
+                    self.inspect = None;
+                    self.foreground = Some((manager, Action::Install, vec![path]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The action menu's entry for `<`: back to the version the installed one
+    // replaced, if dpkg's logs have the upgrade. While the logs are still
+    // being read it is offered without a version, and `<` says so.
+    fn rollback_label(&self, pkg: &Package) -> Option<String> {
+        let list = &self.package_list;
+        if !matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) || !self.allows(Action::Install)
+        {
+            return None;
+        }
+        match list.dpkg_log.get_or_start(&list.runner) {
+            Some(log) => rollback::upgraded_from(log, pkg)
+                .map(|version| i18n::fill("action.rollback_to", &[("version", &version)])),
+            None => Some(i18n::text("action.rollback").to_string()),
+        }
+    }
+
+    // Offers to install the cached .deb of the version the selected package
+    // was upgraded from, or says why there is none.
+    fn start_rollback(&mut self) {
+        let list = &self.package_list;
+        if !matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            self.message = Some(i18n::text("message.rollback_dpkg").to_string());
+            return;
+        }
+        if self.read_only {
+            self.message = Some(i18n::text("message.read_only_install").to_string());
+            return;
+        }
+        let Some(pkg) = list.selected() else {
+            return;
+        };
+        let Some(log) = list.dpkg_log.get_or_start(&list.runner) else {
+            self.message = Some(i18n::text("message.reading_log").to_string());
+            return;
+        };
+        let Some(plan) = rollback::Plan::new(log, pkg) else {
+            self.message = Some(i18n::fill(
+                "message.no_rollback",
+                &[("name", &pkg.name), ("version", &pkg.version)],
+            ));
+            return;
+        };
+        let Some(target) = plan.target.clone() else {
+            self.no_rollback = Some(rollback::NoCache::new(&plan, pkg));
+            return;
+        };
+        let path = target.path.to_string_lossy().into_owned();
+        let mut lines = vec![format!("apt install {}", path), String::new()];
+        lines.extend(plan.warning(&target));
+        self.confirm = Some(menu::Confirm {
+            title: i18n::fill(
+                "confirm.rollback",
+                &[
+                    ("name", &plan.name),
+                    ("from", &plan.installed),
+                    ("to", &target.version),
+                ],
+            ),
+            lines,
+            value: (Action::Install, vec![path]),
+        });
+        self.rollback = Some(plan);
+    }
+
+    // After the rollback's install: whether it took, and if so the offer to
+    // hold the package there.
+    fn finish_rollback(&mut self, plan: rollback::Plan) {
+        let Some(target) = plan.target else {
+            return;
+        };
+        let list = &mut self.package_list;
+        list.select_package(&plan.name);
+        let now = list
+            .items
+            .iter()
+            .find(|pkg| pkg.name == plan.name)
+            .map(|pkg| pkg.version.clone());
+        if now.as_deref() != Some(target.version.as_str()) {
+            // The install failed, and the message already says so
+            return;
+        }
+        self.message = Some(i18n::fill(
+            "message.rolled_back",
+            &[("name", &plan.name), ("version", &target.version)],
+        ));
+        if list.held.contains(&plan.name) || !self.allows(Action::Hold) {
+            return;
+        }
+        self.confirm = Some(menu::Confirm {
+            title: i18n::fill(
+                "confirm.hold_at",
+                &[("name", &plan.name), ("version", &target.version)],
+            ),
+            lines: vec![
+                format!("apt-mark hold {}", plan.name),
+                String::new(),
+                i18n::fill("confirm.hold_warning", &[("version", &plan.installed)]),
+            ],
+            value: (Action::Hold, vec![plan.name]),
+        });
+    }
+
+    fn handle_rollback_key(&mut self, key: KeyEvent) {
+        let Some(view) = &self.no_rollback else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | '<') => self.no_rollback = None,
+            KeyCode::Char('y') => {
+                self.message = Some(if clipboard::copy(&view.url).is_ok() {
+                    i18n::fill("message.builds_copied", &[("name", &view.name)])
+                } else {
+                    i18n::text("message.no_clipboard").to_string()
+                });
+                self.no_rollback = None;
+            }
+            _ => {}
+        }
+    }
+
+    // dpkg's sizes are read with the list unless the fast profile skipped
+    // them; the view needs them now.
+    fn open_usage(&mut self) {
+        let list = &mut self.package_list;
+        if list.sizes.is_empty() && list.package_manager != PackageManager::Pip {
+            list.load_sizes();
+        }
+        self.usage = Some(usage::UsageView::new(list));
+    }
+
+    fn close_usage(&mut self) {
+        if let Some(worker) = self.usage.take().and_then(|mut view| view.close()) {
+            self.workers.push(worker);
+        }
+    }
+
+    fn handle_usage_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.usage else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | '%') => self.close_usage(),
+            KeyCode::Down | KeyCode::Char('j') => view.select_by(1),
+            KeyCode::Up | KeyCode::Char('k') => view.select_by(-1),
+            KeyCode::PageDown => view.select_by(10),
+            KeyCode::PageUp => view.select_by(-10),
+            KeyCode::Home | KeyCode::Char('g') => view.select_first(),
+            KeyCode::End | KeyCode::Char('G') => view.select_last(),
+            KeyCode::Enter => {
+                let name = view.selected().map(str::to_string);
+                self.close_usage();
+                if let Some(name) = name {
+                    self.package_list.select_package(&name);
+                    self.details_scroll = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // After an install or removal: the list is read again, landing on the
+    // installed package, or (through set_items) next to the removed ones,
+    // whose marks go with them.
+    fn finish_foreground(
+        &mut self,
+        action: Action,
+        names: &[String],
+        words: &[String],
+        status: Result<std::process::ExitStatus, String>,
+    ) {
+        self.refresh(false);
+        // pkg and apt share dpkg's database, and pip may have changed too
+        self.parked.clear();
+        let list = &mut self.package_list;
+        // A removed package stays in the list, struck through, until the
+        // next refresh
+        let present: Vec<&str> = names
+            .iter()
+            .filter(|name| list.items.iter().any(|pkg| &pkg.name == *name))
+            .filter(|name| list.changes.get(*name) != Some(&watch::Change::Removed))
+            .map(String::as_str)
+            .collect();
+        let name = match names {
+            [name] => name.clone(),
+            _ => i18n::fill("message.packages", &[("count", &names.len())]),
+        };
+        if action == Action::Install && !present.is_empty() {
+            list.select_package(present[0]);
+        }
+        self.details_version += 1;
+        self.details_scroll = 0;
+        let command = commands::join_words(words);
+        self.message = Some(match status {
+            Err(err) => i18n::fill(
+                "message.run_failed",
+                &[("command", &command), ("error", &err)],
+            ),
+            Ok(status) if !status.success() => match status.code() {
+                Some(code) => i18n::fill(
+                    "message.command_failed",
+                    &[("command", &command), ("code", &code)],
+                ),
+                None => i18n::fill("message.interrupted", &[("command", &command)]),
+            },
+            Ok(_) if action == Action::Install => {
+                i18n::fill("message.installed", &[("name", &name)])
+            }
+            Ok(_) if present.len() == 1 => i18n::fill(
+                "message.still_installed_one",
+                &[("command", &command), ("name", &present[0])],
+            ),
+            Ok(_) if !present.is_empty() => i18n::fill(
+                "message.still_installed",
+                &[("command", &command), ("names", &present.join(", "))],
+            ),
+            Ok(_) => i18n::fill("message.removed", &[("name", &name)]),
+        });
+        if let Some(plan) = self.rollback.take() {
+            self.finish_rollback(plan);
+        }
+    }
+
+    fn no_upgrade_reason(&self, pkg: &Package) -> Option<&str> {
+        if self.package_list.package_manager != PackageManager::Pip {
+            return None;
+        }
+        self.no_upgrade.reason(&pkg.name)
+    }
+
+    // Adds the selected pip package to the do-not-upgrade list, or takes it
+    // off.
+    fn toggle_no_upgrade(&mut self) {
+        let list = &self.package_list;
+        if list.package_manager != PackageManager::Pip {
+            self.message = Some(i18n::text("message.no_upgrade_pip").to_string());
+            return;
+        }
+        let Some(pkg) = list.selected() else {
+            return;
+        };
+        self.details_version += 1;
+        let before = self.no_upgrade.reason(&pkg.name).map(str::to_string);
+        let toggled = self.no_upgrade.toggle(&pkg.name);
+        if toggled.is_ok() {
+            self.history.record(undo::Change::NoUpgrade {
+                name: pkg.name.clone(),
+                before,
+                after: self.no_upgrade.reason(&pkg.name).map(str::to_string),
+            });
+        }
+        self.message = Some(match toggled {
+            Ok(true) => i18n::fill("message.no_upgrade_on", &[("name", &pkg.name)]),
+            Ok(false) => i18n::fill("message.no_upgrade_off", &[("name", &pkg.name)]),
+            Err(err) => i18n::fill("message.no_upgrade_failed", &[("error", &err)]),
+        });
+    }
+
+    // Records how the marks changed since `before`, for `u`.
+    fn record_marks(&mut self, before: &HashSet<(PackageManager, String)>) {
+        let now = &self.package_list.marked;
+        self.history.record(undo::Change::Marks {
+            marked: now.difference(before).cloned().collect(),
+            unmarked: before.difference(now).cloned().collect(),
+        });
+    }
+
+    // `u`: takes back the last mark, hide or do-not-upgrade change.
+    fn undo(&mut self) {
+        self.message = Some(match self.history.undo() {
+            Some(change) => match self.apply_change(&change.inverse()) {
+                Ok(()) => i18n::fill("message.undid", &[("change", &change.label())]),
+                Err(err) => i18n::fill(
+                    "message.undo_failed",
+                    &[("change", &change.label()), ("error", &err)],
+                ),
+            },
+            None => i18n::text("message.nothing_to_undo").to_string(),
+        });
+    }
+
+    // Ctrl+r: makes the last undone change again.
+    fn redo(&mut self) {
+        self.message = Some(match self.history.redo() {
+            Some(change) => match self.apply_change(&change) {
+                Ok(()) => i18n::fill("message.redid", &[("change", &change.label())]),
+                Err(err) => i18n::fill(
+                    "message.redo_failed",
+                    &[("change", &change.label()), ("error", &err)],
+                ),
+            },
+            None => i18n::text("message.nothing_to_redo").to_string(),
+        });
+    }
+
+    // Marks are set whatever the list shows, so filters, sorting and Tab
+    // in between don't matter; hidden packages leave the rows or come back.
+    fn apply_change(&mut self, change: &undo::Change) -> Result<(), String> {
+        let list = &mut self.package_list;
+        match change {
+            undo::Change::Marks { marked, unmarked } => {
+                for key in unmarked {
+                    list.marked.remove(key);
+                }
+                list.marked.extend(marked.iter().cloned());
+                Ok(())
+            }
+            undo::Change::Hidden {
+                manager,
+                names,
+                hidden,
+            } => {
+                let saved = list.hidden.set(*manager, names, *hidden);
+                let row = list.state.selected().unwrap_or(0);
+                let (package, group) = list.selection();
+                if !list.rebuild_rows_keeping(package, group) {
+                    list.select_near(row);
+                }
+                saved.map_err(|err| i18n::fill("message.save_hidden_failed", &[("error", &err)]))
+            }
+            undo::Change::NoUpgrade { name, after, .. } => {
+                self.details_version += 1;
+                self.no_upgrade
+                    .set(name, after.clone())
+                    .map_err(|err| i18n::fill("message.save_no_upgrade_failed", &[("error", &err)]))
+            }
+        }
+    }
+
+    fn handle_sessions_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.sessions else {
+            return;
+        };
+        if let Some(name) = &mut view.naming {
+            match key.code {
+                KeyCode::Esc => view.naming = None,
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Char(c) => name.push(c),
+                KeyCode::Enter => {
+                    let name = name.trim().to_string();
+                    if name.is_empty() {
+                        self.message = Some(i18n::text("message.session_name").to_string());
+                        return;
+                    }
+                    view.naming = None;
+                    self.save_session(name);
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.sessions = None,
+            KeyCode::Down | KeyCode::Char('j') => view.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => view.select_previous(),
+            KeyCode::Char('s') => view.naming = Some(String::new()),
+            KeyCode::Char('d') => {
+                self.message = Some(match view.delete_selected() {
+                    Ok(name) => i18n::fill("message.session_deleted", &[("name", &name)]),
+                    Err(err) => err,
+                });
+            }
+            KeyCode::Enter => {
+                if let Some(session) = view.selected().cloned() {
+                    self.sessions = None;
+                    self.restore_session(session);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn save_session(&mut self, name: String) {
+        let list = &self.package_list;
+        let mut marks: Vec<(PackageManager, String)> = list.marked.iter().cloned().collect();
+        marks.sort_by(|a, b| (a.0.name(), &a.1).cmp(&(b.0.name(), &b.1)));
+        let session = sessions::Session {
+            name: name.clone(),
+            saved: 0,
+            manager: list.package_manager,
+            group_by: list.group_by,
+            // A manifest comparison, upgradable packages or an orphan scan
+            // aren't part of a session, so neither are their filters
+            filter: list.filter.clone().filter(|filter| {
+                !matches!(
+                    filter,
+                    ListFilter::Drift(_) | ListFilter::Upgradable | ListFilter::Orphans
+                )
+            }),
+            search: self
+                .search
+                .as_ref()
+                .map(|search| search.query.clone())
+                .filter(|query| !query.is_empty()),
+            marks,
+        };
+        let Some(view) = &mut self.sessions else {
+            return;
+        };
+        self.message = Some(match view.add(session) {
+            Ok(dropped) if dropped.is_empty() => {
+                i18n::fill("message.session_saved", &[("name", &name)])
+            }
+            Ok(dropped) => i18n::fill(
+                "message.session_saved_dropped",
+                &[("name", &name), ("dropped", &dropped.join(", "))],
+            ),
+            Err(err) => err,
+        });
+    }
+
+    // Brings back the session's list, view and marks. Marks of packages that
+    // are gone since are reported rather than restored.
+    fn restore_session(&mut self, session: sessions::Session) {
+        if !self.managers.enabled.contains(&session.manager) {
+            self.message = Some(i18n::fill(
+                "message.session_disabled",
+                &[
+                    ("name", &session.name),
+                    ("manager", &session.manager.name()),
+                ],
+            ));
+            return;
+        }
+        self.loader.cancel();
+        if self.package_list.package_manager != session.manager {
+            self.package_list.switch_to(session.manager);
+        }
+        let list = &mut self.package_list;
+        list.filter = session
+            .filter
+            .filter(|filter| filter.applies_to(session.manager));
+        if session.group_by.applies_to(session.manager) {
+            list.set_group_by(session.group_by);
+        } else {
+            list.set_group_by(GroupBy::None);
+        }
+        self.search = session.search.map(|query| {
+            let mut search = search::Search::new();
+            search.query = query;
+            search.editing = false;
+            search
+        });
+        self.sync_query();
+        self.details_version += 1;
+        self.details_scroll = 0;
+
+        // Other managers' marks are checked against a quick listing of theirs
+        let mut installed: HashMap<PackageManager, HashSet<String>> = HashMap::new();
+        let mut missing = Vec::new();
+        let mut marked = HashSet::new();
+        for (manager, name) in session.marks {
+            let names = installed.entry(manager).or_insert_with(|| {
+                let list = &self.package_list;
+                if manager == list.package_manager {
+                    list.items.iter().map(|pkg| pkg.name.clone()).collect()
+                } else if commands::is_available(manager) {
+                    PackageList::load(manager, list.runner.clone(), config::Profile::Fast)
+                        .items
+                        .into_iter()
+                        .map(|pkg| pkg.name)
+                        .collect()
+                } else {
+                    HashSet::new()
+                }
+            });
+            if names.contains(&name) {
+                marked.insert((manager, name));
+            } else if manager == session.manager {
+                missing.push(name);
+            } else {
+                missing.push(format!("{} ({})", name, manager.name()));
+            }
+        }
+        let restored = marked.len();
+        let before = std::mem::replace(&mut self.package_list.marked, marked);
+        self.record_marks(&before);
+        if self.search.is_some() && !self.selected_matches() {
+            self.step_list_match(true);
+        }
+
+        let mut message = i18n::fill(
+            "message.session_restored",
+            &[("name", &session.name), ("count", &restored)],
+        );
+        if !missing.is_empty() {
+            const SHOWN: usize = 5;
+            message.push_str(&i18n::fill(
+                "message.session_missing",
+                &[
+                    ("count", &missing.len()),
+                    ("names", &missing[..missing.len().min(SHOWN)].join(", ")),
+                ],
+            ));
+            if missing.len() > SHOWN {
+                message.push_str(&i18n::fill(
+                    "message.and_more",
+                    &[("count", &(missing.len() - SHOWN))],
+                ));
+            }
+        }
+        self.message = Some(message);
+    }
+
+    // The marked packages of every manager, or the selected one.
+    fn open_export(&mut self) {
+        let list = &self.package_list;
+        let mut packages: Vec<(PackageManager, String)> = list.marked.iter().cloned().collect();
+        if packages.is_empty() {
+            match list.selected() {
+                Some(pkg) => packages.push((list.package_manager, pkg.name.clone())),
+                None => return,
+            }
+        }
+        packages.sort_by(|a, b| (a.0.name(), &a.1).cmp(&(b.0.name(), &b.1)));
+        self.export = Some(backup::ExportView::new(packages));
+    }
+
+    // The marked packages of every manager, or the selected one, with their
+    // versions. Other managers' versions come from a quick listing of theirs.
+    fn open_install(&mut self) {
+        let list = &self.package_list;
+        let mut marks: Vec<(PackageManager, String)> = list.marked.iter().cloned().collect();
+        if marks.is_empty() {
+            match list.selected() {
+                Some(pkg) => marks.push((list.package_manager, pkg.name.clone())),
+                None => return,
+            }
+        }
+        let mut versions: HashMap<PackageManager, HashMap<String, String>> = HashMap::new();
+        let packages = marks
+            .into_iter()
+            .map(|(manager, name)| {
+                let known = versions.entry(manager).or_insert_with(|| {
+                    let items = if manager == list.package_manager {
+                        list.items.clone()
+                    } else if commands::is_available(manager) {
+                        PackageList::load(manager, list.runner.clone(), config::Profile::Fast).items
+                    } else {
+                        Vec::new()
+                    };
+                    items
+                        .into_iter()
+                        .map(|pkg| (pkg.name, pkg.version))
+                        .collect()
+                });
+                let version = known.get(&name).cloned();
+                (manager, name, version)
+            })
+            .collect();
+        self.install = Some(install::InstallCommands::new(
+            packages,
+            config::install_width(),
+        ));
+    }
+
+    fn handle_install_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.install else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'I') => self.install = None,
+            KeyCode::Char('v') => view.versions = !view.versions,
+            KeyCode::Char('y') | KeyCode::Enter => {
+                self.message = Some(if clipboard::copy(&view.commands().join("\n")).is_ok() {
+                    i18n::fill("message.copied", &[("what", &view.summary())])
+                } else {
+                    i18n::text("message.no_clipboard").to_string()
+                });
+                self.install = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_export_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.export else {
+            return;
+        };
+        if view.is_running() {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                view.cancel();
+                self.message = Some(i18n::text("message.export_cancelled").to_string());
+            }
+            return;
+        }
+        if let Some(dir) = &mut view.editing {
+            match key.code {
+                KeyCode::Esc => view.editing = None,
+                KeyCode::Backspace => {
+                    dir.pop();
+                }
+                KeyCode::Char(c) => dir.push(c),
+                KeyCode::Enter => view.subtree = view.editing.take().unwrap_or_default(),
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'E') => self.close_export(),
+            KeyCode::Char('e') => view.existing_only = !view.existing_only,
+            KeyCode::Char('c') => view.to_clipboard = !view.to_clipboard,
+            KeyCode::Char('t') => view.editing = Some(view.subtree.clone()),
+            KeyCode::Enter => view.start(self.package_list.runner.clone()),
+            _ => {}
+        }
+    }
+
+    fn close_export(&mut self) {
+        if let Some(view) = self.export.take() {
+            self.workers.extend(view.into_worker());
+        }
+    }
+
+    // Delivers a finished export to the file or the clipboard.
+    fn poll_export(&mut self) -> bool {
+        let Some(view) = &mut self.export else {
+            return false;
+        };
+        let (changed, export) = view.poll();
+        let Some(export) = export else {
+            return changed;
+        };
+        let to_clipboard = view.to_clipboard;
+        self.close_export();
+        self.message = Some(match export {
+            Err(err) => i18n::fill("message.export_failed", &[("error", &err)]),
+            Ok(export) if to_clipboard => {
+                if clipboard::copy(&export.paths.join("\n")).is_ok() {
+                    let clipboard = i18n::text("message.the_clipboard");
+                    i18n::fill(
+                        "message.copied",
+                        &[("what", &backup::summary(&export, clipboard))],
+                    )
+                } else {
+                    i18n::text("message.no_clipboard").to_string()
+                }
+            }
+            Ok(export) => match backup::write(&export) {
+                Ok(path) => i18n::fill(
+                    "message.wrote",
+                    &[(
+                        "what",
+                        &backup::summary(&export, &path.display().to_string()),
+                    )],
+                ),
+                Err(err) => i18n::fill(
+                    "message.write_failed",
+                    &[("file", &backup::FILES_NAME), ("error", &err)],
+                ),
+            },
+        });
+        true
+    }
+
+    // The marked packages, or the selected one, as the removal impact sees it.
+    fn open_impact(&mut self) {
+        let list = &self.package_list;
+        let Some(explainer) = &list.why else {
+            self.message = Some(i18n::fill(
+                "message.no_graph",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        };
+        let mut names = list.marked_names();
+        if names.is_empty() {
+            match list.selected() {
+                Some(pkg) => names.push(pkg.name.clone()),
+                None => return,
+            }
+        }
+        self.impact = Some(impact::ImpactView::new(
+            explainer,
+            list.package_manager,
+            names,
+        ));
+    }
+
+    fn open_holds(&mut self) {
+        let list = &self.package_list;
+        if !commands::supports(list.package_manager, Action::Held) {
+            self.message = Some(i18n::fill(
+                "message.no_holds",
+                &[("manager", &list.package_manager.name())],
+            ));
+            return;
+        }
+        let entries = holds::load(list.runner.as_ref(), list.package_manager, &list.held);
+        self.holds = Some(holds::HoldView::new(entries, config::hold_warn_versions()));
+    }
+
+    fn handle_holds_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.holds else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'P') => self.holds = None,
+            KeyCode::Down | KeyCode::Char('j') => view.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => view.select_previous(),
+            KeyCode::Char(' ') => view.toggle_mark(),
+            KeyCode::Char('u') => {
+                let names = view.targets();
+                if self.read_only {
+                    self.message = Some(i18n::text("message.read_only_unhold").to_string());
+                } else if !names.is_empty() {
+                    self.confirm_holds(Action::Unhold, names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn open_repos(&mut self) {
+        let list = &self.package_list;
+        if !matches!(
+            list.package_manager,
+            PackageManager::Pkg | PackageManager::Apt
+        ) {
+            self.message = Some(i18n::text("message.repos_dpkg").to_string());
+            return;
+        }
+        let sources = repos::load(list.runner.as_ref(), list.package_manager);
+        self.repos = Some(repos::RepoView::new(sources, &list.items, &list.pins));
+    }
+
+    // "Everything built from source", the packages most likely to break when
+    // python or a library they link against is upgraded.
+    fn toggle_source_filter(&mut self) {
+        let list = &mut self.package_list;
+        if list.package_manager != PackageManager::Pip {
+            self.message = Some(i18n::text("message.builds_pip").to_string());
+            return;
+        }
+        if list.builds.is_empty() {
+            self.message = Some(i18n::text("message.no_wheels").to_string());
+            return;
+        }
+        let source = ListFilter::Built(wheels::Build::Source);
+        list.filter = match list.filter {
+            Some(ListFilter::Built(_)) => None,
+            _ => Some(source),
+        };
+        list.rebuild_rows();
+    }
+
+    fn handle_repos_key(&mut self, key: KeyEvent) {
+        let Some(view) = &mut self.repos else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'O') => self.repos = None,
+            KeyCode::Down | KeyCode::Char('j') => view.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => view.select_previous(),
+            KeyCode::Enter => {
+                if let Some(source) = view.selected() {
+                    self.package_list.filter = Some(ListFilter::Repo {
+                        label: source.label(),
+                        suites: source.names.clone(),
+                    });
+                    self.package_list.rebuild_rows();
+                }
+                self.repos = None;
+            }
+            _ => {}
+        }
+    }
+
+    // Marks (Some(true)), unmarks (Some(false)) or inverts (None) every
+    // package in view.
+    fn mark_visible(&mut self, mark: Option<bool>) {
+        let list = &mut self.package_list;
+        let before = list.marked.clone();
+        let names = list.visible_names(self.search.as_ref());
+        for name in &names {
+            let marked = mark.unwrap_or(!list.is_marked(name));
+            list.set_mark(name, marked);
+        }
+        self.record_marks(&before);
+        let key = match mark {
+            Some(true) => "message.marked_in_view",
+            Some(false) => "message.unmarked_in_view",
+            None => "message.inverted_in_view",
+        };
+        self.message = Some(i18n::fill(key, &[("count", &names.len())]));
+    }
+
+    fn handle_mark_prompt_key(&mut self, key: KeyEvent) {
+        let Some((mark, text)) = &mut self.mark_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.mark_prompt = None,
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Tab | KeyCode::BackTab => *mark = !*mark,
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Enter => {
+                let (mark, text) = (*mark, text.clone());
+                self.mark_prompt = None;
+                if !text.trim().is_empty() {
+                    self.mark_by_pattern(&text, mark);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Packages of the current manager matching any of the patterns, whether
+    // or not the filter, search or a collapsed group hides them.
+    fn pattern_matches(&self, patterns: &[pattern::Pattern]) -> Vec<String> {
+        self.package_list
+            .items
+            .iter()
+            .filter(|pkg| patterns.iter().any(|pattern| pattern.matches(&pkg.name)))
+            .map(|pkg| pkg.name.clone())
+            .collect()
+    }
+
+    fn mark_by_pattern(&mut self, text: &str, mark: bool) {
+        let patterns = pattern::parse(text);
+        let names = self.pattern_matches(&patterns);
+        let list = &mut self.package_list;
+        let before = list.marked.clone();
+        let changed = names
+            .iter()
+            .filter(|name| list.is_marked(name) != mark)
+            .count();
+        for name in &names {
+            list.set_mark(name, mark);
+        }
+        self.record_marks(&before);
+        let unchanged = names.len() - changed;
+        let (done, unchanged_key) = if mark {
+            ("message.marked", "message.already_marked")
+        } else {
+            ("message.unmarked", "message.not_marked")
+        };
+        let mut message = i18n::fill(done, &[("count", &changed)]);
+        if unchanged > 0 {
+            message.push_str(&i18n::fill(unchanged_key, &[("count", &unchanged)]));
+        }
+        let missing: Vec<&str> = patterns
+            .iter()
+            .filter(|pattern| !names.iter().any(|name| pattern.matches(name)))
+            .map(|pattern| pattern.text.as_str())
+            .collect();
+        if !missing.is_empty() {
+            message.push_str(&i18n::fill(
+                "message.nothing_matches",
+                &[
+                    ("manager", &self.package_list.package_manager.name()),
+                    ("patterns", &missing.join(", ")),
+                ],
+            ));
+        }
+        self.message = Some(message);
+    }
+
+    fn open_homepage(&mut self) {
+        let list = &self.package_list;
+        let Some(pkg) = list.selected() else {
+            return;
+        };
+        let Some(details) = list.loaded_details(pkg) else {
+            self.message = Some(i18n::fill(
+                "message.details_loading",
+                &[("name", &pkg.name)],
+            ));
+            return;
+        };
+        let Some(url) = details.homepage() else {
+            self.message = Some(i18n::fill("message.no_homepage", &[("name", &pkg.name)]));
+            return;
+        };
+
+        // termux-open-url hands the link to Android; xdg-open covers desktops.
+        let opened = ["termux-open-url", "xdg-open"].iter().any(|opener| {
+            Command::new(opener)
+                .arg(url)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .is_ok()
+        });
+        self.message = Some(if opened {
+            i18n::fill("message.opening", &[("url", &url)])
+        } else {
+            i18n::text("message.no_opener").to_string()
+        });
+    }
+
+    // Marked packages of every manager when there are any, otherwise the full
+    // inventory.
+    #[cfg(feature = "reports")]
+    fn write_bootstrap_script(&mut self) {
+        let list = &self.package_list;
+        let (written, sections) = if list.marked.is_empty() {
+            (
+                "message.bootstrap_written",
+                bootstrap::full_inventory(list.runner.as_ref()),
+            )
+        } else {
+            let sections = PackageManager::ALL
+                .into_iter()
+                .filter_map(|manager| {
+                    let mut names: Vec<String> = list
+                        .marked
+                        .iter()
+                        .filter(|(marked, _)| *marked == manager)
+                        .map(|(_, name)| name.clone())
+                        .collect();
+                    names.sort();
+                    (!names.is_empty()).then_some(bootstrap::Section { manager, names })
+                })
+                .collect();
+            ("message.bootstrap_written_marked", sections)
+        };
+
+        self.message = Some(
+            match bootstrap::write_export(BOOTSTRAP_NAME, &bootstrap::script(&sections)) {
+                Ok(path) => i18n::fill(written, &[("file", &path.display())]),
+                Err(err) => i18n::fill(
+                    "message.write_failed",
+                    &[("file", &BOOTSTRAP_NAME), ("error", &err)],
+                ),
+            },
+        );
+    }
+
+    #[cfg(feature = "reports")]
+    fn write_report(&mut self) {
+        let list = &self.package_list;
+        let entries: Vec<report::ReportEntry> = list
+            .items
+            .iter()
+            .filter(|pkg| list.is_marked(&pkg.name))
+            .map(|pkg| list.report_entry(pkg))
+            .collect();
+
+        if entries.is_empty() {
+            self.message = Some(i18n::text("message.none_marked").to_string());
+            return;
+        }
+
+        self.message = Some(
+            match exports::write(REPORT_NAME, &report::markdown(&entries)) {
+                Ok(path) => i18n::fill(
+                    "message.report_written",
+                    &[("count", &entries.len()), ("file", &path.display())],
+                ),
+                Err(err) => i18n::fill(
+                    "message.write_failed",
+                    &[("file", &REPORT_NAME), ("error", &err)],
+                ),
+            },
+        );
+    }
+
+    fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> io::Result<()> {
+        terminal.draw(|f| self.ui(f))?;
+        self.update_terminal_title();
+        log::debug(&format!(
+            "startup: first paint after {} ms (profile {})",
+            self.started.elapsed().as_millis(),
+            self.package_list.profile.name()
+        ));
+        loop {
+            // Wake up periodically while checks or detail views load in the
+            // background, and when the next watch refresh is due
+            let busy = self
+                .health
+                .as_ref()
+                .is_some_and(|health| health.is_running())
+                || self.tabs.is_loading()
+                || self.loader.is_loading()
+                || self.package_list.shown.is_loading()
+                || self.dep_wait.is_some()
+                || self
+                    .package_list
+                    .orphans
+                    .as_ref()
+                    .is_some_and(|scan| scan.is_running())
+                || self.export.as_ref().is_some_and(|view| view.is_running())
+                || self.usage.as_ref().is_some_and(|view| view.is_running())
+                || self.python_check.is_some();
+            let mut timeout = match (&self.watch, busy) {
+                (Some(watch), true) => Some(watch.timeout().min(Duration::from_millis(200))),
+                (Some(watch), false) => Some(watch.timeout()),
+                (None, true) => Some(Duration::from_millis(200)),
+                // Often enough for the list's age to tick over
+                (None, false) => Some(Duration::from_secs(30)),
+            };
+            // And when the selection comes to rest, to show its details
+            if self.details_fetch != preview::Fetch::Eager {
+                if let Some(rest) = self.rest.remaining(self.details_delay, Instant::now()) {
+                    timeout = timeout.map(|timeout| timeout.min(rest));
+                }
+            }
+            // And when the message is due to go
+            if let Some(left) = self.message_expiry.remaining(Instant::now()) {
+                timeout = timeout.map(|timeout| timeout.min(left));
+            }
+            // And when the screen's details are due to be prefetched
+            if let Some(idle) = self.prefetch.remaining(Instant::now()) {
+                timeout = timeout.map(|timeout| timeout.min(idle));
+            }
+            // Only draw when something happened: idle wake-ups in watch mode
+            // and while loading would otherwise rebuild every pane.
+            let mut changed = false;
+            if timeout.map_or(Ok(true), event::poll)? {
+                self.handle_event(event::read()?);
+                self.prefetch.input(Instant::now());
+                changed = true;
+            }
+            // Some terminals (Termux when the phone rotates) leave what was
+            // drawn for the old size on screen, so start from a blank one
+            if std::mem::take(&mut self.resized) {
+                terminal.autoresize()?;
+                terminal.clear()?;
+            }
+            if let Some(health) = &mut self.health {
+                if health.poll() {
+                    // Findings show the packages they name even when hidden
+                    self.package_list.flagged = health.flagged();
+                    self.package_list.rebuild_rows();
+                    changed = true;
+                }
+            }
+            changed |= self.tabs.poll();
+            changed |= self.package_list.shown.poll();
+            changed |= self.package_list.dpkg_log.poll();
+            changed |= self.poll_dep_wait();
+            changed |= self.package_list.poll_orphans();
+            changed |= self.poll_rdepends();
+            changed |= self.refresh_stats();
+            changed |= self.observe_rest();
+            self.observe_prefetch();
+            changed |= self.poll_export();
+            changed |= self.usage.as_mut().is_some_and(|view| view.poll());
+            let listed = self.poll_loading();
+            changed |= listed;
+            changed |= self.poll_python();
+            changed |= self.watch_tick();
+            changed |= self.expire_message();
+            changed |= self.age_label().0 != self.drawn_age;
+            if self.quit_when_done && self.running_operations().is_empty() {
+                self.should_exit = true;
+            }
+
+            if self.should_exit {
+                if !self.abandon {
+                    if self.has_workers() {
+                        self.message = Some(i18n::text("message.waiting").to_string());
+                        terminal.draw(|f| self.ui(f))?;
+                    }
+                    self.join_workers();
+                }
+                return Ok(());
+            }
+            if let Some((manager, action, names)) = self.foreground.take() {
+                let words = commands::resolve_names(manager, action, &names)
+                    .expect("only supported actions are queued");
+                let status = run_in_foreground(terminal, !self.inline, &words)?;
+                self.terminal_title = None;
+                self.finish_foreground(action, &names, &words, status);
+                changed = true;
+            }
+            if self.should_suspend {
+                self.should_suspend = false;
+                suspend(terminal, !self.inline)?;
+                self.terminal_title = None; // Put back on the shell's
+                changed = true;
+            }
+            if changed {
+                let drawing = Instant::now();
+                terminal.draw(|f| self.ui(f))?;
+                if listed {
+                    startup::step("first full list paint", drawing);
+                }
+                self.update_terminal_title();
+            }
+        }
+    }
+
+    // E.g. "tpi · pkg 913 · 12 upgradable", once `V` has looked.
+    fn terminal_title_text(&self) -> String {
+        let list = &self.package_list;
+        let manager = list.package_manager.name();
+        if self.loader.is_loading() {
+            return format!("tpi · {} loading…", manager);
+        }
+        let mut title = format!("tpi · {} {}", manager, list.items.len());
+        if list.upgradable.contains_key(&list.package_manager) {
+            let count = list
+                .items
+                .iter()
+                .filter(|pkg| list.candidate(pkg).is_some())
+                .count();
+            title.push_str(&format!(" · {} upgradable", count));
+        }
+        title
+    }
+
+    // Between draws, so it never lands in the middle of one.
+    fn update_terminal_title(&mut self) {
+        if !config::terminal_title() {
+            return;
+        }
+        let title = self.terminal_title_text();
+        if self.terminal_title.as_ref() != Some(&title) && termtitle::set(&title).is_ok() {
+            self.terminal_title = Some(title);
+        }
+    }
+
+    // All input goes through here, so the app can be driven without a terminal.
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key),
+            // A click on a column header of the list
+            Event::Mouse(mouse)
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left) && !self.is_busy() =>
+            {
+                let column = self.sort_hits.iter().find_map(|(area, column)| {
+                    let hit = (area.left()..area.right()).contains(&mouse.column)
+                        && (area.top()..area.bottom()).contains(&mouse.row);
+                    hit.then_some(*column)
+                });
+                if let Some(column) = column {
+                    self.sort(self.package_list.sort_by.clicked(column));
+                } else if self.tabs.click(mouse.column, mouse.row) {
+                    // A click on the tab strip of the detail pane
+                    self.details_scroll = 0;
+                }
+            }
+            // The list's offset follows the selection on the next draw, and
+            // the detail scroll is clamped to the new wrapping there
+            Event::Resize(..) => {
+                self.package_list.clamp_selection();
+                self.resized = true;
+            }
+            _ => {}
+        }
+    }
+
+    // How far the detail text can scroll before its last line reaches the
+    // bottom of the pane, as of the last draw.
+    fn max_details_scroll(&self) -> u16 {
+        let (lines, rows) = self.details_rows;
+        lines.saturating_sub(rows)
+    }
+
+    // A pane's height less one line, so the line at the edge stays in view.
+    fn scroll_details_page(&mut self, down: bool) {
+        let page = self.details_rows.1.saturating_sub(1).max(1);
+        self.details_scroll = if down {
+            self.details_scroll
+                .saturating_add(page)
+                .min(self.max_details_scroll())
+        } else {
+            self.details_scroll.saturating_sub(page)
+        };
+    }
+
+    fn step_back(&mut self) -> bool {
+        if self.details_page {
+            self.details_page = false;
+        } else if self.tabs.leave_files() {
+            self.details_scroll = 0;
+        } else if self.search.is_some() {
+            self.search = None;
+            self.sync_query();
+        } else if self.package_list.filter.is_some() {
+            self.package_list.filter = None;
+            self.package_list.rebuild_rows();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // The main-list actions the config file can put on other keys.
+    fn apply_binding(&mut self, binding: keymap::Binding) {
+        let list = &mut self.package_list;
+        match binding {
+            keymap::Binding::Quit => return self.request_quit(),
+            keymap::Binding::SwitchManager => return self.next_manager(false),
+            keymap::Binding::PreviousManager => return self.next_manager(true),
+            // On the details page the list isn't there to move in
+            keymap::Binding::Next | keymap::Binding::Previous if self.details_page => {
+                let down = binding == keymap::Binding::Next;
+                self.details_scroll = if down {
+                    self.details_scroll
+                        .saturating_add(1)
+                        .min(self.max_details_scroll())
+                } else {
+                    self.details_scroll.saturating_sub(1)
+                };
+                return;
+            }
+            keymap::Binding::Next => list.select_next(),
+            keymap::Binding::Previous => list.select_previous(),
+            keymap::Binding::First => list.select_first(),
+            keymap::Binding::Last => list.select_last(),
+        }
+        self.details_scroll = 0; // Reset scroll when selecting a new package
+    }
+
+    // What has the keys, in the order things were opened over the list.
+    fn focus(&self) -> Focus {
+        let open = [
+            (self.failures.is_some(), Focus::Failures),
+            (self.tour.is_some(), Focus::Tour),
+            (self.help.is_some(), Focus::Help),
+            (
+                self.search.as_ref().is_some_and(|search| search.editing),
+                Focus::Search,
+            ),
+            (self.owner_prompt.is_some(), Focus::OwnerPrompt),
+            (self.install_prompt.is_some(), Focus::InstallPrompt),
+            (self.inventory_prompt.is_some(), Focus::InventoryPrompt),
+            (self.mark_prompt.is_some(), Focus::MarkPrompt),
+            (self.file_prompt.is_some(), Focus::FilePrompt),
+            (self.confirm.is_some(), Focus::Confirm),
+            (self.quit_menu.is_some(), Focus::QuitMenu),
+            (self.action_menu.is_some(), Focus::ActionMenu),
+            (self.group_menu.is_some(), Focus::GroupMenu),
+            (self.switch_menu.is_some(), Focus::SwitchMenu),
+            (self.priority_menu.is_some(), Focus::PriorityMenu),
+            (self.section_menu.is_some(), Focus::SectionMenu),
+            (self.drift_menu.is_some(), Focus::DriftMenu),
+            (self.dep_tree.is_some(), Focus::DepTree),
+            (self.rdepends.is_some(), Focus::Rdepends),
+            (self.show_health, Focus::Health),
+            (self.holds.is_some(), Focus::Holds),
+            (self.repos.is_some(), Focus::Repos),
+            (self.sessions.is_some(), Focus::Sessions),
+            (self.export.is_some(), Focus::Export),
+            (self.install.is_some(), Focus::Install),
+            (self.impact.is_some(), Focus::Impact),
+            (self.upgrades.is_some(), Focus::Upgrades),
+            (self.stats.is_some(), Focus::Stats),
+            (self.growth.is_some(), Focus::Growth),
+            (self.inspect.is_some(), Focus::Inspect),
+            (self.usage.is_some(), Focus::Usage),
+            (self.no_rollback.is_some(), Focus::Rollback),
+            (self.show_python, Focus::Python),
+            (self.show_diagnostics, Focus::Diagnostics),
+            (self.details_page, Focus::DetailsPage),
+        ];
+        open.into_iter()
+            .find_map(|(open, focus)| open.then_some(focus))
+            .unwrap_or(Focus::List)
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        self.message_expiry.reset();
+        // Raw mode delivers Ctrl+Z as a key instead of stopping the process
+        if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_suspend = true;
+            return;
+        }
+        match self.focus() {
+            Focus::Failures => self.handle_failures_key(key),
+            Focus::Tour => self.handle_tour_key(key),
+            Focus::Help => self.handle_help_key(key),
+            Focus::Search => {
+                self.handle_search_key(key);
+                self.sync_query();
+            }
+            Focus::OwnerPrompt => self.handle_owner_key(key),
+            Focus::InstallPrompt => self.handle_install_prompt_key(key),
+            Focus::InventoryPrompt => self.handle_inventory_prompt_key(key),
+            Focus::MarkPrompt => self.handle_mark_prompt_key(key),
+            Focus::FilePrompt => self.handle_file_prompt_key(key),
+            Focus::Inspect => self.handle_inspect_key(key),
+            Focus::Usage => self.handle_usage_key(key),
+            Focus::Rollback => self.handle_rollback_key(key),
+            Focus::Confirm => {
+                let Some(confirm) = self.confirm.take() else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Enter => match confirm.value {
+                        (Action::Remove, names) => {
+                            let manager = self.package_list.package_manager;
+                            self.foreground = Some((manager, Action::Remove, names));
+                        }
+                        // Only a rollback installs from here: its cached .deb
+                        (Action::Install, paths) => {
+                            self.foreground = Some((PackageManager::Apt, Action::Install, paths));
+                        }
+                        (action, names) => self.apply_holds(action, names),
+                    },
+                    KeyCode::Char('n' | 'q') | KeyCode::Esc => self.rollback = None,
+                    _ => self.confirm = Some(confirm),
+                }
+            }
+            Focus::QuitMenu => {
+                let Some(menu) = &mut self.quit_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        self.quit_menu = None;
+                        self.quit_when_done = false;
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        let choice = menu.selected_value();
+                        self.quit_menu = None;
+                        if let Some(choice) = choice {
+                            self.quit(choice);
+                        }
+                    }
+                    KeyCode::Char('w') => {
+                        self.quit_menu = None;
+                        self.quit(QuitChoice::Wait);
+                    }
+                    KeyCode::Char('c') => {
+                        self.quit_menu = None;
+                        self.quit(QuitChoice::Cancel);
+                    }
+                    _ => {}
+                }
+            }
+            Focus::ActionMenu => {
+                let Some(menu) = &mut self.action_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.action_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        let code = menu.selected_value();
+                        self.action_menu = None;
+                        if let Some(code) = code {
+                            self.handle_key(KeyEvent::from(code));
+                        }
+                    }
+                    code if menu.contains(code) => {
+                        self.action_menu = None;
+                        self.handle_key(KeyEvent::from(code));
+                    }
+                    _ => {}
+                }
+            }
+            Focus::GroupMenu => {
+                let Some(menu) = &mut self.group_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q') => self.group_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        if let Some(group_by) = menu.selected_value() {
+                            self.package_list.set_group_by(group_by);
+                        }
+                        self.group_menu = None;
+                    }
+                    _ => {}
+                }
+            }
+            Focus::SwitchMenu => {
+                let Some(menu) = &mut self.switch_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'S') => self.switch_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        let switch = menu
+                            .selected_value()
+                            .and_then(|i| self.package_list.switches.get(i).cloned());
+                        self.switch_menu = None;
+                        if let Some(switch) = switch {
+                            self.package_list.pick_switch(switch);
+                            self.details_version += 1;
+                            self.details_scroll = 0;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Focus::PriorityMenu => {
+                let Some(menu) = &mut self.priority_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'T') => self.priority_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        if let Some(choice) = menu.selected_value() {
+                            self.pick_priority(choice);
+                        }
+                        self.priority_menu = None;
+                    }
+                    _ => {}
+                }
+            }
+            Focus::SectionMenu => {
+                let Some((menu, _)) = &mut self.section_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | '#') => self.section_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        if let Some((menu, sections)) = self.section_menu.take() {
+                            if let Some(choice) = menu.selected_value() {
+                                self.pick_section(choice, sections);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Focus::DriftMenu => {
+                let Some(menu) = &mut self.drift_menu else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'd') => self.drift_menu = None,
+                    KeyCode::Down | KeyCode::Char('j') => menu.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => menu.select_previous(),
+                    KeyCode::Enter => {
+                        if let Some(choice) = menu.selected_value() {
+                            self.pick_drift(choice);
+                        }
+                        self.drift_menu = None;
+                    }
+                    _ => {}
+                }
+            }
+            Focus::DepTree => self.handle_dep_tree_key(key),
+            Focus::Rdepends => self.handle_rdepends_key(key),
+            Focus::Health => self.handle_health_key(key),
+            Focus::Holds => self.handle_holds_key(key),
+            Focus::Repos => self.handle_repos_key(key),
+            Focus::Sessions => self.handle_sessions_key(key),
+            Focus::Export => self.handle_export_key(key),
+            Focus::Install => self.handle_install_key(key),
+            Focus::Impact => {
+                let Some(view) = &mut self.impact else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'X') => self.impact = None,
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        view.scroll = view.scroll.saturating_add(1)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => view.scroll = view.scroll.saturating_sub(1),
+                    KeyCode::Char('a') => {
+                        if let Err(err) = view.run_dry_run(self.package_list.runner.as_ref()) {
+                            self.message = Some(err);
+                        }
+                    }
+                    KeyCode::Char(c @ ('p' | 'c')) => {
+                        let plan = plan::build(&self.package_list, view);
+                        self.message = Some(if c == 'c' {
+                            if clipboard::copy(&plan.text).is_ok() {
+                                i18n::fill(
+                                    "message.plan_copied",
+                                    &[("summary", &plan::summary(&plan))],
+                                )
+                            } else {
+                                i18n::text("message.no_clipboard").to_string()
+                            }
+                        } else {
+                            match plan::write(&plan) {
+                                Ok(path) => i18n::fill(
+                                    "message.plan_written",
+                                    &[
+                                        ("file", &path.display()),
+                                        ("summary", &plan::summary(&plan)),
+                                    ],
+                                ),
+                                Err(err) => i18n::fill(
+                                    "message.write_failed",
+                                    &[("file", &plan::PLAN_NAME), ("error", &err)],
+                                ),
+                            }
+                        });
+                    }
+                    _ => {}
+                }
+            }
+            Focus::Upgrades => {
+                let Some(view) = &mut self.upgrades else {
+                    return;
+                };
+                match (key.code, view.opened.is_some()) {
+                    (KeyCode::Esc | KeyCode::Char('q'), true) => view.opened = None,
+                    (KeyCode::Esc | KeyCode::Char('q'), false) => self.upgrades = None,
+                    (KeyCode::Down | KeyCode::Char('j'), true) => {
+                        view.scroll = view.scroll.saturating_add(1)
+                    }
+                    (KeyCode::Up | KeyCode::Char('k'), true) => {
+                        view.scroll = view.scroll.saturating_sub(1)
+                    }
+                    (KeyCode::Down | KeyCode::Char('j'), false) => view.select_next(),
+                    (KeyCode::Up | KeyCode::Char('k'), false) => view.select_previous(),
+                    (KeyCode::Char('s'), false) => view.toggle_sort(),
+                    (KeyCode::Enter, false) => {
+                        let list = &self.package_list;
+                        let Some(pkg) = view.selected().and_then(|upgrade| {
+                            list.items.iter().find(|pkg| pkg.name == upgrade.name)
+                        }) else {
+                            return;
+                        };
+                        match list.loaded_details(pkg) {
+                            Some(after) => view.open(after),
+                            None => {
+                                self.message = Some(i18n::fill(
+                                    "message.details_loading",
+                                    &[("name", &pkg.name)],
+                                ))
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Focus::Stats => {
+                let Some(view) = &mut self.stats else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'L') => self.stats = None,
+                    KeyCode::Down | KeyCode::Char('j') => view.scroll_by(true, 1),
+                    KeyCode::Up | KeyCode::Char('k') => view.scroll_by(false, 1),
+                    KeyCode::PageDown => view.scroll_by(true, 10),
+                    KeyCode::PageUp => view.scroll_by(false, 10),
+                    _ => {}
+                }
+            }
+            Focus::Growth => {
+                let Some(view) = &mut self.growth else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('q' | 'D') => self.growth = None,
+                    KeyCode::Down | KeyCode::Char('j') => view.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => view.select_previous(),
+                    KeyCode::Char(' ') => view.set_from(),
+                    _ => {}
+                }
+            }
+            Focus::Python => match key.code {
+                KeyCode::Char('p' | 'q') | KeyCode::Esc => self.show_python = false,
+                KeyCode::Char('i') => self.include_listed = !self.include_listed,
+                KeyCode::Char('y') => {
+                    let command = self.python.as_ref().map(|python| {
+                        python.reinstall_command(&self.no_upgrade, self.include_listed)
+                    });
+                    self.message = Some(if clipboard::copy(&command.unwrap_or_default()).is_ok() {
+                        i18n::text("message.pip_command_copied").to_string()
+                    } else {
+                        i18n::text("message.no_clipboard").to_string()
+                    });
+                    self.show_python = false;
+                }
+                _ => {}
+            },
+            Focus::Diagnostics => match key.code {
+                KeyCode::Char('!' | 'q') | KeyCode::Esc => self.show_diagnostics = false,
+                KeyCode::Char('y') if self.package_list.rejected_count > 0 => {
+                    self.message = Some(if clipboard::copy(&self.parse_report()).is_ok() {
+                        i18n::text("message.parse_report_copied").to_string()
+                    } else {
+                        i18n::text("message.no_clipboard").to_string()
+                    });
+                    self.show_diagnostics = false;
+                }
+                _ => {}
+            },
+            Focus::List | Focus::DetailsPage => self.handle_list_key(key),
+        }
+    }
+
+    fn handle_list_key(&mut self, key: KeyEvent) {
+        // Esc leaves the Files view and clears a search or filter before
+        // whatever it is bound to (quitting, by default)
+        if key.code == KeyCode::Esc && self.step_back() {
+            return;
+        }
+        if let Some(binding) = self.keys.lookup(&key) {
+            self.apply_binding(binding);
+            return;
+        }
+        match key.code {
+            KeyCode::Char('?') => {
+                self.help = Some(help::Help::new(&self.keys, self.read_only));
+            }
+            KeyCode::Char('/') => {
+                self.search = Some(search::Search::new());
+            }
+            KeyCode::Char('n' | 'N') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.step_detail_match(key.code == KeyCode::Char('n'));
+            }
+            KeyCode::Char('n') => self.step_list_match(true),
+            KeyCode::Char('N') => self.step_list_match(false),
+            KeyCode::Char('d' | 'u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.scroll_details_page(key.code == KeyCode::Char('d'));
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.copy_details();
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.redo(),
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.file_prompt = Some(inspect::DEFAULT_DIR.to_string());
+            }
+            KeyCode::Char('%') => self.open_usage(),
+            KeyCode::Char('<') => self.start_rollback(),
+            KeyCode::PageDown => self.scroll_details_page(true),
+            KeyCode::PageUp => self.scroll_details_page(false),
+            KeyCode::Enter => match self.package_list.selected_header() {
+                Some(collapsed) => self.package_list.set_collapsed(!collapsed),
+                // On a short screen the details are a page of their own
+                None if self.arrangement == layout::Arrangement::Compact
+                    && !self.details_page
+                    && self.package_list.selected().is_some() =>
+                {
+                    self.details_page = true;
+                    self.details_scroll = 0;
+                }
+                None => self.open_action_menu(),
+            },
+            KeyCode::Char('v') => {
+                self.open_group_menu();
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.package_list.set_collapsed(true);
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.package_list.set_collapsed(false);
+            }
+            KeyCode::Char('{') => {
+                self.package_list.select_group(false);
+            }
+            KeyCode::Char('}') => {
+                self.package_list.select_group(true);
+            }
+            KeyCode::Char(' ') => {
+                let before = self.package_list.marked.clone();
+                self.package_list.toggle_mark();
+                self.record_marks(&before);
+            }
+            KeyCode::Char('a') => self.mark_visible(Some(true)),
+            KeyCode::Char('A') => self.mark_visible(Some(false)),
+            KeyCode::Char('~') => self.mark_visible(None),
+            KeyCode::Char('m') => self.mark_prompt = Some((true, String::new())),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('z') => self.toggle_hidden(),
+            KeyCode::Char('c') => self.toggle_orphans(),
+            KeyCode::Char('Z') => {
+                let list = &mut self.package_list;
+                list.show_hidden = !list.show_hidden;
+                list.rebuild_rows();
+            }
+            KeyCode::Char('H') => {
+                self.toggle_hold();
+            }
+            KeyCode::Char('P') => {
+                self.open_holds();
+            }
+            KeyCode::Char('w') => {
+                self.open_homepage();
+            }
+            #[cfg(feature = "reports")]
+            KeyCode::Char('M') => {
+                self.write_report();
+            }
+            #[cfg(feature = "reports")]
+            KeyCode::Char('B') => {
+                self.write_bootstrap_script();
+            }
+            KeyCode::Char('!') => {
+                self.show_diagnostics = true;
+            }
+            KeyCode::Char('O') => {
+                self.open_repos();
+            }
+            KeyCode::Char('b') => {
+                self.toggle_source_filter();
+            }
+            KeyCode::Char('T') => {
+                self.open_priority_menu();
+            }
+            KeyCode::Char('#') => self.open_section_menu(),
+            KeyCode::Char('d') => {
+                self.open_drift_menu();
+            }
+            KeyCode::Char('t') => {
+                self.open_dep_tree();
+            }
+            KeyCode::Char('y') => {
+                self.open_rdepends();
+            }
+            KeyCode::Char('Y') => {
+                self.copy_name();
+            }
+            KeyCode::Char('V') => {
+                self.toggle_upgradable();
+            }
+            KeyCode::Char('e') => {
+                self.inventory_prompt = Some(inventory::DEFAULT_PATH.to_string());
+            }
+            KeyCode::Char('i') => {
+                if let Some(refused) = self.refuse(Action::Install) {
+                    self.message = Some(refused);
+                } else {
+                    self.install_prompt = Some(String::new());
+                }
+            }
+            KeyCode::Char('x') if !self.root_banner => {
+                self.confirm_remove();
+            }
+            KeyCode::Char('x') if self.root_banner => {
+                self.root_banner = false;
+                if let Err(err) = root::dismiss() {
+                    self.message = Some(i18n::fill("message.dismiss_failed", &[("error", &err)]));
+                }
+            }
+            KeyCode::Char('S') => {
+                self.open_switch_menu();
+            }
+            KeyCode::Char('s') => {
+                self.sessions = Some(sessions::SessionsView::load());
+            }
+            KeyCode::Char('o') => {
+                self.sort(self.package_list.sort_by.next());
+            }
+            KeyCode::Char('X') => {
+                self.open_impact();
+            }
+            KeyCode::Char('E') => {
+                self.open_export();
+            }
+            KeyCode::Char('I') => {
+                self.open_install();
+            }
+            KeyCode::Char('r') => self.refresh(false),
+            KeyCode::Char('R') => self.refresh(true),
+            KeyCode::Char('F') => {
+                // Starts from the last search, else a one-word clipboard
+                let query = self.search.as_ref().map(|search| search.query.clone());
+                let guess = query.filter(|query| !query.is_empty()).or_else(|| {
+                    clipboard::paste()
+                        .map(|text| text.trim().to_string())
+                        .filter(|text| !text.is_empty() && !text.contains(char::is_whitespace))
+                });
+                self.owner_prompt = Some(guess.unwrap_or_default());
+            }
+            KeyCode::Char('U') => {
+                self.toggle_no_upgrade();
+            }
+            KeyCode::Char('D') => {
+                self.growth = Some(growth::GrowthView::load(&self.managers.enabled));
+            }
+            KeyCode::Char('L') => self.open_stats(),
+            KeyCode::Char('p') if self.python.is_some() => {
+                self.show_python = true;
+            }
+            KeyCode::Char('W') => {
+                let interval = self.watch_interval.unwrap_or(watch::DEFAULT_INTERVAL);
+                self.toggle_watch(interval);
+            }
+            KeyCode::Char('C') => {
+                if self.health.is_none() {
+                    let runner = self.package_list.runner.clone();
+                    self.health = Some(dashboard::Dashboard::open(runner));
+                }
+                self.show_health = true;
+            }
+            KeyCode::Char('[' | ']') => {
+                self.tabs.step(key.code == KeyCode::Char(']'));
+                self.details_scroll = 0;
+            }
+            KeyCode::Char('f') => {
+                self.tabs.toggle_files();
+                self.details_scroll = 0;
+            }
+            KeyCode::Char('J') => {
+                // Scroll details down
+                self.details_scroll = self
+                    .details_scroll
+                    .saturating_add(1)
+                    .min(self.max_details_scroll());
+            }
+            KeyCode::Char('K') => {
+                // Scroll details up
+                self.details_scroll = self.details_scroll.saturating_sub(1);
+            }
+            KeyCode::Char('+') => {
+                // Increase details section size (up to 80%)
+                self.details_height_percentage = (self.details_height_percentage + 5).min(80);
+            }
+            KeyCode::Char('-') => {
+                // Decrease details section size (down to 10%)
+                self.details_height_percentage =
+                    self.details_height_percentage.saturating_sub(5).max(10);
+            }
+            _ => {}
+        }
+    }
+
+    // "apt · 5m ago", and whether that is older than the manager's threshold.
+    fn age_label(&self) -> (String, bool) {
+        let list = &self.package_list;
+        if self.loader.is_loading() {
+            let state = if list.items.is_empty() {
+                i18n::text("list.loading")
+            } else {
+                i18n::text("list.cached_loading")
+            };
+            return (
+                format!("{} · {}", list.package_manager.name(), state),
+                false,
+            );
+        }
+        let age = list.loaded_at.elapsed();
+        (
+            format!(
+                "{} · {}",
+                list.package_manager.name(),
+                format_age(age.as_secs())
+            ),
+            age > config::stale_after(list.package_manager),
+        )
+    }
+
+    // Re-reads the current manager's list, or with `everything` also what is
+    // checked once for all managers (the python upgrade banner).
+    fn refresh(&mut self, everything: bool) {
+        if self.loader.is_loading() {
+            self.message = Some(i18n::text("message.still_loading").to_string());
+            return;
+        }
+        let upgraded = match self.package_list.refresh() {
+            Ok(upgraded) => upgraded,
+            Err(err) => {
+                self.message = Some(err);
+                return;
+            }
+        };
+        self.tabs.clear();
+        self.details_version += 1;
+        let list = &self.package_list;
+        listcache::save(list.package_manager, &list.items);
+        let mut message = i18n::fill(
+            "message.refreshed",
+            &[
+                ("manager", &list.package_manager.name()),
+                ("count", &list.items.len()),
+            ],
+        );
+        if everything && self.managers.enabled.contains(&PackageManager::Pip) {
+            self.python = python::check(list.runner.as_ref());
+            message.push_str(i18n::text("message.refreshed_python"));
+        }
+        if !upgraded.is_empty() {
+            message.push_str(&i18n::fill(
+                "message.refreshed_upgraded",
+                &[("count", &upgraded.len())],
+            ));
+            self.upgrades = Some(upgrades::UpgradesView::new(upgraded));
+        }
+        self.message = Some(message);
+    }
+
+    // Background work that quitting would cut short; detail views loading in
+    // the background only read, and don't count.
+    fn running_operations(&self) -> Vec<String> {
+        let mut running = Vec::new();
+        let checks = self.health.as_ref().map_or(0, |health| health.running());
+        match checks {
+            0 => {}
+            1 => running.push(i18n::text("quit.check").to_string()),
+            n => running.push(i18n::fill("quit.checks", &[("count", &n)])),
+        }
+        if self.export.as_ref().is_some_and(|view| view.is_running()) {
+            running.push(i18n::text("quit.export").to_string());
+        }
+        running
+    }
+
+    // Quits, unless something runs in the background: then asks whether to
+    // wait for it, cancel it or leave it behind.
+    fn request_quit(&mut self) {
+        let running = self.running_operations();
+        if running.is_empty() {
+            self.should_exit = true;
+            return;
+        }
+        let entry = |label: &str, hint: &str, value| menu::MenuEntry {
+            label: label.to_string(),
+            hint: hint.to_string(),
+            value,
+        };
+        self.quit_menu = Some(menu::Menu::new(
+            i18n::fill("quit.title", &[("running", &running.join(", "))]),
+            vec![
+                entry(i18n::text("quit.wait"), "w", QuitChoice::Wait),
+                entry(i18n::text("quit.cancel"), "c", QuitChoice::Cancel),
+                entry(i18n::text("quit.abandon"), "", QuitChoice::Abandon),
+            ],
+        ));
+    }
+
+    fn quit(&mut self, choice: QuitChoice) {
+        match choice {
+            QuitChoice::Wait => {
+                self.quit_when_done = true;
+                self.message = Some(i18n::text("message.quitting").to_string());
+            }
+            QuitChoice::Cancel => {
+                if let Some(health) = &mut self.health {
+                    health.cancel_all();
+                }
+                if let Some(view) = &mut self.export {
+                    view.cancel();
+                }
+                self.should_exit = true;
+            }
+            QuitChoice::Abandon => {
+                self.abandon = true;
+                self.should_exit = true;
+            }
+        }
+    }
+
+    // Cancelled work stops at its next step, but a command already started
+    // runs to the end; waiting for it means none is left behind, say holding
+    // the dpkg lock, once the terminal is restored.
+    fn has_workers(&self) -> bool {
+        self.health
+            .as_ref()
+            .is_some_and(|health| health.has_workers())
+            || self.export.as_ref().is_some_and(|view| view.is_running())
+            || self.workers.iter().any(|worker| !worker.is_finished())
+    }
+
+    fn join_workers(&mut self) {
+        if let Some(health) = &mut self.health {
+            health.join();
+        }
+        self.close_export();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+
+    // Whether watch refreshes should wait: a screen or prompt is open, a list
+    // is loading or health checks are running. Typing a search doesn't count,
+    // as the list filters as it goes anyway.
+    fn is_busy(&self) -> bool {
+        let focus = self.focus();
+        !(focus.on_list() || focus == Focus::Search)
+            || self.loader.is_loading()
+            || self
+                .health
+                .as_ref()
+                .is_some_and(|health| health.is_running())
+    }
+
+    // Returns whether the list was refreshed (or failed to be).
+    fn watch_tick(&mut self) -> bool {
+        let Some(watch) = &self.watch else {
+            return false;
+        };
+        if !watch.is_due() {
+            return false;
+        }
+        if self.is_busy() {
+            if let Some(watch) = &mut self.watch {
+                watch.postpone();
+            }
+            return false;
+        }
+        match self.package_list.refresh() {
+            Ok(_) => {
+                self.tabs.clear();
+                self.details_version += 1;
+            }
+            Err(err) => self.message = Some(err),
+        }
+        if let Some(watch) = &mut self.watch {
+            watch.refreshed();
+        }
+        true
+    }
+
+    fn toggle_watch(&mut self, interval: Duration) {
+        if self.watch.take().is_some() {
+            self.package_list.changes.clear();
+            self.message = Some(i18n::text("message.watch_off").to_string());
+        } else {
+            self.watch = Some(watch::Watch::new(interval));
+            self.message = Some(i18n::fill(
+                "message.watching",
+                &[("seconds", &interval.as_secs())],
+            ));
+        }
+    }
+
+    // The list shows only what the search matches, narrowing as it is typed.
+    fn sync_query(&mut self) {
+        let query = self
+            .search
+            .as_ref()
+            .map_or("", |search| search.query.as_str());
+        self.package_list.set_query(query);
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Enter => {
+                if search.query.is_empty() {
+                    self.search = None;
+                } else {
+                    search.editing = false;
+                    if !self.selected_matches() {
+                        self.step_list_match(true);
+                    }
+                }
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            KeyCode::Char(c) => search.query.push(c),
+            _ => {}
+        }
+    }
+
+    fn handle_owner_key(&mut self, key: KeyEvent) {
+        let Some(command) = &mut self.owner_prompt else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.owner_prompt = None,
+            KeyCode::Backspace => {
+                command.pop();
+            }
+            KeyCode::Char(c) => command.push(c),
+            KeyCode::Enter => {
+                let command = command.trim().to_string();
+                self.owner_prompt = None;
+                if !command.is_empty() {
+                    self.show_owner(&command);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Traces a command to its file and jumps to the package that installed
+    // it, saying where the file is either way.
+    fn show_owner(&mut self, command: &str) {
+        let found = match which::find(self.package_list.runner.as_ref(), command) {
+            Ok(found) => found,
+            Err(err) => {
+                self.message = Some(err);
+                return;
+            }
+        };
+        let mut file = found.path.display().to_string();
+        if let Some(target) = &found.target {
+            file.push_str(&format!(" → {}", target.display()));
+        }
+        let Some((mut manager, name)) = found.owner else {
+            self.message = Some(i18n::fill(
+                "message.owner_unknown",
+                &[("command", &command), ("file", &file)],
+            ));
+            return;
+        };
+        // dpkg's files belong to pkg and apt alike; stay on the one shown
+        if manager == PackageManager::Apt
+            && (self.package_list.package_manager == PackageManager::Pkg
+                || !self.managers.enabled.contains(&PackageManager::Apt))
+        {
+            manager = PackageManager::Pkg;
+        }
+        self.jump_to(manager, &name);
+        if self.message.is_none() {
+            self.message = Some(i18n::fill(
+                "message.owner",
+                &[
+                    ("command", &command),
+                    ("file", &file),
+                    ("name", &name),
+                    ("manager", &manager.name()),
+                ],
+            ));
+        }
+    }
+
+    fn selected_matches(&self) -> bool {
+        match (&self.search, self.package_list.selected()) {
+            (Some(search), Some(pkg)) => search.matches(&pkg.name),
+            _ => false,
+        }
+    }
+
+    // Moves to the next (or previous) package whose name matches, wrapping.
+    fn step_list_match(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let list = &self.package_list;
+        let len = list.rows.len();
+        let start = list.state.selected().unwrap_or(0);
+        let found = (1..=len)
+            .map(|offset| {
+                if forward {
+                    (start + offset) % len
+                } else {
+                    (start + len - offset % len) % len
+                }
+            })
+            .find(
+                |&i| matches!(list.rows[i], Row::Package(p) if list.items.get(p).is_some_and(|pkg| search.matches(&pkg.name))),
+            );
+        match found {
+            Some(row) => {
+                self.package_list.state.select(Some(row));
+                self.details_scroll = 0;
+            }
+            None => {
+                self.message = Some(i18n::fill("message.no_match", &[("query", &search.query)]))
+            }
+        }
+    }
+
+    // Moves to the next (or previous) occurrence in the detail pane and
+    // scrolls it into view.
+    fn step_detail_match(&mut self, forward: bool) {
+        let Some(pkg) = self.package_list.selected() else {
+            return;
+        };
+        let name = pkg.name.clone();
+        let text = self.detail_content();
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let count = text
+            .lines()
+            .map(|line| search.occurrences(line).len())
+            .sum();
+        if count == 0 {
+            self.message = Some(i18n::fill(
+                "message.no_detail_match",
+                &[("query", &search.query)],
+            ));
+            return;
+        }
+        search.step_detail(&name, count, forward);
+        let (_, line) = search.highlight_lines(&text, search.detail_match(&name));
+        self.details_scroll = line.unwrap_or(0) as u16;
+    }
+
+    // The Fields view with each value's renderer, once `show` has loaded.
+    // The search highlights the plain text instead.
+    fn field_lines(&self) -> Option<Vec<Line<'static>>> {
+        if self.tabs.view != tabs::View::Fields {
+            return None;
+        }
+        let list = &self.package_list;
+        let pkg = list.selected()?;
+        if self.details_pane(pkg) != preview::Pane::Details {
+            return None;
+        }
+        let mut lines = tabs::field_lines(
+            &list.loaded_details(pkg)?,
+            list.package_manager,
+            &list.render_context(),
+        );
+        // Above the fields: it explains an upgrade that changed nothing
+        if let Some(notes) = list.shadow_notes(pkg) {
+            let style = Style::default().fg(Color::Magenta);
+            let mut top: Vec<Line> = notes
+                .iter()
+                .map(|note| Line::styled(format!("⚠ {}", note), style))
+                .collect();
+            top.push(Line::from(""));
+            lines.splice(0..0, top);
+        }
+        Some(lines)
+    }
+
+    // Notes where the selection is for the detail pane's delay. Returns
+    // whether it just came to rest.
+    fn observe_rest(&mut self) -> bool {
+        let list = &self.package_list;
+        let selected = list
+            .selected()
+            .map(|pkg| (list.package_manager, pkg.name.clone()));
+        self.rest
+            .observe(selected, self.details_delay, Instant::now())
+    }
+
+    // Counts the selection's details as prefetched or not, and starts the
+    // screen's batch once idle.
+    fn observe_prefetch(&mut self) {
+        let list = &self.package_list;
+        let selected = list
+            .selected()
+            .map(|pkg| (pkg.name.clone(), pkg.version.clone()));
+        let cached = selected
+            .as_ref()
+            .is_some_and(|(name, version)| list.shown.contains(name, version));
+        self.prefetch.select(selected, cached);
+        self.prefetch
+            .tick(&list.shown, &list.runner, Instant::now());
+    }
+
+    // Clears the message once it has been up for a while. Returns whether
+    // it did.
+    fn expire_message(&mut self) -> bool {
+        if !self
+            .message_expiry
+            .observe(self.message.as_deref(), Instant::now())
+        {
+            return false;
+        }
+        self.message = None;
+        self.message_expiry.reset();
+        true
+    }
+
+    // What the detail pane shows for the selected package under the
+    // `details` setting. Timelines don't run `show`, so they always show.
+    fn details_pane(&self, pkg: &Package) -> preview::Pane {
+        if self.tabs.view == tabs::View::Timeline {
+            return preview::Pane::Details;
+        }
+        let cached = self.package_list.shown.contains(&pkg.name, &pkg.version);
+        preview::pane(self.details_fetch, self.rest.rested(), cached)
+    }
+
+    // Text of the active detail view for the selected package. Views built
+    // from the manager's `show` output are cached until another package or
+    // view is selected or `details_version` moves, so redraws for scrolling
+    // or a status message don't rebuild them; `show` itself runs in the
+    // background the first time a package is shown.
+    fn detail_content(&mut self) -> String {
+        if let (Some(name), Some(drift)) = (
+            self.package_list.selected_missing(),
+            &self.package_list.drift,
+        ) {
+            return format!(
+                "{} is in {} but not installed with {}.",
+                name,
+                drift.manifest.path.display(),
+                self.package_list.package_manager.name()
+            );
+        }
+        let Some(pkg) = self.package_list.selected().cloned() else {
+            return i18n::text("message.no_selection").to_string();
+        };
+        match self.details_pane(&pkg) {
+            preview::Pane::Details => {}
+            preview::Pane::Waiting => return i18n::text("details.loading").to_string(),
+            preview::Pane::Summary => return preview::summary(&pkg),
+        }
+        let key = DetailKey {
+            manager: self.package_list.package_manager,
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            view: self.tabs.view,
+            details_version: self.details_version,
+        };
+        if let Some((cached, text)) = &self.detail_cache {
+            if *cached == key {
+                return text.clone();
+            }
+        }
+        let list = &self.package_list;
+        let shown = match self.tabs.view {
+            tabs::View::Raw | tabs::View::Fields | tabs::View::Deps => {
+                match list.shown.get_or_start(
+                    &list.runner,
+                    list.package_manager,
+                    &pkg.name,
+                    &pkg.version,
+                ) {
+                    Some(text) => text,
+                    None => return i18n::text("details.loading").to_string(),
+                }
+            }
+            _ => String::new(),
+        };
+        let details = || details::Details::parse_for(list.package_manager, &shown);
+        let text = match self.tabs.view {
+            tabs::View::Raw => self.detail_text(&pkg, shown.clone()),
+            tabs::View::Fields => {
+                tabs::fields_text(&details(), list.package_manager, &list.render_context())
+            }
+            tabs::View::Deps => tabs::deps_text(
+                &details(),
+                list.why_text(&pkg),
+                list.extras
+                    .as_ref()
+                    .and_then(|extras| extras.text(&pkg.name)),
+            ),
+            tabs::View::Timeline => match list.package_manager {
+                PackageManager::Pkg | PackageManager::Apt => {
+                    match list.dpkg_log.get_or_start(&list.runner) {
+                        Some(log) => dpkglog::timeline(log, &pkg.name),
+                        None => i18n::text("details.reading_log").to_string(),
+                    }
+                }
+                manager => format!(
+                    "No timeline for {} packages: only dpkg logs them",
+                    manager.name()
+                ),
+            },
+            // Already cached by the tabs, and may still be loading
+            tabs::View::Files | tabs::View::Policy => {
+                return match self.tabs.loaded(
+                    &list.runner,
+                    list.package_manager,
+                    &pkg.name,
+                    &pkg.version,
+                ) {
+                    Some(Ok(text)) if self.tabs.view == tabs::View::Policy => {
+                        match pins::explain(&list.pins, &pkg.name, &text) {
+                            Some(pins) => format!("{}\n{}", text.trim_end(), pins),
+                            None => text,
+                        }
+                    }
+                    Some(Ok(text)) => {
+                        let text = match list.shadow_text(&pkg) {
+                            Some(shadows) => format!("{}\n{}", shadows, text),
+                            None => text,
+                        };
+                        match list.stray_files(&pkg) {
+                            Some(strays) => format!(
+                                "{}:\n  {}\n\n{}",
+                                strays::label(list.package_manager),
+                                strays.join("\n  "),
+                                text
+                            ),
+                            None => text,
+                        }
+                    }
+                    Some(Err(err)) => err,
+                    None => self.placeholders().swap_remove(1),
+                };
+            }
+        };
+        self.detail_cache = Some((key, text.clone()));
+        text
+    }
+
+    // What the detail pane shows while its view loads: the details, the
+    // current view's own command, or dpkg's logs.
+    fn placeholders(&self) -> Vec<String> {
+        vec![
+            i18n::text("details.loading").to_string(),
+            i18n::fill(
+                "details.loading_view",
+                &[("view", &self.tabs.view.label().to_lowercase())],
+            ),
+            i18n::text("details.reading_log").to_string(),
+        ]
+    }
+
+    // What the Raw view shows for a package, from its `show` output.
+    fn detail_text(&self, pkg: &Package, mut text: String) -> String {
+        if let Some(why) = self.package_list.why_text(pkg) {
+            text = format!("{}\n\n{}", text.trim_end(), why);
+        }
+        if let Some(shadows) = self.package_list.shadow_text(pkg) {
+            text = format!("{}\n\n{}", text.trim_end(), shadows);
+        }
+        if let Some(reason) = self.no_upgrade_reason(pkg) {
+            text = format!(
+                "{}\n\nDo not upgrade with pip: {}\n",
+                text.trim_end(),
+                reason
+            );
+        }
+        if let Some(info) = self.package_list.build_info(pkg) {
+            text = format!("{}\n\n{}", text.trim_end(), info.text());
+        }
+        if let Some(candidate) = self.package_list.candidate(pkg) {
+            text = format!(
+                "{}\n\nUpgradable: {} installed, {} available\n",
+                text.trim_end(),
+                pkg.version,
+                candidate
+            );
+        }
+        if let Some(history) = self.package_list.history_text(pkg) {
+            text = format!("{}\n\n{}", text.trim_end(), history);
+        }
+        text
+    }
+
+    fn handle_health_key(&mut self, key: KeyEvent) {
+        let Some(health) = &mut self.health else {
+            self.show_health = false;
+            return;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'C') => self.show_health = false,
+            KeyCode::Down | KeyCode::Char('j') => health.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => health.select_previous(),
+            KeyCode::Right | KeyCode::Char('l') => health.set_expanded(true),
+            KeyCode::Left | KeyCode::Char('h') => health.set_expanded(false),
+            KeyCode::Char('r') => health.run_selected(),
+            KeyCode::Char('R') => health.run_all(),
+            KeyCode::Char('c') => health.cancel_selected(),
+            KeyCode::Enter => match health.selection() {
+                Some(dashboard::Selection::Finding(finding)) => {
+                    let (manager, name) = (finding.manager, finding.package.clone());
+                    self.jump_to(manager, &name);
+                }
+                Some(dashboard::Selection::Check) => health.toggle_expanded(),
+                None => {}
+            },
+            // The second package of a finding that involves two
+            KeyCode::Char('o') => {
+                if let Some(dashboard::Selection::Finding(finding)) = health.selection() {
+                    if let Some((manager, name)) = finding.other.clone() {
+                        self.jump_to(manager, &name);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The `o` key and the column headers both come here, so they share one
+    // order.
+    fn sort(&mut self, sort_by: SortBy) {
+        let list = &mut self.package_list;
+        list.set_sort(sort_by);
+        self.message = Some(match sort_by {
+            SortBy::Size | SortBy::SizeAscending if list.sizes.is_empty() => i18n::fill(
+                "message.sorted_no_sizes",
+                &[("manager", &list.package_manager.name())],
+            ),
+            sort_by => i18n::fill("message.sorted", &[("order", &sort_by.label())]),
+        });
+    }
+
+    // Shows the next manager's list, kept from the last time with its
+    // selection, or loaded in the background the first time (the title
+    // changes at once). The search and repository filter stay applied, so the
+    // same query can be checked under every manager; a fresh list starts at
+    // its first match. $TPI_CLEAR_SEARCH_ON_SWITCH=1 drops them instead.
+    fn next_manager(&mut self, backwards: bool) {
+        let Some(next) = self
+            .package_list
+            .next_manager(&self.managers.enabled, backwards)
+        else {
+            return;
+        };
+        // A list still loading, or that failed to, is loaded again next time
+        let keep = !self.loader.is_loading() && self.package_list.load_error.is_none();
+        self.loader.cancel();
+        let previous = match self.parked.remove(&next) {
+            Some(mut parked) => {
+                let selected = parked.selected().map(|pkg| pkg.name.clone());
+                parked.take_on(self.package_list.hand_on());
+                let previous = std::mem::replace(&mut self.package_list, parked);
+                if !selected.is_some_and(|name| self.package_list.select_package(&name)) {
+                    self.select_first_match();
+                }
+                previous
+            }
+            None => {
+                let loading = self.package_list.loading(next);
+                let (runner, profile) = (loading.runner.clone(), loading.profile);
+                self.loader.start(next, move || {
+                    (PackageList::open(next, runner, profile), Vec::new())
+                });
+                std::mem::replace(&mut self.package_list, loading)
+            }
+        };
+        if keep {
+            self.parked.insert(previous.package_manager, previous);
+        }
+        self.details_version += 1; // Another list, so why-installed and history differ
+        self.details_scroll = 0;
+        if config::clear_search_on_switch() {
+            self.search = None;
+            self.sync_query();
+            if self.package_list.filter.take().is_some() {
+                self.package_list.rebuild_rows();
+            }
+        }
+    }
+
+    // The python upgrade check started with tpi, once it is done.
+    fn poll_python(&mut self) -> bool {
+        let Some(receiver) = &self.python_check else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Ok(python) => {
+                self.python = python;
+                self.python_check = None;
+                self.python.is_some()
+            }
+            Err(mpsc::TryRecvError::Empty) => false,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.python_check = None;
+                false
+            }
+        }
+    }
+
+    // Takes over the list loaded in the background, once it arrives. At
+    // startup, when no manager could be listed, the failures screen opens
+    // instead.
+    fn poll_loading(&mut self) -> bool {
+        let Some((list, errors)) = self.loader.poll() else {
+            return false;
+        };
+        if list.load_error.is_some() && !errors.is_empty() {
+            self.failures = Some(failures::Failures::new(errors));
+        } else if let Some(err) = errors.first() {
+            self.message = Some(i18n::fill(
+                "message.showing_instead",
+                &[("error", &err), ("manager", &list.package_manager.name())],
+            ));
+        }
+        log::debug(&format!(
+            "{} listed {} ms after startup",
+            list.package_manager.name(),
+            self.started.elapsed().as_millis()
+        ));
+        if list.load_error.is_none() {
+            listcache::save(list.package_manager, &list.items);
+        }
+        // Moving around the cached list carries over
+        let selected = self
+            .package_list
+            .selected()
+            .filter(|_| self.package_list.package_manager == list.package_manager)
+            .map(|pkg| pkg.name.clone());
+        self.package_list.replace(list);
+        self.details_version += 1;
+        self.details_scroll = 0;
+        match selected {
+            Some(name) if self.package_list.select_package(&name) => {}
+            _ => self.select_first_match(),
+        }
+        true
+    }
+
+    // Starts the list at the first package matching the search, if any.
+    fn select_first_match(&mut self) {
+        let Some(search) = self
+            .search
+            .as_ref()
+            .filter(|search| !search.query.is_empty())
+        else {
+            return;
+        };
+        let list = &self.package_list;
+        let first = list
+            .rows
+            .iter()
+            .position(|row| matches!(row, Row::Package(i) if list.items.get(*i).is_some_and(|pkg| search.matches(&pkg.name))));
+        if let Some(row) = first {
+            self.package_list.state.select(Some(row));
+        }
+    }
+
+    // Leaves the health screen for the package in the main list.
+    fn jump_to(&mut self, manager: PackageManager, name: &str) {
+        self.loader.cancel();
+        // The package may be from another repository than the one shown, or
+        // not match the search
+        if self.package_list.filter.take().is_some() {
+            self.package_list.rebuild_rows();
+        }
+        if self.search.take().is_some() {
+            self.sync_query();
+        }
+        if self.package_list.package_manager != manager {
+            if !commands::is_available(manager) {
+                self.message = Some(i18n::fill(
+                    "message.not_installed",
+                    &[("name", &manager.name())],
+                ));
+                return;
+            }
+            self.package_list.switch_to(manager);
+        }
+        if self.package_list.select_package(name) {
+            self.show_health = false;
+            self.details_scroll = 0;
+        } else {
+            self.message = Some(i18n::fill(
+                "message.not_in_list",
+                &[("name", &name), ("manager", &manager.name())],
+            ));
+        }
+    }
+
+    // Everything needed to fix a parser: the command, and each rejected line
+    // with the rule it broke.
+    fn parse_report(&self) -> String {
+        let list = &self.package_list;
+        let command = commands::resolve(list.package_manager, Action::List, "")
+            .map(|words| commands::join_words(&words))
+            .unwrap_or_default();
+        let mut text = format!(
+            "tpi {}: {} of {} listing lines not understood\n\nCommand: `{}`\n\n```\n",
+            env!("CARGO_PKG_VERSION"),
+            list.rejected_count,
+            list.package_manager.name(),
+            command
+        );
+        for rejection in &list.rejected {
+            text.push_str(&format!("{}\t# {}\n", rejection.line, rejection.rule));
+        }
+        if list.rejected_count > list.rejected.len() {
+            text.push_str(&format!(
+                "# ... {} more not kept\n",
+                list.rejected_count - list.rejected.len()
+            ));
+        }
+        text.push_str("```\n");
+        text
+    }
+
+    fn diagnostics_text(&self) -> String {
+        let manager = self.package_list.package_manager;
+        let names: Vec<&str> = self.managers.enabled.iter().map(|m| m.name()).collect();
+        let mut text = format!(
+            "Managers: {} ({})\n",
+            names.join(", "),
+            if self.managers.explicit {
+                "from TPI_MANAGERS"
+            } else {
+                "detected"
+            }
+        );
+        if !self.managers.unknown.is_empty() {
+            text.push_str(&format!(
+                "  Unknown names in TPI_MANAGERS: {}\n",
+                self.managers.unknown.join(", ")
+            ));
+        }
+
+        text.push_str(&format!("\nCommands for {}:\n", manager.name()));
+        for action in Action::ALL {
+            if let Some(words) = commands::resolve(manager, action, "{name}") {
+                text.push_str(&format!(
+                    "  {:?}: {}\n",
+                    action,
+                    commands::join_words(&words)
+                ));
+            }
+        }
+
+        if let Some(home) = &self.package_list.home {
+            text.push_str(&format!("  Global home: {}\n", home));
+        }
+
+        text.push_str(match self.package_list.profile {
+            config::Profile::Fast => {
+                "\nProfile: fast (holds, architecture, sizes, why-installed and history skipped)\n"
+            }
+            config::Profile::Full => "\nProfile: full\n",
+        });
+
+        let list = &self.package_list;
+        if list.rejected_count > 0 {
+            text.push_str(&format!(
+                "\nLines not understood ({}, y copies a bug report):\n",
+                list.rejected_count
+            ));
+            for rejection in list.rejected.iter().take(DIAGNOSTIC_REJECTIONS) {
+                text.push_str(&format!("  {}\n    {}\n", rejection.line, rejection.rule));
+            }
+            if list.rejected_count > DIAGNOSTIC_REJECTIONS {
+                text.push_str(&format!(
+                    "  ... and {} more\n",
+                    list.rejected_count - DIAGNOSTIC_REJECTIONS
+                ));
+            }
+        }
+
+        text.push_str("\nCommand overrides:\n");
+        let overrides = commands::active_overrides();
+        if overrides.is_empty() {
+            text.push_str("  none (set TPI_<MANAGER>_<ACTION>_CMD to replace a command)\n");
+        }
+        for (var, value) in overrides {
+            text.push_str(&format!("  {}={}\n", var, value));
+        }
+
+        if let Some(primary) = &self.package_list.primary_arch {
+            let foreign = health::foreign_architecture(&self.package_list.items, primary);
+            text.push_str(&format!("\nSystem architecture: {}\n", primary));
+            if foreign.is_empty() {
+                text.push_str("  No packages built for another architecture\n");
+            } else {
+                text.push_str(&format!(
+                    "  {} packages built for another architecture (likely migration leftovers):\n",
+                    foreign.len()
+                ));
+                for pkg in foreign {
+                    text.push_str(&format!(
+                        "    {} {} ({})\n",
+                        pkg.name,
+                        pkg.version,
+                        pkg.arch.as_deref().unwrap_or("?")
+                    ));
+                }
+            }
+        }
+
+        text.push_str(&format!(
+            "\nDebug log: {}\n",
+            log::path().unwrap_or_else(|| "off (set TPI_LOG=<file>)".to_string())
+        ));
+        text
+    }
+
+    fn ui(&mut self, f: &mut Frame) {
+        if let Some(failures) = &self.failures {
+            failures.render(f, f.size());
+            return;
+        }
+        if self.show_health {
+            if let Some(health) = &mut self.health {
+                health.render(f, f.size());
+                return;
+            }
+        }
+
+        // Root warning across the top until dismissed
+        let mut area = f.size();
+        if self.root_banner {
+            let banner = Rect { height: 1, ..area };
+            area.y += 1;
+            area.height = area.height.saturating_sub(1);
+            f.render_widget(
+                Paragraph::new(i18n::fill(
+                    "banner.root",
+                    &[("warning", &root::warning(!self.read_only))],
+                ))
+                .style(
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                banner,
+            );
+        }
+
+        // Python upgrade warning across the top until the packages are moved
+        if let Some(python) = &self.python {
+            let banner = Rect { height: 1, ..area };
+            area.y += 1;
+            area.height = area.height.saturating_sub(1);
+            f.render_widget(
+                Paragraph::new(i18n::fill(
+                    "banner.python",
+                    &[("summary", &python.summary())],
+                ))
+                .style(
+                    Style::default()
+                        .fg(Color::White)
+                        .bg(Color::Red)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                banner,
+            );
+        }
+
+        // Status bar along the bottom: the message or the list's counts, and
+        // a key reminder
+        let mut status_area = Rect { height: 0, ..area };
+        if area.height > 1 {
+            area.height -= 1;
+            status_area = Rect {
+                y: area.bottom(),
+                height: 1,
+                ..area
+            };
+            status::render(
+                f,
+                status_area,
+                self.focus().mode(),
+                self.message.as_deref(),
+                &self.package_list.summary(),
+                &help::footer(&self.keys),
+            );
+        }
+
+        self.arrangement = layout::Arrangement::for_screen(f.size());
+        // Grown back from a short screen: the details are below the list again
+        self.details_page &= self.arrangement == layout::Arrangement::Compact;
+        let (list_area, detail_area) = layout::split(
+            area,
+            self.arrangement,
+            self.details_height_percentage,
+            self.details_page,
+        );
+        // Where popups over the detail pane go, which may be off screen
+        let overlay_area = if self.arrangement == layout::Arrangement::Compact {
+            area
+        } else {
+            detail_area
+        };
+
+        // Render package list
+        self.package_list.clamp_selection();
+        let list = &self.package_list;
+        let items: Vec<ListItem> = list
+            .rows
+            .iter()
+            .map(|row| {
+                let pkg = match row {
+                    Row::Package(i) => match list.items.get(*i) {
+                        Some(pkg) => pkg,
+                        None => return ListItem::new(""),
+                    },
+                    Row::Missing(i) => {
+                        let name = list.drift.as_ref().map_or("", |drift| &drift.missing[*i]);
+                        return ListItem::new(Line::from(vec![
+                            Span::styled(
+                                format!("  {}", name),
+                                Style::default()
+                                    .fg(Color::DarkGray)
+                                    .add_modifier(Modifier::ITALIC),
+                            ),
+                            Span::styled(
+                                format!(" {}", i18n::text("badge.missing")),
+                                Style::default().fg(Color::Red),
+                            ),
+                        ]));
+                    }
+                    Row::Header {
+                        name,
+                        count,
+                        collapsed,
+                    } => {
+                        let arrow = if *collapsed { "▸" } else { "▾" };
+                        return ListItem::new(Line::from(Span::styled(
+                            format!("{} {} ({})", arrow, name, count),
+                            Style::default()
+                                .fg(Color::Yellow)
+                                .add_modifier(Modifier::BOLD),
+                        )));
+                    }
+                };
+                let mark = if list.is_marked(&pkg.name) {
+                    "* "
+                } else {
+                    "  "
+                };
+                let style = match list.changes.get(&pkg.name) {
+                    Some(watch::Change::Added) => Style::default().fg(Color::Green),
+                    Some(watch::Change::Upgraded) => Style::default().fg(Color::Yellow),
+                    Some(watch::Change::Removed) => Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                    None if pkg.auto_installed => Style::default().add_modifier(Modifier::DIM),
+                    None => Style::default(),
+                };
+                let mut spans = vec![Span::styled(mark, style)];
+                match &self.search {
+                    Some(search) => spans.extend(search.highlight(&pkg.name, style, None)),
+                    None => spans.push(Span::styled(pkg.name.clone(), style)),
+                }
+                spans.push(Span::styled(format!(" {}", pkg.version), style));
+                if let Some(candidate) = list.candidate(pkg) {
+                    spans.push(Span::styled(
+                        format!(" → {}", candidate),
+                        Style::default().fg(Color::LightGreen),
+                    ));
+                }
+                if let Some(arch) = &pkg.arch {
+                    let style = if list.is_foreign(pkg) {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default().fg(Color::DarkGray)
+                    };
+                    spans.push(Span::styled(format!(" {}", arch), style));
+                }
+                if let Some(priority) = &pkg.priority {
+                    // Misprioritized essentials are flagged even without the column
+                    if list.is_misprioritized(pkg) {
+                        spans.push(Span::styled(
+                            format!(" {}!", priority),
+                            Style::default().fg(Color::Red),
+                        ));
+                    } else if self.show_priority {
+                        spans.push(Span::styled(
+                            format!(" {}", priority),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                }
+                if self.show_section && pkg.section.is_some() {
+                    spans.push(Span::styled(
+                        format!(" {}", sections::of(pkg)),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                if list.held.contains(&pkg.name) {
+                    spans.push(Span::raw(format!(" {}", i18n::text("badge.held"))));
+                }
+                if list.is_orphan(pkg) {
+                    spans.push(Span::styled(
+                        format!(" {}", i18n::text("badge.orphan")),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                // Pinned through preferences, which apt-mark knows nothing of
+                if pins::is_pinned(&list.pins, &pkg.name) {
+                    spans.push(Span::styled(
+                        format!(" {}", i18n::text("badge.pinned")),
+                        Style::default().fg(Color::Cyan),
+                    ));
+                }
+                if list
+                    .build_info(pkg)
+                    .is_some_and(|info| info.build == wheels::Build::Source)
+                {
+                    spans.push(Span::styled(
+                        format!(" {}", i18n::text("badge.source")),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                if list.stray_files(pkg).is_some() {
+                    let badge = match list.package_manager {
+                        PackageManager::Pip => i18n::text("badge.off_path"),
+                        _ => i18n::text("badge.outside_prefix"),
+                    };
+                    spans.push(Span::styled(
+                        format!(" {}", badge),
+                        Style::default().fg(Color::Magenta),
+                    ));
+                }
+                match list.hiding_overridden(pkg) {
+                    Some(reason) => spans.push(Span::styled(
+                        format!(
+                            " {}",
+                            i18n::fill("badge.hidden_shown", &[("reason", &reason)])
+                        ),
+                        Style::default().fg(Color::Yellow),
+                    )),
+                    None if list.is_hidden(pkg) => {
+                        for span in &mut spans {
+                            span.style = span.style.fg(Color::DarkGray);
+                        }
+                        spans.push(Span::styled(
+                            format!(" {}", i18n::text("badge.hidden")),
+                            Style::default().fg(Color::DarkGray),
+                        ));
+                    }
+                    None => {}
+                }
+                if list.drift_state(pkg) == Some(drift::State::Added) {
+                    spans.push(Span::styled(
+                        format!(" {}", i18n::text("badge.added")),
+                        Style::default().fg(Color::Yellow),
+                    ));
+                }
+                if list.package_manager == PackageManager::Pip
+                    && self.no_upgrade.contains(&pkg.name)
+                {
+                    spans.push(Span::styled(
+                        format!(" {}", i18n::text("badge.no_upgrade")),
+                        Style::default().fg(Color::LightRed),
+                    ));
+                }
+                let mut lines = vec![Line::from(spans)];
+                if let Some(description) = &pkg.description {
+                    lines.push(Line::from(Span::styled(
+                        format!("    {}", description),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let manager = list.package_manager.name();
+        let mut title = match &list.switch {
+            Some(switch) => i18n::fill(
+                "list.title_switch",
+                &[("manager", &manager), ("switch", switch)],
+            ),
+            None => i18n::fill("list.title", &[("manager", &manager)]),
+        };
+        if list.group_by != GroupBy::None {
+            let grouping = list.group_by.label().to_lowercase();
+            title.push_str(&i18n::fill("list.grouped", &[("grouping", &grouping)]));
+        }
+        title.push_str(&format!(" · {}", list.sort_by.label()));
+        if let Some(drift) = &list.drift {
+            let file = drift.manifest.path.file_name().unwrap_or_default();
+            let file = file.to_string_lossy();
+            title.push_str(&i18n::fill("list.compared", &[("file", &file)]));
+        }
+        if self.read_only {
+            title.push_str(i18n::text("list.read_only"));
+        }
+        if lock::holder().is_some() {
+            title.push_str(i18n::text("list.not_saving"));
+        }
+        if let Some(filter) = &list.filter {
+            // The orphan scan reports its progress in place of the label
+            let label = match (filter, &list.orphans) {
+                (ListFilter::Orphans, Some(scan)) => scan.label(),
+                _ => filter.label(),
+            };
+            title.push_str(&i18n::fill("list.filtered", &[("filter", &label)]));
+        }
+        let marked = list.marked_names();
+        match list.hidden_count() {
+            0 => {}
+            n if list.show_hidden => {
+                title.push_str(&i18n::fill("list.hidden_shown", &[("count", &n)]))
+            }
+            n => title.push_str(&i18n::fill("list.hidden", &[("count", &n)])),
+        }
+        if !marked.is_empty() {
+            let sizes: Vec<u64> = marked
+                .iter()
+                .filter_map(|name| list.sizes.get(name).copied())
+                .collect();
+            let total = format_size(sizes.iter().sum());
+            let count = marked.len();
+            title.push_str(&match sizes.len() {
+                0 => i18n::fill("list.marked", &[("count", &count)]),
+                n if n == count => i18n::fill(
+                    "list.marked_size",
+                    &[("count", &count), ("size", &format!("~{}", total))],
+                ),
+                _ => i18n::fill(
+                    "list.marked_size",
+                    &[("count", &count), ("size", &format!("≥{}", total))],
+                ),
+            });
+        }
+        if let Some(search) = self
+            .search
+            .as_ref()
+            .filter(|search| !search.query.is_empty())
+        {
+            let count = list
+                .items
+                .iter()
+                .filter(|pkg| search.matches(&pkg.name))
+                .count();
+            title.push_str(&match count {
+                0 => i18n::fill(
+                    "list.no_matches",
+                    &[("query", &search.query), ("manager", &manager)],
+                ),
+                _ => i18n::fill(
+                    "list.search",
+                    &[
+                        ("query", &search.query),
+                        ("count", &count),
+                        ("total", &list.items.len()),
+                    ],
+                ),
+            });
+        }
+
+        let (age, stale) = self.age_label();
+        let age_style = if stale {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let mut block = Block::default()
+            .title(title)
+            .title(
+                block::Title::from(Span::styled(format!(" {} ", age), age_style))
+                    .alignment(Alignment::Right),
+            )
+            .borders(Borders::ALL);
+        self.drawn_age = age;
+        let note = match list.rejected_count {
+            _ if self.search.as_ref().is_some_and(|search| search.editing) => self
+                .search
+                .as_ref()
+                .map(|search| format!("/{}▏", search.query)),
+            _ if self.inventory_prompt.is_some() => self.inventory_prompt.as_ref().map(|path| {
+                let (items, marked) = list.export_items();
+                let key = if marked {
+                    "prompt.export_marked"
+                } else {
+                    "prompt.export"
+                };
+                let path = format!("{}▏", path);
+                i18n::fill(key, &[("count", &items.len()), ("path", &path)])
+            }),
+            _ if self.install_prompt.is_some() => self.install_prompt.as_ref().map(|name| {
+                let name = format!("{}▏", name);
+                i18n::fill("prompt.install", &[("manager", &manager), ("name", &name)])
+            }),
+            _ if self.file_prompt.is_some() => self
+                .file_prompt
+                .as_ref()
+                .map(|path| i18n::fill("prompt.file", &[("path", &format!("{}▏", path))])),
+            _ if self.owner_prompt.is_some() => self.owner_prompt.as_ref().map(|command| {
+                let command = format!("{}▏", command);
+                i18n::fill("prompt.owner", &[("command", &command)])
+            }),
+            _ if self.mark_prompt.is_some() => self.mark_prompt.as_ref().map(|(mark, text)| {
+                let key = if *mark {
+                    "prompt.mark"
+                } else {
+                    "prompt.unmark"
+                };
+                let count = self.pattern_matches(&pattern::parse(text)).len();
+                i18n::fill(
+                    key,
+                    &[
+                        ("pattern", &format!("{}▏", text)),
+                        ("count", &count),
+                        ("total", &list.items.len()),
+                    ],
+                )
+            }),
+            0 => None,
+            1 => Some(i18n::text("list.rejected_one").to_string()),
+            n => Some(i18n::fill("list.rejected", &[("count", &n)])),
+        };
+        if let Some(note) = note {
+            block = block.title(block::Title::from(note).position(block::Position::Bottom));
+        }
+        if let Some(watch) = &self.watch {
+            let last = match watch.last_refresh {
+                Some(secs) => i18n::fill("list.refreshed", &[("time", &format_time(secs))]),
+                None => i18n::text("list.not_refreshed").to_string(),
+            };
+            let note = i18n::fill(
+                "list.watch",
+                &[("seconds", &watch.interval.as_secs()), ("last", &last)],
+            );
+            block = block.title(
+                block::Title::from(format!(" {} ", note))
+                    .position(block::Position::Bottom)
+                    .alignment(Alignment::Right),
+            );
+        }
+
+        self.sort_hits.clear();
+        let list = List::new(items)
+            .highlight_style(self.highlight.0)
+            .highlight_symbol(&self.highlight.1);
+
+        if self.package_list.rows.is_empty() {
+            let list = &self.package_list;
+            let text = match (&list.load_error, &list.filter) {
+                _ if self.loader.is_loading() => i18n::fill(
+                    "list.loading_manager",
+                    &[("manager", &list.package_manager.name())],
+                ),
+                (Some(err), _) => err.to_string(),
+                (None, Some(filter)) => {
+                    i18n::fill("list.none_filtered", &[("filter", &filter.label())])
+                }
+                (None, None) if !list.items.is_empty() => {
+                    i18n::text("list.none_matching").to_string()
+                }
+                (None, None) => i18n::text("list.none_installed").to_string(),
+            };
+            let empty = Paragraph::new(text).block(block).wrap(Wrap { trim: true });
+            f.render_widget(empty, list_area);
+        } else {
+            let inner = block.inner(list_area);
+            f.render_widget(block, list_area);
+            let [header_area, rows_area] = *Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(inner)
+            else {
+                return;
+            };
+            let header =
+                sorting::header(self.package_list.sort_by, header_area, &mut self.sort_hits);
+            f.render_widget(Paragraph::new(header), header_area);
+            f.render_stateful_widget(list, rows_area, &mut self.package_list.state);
+            let list = &self.package_list;
+            let screen = list
+                .rows
+                .iter()
+                .skip(list.state.offset())
+                .take(usize::from(rows_area.height))
+                .filter_map(|row| match row {
+                    Row::Package(index) => list.items.get(*index),
+                    _ => None,
+                })
+                .map(|pkg| (pkg.name.clone(), pkg.version.clone()))
+                .collect();
+            self.prefetch.viewport(list.package_manager, screen);
+        }
+
+        // Render selected package details with scrolling
+        let detail = self.detail_content();
+        let mut detail_title = i18n::text("details.title").to_string();
+        let mut text = match (&self.search, self.package_list.selected()) {
+            (Some(search), Some(pkg)) if !search.query.is_empty() => {
+                let count: usize = detail
+                    .lines()
+                    .map(|line| search.occurrences(line).len())
+                    .sum();
+                let current = search.detail_match(&pkg.name) % count.max(1);
+                detail_title.push_str(&match count {
+                    0 => i18n::text("details.no_matches").to_string(),
+                    _ => i18n::fill(
+                        "details.match",
+                        &[("current", &(current + 1)), ("count", &count)],
+                    ),
+                });
+                Text::from(search.highlight_lines(&detail, current).0)
+            }
+            _ => match self.field_lines() {
+                Some(lines) => Text::from(lines),
+                None => Text::from(detail.as_str()),
+            },
+        };
+        let fields = self.tabs.view == tabs::View::Fields;
+        if fields {
+            tabs::emphasize_synopsis(&mut text.lines);
+        }
+
+        if self.details_page {
+            detail_title.push_str(i18n::text("details.page"));
+        }
+        let block = Block::default().title(detail_title).borders(Borders::ALL);
+        let inner = block.inner(detail_area);
+        f.render_widget(block, detail_area);
+        let [strip_area, text_area] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner)
+        else {
+            return;
+        };
+        f.render_widget(Paragraph::new(self.tabs.strip(strip_area)), strip_area);
+
+        // Column-aligned views keep their leading spaces
+        let aligned = fields || self.tabs.view == tabs::View::Timeline;
+        let paragraph = Paragraph::new(text).wrap(Wrap { trim: !aligned });
+        let lines = paragraph.line_count(text_area.width);
+        self.details_rows = (u16::try_from(lines).unwrap_or(u16::MAX), text_area.height);
+        // Keeps a jump to a search match, or a shorter text, from leaving the pane blank
+        self.details_scroll = self.details_scroll.min(self.max_details_scroll());
+        f.render_widget(paragraph.scroll((self.details_scroll, 0)), text_area);
+
+        if self.max_details_scroll() > 0 {
+            let mut state = ScrollbarState::new(usize::from(self.max_details_scroll()) + 1)
+                .position(usize::from(self.details_scroll))
+                .viewport_content_length(usize::from(text_area.height));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                // On the right border, beside the text and not the view strip
+                Rect {
+                    x: detail_area.right().saturating_sub(1),
+                    width: 1,
+                    ..text_area
+                },
+                &mut state,
+            );
+        }
+        if let Some(tree) = &mut self.dep_tree {
+            tree.render(f, overlay_area);
+        }
+        if let Some(lookup) = &mut self.rdepends {
+            lookup.render(f, overlay_area);
+        }
+
+        if let Some(menu) = &mut self.action_menu {
+            menu.render(f);
+        }
+        if let Some(menu) = &mut self.group_menu {
+            menu.render(f);
+        }
+        if let Some(menu) = &mut self.priority_menu {
+            menu.render(f);
+        }
+        if let Some((menu, _)) = &mut self.section_menu {
+            menu.render(f);
+        }
+        if let Some(menu) = &mut self.drift_menu {
+            menu.render(f);
+        }
+        if let Some(menu) = &mut self.switch_menu {
+            menu.render(f);
+        }
+        if let Some(menu) = &mut self.quit_menu {
+            menu.render(f);
+        }
+        if let Some(view) = &mut self.holds {
+            view.render(f, centered_rect(80, 70, f.size()), self.message.as_deref());
+        }
+        if let Some(view) = &mut self.repos {
+            view.render(f, centered_rect(90, 70, f.size()));
+        }
+        if let Some(view) = &mut self.sessions {
+            view.render(f, centered_rect(80, 60, f.size()), self.message.as_deref());
+        }
+        if let Some(view) = &mut self.growth {
+            view.render(f, centered_rect(90, 80, f.size()));
+        }
+        if let Some(view) = &mut self.stats {
+            view.render(f, centered_rect(95, 90, f.size()));
+        }
+        if let Some(view) = &mut self.upgrades {
+            view.render(f, centered_rect(80, 70, f.size()));
+        }
+        if let Some(view) = &self.impact {
+            view.render(f, centered_rect(80, 70, f.size()));
+        }
+        if let Some(view) = &self.export {
+            view.render(f, centered_rect(70, 40, f.size()));
+        }
+        if let Some(view) = &self.install {
+            view.render(f, centered_rect(80, 60, f.size()));
+        }
+
+        if self.show_diagnostics {
+            let area = centered_rect(80, 60, f.size());
+            let popup = Paragraph::new(self.diagnostics_text())
+                .block(
+                    Block::default()
+                        .title(if self.package_list.rejected_count > 0 {
+                            i18n::text("popup.diagnostics_report")
+                        } else {
+                            i18n::text("popup.diagnostics")
+                        })
+                        .borders(Borders::ALL),
+                )
+                .wrap(Wrap { trim: false });
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
+        if let (true, Some(python)) = (self.show_python, &self.python) {
+            let area = centered_rect(80, 70, f.size());
+            let popup = Paragraph::new(python.text(&self.no_upgrade, self.include_listed))
+                .block(
+                    Block::default()
+                        .title(i18n::text("popup.python"))
+                        .borders(Borders::ALL),
+                )
+                .wrap(Wrap { trim: false });
+            f.render_widget(Clear, area);
+            f.render_widget(popup, area);
+        }
+        if let Some(view) = &mut self.inspect {
+            view.render(f, centered_rect(90, 90, f.size()), self.read_only);
+        }
+        if let Some(view) = &mut self.usage {
+            view.render(f, centered_rect(90, 90, f.size()));
+        }
+        if let Some(view) = &self.no_rollback {
+            view.render(f, centered_rect(80, 60, f.size()));
+        }
+        if let Some(confirm) = &self.confirm {
+            confirm.render(f);
+        }
+        if let Some(help) = &mut self.help {
+            help.render(f, centered_rect(80, 80, f.size()));
+        }
+        if let Some(tour) = &self.tour {
+            let target = match tour.stop() {
+                tour::Stop::List => list_area,
+                tour::Stop::Details => overlay_area,
+                tour::Stop::Tabs => strip_area,
+                tour::Stop::Status => status_area,
+            };
+            tour.render(f, target);
+        }
+    }
+}
+
+// termux-clipboard-get on Android; wl-paste and xclip on desktops.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
 }
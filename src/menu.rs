@@ -0,0 +1,194 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::i18n;
+
+pub struct MenuEntry<T> {
+    pub label: String,
+    pub hint: String,
+    pub value: T,
+}
+
+// Small popup list of choices, navigated with j/k and picked with Enter.
+pub struct Menu<T> {
+    pub title: String,
+    pub entries: Vec<MenuEntry<T>>,
+    pub state: ListState,
+}
+
+// Actions for the selected package. An entry is executed by replaying its
+// direct key, so the menu can never drift from the keybindings it advertises.
+pub type ActionMenu = Menu<KeyCode>;
+
+impl ActionMenu {
+    pub fn actions(title: String, actions: Vec<(String, KeyCode)>) -> Self {
+        let entries = actions
+            .into_iter()
+            .map(|(label, code)| MenuEntry {
+                label,
+                hint: key_label(code),
+                value: code,
+            })
+            .collect();
+        Self::new(title, entries)
+    }
+}
+
+impl<T: Copy + PartialEq> Menu<T> {
+    pub fn new(title: String, entries: Vec<MenuEntry<T>>) -> Self {
+        let mut state = ListState::default();
+        if !entries.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            title,
+            entries,
+            state,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.entries.len()));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            let len = self.entries.len();
+            self.state.select(Some((i + len - 1) % len));
+        }
+    }
+
+    pub fn select_value(&mut self, value: T) {
+        if let Some(i) = self.entries.iter().position(|entry| entry.value == value) {
+            self.state.select(Some(i));
+        }
+    }
+
+    pub fn selected_value(&self) -> Option<T> {
+        self.state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .map(|entry| entry.value)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.entries.iter().any(|entry| entry.value == value)
+    }
+
+    pub fn render(&mut self, f: &mut Frame) {
+        let label_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let hint_width = self
+            .entries
+            .iter()
+            .map(|entry| entry.hint.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<width$}  ", entry.label, width = label_width)),
+                    Span::styled(entry.hint.as_str(), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        let width = (label_width + hint_width + 6).max(self.title.chars().count() + 4) as u16;
+        let height = self.entries.len() as u16 + 2;
+        let area = centered(width, height, f.size());
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(self.title.as_str())
+                    .borders(Borders::ALL),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+// Yes/no question listing everything an action is about to change; `value`
+// says what to do when confirmed.
+pub struct Confirm<T> {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub value: T,
+}
+
+impl<T> Confirm<T> {
+    pub fn render(&self, f: &mut Frame) {
+        let footer = i18n::text("confirm.footer");
+        // In a short inline viewport the list is cut, never the footer
+        let room = (f.size().height as usize).saturating_sub(4).max(1);
+        let mut text: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        if text.len() > room {
+            let hidden = text.len() - room + 1;
+            text.truncate(room - 1);
+            text.push(Line::raw(i18n::fill("confirm.more", &[("count", &hidden)])));
+        }
+        text.push(Line::raw(""));
+        text.push(Line::styled(footer, Style::default().fg(Color::DarkGray)));
+
+        let width = self
+            .lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([self.title.chars().count(), footer.chars().count()])
+            .max()
+            .unwrap_or(0)
+            + 4;
+        let height = text.len() as u16 + 2;
+        let area = centered(width as u16, height, f.size());
+
+        let popup = Paragraph::new(text).block(
+            Block::default()
+                .title(self.title.as_str())
+                .borders(Borders::ALL),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+}
+
+pub fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn centered(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,310 @@
+use crate::PackageManager;
+
+// How sure `correlate` is that two packages are builds of the same software.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,     // Same stem once library or -dev trimmings are dropped
+    Medium,  // Same name in two ecosystems, e.g. ripgrep from apt and cargo
+    High,    // A language prefix naming the other one: python-numpy and pip's numpy
+    Certain, // The same package as the manager compares names
+}
+
+impl Confidence {
+    pub fn label(self) -> &'static str {
+        match self {
+            Confidence::Low => "possibly",
+            Confidence::Medium => "probably",
+            Confidence::High => "very likely",
+            Confidence::Certain => "certainly",
+        }
+    }
+}
+
+// Prefixes dpkg packages carry for another ecosystem's software, e.g.
+// Termux's python-numpy or Debian's python3-numpy and node-semver.
+const ECOSYSTEM_PREFIXES: [(&str, PackageManager); 7] = [
+    ("python3-", PackageManager::Pip),
+    ("python-", PackageManager::Pip),
+    ("node-", PackageManager::Npm),
+    ("lua-", PackageManager::Luarocks),
+    ("ocaml-", PackageManager::Opam),
+    ("php-", PackageManager::Composer),
+    ("rust-", PackageManager::Cargo),
+];
+
+// A name as the manager itself compares them, so two spellings of one
+// package come out equal: PEP 503 for pip, `-` and `_` alike for crates,
+// dpkg's `:arch` dropped, and case ignored everywhere.
+pub fn canonical(manager: PackageManager, name: &str) -> String {
+    match manager {
+        PackageManager::Pip => pip(name),
+        PackageManager::Cargo => name.to_ascii_lowercase().replace('_', "-"),
+        PackageManager::Pkg | PackageManager::Apt => {
+            name.split(':').next().unwrap_or(name).to_ascii_lowercase()
+        }
+        PackageManager::Luarocks
+        | PackageManager::Composer
+        | PackageManager::Opam
+        | PackageManager::Npm => name.to_ascii_lowercase(),
+    }
+}
+
+// PEP 503 normalization, which is what the requires script prints.
+pub fn pip(name: &str) -> String {
+    let mut canonical = String::new();
+    for c in name.chars() {
+        if "-_.".contains(c) {
+            if !canonical.ends_with('-') {
+                canonical.push('-');
+            }
+        } else {
+            canonical.push(c.to_ascii_lowercase());
+        }
+    }
+    canonical
+}
+
+// The software a package is a build of, with the ecosystem's trimmings
+// dropped and separators unified, and the ecosystem a dpkg prefix points
+// to. `trimmed` says whether a library or -dev guess was involved.
+struct Stem {
+    name: String,
+    points_to: Option<PackageManager>,
+    trimmed: bool,
+}
+
+fn stem(manager: PackageManager, name: &str) -> Stem {
+    let name = canonical(manager, name);
+    let mut points_to = None;
+    let mut trimmed = false;
+    let mut rest = name.as_str();
+    match manager {
+        PackageManager::Pkg | PackageManager::Apt => {
+            if let Some((bare, ecosystem)) = ECOSYSTEM_PREFIXES
+                .iter()
+                .find_map(|(prefix, ecosystem)| Some((rest.strip_prefix(prefix)?, *ecosystem)))
+            {
+                rest = bare;
+                points_to = Some(ecosystem);
+            }
+            for suffix in ["-dev", "-static", "-doc"] {
+                if let Some(bare) = rest.strip_suffix(suffix) {
+                    rest = bare;
+                    trimmed = true;
+                }
+            }
+            // libssl3, libpng16-16, libxml2: the soname goes, then `lib`
+            if let Some(library) = rest.strip_prefix("lib").filter(|bare| bare.len() > 1) {
+                let bare =
+                    library.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-');
+                if !bare.is_empty() {
+                    rest = bare;
+                    trimmed = true;
+                }
+            }
+        }
+        // vendor/name and @scope/name: the vendor or scope isn't the software
+        PackageManager::Composer | PackageManager::Npm => {
+            rest = rest.rsplit('/').next().unwrap_or(rest);
+        }
+        PackageManager::Pip
+        | PackageManager::Luarocks
+        | PackageManager::Opam
+        | PackageManager::Cargo => {}
+    }
+    Stem {
+        name: pip(rest),
+        points_to,
+        trimmed,
+    }
+}
+
+// Whether two packages, of the same manager or not, are likely the same
+// software, and how sure that is. None when nothing ties them.
+pub fn correlate(a: (PackageManager, &str), b: (PackageManager, &str)) -> Option<Confidence> {
+    // pkg and apt are one database
+    let dpkg = |manager| matches!(manager, PackageManager::Pkg | PackageManager::Apt);
+    if a.0 == b.0 || (dpkg(a.0) && dpkg(b.0)) {
+        return (canonical(a.0, a.1) == canonical(b.0, b.1)).then_some(Confidence::Certain);
+    }
+    let (first, second) = (stem(a.0, a.1), stem(b.0, b.1));
+    if first.name.is_empty() || first.name != second.name {
+        return None;
+    }
+    let confidence = if first.trimmed || second.trimmed {
+        Confidence::Low
+    } else if first.points_to == Some(b.0) || second.points_to == Some(a.0) {
+        Confidence::High
+    } else if first.points_to.is_none() && second.points_to.is_none() {
+        Confidence::Medium
+    } else {
+        // A prefix for a third ecosystem: python-foo against npm's foo
+        Confidence::Low
+    };
+    Some(confidence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PackageManager::*;
+
+    #[test]
+    fn pip_names_as_pep_503() {
+        assert_eq!(pip("Django"), "django");
+        assert_eq!(pip("zope.interface"), "zope-interface");
+        assert_eq!(pip("typing_extensions"), "typing-extensions");
+        assert_eq!(pip("A-_.b"), "a-b");
+        assert_eq!(pip(""), "");
+    }
+
+    #[test]
+    fn canonical_per_manager() {
+        assert_eq!(canonical(Pip, "Typing_Extensions"), "typing-extensions");
+        assert_eq!(canonical(Cargo, "Serde_JSON"), "serde-json");
+        assert_eq!(canonical(Apt, "libc6:arm64"), "libc6");
+        assert_eq!(canonical(Pkg, "Python"), "python");
+        // Everywhere else only the case
+        assert_eq!(canonical(Npm, "@Types/Node_x"), "@types/node_x");
+        assert_eq!(canonical(Composer, "Vendor/Pkg.Name"), "vendor/pkg.name");
+        assert_eq!(canonical(Luarocks, "LuaSocket"), "luasocket");
+        assert_eq!(canonical(Opam, "Ocaml_Base"), "ocaml_base");
+        // Dots and underscores stay apart outside pip
+        assert_ne!(canonical(Cargo, "a.b"), canonical(Cargo, "a-b"));
+    }
+
+    #[test]
+    fn same_manager_is_certain_or_nothing() {
+        assert_eq!(
+            correlate((Pip, "PyYAML"), (Pip, "pyyaml")),
+            Some(Confidence::Certain)
+        );
+        assert_eq!(
+            correlate((Cargo, "cargo_edit"), (Cargo, "cargo-edit")),
+            Some(Confidence::Certain)
+        );
+        assert_eq!(
+            correlate((Apt, "bash:aarch64"), (Pkg, "bash")),
+            Some(Confidence::Certain)
+        );
+        // No guessing within one manager
+        assert_eq!(correlate((Apt, "libssl3"), (Apt, "openssl")), None);
+        assert_eq!(correlate((Apt, "python-numpy"), (Pkg, "numpy")), None);
+        assert_eq!(correlate((Npm, "@types/node"), (Npm, "node")), None);
+    }
+
+    #[test]
+    fn prefix_naming_the_other_ecosystem_is_high() {
+        for (dpkg, other, name) in [
+            ("python-numpy", Pip, "numpy"),
+            ("python3-yaml", Pip, "YAML"),
+            ("node-semver", Npm, "semver"),
+            ("lua-lpeg", Luarocks, "lpeg"),
+            ("ocaml-dune", Opam, "dune"),
+            ("php-monolog", Composer, "monolog/monolog"),
+            ("rust-ripgrep", Cargo, "ripgrep"),
+        ] {
+            assert_eq!(
+                correlate((Apt, dpkg), (other, name)),
+                Some(Confidence::High),
+                "{} and {}",
+                dpkg,
+                name
+            );
+            // Either way round
+            assert_eq!(
+                correlate((other, name), (Pkg, dpkg)),
+                Some(Confidence::High),
+                "{} and {}",
+                name,
+                dpkg
+            );
+        }
+    }
+
+    #[test]
+    fn same_name_in_two_ecosystems_is_medium() {
+        assert_eq!(
+            correlate((Apt, "ripgrep"), (Cargo, "ripgrep")),
+            Some(Confidence::Medium)
+        );
+        assert_eq!(
+            correlate((Pip, "black"), (Npm, "black")),
+            Some(Confidence::Medium)
+        );
+        // Scopes, vendors and separators don't count
+        assert_eq!(
+            correlate((Npm, "@biomejs/biome"), (Cargo, "biome")),
+            Some(Confidence::Medium)
+        );
+        assert_eq!(
+            correlate((Pip, "tree_sitter"), (Cargo, "tree-sitter")),
+            Some(Confidence::Medium)
+        );
+    }
+
+    #[test]
+    fn trimmed_or_misdirected_names_are_low() {
+        // Library sonames and -dev and friends
+        assert_eq!(
+            correlate((Apt, "libyaml-0-2"), (Pip, "yaml")),
+            Some(Confidence::Low)
+        );
+        assert_eq!(
+            correlate((Apt, "libxml2"), (Pip, "xml")),
+            Some(Confidence::Low)
+        );
+        assert_eq!(
+            correlate((Apt, "libpng16-16"), (Cargo, "png")),
+            Some(Confidence::Low)
+        );
+        assert_eq!(
+            correlate((Pkg, "libsodium-static"), (Npm, "sodium")),
+            Some(Confidence::Low)
+        );
+        assert_eq!(
+            correlate((Apt, "python-lxml-doc"), (Pip, "lxml")),
+            Some(Confidence::Low)
+        );
+        // A prefix pointing at a third ecosystem
+        assert_eq!(
+            correlate((Apt, "python-semver"), (Npm, "semver")),
+            Some(Confidence::Low)
+        );
+    }
+
+    #[test]
+    fn nothing_in_common_is_none() {
+        assert_eq!(correlate((Apt, "curl"), (Pip, "requests")), None);
+        assert_eq!(correlate((Pip, ""), (Npm, "")), None);
+        // `lib` alone, or with only a soname after it, is kept whole
+        assert_eq!(
+            correlate((Apt, "lib"), (Pip, "lib")),
+            Some(Confidence::Medium)
+        );
+        assert_eq!(
+            correlate((Apt, "lib3.0"), (Pip, "lib3.0")),
+            Some(Confidence::Medium)
+        );
+        assert_eq!(correlate((Apt, "lib3.0"), (Pip, "")), None);
+        // Only whole stems match
+        assert_eq!(correlate((Apt, "python-numpy"), (Pip, "numpy-stubs")), None);
+    }
+
+    #[test]
+    fn confidence_orders_and_reads() {
+        assert!(Confidence::Low < Confidence::Medium);
+        assert!(Confidence::Medium < Confidence::High);
+        assert!(Confidence::High < Confidence::Certain);
+        let labels: Vec<&str> = [
+            Confidence::Low,
+            Confidence::Medium,
+            Confidence::High,
+            Confidence::Certain,
+        ]
+        .iter()
+        .map(|confidence| confidence.label())
+        .collect();
+        assert_eq!(labels, ["possibly", "probably", "very likely", "certainly"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::names;
+use crate::{config, format_date, lock};
+
+// Python packages Termux builds and patches itself; `pip install -U` replaces
+// them with a generic build that usually fails to compile or import.
+const DEFAULTS: [(&str, &str); 8] = [
+    ("numpy", "Termux package python-numpy, upgrade with pkg"),
+    ("scipy", "Termux package python-scipy, upgrade with pkg"),
+    ("pillow", "Termux package python-pillow, upgrade with pkg"),
+    (
+        "cryptography",
+        "Termux package python-cryptography, upgrade with pkg",
+    ),
+    ("lxml", "Termux package python-lxml, upgrade with pkg"),
+    ("grpcio", "Termux package python-grpcio, upgrade with pkg"),
+    ("pyarrow", "Termux package python-pyarrow, upgrade with pkg"),
+    ("torch", "Termux package python-torch, upgrade with pkg"),
+];
+
+// The "do not upgrade via pip" list: one `name<TAB>reason` line per package,
+// `#` starting a comment. Until the file exists the defaults above apply;
+// the first change writes them out, so removing a default sticks.
+pub struct NoUpgrade {
+    entries: BTreeMap<String, String>, // Canonical name → reason
+}
+
+// $TPI_PIP_NO_UPGRADE, or $XDG_CONFIG_HOME/tpi/pip-no-upgrade.tsv
+// (~/.config by default).
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TPI_PIP_NO_UPGRADE").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".config")))?;
+    Some(config.join("tpi").join("pip-no-upgrade.tsv"))
+}
+
+impl NoUpgrade {
+    pub fn load() -> Self {
+        let Some(text) = path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return Self {
+                entries: DEFAULTS
+                    .iter()
+                    .map(|(name, reason)| (name.to_string(), reason.to_string()))
+                    .collect(),
+            };
+        };
+        let entries = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (name, reason) = line.split_once('\t').unwrap_or((line, ""));
+                (names::pip(name.trim()), reason.trim().to_string())
+            })
+            .collect();
+        Self { entries }
+    }
+
+    // Why a package is on the list, or None when it isn't.
+    pub fn reason(&self, name: &str) -> Option<&str> {
+        self.entries.get(&names::pip(name)).map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.reason(name).is_some()
+    }
+
+    // Adds the package, or removes it when it is listed, and saves the list.
+    // Returns whether it is listed now.
+    pub fn toggle(&mut self, name: &str) -> io::Result<bool> {
+        let key = names::pip(name);
+        let listed = if self.entries.remove(&key).is_some() {
+            false
+        } else {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let reason = format!("added in tpi on {}", format_date(now));
+            self.entries.insert(key, reason);
+            true
+        };
+        self.save()?;
+        Ok(listed)
+    }
+
+    // Lists the package with the reason, or takes it off for None, and
+    // saves the list.
+    pub fn set(&mut self, name: &str, reason: Option<String>) -> io::Result<()> {
+        let key = names::pip(name);
+        match reason {
+            Some(reason) => self.entries.insert(key, reason),
+            None => self.entries.remove(&key),
+        };
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no location: set TPI_PIP_NO_UPGRADE, XDG_CONFIG_HOME or HOME",
+            ));
+        };
+        let mut text = String::from("# Packages tpi leaves out of pip installs: name<TAB>reason\n");
+        for (name, reason) in &self.entries {
+            text.push_str(&format!("{}\t{}\n", name, reason));
+        }
+        lock::write_atomic(&path, &text)
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use crate::commands::{self, Action, Runner};
+use crate::{rdepends, PackageManager};
+
+enum Progress {
+    Checked(usize), // Packages done so far
+    Orphan(String), // Found one
+    Failed(String), // rdepends couldn't be run; the scan stops
+}
+
+// Likely orphans among apt's automatically installed packages: those that no
+// installed package depends on any more, per `apt-cache rdepends --installed`.
+// That is one command per package, slow enough with hundreds of them that
+// the scan runs on a background thread and the list fills in as it goes.
+// The result belongs to one listing; a reload starts over.
+pub struct Scan {
+    pub total: usize,
+    pub checked: usize,
+    pub found: HashSet<String>,
+    pub error: Option<String>,
+    receiver: Receiver<Progress>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Scan {
+    pub fn start(runner: Arc<dyn Runner>, manager: PackageManager, names: Vec<String>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = cancel.clone();
+        let total = names.len();
+        thread::spawn(move || {
+            for (done, name) in names.iter().enumerate() {
+                if flag.load(Ordering::Relaxed) {
+                    return;
+                }
+                let progress =
+                    match commands::run_with(runner.as_ref(), manager, Action::Rdepends, name) {
+                        Ok(output) if output.status.success() => {
+                            rdepends::parse(&String::from_utf8_lossy(&output.stdout))
+                                .is_empty()
+                                .then(|| Progress::Orphan(name.clone()))
+                        }
+                        Ok(output) => Some(Progress::Failed(
+                            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                        )),
+                        Err(err) => Some(Progress::Failed(err.to_string())),
+                    };
+                let failed = matches!(progress, Some(Progress::Failed(_)));
+                if let Some(progress) = progress {
+                    let _ = sender.send(progress);
+                }
+                if failed {
+                    return;
+                }
+                let _ = sender.send(Progress::Checked(done + 1));
+            }
+        });
+        Self {
+            total,
+            checked: 0,
+            found: HashSet::new(),
+            error: None,
+            receiver,
+            cancel,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.error.is_none() && self.checked < self.total
+    }
+
+    // Takes in what the thread found. Returns whether anything changed.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(progress) = self.receiver.try_recv() {
+            changed = true;
+            match progress {
+                Progress::Checked(count) => self.checked = count,
+                Progress::Orphan(name) => {
+                    self.found.insert(name);
+                }
+                Progress::Failed(err) => self.error = Some(err),
+            }
+        }
+        changed
+    }
+
+    // For the list title.
+    pub fn label(&self) -> String {
+        match &self.error {
+            Some(err) => format!("orphan scan failed: {}", err),
+            None if self.is_running() => format!("scanning {}/{}…", self.checked, self.total),
+            None => format!("{} likely orphans", self.found.len()),
+        }
+    }
+}
+
+impl Drop for Scan {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::commands::{self, Action, Runner};
+use crate::PackageManager;
+
+// An installed package with a newer version available.
+#[derive(Clone)]
+pub struct Outdated {
+    pub name: String,
+    pub installed: String,
+    pub candidate: String,
+}
+
+// What the manager says can be upgraded, from its indexes as they are (apt
+// doesn't update them for this). None for managers that can't tell.
+pub fn load(runner: &dyn Runner, manager: PackageManager) -> Option<Result<Vec<Outdated>, String>> {
+    if !commands::supports(manager, Action::Upgradable) {
+        return None;
+    }
+    let output = match commands::run_with(runner, manager, Action::Upgradable, "") {
+        Ok(output) => output,
+        Err(err) => return Some(Err(err.to_string())),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().map(str::trim).find(|line| !line.is_empty());
+        return Some(Err(reason.unwrap_or("command failed").to_string()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(Ok(stdout
+        .lines()
+        .filter_map(|line| parse_line(manager, line))
+        .collect()))
+}
+
+fn parse_line(manager: PackageManager, line: &str) -> Option<Outdated> {
+    match manager {
+        // apt list --upgradable: name/suite candidate arch [upgradable from: installed]
+        PackageManager::Pkg | PackageManager::Apt => {
+            let (name, rest) = line.split_once('/')?;
+            let candidate = rest.split_whitespace().nth(1)?;
+            let installed = rest.split_once("[upgradable from: ")?.1.strip_suffix(']')?;
+            Some(Outdated {
+                name: name.to_string(),
+                installed: installed.trim().to_string(),
+                candidate: candidate.to_string(),
+            })
+        }
+        // pip list --outdated: "Package Version Latest Type" columns
+        PackageManager::Pip => {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 3 || columns[0] == "Package" || line.starts_with("---") {
+                return None;
+            }
+            Some(Outdated {
+                name: columns[0].to_string(),
+                installed: columns[1].to_string(),
+                candidate: columns[2].to_string(),
+            })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{Package, PackageManager};
+
+// Lines of a listing that looked like packages but didn't parse, kept so a
+// "package X is missing" report can be investigated. Bounded per load.
+pub const MAX_REJECTIONS: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub line: String,
+    pub rule: &'static str, // Which expectation the line failed
+}
+
+#[derive(Debug, Default)]
+pub struct Listing {
+    pub packages: Vec<Package>,
+    pub rejected: Vec<Rejection>, // The first MAX_REJECTIONS
+    pub rejected_count: usize,
+}
+
+impl Listing {
+    pub fn reject(&mut self, line: &str, rule: &'static str) {
+        self.rejected_count += 1;
+        if self.rejected.len() < MAX_REJECTIONS {
+            self.rejected.push(Rejection {
+                line: line.to_string(),
+                rule,
+            });
+        }
+    }
+}
+
+pub fn parse_package_list(
+    package_manager: PackageManager,
+    stdout: &str,
+) -> Result<Listing, String> {
+    match package_manager {
+        PackageManager::Composer => return parse_composer_list(stdout),
+        PackageManager::Npm => return parse_npm_list(stdout),
+        _ => {}
+    }
+    Ok(lines(package_manager, stdout))
+}
+
+// Listings with a package per line.
+fn lines(package_manager: PackageManager, stdout: &str) -> Listing {
+    let mut listing = Listing::default();
+    for line in stdout.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(package_manager, line) {
+            Ok(Some(pkg)) => listing.packages.push(pkg),
+            Ok(None) => {}
+            Err(rule) => listing.reject(line, rule),
+        }
+    }
+    listing
+}
+
+// `pkg list-installed`, which is `apt list --installed` underneath.
+pub fn parse_pkg_list(stdout: &str) -> Vec<Package> {
+    lines(PackageManager::Pkg, stdout).packages
+}
+
+// `apt list --installed`, without the "Listing..." header or apt's warning
+// about its CLI when that ends up on stdout.
+pub fn parse_apt_list(stdout: &str) -> Vec<Package> {
+    lines(PackageManager::Apt, stdout).packages
+}
+
+// `pip list`, without its column headers and rule.
+pub fn parse_pip_list(stdout: &str) -> Vec<Package> {
+    lines(PackageManager::Pip, stdout).packages
+}
+
+// A package, Ok(None) for known non-package lines (headers), or the rule the
+// line broke.
+pub fn parse_line(
+    package_manager: PackageManager,
+    line: &str,
+) -> Result<Option<Package>, &'static str> {
+    match package_manager {
+        // `pkg list-installed` is `apt list --installed` underneath:
+        // name/suite,now version arch [installed,automatic]
+        PackageManager::Pkg | PackageManager::Apt => {
+            if line.starts_with("Listing...") || line.starts_with("WARNING:") {
+                return Ok(None);
+            }
+            // The header in other locales, e.g. "Auflistung… Fertig"
+            let first_word = line.split_whitespace().next().unwrap_or_default();
+            if !line.contains('/') && (first_word.ends_with("...") || first_word.ends_with('…')) {
+                return Ok(None);
+            }
+            let (name, rest) = line
+                .split_once('/')
+                .ok_or("apt: no '/' between name and suite")?;
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err("apt: expected a package name before '/'");
+            }
+            if fields.len() < 2 {
+                return Err("apt: expected suite and version after the name");
+            }
+            Ok(Some(Package {
+                name: name.to_string(),
+                version: fields[1].to_string(),
+                arch: fields
+                    .get(2)
+                    .filter(|arch| !arch.starts_with('['))
+                    .map(|arch| arch.to_string()),
+                repo: fields[0]
+                    .split(',')
+                    .find(|suite| *suite != "now")
+                    .map(String::from),
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: fields[2..].iter().any(|tag| {
+                    tag.trim_matches(['[', ']'])
+                        .split(',')
+                        .any(|tag| tag == "automatic")
+                }),
+            }))
+        }
+        // Column table: "Package  Version" header, a dashed rule, then rows
+        PackageManager::Pip => {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.first() == Some(&"Package") && parts.get(1) == Some(&"Version") {
+                return Ok(None);
+            }
+            if line.starts_with("---") {
+                return Ok(None);
+            }
+            if parts.len() < 2 {
+                return Err("pip: expected name and version columns");
+            }
+            Ok(Some(Package {
+                name: parts[0].to_string(),
+                version: parts[1].to_string(),
+                arch: None,
+                repo: None,
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: false,
+            }))
+        }
+        // luarocks list --porcelain: name, version, status and tree, tab separated.
+        // A rock installed in several versions appears once per version.
+        PackageManager::Luarocks => {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 2 || fields[0].is_empty() {
+                return Err("luarocks: expected tab-separated name and version");
+            }
+            Ok(Some(Package {
+                name: fields[0].to_string(),
+                version: fields[1].to_string(),
+                arch: None,
+                repo: fields.get(3).map(|tree| tree.to_string()), // Labelled by the app
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: false,
+            }))
+        }
+        PackageManager::Composer | PackageManager::Npm => Ok(None), // JSON, see parse_*_list
+        // opam list --columns=name,installed-version with a tab separator; the
+        // "# Packages matching" and column headers start with '#'. Versions are
+        // kept as opam prints them, ~dev and pinned ones included.
+        PackageManager::Opam => {
+            if line.starts_with('#') {
+                return Ok(None);
+            }
+            let (name, version) = line
+                .split_once('\t')
+                .ok_or("opam: expected tab-separated name and version")?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err("opam: expected a package name before the tab");
+            }
+            Ok(Some(Package {
+                name: name.to_string(),
+                version: version.trim().to_string(),
+                arch: None,
+                repo: None,
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: false,
+            }))
+        }
+        // cargo install --list: a "name v1.2.3:" header per crate, with the
+        // source in parentheses when it isn't crates.io, then its binaries
+        // indented below it.
+        PackageManager::Cargo => {
+            if line.starts_with(char::is_whitespace) {
+                return Ok(None);
+            }
+            let header = line
+                .strip_suffix(':')
+                .ok_or("cargo: expected a header ending in ':'")?;
+            let mut words = header.splitn(3, ' ');
+            let name = words.next().filter(|name| !name.is_empty());
+            let version = words.next().and_then(|version| version.strip_prefix('v'));
+            let (Some(name), Some(version)) = (name, version) else {
+                return Err("cargo: expected name and v-prefixed version");
+            };
+            Ok(Some(Package {
+                name: name.to_string(),
+                version: version.to_string(),
+                arch: None,
+                repo: words.next().map(cargo_source_label),
+                description: None,
+                priority: None,
+                section: None,
+                auto_installed: false,
+            }))
+        }
+    }
+}
+
+// Crates installed with --git or --path, as opposed to from a registry.
+fn cargo_source_label(source: &str) -> String {
+    let source = source.trim_start_matches('(').trim_end_matches(')');
+    if source.contains("://") {
+        "git".to_string()
+    } else {
+        "path".to_string()
+    }
+}
+
+// npm list -g --depth=0 --json:
+// {"dependencies": {"name": {"version": "1.2.3", ...}}}. A package that is
+// missing or invalid has no version but is still listed.
+pub fn parse_npm_list(stdout: &str) -> Result<Listing, String> {
+    let mut listing = Listing::default();
+    if stdout.trim().is_empty() {
+        return Ok(listing);
+    }
+    let json: serde_json::Value =
+        serde_json::from_str(stdout).map_err(|err| format!("Unreadable npm output: {}", err))?;
+    let Some(dependencies) = json.get("dependencies") else {
+        return Ok(listing); // Nothing installed globally
+    };
+    let dependencies = dependencies
+        .as_object()
+        .ok_or("Unreadable npm output: \"dependencies\" is not an object")?;
+    for (name, entry) in dependencies {
+        listing.packages.push(Package {
+            name: name.clone(),
+            version: entry
+                .get("version")
+                .and_then(|version| version.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            arch: None,
+            repo: name
+                .strip_prefix('@')
+                .and_then(|scoped| scoped.split_once('/'))
+                .map(|(scope, _)| format!("@{}", scope)),
+            description: None,
+            priority: None,
+            section: None,
+            auto_installed: false,
+        });
+    }
+    Ok(listing)
+}
+
+// composer global show --format=json:
+// {"installed": [{"name": "vendor/pkg", "version": "1.2.3", "description": "..."}]}
+pub fn parse_composer_list(stdout: &str) -> Result<Listing, String> {
+    let mut listing = Listing::default();
+    if stdout.trim().is_empty() {
+        return Ok(listing); // Nothing installed globally yet
+    }
+    let json: serde_json::Value = serde_json::from_str(stdout)
+        .map_err(|err| format!("Unreadable composer output: {}", err))?;
+    let installed = json
+        .get("installed")
+        .and_then(|installed| installed.as_array())
+        .ok_or("Unreadable composer output: no \"installed\" list")?;
+
+    for entry in installed {
+        let Some(name) = entry.get("name").and_then(|name| name.as_str()) else {
+            listing.reject(&entry.to_string(), "composer: entry without a name");
+            continue;
+        };
+        let text = |key: &str| {
+            entry
+                .get(key)
+                .and_then(|value| value.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+        };
+        listing.packages.push(Package {
+            name: name.to_string(),
+            version: text("version").unwrap_or_default(),
+            arch: None,
+            repo: name.split_once('/').map(|(vendor, _)| vendor.to_string()),
+            description: text("description"),
+            priority: None,
+            section: None,
+            auto_installed: false,
+        });
+    }
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // In the form apt 2.7 and pip 24 print them on Termux (aarch64)
+    const APT_LIST: &str = include_str!("../tests/fixtures/apt-list.txt");
+    const APT_LIST_DE: &str = include_str!("../tests/fixtures/apt-list-de.txt");
+    const PKG_LIST: &str = include_str!("../tests/fixtures/pkg-list.txt");
+    const PIP_LIST: &str = include_str!("../tests/fixtures/pip-list.txt");
+
+    fn names(packages: &[Package]) -> Vec<&str> {
+        packages.iter().map(|pkg| pkg.name.as_str()).collect()
+    }
+
+    #[test]
+    fn apt_list_skips_warning_and_header() {
+        let packages = parse_apt_list(APT_LIST);
+        assert_eq!(
+            names(&packages),
+            [
+                "apt",
+                "bash",
+                "ca-certificates",
+                "libandroid-support",
+                "xeyes"
+            ]
+        );
+        let bash = &packages[1];
+        assert_eq!(bash.version, "5.2.26-1");
+        assert_eq!(bash.arch.as_deref(), Some("aarch64"));
+        assert_eq!(bash.repo.as_deref(), Some("stable"));
+        assert_eq!(packages[2].version, "1:2024.07.02");
+        assert_eq!(packages[2].arch.as_deref(), Some("all"));
+        assert_eq!(packages[4].repo.as_deref(), Some("x11"));
+    }
+
+    #[test]
+    fn apt_list_reads_automatic() {
+        let packages = parse_apt_list(APT_LIST);
+        let automatic: Vec<&str> = packages
+            .iter()
+            .filter(|pkg| pkg.auto_installed)
+            .map(|pkg| pkg.name.as_str())
+            .collect();
+        assert_eq!(automatic, ["libandroid-support"]);
+    }
+
+    #[test]
+    fn apt_list_skips_translated_header() {
+        let listing = parse_package_list(PackageManager::Apt, APT_LIST_DE).unwrap();
+        assert_eq!(names(&listing.packages), ["bash", "libandroid-support"]);
+        assert_eq!(listing.rejected_count, 0);
+        let spanish = "Listando... Hecho\nbash/stable,now 5.2.26-1 aarch64 [instalado]\n";
+        let listing = parse_package_list(PackageManager::Apt, spanish).unwrap();
+        assert_eq!(names(&listing.packages), ["bash"]);
+        assert_eq!(listing.rejected_count, 0);
+    }
+
+    #[test]
+    fn pkg_list_is_apt_list() {
+        let packages = parse_pkg_list(PKG_LIST);
+        assert_eq!(names(&packages), ["bash", "zlib"]);
+        assert!(!packages[0].auto_installed);
+        assert!(packages[1].auto_installed);
+        assert_eq!(packages[1].version, "1.3.1");
+    }
+
+    #[test]
+    fn pip_list_skips_header_and_rule() {
+        let packages = parse_pip_list(PIP_LIST);
+        assert_eq!(names(&packages), ["certifi", "pip", "requests"]);
+        assert_eq!(packages[0].version, "2024.7.4");
+        assert_eq!(packages[0].arch, None);
+    }
+
+    #[test]
+    fn empty_output_has_no_packages() {
+        assert!(parse_apt_list("").is_empty());
+        assert!(parse_pkg_list("").is_empty());
+        assert!(parse_pip_list("").is_empty());
+        assert!(parse_apt_list("Listing... Done\n").is_empty());
+        assert!(parse_pip_list("\n\n").is_empty());
+    }
+
+    #[test]
+    fn unparsable_lines_are_rejected_not_dropped() {
+        let stdout = "Listing... Done\nbash/stable,now 5.2.26-1 aarch64 [installed]\nE: something broke\nzlib/\n";
+        let listing = parse_package_list(PackageManager::Apt, stdout).unwrap();
+        assert_eq!(names(&listing.packages), ["bash"]);
+        assert_eq!(listing.rejected_count, 2);
+        assert_eq!(listing.rejected[0].line, "E: something broke");
+        assert_eq!(
+            listing.rejected[0].rule,
+            "apt: no '/' between name and suite"
+        );
+        assert_eq!(
+            listing.rejected[1].rule,
+            "apt: expected suite and version after the name"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+// Name patterns for marking by pattern (`m`, `u`), case-insensitive. `*`
+// matches any run of characters, `?` one character and `[abc]`, `[a-z]` or
+// `[!abc]` one of a set. A pattern without any of those matches every name
+// containing it, like the search.
+pub struct Pattern {
+    pub text: String,
+    chars: Vec<char>, // Lowercased
+    glob: bool,
+}
+
+impl Pattern {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            chars: text.to_lowercase().chars().collect(),
+            glob: text.contains(['*', '?', '[']),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        if !self.glob {
+            let needle: String = self.chars.iter().collect();
+            return name.contains(&needle);
+        }
+        let name: Vec<char> = name.chars().collect();
+        glob(&self.chars, &name)
+    }
+}
+
+// Several patterns at once, separated by spaces or commas.
+pub fn parse(text: &str) -> Vec<Pattern> {
+    text.split([' ', ','])
+        .filter(|part| !part.is_empty())
+        .map(Pattern::new)
+        .collect()
+}
+
+// Matches left to right, going back to the last `*` to let it take one more
+// character when the rest doesn't match.
+fn glob(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None; // Pattern after the `*`, name it resumes at
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => class(&pattern[p..], name[n]),
+            Some(c) if *c == name[n] => Some(1),
+            _ => None,
+        };
+        match (step, star) {
+            (Some(len), _) => {
+                p += len;
+                n += 1;
+            }
+            (None, Some((after, resume))) => {
+                p = after;
+                n = resume + 1;
+                star = Some((after, resume + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// A `[...]` class at the start of `pattern`: its length if `c` is in it.
+// An unclosed `[` is a literal.
+fn class(pattern: &[char], c: char) -> Option<usize> {
+    let Some(close) = pattern.iter().skip(2).position(|ch| *ch == ']') else {
+        return (c == '[').then_some(1);
+    };
+    let close = close + 2;
+    let mut set = &pattern[1..close];
+    let negated = set.first() == Some(&'!');
+    if negated {
+        set = &set[1..];
+    }
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+    (found != negated).then_some(close + 1)
+}
@@ -0,0 +1,509 @@
+use std::fs;
+
+use crate::prefix;
+use crate::repos::Source;
+
+// One record of the apt preferences files.
+pub struct Pin {
+    pub file: String,
+    pub packages: Vec<String>, // Names, globs or /regexes/ of the Package field
+    pub pin: String,           // "version 8.0*", "release a=stable", "origin host"
+    pub priority: i32,
+}
+
+impl Pin {
+    // `Package: *` records apply to every package and lose against any
+    // record that names the package (apt_preferences(5)).
+    pub fn is_general(&self) -> bool {
+        self.packages.iter().all(|pattern| pattern == "*")
+    }
+
+    // Regular expressions are not evaluated and never match.
+    pub fn names(&self, name: &str) -> bool {
+        self.packages
+            .iter()
+            .any(|pattern| !pattern.starts_with('/') && glob(pattern, name))
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "Pin: {}, priority {} ({})",
+            self.pin, self.priority, self.file
+        )
+    }
+
+    // Whether the record covers a version apt-cache policy lists from this
+    // source line, e.g. "https://host/apt stable/main aarch64 Packages" or the
+    // dpkg status file. None when it depends on Release fields that aren't
+    // shown there (o=, l=, v=).
+    fn covers(&self, version: &str, source: &str) -> Option<bool> {
+        let (kind, value) = self.split();
+        let mut words = source.split_whitespace();
+        let uri = words.next().unwrap_or("");
+        let (suite, component) = words
+            .next()
+            .map(|dist| dist.split_once('/').unwrap_or((dist, "")))
+            .unwrap_or(("", ""));
+        match kind {
+            "version" => Some(glob(value, version)),
+            "origin" => Some(host(uri) == value.trim_matches('"')),
+            "release" => {
+                let mut all = true;
+                for (key, wanted) in conditions(value) {
+                    all &= match key {
+                        "a" | "n" => suite == wanted,
+                        "c" => component == wanted,
+                        _ => return None,
+                    };
+                }
+                Some(all)
+            }
+            _ => None,
+        }
+    }
+
+    // Whether a record is about this repository, for the repositories view.
+    // Version pins are about packages, not sources.
+    pub fn covers_source(&self, source: &Source) -> Option<bool> {
+        let (kind, value) = self.split();
+        match kind {
+            "origin" => Some(host(&source.uri) == value.trim_matches('"')),
+            "release" => {
+                let mut all = true;
+                for (key, wanted) in conditions(value) {
+                    all &= match key {
+                        "a" | "n" => source.names.contains(wanted),
+                        "c" => source.components.iter().any(|c| c == wanted),
+                        _ => return None,
+                    };
+                }
+                Some(all)
+            }
+            _ => None,
+        }
+    }
+
+    // "release a=stable" -> ("release", "a=stable")
+    fn split(&self) -> (&str, &str) {
+        let (kind, value) = self.pin.split_once(' ').unwrap_or((&self.pin, ""));
+        (kind, value.trim())
+    }
+}
+
+// Every record of $PREFIX/etc/apt/preferences and preferences.d/, in the
+// order apt reads them.
+pub fn load() -> Vec<Pin> {
+    load_from(&format!("{}/etc/apt", prefix()))
+}
+
+// Like apt, preferences.d only contributes files without an extension or
+// ending in .pref.
+fn load_from(etc: &str) -> Vec<Pin> {
+    let mut files = vec![format!("{}/preferences", etc)];
+    if let Ok(entries) = fs::read_dir(format!("{}/preferences.d", etc)) {
+        let mut extra: Vec<String> = entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                let stem = name.strip_suffix(".pref").unwrap_or(&name);
+                !stem.is_empty()
+                    && stem
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "-_".contains(c))
+            })
+            .map(|entry| entry.path().to_string_lossy().into_owned())
+            .collect();
+        extra.sort();
+        files.extend(extra);
+    }
+
+    let mut pins = Vec::new();
+    for file in files {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for stanza in text.split("\n\n") {
+            let field = |key: &str| {
+                stanza
+                    .lines()
+                    .filter(|line| !line.starts_with('#'))
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.trim()
+                            .eq_ignore_ascii_case(key)
+                            .then(|| value.trim().to_string())
+                    })
+            };
+            let (Some(packages), Some(pin), Some(priority)) = (
+                field("Package"),
+                field("Pin"),
+                field("Pin-Priority").and_then(|p| p.parse().ok()),
+            ) else {
+                continue;
+            };
+            pins.push(Pin {
+                file: file.clone(),
+                packages: packages.split_whitespace().map(String::from).collect(),
+                pin,
+                priority,
+            });
+        }
+    }
+    pins
+}
+
+// Records naming this package, then the general ones, each in file order:
+// the order apt tries them in.
+pub fn for_package<'a>(pins: &'a [Pin], name: &str) -> Vec<&'a Pin> {
+    let (general, specific): (Vec<&Pin>, Vec<&Pin>) = pins
+        .iter()
+        .filter(|pin| pin.names(name))
+        .partition(|pin| pin.is_general());
+    specific.into_iter().chain(general).collect()
+}
+
+// Whether a record other than `Package: *` names this package.
+pub fn is_pinned(pins: &[Pin], name: &str) -> bool {
+    pins.iter().any(|pin| !pin.is_general() && pin.names(name))
+}
+
+// The records that apply to a package and, per version of its `apt-cache
+// policy` table, which one decides its priority, next to the priority apt
+// itself reports. A mismatch means a rule tpi can't evaluate got there first.
+pub fn explain(pins: &[Pin], name: &str, policy: &str) -> Option<String> {
+    let candidates = for_package(pins, name);
+    if candidates.is_empty() {
+        return None;
+    }
+    let mut text = String::from("\nPreferences:\n");
+    for pin in &candidates {
+        text.push_str(&format!("  {}\n", pin.label()));
+    }
+    for (version, reported, sources) in parse_table(policy) {
+        let decided = candidates.iter().find_map(|pin| {
+            match sources
+                .iter()
+                .map(|source| pin.covers(&version, source))
+                .try_fold(false, |any, covered| Some(any || covered?))
+            {
+                Some(true) => Some(Some(pin)),
+                Some(false) => None,
+                None => Some(None), // Can't tell, so nothing after it can be trusted
+            }
+        });
+        let line = match decided {
+            Some(Some(pin)) if Some(pin.priority) == reported => {
+                format!("{}: {} by \"Pin: {}\"", version, pin.priority, pin.pin)
+            }
+            Some(Some(pin)) => format!(
+                "{}: {} by \"Pin: {}\", but apt reports {}",
+                version,
+                pin.priority,
+                pin.pin,
+                reported.map_or("?".to_string(), |p| p.to_string())
+            ),
+            Some(None) => format!(
+                "{}: depends on Release fields, apt reports {}",
+                version,
+                reported.map_or("?".to_string(), |p| p.to_string())
+            ),
+            None => format!("{}: no record applies", version),
+        };
+        text.push_str(&format!("  {}\n", line));
+    }
+    Some(text)
+}
+
+// The version table of `apt-cache policy`: each version with the priority
+// apt gives it and the sources listed under it.
+//
+//      *** 8.0 1001
+//             500 https://host/apt stable/main aarch64 Packages
+//             100 /data/data/com.termux/files/usr/var/lib/dpkg/status
+type Version = (String, Option<i32>, Vec<String>);
+
+fn parse_table(policy: &str) -> Vec<Version> {
+    let mut table: Vec<Version> = Vec::new();
+    let mut in_table = false;
+    for line in policy.lines() {
+        let trimmed = line.trim();
+        if trimmed == "Version table:" {
+            in_table = true;
+            continue;
+        }
+        if !in_table || trimmed.is_empty() {
+            continue;
+        }
+        let words: Vec<&str> = trimmed
+            .trim_start_matches("***")
+            .split_whitespace()
+            .collect();
+        // Sources are the URI or the status file after their priority; the
+        // indent can't tell, apt right-aligns 4-digit priorities into it
+        let source = words
+            .get(1)
+            .is_some_and(|word| word.starts_with('/') || word.contains("://"));
+        if source {
+            if let Some((_, _, sources)) = table.last_mut() {
+                sources.push(words[1..].join(" "));
+            }
+        } else if let Some(version) = words.first() {
+            let priority = words.get(1).and_then(|p| p.parse().ok());
+            table.push((version.to_string(), priority, Vec::new()));
+        }
+    }
+    table
+}
+
+// `a=stable, c=main` as (key, value) pairs; a bare word is an archive name.
+fn conditions(value: &str) -> Vec<(&str, &str)> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|condition| !condition.is_empty())
+        .map(|condition| match condition.split_once('=') {
+            Some((key, wanted)) => (key.trim(), wanted.trim().trim_matches('"')),
+            None => ("a", condition.trim_matches('"')),
+        })
+        .collect()
+}
+
+// "https://host/path" -> "host"; local sources have none.
+fn host(uri: &str) -> &str {
+    uri.split_once("://")
+        .map_or("", |(_, rest)| rest.split('/').next().unwrap_or(""))
+}
+
+// Shell-style `*` and `?`, as apt matches package names and Pin: version.
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // preferences and preferences.d as a Termux user with TUR might have
+    // them, plus files apt skips
+    fn fixture() -> Vec<Pin> {
+        load_from(&format!(
+            "{}/tests/fixtures/apt",
+            env!("CARGO_MANIFEST_DIR")
+        ))
+    }
+
+    fn pins_of<'a>(pins: &[&'a Pin]) -> Vec<(&'a str, i32)> {
+        pins.iter()
+            .map(|pin| (pin.pin.as_str(), pin.priority))
+            .collect()
+    }
+
+    #[test]
+    fn loads_records_in_apt_order() {
+        let pins = fixture();
+        let read: Vec<(&str, i32)> = pins
+            .iter()
+            .map(|pin| (pin.file.rsplit('/').next().unwrap(), pin.priority))
+            .collect();
+        // Only files apt reads, sorted; records missing a field are skipped
+        assert_eq!(
+            read,
+            [
+                ("preferences", 1001),
+                ("preferences", 500),
+                ("preferences", 900),
+                ("preferences", 50),
+                ("10local", 700),
+                ("50-tur.pref", 100),
+                ("50-tur.pref", 990),
+            ]
+        );
+        assert_eq!(pins[0].packages, ["openssl", "libssl3"]);
+        // Field names in any case
+        assert_eq!(pins[6].pin, "release n=tur-packages, c=tur");
+    }
+
+    #[test]
+    fn nothing_to_load() {
+        assert!(load_from("/nonexistent/etc/apt").is_empty());
+    }
+
+    #[test]
+    fn named_records_before_general_ones() {
+        let pins = fixture();
+        assert_eq!(
+            pins_of(&for_package(&pins, "openssl")),
+            [
+                ("version 3.3*", 1001),
+                ("release a=stable", 500),
+                ("origin tur.kcubeterm.com", 100)
+            ]
+        );
+        assert_eq!(
+            pins_of(&for_package(&pins, "nodejs-lts")),
+            [
+                ("origin packages-cf.termux.dev", 900),
+                ("release n=tur-packages, c=tur", 990),
+                ("release a=stable", 500),
+                ("origin tur.kcubeterm.com", 100)
+            ]
+        );
+        assert!(is_pinned(&pins, "libssl3"));
+        assert!(is_pinned(&pins, "nodejs"));
+        assert!(!is_pinned(&pins, "bash"));
+        // Regular expressions are never evaluated
+        assert!(!is_pinned(&pins, "python3-pip"));
+        assert_eq!(for_package(&pins, "python3-pip").len(), 2);
+    }
+
+    #[test]
+    fn explains_each_version_of_the_policy() {
+        let pins = fixture();
+        let policy = include_str!("../tests/fixtures/apt-cache-policy-openssl.txt");
+        assert_eq!(
+            explain(&pins, "openssl", policy).unwrap(),
+            "
+Preferences:
+  Pin: version 3.3*, priority 1001 (PREFERENCES)
+  Pin: release a=stable, priority 500 (PREFERENCES)
+  Pin: origin tur.kcubeterm.com, priority 100 (TUR)
+  3.4.0: 500 by \"Pin: release a=stable\"
+  3.3.1: 1001 by \"Pin: version 3.3*\"
+"
+            .replace("PREFERENCES", &pins[0].file)
+            .replace("TUR", &pins[5].file)
+        );
+    }
+
+    #[test]
+    fn explains_mismatches_and_what_it_cant_tell() {
+        let pins = fixture();
+        let policy = "\
+curl:
+  Version table:
+ *** 8.9.1 700
+        500 https://packages-cf.termux.dev/apt/termux-main stable/main aarch64 Packages
+     8.8.0 100
+        100 /data/data/com.termux/files/usr/var/lib/dpkg/status
+";
+        let text = explain(&pins, "curl", policy).unwrap();
+        // o= is only in the Release file, and the record naming curl comes
+        // first for every version
+        assert!(
+            text.contains("  8.9.1: depends on Release fields, apt reports 700\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("  8.8.0: depends on Release fields, apt reports 100\n"),
+            "{}",
+            text
+        );
+
+        // Nothing covers the status file
+        let policy = "\
+bash:
+  Version table:
+ *** 5.2.21 100
+        100 /data/data/com.termux/files/usr/var/lib/dpkg/status
+";
+        let text = explain(&pins, "bash", policy).unwrap();
+        assert!(text.ends_with("  5.2.21: no record applies\n"), "{}", text);
+
+        let policy = "\
+nodejs-lts:
+  Version table:
+     22.9.0 500
+        500 https://tur.kcubeterm.com tur-packages/tur aarch64 Packages
+";
+        let text = explain(&pins, "nodejs-lts", policy).unwrap();
+        assert!(
+            text.contains(
+                "  22.9.0: 990 by \"Pin: release n=tur-packages, c=tur\", but apt reports 500\n"
+            ),
+            "{}",
+            text
+        );
+        assert!(explain(&[], "curl", policy).is_none());
+    }
+
+    #[test]
+    fn policy_table_with_wide_and_negative_priorities() {
+        let policy = "\
+openssl:
+  Installed: 3.3.1
+  Candidate: 3.3.1
+  Version table:
+     3.4.0 -1
+         -1 https://tur.kcubeterm.com tur-packages/tur aarch64 Packages
+ *** 3.3.1 1001
+       1001 https://packages-cf.termux.dev/apt/termux-main stable/main aarch64 Packages
+        100 /data/data/com.termux/files/usr/var/lib/dpkg/status
+";
+        let table = parse_table(policy);
+        let versions: Vec<(&str, Option<i32>, usize)> = table
+            .iter()
+            .map(|(version, priority, sources)| (version.as_str(), *priority, sources.len()))
+            .collect();
+        assert_eq!(versions, [("3.4.0", Some(-1), 1), ("3.3.1", Some(1001), 2)]);
+        assert_eq!(
+            table[1].2,
+            [
+                "https://packages-cf.termux.dev/apt/termux-main stable/main aarch64 Packages",
+                "/data/data/com.termux/files/usr/var/lib/dpkg/status"
+            ]
+        );
+        assert!(parse_table("N: Unable to locate package nope\n").is_empty());
+    }
+
+    #[test]
+    fn globs_as_apt_matches_them() {
+        assert!(glob("*", ""));
+        assert!(glob("3.3*", "3.3.1"));
+        assert!(!glob("3.3*", "3.4.0"));
+        assert!(glob("lib?ssl*", "libxssl3"));
+        assert!(glob("*-dev", "libssl-dev"));
+        assert!(glob("a*b*c", "axxbyyc"));
+        assert!(!glob("a*b*c", "axxbyy"));
+        assert!(!glob("curl", "curl2"));
+    }
+
+    #[test]
+    fn conditions_and_hosts() {
+        assert_eq!(
+            conditions("a=stable, c=\"main\","),
+            [("a", "stable"), ("c", "main")]
+        );
+        assert_eq!(conditions("stable"), [("a", "stable")]);
+        assert_eq!(host("https://tur.kcubeterm.com/apt"), "tur.kcubeterm.com");
+        assert_eq!(
+            host("/data/data/com.termux/files/usr/var/lib/dpkg/status"),
+            ""
+        );
+    }
+}
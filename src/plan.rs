@@ -0,0 +1,172 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::commands::{self, Action};
+use crate::impact::ImpactView;
+use crate::{format_date, format_size, i18n, PackageList};
+
+// What `p` in the "What would break?" popup names the plan (see
+// exports::write) unless it goes to the clipboard.
+pub const PLAN_NAME: &str = "tpi-removal-plan.sh";
+
+// A removal plan: what `x` would run for the packages in the popup and what
+// that would do, as a shell script to review and run later by hand. The
+// analysis is comments, the commands are the script. Nothing is removed, so
+// it is offered in read-only mode too.
+pub struct Plan {
+    pub text: String,
+    pub packages: usize,
+    pub reclaimed: u64, // KiB, of the packages whose size is known
+    pub warnings: usize,
+}
+
+pub fn build(list: &PackageList, view: &mut ImpactView) -> Plan {
+    let manager = view.manager;
+    let names = view.names.clone();
+    // apt's own answer, when it has one
+    let simulated = commands::supports(manager, Action::SimulateRemove)
+        .then(|| view.simulated(list.runner.as_ref()).map(<[String]>::to_vec));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut text = String::from("#!/bin/sh\n");
+    text.push_str(&format!(
+        "# Removal plan for {} ({}), written by tpi on {}.\n",
+        names.join(" "),
+        manager.name(),
+        format_date(now)
+    ));
+    text.push_str("# Nothing has been removed. Review it, then run it with sh.\n");
+
+    // Space: the packages themselves, and what autoremove takes after them
+    let mut going = names.clone();
+    if view.autoremoves {
+        going.extend(view.impact.orphaned.iter().cloned());
+    }
+    let (known, unknown): (Vec<&String>, Vec<&String>) = going
+        .iter()
+        .partition(|name| list.sizes.contains_key(*name));
+    let reclaimed: u64 = known.iter().filter_map(|name| list.sizes.get(*name)).sum();
+    text.push_str("#\n");
+    text.push_str(&format!("# Frees about {}", format_size(reclaimed)));
+    if !unknown.is_empty() {
+        let unknown: Vec<&str> = unknown.iter().map(|name| name.as_str()).collect();
+        text.push_str(&format!(", plus {} (size unknown)", unknown.join(" ")));
+    }
+    text.push_str(".\n");
+
+    let impact = &view.impact;
+    let held: Vec<&String> = names
+        .iter()
+        .filter(|name| list.held.contains(*name))
+        .collect();
+    let essential: Vec<&String> = names
+        .iter()
+        .filter(|name| list.essential.contains(*name))
+        .collect();
+    let warnings: Vec<(&str, Vec<&String>)> = [
+        ("Held (apt asks before changing held packages)", held),
+        ("Essential to the system", essential),
+        (
+            "Would have unsatisfied dependencies",
+            impact.broken.iter().collect(),
+        ),
+        (
+            "Not in the dependency graph",
+            impact.unknown.iter().collect(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, names)| !names.is_empty())
+    .collect();
+    text.push_str("#\n");
+    if warnings.is_empty() {
+        text.push_str("# Warnings: none\n");
+    } else {
+        text.push_str("# Warnings:\n");
+    }
+    for (title, packages) in &warnings {
+        let packages: Vec<&str> = packages.iter().map(|name| name.as_str()).collect();
+        text.push_str(&format!("#   {}: {}\n", title, packages.join(" ")));
+    }
+    if !impact.orphaned.is_empty() {
+        let title = if view.autoremoves {
+            "Removed after them by autoremove (no longer needed)"
+        } else {
+            "Left with nothing requiring them (pip doesn't remove these)"
+        };
+        text.push_str(&format!("#\n# {}: {}\n", title, impact.orphaned.join(" ")));
+    }
+
+    if let Some(simulated) = simulated {
+        text.push_str("#\n");
+        match simulated {
+            Ok(lines) => {
+                text.push_str("# Dry run (apt-get --simulate remove):\n");
+                for line in lines {
+                    text.push_str(&format!("#   {}\n", line));
+                }
+            }
+            Err(err) => text.push_str(&format!("# {}\n", err)),
+        }
+    }
+
+    // The commands, in the order `x` and autoremove would run them
+    let commands: Vec<String> = match commands::resolve_names(manager, Action::Remove, &names) {
+        Some(words) => vec![commands::join_words(&words)],
+        None => names
+            .iter()
+            .filter_map(|name| commands::resolve(manager, Action::Remove, name))
+            .map(|words| commands::join_words(&words))
+            .collect(),
+    };
+    text.push('\n');
+    if commands.is_empty() {
+        text.push_str(&format!(
+            "# tpi has no remove command for {}.\n",
+            manager.name()
+        ));
+    } else {
+        text.push_str("set -e\n");
+        for command in commands {
+            text.push_str(&command);
+            text.push('\n');
+        }
+        if view.autoremoves && !impact.orphaned.is_empty() {
+            text.push_str("apt autoremove\n");
+        }
+    }
+
+    Plan {
+        text,
+        packages: names.len(),
+        reclaimed,
+        warnings: warnings.len(),
+    }
+}
+
+pub fn write(plan: &Plan) -> io::Result<PathBuf> {
+    crate::exports::write(PLAN_NAME, &plan.text)
+}
+
+// For the status line, after "Wrote tpi-removal-plan.sh" or "Copied the plan".
+pub fn summary(plan: &Plan) -> String {
+    let what = match plan.packages {
+        1 => i18n::text("message.one_package").to_string(),
+        n => i18n::fill("message.packages", &[("count", &n)]),
+    };
+    let warnings = match plan.warnings {
+        0 => i18n::text("message.no_warnings").to_string(),
+        1 => i18n::text("message.one_warning").to_string(),
+        n => i18n::fill("message.warnings", &[("count", &n)]),
+    };
+    i18n::fill(
+        "message.plan_summary",
+        &[
+            ("what", &what),
+            ("size", &format_size(plan.reclaimed)),
+            ("warnings", &warnings),
+        ],
+    )
+}
@@ -0,0 +1,227 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::commands::Runner;
+use crate::showcache::{Key, ShowCache};
+use crate::{log, PackageManager};
+
+// How long without a key press before the screen's details are fetched:
+// two of the event loop's 200 ms ticks while it is busy.
+const IDLE: Duration = Duration::from_millis(400);
+
+// `prefetch = true`: once tpi has been idle for a moment, `show` runs for
+// the packages on screen that haven't been shown yet, top to bottom, so
+// moving the selection among them shows their details at once. Scrolling
+// to another screenful cancels the batch and waits for the next idle spell.
+pub struct Prefetch {
+    pub enabled: bool,
+    manager: PackageManager,
+    screen: Vec<Key>,               // The packages in the list's viewport
+    started: bool,                  // Whether this screen's batch has run
+    batch: Option<Arc<AtomicBool>>, // Cancels the running batch
+    input: Instant,                 // Of the last key press
+    selected: Option<Key>,          // Last counted as a hit or a miss
+    hits: usize,                    // Selections whose details were cached
+    misses: usize,                  // And those that had to be loaded
+}
+
+impl Prefetch {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            manager: PackageManager::Pkg,
+            screen: Vec::new(),
+            started: false,
+            batch: None,
+            input: Instant::now(),
+            selected: None,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn input(&mut self, now: Instant) {
+        self.input = now;
+    }
+
+    // Notes what the list shows as it is drawn. A different screen cancels
+    // the batch for the old one.
+    pub fn viewport(&mut self, manager: PackageManager, screen: Vec<Key>) {
+        if !self.enabled || (manager == self.manager && screen == self.screen) {
+            return;
+        }
+        self.cancel();
+        self.manager = manager;
+        self.screen = screen;
+        self.started = false;
+    }
+
+    pub fn cancel(&mut self) {
+        if let Some(cancel) = self.batch.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Counts the selection as a hit or a miss the first time it is seen.
+    pub fn select(&mut self, selected: Option<Key>, cached: bool) {
+        if !self.enabled || selected.is_none() || selected == self.selected {
+            return;
+        }
+        self.selected = selected;
+        if cached {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+
+    // Until the screen's batch is due, for the event loop's timeout.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        (self.enabled && !self.started && !self.screen.is_empty())
+            .then(|| IDLE.saturating_sub(now.duration_since(self.input)))
+    }
+
+    // Starts the screen's batch once tpi has been idle long enough.
+    pub fn tick(&mut self, cache: &ShowCache, runner: &Arc<dyn Runner>, now: Instant) {
+        if self.remaining(now) != Some(Duration::ZERO) {
+            return;
+        }
+        self.started = true;
+        let missing: Vec<Key> = self
+            .screen
+            .iter()
+            .filter(|(name, version)| !cache.contains(name, version))
+            .cloned()
+            .collect();
+        log::debug(&format!(
+            "prefetch: {} of {} on screen to fetch; selections so far {} hits, {} misses",
+            missing.len(),
+            self.screen.len(),
+            self.hits,
+            self.misses
+        ));
+        if missing.is_empty() {
+            return;
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        cache.prefetch(runner, self.manager, missing, cancel.clone());
+        self.batch = Some(cancel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::tests::Mock;
+
+    fn keys(names: &[&str]) -> Vec<Key> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), "1.0".to_string()))
+            .collect()
+    }
+
+    // Waits for the batch to have run `count` commands.
+    fn wait_for(mock: &Mock, count: usize) -> Vec<String> {
+        let began = Instant::now();
+        while mock.ran("apt show") < count {
+            assert!(began.elapsed() < Duration::from_secs(5), "batch never ran");
+            thread::sleep(Duration::from_millis(5));
+        }
+        mock.ran.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn waits_for_a_pause_then_fetches_what_isnt_cached() {
+        let mock = Arc::new(Mock::new());
+        let runner: Arc<dyn Runner> = mock.clone();
+        let cache = ShowCache::default();
+        cache.get(runner.as_ref(), PackageManager::Apt, "bash", "1.0");
+        let mut prefetch = Prefetch::new(true);
+        let start = Instant::now();
+        prefetch.input(start);
+        prefetch.viewport(PackageManager::Apt, keys(&["bash", "curl", "zlib"]));
+        assert_eq!(prefetch.remaining(start), Some(IDLE));
+
+        prefetch.tick(&cache, &runner, start + IDLE / 2);
+        assert_eq!(mock.ran("apt show"), 1);
+        prefetch.tick(&cache, &runner, start + IDLE);
+        assert_eq!(
+            wait_for(&mock, 3),
+            ["apt show bash", "apt show curl", "apt show zlib"]
+        );
+        assert!(cache.contains("zlib", "1.0"));
+        // Once per screen
+        assert_eq!(prefetch.remaining(start + IDLE), None);
+        prefetch.tick(&cache, &runner, start + IDLE * 2);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(mock.ran("apt show"), 3);
+    }
+
+    #[test]
+    fn another_screen_cancels_the_batch() {
+        let mock = Arc::new(Mock::new().slow(Duration::from_millis(30)));
+        let runner: Arc<dyn Runner> = mock.clone();
+        let cache = ShowCache::default();
+        let mut prefetch = Prefetch::new(true);
+        let start = Instant::now();
+        prefetch.input(start);
+        let names: Vec<String> = (0..20).map(|n| format!("lib{}", n)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        prefetch.viewport(PackageManager::Apt, keys(&names));
+        prefetch.tick(&cache, &runner, start + IDLE);
+        wait_for(&mock, 1);
+        prefetch.viewport(PackageManager::Apt, keys(&["curl"]));
+        thread::sleep(Duration::from_millis(100));
+        let ran = mock.ran("apt show");
+        assert!(ran < 10, "{} ran", ran);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(mock.ran("apt show"), ran);
+        // The new screen is due a batch of its own
+        assert_eq!(prefetch.remaining(start + IDLE), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn the_selections_own_load_goes_first() {
+        let mock = Arc::new(Mock::new().slow(Duration::from_millis(50)));
+        let runner: Arc<dyn Runner> = mock.clone();
+        let cache = ShowCache::default();
+        assert!(cache
+            .get_or_start(&runner, PackageManager::Apt, "zlib", "1.0")
+            .is_none());
+        let mut prefetch = Prefetch::new(true);
+        let start = Instant::now();
+        prefetch.input(start);
+        prefetch.viewport(PackageManager::Apt, keys(&["bash", "curl"]));
+        prefetch.tick(&cache, &runner, start + IDLE);
+        assert_eq!(
+            wait_for(&mock, 3),
+            ["apt show zlib", "apt show bash", "apt show curl"]
+        );
+    }
+
+    #[test]
+    fn off_unless_enabled() {
+        let mut prefetch = Prefetch::new(false);
+        let start = Instant::now();
+        prefetch.viewport(PackageManager::Apt, keys(&["bash"]));
+        assert_eq!(prefetch.remaining(start + IDLE), None);
+        prefetch.select(Some(keys(&["bash"])[0].clone()), false);
+        assert_eq!(prefetch.misses, 0);
+    }
+
+    #[test]
+    fn selections_counted_once_as_hits_or_misses() {
+        let mut prefetch = Prefetch::new(true);
+        let [bash, curl]: [Key; 2] = keys(&["bash", "curl"]).try_into().unwrap();
+        prefetch.select(Some(bash.clone()), true);
+        prefetch.select(Some(bash.clone()), false);
+        prefetch.select(None, false);
+        prefetch.select(Some(curl), false);
+        prefetch.select(Some(bash), false);
+        assert_eq!((prefetch.hits, prefetch.misses), (1, 2));
+    }
+}
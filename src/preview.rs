@@ -0,0 +1,224 @@
+use std::time::{Duration, Instant};
+
+use crate::{Package, PackageManager};
+
+// When the detail pane runs the commands behind it (`show`, the file list,
+// the policy) for the selected package: `details` in the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Fetch {
+    #[default]
+    Eager, // As soon as a package is selected
+    Debounced, // Once the selection has rested for the delay; "Loading…" until then
+    Dwell,     // Likewise, with a summary from the list meanwhile
+}
+
+impl Fetch {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "eager" => Some(Fetch::Eager),
+            "debounced" => Some(Fetch::Debounced),
+            "dwell" => Some(Fetch::Dwell),
+            _ => None,
+        }
+    }
+}
+
+// What the detail pane shows for the selected package right now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pane {
+    Details, // The view, running its command if it must
+    Waiting, // "Loading…", without running anything yet
+    Summary, // Name, version and description from the list
+}
+
+// Whether the detail pane may show, and so fetch, the details yet. `cached`
+// says `show` already ran for the package: debouncing only spares
+// commands, so that is shown at once, while dwelling keeps the summary
+// until the selection rests whatever is cached.
+pub fn pane(fetch: Fetch, rested: bool, cached: bool) -> Pane {
+    match fetch {
+        Fetch::Eager => Pane::Details,
+        Fetch::Debounced if rested || cached => Pane::Details,
+        Fetch::Debounced => Pane::Waiting,
+        Fetch::Dwell if rested => Pane::Details,
+        Fetch::Dwell => Pane::Summary,
+    }
+}
+
+// How long the selection has stayed on one package.
+pub struct Rest {
+    selected: Option<(PackageManager, String)>,
+    since: Instant,
+    rested: bool,
+}
+
+impl Rest {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            since: Instant::now(),
+            rested: false,
+        }
+    }
+
+    // Notes the selection as of `now`. Returns whether it has just come to
+    // rest, which needs a redraw to show the details.
+    pub fn observe(
+        &mut self,
+        selected: Option<(PackageManager, String)>,
+        delay: Duration,
+        now: Instant,
+    ) -> bool {
+        if selected != self.selected {
+            self.selected = selected;
+            self.since = now;
+            self.rested = delay.is_zero();
+            return false;
+        }
+        if !self.rested && now.duration_since(self.since) >= delay {
+            self.rested = true;
+            return true;
+        }
+        false
+    }
+
+    pub fn rested(&self) -> bool {
+        self.rested
+    }
+
+    // Until the selection comes to rest, for the event loop's timeout.
+    pub fn remaining(&self, delay: Duration, now: Instant) -> Option<Duration> {
+        (!self.rested).then(|| delay.saturating_sub(now.duration_since(self.since)))
+    }
+}
+
+// The pane while dwelling: only what the list already knows.
+pub fn summary(pkg: &Package) -> String {
+    let mut text = format!("{} {}\n", pkg.name, pkg.version);
+    if let Some(description) = &pkg.description {
+        text.push_str(&format!("{}\n", description));
+    }
+    text.push_str("\nDetails show once the selection rests here.");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(300);
+
+    // Moves the selection through `steps`, each a package selected and
+    // after how long, drawing the pane on every step as the event loop
+    // would. Returns the packages whose details were fetched, in order.
+    fn fetched(fetch: Fetch, steps: &[(&str, u64)]) -> Vec<String> {
+        let start = Instant::now();
+        let mut rest = Rest::new();
+        let mut cached: Vec<String> = Vec::new();
+        let mut fetched = Vec::new();
+        for (name, at) in steps {
+            let now = start + Duration::from_millis(*at);
+            rest.observe(Some((PackageManager::Apt, name.to_string())), DELAY, now);
+            let is_cached = cached.iter().any(|other| other == name);
+            if pane(fetch, rest.rested(), is_cached) == Pane::Details && !is_cached {
+                cached.push(name.to_string());
+                fetched.push(name.to_string());
+            }
+        }
+        fetched
+    }
+
+    // j held down from bash to zlib, resting on zlib, then back up to bash
+    // and resting on it
+    const SCROLL: [(&str, u64); 9] = [
+        ("bash", 0),
+        ("curl", 50),
+        ("libssl3", 100),
+        ("openssl", 150),
+        ("zlib", 200),
+        ("zlib", 600),
+        ("openssl", 650),
+        ("bash", 700),
+        ("bash", 1100),
+    ];
+
+    #[test]
+    fn eager_fetches_every_package_passed() {
+        assert_eq!(
+            fetched(Fetch::Eager, &SCROLL),
+            ["bash", "curl", "libssl3", "openssl", "zlib"]
+        );
+    }
+
+    #[test]
+    fn debounced_and_dwell_fetch_where_it_rests() {
+        assert_eq!(fetched(Fetch::Debounced, &SCROLL), ["zlib", "bash"]);
+        assert_eq!(fetched(Fetch::Dwell, &SCROLL), ["zlib", "bash"]);
+    }
+
+    #[test]
+    fn debounced_shows_cached_details_at_once_and_dwell_doesnt() {
+        assert_eq!(pane(Fetch::Debounced, false, true), Pane::Details);
+        assert_eq!(pane(Fetch::Debounced, false, false), Pane::Waiting);
+        assert_eq!(pane(Fetch::Dwell, false, true), Pane::Summary);
+        assert_eq!(pane(Fetch::Dwell, false, false), Pane::Summary);
+        for cached in [false, true] {
+            assert_eq!(pane(Fetch::Eager, false, cached), Pane::Details);
+            for fetch in [Fetch::Eager, Fetch::Debounced, Fetch::Dwell] {
+                assert_eq!(pane(fetch, true, cached), Pane::Details);
+            }
+        }
+    }
+
+    #[test]
+    fn rest_comes_once_per_selection() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let bash = || Some((PackageManager::Apt, "bash".to_string()));
+        let mut rest = Rest::new();
+        assert!(!rest.observe(bash(), DELAY, at(0)));
+        assert_eq!(
+            rest.remaining(DELAY, at(100)),
+            Some(Duration::from_millis(200))
+        );
+        assert!(!rest.observe(bash(), DELAY, at(299)));
+        // Just came to rest: one redraw, then no more
+        assert!(rest.observe(bash(), DELAY, at(300)));
+        assert!(rest.rested());
+        assert!(!rest.observe(bash(), DELAY, at(400)));
+        assert_eq!(rest.remaining(DELAY, at(400)), None);
+
+        // Another package, or the same name from another manager, starts over
+        rest.observe(
+            Some((PackageManager::Pip, "bash".to_string())),
+            DELAY,
+            at(500),
+        );
+        assert!(!rest.rested());
+        assert_eq!(rest.remaining(DELAY, at(900)), Some(Duration::ZERO));
+        rest.observe(None, DELAY, at(1000));
+        assert!(!rest.rested());
+    }
+
+    #[test]
+    fn no_delay_rests_at_once() {
+        let mut rest = Rest::new();
+        let now = Instant::now();
+        assert!(!rest.observe(
+            Some((PackageManager::Apt, "bash".to_string())),
+            Duration::ZERO,
+            now
+        ));
+        assert!(rest.rested());
+        assert_eq!(rest.remaining(Duration::ZERO, now), None);
+    }
+
+    #[test]
+    fn fetch_names() {
+        assert_eq!(Fetch::from_name("eager"), Some(Fetch::Eager));
+        assert_eq!(Fetch::from_name("debounced"), Some(Fetch::Debounced));
+        assert_eq!(Fetch::from_name("dwell"), Some(Fetch::Dwell));
+        assert_eq!(Fetch::from_name("Eager"), None);
+        assert_eq!(Fetch::default(), Fetch::Eager);
+    }
+}
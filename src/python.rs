@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::Path;
+
+use crate::commands::{self, Action, Runner};
+use crate::noupgrade::NoUpgrade;
+use crate::{prefix, PackageManager};
+
+// Distributions left in the site-packages of an older interpreter, e.g.
+// after Termux moved python from 3.11 to 3.12. pip of the new interpreter
+// doesn't see them, so they are effectively uninstalled.
+pub struct Stale {
+    pub version: String,                 // "3.11"
+    pub packages: Vec<(String, String)>, // (name, version), sorted by name
+}
+
+pub struct Upgrade {
+    pub current: String,
+    pub stale: Vec<Stale>,
+}
+
+// None when the interpreter can't be asked for its version or nothing is
+// left behind.
+pub fn check(runner: &dyn Runner) -> Option<Upgrade> {
+    let output = commands::run_with(runner, PackageManager::Pip, Action::PyVersion, "").ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let current = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if current.is_empty() {
+        return None;
+    }
+
+    let lib = Path::new(&prefix()).join("lib");
+    let mut stale: Vec<Stale> = fs::read_dir(&lib)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let version = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("python")?
+                .to_string();
+            if version == current || !version.starts_with("3.") {
+                return None;
+            }
+            let packages = read_dist_info(&entry.path().join("site-packages"));
+            (!packages.is_empty()).then_some(Stale { version, packages })
+        })
+        .collect();
+    if stale.is_empty() {
+        return None;
+    }
+    // Numerically, so 3.9 comes before 3.10
+    stale.sort_by_key(|stale| {
+        let minor = stale
+            .version
+            .strip_prefix("3.")
+            .and_then(|minor| minor.parse().ok());
+        minor.unwrap_or(0u32)
+    });
+    Some(Upgrade { current, stale })
+}
+
+// Name and version of every `*.dist-info` directory, from the Name and
+// Version headers of its METADATA, or from the directory name
+// (`{name}-{version}.dist-info`) when that file is missing.
+pub fn read_dist_info(site_packages: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(site_packages) else {
+        return Vec::new();
+    };
+    let mut packages: Vec<(String, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let stem = file_name.to_str()?.strip_suffix(".dist-info")?;
+            let metadata = fs::read_to_string(entry.path().join("METADATA")).unwrap_or_default();
+            let header = |key: &str| {
+                metadata
+                    .lines()
+                    .take_while(|line| !line.is_empty())
+                    .find_map(|line| line.strip_prefix(key))
+                    .map(|value| value.trim().to_string())
+            };
+            let (dir_name, dir_version) = stem.rsplit_once('-').unwrap_or((stem, ""));
+            Some((
+                header("Name:").unwrap_or_else(|| dir_name.to_string()),
+                header("Version:").unwrap_or_else(|| dir_version.to_string()),
+            ))
+        })
+        .collect();
+    packages.sort_by_key(|(name, _)| name.to_lowercase());
+    packages
+}
+
+impl Upgrade {
+    pub fn count(&self) -> usize {
+        self.stale.iter().map(|stale| stale.packages.len()).sum()
+    }
+
+    // "47 packages installed for python3.11, current is 3.12"
+    pub fn summary(&self) -> String {
+        let old: Vec<String> = self
+            .stale
+            .iter()
+            .map(|stale| format!("python{}", stale.version))
+            .collect();
+        let count = self.count();
+        format!(
+            "{} package{} installed for {}, current is {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            old.join(" and "),
+            self.current
+        )
+    }
+
+    fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .stale
+            .iter()
+            .flat_map(|stale| stale.packages.iter().map(|(name, _)| name.clone()))
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        names
+    }
+
+    // One `pip install` for everything left behind, without what the
+    // do-not-upgrade list names unless `include_listed`. Versions are not
+    // pinned: the old ones may have no build for the new interpreter.
+    pub fn reinstall_command(&self, no_upgrade: &NoUpgrade, include_listed: bool) -> String {
+        let mut words = vec!["pip".to_string(), "install".to_string()];
+        words.extend(
+            self.names()
+                .into_iter()
+                .filter(|name| include_listed || !no_upgrade.contains(name)),
+        );
+        commands::join_words(&words)
+    }
+
+    // Popup text: the summary, each old directory with its packages, then the
+    // command to run and what the do-not-upgrade list keeps out of it.
+    pub fn text(&self, no_upgrade: &NoUpgrade, include_listed: bool) -> String {
+        let mut text = format!("{}\n", self.summary());
+        for stale in &self.stale {
+            text.push_str(&format!(
+                "\n{}/lib/python{}/site-packages:\n",
+                prefix(),
+                stale.version
+            ));
+            for (name, version) in &stale.packages {
+                text.push_str(&format!("  {} {}\n", name, version));
+            }
+        }
+        text.push_str(&format!(
+            "\nReinstall them for python{} with:\n\n  {}\n",
+            self.current,
+            self.reinstall_command(no_upgrade, include_listed)
+        ));
+        let listed: Vec<String> = self
+            .names()
+            .into_iter()
+            .filter_map(|name| Some(format!("  {}: {}\n", name, no_upgrade.reason(&name)?)))
+            .collect();
+        if !listed.is_empty() {
+            text.push_str(if include_listed {
+                "\nIncluded although the do-not-upgrade list names them (i to leave them out):\n"
+            } else {
+                "\nLeft out, the do-not-upgrade list names them (i to include them anyway):\n"
+            });
+            text.push_str(&listed.concat());
+        }
+        text
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+};
+
+use crate::commands::{self, Action, Runner};
+use crate::why::{self, Graph};
+use crate::{names, PackageManager};
+
+// What the lookup thread sends back: the dependents, and for pip the
+// Requires graph it had to read, so the next lookup needn't.
+type Found = Result<(Vec<String>, Option<Graph>), String>;
+
+// What installed packages depend on one package (`y`), over the detail
+// pane like the dependency tree. For dpkg that is `apt-cache rdepends
+// --installed`; for pip the Requires of every distribution, read once with
+// the requires script and kept for the listing.
+pub struct Lookup {
+    pub root: String,
+    dependents: Option<Vec<String>>, // None while looking
+    error: Option<String>,
+    receiver: Option<Receiver<Found>>,
+    graph: Option<Graph>, // Read by this lookup, for the list to keep
+    pub state: ListState,
+}
+
+impl Lookup {
+    // `graph` is pip's Requires when already read; without it, and for
+    // dpkg always, the lookup runs on a background thread.
+    pub fn start(
+        runner: Arc<dyn Runner>,
+        manager: PackageManager,
+        root: String,
+        graph: Option<&Graph>,
+    ) -> Self {
+        let mut lookup = Self {
+            root: root.clone(),
+            dependents: None,
+            error: None,
+            receiver: None,
+            graph: None,
+            state: ListState::default(),
+        };
+        if let Some(graph) = graph.filter(|_| manager == PackageManager::Pip) {
+            lookup.finish(Ok((requiring(graph, &root), None)));
+            return lookup;
+        }
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let found = match manager {
+                PackageManager::Pip => {
+                    read(runner.as_ref(), manager, Action::Depends, "").map(|stdout| {
+                        let graph = why::parse_requires(&stdout);
+                        (requiring(&graph, &root), Some(graph))
+                    })
+                }
+                _ => read(runner.as_ref(), manager, Action::Rdepends, &root)
+                    .map(|stdout| (parse(&stdout), None)),
+            };
+            let _ = sender.send(found);
+        });
+        lookup.receiver = Some(receiver);
+        lookup
+    }
+
+    fn finish(&mut self, found: Found) {
+        match found {
+            Ok((dependents, graph)) => {
+                self.state.select((!dependents.is_empty()).then_some(0));
+                self.dependents = Some(dependents);
+                self.graph = graph;
+            }
+            Err(err) => self.error = Some(err),
+        }
+        self.receiver = None;
+    }
+
+    // Takes in the thread's answer. Returns whether it came.
+    pub fn poll(&mut self) -> bool {
+        let Some(found) = self.receiver.as_ref().and_then(|rx| rx.try_recv().ok()) else {
+            return false;
+        };
+        self.finish(found);
+        true
+    }
+
+    // The Requires graph this lookup read, once.
+    pub fn take_graph(&mut self) -> Option<Graph> {
+        self.graph.take()
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        let dependents = self.dependents.as_ref()?;
+        dependents.get(self.state.selected()?).map(String::as_str)
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.dependents.as_ref().map_or(0, Vec::len);
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1).min(len.saturating_sub(1))));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .title(format!("What needs {} — Enter go to, y close", self.root))
+            .borders(Borders::ALL);
+        let message = match (&self.error, &self.dependents) {
+            (Some(err), _) => Some(format!("Lookup failed: {}", err)),
+            (None, None) => Some("Looking up reverse dependencies…".to_string()),
+            (None, Some(dependents)) if dependents.is_empty() => {
+                Some("Nothing installed depends on this package — safe to remove".to_string())
+            }
+            (None, Some(_)) => None,
+        };
+        if let Some(message) = message {
+            let empty = List::new([ListItem::new(message)]).block(block);
+            f.render_widget(empty, area);
+            return;
+        }
+        let items: Vec<ListItem> = self
+            .dependents
+            .iter()
+            .flatten()
+            .map(|name| ListItem::new(format!("← {}", name)))
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+fn read(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    action: Action,
+    name: &str,
+) -> Result<String, String> {
+    let output =
+        commands::run_with(runner, manager, action, name).map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `apt-cache rdepends` prints the package, "Reverse Depends:" and then one
+// indented line per package depending on it (`|` marks an alternative, and
+// a package shows up once per dependency it has).
+pub fn parse(stdout: &str) -> Vec<String> {
+    let mut dependents: Vec<String> = stdout
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("Reverse Depends:"))
+        .skip(1)
+        .map(|line| line.trim().trim_start_matches('|'))
+        .map(|name| name.split(':').next().unwrap_or(name).to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    dependents.sort();
+    dependents.dedup();
+    dependents
+}
+
+// Distributions whose Requires name the package.
+fn requiring(graph: &Graph, name: &str) -> Vec<String> {
+    let key = names::pip(name);
+    let mut dependents: Vec<String> = graph
+        .edges
+        .iter()
+        .filter(|(_, requires)| requires.contains(&key))
+        .map(|(name, _)| name.clone())
+        .collect();
+    dependents.sort();
+    dependents
+}
@@ -0,0 +1,485 @@
+use ratatui::prelude::*;
+
+use crate::{days_from_civil, format_age, format_size, PackageManager};
+
+// What renderers may compare a value with.
+pub struct Context {
+    pub largest: Option<u64>, // Largest installed size in the list, KiB
+    pub now: u64,             // Seconds since the epoch
+}
+
+type Render = fn(&str, &Context) -> Vec<Line<'static>>;
+
+// How the Fields view draws a field's value. The first entry whose name
+// matches (any case) and that applies to the manager wins; anything else is
+// drawn as plain text.
+struct Renderer {
+    fields: &'static [&'static str],
+    managers: &'static [PackageManager], // Empty for every manager
+    render: Render,
+}
+
+const RENDERERS: [Renderer; 6] = [
+    Renderer {
+        fields: &["Description"],
+        managers: &[],
+        render: description,
+    },
+    Renderer {
+        fields: &[
+            "Depends",
+            "Pre-Depends",
+            "Recommends",
+            "Suggests",
+            "Breaks",
+            "Conflicts",
+            "Provides",
+            "Replaces",
+            "Requires",
+            "Required-by",
+        ],
+        managers: &[],
+        render: relations,
+    },
+    // dpkg's Installed-Size is in KiB
+    Renderer {
+        fields: &["Installed-Size"],
+        managers: &[PackageManager::Pkg, PackageManager::Apt],
+        render: size,
+    },
+    Renderer {
+        fields: &[
+            "Homepage",
+            "Home-page",
+            "Project-URL",
+            "Project-URLs",
+            "Bugs",
+            "Vcs-Browser",
+            "Vcs-Git",
+            "URL",
+            "source",
+        ],
+        managers: &[],
+        render: urls,
+    },
+    // composer's `released` already says how long ago
+    Renderer {
+        fields: &["released"],
+        managers: &[PackageManager::Composer],
+        render: plain,
+    },
+    Renderer {
+        fields: &["Date", "Build-Date", "Last-Modified", "released"],
+        managers: &[],
+        render: date,
+    },
+];
+
+pub fn render(
+    manager: PackageManager,
+    key: &str,
+    value: &str,
+    context: &Context,
+) -> Vec<Line<'static>> {
+    let renderer = RENDERERS.iter().find(|renderer| {
+        renderer
+            .fields
+            .iter()
+            .any(|field| field.eq_ignore_ascii_case(key))
+            && (renderer.managers.is_empty() || renderer.managers.contains(&manager))
+    });
+    match renderer {
+        Some(renderer) => (renderer.render)(value, context),
+        None => plain(value, context),
+    }
+}
+
+fn plain(value: &str, _context: &Context) -> Vec<Line<'static>> {
+    value
+        .lines()
+        .map(|line| Line::from(line.trim().to_string()))
+        .collect()
+}
+
+// A Debian Description: the synopsis in bold, a blank line, then the long
+// text with the one-space continuation indent removed and ` .` lines as
+// paragraph breaks. Lines indented further are verbatim (lists, examples)
+// and keep the rest of their indent.
+fn description(value: &str, _context: &Context) -> Vec<Line<'static>> {
+    let mut lines = value.lines();
+    let mut out = vec![Line::from(lines.next().unwrap_or("").trim().to_string()).bold()];
+    for line in lines {
+        let line = line
+            .strip_prefix(' ')
+            .or_else(|| line.strip_prefix('\t'))
+            .unwrap_or(line)
+            .trim_end();
+        if line.trim() == "." || line.is_empty() {
+            if out.last().is_some_and(|last| last.width() > 0) {
+                out.push(Line::default());
+            }
+            continue;
+        }
+        if out.len() == 1 {
+            out.push(Line::default()); // Between the synopsis and the long text
+        }
+        out.push(Line::from(line.to_string()));
+    }
+    while out.len() > 1 && out.last().is_some_and(|last| last.width() == 0) {
+        out.pop();
+    }
+    out
+}
+
+// One relation per line, in color with any version constraint dimmed.
+// Spaces inside a relation don't break, so a long one wraps as a whole.
+fn relations(value: &str, _context: &Context) -> Vec<Line<'static>> {
+    let name = Style::default().fg(Color::Cyan);
+    let constraint = Style::default().fg(Color::DarkGray);
+    let mut out = Vec::new();
+    let items = value
+        .split(',')
+        .map(|item| item.split_whitespace().collect::<Vec<_>>().join("\u{a0}"))
+        .filter(|item| !item.is_empty());
+    for item in items {
+        let mut spans = Vec::new();
+        // "a (>= 1) | b": parenthesized parts are constraints
+        let mut rest = item.as_str();
+        while let Some(open) = rest.find('(') {
+            let close = rest[open..]
+                .find(')')
+                .map_or(rest.len(), |at| open + at + 1);
+            if open > 0 {
+                spans.push(Span::styled(rest[..open].to_string(), name));
+            }
+            spans.push(Span::styled(rest[open..close].to_string(), constraint));
+            rest = &rest[close..];
+        }
+        if !rest.is_empty() {
+            spans.push(Span::styled(rest.to_string(), name));
+        }
+        out.push(Line::from(spans));
+    }
+    if out.is_empty() {
+        out.push(Line::default()); // pip's empty Requires
+    }
+    out
+}
+
+// Human units, and a bar against the largest package in the list.
+fn size(value: &str, context: &Context) -> Vec<Line<'static>> {
+    const BAR: usize = 16;
+    let Ok(kib) = value.trim().parse::<u64>() else {
+        return plain(value, context);
+    };
+    let mut spans = vec![Span::raw(format_size(kib))];
+    if let Some(largest) = context.largest.filter(|largest| *largest > 0) {
+        let filled = ((kib.min(largest) * BAR as u64).div_ceil(largest)) as usize;
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            "█".repeat(filled),
+            Style::default().fg(Color::Yellow),
+        ));
+        spans.push(Span::styled(
+            "░".repeat(BAR - filled),
+            Style::default().fg(Color::DarkGray),
+        ));
+        spans.push(Span::styled(
+            format!(" {}% of the largest", kib * 100 / largest),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    vec![Line::from(spans)]
+}
+
+// URLs underlined in blue, whatever text surrounds them (pip's Project-URL
+// is "Label, https://...").
+fn urls(value: &str, _context: &Context) -> Vec<Line<'static>> {
+    let link = Style::default()
+        .fg(Color::Blue)
+        .add_modifier(Modifier::UNDERLINED);
+    value
+        .lines()
+        .map(|line| {
+            let spans: Vec<Span> = line
+                .trim()
+                .split_inclusive(' ')
+                .flat_map(|word| {
+                    let url = word.trim_end();
+                    if url.contains("://") {
+                        vec![
+                            Span::styled(url.to_string(), link),
+                            Span::raw(word[url.len()..].to_string()),
+                        ]
+                    } else {
+                        vec![Span::raw(word.to_string())]
+                    }
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+// The value as given, then how long ago it was. Dates are read from a
+// leading YYYY-MM-DD (midnight UTC) or seconds since the epoch.
+fn date(value: &str, context: &Context) -> Vec<Line<'static>> {
+    let text = value.trim();
+    let Some(secs) = parse_date(text).filter(|secs| *secs <= context.now) else {
+        return plain(value, context);
+    };
+    vec![Line::from(vec![
+        Span::raw(text.to_string()),
+        Span::styled(
+            format!(" ({})", format_age(context.now - secs)),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])]
+}
+
+fn parse_date(text: &str) -> Option<u64> {
+    if let Ok(secs) = text.parse::<u64>() {
+        return Some(secs);
+    }
+    let date = text.get(..10)?;
+    let mut parts = date.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    u64::try_from(days_from_civil(year, month, day) * 86_400).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::widgets::{Paragraph, Wrap};
+
+    // 2024-05-11, ten days after the dates below
+    const NOW: u64 = 1_715_385_600;
+
+    fn context(largest: Option<u64>) -> Context {
+        Context { largest, now: NOW }
+    }
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn styled<'a>(line: &'a Line, style: Style) -> Vec<&'a str> {
+        line.spans
+            .iter()
+            .filter(|span| span.style == style)
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    // `lines` drawn `width` columns wide, wrapping as the Fields view does.
+    fn drawn(lines: Vec<Line<'static>>, width: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, 6)).unwrap();
+        terminal
+            .draw(|f| {
+                let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+                f.render_widget(paragraph, f.size());
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                let row: String = (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .filter(|row| !row.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn depends_one_relation_per_line() {
+        let lines = render(
+            PackageManager::Apt,
+            "depends",
+            "libc6 (>= 2.34), libssl3 (>= 3.0.0),  zlib1g | libz (<< 2)",
+            &context(None),
+        );
+        let nbsp = |text: &str| text.replace(' ', "\u{a0}");
+        assert_eq!(
+            texts(&lines),
+            [
+                nbsp("libc6 (>= 2.34)"),
+                nbsp("libssl3 (>= 3.0.0)"),
+                nbsp("zlib1g | libz (<< 2)")
+            ]
+        );
+        let name = Style::default().fg(Color::Cyan);
+        let constraint = Style::default().fg(Color::DarkGray);
+        assert_eq!(styled(&lines[2], name), [nbsp("zlib1g | libz ")]);
+        assert_eq!(styled(&lines[2], constraint), [nbsp("(<< 2)")]);
+        assert_eq!(styled(&lines[0], constraint), [nbsp("(>= 2.34)")]);
+    }
+
+    #[test]
+    fn depends_wrap_whole_relations() {
+        let lines = render(
+            PackageManager::Apt,
+            "Depends",
+            "libandroid-support (>= 28), libc++",
+            &context(None),
+        );
+        // Too long for the pane: it is cut, never split at its spaces
+        assert_eq!(
+            drawn(lines, 14),
+            ["libandroid-sup", "port\u{a0}(>=\u{a0}28)", "libc++"]
+        );
+    }
+
+    #[test]
+    fn empty_requires_is_one_blank_line() {
+        let lines = render(PackageManager::Pip, "Requires", "", &context(None));
+        assert_eq!(texts(&lines), [""]);
+    }
+
+    #[test]
+    fn unclosed_constraint_runs_to_the_end() {
+        let lines = render(PackageManager::Apt, "Depends", "foo (>= 1", &context(None));
+        let constraint = Style::default().fg(Color::DarkGray);
+        assert_eq!(styled(&lines[0], constraint), ["(>=\u{a0}1"]);
+    }
+
+    #[test]
+    fn size_with_a_bar_against_the_largest() {
+        let lines = render(
+            PackageManager::Apt,
+            "Installed-Size",
+            "2048",
+            &context(Some(8192)),
+        );
+        assert_eq!(
+            texts(&lines),
+            ["2.0 MiB  ████░░░░░░░░░░░░ 25% of the largest"]
+        );
+        let lines = render(
+            PackageManager::Pkg,
+            "Installed-Size",
+            "8192",
+            &context(Some(8192)),
+        );
+        assert_eq!(
+            texts(&lines),
+            ["8.0 MiB  ████████████████ 100% of the largest"]
+        );
+        // Any size at all shows
+        let lines = render(
+            PackageManager::Apt,
+            "Installed-Size",
+            "1",
+            &context(Some(8192)),
+        );
+        assert!(texts(&lines)[0].starts_with("1 KiB  █░"));
+    }
+
+    #[test]
+    fn size_without_a_largest_or_a_number() {
+        let lines = render(PackageManager::Apt, "Installed-Size", "512", &context(None));
+        assert_eq!(texts(&lines), ["512 KiB"]);
+        let lines = render(
+            PackageManager::Apt,
+            "Installed-Size",
+            "512",
+            &context(Some(0)),
+        );
+        assert_eq!(texts(&lines), ["512 KiB"]);
+        let lines = render(
+            PackageManager::Apt,
+            "Installed-Size",
+            "about 5 MB",
+            &context(Some(1)),
+        );
+        assert_eq!(texts(&lines), ["about 5 MB"]);
+        // Only dpkg's is in KiB
+        let lines = render(
+            PackageManager::Pip,
+            "Installed-Size",
+            "2048",
+            &context(Some(1)),
+        );
+        assert_eq!(texts(&lines), ["2048"]);
+    }
+
+    #[test]
+    fn urls_underlined_wherever_they_are() {
+        let link = Style::default()
+            .fg(Color::Blue)
+            .add_modifier(Modifier::UNDERLINED);
+        let lines = render(
+            PackageManager::Pip,
+            "Project-URL",
+            "Source, https://github.com/psf/requests\nDocs, https://requests.readthedocs.io ",
+            &context(None),
+        );
+        assert_eq!(
+            texts(&lines),
+            [
+                "Source, https://github.com/psf/requests",
+                "Docs, https://requests.readthedocs.io"
+            ]
+        );
+        assert_eq!(styled(&lines[0], link), ["https://github.com/psf/requests"]);
+        assert_eq!(styled(&lines[1], link), ["https://requests.readthedocs.io"]);
+        let lines = render(
+            PackageManager::Apt,
+            "Homepage",
+            "none given",
+            &context(None),
+        );
+        assert!(styled(&lines[0], link).is_empty());
+    }
+
+    #[test]
+    fn dates_say_how_long_ago() {
+        let ago = format!(" ({})", format_age(10 * 86_400));
+        for value in ["2024-05-01", "2024-05-01T12:00:00Z", "1714521600"] {
+            let lines = render(PackageManager::Npm, "Date", value, &context(None));
+            assert_eq!(texts(&lines), [format!("{}{}", value, ago)], "{}", value);
+        }
+        let lines = render(
+            PackageManager::Cargo,
+            "Last-Modified",
+            "2024-05-11",
+            &context(None),
+        );
+        assert_eq!(texts(&lines), [format!("2024-05-11 ({})", format_age(0))]);
+    }
+
+    #[test]
+    fn other_dates_as_given() {
+        for value in ["2024-13-01", "May 1, 2024", "2024-06-01", "2024-5-1"] {
+            let lines = render(PackageManager::Npm, "Date", value, &context(None));
+            assert_eq!(texts(&lines), [value], "{}", value);
+        }
+        // composer's already says how long ago
+        let lines = render(
+            PackageManager::Composer,
+            "released",
+            "2024-05-01, 1 week ago",
+            &context(None),
+        );
+        assert_eq!(texts(&lines), ["2024-05-01, 1 week ago"]);
+    }
+
+    #[test]
+    fn everything_else_plain() {
+        let lines = render(
+            PackageManager::Apt,
+            "Maintainer",
+            "  Termux members  \n",
+            &context(None),
+        );
+        assert_eq!(texts(&lines), ["Termux members"]);
+        assert_eq!(lines[0].spans[0].style, Style::default());
+    }
+}
@@ -0,0 +1,53 @@
+use crate::details::Details;
+use crate::PackageManager;
+
+pub struct ReportEntry {
+    pub manager: PackageManager,
+    pub name: String,
+    pub version: String,
+    pub details: Details,
+}
+
+// Human-readable Markdown document: a summary table followed by one
+// section per package.
+pub fn markdown(entries: &[ReportEntry]) -> String {
+    let mut out = String::new();
+
+    out.push_str("# Package report\n\n");
+    out.push_str("| Package | Version | Manager | Size |\n");
+    out.push_str("|---------|---------|---------|------|\n");
+    for entry in entries {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            table_cell(&entry.name),
+            table_cell(&entry.version),
+            entry.manager.name(),
+            table_cell(entry.details.installed_size().unwrap_or("-")),
+        ));
+    }
+
+    for entry in entries {
+        out.push_str(&format!("\n## {}\n\n", entry.name));
+        out.push_str(&format!("- **Version:** {}\n", entry.version));
+        out.push_str(&format!("- **Manager:** {}\n", entry.manager.name()));
+        if let Some(homepage) = entry.details.homepage() {
+            out.push_str(&format!("- **Homepage:** <{}>\n", homepage));
+        }
+        if let Some(size) = entry.details.installed_size() {
+            out.push_str(&format!("- **Size:** {}\n", size));
+        }
+
+        if let Some(summary) = entry.details.summary() {
+            out.push_str(&format!("\n{}\n", summary));
+        }
+        if let Some(description) = entry.details.description() {
+            out.push_str(&format!("\n{}\n", description));
+        }
+    }
+
+    out
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
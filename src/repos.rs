@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::fs;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::commands::{self, Action, Runner};
+use crate::pins::Pin;
+use crate::{prefix, Package, PackageManager};
+
+// One `deb` line (or one suite of a deb822 stanza) from the apt sources.
+pub struct Source {
+    pub uri: String,
+    pub suite: String,
+    pub components: Vec<String>,
+    pub file: String,          // The sources file it came from
+    pub owner: Option<String>, // Package that ships that file, e.g. x11-repo
+    // Names apt list may show for packages from here: the configured suite
+    // plus the Suite and Codename of its Release file
+    pub names: HashSet<String>,
+    pub indexed: bool, // apt update has fetched its Release file
+}
+
+impl Source {
+    pub fn label(&self) -> String {
+        format!("{} {} {}", self.uri, self.suite, self.components.join(" "))
+    }
+
+    pub fn provides(&self, pkg: &Package) -> bool {
+        pkg.repo
+            .as_ref()
+            .is_some_and(|repo| self.names.contains(repo))
+    }
+}
+
+// Every configured source, in the order apt reads them.
+pub fn load(runner: &dyn Runner, manager: PackageManager) -> Vec<Source> {
+    let etc = format!("{}/etc/apt", prefix());
+    let mut files = vec![format!("{}/sources.list", etc)];
+    if let Ok(entries) = fs::read_dir(format!("{}/sources.list.d", etc)) {
+        let mut extra: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path().to_string_lossy().into_owned())
+            .filter(|path| path.ends_with(".list") || path.ends_with(".sources"))
+            .collect();
+        extra.sort();
+        files.extend(extra);
+    }
+
+    let mut sources = Vec::new();
+    for file in files {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let entries = if file.ends_with(".sources") {
+            parse_deb822(&text)
+        } else {
+            parse_one_line(&text)
+        };
+        if entries.is_empty() {
+            continue;
+        }
+        let owner = owner(runner, manager, &file);
+        for (uri, suite, components) in entries {
+            let (names, indexed) = release_names(&uri, &suite);
+            sources.push(Source {
+                uri,
+                suite,
+                components,
+                file: file.clone(),
+                owner: owner.clone(),
+                names,
+                indexed,
+            });
+        }
+    }
+    sources
+}
+
+type Entry = (String, String, Vec<String>);
+
+// `deb [options] uri suite component...`; deb-src lines are skipped.
+fn parse_one_line(text: &str) -> Vec<Entry> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.trim();
+            let rest = line.strip_prefix("deb ")?.trim_start();
+            let rest = match rest.strip_prefix('[') {
+                Some(options) => options.split_once(']')?.1,
+                None => rest,
+            };
+            let mut words = rest.split_whitespace();
+            let uri = words.next()?.trim_end_matches('/').to_string();
+            let suite = words.next()?.to_string();
+            Some((uri, suite, words.map(String::from).collect()))
+        })
+        .collect()
+}
+
+// deb822 stanzas (Types, URIs, Suites, Components), one entry per URI and
+// suite of each stanza that is of type deb and not disabled.
+fn parse_deb822(text: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for stanza in text.split("\n\n") {
+        let field = |key: &str| {
+            stanza
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.trim()
+                        .eq_ignore_ascii_case(key)
+                        .then(|| value.split_whitespace().map(String::from).collect())
+                })
+                .unwrap_or_else(Vec::<String>::new)
+        };
+        let enabled = field("Enabled").first().is_none_or(|value| value != "no");
+        if !enabled || !field("Types").iter().any(|kind| kind == "deb") {
+            continue;
+        }
+        let components = field("Components");
+        for uri in field("URIs") {
+            for suite in field("Suites") {
+                let uri = uri.trim_end_matches('/').to_string();
+                entries.push((uri, suite, components.clone()));
+            }
+        }
+    }
+    entries
+}
+
+// The package that ships a sources file (`dpkg -S`), if any does.
+fn owner(runner: &dyn Runner, manager: PackageManager, file: &str) -> Option<String> {
+    let output = commands::run_with(runner, manager, Action::Owner, file).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (packages, _) = stdout.lines().next()?.split_once(':')?;
+    Some(packages.trim().to_string())
+}
+
+// Suite and codename from the Release file apt downloaded for this source.
+// apt names it after the URI without its scheme, `/` turned into `_` (and
+// `_` escaped as %5f): http://host/apt + stable -> host_apt_dists_stable_InRelease.
+fn release_names(uri: &str, suite: &str) -> (HashSet<String>, bool) {
+    let mut names = HashSet::from([suite.to_string()]);
+    let host_path = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+    let host_path = host_path
+        .rsplit_once('@')
+        .map_or(host_path, |(_, rest)| rest);
+    let base = format!(
+        "{}/var/lib/apt/lists/{}_dists_{}",
+        prefix(),
+        host_path.replace('_', "%5f").replace('/', "_"),
+        suite.replace('/', "_")
+    );
+    let release = ["_InRelease", "_Release"]
+        .iter()
+        .find_map(|suffix| fs::read_to_string(format!("{}{}", base, suffix)).ok());
+    let Some(release) = release else {
+        return (names, false);
+    };
+    // Checksum lines are indented, so only the header can match
+    for line in release.lines() {
+        if let Some(value) = line
+            .strip_prefix("Suite:")
+            .or_else(|| line.strip_prefix("Codename:"))
+        {
+            names.insert(value.trim().to_string());
+        }
+    }
+    (names, true)
+}
+
+// Screen listing every configured repository with how many installed
+// packages came from it. Enter filters the package list to those.
+pub struct RepoView {
+    pub sources: Vec<Source>,
+    counts: Vec<usize>,
+    pins: Vec<Vec<String>>, // Labels of the preferences records about each source
+    pub state: ListState,
+}
+
+impl RepoView {
+    pub fn new(sources: Vec<Source>, items: &[Package], pins: &[Pin]) -> Self {
+        let counts = sources
+            .iter()
+            .map(|source| items.iter().filter(|pkg| source.provides(pkg)).count())
+            .collect();
+        let pins = sources
+            .iter()
+            .map(|source| {
+                pins.iter()
+                    .filter(|pin| pin.covers_source(source) == Some(true))
+                    .map(|pin| format!("    {} {}", pin.packages.join(" "), pin.label()))
+                    .collect()
+            })
+            .collect();
+        let mut state = ListState::default();
+        if !sources.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            sources,
+            counts,
+            pins,
+            state,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.sources.len()));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            let len = self.sources.len();
+            self.state.select(Some((i + len - 1) % len));
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Source> {
+        self.state.selected().and_then(|i| self.sources.get(i))
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .title("Repositories — Enter filter list, Esc close")
+            .borders(Borders::ALL);
+        f.render_widget(Clear, area);
+        if self.sources.is_empty() {
+            f.render_widget(
+                Paragraph::new(format!("No sources configured in {}/etc/apt", prefix()))
+                    .block(block),
+                area,
+            );
+            return;
+        }
+
+        let items: Vec<ListItem> = self
+            .sources
+            .iter()
+            .zip(&self.counts)
+            .zip(&self.pins)
+            .map(|((source, count), pins)| {
+                let mut first = vec![
+                    Span::raw(source.label()),
+                    Span::styled(
+                        format!("  {} installed", count),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                ];
+                if !source.indexed {
+                    first.push(Span::styled(
+                        "  ⚠ no index — unreachable at the last apt update?",
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                let origin = match &source.owner {
+                    Some(owner) => format!("    {} (from {})", source.file, owner),
+                    None => format!("    {}", source.file),
+                };
+                let mut lines = vec![
+                    Line::from(first),
+                    Line::styled(origin, Style::default().fg(Color::DarkGray)),
+                ];
+                lines.extend(
+                    pins.iter()
+                        .map(|pin| Line::styled(pin.as_str(), Style::default().fg(Color::Cyan))),
+                );
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
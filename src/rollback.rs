@@ -0,0 +1,385 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::dpkglog::{Kind, Log};
+use crate::{prefix, Package};
+
+// Where apt leaves the .debs it downloaded, until `apt clean` or autoclean.
+pub fn archives() -> PathBuf {
+    Path::new(&prefix()).join("var/cache/apt/archives")
+}
+
+// dpkg's version order: epoch, then the upstream version, then the Debian
+// revision, the last two compared as runs of non-digits (letters before
+// other characters, `~` before anything, even the end) and numbers.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let (a_epoch, a_upstream, a_revision) = split(a);
+    let (b_epoch, b_upstream, b_revision) = split(b);
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| compare_part(a_upstream, b_upstream))
+        .then_with(|| compare_part(a_revision, b_revision))
+}
+
+// "1:2.3-4" is (1, "2.3", "4"); without a colon the epoch is 0, and without
+// a hyphen the revision is empty.
+fn split(version: &str) -> (u64, &str, &str) {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((epoch, rest)) if epoch.bytes().all(|b| b.is_ascii_digit()) => {
+            (epoch.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    };
+    match rest.rsplit_once('-') {
+        Some((upstream, revision)) => (epoch, upstream, revision),
+        None => (epoch, rest, ""),
+    }
+}
+
+// dpkg's verrevcmp.
+fn compare_part(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    let non_digit = |s: &[u8]| s.first().filter(|c| !c.is_ascii_digit()).copied();
+    while !a.is_empty() || !b.is_empty() {
+        while non_digit(a).is_some() || non_digit(b).is_some() {
+            let (a_weight, b_weight) = (weight(non_digit(a)), weight(non_digit(b)));
+            if a_weight != b_weight {
+                return a_weight.cmp(&b_weight);
+            }
+            a = a.get(1..).unwrap_or_default();
+            b = b.get(1..).unwrap_or_default();
+        }
+        let (a_number, a_rest) = number(a);
+        let (b_number, b_rest) = number(b);
+        // Without leading zeros, the longer number is the larger
+        let order = a_number
+            .len()
+            .cmp(&b_number.len())
+            .then_with(|| a_number.cmp(b_number));
+        if order != Ordering::Equal {
+            return order;
+        }
+        a = a_rest;
+        b = b_rest;
+    }
+    Ordering::Equal
+}
+
+// The run of digits `s` starts with, without its leading zeros, and what
+// follows it.
+fn number(s: &[u8]) -> (&[u8], &[u8]) {
+    let s = &s[s.iter().take_while(|c| **c == b'0').count()..];
+    let len = s.iter().take_while(|c| c.is_ascii_digit()).count();
+    s.split_at(len)
+}
+
+fn weight(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(c),
+        Some(c) => i32::from(c) + 256,
+    }
+}
+
+// A .deb of the package in apt's cache.
+#[derive(Clone, Debug)]
+pub struct Cached {
+    pub path: PathBuf,
+    pub version: String,
+}
+
+// "name_version_arch.deb", the version's epoch colon written `%3a`.
+pub fn parse_file_name(file: &str) -> Option<(String, String, String)> {
+    let stem = file.strip_suffix(".deb")?;
+    let mut parts = stem.split('_');
+    let (name, version, arch) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let version = version.replace("%3a", ":").replace("%3A", ":");
+    Some((name.to_string(), version, arch.to_string()))
+}
+
+// The package's cached .debs older than `installed`, for its architecture
+// (or `all`), newest first.
+pub fn cached(dir: &Path, pkg: &Package) -> Vec<Cached> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<Cached> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file = entry.file_name().to_string_lossy().into_owned();
+            let (name, version, arch) = parse_file_name(&file)?;
+            let arch_fits = match &pkg.arch {
+                Some(own) => arch == *own || arch == "all",
+                None => true,
+            };
+            (name == pkg.name && arch_fits && compare(&version, &pkg.version).is_lt()).then(|| {
+                Cached {
+                    path: entry.path(),
+                    version,
+                }
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| compare(&b.version, &a.version));
+    found
+}
+
+// The version the installed one replaced, from the last upgrade to it in
+// dpkg's logs. None if the package wasn't upgraded to what is installed.
+pub fn upgraded_from<'a>(log: &'a Log, pkg: &Package) -> Option<&'a str> {
+    log.events(&pkg.name)
+        .into_iter()
+        .rev()
+        .find(|event| event.kind == Kind::Upgrade && event.to.as_deref() == Some(&pkg.version))
+        .and_then(|event| event.from.as_deref())
+}
+
+// What `<` would do for the package.
+pub struct Plan {
+    pub name: String,
+    pub installed: String,
+    pub previous: String,       // From the log
+    pub target: Option<Cached>, // The previous version's .deb, else the newest older one
+    pub others: usize,          // Older .debs besides the target
+}
+
+impl Plan {
+    pub fn new(log: &Log, pkg: &Package) -> Option<Self> {
+        let previous = upgraded_from(log, pkg)?.to_string();
+        let mut found = cached(&archives(), pkg);
+        let at = found
+            .iter()
+            .position(|cached| cached.version == previous)
+            .unwrap_or(0);
+        let target = (!found.is_empty()).then(|| found.remove(at));
+        Some(Self {
+            name: pkg.name.clone(),
+            installed: pkg.version.clone(),
+            previous,
+            others: found.len(),
+            target,
+        })
+    }
+
+    // What the confirmation says besides the command.
+    pub fn warning(&self, target: &Cached) -> Vec<String> {
+        let mut lines = Vec::new();
+        if target.version != self.previous {
+            lines.push(format!(
+                "{} (before the upgrade) isn't cached; {} is the newest older one.",
+                self.previous, target.version
+            ));
+        }
+        match self.others {
+            0 => {}
+            1 => lines.push(format!(
+                "One older .deb is cached too, in {}.",
+                archives().display()
+            )),
+            n => lines.push(format!(
+                "{} older .debs are cached too, in {}.",
+                n,
+                archives().display()
+            )),
+        }
+        lines.push(format!(
+            "⚠ The next upgrade brings {} back unless {} is held.",
+            self.installed, self.name
+        ));
+        lines.push("  tpi offers to hold it once it is installed.".to_string());
+        lines
+    }
+}
+
+// Where Termux's repositories keep the package's builds, Debian's pool
+// layout ("libs" under lib + their fourth letter). Old versions are dropped
+// from it over time, so it may only have the current one.
+pub fn pool_url(pkg: &Package) -> String {
+    let repository = match pkg.repo.as_deref() {
+        Some("x11") => "termux-x11",
+        Some("root") => "termux-root",
+        _ => "termux-main",
+    };
+    let name = pkg.name.as_str();
+    let letter = match name.strip_prefix("lib") {
+        Some(rest) if !rest.is_empty() => &name[..4],
+        _ => &name[..name.len().min(1)],
+    };
+    format!(
+        "https://packages.termux.dev/apt/{}/pool/main/{}/{}/",
+        repository, letter, name
+    )
+}
+
+// No older .deb to go back to: why, and where one might be found.
+pub struct NoCache {
+    pub name: String,
+    pub url: String,
+    lines: Vec<String>,
+}
+
+impl NoCache {
+    pub fn new(plan: &Plan, pkg: &Package) -> Self {
+        let url = pool_url(pkg);
+        let lines = vec![
+            format!(
+                "{} was upgraded from {} to {}, but no older .deb of it is in",
+                plan.name, plan.previous, plan.installed
+            ),
+            format!("{}.", archives().display()),
+            String::new(),
+            "`pkg clean`, `apt clean` and `apt autoclean` empty that cache, and".to_string(),
+            "apt keeps nothing when configured not to.".to_string(),
+            String::new(),
+            "Termux's repository may still have older builds (often only the".to_string(),
+            "current one):".to_string(),
+            format!("  {}", url),
+            String::new(),
+            "Download the .deb for your architecture, then open it with Ctrl+o to".to_string(),
+            "check it and install it.".to_string(),
+        ];
+        Self {
+            name: plan.name.clone(),
+            url,
+            lines,
+        }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let text: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        let title = format!(
+            "Can't roll back {} (y copies the link, Esc closes)",
+            self.name
+        );
+        let popup = Paragraph::new(text)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, arch: &str, repo: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            arch: Some(arch.to_string()),
+            repo: Some(repo.to_string()),
+            description: None,
+            priority: None,
+            section: None,
+            auto_installed: false,
+        }
+    }
+
+    #[test]
+    fn versions_in_dpkg_order() {
+        // Each pair in ascending order, as `dpkg --compare-versions` has it
+        let ascending = [
+            ("1.0", "1.1"),
+            ("1.9", "1.10"),
+            ("1.0~rc1", "1.0"),
+            ("1.0~~", "1.0~"),
+            ("1.0", "1.0a"),
+            ("1.0a", "1.0+"),
+            ("1.0-1", "1.0-2"),
+            ("1.0-9", "1.0-10"),
+            ("2.0", "1:0.1"),
+            ("1:2.0", "2:1.0"),
+            ("3.3.1", "3.3.1-1"),
+            ("8.9.1", "8.10.0"),
+        ];
+        for (lower, higher) in ascending {
+            assert_eq!(
+                compare(lower, higher),
+                Ordering::Less,
+                "{} < {}",
+                lower,
+                higher
+            );
+            assert_eq!(compare(higher, lower), Ordering::Greater);
+        }
+        assert_eq!(compare("1.01", "1.1"), Ordering::Equal);
+        assert_eq!(compare("0:1.0", "1.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn file_names_split_into_name_version_arch() {
+        assert_eq!(
+            parse_file_name("curl_8.9.1_aarch64.deb"),
+            Some(("curl".into(), "8.9.1".into(), "aarch64".into()))
+        );
+        assert_eq!(
+            parse_file_name("vim_2%3a9.1.0-1_arm.deb"),
+            Some(("vim".into(), "2:9.1.0-1".into(), "arm".into()))
+        );
+        assert_eq!(parse_file_name("curl_8.9.1.deb"), None);
+        assert_eq!(parse_file_name("curl_8.9.1_aarch64_x.deb"), None);
+        assert_eq!(parse_file_name("curl_8.9.1_aarch64.tar"), None);
+    }
+
+    #[test]
+    fn cached_older_builds_newest_first() {
+        let dir = crate::tests::sandbox().join("rollback-archives");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("partial")).unwrap();
+        for file in [
+            "curl_8.7.1_aarch64.deb",
+            "curl_8.8.0_aarch64.deb",
+            "curl_8.8.0-1_all.deb",
+            "curl_8.9.1_aarch64.deb", // Installed
+            "curl_8.10.0_aarch64.deb",
+            "curl_8.6.0_arm.deb",
+            "libcurl_8.8.0_aarch64.deb",
+            "lock",
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let curl = package("curl", "8.9.1", "aarch64", "stable");
+        let versions: Vec<String> = cached(&dir, &curl)
+            .into_iter()
+            .map(|cached| cached.version)
+            .collect();
+        assert_eq!(versions, ["8.8.0-1", "8.8.0", "8.7.1"]);
+        assert!(cached(&dir.join("gone"), &curl).is_empty());
+    }
+
+    #[test]
+    fn pool_urls_by_repository_and_letter() {
+        let url = |name, repo| pool_url(&package(name, "1", "all", repo));
+        assert_eq!(
+            url("curl", "stable"),
+            "https://packages.termux.dev/apt/termux-main/pool/main/c/curl/"
+        );
+        assert_eq!(
+            url("libcurl", "stable"),
+            "https://packages.termux.dev/apt/termux-main/pool/main/libc/libcurl/"
+        );
+        assert_eq!(
+            url("lib", "x11"),
+            "https://packages.termux.dev/apt/termux-x11/pool/main/l/lib/"
+        );
+        assert_eq!(
+            url("tsu", "root"),
+            "https://packages.termux.dev/apt/termux-root/pool/main/t/tsu/"
+        );
+    }
+}
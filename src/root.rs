@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::prefix;
+
+// Termux is meant to be used as its app's own user. Under `tsu` or `su` pkg
+// leaves root-owned files in $PREFIX that the app can no longer update, pip
+// installs into root's site-packages, and $HOME is root's (tsu's .suroot),
+// so files tpi would save there aren't where the Termux user looks.
+pub fn warning(changes_allowed: bool) -> String {
+    let changes = if changes_allowed {
+        "changes are allowed by TPI_ALLOW_ROOT"
+    } else {
+        "changing packages is disabled (TPI_ALLOW_ROOT=1 allows it)"
+    };
+    format!(
+        "pkg may leave files the Termux user can't update and pip installs for root; {}",
+        changes
+    )
+}
+
+pub fn detected() -> bool {
+    // SAFETY: geteuid has no preconditions and can't fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+// Where dismissing the banner is remembered. Not under $HOME, which is root's
+// here and may change between `su` and `tsu`; only root ever reads it.
+fn marker() -> PathBuf {
+    PathBuf::from(prefix()).join("var/lib/tpi/root-warning-dismissed")
+}
+
+pub fn dismissed() -> bool {
+    marker().exists()
+}
+
+pub fn dismiss() -> io::Result<()> {
+    write_marker(&marker())
+}
+
+fn write_marker(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The effective UID as the kernel reports it, apart from geteuid.
+    fn effective_uid() -> u32 {
+        let status = fs::read_to_string("/proc/self/status").unwrap();
+        let uids = status
+            .lines()
+            .find_map(|line| line.strip_prefix("Uid:"))
+            .unwrap();
+        uids.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn detected_as_the_effective_uid_is_root() {
+        assert_eq!(detected(), effective_uid() == 0);
+    }
+
+    #[test]
+    fn dismissal_is_kept_under_prefix() {
+        let prefix = crate::tests::sandbox();
+        assert_eq!(marker(), prefix.join("var/lib/tpi/root-warning-dismissed"));
+        // The test sandbox starts dismissed, so app tests draw the same as
+        // root as they do otherwise
+        assert!(dismissed());
+    }
+
+    #[test]
+    fn dismissing_creates_the_marker_once_or_again() {
+        let dir = crate::tests::sandbox().join("root-tests");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("var/lib/tpi/root-warning-dismissed");
+        write_marker(&path).unwrap();
+        assert!(path.exists());
+        write_marker(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        // Where no directory can be made it says so
+        let blocked = dir.join("var/lib/tpi/root-warning-dismissed/marker");
+        assert!(write_marker(&blocked).is_err());
+    }
+
+    #[test]
+    fn warning_says_whether_changes_are_allowed() {
+        assert!(warning(true).ends_with("changes are allowed by TPI_ALLOW_ROOT"));
+        assert!(
+            warning(false).ends_with("changing packages is disabled (TPI_ALLOW_ROOT=1 allows it)")
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use serde_json::{json, Value};
+
+use crate::details::Details;
+use crate::doctor;
+use crate::health::{Check, Outcome};
+use crate::outdated::Outdated;
+use crate::sessions::Session;
+use crate::stats::Stats;
+use crate::{ListFilter, Package, PackageList, PackageManager};
+
+// Version of the JSON documents tpi writes, in their `schema_version` field.
+// Bump it when a field is renamed, removed or changes meaning; new fields
+// don't need a bump. Every document is built here so that can't be missed.
+pub const VERSION: u64 = 1;
+
+// `tpi health --json`.
+pub fn health(results: &[(Check, Outcome)]) -> Value {
+    let healthy = results
+        .iter()
+        .all(|(_, outcome)| matches!(outcome, Ok(findings) if findings.is_empty()));
+    let checks: Vec<Value> = results
+        .iter()
+        .map(|(check, outcome)| {
+            let (status, findings, error) = match outcome {
+                Ok(findings) if findings.is_empty() => ("pass", findings.as_slice(), None),
+                Ok(findings) => ("fail", findings.as_slice(), None),
+                Err(err) => ("error", &[][..], Some(err)),
+            };
+            json!({
+                "id": check.id(),
+                "label": check.label(),
+                "status": status,
+                "error": error,
+                "findings": findings
+                    .iter()
+                    .map(|finding| json!({
+                        "manager": finding.manager.name(),
+                        "package": finding.package,
+                        "detail": finding.detail,
+                        "other": finding.other.as_ref().map(|(manager, package)| json!({
+                            "manager": manager.name(),
+                            "package": package,
+                        })),
+                    }))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json!({
+        "schema_version": VERSION,
+        "healthy": healthy,
+        "checks": checks,
+    })
+}
+
+// `tpi doctor --json`.
+pub fn doctor(items: &[doctor::Item]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "ok": !items.iter().any(|item| item.level == doctor::Level::Fail),
+        "items": items
+            .iter()
+            .map(|item| json!({
+                "section": item.section,
+                "name": item.name,
+                "status": item.level.name(),
+                "detail": item.detail,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// `tpi serve` /packages: every package of each manager asked for. A manager
+// whose listing failed has an `error` and no packages.
+pub fn packages(lists: &[&PackageList]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "managers": lists
+            .iter()
+            .map(|list| json!({
+                "manager": list.package_manager.name(),
+                "error": list.load_error.as_ref().map(ToString::to_string),
+                "packages": list
+                    .items
+                    .iter()
+                    .map(|pkg| package_entry(list, pkg))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn package_entry(list: &PackageList, pkg: &Package) -> Value {
+    json!({
+        "name": pkg.name,
+        "version": pkg.version,
+        "arch": pkg.arch,
+        "repo": pkg.repo,
+        "description": pkg.description,
+        "priority": pkg.priority,
+        "section": pkg.section,
+        "auto_installed": pkg.auto_installed,
+        "size_kib": list.sizes.get(&pkg.name),
+        "held": list.held.contains(&pkg.name),
+    })
+}
+
+// `e` and `--export` to a .json file. A bare array of what asset trackers
+// import, so unlike the other documents it has no schema_version.
+pub fn inventory(packages: &[(PackageManager, &Package)]) -> Value {
+    Value::Array(
+        packages
+            .iter()
+            .map(|(manager, pkg)| {
+                json!({
+                    "name": pkg.name,
+                    "version": pkg.version,
+                    "manager": manager.name(),
+                })
+            })
+            .collect(),
+    )
+}
+
+// A manager's list: `tpi list --format json`, and the last one cached for
+// the next start ($TPI_CACHE_DIR).
+pub fn list_cache(manager: PackageManager, packages: &[Package]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "manager": manager.name(),
+        "packages": packages
+            .iter()
+            .map(|pkg| json!({
+                "name": pkg.name,
+                "version": pkg.version,
+                "arch": pkg.arch,
+                "repo": pkg.repo,
+                "description": pkg.description,
+                "priority": pkg.priority,
+                "section": pkg.section,
+                "auto_installed": pkg.auto_installed,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// `tpi count --format json`: packages per manager, or why it couldn't list.
+pub fn counts(counts: &[(PackageManager, Result<usize, String>)]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "managers": counts
+            .iter()
+            .map(|(manager, count)| json!({
+                "manager": manager.name(),
+                "count": count.as_ref().ok(),
+                "error": count.as_ref().err(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// `tpi stats`: what the L view shows. Counts that weren't checked are null.
+pub fn stats(stats: &Stats) -> Value {
+    let counts = |rows: &[(&str, u64)]| {
+        rows.iter()
+            .map(|(label, count)| json!({ "label": label, "count": count }))
+            .collect::<Vec<_>>()
+    };
+    let dated = |rows: &[(String, String)]| {
+        rows.iter()
+            .map(|(date, name)| json!({ "name": name, "date": date }))
+            .collect::<Vec<_>>()
+    };
+    json!({
+        "schema_version": VERSION,
+        "manager": stats.manager.name(),
+        "managers": stats
+            .managers
+            .iter()
+            .map(|(manager, count, cached)| json!({
+                "manager": manager.name(),
+                "count": count,
+                "cached": cached,
+            }))
+            .collect::<Vec<_>>(),
+        "sizes": counts(&stats.sizes),
+        "unknown_size": stats.unknown_size,
+        "reasons": counts(&stats.reasons),
+        "repositories": stats
+            .origins
+            .iter()
+            .map(|(repo, count)| json!({ "repo": repo, "count": count }))
+            .collect::<Vec<_>>(),
+        "oldest": dated(&stats.oldest),
+        "newest": dated(&stats.newest),
+        "monthly_activity": stats.activity,
+        "upgradable": stats.upgradable,
+        "held": stats.held,
+        "broken": stats.broken,
+    })
+}
+
+// `tpi serve` /package/<manager>/<name>: the list entry plus every field of
+// the manager's `show` output, in its order.
+pub fn package(list: &PackageList, pkg: &Package, details: &Details) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "manager": list.package_manager.name(),
+        "package": package_entry(list, pkg),
+        "fields": details
+            .fields
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+// `tpi serve` /outdated: per manager that can tell, what has a newer version.
+pub fn outdated(results: &[(PackageManager, Result<Vec<Outdated>, String>)]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "managers": results
+            .iter()
+            .map(|(manager, result)| {
+                let (packages, error) = match result {
+                    Ok(packages) => (packages.as_slice(), None),
+                    Err(err) => (&[][..], Some(err)),
+                };
+                json!({
+                    "manager": manager.name(),
+                    "error": error,
+                    "packages": packages
+                        .iter()
+                        .map(|pkg| json!({
+                            "name": pkg.name,
+                            "installed": pkg.installed,
+                            "candidate": pkg.candidate,
+                        }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+// The saved sessions file, $TPI_SESSIONS.
+pub fn sessions(sessions: &[Session]) -> Value {
+    json!({
+        "schema_version": VERSION,
+        "sessions": sessions
+            .iter()
+            .map(|session| json!({
+                "name": session.name,
+                "saved": session.saved,
+                "manager": session.manager.name(),
+                "group_by": session.group_by.id(),
+                "filter": session.filter.as_ref().map(filter),
+                "search": session.search,
+                "marks": session
+                    .marks
+                    .iter()
+                    .map(|(manager, package)| json!({
+                        "manager": manager.name(),
+                        "package": package,
+                    }))
+                    .collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn filter(filter: &ListFilter) -> Value {
+    match filter {
+        ListFilter::Repo { label, suites } => {
+            let mut suites: Vec<&String> = suites.iter().collect();
+            suites.sort();
+            json!({ "repo": label, "suites": suites })
+        }
+        ListFilter::Built(build) => json!({ "built": build.id() }),
+        ListFilter::Priority(priority) => json!({ "priority": priority }),
+        ListFilter::Section(section) => json!({ "section": section }),
+        ListFilter::Drift(state) => json!({ "drift": state.label() }),
+        ListFilter::Upgradable => json!({ "upgradable": true }),
+        ListFilter::Orphans => json!({ "orphans": true }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::failures::{Failure, LoadError};
+    use crate::health::Finding;
+    use crate::tests::{sandbox, Mock};
+    use crate::{config, Listing};
+
+    // Each document is compared with tests/fixtures/schema/<name>.json. A
+    // document that lost or renamed a field fails until VERSION is bumped,
+    // so clients are never broken by accident; any other change only needs
+    // the file updated.
+    fn golden(name: &str, document: &Value) {
+        let path = format!(
+            "{}/tests/fixtures/schema/{}.json",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        let text = serde_json::to_string_pretty(document).unwrap() + "\n";
+        // What clients read back is what was written
+        let parsed: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(&parsed, document);
+        if std::env::var_os("TPI_UPDATE_GOLDEN").is_some() {
+            fs::write(&path, &text).unwrap();
+            return;
+        }
+        let expected: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        if &expected == document {
+            return;
+        }
+        let lost: Vec<String> = fields(&expected)
+            .difference(&fields(document))
+            .cloned()
+            .collect();
+        if !lost.is_empty() && expected["schema_version"] == document["schema_version"] {
+            panic!(
+                "{} lost {:?} at schema_version {}: bump schema::VERSION, then update {}",
+                name, lost, VERSION, path
+            );
+        }
+        panic!(
+            "{} changed; if no field changed meaning, update {} \
+             (TPI_UPDATE_GOLDEN=1 cargo test rewrites it):\n{}",
+            name, path, text
+        );
+    }
+
+    // Every field path in `value`, array items merged: "managers[].error".
+    fn fields(value: &Value) -> BTreeSet<String> {
+        let mut found = BTreeSet::new();
+        collect(value, "", &mut found);
+        found
+    }
+
+    fn collect(value: &Value, path: &str, found: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    let path = format!("{}.{}", path, key);
+                    found.insert(path.clone());
+                    collect(value, &path, found);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    collect(item, &format!("{}[]", path), found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apt_list() -> PackageList {
+        sandbox();
+        let mock = Mock::new().reply("apt-mark showhold", "openssl\n").reply(
+            "dpkg-query -W -f=${Package}\t${Installed-Size}",
+            "bash\t4032\ncurl\t512\nlibssl3\t7040\nopenssl\t2304\nzlib\t196\n",
+        );
+        PackageList::load(PackageManager::Apt, Arc::new(mock), config::Profile::Full)
+    }
+
+    #[test]
+    fn packages_document() {
+        let apt = apt_list();
+        let failure = Failure::NotInstalled {
+            program: "pip".to_string(),
+        };
+        let pip = PackageList::with_listing(
+            PackageManager::Pip,
+            Arc::new(Mock::new()),
+            Listing::default(),
+            Some(LoadError::new(PackageManager::Pip, failure)),
+        );
+        golden("packages", &packages(&[&apt, &pip]));
+    }
+
+    #[test]
+    fn package_document() {
+        let list = apt_list();
+        let pkg = list.items.iter().find(|pkg| pkg.name == "curl").unwrap();
+        let details = Details::parse(
+            "Package: curl\nVersion: 8.9.1\nDepends: libcurl (= 8.9.1), openssl\n\
+             Homepage: https://curl.se/\nDescription: Command line tool for transferring data with URL syntax\n",
+        );
+        golden("package", &package(&list, pkg, &details));
+    }
+
+    #[test]
+    fn outdated_document() {
+        let results = [
+            (
+                PackageManager::Apt,
+                Ok(vec![Outdated {
+                    name: "curl".to_string(),
+                    installed: "8.9.1".to_string(),
+                    candidate: "8.10.0".to_string(),
+                }]),
+            ),
+            (PackageManager::Pip, Ok(Vec::new())),
+            (
+                PackageManager::Npm,
+                Err("npm outdated failed (exit 1)".to_string()),
+            ),
+        ];
+        golden("outdated", &outdated(&results));
+    }
+
+    #[test]
+    fn health_document() {
+        let results = [
+            (Check::BrokenState, Ok(Vec::new())),
+            (
+                Check::FileConflicts,
+                Ok(vec![Finding {
+                    manager: PackageManager::Pip,
+                    package: "httpie".to_string(),
+                    detail: "bin/http is also installed by httpie (apt)".to_string(),
+                    other: Some((PackageManager::Apt, "httpie".to_string())),
+                }]),
+            ),
+            (
+                Check::Orphans,
+                Ok(vec![Finding {
+                    manager: PackageManager::Apt,
+                    package: "libpng".to_string(),
+                    detail: "automatically installed and no longer needed".to_string(),
+                    other: None,
+                }]),
+            ),
+            (
+                Check::StrayFiles,
+                Err("pip: couldn't read RECORD files".to_string()),
+            ),
+        ];
+        let document = health(&results);
+        assert_eq!(document["healthy"], false);
+        golden("health", &document);
+        assert_eq!(health(&results[..1])["healthy"], true);
+    }
+
+    #[test]
+    fn lost_fields_are_found() {
+        let before = json!({ "managers": [{ "manager": "apt", "error": null }] });
+        let after = json!({ "managers": [{ "manager": "apt" }] });
+        let lost: Vec<String> = fields(&before)
+            .difference(&fields(&after))
+            .cloned()
+            .collect();
+        assert_eq!(lost, [".managers[].error"]);
+    }
+}
@@ -0,0 +1,115 @@
+use ratatui::prelude::*;
+
+// A query applied to both panes: package names in the list and the text of
+// the detail pane. Matching is case-insensitive.
+pub struct Search {
+    pub query: String,
+    pub editing: bool, // Typing into the query line
+    detail_package: String,
+    detail_match: usize, // Current occurrence in the detail pane
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            detail_package: String::new(),
+            detail_match: 0,
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        !self.query.is_empty() && text.to_lowercase().contains(&self.query.to_lowercase())
+    }
+
+    // Byte ranges of every occurrence in `text`.
+    pub fn occurrences(&self, text: &str) -> Vec<(usize, usize)> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+        let haystack = text.to_lowercase();
+        let needle = self.query.to_lowercase();
+        // Lowercasing can change byte lengths outside ASCII; only use ranges
+        // that still fall on character boundaries of the original.
+        haystack
+            .match_indices(&needle)
+            .map(|(start, found)| (start, start + found.len()))
+            .filter(|(start, end)| text.is_char_boundary(*start) && text.is_char_boundary(*end))
+            .collect()
+    }
+
+    // Index of the current detail occurrence for this package; moving to
+    // another package starts again from the first one.
+    pub fn detail_match(&self, package: &str) -> usize {
+        if self.detail_package == package {
+            self.detail_match
+        } else {
+            0
+        }
+    }
+
+    // Steps the current detail occurrence, wrapping around `count`.
+    pub fn step_detail(&mut self, package: &str, count: usize, forward: bool) {
+        if count == 0 {
+            return;
+        }
+        let current = self.detail_match(package) % count;
+        self.detail_match = if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        };
+        self.detail_package = package.to_string();
+    }
+
+    // `text` as spans with occurrences highlighted. `current` is the global
+    // index of the first occurrence in this text that should stand out.
+    pub fn highlight(&self, text: &str, base: Style, current: Option<usize>) -> Vec<Span<'static>> {
+        let mut spans = Vec::new();
+        let mut last = 0;
+        for (i, (start, end)) in self.occurrences(text).into_iter().enumerate() {
+            if start > last {
+                spans.push(Span::styled(text[last..start].to_string(), base));
+            }
+            let style = if current == Some(i) {
+                base.fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                base.fg(Color::Black).bg(Color::Yellow)
+            };
+            spans.push(Span::styled(text[start..end].to_string(), style));
+            last = end;
+        }
+        if last < text.len() {
+            spans.push(Span::styled(text[last..].to_string(), base));
+        }
+        spans
+    }
+
+    // The detail text with occurrences highlighted, and the line holding the
+    // current occurrence so the pane can scroll to it.
+    pub fn highlight_lines(
+        &self,
+        text: &str,
+        current: usize,
+    ) -> (Vec<Line<'static>>, Option<usize>) {
+        let mut seen = 0;
+        let mut current_line = None;
+        let lines = text
+            .lines()
+            .enumerate()
+            .map(|(number, line)| {
+                let count = self.occurrences(line).len();
+                let local = (current >= seen && current < seen + count).then(|| current - seen);
+                if local.is_some() {
+                    current_line = Some(number);
+                }
+                seen += count;
+                Line::from(self.highlight(line, Style::default(), local))
+            })
+            .collect();
+        (lines, current_line)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::Package;
+
+// Debian's archive sections (policy 2.4) by what they hold. Termux uses the
+// same names; one missing here is shown as it is.
+const TITLES: [(&str, &str); 57] = [
+    ("admin", "Administration utilities"),
+    ("cli-mono", "Mono and the CLI"),
+    ("comm", "Communication programs"),
+    ("database", "Databases"),
+    ("debug", "Debug symbols"),
+    ("devel", "Development"),
+    ("doc", "Documentation"),
+    ("editors", "Editors"),
+    ("education", "Education"),
+    ("electronics", "Electronics"),
+    ("embedded", "Embedded software"),
+    ("fonts", "Fonts"),
+    ("games", "Games"),
+    ("gnome", "GNOME"),
+    ("gnu-r", "GNU R"),
+    ("gnustep", "GNUstep"),
+    ("graphics", "Graphics"),
+    ("hamradio", "Ham radio"),
+    ("haskell", "Haskell"),
+    ("httpd", "Web servers"),
+    ("interpreters", "Interpreters"),
+    ("introspection", "GObject introspection"),
+    ("java", "Java"),
+    ("javascript", "JavaScript"),
+    ("kde", "KDE"),
+    ("kernel", "Kernels"),
+    ("libdevel", "Library development files"),
+    ("libs", "Libraries"),
+    ("lisp", "Lisp"),
+    ("localization", "Localization"),
+    ("mail", "Mail"),
+    ("math", "Mathematics"),
+    ("metapackages", "Metapackages"),
+    ("misc", "Miscellaneous"),
+    ("net", "Network"),
+    ("news", "Newsgroups"),
+    ("ocaml", "OCaml"),
+    ("oldlibs", "Old libraries"),
+    ("otherosfs", "Other systems' file systems"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("python", "Python"),
+    ("ruby", "Ruby"),
+    ("rust", "Rust"),
+    ("science", "Science"),
+    ("shells", "Shells"),
+    ("sound", "Sound"),
+    ("tasks", "Tasks"),
+    ("tex", "TeX"),
+    ("text", "Text processing"),
+    ("utils", "Utilities"),
+    ("vcs", "Version control"),
+    ("video", "Video"),
+    ("web", "Web software"),
+    ("x11", "X Window System"),
+    ("xfce", "Xfce"),
+    ("zope", "Zope"),
+];
+
+// Where packages without a Section go, as many Termux-built ones are.
+pub const UNSECTIONED: &str = "unsectioned";
+
+// The package's section without its archive area: "contrib/net" is "net".
+pub fn of(pkg: &Package) -> &str {
+    match pkg.section.as_deref().map(str::trim) {
+        Some(section) if !section.is_empty() => section.rsplit('/').next().unwrap_or(section),
+        _ => UNSECTIONED,
+    }
+}
+
+// "Network (net)", or the name alone when Debian has no such section.
+pub fn title(section: &str) -> String {
+    if section == UNSECTIONED {
+        return format!("({})", UNSECTIONED);
+    }
+    match TITLES.iter().find(|(name, _)| *name == section) {
+        Some((_, title)) => format!("{} ({})", title, section),
+        None => section.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_section(section: Option<&str>) -> Package {
+        Package {
+            name: "curl".to_string(),
+            version: "8.9.1".to_string(),
+            arch: None,
+            repo: None,
+            description: None,
+            priority: None,
+            section: section.map(String::from),
+            auto_installed: false,
+        }
+    }
+
+    #[test]
+    fn sections_without_their_archive_area() {
+        assert_eq!(of(&in_section(Some("net"))), "net");
+        assert_eq!(of(&in_section(Some("contrib/net"))), "net");
+        assert_eq!(of(&in_section(Some(" libs "))), "libs");
+        assert_eq!(of(&in_section(Some(""))), UNSECTIONED);
+        assert_eq!(of(&in_section(None)), UNSECTIONED);
+    }
+
+    #[test]
+    fn titles_name_debians_sections() {
+        assert_eq!(title("net"), "Network (net)");
+        assert_eq!(title("libdevel"), "Library development files (libdevel)");
+        assert_eq!(title("termux-extra"), "termux-extra");
+        assert_eq!(title(UNSECTIONED), "(unsectioned)");
+        // Kept sorted, so a missing one is easy to spot
+        assert!(TITLES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::commands::{self, Runner};
+use crate::health::{self, Check};
+use crate::{config, lock, log, outdated, schema, PackageList, PackageManager};
+
+// Lists are read again once they are older than this, so a dashboard polling
+// every few seconds doesn't run `apt list` on every request.
+const FRESH_FOR: Duration = Duration::from_secs(30);
+
+// Slow or silent clients are dropped rather than holding up the others.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Read-only JSON over HTTP on the loopback interface, for dashboards. Every
+// response is one of the schema documents; nothing here changes packages, and
+// the process writes no state files either (not even the version history).
+// Each connection is answered on its own thread, so a slow client or a
+// health check doesn't hold up the others.
+pub struct Server {
+    runner: Arc<dyn Runner>,
+    profile: config::Profile,
+    managers: Vec<PackageManager>, // Enabled and installed
+    lists: Mutex<HashMap<PackageManager, PackageList>>,
+    token: Option<String>,
+}
+
+impl Server {
+    pub fn new(runner: Arc<dyn Runner>, profile: config::Profile) -> Self {
+        lock::forbid_writes();
+        Self {
+            runner,
+            profile,
+            managers: config::managers()
+                .enabled
+                .into_iter()
+                .filter(|manager| commands::is_available(*manager))
+                .collect(),
+            lists: Mutex::new(HashMap::new()),
+            token: config::serve_token(),
+        }
+    }
+
+    pub fn listen(self, port: u16) -> io::Result<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let port = listener.local_addr()?.port();
+        eprintln!(
+            "tpi serve: listening on http://127.0.0.1:{}{}",
+            port,
+            if self.token.is_some() {
+                " (X-Tpi-Token required)"
+            } else {
+                ""
+            }
+        );
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let server = server.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = server.handle(stream, port) {
+                            log::debug(&format!("serve: {}", err));
+                        }
+                    });
+                }
+                Err(err) => log::debug(&format!("serve: accept: {}", err)),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle(&self, stream: TcpStream, port: u16) -> io::Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        let (mut host, mut token) = (None, None);
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                let name = name.trim();
+                if name.eq_ignore_ascii_case("host") {
+                    host = Some(value.trim().to_string());
+                } else if name.eq_ignore_ascii_case("x-tpi-token") {
+                    token = Some(value.trim().to_string());
+                }
+            }
+        }
+
+        let mut words = request.split_whitespace();
+        let (method, target) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+        let (status, body) = if method != "GET" {
+            (405, error("only GET is served"))
+        } else if !is_loopback_host(host.as_deref(), port) {
+            (403, error("the Host header must be 127.0.0.1 or localhost"))
+        } else if !self
+            .token
+            .as_deref()
+            .is_none_or(|expected| same_token(token.as_deref().unwrap_or(""), expected))
+        {
+            (401, error("missing or wrong X-Tpi-Token header"))
+        } else {
+            self.route(target)
+        };
+        log::debug(&format!("serve: {} {} -> {}", method, target, status));
+        respond(&stream, status, &body)
+    }
+
+    fn route(&self, target: &str) -> (u16, Value) {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let path = decode(path);
+        let segments: Vec<&str> = path.trim_matches('/').splitn(3, '/').collect();
+        match segments.as_slice() {
+            ["packages"] => {
+                let manager = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("manager="))
+                    .map(decode);
+                match manager.as_deref() {
+                    None | Some("") => (200, self.packages(&self.managers)),
+                    Some(name) => match self.manager(name) {
+                        Ok(manager) => (200, self.packages(&[manager])),
+                        Err(err) => (404, error(&err)),
+                    },
+                }
+            }
+            ["package", manager, name] => match self.manager(manager) {
+                Ok(manager) => self.package(manager, name),
+                Err(err) => (404, error(&err)),
+            },
+            ["outdated"] => (200, self.outdated()),
+            ["health"] => (200, self.health()),
+            _ => (
+                404,
+                error(
+                    "unknown path: try /packages, /package/<manager>/<name>, /outdated or /health",
+                ),
+            ),
+        }
+    }
+
+    fn manager(&self, name: &str) -> Result<PackageManager, String> {
+        match PackageManager::from_name(name) {
+            Some(manager) if self.managers.contains(&manager) => Ok(manager),
+            Some(_) => Err(format!("{} is not enabled or not installed", name)),
+            None => Err(format!("unknown manager '{}'", name)),
+        }
+    }
+
+    // The lists of `managers`, each read again when it is older than
+    // FRESH_FOR. Requests for lists wait for each other, so one is never
+    // read twice at once.
+    fn lists(
+        &self,
+        managers: &[PackageManager],
+    ) -> MutexGuard<'_, HashMap<PackageManager, PackageList>> {
+        let mut lists = self.lists.lock().unwrap_or_else(|err| err.into_inner());
+        for manager in managers {
+            let stale = lists
+                .get(manager)
+                .is_none_or(|list| list.loaded_at.elapsed() > FRESH_FOR);
+            if stale {
+                let list = PackageList::load(*manager, self.runner.clone(), self.profile);
+                lists.insert(*manager, list);
+            }
+        }
+        lists
+    }
+
+    fn packages(&self, managers: &[PackageManager]) -> Value {
+        let lists = self.lists(managers);
+        let lists: Vec<&PackageList> = managers
+            .iter()
+            .filter_map(|manager| lists.get(manager))
+            .collect();
+        schema::packages(&lists)
+    }
+
+    fn package(&self, manager: PackageManager, name: &str) -> (u16, Value) {
+        let lists = self.lists(&[manager]);
+        let list = &lists[&manager];
+        match list.items.iter().find(|pkg| pkg.name == name) {
+            Some(pkg) => (200, schema::package(list, pkg, &list.package_details(pkg))),
+            None => (
+                404,
+                error(&format!(
+                    "{} is not installed with {}",
+                    name,
+                    manager.name()
+                )),
+            ),
+        }
+    }
+
+    fn outdated(&self) -> Value {
+        let results: Vec<_> = self
+            .managers
+            .iter()
+            .filter_map(|manager| {
+                outdated::load(self.runner.as_ref(), *manager).map(|result| (*manager, result))
+            })
+            .collect();
+        schema::outdated(&results)
+    }
+
+    fn health(&self) -> Value {
+        schema::health(&health::run_all(self.runner.clone(), &Check::ALL))
+    }
+
+    // `--once`: what the endpoints without parameters would answer, as
+    // packages.json, outdated.json and health.json in `dir`.
+    pub fn dump(&self, dir: &Path) -> io::Result<Vec<String>> {
+        fs::create_dir_all(dir)?;
+        let documents = [
+            ("packages.json", self.packages(&self.managers)),
+            ("outdated.json", self.outdated()),
+            ("health.json", self.health()),
+        ];
+        let mut written = Vec::new();
+        for (name, document) in documents {
+            let path = dir.join(name);
+            let text = serde_json::to_string_pretty(&document).unwrap_or_default();
+            fs::write(&path, text + "\n")?;
+            written.push(path.display().to_string());
+        }
+        Ok(written)
+    }
+}
+
+// Only what a client on this device sends. A web page the phone's browser
+// opens could otherwise read the inventory through a name of its own that it
+// points at 127.0.0.1 (DNS rebinding): its requests carry that name instead.
+fn is_loopback_host(host: Option<&str>, port: u16) -> bool {
+    let Some((name, host_port)) = host.and_then(|host| host.rsplit_once(':')) else {
+        return false;
+    };
+    host_port.parse() == Ok(port) && (name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost"))
+}
+
+// Compares every byte whatever the first difference, so the time taken
+// doesn't tell a client how much of its guess was right.
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn error(message: &str) -> Value {
+    json!({ "schema_version": schema::VERSION, "error": message })
+}
+
+fn respond(mut stream: &TcpStream, status: u16, body: &Value) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    let body = serde_json::to_string_pretty(body).unwrap_or_default() + "\n";
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// %XX escapes, e.g. npm's @scope%2Fname; anything malformed is kept as is.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_loopback_names_on_our_port() {
+        for host in ["127.0.0.1:7377", "localhost:7377", "LocalHost:7377"] {
+            assert!(is_loopback_host(Some(host), 7377), "{}", host);
+        }
+        for host in [
+            None,
+            Some("127.0.0.1"),
+            Some("localhost:8080"),
+            Some("attacker.example:7377"),
+            Some("localhost.attacker.example:7377"),
+            Some("127.0.0.1.nip.io:7377"),
+        ] {
+            assert!(!is_loopback_host(host, 7377), "{:?}", host);
+        }
+    }
+
+    #[test]
+    fn tokens_match_whole() {
+        assert!(same_token("s3cret", "s3cret"));
+        assert!(!same_token("s3cre", "s3cret"));
+        assert!(!same_token("s3cret!", "s3cret"));
+        assert!(!same_token("S3cret", "s3cret"));
+        assert!(!same_token("", "s3cret"));
+    }
+}
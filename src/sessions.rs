@@ -0,0 +1,307 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ratatui::{
+    prelude::*,
+    widgets::{block, Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+use serde_json::Value;
+
+use crate::grouping::{GroupBy, PRIORITIES};
+use crate::wheels::Build;
+use crate::{config, format_date, format_time, i18n, lock, schema, ListFilter, PackageManager};
+
+// Saving more than this many drops the oldest.
+pub const MAX_SESSIONS: usize = 20;
+
+// A named working set: which list was shown, how, and what was marked.
+#[derive(Clone)]
+pub struct Session {
+    pub name: String,
+    pub saved: u64,
+    pub manager: PackageManager,
+    pub group_by: GroupBy,
+    pub filter: Option<ListFilter>,
+    pub search: Option<String>,
+    pub marks: Vec<(PackageManager, String)>,
+}
+
+// $TPI_SESSIONS, or $XDG_DATA_HOME/tpi/sessions.json (~/.local/share by
+// default).
+pub fn path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TPI_SESSIONS").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".local/share")))?;
+    Some(data.join("tpi").join("sessions.json"))
+}
+
+// Saved sessions, newest first. A missing file is no sessions; an unreadable
+// one is an error, so saving can't overwrite what is in it.
+pub fn load() -> Result<Vec<Session>, String> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+    let json: Value = serde_json::from_str(&text)
+        .map_err(|err| format!("{}: not a sessions file ({})", path.display(), err))?;
+    let mut sessions: Vec<Session> = json
+        .get("sessions")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("{}: no \"sessions\" list", path.display()))?
+        .iter()
+        .filter_map(parse)
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.saved));
+    Ok(sessions)
+}
+
+fn save(sessions: &[Session]) -> io::Result<()> {
+    let Some(path) = path() else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no location: set TPI_SESSIONS, XDG_DATA_HOME or HOME",
+        ));
+    };
+    let text = serde_json::to_string_pretty(&schema::sessions(sessions)).unwrap_or_default();
+    lock::write_atomic(&path, &(text + "\n"))
+}
+
+// Entries that don't make sense any more (an unknown manager, say) are
+// skipped rather than failing the whole file.
+fn parse(entry: &Value) -> Option<Session> {
+    let text = |key: &str| entry.get(key).and_then(Value::as_str);
+    let filter = match entry.get("filter") {
+        Some(filter) if !filter.is_null() => Some(parse_filter(filter)?),
+        _ => None,
+    };
+    let marks = entry
+        .get("marks")
+        .and_then(Value::as_array)
+        .map(|marks| {
+            marks
+                .iter()
+                .filter_map(|mark| {
+                    let manager = mark.get("manager")?.as_str()?;
+                    let package = mark.get("package")?.as_str()?;
+                    Some((PackageManager::from_name(manager)?, package.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Session {
+        name: text("name")?.to_string(),
+        saved: entry.get("saved").and_then(Value::as_u64).unwrap_or(0),
+        manager: PackageManager::from_name(text("manager")?)?,
+        group_by: text("group_by")
+            .and_then(GroupBy::from_id)
+            .unwrap_or(GroupBy::None),
+        filter,
+        search: text("search").map(String::from),
+        marks,
+    })
+}
+
+fn parse_filter(filter: &Value) -> Option<ListFilter> {
+    if let Some(label) = filter.get("repo").and_then(Value::as_str) {
+        let suites = filter
+            .get("suites")?
+            .as_array()?
+            .iter()
+            .filter_map(|suite| suite.as_str().map(String::from))
+            .collect();
+        return Some(ListFilter::Repo {
+            label: label.to_string(),
+            suites,
+        });
+    }
+    if let Some(build) = filter.get("built").and_then(Value::as_str) {
+        return Build::from_id(build).map(ListFilter::Built);
+    }
+    if let Some(section) = filter.get("section").and_then(Value::as_str) {
+        return Some(ListFilter::Section(section.to_string()));
+    }
+    let priority = filter.get("priority")?.as_str()?;
+    PRIORITIES
+        .into_iter()
+        .find(|known| *known == priority)
+        .map(ListFilter::Priority)
+}
+
+// The sessions popup: the saved sessions, newest first, and a name line while
+// saving the current one.
+pub struct SessionsView {
+    pub sessions: Vec<Session>,
+    pub state: ListState,
+    pub naming: Option<String>, // Name being typed for the current session
+    error: Option<String>,      // Why the file couldn't be read
+}
+
+impl SessionsView {
+    pub fn load() -> Self {
+        let (sessions, error) = match load() {
+            Ok(sessions) => (sessions, None),
+            Err(err) => (Vec::new(), Some(err)),
+        };
+        let mut state = ListState::default();
+        if !sessions.is_empty() {
+            state.select(Some(0));
+        }
+        Self {
+            sessions,
+            state,
+            naming: None,
+            error,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.sessions.len()));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            let len = self.sessions.len();
+            self.state.select(Some((i + len - 1) % len));
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Session> {
+        self.state.selected().and_then(|i| self.sessions.get(i))
+    }
+
+    // Saves `session`, replacing one of the same name. Returns the names of
+    // sessions dropped to stay within MAX_SESSIONS.
+    pub fn add(&mut self, mut session: Session) -> Result<Vec<String>, String> {
+        if let Some(err) = &self.error {
+            return Err(i18n::fill("session.unreadable", &[("error", err)]));
+        }
+        session.saved = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut sessions = self.sessions.clone();
+        sessions.retain(|saved| saved.name != session.name);
+        sessions.insert(0, session);
+        let dropped = sessions
+            .split_off(MAX_SESSIONS.min(sessions.len()))
+            .into_iter()
+            .map(|session| session.name)
+            .collect();
+        save(&sessions).map_err(|err| i18n::fill("session.save_failed", &[("error", &err)]))?;
+        self.sessions = sessions;
+        self.state.select(Some(0));
+        Ok(dropped)
+    }
+
+    pub fn delete_selected(&mut self) -> Result<String, String> {
+        let Some(i) = self.state.selected().filter(|&i| i < self.sessions.len()) else {
+            return Err(i18n::text("session.no_selection").to_string());
+        };
+        let mut sessions = self.sessions.clone();
+        let removed = sessions.remove(i);
+        save(&sessions).map_err(|err| i18n::fill("session.save_failed", &[("error", &err)]))?;
+        self.sessions = sessions;
+        self.state.select(match self.sessions.len() {
+            0 => None,
+            len => Some(i.min(len - 1)),
+        });
+        Ok(removed.name)
+    }
+
+    // `message` is the outcome of the last action, shown in the bottom border.
+    pub fn render(&mut self, f: &mut Frame, area: Rect, message: Option<&str>) {
+        let title = if self.naming.is_some() {
+            i18n::text("session.naming")
+        } else {
+            i18n::text("session.title")
+        };
+        let mut block = Block::default().title(title).borders(Borders::ALL);
+        if let Some(message) = message {
+            block = block.title(block::Title::from(message).position(block::Position::Bottom));
+        }
+        f.render_widget(Clear, area);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+        let footer = match (&self.naming, &self.error) {
+            (Some(name), _) => Line::from(i18n::fill("session.name", &[("name", name)])),
+            (None, Some(err)) => Line::styled(err.clone(), Style::default().fg(Color::Red)),
+            (None, None) => Line::styled(
+                i18n::fill(
+                    "session.saved",
+                    &[("count", &self.sessions.len()), ("max", &MAX_SESSIONS)],
+                ),
+                Style::default().fg(Color::DarkGray),
+            ),
+        };
+        f.render_widget(Paragraph::new(footer), layout[1]);
+
+        if self.sessions.is_empty() {
+            f.render_widget(Paragraph::new(i18n::text("session.none")), layout[0]);
+            return;
+        }
+        let width = self
+            .sessions
+            .iter()
+            .map(|session| session.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .sessions
+            .iter()
+            .map(|session| {
+                let mut about = vec![session.manager.name().to_string()];
+                if !session.marks.is_empty() {
+                    about.push(i18n::fill(
+                        "session.marked",
+                        &[("count", &session.marks.len())],
+                    ));
+                }
+                if let Some(filter) = &session.filter {
+                    about.push(filter.label());
+                }
+                if session.group_by != GroupBy::None {
+                    about.push(i18n::fill(
+                        "session.grouped",
+                        &[("group", &session.group_by.label().to_lowercase())],
+                    ));
+                }
+                if let Some(query) = &session.search {
+                    about.push(format!("'{}'", query));
+                }
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<width$}  ", session.name, width = width)),
+                    Span::styled(
+                        format!(
+                            "{} {}  ",
+                            format_date(session.saved),
+                            &format_time(session.saved)[..5]
+                        ),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(about.join(", ")),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        f.render_stateful_widget(list, layout[0], &mut self.state);
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands::Runner;
+use crate::health::{Finding, Outcome};
+use crate::{filelists, names, which, PackageManager};
+
+// A package that installs a command into a directory on PATH.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Provider {
+    pub manager: PackageManager, // Apt stands for either dpkg manager
+    pub package: String,
+    pub path: PathBuf,
+}
+
+impl Provider {
+    // "httpie (pip)"
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.package, self.manager.name())
+    }
+}
+
+// A command installed by more than one package, where PATH decides which
+// runs. `winners` is one package, or several sharing the same file: then the
+// last to install it wins, which only the file conflict check can point at.
+#[derive(Clone, Debug)]
+pub struct Shadowing {
+    pub command: String,
+    pub winners: Vec<Provider>,
+    pub shadowed: Vec<Provider>, // Earliest on PATH first
+}
+
+impl Shadowing {
+    pub fn winner_label(&self) -> String {
+        let labels: Vec<String> = self.winners.iter().map(Provider::label).collect();
+        labels.join(" or ")
+    }
+}
+
+// Which of `providers` runs as `command` given the PATH directories `dirs`,
+// in order, and which are hidden behind it. A package counts once, at its
+// earliest directory; a directory listed twice counts where it comes first;
+// providers outside every directory are out of reach and left out (the
+// stray-file check flags pip's). None unless some package is shadowed.
+pub fn resolve(command: &str, providers: &[Provider], dirs: &[PathBuf]) -> Option<Shadowing> {
+    let mut placed: Vec<(usize, &Provider)> = Vec::new();
+    for provider in providers {
+        let Some(at) = provider
+            .path
+            .parent()
+            .and_then(|parent| dirs.iter().position(|dir| dir == parent))
+        else {
+            continue;
+        };
+        let same = |(_, other): &(usize, &Provider)| {
+            other.manager == provider.manager && other.package == provider.package
+        };
+        match placed.iter_mut().find(|entry| same(entry)) {
+            Some(entry) if entry.0 > at => *entry = (at, provider),
+            Some(_) => {}
+            None => placed.push((at, provider)),
+        }
+    }
+    // Ties within a directory come out in a fixed order: by manager, then name
+    placed.sort_by(|(a_at, a), (b_at, b)| {
+        a_at.cmp(b_at)
+            .then_with(|| a.manager.name().cmp(b.manager.name()))
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    let first = placed.first()?.0;
+    let (winners, shadowed): (Vec<_>, Vec<_>) =
+        placed.into_iter().partition(|(at, _)| *at == first);
+    if shadowed.is_empty() {
+        return None;
+    }
+    Some(Shadowing {
+        command: command.to_string(),
+        winners: winners.into_iter().map(|(_, p)| p.clone()).collect(),
+        shadowed: shadowed.into_iter().map(|(_, p)| p.clone()).collect(),
+    })
+}
+
+// Every command on PATH that more than one installed package provides, by
+// command name. Commands come from dpkg's file lists, pip's RECORDs and
+// cargo's record of installed binaries; a listed file that is gone or not
+// executable doesn't count.
+pub fn find_all(runner: &dyn Runner, cancel: &AtomicBool) -> Result<Vec<Shadowing>, String> {
+    let dirs = which::path_dirs();
+    let dpkg = filelists::dpkg(cancel);
+    if cancel.load(Ordering::Relaxed) {
+        return Err("cancelled".to_string());
+    }
+    let pip = filelists::pip(runner);
+    if let (Err(err), Err(_)) = (&dpkg, &pip) {
+        return Err(err.clone());
+    }
+
+    let on_path = |path: &Path| {
+        path.parent()
+            .is_some_and(|parent| dirs.iter().any(|dir| dir == parent))
+    };
+    let mut commands: HashMap<String, Vec<Provider>> = HashMap::new();
+    let mut add = |manager, package: &str, path: PathBuf| {
+        if let Some(command) = path.file_name().and_then(|name| name.to_str()) {
+            commands
+                .entry(command.to_string())
+                .or_default()
+                .push(Provider {
+                    manager,
+                    package: package.to_string(),
+                    path,
+                });
+        }
+    };
+    for (manager, lists) in [(PackageManager::Apt, &dpkg), (PackageManager::Pip, &pip)] {
+        let Ok(lists) = lists else {
+            continue;
+        };
+        for (name, paths) in lists.iter() {
+            for path in paths {
+                let path = Path::new(path);
+                if on_path(path) {
+                    add(manager, name, path.to_path_buf());
+                }
+            }
+        }
+    }
+    for (name, path) in cargo_binaries() {
+        if on_path(&path) {
+            add(PackageManager::Cargo, &name, path);
+        }
+    }
+
+    let mut found: Vec<Shadowing> = commands
+        .into_iter()
+        .filter(|(_, providers)| providers.len() > 1)
+        .filter_map(|(command, mut providers)| {
+            providers.retain(|provider| which::is_executable(&provider.path));
+            resolve(&command, &providers, &dirs)
+        })
+        .collect();
+    found.sort_by(|a, b| a.command.cmp(&b.command));
+    Ok(found)
+}
+
+// `cargo install`'s record, `$CARGO_HOME/.crates2.json`: each install's
+// "name version (source)" with the binaries it put in `$CARGO_HOME/bin`.
+fn cargo_binaries() -> Vec<(String, PathBuf)> {
+    let Some(home) = std::env::var_os("CARGO_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+    else {
+        return Vec::new();
+    };
+    let Ok(text) = fs::read_to_string(home.join(".crates2.json")) else {
+        return Vec::new();
+    };
+    let Ok(record) = serde_json::from_str::<serde_json::Value>(&text) else {
+        return Vec::new();
+    };
+    let Some(installs) = record
+        .get("installs")
+        .and_then(|installs| installs.as_object())
+    else {
+        return Vec::new();
+    };
+    let bin = home.join("bin");
+    installs
+        .iter()
+        .flat_map(|(id, install)| {
+            let name = id.split(' ').next().unwrap_or(id).to_string();
+            let bins = install
+                .get("bins")
+                .and_then(|bins| bins.as_array())
+                .cloned()
+                .unwrap_or_default();
+            bins.into_iter()
+                .filter_map(|bin| bin.as_str().map(str::to_string))
+                .map(move |file| (name.clone(), file))
+                .collect::<Vec<_>>()
+        })
+        .map(|(name, file)| (name, bin.join(file)))
+        .collect()
+}
+
+// What the selected package's details say: each command of it that another
+// package's hides, and each it hides of another's. Keyed like the list's
+// packages, pip's by canonical name.
+pub fn notes(found: &[Shadowing], manager: PackageManager) -> HashMap<String, Vec<String>> {
+    let manager = match manager {
+        PackageManager::Pkg => PackageManager::Apt,
+        manager => manager,
+    };
+    let key = |package: &str| names::canonical(manager, package);
+    let mut notes: HashMap<String, Vec<String>> = HashMap::new();
+    for shadowing in found {
+        for loser in shadowing.shadowed.iter().filter(|p| p.manager == manager) {
+            notes.entry(key(&loser.package)).or_default().push(format!(
+                "{} at {} is shadowed by {} at {}",
+                shadowing.command,
+                loser.path.display(),
+                shadowing.winner_label(),
+                shadowing.winners[0].path.display()
+            ));
+        }
+        for winner in shadowing.winners.iter().filter(|p| p.manager == manager) {
+            let hidden: Vec<String> = shadowing.shadowed.iter().map(Provider::label).collect();
+            notes.entry(key(&winner.package)).or_default().push(format!(
+                "{} at {} shadows {}",
+                shadowing.command,
+                winner.path.display(),
+                hidden.join(", ")
+            ));
+        }
+    }
+    notes
+}
+
+// One finding per shadowed command of a package, for the health dashboard.
+pub fn find(runner: &dyn Runner, cancel: &AtomicBool) -> Outcome {
+    let found = find_all(runner, cancel)?;
+    Ok(found
+        .iter()
+        .flat_map(|shadowing| {
+            shadowing.shadowed.iter().map(|loser| Finding {
+                manager: loser.manager,
+                package: loser.package.clone(),
+                detail: format!(
+                    "{} shadowed by {} ({} comes first on PATH)",
+                    shadowing.command,
+                    shadowing.winner_label(),
+                    shadowing.winners[0]
+                        .path
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .display()
+                ),
+                other: shadowing
+                    .winners
+                    .first()
+                    .map(|winner| (winner.manager, winner.package.clone())),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USR_BIN: &str = "/data/data/com.termux/files/usr/bin";
+    const LOCAL_BIN: &str = "/data/data/com.termux/files/home/.local/bin";
+    const CARGO_BIN: &str = "/data/data/com.termux/files/home/.cargo/bin";
+
+    fn provider(manager: PackageManager, package: &str, path: &str) -> Provider {
+        Provider {
+            manager,
+            package: package.to_string(),
+            path: PathBuf::from(path),
+        }
+    }
+
+    fn dirs(dirs: &[&str]) -> Vec<PathBuf> {
+        dirs.iter().map(PathBuf::from).collect()
+    }
+
+    fn labels(providers: &[Provider]) -> Vec<String> {
+        providers.iter().map(Provider::label).collect()
+    }
+
+    fn http() -> Vec<Provider> {
+        vec![
+            provider(PackageManager::Apt, "httpie", &format!("{}/http", USR_BIN)),
+            provider(
+                PackageManager::Pip,
+                "httpie",
+                &format!("{}/http", LOCAL_BIN),
+            ),
+            provider(PackageManager::Cargo, "xh", &format!("{}/http", CARGO_BIN)),
+        ]
+    }
+
+    #[test]
+    fn earliest_directory_wins() {
+        let found = resolve("http", &http(), &dirs(&[LOCAL_BIN, CARGO_BIN, USR_BIN])).unwrap();
+        assert_eq!(found.command, "http");
+        assert_eq!(labels(&found.winners), ["httpie (pip)"]);
+        assert_eq!(labels(&found.shadowed), ["xh (cargo)", "httpie (apt)"]);
+
+        // The same packages with PATH the other way round
+        let found = resolve("http", &http(), &dirs(&[USR_BIN, CARGO_BIN, LOCAL_BIN])).unwrap();
+        assert_eq!(labels(&found.winners), ["httpie (apt)"]);
+        assert_eq!(labels(&found.shadowed), ["xh (cargo)", "httpie (pip)"]);
+    }
+
+    #[test]
+    fn nothing_shadowed_is_none() {
+        let one = &http()[..1];
+        assert!(resolve("http", one, &dirs(&[USR_BIN])).is_none());
+        assert!(resolve("http", &[], &dirs(&[USR_BIN])).is_none());
+        // Only one of them is reachable
+        assert!(resolve("http", &http(), &dirs(&[USR_BIN])).is_none());
+        assert!(resolve("http", &http(), &[]).is_none());
+    }
+
+    #[test]
+    fn out_of_reach_providers_are_left_out() {
+        let mut providers = http();
+        providers.push(provider(PackageManager::Pip, "other", "/opt/bin/http"));
+        // A subdirectory of one on PATH isn't on PATH
+        providers.push(provider(
+            PackageManager::Apt,
+            "nested",
+            &format!("{}/sub/http", USR_BIN),
+        ));
+        let found = resolve("http", &providers, &dirs(&[USR_BIN, LOCAL_BIN])).unwrap();
+        assert_eq!(labels(&found.winners), ["httpie (apt)"]);
+        assert_eq!(labels(&found.shadowed), ["httpie (pip)"]);
+    }
+
+    #[test]
+    fn a_package_counts_once_at_its_earliest() {
+        // pip put `http` in two directories; it still hides apt's once
+        let mut providers = http();
+        providers.insert(
+            0,
+            provider(
+                PackageManager::Pip,
+                "httpie",
+                &format!("{}/http", CARGO_BIN),
+            ),
+        );
+        let found = resolve("http", &providers, &dirs(&[USR_BIN, LOCAL_BIN, CARGO_BIN])).unwrap();
+        assert_eq!(labels(&found.shadowed), ["httpie (pip)", "xh (cargo)"]);
+        assert_eq!(found.shadowed[0].path, Path::new(LOCAL_BIN).join("http"));
+
+        // Only a package's own copies are one: apt's and pip's httpie aren't
+        let found = resolve("http", &http()[..2], &dirs(&[USR_BIN, LOCAL_BIN])).unwrap();
+        assert_eq!(found.shadowed.len(), 1);
+    }
+
+    #[test]
+    fn a_directory_listed_twice_counts_first() {
+        let found = resolve(
+            "http",
+            &http(),
+            &dirs(&[CARGO_BIN, USR_BIN, LOCAL_BIN, CARGO_BIN]),
+        )
+        .unwrap();
+        assert_eq!(labels(&found.winners), ["xh (cargo)"]);
+        assert_eq!(labels(&found.shadowed), ["httpie (apt)", "httpie (pip)"]);
+    }
+
+    #[test]
+    fn ties_in_a_directory_by_manager_then_name() {
+        let path = format!("{}/python", USR_BIN);
+        let providers = [
+            provider(PackageManager::Pip, "zz", &path),
+            provider(PackageManager::Apt, "python", &path),
+            provider(PackageManager::Apt, "python-is-python3", &path),
+            provider(
+                PackageManager::Cargo,
+                "rustpython",
+                &format!("{}/python", CARGO_BIN),
+            ),
+        ];
+        let found = resolve("python", &providers, &dirs(&[USR_BIN, CARGO_BIN])).unwrap();
+        assert_eq!(
+            labels(&found.winners),
+            ["python (apt)", "python-is-python3 (apt)", "zz (pip)"]
+        );
+        assert_eq!(
+            found.winner_label(),
+            "python (apt) or python-is-python3 (apt) or zz (pip)"
+        );
+        assert_eq!(labels(&found.shadowed), ["rustpython (cargo)"]);
+
+        // Ties alone shadow nothing: which file won is for the conflict check
+        assert!(resolve("python", &providers[..3], &dirs(&[USR_BIN])).is_none());
+        // And their order doesn't depend on the input's
+        let mut reversed = providers.to_vec();
+        reversed.reverse();
+        let again = resolve("python", &reversed, &dirs(&[USR_BIN, CARGO_BIN])).unwrap();
+        assert_eq!(labels(&again.winners), labels(&found.winners));
+    }
+
+    #[test]
+    fn notes_for_both_sides() {
+        let found = [resolve("http", &http(), &dirs(&[LOCAL_BIN, USR_BIN, CARGO_BIN])).unwrap()];
+        let pip = notes(&found, PackageManager::Pip);
+        assert_eq!(
+            pip["httpie"],
+            [format!(
+                "http at {}/http shadows httpie (apt), xh (cargo)",
+                LOCAL_BIN
+            )]
+        );
+        // pkg's list reads apt's notes
+        let pkg = notes(&found, PackageManager::Pkg);
+        assert_eq!(
+            pkg["httpie"],
+            [format!(
+                "http at {}/http is shadowed by httpie (pip) at {}/http",
+                USR_BIN, LOCAL_BIN
+            )]
+        );
+        assert_eq!(notes(&found, PackageManager::Cargo).len(), 1);
+        assert!(notes(&found, PackageManager::Npm).is_empty());
+    }
+}
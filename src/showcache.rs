@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::commands::{self, Action, Runner};
+use crate::{log, PackageManager};
+
+pub type Key = (String, String); // Name and version
+
+// A failed `show` is reported for this long, then run again the next time
+// the package is shown: dpkg may have been locked, or the index updating.
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+// `show` output per package of one list, so moving back to a package or
+// redrawing never runs the command again. The detail pane loads on a
+// background thread, one package at a time: scrolling quickly through the
+// list only loads where it stops.
+#[derive(Default)]
+pub struct ShowCache {
+    entries: Arc<Mutex<Entries>>,
+    pending: Arc<Mutex<Option<Key>>>,
+    arrived: Arc<AtomicBool>, // Set by the thread, taken by `poll`
+}
+
+#[derive(Default)]
+struct Entries {
+    shown: HashMap<Key, String>,
+    failed: HashMap<Key, (String, Instant)>, // Why, and when
+}
+
+impl Entries {
+    fn store(&mut self, key: Key, fetched: Result<String, String>) -> String {
+        match fetched {
+            Ok(text) => {
+                self.failed.remove(&key);
+                self.shown.insert(key, text.clone());
+                text
+            }
+            Err(err) => {
+                let text = format!("Failed to fetch package details: {}\n", err);
+                self.failed.insert(key, (text.clone(), Instant::now()));
+                text
+            }
+        }
+    }
+
+    fn get(&self, key: &Key) -> Option<String> {
+        self.shown.get(key).cloned().or_else(|| {
+            self.failed
+                .get(key)
+                .filter(|(_, at)| at.elapsed() < RETRY_AFTER)
+                .map(|(text, _)| text.clone())
+        })
+    }
+}
+
+impl ShowCache {
+    // The output, running `show` now if it hasn't been.
+    pub fn get(
+        &self,
+        runner: &dyn Runner,
+        manager: PackageManager,
+        name: &str,
+        version: &str,
+    ) -> String {
+        let key = (name.to_string(), version.to_string());
+        if let Some(text) = self.cached(&key) {
+            return text;
+        }
+        let fetched = fetch(runner, manager, name, version);
+        match self.entries.lock() {
+            Ok(mut entries) => entries.store(key, fetched),
+            Err(_) => fetched.unwrap_or_else(|err| err),
+        }
+    }
+
+    // The output if it is cached; otherwise None, starting a background load
+    // unless another one is still running.
+    pub fn get_or_start(
+        &self,
+        runner: &Arc<dyn Runner>,
+        manager: PackageManager,
+        name: &str,
+        version: &str,
+    ) -> Option<String> {
+        let key = (name.to_string(), version.to_string());
+        if let Some(text) = self.cached(&key) {
+            return Some(text);
+        }
+        let Ok(mut pending) = self.pending.lock() else {
+            return Some(self.get(runner.as_ref(), manager, name, version));
+        };
+        if pending.is_some() {
+            return None;
+        }
+        *pending = Some(key.clone());
+
+        let runner = runner.clone();
+        let entries = self.entries.clone();
+        let pending = self.pending.clone();
+        let arrived = self.arrived.clone();
+        thread::spawn(move || {
+            let fetched = fetch(runner.as_ref(), manager, &key.0, &key.1);
+            if let Ok(mut entries) = entries.lock() {
+                entries.store(key, fetched);
+            }
+            if let Ok(mut pending) = pending.lock() {
+                *pending = None;
+            }
+            arrived.store(true, Ordering::Relaxed);
+        });
+        None
+    }
+
+    // Runs `show` for each package not cached yet, in order, on one
+    // background thread, stopping when `cancel` is set. It waits while the
+    // selected package's own load runs, so that one never queues behind it.
+    pub fn prefetch(
+        &self,
+        runner: &Arc<dyn Runner>,
+        manager: PackageManager,
+        keys: Vec<Key>,
+        cancel: Arc<AtomicBool>,
+    ) {
+        let runner = runner.clone();
+        let entries = self.entries.clone();
+        let pending = self.pending.clone();
+        let arrived = self.arrived.clone();
+        thread::spawn(move || {
+            let mut fetched = 0;
+            for key in keys {
+                while pending.lock().is_ok_and(|pending| pending.is_some())
+                    && !cancel.load(Ordering::Relaxed)
+                {
+                    thread::sleep(Duration::from_millis(20));
+                }
+                if cancel.load(Ordering::Relaxed) {
+                    log::debug(&format!(
+                        "prefetch: cancelled after {} (the screen changed)",
+                        fetched
+                    ));
+                    return;
+                }
+                if entries
+                    .lock()
+                    .is_ok_and(|entries| entries.get(&key).is_some())
+                {
+                    continue;
+                }
+                let text = fetch(runner.as_ref(), manager, &key.0, &key.1);
+                if let Ok(mut entries) = entries.lock() {
+                    entries.store(key, text);
+                }
+                // Redraws a pane waiting on one of these
+                arrived.store(true, Ordering::Relaxed);
+                fetched += 1;
+            }
+            log::debug(&format!("prefetch: done, {} fetched", fetched));
+        });
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.pending.lock().is_ok_and(|pending| pending.is_some())
+    }
+
+    // Whether a background load finished since the last call.
+    pub fn poll(&self) -> bool {
+        self.arrived.swap(false, Ordering::Relaxed)
+    }
+
+    // Forgets everything, e.g. after a refresh that may have upgraded
+    // packages, and returns what was loaded.
+    pub fn take(&self) -> HashMap<Key, String> {
+        self.entries
+            .lock()
+            .map(|mut entries| std::mem::take(&mut *entries).shown)
+            .unwrap_or_default()
+    }
+
+    // Whether `show` already ran for the package (and failed only lately).
+    pub fn contains(&self, name: &str, version: &str) -> bool {
+        self.cached(&(name.to_string(), version.to_string()))
+            .is_some()
+    }
+
+    fn cached(&self, key: &Key) -> Option<String> {
+        self.entries.lock().ok()?.get(key)
+    }
+}
+
+// The output, or why there is none: what the manager said on stderr, or
+// why it couldn't be run.
+fn fetch(
+    runner: &dyn Runner,
+    manager: PackageManager,
+    name: &str,
+    version: &str,
+) -> Result<String, String> {
+    let output = commands::run_for_package(runner, manager, Action::Show, name, version)
+        .map_err(|err| err.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.is_empty() {
+        return Ok(stdout.into_owned());
+    }
+    if output.status.success() {
+        return Ok("No details available".to_string());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.trim() {
+        "" => Err(format!("{} show {}", manager.name(), output.status)),
+        stderr => Err(stderr.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Mock;
+
+    #[test]
+    fn failures_keep_the_error_for_a_while() {
+        let runner = Mock::new().fail("apt show gone", "E: No packages found");
+        let cache = ShowCache::default();
+        let text = cache.get(&runner, PackageManager::Apt, "gone", "1.0");
+        assert_eq!(
+            text,
+            "Failed to fetch package details: E: No packages found\n"
+        );
+        assert!(cache.contains("gone", "1.0"));
+        // Not kept over a refresh, and run again once RETRY_AFTER has passed
+        let key = ("gone".to_string(), "1.0".to_string());
+        if let Some((_, at)) = cache.entries.lock().unwrap().failed.get_mut(&key) {
+            *at -= RETRY_AFTER;
+        }
+        assert!(!cache.contains("gone", "1.0"));
+        cache.get(&runner, PackageManager::Apt, "gone", "1.0");
+        assert_eq!(runner.ran.lock().unwrap().len(), 2);
+        assert!(cache.take().is_empty());
+    }
+
+    #[test]
+    fn output_and_empty_answers_are_kept() {
+        let runner = Mock::new().reply("apt show zlib", "Package: zlib\n");
+        let cache = ShowCache::default();
+        assert_eq!(
+            cache.get(&runner, PackageManager::Apt, "zlib", "1.3"),
+            "Package: zlib\n"
+        );
+        // Succeeding with nothing to say isn't an error
+        assert_eq!(
+            cache.get(&runner, PackageManager::Apt, "quiet", "1.0"),
+            "No details available"
+        );
+        let taken = cache.take();
+        assert_eq!(taken.len(), 2);
+        cache.get(&runner, PackageManager::Apt, "zlib", "1.3");
+        assert_eq!(runner.ran.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,133 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use ratatui::prelude::*;
+
+use crate::{i18n, Package};
+
+// Order of the package list (`o` cycles through them, clicking a column
+// header picks one). Grouping still comes first; this orders the packages
+// within each group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Name,
+    NameDescending,
+    Size,          // Largest first; packages without a size go last
+    SizeAscending, // Smallest first; still without a size last
+}
+
+// Headers above the package list, each sorting by what it names.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Name,
+    Size,
+}
+
+impl SortBy {
+    pub fn next(&self) -> Self {
+        match self {
+            SortBy::Name => SortBy::NameDescending,
+            SortBy::NameDescending => SortBy::Size,
+            SortBy::Size => SortBy::SizeAscending,
+            SortBy::SizeAscending => SortBy::Name,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortBy::Name => i18n::text("sort.name"),
+            SortBy::NameDescending => i18n::text("sort.name_descending"),
+            SortBy::Size => i18n::text("sort.size"),
+            SortBy::SizeAscending => i18n::text("sort.size_ascending"),
+        }
+    }
+
+    pub fn column(&self) -> Column {
+        match self {
+            SortBy::Name | SortBy::NameDescending => Column::Name,
+            SortBy::Size | SortBy::SizeAscending => Column::Size,
+        }
+    }
+
+    // A click on a header: the other way round when already sorted by it.
+    pub fn clicked(&self, column: Column) -> Self {
+        match (column, self) {
+            (Column::Name, SortBy::Name) => SortBy::NameDescending,
+            (Column::Name, _) => SortBy::Name,
+            (Column::Size, SortBy::Size) => SortBy::SizeAscending,
+            (Column::Size, _) => SortBy::Size,
+        }
+    }
+
+    // Whether the header shows ▲: smaller (or earlier) values on top.
+    fn ascending(&self) -> bool {
+        matches!(self, SortBy::Name | SortBy::SizeAscending)
+    }
+
+    // Sorts `indices` into `items`. Ties, and packages without a size, go by
+    // name.
+    pub fn sort(&self, items: &[Package], sizes: &HashMap<String, u64>, indices: &mut [usize]) {
+        let name = |i: &usize| items[*i].name.to_lowercase();
+        match self {
+            SortBy::Name => indices.sort_by_cached_key(name),
+            SortBy::NameDescending => indices.sort_by_cached_key(|i| Reverse(name(i))),
+            SortBy::Size => indices.sort_by_cached_key(|i| {
+                let size = sizes.get(&items[*i].name);
+                (size.is_none(), Reverse(size.copied()), name(i))
+            }),
+            SortBy::SizeAscending => indices.sort_by_cached_key(|i| {
+                let size = sizes.get(&items[*i].name);
+                (size.is_none(), size.copied(), name(i))
+            }),
+        }
+    }
+}
+
+// The header line of the package list: Name over the names, Size at the
+// right, the one sorted by bold with an arrow. Where each was drawn goes to
+// `hits`, for mouse clicks.
+pub fn header(sort_by: SortBy, area: Rect, hits: &mut Vec<(Rect, Column)>) -> Line<'static> {
+    const INDENT: u16 = 5; // The highlight symbol and the mark column
+    hits.clear();
+    let label = |column: Column, name: &str| {
+        if sort_by.column() != column {
+            return Span::styled(name.to_string(), Style::default().fg(Color::DarkGray));
+        }
+        let arrow = if sort_by.ascending() { "▲" } else { "▼" };
+        Span::styled(
+            format!("{} {}", name, arrow),
+            Style::default().add_modifier(Modifier::BOLD),
+        )
+    };
+    let name = label(Column::Name, "Name");
+    let size = label(Column::Size, "Size");
+    let name_width = name.width() as u16;
+    let size_width = size.width() as u16;
+    if area.width < INDENT + name_width + 1 + size_width {
+        return Line::default();
+    }
+    let size_x = area.right() - size_width;
+    hits.push((
+        Rect {
+            x: area.x + INDENT,
+            width: name_width,
+            ..area
+        },
+        Column::Name,
+    ));
+    hits.push((
+        Rect {
+            x: size_x,
+            width: size_width,
+            ..area
+        },
+        Column::Size,
+    ));
+    let gap = usize::from(size_x - area.x - INDENT - name_width);
+    Line::from(vec![
+        Span::raw(" ".repeat(usize::from(INDENT))),
+        name,
+        Span::raw(" ".repeat(gap)),
+        size,
+    ])
+}
@@ -0,0 +1,91 @@
+use ratatui::{
+    buffer::Buffer,
+    prelude::*,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+// Points at one part of the screen: everything outside `target` is dimmed and
+// a callout box is placed next to it, below when there is room, else above,
+// else over its lower edge. Rendered last, over a screen that is already
+// drawn, so any view can use it without knowing how it was laid out.
+pub struct Spotlight<'a> {
+    pub target: Rect,
+    pub title: String,
+    pub lines: Vec<Line<'a>>,
+    pub footer: String,
+}
+
+impl Spotlight<'_> {
+    pub fn render(self, f: &mut Frame) {
+        let screen = f.size();
+        let target = self.target.intersection(screen);
+        dim_outside(f.buffer_mut(), target);
+
+        let width = self
+            .lines
+            .iter()
+            .map(Line::width)
+            .chain([self.title.chars().count(), self.footer.chars().count()])
+            .max()
+            .unwrap_or(0)
+            + 4;
+        let width = (width as u16).min(screen.width);
+        let height = (self.lines.len() as u16 + 4).min(screen.height);
+        let area = place(target, width, height, screen);
+
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut text = self.lines;
+        text.push(Line::raw(""));
+        text.push(Line::styled(self.footer, dim));
+        let callout = Paragraph::new(text).block(
+            Block::default()
+                .title(self.title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(callout, area);
+    }
+}
+
+// Greys out every cell outside `target`, keeping the text readable.
+fn dim_outside(buffer: &mut Buffer, target: Rect) {
+    let area = buffer.area;
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let inside = (target.left()..target.right()).contains(&x)
+                && (target.top()..target.bottom()).contains(&y);
+            if !inside {
+                let cell = buffer.get_mut(x, y);
+                cell.set_fg(Color::DarkGray).set_bg(Color::Reset);
+                cell.modifier = Modifier::DIM;
+            }
+        }
+    }
+}
+
+// Where a `width` × `height` box goes so it covers as little of the target as
+// the screen allows, lined up with its left edge.
+fn place(target: Rect, width: u16, height: u16, screen: Rect) -> Rect {
+    let x = target
+        .x
+        .min(screen.right().saturating_sub(width))
+        .max(screen.x);
+    let y = if screen.bottom().saturating_sub(target.bottom()) >= height {
+        target.bottom()
+    } else if target.top().saturating_sub(screen.top()) >= height {
+        target.top() - height
+    } else {
+        target
+            .bottom()
+            .saturating_sub(height)
+            .min(screen.bottom().saturating_sub(height))
+            .max(screen.y)
+    };
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph},
+};
+
+use crate::{i18n, log};
+
+// When tpi was launched, once --startup-trace asked for the trace.
+static LAUNCHED: OnceLock<Instant> = OnceLock::new();
+// Steps already logged; the trace is of the first time through each.
+static LOGGED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+// --startup-trace: how long each step on the way to the first list took,
+// in the debug log. Without TPI_LOG it goes to $TMPDIR/tpi.log, which is
+// returned to be named on exit.
+pub fn trace(launched: Instant) -> Option<String> {
+    let _ = LAUNCHED.set(launched);
+    if log::path().is_some() {
+        return None;
+    }
+    let dir = std::env::var("TMPDIR").unwrap_or_else(|_| "/tmp".to_string());
+    let path = format!("{}/tpi.log", dir.trim_end_matches('/'));
+    std::env::set_var("TPI_LOG", &path);
+    Some(path)
+}
+
+// Logs a step that began at `began`, with when it ended since launch.
+pub fn step(name: &'static str, began: Instant) {
+    let Some(launched) = LAUNCHED.get() else {
+        return;
+    };
+    let Ok(mut logged) = LOGGED.lock() else {
+        return;
+    };
+    if logged.contains(&name) {
+        return;
+    }
+    logged.push(name);
+    log::debug(&format!(
+        "startup trace: {} took {} ms, done {} ms after launch",
+        name,
+        began.elapsed().as_millis(),
+        launched.elapsed().as_millis()
+    ));
+}
+
+// The screen before anything is read: the panes' borders and "loading…".
+// Drawn right after the terminal is set up, so a slow $PREFIX (eMMC, a
+// texlive-sized dpkg database) shows tpi starting rather than a blank
+// terminal; the config file, list cache and lock file are read after it.
+pub fn first_frame(f: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(60),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+    let list = Paragraph::new(i18n::text("list.loading")).block(
+        Block::default()
+            .title(i18n::text("list.loading_title"))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(list, chunks[0]);
+    f.render_widget(
+        Block::default()
+            .title(i18n::text("details.title"))
+            .borders(Borders::ALL),
+        chunks[1],
+    );
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            format!(" {} ", i18n::text("status.list")),
+            Style::default().add_modifier(Modifier::REVERSED),
+        )),
+        chunks[2],
+    );
+}
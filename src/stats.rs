@@ -0,0 +1,607 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ratatui::{
+    prelude::*,
+    widgets::{BarChart, Block, Borders, Clear, Paragraph, Sparkline, Wrap},
+};
+
+use crate::{i18n, PackageList, PackageManager};
+
+// Installed size buckets, in KiB, for the histogram.
+const SIZE_BUCKETS: [(&str, u64); 5] = [
+    ("<100K", 100),
+    ("<1M", 1024),
+    ("<10M", 10 * 1024),
+    ("<100M", 100 * 1024),
+    (">=100M", u64::MAX),
+];
+
+// Months of dpkg log the activity sparkline spans.
+const MONTHS: usize = 24;
+
+// Packages shown at each end of the install dates.
+const ENDS: usize = 5;
+
+// Below this width the charts give way to a table.
+const CHART_WIDTH: u16 = 80;
+
+// Numbers about the installed packages, from what tpi has already read: the
+// current list with its sizes, holds and why-installed graph, the cached
+// lists of the other managers, dpkg's logs and the health checks that ran.
+// Nothing is listed or run again to make them.
+pub struct Stats {
+    pub manager: PackageManager,
+    pub managers: Vec<(PackageManager, usize, bool)>, // Count, and whether from the list cache
+    pub sizes: Vec<(&'static str, u64)>,              // Packages per size bucket
+    pub unknown_size: usize,                          // Packages without a known size
+    pub reasons: Vec<(&'static str, u64)>,            // Why installed, where that's known
+    pub origins: Vec<(String, u64)>,                  // Packages per repository, most first
+    pub oldest: Vec<(String, String)>,                // (date, name) by last install
+    pub newest: Vec<(String, String)>,
+    pub activity: Vec<u64>,        // Installs and upgrades per month, for dpkg
+    pub reading_log: bool,         // dpkg's logs haven't been read yet
+    pub upgradable: Option<usize>, // None until `V` has checked
+    pub held: usize,
+    pub broken: Option<usize>, // None until the health check has run
+}
+
+impl Stats {
+    // `others` are the other managers' package counts; `broken` is what the
+    // broken-state health check found, if it ran.
+    pub fn collect(
+        list: &PackageList,
+        others: Vec<(PackageManager, usize, bool)>,
+        broken: Option<usize>,
+    ) -> Self {
+        let manager = list.package_manager;
+        let dpkg = matches!(manager, PackageManager::Pkg | PackageManager::Apt);
+        let mut managers = vec![(manager, list.items.len(), false)];
+        managers.extend(others.into_iter().filter(|(other, ..)| *other != manager));
+
+        let mut sizes: Vec<(&'static str, u64)> =
+            SIZE_BUCKETS.iter().map(|(label, _)| (*label, 0)).collect();
+        let mut unknown_size = 0;
+        for pkg in &list.items {
+            match list.sizes.get(&pkg.name) {
+                Some(&kib) => {
+                    let bucket = SIZE_BUCKETS
+                        .iter()
+                        .position(|(_, below)| kib < *below)
+                        .unwrap_or(SIZE_BUCKETS.len() - 1);
+                    sizes[bucket].1 += 1;
+                }
+                None => unknown_size += 1,
+            }
+        }
+
+        let reasons = reasons(list, dpkg);
+
+        let mut by_origin: HashMap<String, u64> = HashMap::new();
+        for pkg in &list.items {
+            let origin = pkg
+                .repo
+                .clone()
+                .unwrap_or_else(|| i18n::text("stats.no_repo").to_string());
+            *by_origin.entry(origin).or_default() += 1;
+        }
+        let mut origins: Vec<(String, u64)> = by_origin.into_iter().collect();
+        origins.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let (mut oldest, mut newest, mut activity) = (Vec::new(), Vec::new(), Vec::new());
+        let log = dpkg
+            .then(|| list.dpkg_log.get_or_start(&list.runner))
+            .flatten();
+        if let Some(log) = log {
+            let mut dated: Vec<(String, String)> = list
+                .items
+                .iter()
+                .filter_map(|pkg| {
+                    Some((log.last_install(&pkg.name)?.to_string(), pkg.name.clone()))
+                })
+                .collect();
+            dated.sort();
+            oldest = dated.iter().take(ENDS).cloned().collect();
+            newest = dated.iter().rev().take(ENDS).cloned().collect();
+            activity = log.monthly(MONTHS);
+        }
+
+        Self {
+            manager,
+            managers,
+            sizes,
+            unknown_size,
+            reasons,
+            origins,
+            oldest,
+            newest,
+            activity,
+            reading_log: dpkg && log.is_none(),
+            upgradable: list.upgradable.get(&manager).map(HashMap::len),
+            held: list.held.len(),
+            broken,
+        }
+    }
+
+    // The numbers as label/value rows: the narrow-terminal table.
+    fn table(&self) -> Vec<Line<'static>> {
+        let heading = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        let mut section = |title: &str, rows: Vec<(String, String)>| {
+            if rows.is_empty() {
+                return;
+            }
+            if !lines.is_empty() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::styled(title.to_string(), heading));
+            let width = rows.iter().map(|(label, _)| label.chars().count()).max();
+            for (label, value) in rows {
+                lines.push(Line::from(format!(
+                    "  {:<width$}  {}",
+                    label,
+                    value,
+                    width = width.unwrap_or(0)
+                )));
+            }
+        };
+        section(i18n::text("stats.packages"), self.manager_rows());
+        section(i18n::text("stats.state"), self.state_rows());
+        section(i18n::text("stats.size"), self.size_rows());
+        section(
+            i18n::text("stats.why"),
+            pairs(
+                self.reasons
+                    .iter()
+                    .map(|(label, n)| (label.to_string(), *n)),
+            ),
+        );
+        section(
+            i18n::text("stats.repos"),
+            pairs(self.origins.iter().cloned()),
+        );
+        section(i18n::text("stats.oldest_installs"), self.oldest.clone());
+        section(i18n::text("stats.newest_installs"), self.newest.clone());
+        if self.reading_log {
+            section(
+                i18n::text("stats.installs"),
+                vec![(
+                    "dpkg.log".to_string(),
+                    i18n::text("stats.still_reading").to_string(),
+                )],
+            );
+        }
+        lines
+    }
+
+    fn manager_rows(&self) -> Vec<(String, String)> {
+        self.managers
+            .iter()
+            .map(|(manager, count, cached)| {
+                let note = if *cached {
+                    i18n::text("stats.cached")
+                } else {
+                    ""
+                };
+                (manager.name().to_string(), format!("{}{}", count, note))
+            })
+            .collect()
+    }
+
+    fn state_rows(&self) -> Vec<(String, String)> {
+        let or_unknown = |count: Option<usize>, how: &str| {
+            count.map_or(i18n::fill("stats.not_checked", &[("how", &how)]), |count| {
+                count.to_string()
+            })
+        };
+        vec![
+            (
+                i18n::text("stats.upgradable").to_string(),
+                or_unknown(self.upgradable, i18n::text("stats.how_upgradable")),
+            ),
+            (i18n::text("stats.held").to_string(), self.held.to_string()),
+            (
+                i18n::text("stats.broken").to_string(),
+                or_unknown(self.broken, i18n::text("stats.how_broken")),
+            ),
+        ]
+    }
+
+    fn size_rows(&self) -> Vec<(String, String)> {
+        let mut rows = pairs(self.sizes.iter().map(|(label, n)| (label.to_string(), *n)));
+        if self.unknown_size > 0 {
+            rows.push((
+                i18n::text("stats.unknown").to_string(),
+                self.unknown_size.to_string(),
+            ));
+        }
+        rows
+    }
+
+    fn render_charts(&self, f: &mut Frame, inner: Rect) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage(35),
+                Constraint::Percentage(30),
+                Constraint::Min(6),
+            ])
+            .split(inner);
+        let top = halves(rows[0]);
+        let middle = halves(rows[1]);
+        let bottom = halves(rows[2]);
+
+        let managers: Vec<(String, u64)> = self
+            .managers
+            .iter()
+            .map(|(manager, count, cached)| {
+                let mark = if *cached { "*" } else { "" };
+                (format!("{}{}", manager.name(), mark), *count as u64)
+            })
+            .collect();
+        let cached = self.managers.iter().any(|(_, _, cached)| *cached);
+        let title = if cached {
+            i18n::text("stats.per_manager_cached")
+        } else {
+            i18n::text("stats.per_manager")
+        };
+        horizontal_bars(f, top[0], title, &managers, Color::Cyan);
+
+        let size_title = match self.unknown_size {
+            0 => i18n::text("stats.size").to_string(),
+            n => i18n::fill("stats.size_unknown", &[("count", &n)]),
+        };
+        let sizes: Vec<(&str, u64)> = self.sizes.clone();
+        f.render_widget(
+            BarChart::default()
+                .block(Block::default().title(size_title))
+                .data(sizes.as_slice())
+                .bar_width(6)
+                .bar_gap(1)
+                .bar_style(Style::default().fg(Color::Yellow))
+                .value_style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+            top[1],
+        );
+
+        let reasons: Vec<(String, u64)> = self
+            .reasons
+            .iter()
+            .map(|(label, n)| (label.to_string(), *n))
+            .collect();
+        horizontal_bars(
+            f,
+            middle[0],
+            i18n::text("stats.why"),
+            &reasons,
+            Color::Green,
+        );
+        let origins: Vec<(String, u64)> = self
+            .origins
+            .iter()
+            .take(usize::from(middle[1].height.saturating_sub(1)))
+            .cloned()
+            .collect();
+        horizontal_bars(
+            f,
+            middle[1],
+            i18n::text("stats.repos"),
+            &origins,
+            Color::Magenta,
+        );
+
+        let state = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(0)])
+            .split(bottom[0]);
+        let rows = self.state_rows();
+        let width = rows.iter().map(|(label, _)| label.chars().count()).max();
+        let lines: Vec<Line> = rows
+            .into_iter()
+            .map(|(label, value)| {
+                Line::from(format!(
+                    "{:<width$} {}",
+                    label,
+                    value,
+                    width = width.unwrap_or(0)
+                ))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().title(i18n::text("stats.state"))),
+            state[0],
+        );
+        if !self.activity.is_empty() {
+            f.render_widget(
+                Sparkline::default()
+                    .block(
+                        Block::default()
+                            .title(i18n::fill("stats.activity", &[("months", &MONTHS)])),
+                    )
+                    .data(&self.activity)
+                    .style(Style::default().fg(Color::Blue)),
+                state[1],
+            );
+        }
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines: Vec<Line> = Vec::new();
+        let titles = [i18n::text("stats.oldest"), i18n::text("stats.newest")];
+        let width = titles.iter().map(|title| title.chars().count()).max();
+        for (title, rows) in titles.into_iter().zip([&self.oldest, &self.newest]) {
+            for (i, (date, name)) in rows.iter().enumerate() {
+                let label = if i == 0 { title } else { "" };
+                lines.push(Line::from(vec![
+                    Span::raw(format!("{:<width$} ", label, width = width.unwrap_or(0))),
+                    Span::styled(format!("{} ", date), dim),
+                    Span::raw(name.clone()),
+                ]));
+            }
+        }
+        if lines.is_empty() && self.reading_log {
+            lines.push(Line::from(i18n::text("details.reading_log")));
+        } else if lines.is_empty() {
+            lines.push(Line::from(i18n::text("stats.no_dates")));
+        }
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().title(i18n::text("stats.last_installed"))),
+            bottom[1],
+        );
+    }
+}
+
+// What the statistics were collected from: the manager, when its list was
+// read, how many upgradable and broken packages were known then, and
+// whether dpkg's logs had been read.
+pub type Source = (PackageManager, Instant, Option<usize>, Option<usize>, bool);
+
+// `L`: the statistics over the whole screen. They are collected again
+// when what they came from changes while the view is open.
+pub struct StatsView {
+    pub stats: Stats,
+    pub source: Source,
+    pub scroll: u16, // Of the table, on narrow terminals
+}
+
+impl StatsView {
+    pub fn scroll_by(&mut self, down: bool, lines: u16) {
+        self.scroll = if down {
+            self.scroll.saturating_add(lines)
+        } else {
+            self.scroll.saturating_sub(lines)
+        };
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let narrow = area.width < CHART_WIDTH;
+        let keys = if narrow {
+            i18n::text("stats.keys_narrow")
+        } else {
+            i18n::text("stats.keys")
+        };
+        let block = Block::default()
+            .title(i18n::fill(
+                "stats.title",
+                &[("manager", &self.stats.manager.name()), ("keys", &keys)],
+            ))
+            .borders(Borders::ALL);
+        f.render_widget(Clear, area);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+        if !narrow {
+            self.stats.render_charts(f, inner);
+            return;
+        }
+        // Scrolling stops with the last line at the bottom
+        let table = Paragraph::new(self.stats.table()).wrap(Wrap { trim: false });
+        let lines = table.line_count(inner.width) as u16;
+        self.scroll = self.scroll.min(lines.saturating_sub(inner.height));
+        f.render_widget(table.scroll((self.scroll, 0)), inner);
+    }
+}
+
+// apt's automatic flag and the Essential field for dpkg; for pip whether
+// anything requires it, from the why-installed graph.
+fn reasons(list: &PackageList, dpkg: bool) -> Vec<(&'static str, u64)> {
+    if dpkg {
+        let (mut essential, mut automatic, mut manual) = (0, 0, 0);
+        for pkg in &list.items {
+            if list.essential.contains(&pkg.name) {
+                essential += 1;
+            } else if pkg.auto_installed {
+                automatic += 1;
+            } else {
+                manual += 1;
+            }
+        }
+        return vec![
+            (i18n::text("stats.manual"), manual),
+            (i18n::text("stats.automatic"), automatic),
+            (i18n::text("stats.essential"), essential),
+        ];
+    }
+    let Some(explainer) = list.why.as_ref() else {
+        return Vec::new();
+    };
+    let requested = list
+        .items
+        .iter()
+        .filter(|pkg| explainer.roots.contains(&explainer.key(&pkg.name)))
+        .count() as u64;
+    vec![
+        (i18n::text("stats.requested"), requested),
+        (
+            i18n::text("stats.dependency"),
+            list.items.len() as u64 - requested,
+        ),
+    ]
+}
+
+fn halves(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area)
+}
+
+fn horizontal_bars(f: &mut Frame, area: Rect, title: &str, data: &[(String, u64)], color: Color) {
+    let block = Block::default().title(title.to_string());
+    if data.is_empty() {
+        f.render_widget(
+            Paragraph::new(i18n::text("stats.not_known")).block(block),
+            area,
+        );
+        return;
+    }
+    let data: Vec<(&str, u64)> = data.iter().map(|(label, n)| (label.as_str(), *n)).collect();
+    f.render_widget(
+        BarChart::default()
+            .block(block)
+            .direction(Direction::Horizontal)
+            .data(data.as_slice())
+            .bar_width(1)
+            .bar_gap(0)
+            .bar_style(Style::default().fg(color))
+            .value_style(Style::default().fg(Color::Black).bg(color)),
+        area,
+    );
+}
+
+fn pairs(rows: impl Iterator<Item = (String, u64)>) -> Vec<(String, String)> {
+    rows.map(|(label, n)| (label, n.to_string())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::dpkglog;
+    use crate::tests::{app, Mock};
+
+    const SIZES: &str = "dpkg-query -W -f=${Package}\t${Installed-Size}";
+
+    // The test list's apt packages, with sizes, a hold and dpkg's log; pip's
+    // count as if from its cached list.
+    fn collected() -> Stats {
+        let mock = Arc::new(
+            Mock::new()
+                .reply(SIZES, "bash\t5000\ncurl\t500\nlibssl3\t50\nopenssl\t2048\n")
+                .reply("apt-mark showhold", "bash\n"),
+        );
+        let app = app(mock);
+        let list = &app.package_list;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let today = crate::format_date(now.as_secs());
+        let mut log = dpkglog::Log::default();
+        log.add(&format!(
+            "2019-01-02 10:00:00 install bash:aarch64 <none> 5.0\n\
+             {today} 10:00:00 install curl:aarch64 <none> 8.9.1\n\
+             {today} 10:00:01 upgrade openssl:aarch64 3.3.0 3.3.1\n\
+             {today} 10:00:02 remove zlib:aarch64 1.3.1 <none>\n"
+        ));
+        assert!(list.dpkg_log.set(log).is_ok());
+        Stats::collect(list, vec![(PackageManager::Pip, 2, true)], Some(0))
+    }
+
+    fn draw(view: &mut StatsView, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| view.render(f, f.size())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                text.push_str(buffer.get(x, y).symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn counted_from_what_was_read() {
+        let stats = collected();
+        assert_eq!(
+            stats.managers,
+            [
+                (PackageManager::Apt, 5, false),
+                (PackageManager::Pip, 2, true)
+            ]
+        );
+        assert_eq!(
+            stats.sizes,
+            [
+                ("<100K", 1),
+                ("<1M", 1),
+                ("<10M", 2),
+                ("<100M", 0),
+                (">=100M", 0)
+            ]
+        );
+        assert_eq!(stats.unknown_size, 1);
+        assert_eq!(
+            stats.reasons,
+            [("manual", 3), ("automatic", 2), ("essential", 0)]
+        );
+        assert_eq!(stats.origins, [("stable".to_string(), 5)]);
+        assert_eq!(
+            (stats.upgradable, stats.held, stats.broken),
+            (None, 1, Some(0))
+        );
+    }
+
+    #[test]
+    fn install_dates_and_activity_from_the_log() {
+        let stats = collected();
+        let names: Vec<&str> = stats.oldest.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, ["bash", "curl"]);
+        let names: Vec<&str> = stats.newest.iter().map(|(_, name)| name.as_str()).collect();
+        assert_eq!(names, ["curl", "bash"]);
+        assert_eq!(stats.oldest[0].0, "2019-01-02");
+        // The install and the upgrade this month; 2019 is out of range
+        assert_eq!(stats.activity.len(), MONTHS);
+        assert_eq!(stats.activity[MONTHS - 1], 2);
+        assert_eq!(stats.activity.iter().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn charts_when_wide_and_a_scrolling_table_when_narrow() {
+        let stats = collected();
+        let source = (PackageManager::Apt, Instant::now(), None, Some(0), true);
+        let mut view = StatsView {
+            stats,
+            source,
+            scroll: 0,
+        };
+        let wide = draw(&mut view, 100, 30);
+        assert!(wide.contains("Statistics (apt) — Esc close"), "{}", wide);
+        assert!(
+            wide.contains("Packages per manager (* cached list)"),
+            "{}",
+            wide
+        );
+        assert!(wide.contains("Installed size (1 unknown)"), "{}", wide);
+        assert!(
+            wide.contains("upgradable not checked (V checks)"),
+            "{}",
+            wide
+        );
+        assert!(wide.contains("oldest 2019-01-02 bash"), "{}", wide);
+
+        let narrow = draw(&mut view, 60, 12);
+        assert!(narrow.contains("j/k scroll, Esc close"), "{}", narrow);
+        assert!(narrow.contains("pip  2 (cached list)"), "{}", narrow);
+        assert!(!narrow.contains("Newest installs"), "{}", narrow);
+        // Scrolling stops with the last line at the bottom
+        view.scroll_by(true, 100);
+        let end = draw(&mut view, 60, 12);
+        assert!(end.contains("Newest installs"), "{}", end);
+        let last = end.lines().nth(10).unwrap();
+        assert!(last.contains("bash"), "{}", end);
+        view.scroll_by(false, 100);
+        assert_eq!(view.scroll, 0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{prelude::*, widgets::Paragraph};
+
+use crate::focus::Mode;
+use crate::{format_size, i18n, PackageManager};
+
+// How long a message stays on the status bar when no key is pressed.
+pub const MESSAGE_TTL: Duration = Duration::from_secs(5);
+
+// When the message on the status bar went up, so it can go on its own.
+pub struct Expiry {
+    shown: Option<(String, Instant)>,
+}
+
+impl Expiry {
+    pub fn new() -> Self {
+        Self { shown: None }
+    }
+
+    // A key was pressed: whatever comes next is a new message, even with the
+    // same text.
+    pub fn reset(&mut self) {
+        self.shown = None;
+    }
+
+    // Notes the message as of `now`. Returns whether it has been up long
+    // enough to clear.
+    pub fn observe(&mut self, message: Option<&str>, now: Instant) -> bool {
+        match (message, &self.shown) {
+            (None, _) => {
+                self.shown = None;
+                false
+            }
+            (Some(message), Some((shown, since))) if shown == message => {
+                now.duration_since(*since) >= MESSAGE_TTL
+            }
+            (Some(message), _) => {
+                self.shown = Some((message.to_string(), now));
+                false
+            }
+        }
+    }
+
+    // Until the message should go, for the event loop's timeout.
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.shown
+            .as_ref()
+            .map(|(_, since)| MESSAGE_TTL.saturating_sub(now.duration_since(*since)))
+    }
+}
+
+// What the status bar says about the list when there is no message.
+pub struct Summary {
+    pub manager: PackageManager,
+    pub total: usize,            // Packages listed
+    pub shown: usize,            // Of those, left by the search, filters and hiding
+    pub selected: Option<usize>, // Among the shown ones
+    pub size: u64,               // KiB, of the packages whose size is known
+    pub sized: usize,
+}
+
+impl Summary {
+    // "apt · 142 of 903 shown · ≥1.2 GiB installed · 12/142"
+    pub fn text(&self) -> String {
+        let mut parts = vec![self.manager.name().to_string()];
+        parts.push(if self.shown == self.total {
+            i18n::fill("status.packages", &[("count", &self.total)])
+        } else {
+            i18n::fill(
+                "status.shown",
+                &[("shown", &self.shown), ("total", &self.total)],
+            )
+        });
+        if self.sized > 0 {
+            let about = if self.sized == self.total { "~" } else { "≥" };
+            let size = format!("{}{}", about, format_size(self.size));
+            parts.push(i18n::fill("status.installed", &[("size", &size)]));
+        }
+        if let Some(i) = self.selected {
+            parts.push(format!("{}/{}", i + 1, self.shown));
+        }
+        parts.join(" · ")
+    }
+}
+
+// The bottom line: where keys go, then the message or the summary, with the
+// key reminder on the right while there is room for it.
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    mode: Mode,
+    message: Option<&str>,
+    summary: &Summary,
+    hints: &str,
+) {
+    let badge = Span::styled(
+        format!(" {} ", mode.label()),
+        Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+    );
+    let left = match message {
+        Some(message) => Span::styled(
+            message.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        None => Span::raw(summary.text()),
+    };
+    let room = usize::from(area.width).saturating_sub(badge.width() + 1 + left.width());
+    let mut spans = vec![badge, Span::raw(" "), left];
+    let hints_width = hints.chars().count();
+    if room > hints_width + 2 {
+        spans.push(Span::raw(" ".repeat(room - hints_width)));
+        spans.push(Span::styled(
+            hints.to_string(),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
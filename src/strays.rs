@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands::Runner;
+use crate::filelists;
+use crate::health::{Finding, Outcome};
+use crate::names;
+use crate::{prefix, PackageManager};
+
+// Installed files that aren't where the manager should have put them: dpkg
+// files outside $PREFIX (on Termux a packaging bug or a leftover), and pip
+// scripts in a bin directory that isn't on PATH, so their commands can't be
+// run by name.
+pub fn stray_paths(manager: PackageManager, paths: &[String]) -> Vec<&str> {
+    match manager {
+        PackageManager::Pkg | PackageManager::Apt => {
+            let prefix = prefix();
+            let prefix = prefix.trim_end_matches('/');
+            if prefix.is_empty() {
+                return Vec::new(); // Everything is inside /
+            }
+            paths
+                .iter()
+                .map(String::as_str)
+                .filter(|path| !inside(path, prefix))
+                .collect()
+        }
+        PackageManager::Pip => {
+            let on_path: Vec<String> = std::env::var_os("PATH")
+                .map(|path| {
+                    std::env::split_paths(&path)
+                        .map(|dir| dir.to_string_lossy().trim_end_matches('/').to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            paths
+                .iter()
+                .map(String::as_str)
+                // Data directories inside a package can be called bin too
+                .filter(|path| !path.contains("/site-packages/"))
+                .filter(|path| {
+                    let dir = Path::new(path).parent();
+                    dir.is_some_and(|dir| {
+                        dir.file_name().is_some_and(|name| name == "bin")
+                            && !on_path.iter().any(|entry| Path::new(entry) == dir)
+                    })
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Heading for a package's stray files in the Files view.
+pub fn label(manager: PackageManager) -> &'static str {
+    match manager {
+        PackageManager::Pip => "Scripts in a bin directory not on PATH",
+        _ => "Installed outside $PREFIX (a packaging bug or a leftover)",
+    }
+}
+
+// The prefix itself, anything under it, and the directories leading to it,
+// which dpkg lists as part of every package.
+fn inside(path: &str, prefix: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    path == prefix
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+        || prefix
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Stray files per package of one manager, for the list badges. pip packages
+// are keyed by canonical name, since RECORDs and `pip list` spell names
+// differently.
+pub fn scan(manager: PackageManager, runner: &dyn Runner) -> HashMap<String, Vec<String>> {
+    let Some(Ok(lists)) = filelists::load(manager, runner, &AtomicBool::new(false)) else {
+        return HashMap::new();
+    };
+    lists
+        .iter()
+        .filter_map(|(name, paths)| {
+            let strays = stray_paths(manager, paths);
+            if strays.is_empty() {
+                return None;
+            }
+            Some((
+                names::canonical(manager, name),
+                strays.into_iter().map(String::from).collect(),
+            ))
+        })
+        .collect()
+}
+
+// One finding per package with stray files, for the health dashboard.
+pub fn find(runner: &dyn Runner, cancel: &AtomicBool) -> Outcome {
+    let dpkg = filelists::dpkg(cancel);
+    if cancel.load(Ordering::Relaxed) {
+        return Err("cancelled".to_string());
+    }
+    let pip = filelists::pip(runner);
+    if let (Err(err), Err(_)) = (&dpkg, &pip) {
+        return Err(err.clone());
+    }
+
+    let mut findings = Vec::new();
+    for (manager, lists, what) in [
+        (PackageManager::Apt, &dpkg, "outside $PREFIX"),
+        (PackageManager::Pip, &pip, "in a bin directory not on PATH"),
+    ] {
+        let Ok(lists) = lists else {
+            continue;
+        };
+        for (name, paths) in lists.iter() {
+            let strays = stray_paths(manager, paths);
+            let Some(first) = strays.first() else {
+                continue;
+            };
+            let files = if strays.len() == 1 { "file" } else { "files" };
+            findings.push(Finding {
+                manager,
+                package: name.clone(),
+                detail: format!("{} {} {}, e.g. {}", strays.len(), files, what, first),
+                other: None,
+            });
+        }
+    }
+    findings.sort_by(|a, b| (a.manager.name(), &a.package).cmp(&(b.manager.name(), &b.package)));
+    Ok(findings)
+}
@@ -0,0 +1,538 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use ratatui::prelude::*;
+
+use crate::commands::{self, Action, Runner};
+use crate::details::Details;
+use crate::{i18n, renderers, PackageManager};
+
+// What the detail pane shows for the selected package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum View {
+    Raw,    // `show` output, with why-installed and version history
+    Fields, // The same output as an aligned field table; the default
+    Files,
+    Deps,
+    Policy,   // Installed and candidate versions per repository
+    Timeline, // Installs, upgrades and removals in dpkg's logs
+}
+
+impl View {
+    pub const ALL: [View; 6] = [
+        View::Raw,
+        View::Fields,
+        View::Files,
+        View::Deps,
+        View::Policy,
+        View::Timeline,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            View::Raw => i18n::text("tab.raw"),
+            View::Fields => i18n::text("tab.fields"),
+            View::Files => i18n::text("tab.files"),
+            View::Deps => i18n::text("tab.deps"),
+            View::Policy => i18n::text("tab.policy"),
+            View::Timeline => i18n::text("tab.timeline"),
+        }
+    }
+
+    // Views that run a command of their own rather than reading `show`.
+    fn action(&self) -> Option<Action> {
+        match self {
+            View::Files => Some(Action::Files),
+            View::Policy => Some(Action::Policy),
+            View::Raw | View::Fields | View::Deps | View::Timeline => None,
+        }
+    }
+}
+
+type Key = (PackageManager, String, View);
+type Loaded = Result<String, String>;
+
+// Tab strip of the detail pane. Command-backed views are loaded on a
+// background thread the first time they are shown for a package and cached.
+// Results are keyed by package, so a slow load never shows up under another.
+pub struct DetailTabs {
+    pub view: View, // Kept while moving between packages
+    cache: HashMap<Key, Loaded>,
+    pending: HashSet<Key>,
+    sender: Sender<(Key, Loaded)>,
+    receiver: Receiver<(Key, Loaded)>,
+    hits: Vec<(Rect, View)>, // Where each label was last drawn, for mouse clicks
+    back: Option<View>,      // View `f` switched away from, restored by `f` or Esc
+}
+
+impl DetailTabs {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            view: View::Fields,
+            cache: HashMap::new(),
+            pending: HashSet::new(),
+            sender,
+            receiver,
+            hits: Vec::new(),
+            back: None,
+        }
+    }
+
+    pub fn step(&mut self, forward: bool) {
+        self.back = None;
+        let len = View::ALL.len();
+        let i = View::ALL.iter().position(|v| *v == self.view).unwrap_or(0);
+        self.view = View::ALL[if forward {
+            (i + 1) % len
+        } else {
+            (i + len - 1) % len
+        }];
+    }
+
+    // Selects the view whose label is at this screen position.
+    pub fn click(&mut self, column: u16, row: u16) -> bool {
+        let hit = self
+            .hits
+            .iter()
+            .find(|(area, _)| row == area.y && column >= area.x && column < area.x + area.width);
+        match hit {
+            Some((_, view)) => {
+                self.view = *view;
+                self.back = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // `f`: the file list, or back to the view it was opened from.
+    pub fn toggle_files(&mut self) {
+        if self.view == View::Files {
+            self.view = self.back.take().unwrap_or(View::Fields);
+        } else {
+            self.back = Some(self.view);
+            self.view = View::Files;
+        }
+    }
+
+    // Esc in a file list opened with `f` goes back. Returns whether it did.
+    pub fn leave_files(&mut self) -> bool {
+        match self.back.take() {
+            Some(view) if self.view == View::Files => {
+                self.view = view;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // Collects finished loads. Returns whether anything arrived.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((key, loaded)) = self.receiver.try_recv() {
+            self.pending.remove(&key);
+            self.cache.insert(key, loaded);
+            changed = true;
+        }
+        changed
+    }
+
+    // Drops everything cached, e.g. after the packages changed.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    // Text of the current view, which must be command-backed. None while it
+    // is loading; the first call for a package starts the load.
+    pub fn loaded(
+        &mut self,
+        runner: &Arc<dyn Runner>,
+        manager: PackageManager,
+        name: &str,
+        version: &str,
+    ) -> Option<Loaded> {
+        let view = self.view;
+        let action = view.action()?;
+        let key = (manager, name.to_string(), view);
+        if let Some(loaded) = self.cache.get(&key) {
+            return Some(loaded.clone());
+        }
+        if !commands::supports(manager, action) {
+            let loaded = Err(i18n::fill(
+                "tab.unsupported",
+                &[
+                    ("view", &view.label().to_lowercase()),
+                    ("manager", &manager.name()),
+                ],
+            ));
+            self.cache.insert(key, loaded.clone());
+            return Some(loaded);
+        }
+        if self.pending.insert(key.clone()) {
+            let runner = runner.clone();
+            let sender = self.sender.clone();
+            let (name, version) = (name.to_string(), version.to_string());
+            thread::spawn(move || {
+                let loaded = match commands::run_for_package(
+                    runner.as_ref(),
+                    manager,
+                    action,
+                    &name,
+                    &version,
+                ) {
+                    Ok(output) if output.status.success() => Ok(format_output(
+                        view,
+                        &String::from_utf8_lossy(&output.stdout),
+                    )),
+                    Ok(output) => Err(i18n::fill(
+                        "tab.failed",
+                        &[
+                            ("view", &view.label().to_lowercase()),
+                            ("error", &String::from_utf8_lossy(&output.stderr).trim()),
+                        ],
+                    )),
+                    Err(err) => Err(i18n::fill(
+                        "tab.failed",
+                        &[("view", &view.label().to_lowercase()), ("error", &err)],
+                    )),
+                };
+                let _ = sender.send((key, loaded));
+            });
+        }
+        None
+    }
+
+    // The strip of view labels, current one highlighted, remembering where
+    // each label lands inside `area`.
+    pub fn strip(&mut self, area: Rect) -> Line<'static> {
+        self.hits.clear();
+        let mut spans = Vec::new();
+        let mut x = area.x;
+        for (i, view) in View::ALL.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+                x += 3;
+            }
+            let label = view.label();
+            let width = label.chars().count() as u16;
+            self.hits.push((
+                Rect {
+                    x,
+                    y: area.y,
+                    width,
+                    height: 1,
+                },
+                view,
+            ));
+            x += width;
+            spans.push(if view == self.view {
+                Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                Span::raw(label)
+            });
+        }
+        spans.push(Span::styled(
+            "   [ ] switch",
+            Style::default().fg(Color::DarkGray),
+        ));
+        Line::from(spans)
+    }
+}
+
+// File lists as one path per line: `dpkg -L` prints them that way already
+// (minus its "/." root), `pip show -f` indents them under "Files:".
+fn format_output(view: View, stdout: &str) -> String {
+    if view != View::Files {
+        return stdout.to_string();
+    }
+    let lines: Vec<String> = match stdout.lines().position(|line| line.trim() == "Files:") {
+        // pip: paths relative to the Location field
+        Some(start) => {
+            let location = stdout
+                .lines()
+                .find_map(|line| line.strip_prefix("Location:"))
+                .map(|location| Path::new(location.trim()));
+            stdout
+                .lines()
+                .skip(start + 1)
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| match location {
+                    Some(location) => absolute(location, line),
+                    None => line.to_string(),
+                })
+                .collect()
+        }
+        None => stdout
+            .lines()
+            .filter(|line| *line != "/." && !line.trim().is_empty())
+            .map(String::from)
+            .collect(),
+    };
+    if lines.is_empty() {
+        return "The package database lists no files for this package".to_string();
+    }
+    lines.join("\n")
+}
+
+// `relative` under `base`, with `..` resolved without touching the disk
+// (pip records scripts as ../../../bin/name).
+fn absolute(base: &Path, relative: &str) -> String {
+    let mut path = PathBuf::new();
+    for component in base.join(relative).components() {
+        match component {
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::CurDir => {}
+            other => path.push(other),
+        }
+    }
+    path.display().to_string()
+}
+
+// `show` fields as an aligned table with the names in bold, each value
+// drawn by its renderer (see renderers.rs); continuation lines keep their
+// indent under the value column. Fields without a renderer are shown as
+// they are, so nothing the manager printed goes missing. Drawn without trimming, so verbatim blocks of a Description stay
+// put.
+pub fn field_lines(
+    details: &Details,
+    manager: PackageManager,
+    context: &renderers::Context,
+) -> Vec<Line<'static>> {
+    if details.fields.is_empty() {
+        return vec![Line::from("No fields")];
+    }
+    let width = details
+        .fields
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = Vec::new();
+    for (key, value) in &details.fields {
+        let mut lines = renderers::render(manager, key, value, context).into_iter();
+        let first = lines.next().unwrap_or_default();
+        let mut spans = vec![Span::styled(
+            format!("{:<width$}  ", key, width = width),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        let style = first.style;
+        spans.extend(first.spans);
+        out.push(Line::from(spans).style(style));
+        for line in lines {
+            if line.width() == 0 {
+                out.push(line);
+            } else {
+                let mut spans = vec![Span::raw(format!("{:width$}  ", "", width = width))];
+                let style = line.style;
+                spans.extend(line.spans);
+                out.push(Line::from(spans).style(style));
+            }
+        }
+    }
+    out
+}
+
+// The same table as text, for the search and the detail cache.
+pub fn fields_text(
+    details: &Details,
+    manager: PackageManager,
+    context: &renderers::Context,
+) -> String {
+    field_lines(details, manager, context)
+        .iter()
+        .map(|line| {
+            let mut text: String = line
+                .spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect();
+            text.push('\n');
+            text
+        })
+        .collect()
+}
+
+// The Description synopsis in bold, found by its row in `fields_text`.
+pub fn emphasize_synopsis(lines: &mut [Line]) {
+    for line in lines {
+        let text: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        if text.starts_with("Description ") {
+            line.style = line.style.add_modifier(Modifier::BOLD);
+        }
+    }
+}
+
+// Dependency-like fields (Depends, Pre-Depends, Recommends, Requires,
+// Required-by, ...) with one relation per line, after the why-installed line
+// and, for pip, the state of each extra.
+pub fn deps_text(details: &Details, why: Option<String>, extras: Option<String>) -> String {
+    // Both lines end in a newline; a blank line separates them from the fields
+    let mut text: String = why.into_iter().chain(extras).collect();
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    for (key, value) in &details.fields {
+        let lower = key.to_lowercase();
+        let relation = [
+            "depend",
+            "require",
+            "recommend",
+            "suggest",
+            "conflict",
+            "breaks",
+        ]
+        .iter()
+        .any(|word| lower.contains(word));
+        if !relation || value.trim().is_empty() {
+            continue;
+        }
+        text.push_str(&format!("\n{}:\n", key));
+        for dep in value
+            .split(',')
+            .map(str::trim)
+            .filter(|dep| !dep.is_empty())
+        {
+            text.push_str(&format!("  {}\n", dep));
+        }
+    }
+    if text.is_empty() {
+        return "No dependencies listed".to_string();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::widgets::Paragraph;
+
+    fn context() -> renderers::Context {
+        renderers::Context {
+            largest: None,
+            now: 0,
+        }
+    }
+
+    fn fields(show: &str) -> Vec<String> {
+        let details = Details::parse(show);
+        fields_text(&details, PackageManager::Apt, &context())
+            .lines()
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn description_paragraphs_lists_and_examples() {
+        let show = "\
+Package: jq
+Version: 1.7.1
+Description: Command-line JSON processor
+ jq is like sed for JSON data.
+ It slices, filters and maps.
+ .
+ Features:
+   * no runtime dependencies
+   * written in portable C
+ .
+ Example:
+     jq '.[0] | {name}' data.json
+Homepage: https://jqlang.github.io/jq/
+";
+        assert_eq!(
+            fields(show),
+            [
+                "Package      jq",
+                "Version      1.7.1",
+                "Description  Command-line JSON processor",
+                "",
+                "             jq is like sed for JSON data.",
+                "             It slices, filters and maps.",
+                "",
+                "             Features:",
+                "               * no runtime dependencies",
+                "               * written in portable C",
+                "",
+                "             Example:",
+                "                 jq '.[0] | {name}' data.json",
+                "Homepage     https://jqlang.github.io/jq/",
+            ]
+        );
+        // Drawn as is in the pane
+        let lines = field_lines(&Details::parse(show), PackageManager::Apt, &context());
+        let mut terminal = Terminal::new(TestBackend::new(50, 14)).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new(lines), f.size()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                let row: String = (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect();
+                row.trim_end().to_string()
+            })
+            .collect();
+        assert_eq!(rows, fields(show));
+        let bold = |x, y| buffer.get(x, y).modifier.contains(Modifier::BOLD);
+        assert!(bold(13, 2) && !bold(13, 4));
+    }
+
+    #[test]
+    fn description_synopsis_alone_is_bold() {
+        let details = Details::parse("Package: tree\nDescription: Recursive directory lister\n");
+        let lines = field_lines(&details, PackageManager::Apt, &context());
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1].to_string(),
+            "Description  Recursive directory lister"
+        );
+        assert!(lines[1].style.add_modifier.contains(Modifier::BOLD));
+        assert!(!lines[0].style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn description_breaks_never_stack_or_trail() {
+        let show = "\
+Description: Synopsis
+ .
+ .
+ One.
+ .
+ .
+ Two.
+ .
+";
+        assert_eq!(
+            fields(show),
+            [
+                "Description  Synopsis",
+                "",
+                "             One.",
+                "",
+                "             Two.",
+            ]
+        );
+    }
+}
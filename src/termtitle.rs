@@ -0,0 +1,32 @@
+use std::io::{self, stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::{execute, terminal::SetTitle};
+
+// The terminal's title, with $TPI_TERMINAL_TITLE=1: what is listed, for a tpi
+// left in a background session or tmux window. The title from before is
+// pushed onto xterm's title stack the first time and popped on exit;
+// terminals without the stack ignore both and keep tpi's title.
+
+static PUSHED: AtomicBool = AtomicBool::new(false);
+
+const PUSH: &str = "\x1b[22;0t";
+const POP: &str = "\x1b[23;0t";
+
+pub fn set(title: &str) -> io::Result<()> {
+    let mut out = stdout();
+    if !PUSHED.swap(true, Ordering::Relaxed) {
+        out.write_all(PUSH.as_bytes())?;
+    }
+    execute!(out, SetTitle(title))
+}
+
+// Puts the old title back. Called once the screen is handed back, so it is
+// never written in the middle of leaving the alternate screen.
+pub fn restore() {
+    if PUSHED.swap(false, Ordering::Relaxed) {
+        let mut out = stdout();
+        let _ = out.write_all(POP.as_bytes());
+        let _ = out.flush();
+    }
+}
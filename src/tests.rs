@@ -0,0 +1,997 @@
+// The app driven like a terminal would, against canned command output:
+// scripted key events in, state and the drawn screen out.
+
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Output};
+use std::sync::{Mutex, OnceLock};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::backend::TestBackend;
+
+use super::*;
+
+const APT_LIST: &str = "Listing... Done
+bash/stable,now 5.2.21 aarch64 [installed]
+curl/stable,now 8.9.1 aarch64 [installed]
+libssl3/stable,now 3.3.1 aarch64 [installed,automatic]
+openssl/stable,now 3.3.1 aarch64 [installed]
+zlib/stable,now 1.3.1 aarch64 [installed,automatic]
+";
+
+const PIP_LIST: &str = "Package    Version
+---------- -------
+requests   2.32.3
+urllib3    2.2.2
+";
+
+// Answers each command line with the reply of the first prefix it starts
+// with; anything else succeeds with no output. Every line run is kept.
+pub struct Mock {
+    replies: Mutex<Vec<(String, String)>>,
+    failing: Vec<(String, String)>, // Prefixes that exit 100, and their stderr
+    delay: Duration,                // Before every answer, like a slow device
+    pub ran: Mutex<Vec<String>>,
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Self {
+            replies: Mutex::new(vec![
+                ("apt list --installed".to_string(), APT_LIST.to_string()),
+                ("pip list".to_string(), PIP_LIST.to_string()),
+            ]),
+            failing: Vec::new(),
+            delay: Duration::ZERO,
+            ran: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn reply(self, prefix: &str, output: &str) -> Self {
+        self.set_reply(prefix, output);
+        self
+    }
+
+    pub fn fail(mut self, prefix: &str, stderr: &str) -> Self {
+        self.failing.push((prefix.to_string(), stderr.to_string()));
+        self
+    }
+
+    pub fn slow(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    // Changes an answer from now on, as an install or removal would.
+    pub fn set_reply(&self, prefix: &str, output: &str) {
+        let mut replies = self.replies.lock().unwrap();
+        replies.retain(|(other, _)| other != prefix);
+        replies.insert(0, (prefix.to_string(), output.to_string()));
+    }
+
+    pub fn ran(&self, prefix: &str) -> usize {
+        let ran = self.ran.lock().unwrap();
+        ran.iter().filter(|line| line.starts_with(prefix)).count()
+    }
+}
+
+impl Runner for Mock {
+    fn output(&self, words: &[String]) -> io::Result<Output> {
+        thread::sleep(self.delay);
+        let line = words.join(" ");
+        self.ran.lock().unwrap().push(line.clone());
+        if let Some((_, stderr)) = self
+            .failing
+            .iter()
+            .find(|(prefix, _)| line.starts_with(prefix.as_str()))
+        {
+            return Ok(Output {
+                status: ExitStatus::from_raw(100 << 8),
+                stdout: Vec::new(),
+                stderr: stderr.clone().into_bytes(),
+            });
+        }
+        let stdout = self
+            .replies
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(prefix, _)| line.starts_with(prefix.as_str()))
+            .map(|(_, output)| output.clone())
+            .unwrap_or_default();
+        Ok(Output {
+            status: ExitStatus::from_raw(0),
+            stdout: stdout.into_bytes(),
+            stderr: Vec::new(),
+        })
+    }
+}
+
+// Every file tpi reads or writes goes under one directory per test run, so
+// nothing on the machine running the tests is used or touched. The
+// variables are the same for every test, which run in parallel.
+pub fn sandbox() -> PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    // Named once: TMPDIR is one of the variables moved into it
+    DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("tpi-tests-{}", std::process::id()));
+        fs::create_dir_all(dir.join("var/lib/dpkg")).unwrap();
+        for (name, value) in [
+            ("HOME", dir.clone()),
+            ("PREFIX", dir.clone()),
+            ("TMPDIR", dir.clone()),
+            ("XDG_CONFIG_HOME", dir.join("config")),
+            ("XDG_CACHE_HOME", dir.join("cache")),
+            ("XDG_DATA_HOME", dir.join("data")),
+            ("XDG_STATE_HOME", dir.join("state")),
+            ("TPI_CACHE_DIR", dir.join("cache")),
+            ("TPI_HISTORY", dir.join("history.tsv")),
+            ("TPI_HIDDEN", dir.join("hidden.tsv")),
+            ("TPI_LOCK", dir.join("tpi.lock")),
+            ("TPI_SESSIONS", dir.join("sessions")),
+            ("TPI_PIP_NO_UPGRADE", dir.join("no-upgrade.tsv")),
+        ] {
+            std::env::set_var(name, value);
+        }
+        std::env::set_var("TPI_MANAGERS", "apt,pip");
+        std::env::set_var("TPI_ALLOW_ROOT", "1");
+        std::env::remove_var("TPI_LOG");
+        std::env::remove_var("TPI_CONFIG");
+        std::env::remove_var("TPI_PROFILE");
+        std::env::remove_var("TPI_READ_ONLY");
+        // Run as root or not, the root banner stays out of the screens
+        root::dismiss().unwrap();
+        dir
+    })
+    .clone()
+}
+
+// The app on `runner`, its first list loaded.
+pub fn app(runner: Arc<dyn Runner>) -> App {
+    sandbox();
+    let mut app = App::with_runner(runner, &cli::Options::default());
+    // The first-run tour would take the keys
+    app.tour = None;
+    settle(&mut app);
+    app
+}
+
+// Waits for the list being loaded, as the event loop would.
+pub fn settle(app: &mut App) {
+    let began = Instant::now();
+    while app.loader.is_loading() {
+        app.poll_loading();
+        assert!(
+            began.elapsed() < Duration::from_secs(10),
+            "list never loaded"
+        );
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+pub fn press(app: &mut App, code: KeyCode) {
+    app.handle_event(Event::Key(KeyEvent::from(code)));
+}
+
+pub fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        press(app, KeyCode::Char(c));
+    }
+}
+
+// The screen as drawn on a `width` × `height` terminal, one line per row.
+pub fn screen(app: &mut App, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| app.ui(f)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..buffer.area.height {
+        for x in 0..buffer.area.width {
+            text.push_str(buffer.get(x, y).symbol());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Draws until the selected package's `show` has arrived, as the event
+// loop redraws when it does.
+pub fn screen_loaded(app: &mut App, width: u16, height: u16) -> String {
+    let began = Instant::now();
+    loop {
+        let text = screen(app, width, height);
+        if !text.contains("Loading details") {
+            return text;
+        }
+        assert!(
+            began.elapsed() < Duration::from_secs(10),
+            "details never loaded"
+        );
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+// Polls what `show` loads in the background for until `done` holds, as the
+// event loop would.
+fn wait_until(app: &mut App, done: impl Fn(&App) -> bool) {
+    let began = Instant::now();
+    while !done(app) {
+        app.package_list.shown.poll();
+        app.poll_dep_wait();
+        assert!(began.elapsed() < Duration::from_secs(10), "never got there");
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+fn selected_name(app: &App) -> Option<String> {
+    app.package_list.selected().map(|pkg| pkg.name.clone())
+}
+
+#[test]
+fn search_mark_switch_and_details() {
+    let mock = Arc::new(
+        Mock::new()
+            .reply(
+                "apt show openssl",
+                "Package: openssl\nVersion: 3.3.1\nDescription: Secure Sockets Layer toolkit\n",
+            )
+            .reply(
+                "apt show libssl3",
+                "Package: libssl3\nVersion: 3.3.1\nDescription: SSL shared libraries\n",
+            ),
+    );
+    let mut app = app(mock.clone());
+    assert_eq!(app.package_list.package_manager, PackageManager::Apt);
+    assert_eq!(app.package_list.items.len(), 5);
+
+    // "ssl" finds libssl3 first, then openssl
+    press(&mut app, KeyCode::Char('/'));
+    type_text(&mut app, "ssl");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(selected_name(&app).as_deref(), Some("libssl3"));
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(selected_name(&app).as_deref(), Some("openssl"));
+    press(&mut app, KeyCode::Char(' '));
+    let mut marked: Vec<&str> = app
+        .package_list
+        .marked
+        .iter()
+        .map(|(_, name)| name.as_str())
+        .collect();
+    marked.sort();
+    assert_eq!(marked, ["libssl3", "openssl"]);
+
+    let drawn = screen_loaded(&mut app, 80, 24);
+    assert!(drawn.contains("Secure Sockets Layer toolkit"), "{}", drawn);
+    assert!(drawn.contains("2 marked"), "{}", drawn);
+
+    // Tab lists pip, once Esc has cleared the search; the apt marks are
+    // kept for when it comes back
+    press(&mut app, KeyCode::Esc);
+    assert!(app.search.is_none());
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    assert_eq!(app.package_list.package_manager, PackageManager::Pip);
+    let names: Vec<&str> = app
+        .package_list
+        .items
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect();
+    assert_eq!(names, ["requests", "urllib3"]);
+    let drawn = screen(&mut app, 80, 24);
+    assert!(drawn.contains("requests"), "{}", drawn);
+    assert!(!drawn.contains("openssl"), "{}", drawn);
+
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    assert_eq!(app.package_list.package_manager, PackageManager::Apt);
+    assert_eq!(app.package_list.marked.len(), 2);
+    // From the list kept since, without listing again
+    assert_eq!(mock.ran("apt list --installed"), 1);
+    assert_eq!(mock.ran("pip list"), 1);
+}
+
+#[test]
+fn removing_the_selected_last_package_keeps_drawing() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock.clone());
+    press(&mut app, KeyCode::End);
+    assert_eq!(selected_name(&app).as_deref(), Some("zlib"));
+    press(&mut app, KeyCode::Char(' '));
+    screen(&mut app, 80, 24);
+
+    // zlib went away outside tpi; `r` lists again
+    let without_zlib: String = APT_LIST
+        .lines()
+        .filter(|line| !line.starts_with("zlib/"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    mock.set_reply("apt list --installed", &without_zlib);
+    press(&mut app, KeyCode::Char('r'));
+    // Struck through until the next refresh, then gone
+    assert_eq!(app.package_list.items.len(), 5);
+    screen(&mut app, 80, 24);
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(app.package_list.items.len(), 4);
+    // The nearest package is selected and zlib's mark is gone
+    assert_eq!(selected_name(&app).as_deref(), Some("openssl"));
+    assert!(app.package_list.marked.is_empty());
+    let drawn = screen(&mut app, 80, 24);
+    assert!(!drawn.contains("zlib"), "{}", drawn);
+
+    // Down to nothing at all, and a resize while empty
+    mock.set_reply("apt list --installed", "Listing... Done\n");
+    press(&mut app, KeyCode::Char('r'));
+    screen(&mut app, 80, 24);
+    press(&mut app, KeyCode::Char('r'));
+    assert!(app.package_list.items.is_empty());
+    assert_eq!(app.package_list.state.selected(), None);
+    app.handle_event(Event::Resize(40, 10));
+    press(&mut app, KeyCode::Down);
+    screen(&mut app, 40, 10);
+}
+
+#[test]
+fn first_frame_comes_before_the_list() {
+    sandbox();
+    let delay = Duration::from_millis(300);
+    let began = Instant::now();
+    let mut app = App::with_runner(Arc::new(Mock::new().slow(delay)), &cli::Options::default());
+    app.tour = None;
+    // Nothing on the way to the first draw waits for a command
+    let drawn = screen(&mut app, 80, 24);
+    assert!(began.elapsed() < delay, "{:?}", began.elapsed());
+    assert!(app.loader.is_loading());
+    // Empty, or the cached list, until it arrives
+    assert!(drawn.contains("loading…"), "{}", drawn);
+    settle(&mut app);
+    assert!(began.elapsed() >= delay);
+    assert_eq!(app.package_list.items.len(), 5);
+}
+
+#[test]
+fn first_frame_shows_loading() {
+    let mut terminal = Terminal::new(TestBackend::new(60, 16)).unwrap();
+    terminal.draw(startup::first_frame).unwrap();
+    let buffer = terminal.backend().buffer();
+    let text: String = (0..buffer.area.height)
+        .flat_map(|y| (0..buffer.area.width).map(move |x| (x, y)))
+        .map(|(x, y)| buffer.get(x, y).symbol())
+        .collect();
+    assert!(text.contains(i18n::text("list.loading")), "{}", text);
+    assert!(text.contains(i18n::text("details.title")), "{}", text);
+}
+
+// The key that opens each mode from the list, and the focus it gives.
+const MODES: [(KeyCode, Focus); 19] = [
+    (KeyCode::Char('?'), Focus::Help),
+    (KeyCode::Char('/'), Focus::Search),
+    (KeyCode::Char('m'), Focus::MarkPrompt),
+    (KeyCode::Char('e'), Focus::InventoryPrompt),
+    (KeyCode::Char('i'), Focus::InstallPrompt),
+    (KeyCode::Char('x'), Focus::Confirm),
+    (KeyCode::Enter, Focus::ActionMenu),
+    (KeyCode::Char('v'), Focus::GroupMenu),
+    (KeyCode::Char('t'), Focus::DepTree),
+    (KeyCode::Char('y'), Focus::Rdepends),
+    (KeyCode::Char('P'), Focus::Holds),
+    (KeyCode::Char('O'), Focus::Repos),
+    (KeyCode::Char('s'), Focus::Sessions),
+    (KeyCode::Char('E'), Focus::Export),
+    (KeyCode::Char('I'), Focus::Install),
+    (KeyCode::Char('X'), Focus::Impact),
+    (KeyCode::Char('L'), Focus::Stats),
+    (KeyCode::Char('D'), Focus::Growth),
+    (KeyCode::Char('!'), Focus::Diagnostics),
+];
+
+#[test]
+fn each_mode_takes_the_keys_until_closed() {
+    let mut app = app(Arc::new(Mock::new()));
+    app.root_banner = false;
+    for (open, focus) in MODES {
+        assert_eq!(app.focus(), Focus::List);
+        press(&mut app, KeyCode::Home);
+        press(&mut app, open);
+        assert_eq!(app.focus(), focus, "{:?}", open);
+        // The mode's status bar label
+        let drawn = screen(&mut app, 100, 30);
+        let label = format!(" {} ", focus.mode().label());
+        assert!(
+            drawn.lines().last().unwrap().starts_with(&label),
+            "{:?}: {}",
+            focus,
+            drawn
+        );
+
+        // Keys the list would act on go to the mode instead
+        press(&mut app, KeyCode::Char('j'));
+        if focus == Focus::Search {
+            // Typed into the query, which narrows the list
+            assert_eq!(app.search.as_ref().unwrap().query, "j");
+        } else {
+            assert_eq!(app.package_list.state.selected(), Some(0), "{:?}", focus);
+        }
+        assert_eq!(app.focus(), focus, "{:?}", focus);
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.focus(), Focus::List, "{:?} not closed by Esc", focus);
+    }
+    // And with everything closed, the list has them again
+    press(&mut app, KeyCode::Char('j'));
+    assert_eq!(app.package_list.state.selected(), Some(1));
+}
+
+#[test]
+fn the_first_thing_open_takes_the_keys() {
+    let mut app = app(Arc::new(Mock::new()));
+    // A kept search leaves the keys to the list: `n` steps its matches
+    press(&mut app, KeyCode::Char('/'));
+    type_text(&mut app, "ssl");
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.focus(), Focus::List);
+    assert_eq!(selected_name(&app).as_deref(), Some("libssl3"));
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(selected_name(&app).as_deref(), Some("openssl"));
+
+    // Help over it, and the tour over both
+    press(&mut app, KeyCode::Char('?'));
+    app.tour = Some(tour::Tour::new());
+    assert_eq!(app.focus(), Focus::Tour);
+    press(&mut app, KeyCode::Esc);
+    assert!(app.tour.is_none());
+    assert_eq!(app.focus(), Focus::Help);
+    press(&mut app, KeyCode::Char('n'));
+    assert_eq!(selected_name(&app).as_deref(), Some("openssl"));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.focus(), Focus::List);
+    assert_eq!(app.search.as_ref().unwrap().query, "ssl");
+}
+
+#[test]
+fn short_screens_page_the_details() {
+    let mut app = app(Arc::new(Mock::new()));
+    screen(&mut app, 40, 12);
+    assert_eq!(app.arrangement, layout::Arrangement::Compact);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.focus(), Focus::DetailsPage);
+    assert_eq!(app.focus().mode(), focus::Mode::Detail);
+    let drawn = screen(&mut app, 40, 12);
+    let label = format!(" {} ", focus::Mode::Detail.label());
+    assert!(
+        drawn.lines().last().unwrap().starts_with(&label),
+        "{}",
+        drawn
+    );
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(app.focus(), Focus::List);
+}
+
+#[test]
+fn nothing_listed_opens_the_failures_screen() {
+    let mock = Arc::new(
+        Mock::new()
+            .fail(
+                "apt list --installed",
+                "E: Could not open lock file /data/data/com.termux/files/usr/var/lib/dpkg/lock",
+            )
+            .fail("pip list", "\nTraceback (most recent call last):\n"),
+    );
+    let mut app = app(mock);
+    assert_eq!(app.focus(), Focus::Failures);
+    let drawn = screen(&mut app, 80, 12);
+    let lines: Vec<&str> = drawn.lines().map(str::trim_end).collect();
+    assert_eq!(
+        lines,
+        [
+            "┌Nothing could be loaded───────────────────────────────────────────────────────┐",
+            "│tpi couldn't list packages from any package manager it tried:                 │",
+            "│                                                                              │",
+            "│apt  Failed to list apt packages (exit 100): E: Could not open lock file      │",
+            "│/data/data/com.termux/files/usr/var/lib/dpkg/lock                             │",
+            "│  → run pkg update, then r to try again                                       │",
+            "│                                                                              │",
+            "│pip  Failed to list pip packages (exit 100): Traceback (most recent call      │",
+            "│last):                                                                        │",
+            "│  → run pip list yourself to see the whole error                              │",
+            "│                                                                              │",
+            "└ r retry · d doctor report · q quit ──────────────────────────────────────────┘",
+        ]
+    );
+    press(&mut app, KeyCode::Char('d'));
+    assert!(screen(&mut app, 80, 12).starts_with("┌Doctor report"));
+    press(&mut app, KeyCode::Esc);
+    assert_eq!(screen(&mut app, 80, 12), drawn);
+}
+
+// dpkg-query's line for each of `names`: curl and openssl need libssl3.
+fn dpkg_graph(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| {
+            let depends = match *name {
+                "curl" | "openssl" => ", libssl3",
+                _ => ", ",
+            };
+            format!("{}\tii \t{}\t\t\t\toptional\n", name, depends)
+        })
+        .collect()
+}
+
+// What the detail pane shows for the selected package, once `show` has
+// answered.
+fn details(app: &mut App) -> String {
+    let began = Instant::now();
+    loop {
+        let text = app.detail_content();
+        if !text.starts_with("Loading") {
+            return text;
+        }
+        assert!(
+            began.elapsed() < Duration::from_secs(10),
+            "details never loaded"
+        );
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+#[test]
+fn details_follow_the_list() {
+    let mock = Arc::new(
+        Mock::new()
+            .reply(
+                "dpkg-query -W",
+                &dpkg_graph(&["bash", "curl", "libssl3", "openssl", "zlib"]),
+            )
+            .reply("apt-mark showmanual", "curl\n"),
+    );
+    let mut app = app(mock.clone());
+    app.tabs.view = tabs::View::Raw;
+    app.package_list.select_package("libssl3");
+    assert!(details(&mut app).contains("Why installed: curl → libssl3"));
+
+    // curl went away outside tpi and openssl was marked manual; the same
+    // libssl3 is now there for openssl
+    let without_curl: String = APT_LIST
+        .lines()
+        .filter(|line| !line.starts_with("curl/"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    mock.set_reply("apt list --installed", &without_curl);
+    mock.set_reply(
+        "dpkg-query -W",
+        &dpkg_graph(&["bash", "libssl3", "openssl", "zlib"]),
+    );
+    mock.set_reply("apt-mark showmanual", "openssl\n");
+    press(&mut app, KeyCode::Char('r'));
+    assert_eq!(selected_name(&app).as_deref(), Some("libssl3"));
+    let text = details(&mut app);
+    assert!(
+        text.contains("Why installed: openssl → libssl3"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn details_follow_marked_removals() {
+    let mock = Arc::new(
+        Mock::new()
+            .reply(
+                "dpkg-query -W",
+                &dpkg_graph(&["bash", "curl", "libssl3", "openssl", "zlib"]),
+            )
+            .reply("apt-mark showmanual", "curl\nopenssl\n"),
+    );
+    let mut app = app(mock.clone());
+    app.tabs.view = tabs::View::Raw;
+    app.package_list.select_package("libssl3");
+    let before = details(&mut app);
+    assert!(
+        before.contains("Why installed: curl → libssl3"),
+        "{}",
+        before
+    );
+
+    // Marking changes nothing in the pane
+    app.package_list.select_package("curl");
+    press(&mut app, KeyCode::Char(' '));
+    app.package_list.select_package("libssl3");
+    press(&mut app, KeyCode::Char(' '));
+    assert_eq!(app.package_list.marked.len(), 2);
+    assert_eq!(details(&mut app), before);
+
+    // Removing the marked curl leaves libssl3 to openssl, as the removal
+    // run in the terminal would
+    let without_curl: String = APT_LIST
+        .lines()
+        .filter(|line| !line.starts_with("curl/"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    mock.set_reply("apt list --installed", &without_curl);
+    mock.set_reply(
+        "dpkg-query -W",
+        &dpkg_graph(&["bash", "libssl3", "openssl", "zlib"]),
+    );
+    mock.set_reply("apt-mark showmanual", "openssl\n");
+    let names = ["curl".to_string()];
+    let words = ["apt".to_string(), "remove".to_string(), "curl".to_string()];
+    app.finish_foreground(Action::Remove, &names, &words, Ok(ExitStatus::from_raw(0)));
+    assert_eq!(app.message.as_deref(), Some("Removed curl"));
+    assert_eq!(selected_name(&app).as_deref(), Some("libssl3"));
+    let text = details(&mut app);
+    assert!(
+        text.contains("Why installed: openssl → libssl3"),
+        "{}",
+        text
+    );
+}
+
+#[test]
+fn details_follow_do_not_upgrade() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock);
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    assert_eq!(app.package_list.package_manager, PackageManager::Pip);
+    app.tabs.view = tabs::View::Raw;
+    app.package_list.select_package("urllib3");
+    assert!(!details(&mut app).contains("Do not upgrade"));
+
+    press(&mut app, KeyCode::Char('U'));
+    let text = details(&mut app);
+    assert!(text.contains("Do not upgrade with pip:"), "{}", text);
+    // Undo and redo move it too
+    press(&mut app, KeyCode::Char('u'));
+    assert!(!details(&mut app).contains("Do not upgrade"));
+    app.handle_event(Event::Key(KeyEvent::new(
+        KeyCode::Char('r'),
+        KeyModifiers::CONTROL,
+    )));
+    assert!(details(&mut app).contains("Do not upgrade with pip:"));
+    press(&mut app, KeyCode::Char('U'));
+    assert!(!details(&mut app).contains("Do not upgrade"));
+}
+
+#[test]
+fn debounced_details_run_show_where_the_selection_rests() {
+    let mock = Arc::new(Mock::new().reply(
+        "apt show zlib",
+        "Package: zlib\nVersion: 1.3.1\nDescription: Compression library\n",
+    ));
+    let mut app = app(mock.clone());
+    app.details_fetch = preview::Fetch::Debounced;
+    app.details_delay = Duration::from_millis(300);
+    app.observe_rest();
+    // Scrolling past runs nothing
+    for _ in 0..4 {
+        press(&mut app, KeyCode::Down);
+        app.observe_rest();
+        let drawn = screen(&mut app, 80, 24);
+        assert!(drawn.contains("Loading details"), "{}", drawn);
+    }
+    assert_eq!(selected_name(&app).as_deref(), Some("zlib"));
+    assert_eq!(mock.ran("apt show"), 0);
+
+    thread::sleep(Duration::from_millis(300));
+    assert!(app.observe_rest());
+    let drawn = screen_loaded(&mut app, 80, 24);
+    assert!(drawn.contains("Compression library"), "{}", drawn);
+    assert_eq!(mock.ran("apt show"), 1);
+    assert_eq!(mock.ran("apt show zlib"), 1);
+
+    // Dwelling shows what the list knows until then
+    app.details_fetch = preview::Fetch::Dwell;
+    press(&mut app, KeyCode::Up);
+    app.observe_rest();
+    let drawn = screen(&mut app, 80, 24);
+    assert!(drawn.contains("openssl 3.3.1"), "{}", drawn);
+    assert!(
+        drawn.contains("Details show once the selection rests"),
+        "{}",
+        drawn
+    );
+    assert_eq!(mock.ran("apt show"), 1);
+}
+
+#[test]
+fn root_banner_dismissed_for_good() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock.clone());
+    assert!(!app.root_banner);
+    // As a root run first shows it
+    app.root_banner = true;
+    let drawn = screen(&mut app, 100, 24);
+    assert!(
+        drawn.lines().next().unwrap().contains("Running as root"),
+        "{}",
+        drawn
+    );
+    press(&mut app, KeyCode::Char('x'));
+    assert!(!app.root_banner);
+    // x only dismissed it, and a later start remembers
+    assert_eq!(app.focus(), Focus::List);
+    assert!(root::dismissed());
+    let drawn = screen(&mut app, 100, 24);
+    assert!(!drawn.contains("Running as root"), "{}", drawn);
+    let again = self::app(mock);
+    assert!(!again.root_banner);
+}
+
+#[test]
+fn bulk_marks_undone_and_redone_across_tabs() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock);
+    press(&mut app, KeyCode::Char('a'));
+    assert_eq!(app.package_list.marked_names().len(), 5);
+    app.package_list.select_package("curl");
+    press(&mut app, KeyCode::Char(' '));
+    assert_eq!(app.package_list.marked_names().len(), 4);
+
+    // Undo reaches the apt marks from the pip tab too
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    press(&mut app, KeyCode::Char('u'));
+    assert_eq!(app.message.as_deref(), Some("undid: unmark curl"));
+    press(&mut app, KeyCode::Char('u'));
+    assert_eq!(app.message.as_deref(), Some("undid: mark 5 packages"));
+    press(&mut app, KeyCode::Char('u'));
+    assert_eq!(app.message.as_deref(), Some("Nothing to undo"));
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    assert!(app.package_list.marked_names().is_empty());
+
+    app.handle_event(Event::Key(KeyEvent::new(
+        KeyCode::Char('r'),
+        KeyModifiers::CONTROL,
+    )));
+    assert_eq!(app.message.as_deref(), Some("redid: mark 5 packages"));
+    assert_eq!(app.package_list.marked_names().len(), 5);
+    // A new change drops the redo of the unmark
+    press(&mut app, KeyCode::Char('A'));
+    app.handle_event(Event::Key(KeyEvent::new(
+        KeyCode::Char('r'),
+        KeyModifiers::CONTROL,
+    )));
+    assert_eq!(app.message.as_deref(), Some("Nothing to redo"));
+    assert!(app.package_list.marked_names().is_empty());
+}
+
+#[test]
+fn roll_back_to_the_cached_previous_version_then_offer_a_hold() {
+    let listed = |gzip: &str| {
+        format!(
+            "{}gzip/stable,now {} aarch64 [installed]\nnano/stable,now 8.1 aarch64 [installed]\n",
+            APT_LIST, gzip
+        )
+    };
+    let mock = Arc::new(Mock::new().reply("apt list --installed", &listed("1.13")));
+    let dir = sandbox();
+    fs::create_dir_all(dir.join("var/log")).unwrap();
+    fs::write(
+        dir.join("var/log/dpkg.log.9"),
+        "2024-05-01 10:20:30 upgrade gzip:aarch64 1.12 1.13\n\
+         2024-05-01 10:20:31 upgrade nano:aarch64 8.0 8.1\n",
+    )
+    .unwrap();
+    let archives = rollback::archives();
+    fs::create_dir_all(&archives).unwrap();
+    for file in ["gzip_1.11_aarch64.deb", "gzip_1.12_aarch64.deb"] {
+        fs::write(archives.join(file), "").unwrap();
+    }
+    let mut app = app(mock.clone());
+
+    // dpkg's logs are read in the background the first time they're needed
+    app.package_list.select_package("gzip");
+    press(&mut app, KeyCode::Char('<'));
+    assert_eq!(
+        app.message.as_deref(),
+        Some("dpkg's logs are still being read")
+    );
+    wait_until(&mut app, |app| app.package_list.dpkg_log.is_read());
+
+    // Nothing cached for nano: where older builds might be instead
+    app.package_list.select_package("nano");
+    press(&mut app, KeyCode::Char('<'));
+    assert!(app.confirm.is_none());
+    let url = &app.no_rollback.as_ref().unwrap().url;
+    assert!(url.ends_with("/pool/main/n/nano/"), "{}", url);
+    press(&mut app, KeyCode::Esc);
+    assert!(app.no_rollback.is_none());
+
+    app.package_list.select_package("gzip");
+    press(&mut app, KeyCode::Char('<'));
+    let confirm = app.confirm.as_ref().unwrap();
+    assert_eq!(confirm.title, "Roll back gzip from 1.13 to 1.12?");
+    let deb = archives.join("gzip_1.12_aarch64.deb");
+    let deb = deb.to_string_lossy().into_owned();
+    assert_eq!(confirm.lines[0], format!("apt install {}", deb));
+    assert!(confirm.lines[2].starts_with("One older .deb is cached too"));
+    press(&mut app, KeyCode::Char('y'));
+    let paths = vec![deb.clone()];
+    assert_eq!(
+        app.foreground,
+        Some((PackageManager::Apt, Action::Install, paths.clone()))
+    );
+
+    // As the install run in the terminal would
+    app.foreground = None;
+    mock.set_reply("apt list --installed", &listed("1.12"));
+    let words = ["apt".to_string(), "install".to_string(), deb];
+    app.finish_foreground(Action::Install, &paths, &words, Ok(ExitStatus::from_raw(0)));
+    assert_eq!(app.message.as_deref(), Some("Rolled back gzip to 1.12"));
+    let hold = app.confirm.as_ref().unwrap();
+    assert_eq!(hold.title, "Hold gzip at 1.12?");
+    assert_eq!(hold.value, (Action::Hold, vec!["gzip".to_string()]));
+}
+
+#[test]
+fn marked_packages_removed_and_exported_together() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock.clone());
+    // A pip mark stays out of apt's batches
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    app.package_list.select_package("requests");
+    press(&mut app, KeyCode::Char(' '));
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    for name in ["zlib", "curl"] {
+        app.package_list.select_package(name);
+        press(&mut app, KeyCode::Char(' '));
+    }
+
+    let path = sandbox().join("marked.csv");
+    app.inventory_prompt = Some(path.to_string_lossy().into_owned());
+    let drawn = screen(&mut app, 100, 24);
+    assert!(drawn.contains("Export 2 marked packages"), "{}", drawn);
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.message,
+        Some(format!("Exported 2 marked packages to {}", path.display()))
+    );
+    let csv = fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        csv.lines().collect::<Vec<_>>(),
+        ["name,version,manager", "curl,8.9.1,apt", "zlib,1.3.1,apt"]
+    );
+
+    press(&mut app, KeyCode::Char('x'));
+    let confirm = app.confirm.as_ref().unwrap();
+    assert_eq!(confirm.title, "Remove 2 packages?");
+    assert_eq!(confirm.lines, ["apt remove curl zlib"]);
+    press(&mut app, KeyCode::Char('y'));
+    let names = vec!["curl".to_string(), "zlib".to_string()];
+    assert_eq!(
+        app.foreground,
+        Some((PackageManager::Apt, Action::Remove, names.clone()))
+    );
+
+    // apt kept zlib, as it would when something still needs it
+    app.foreground = None;
+    let without_curl: String = APT_LIST
+        .lines()
+        .filter(|line| !line.starts_with("curl/"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    mock.set_reply("apt list --installed", &without_curl);
+    let words: Vec<String> = ["apt", "remove", "curl", "zlib"].map(String::from).into();
+    app.finish_foreground(Action::Remove, &names, &words, Ok(ExitStatus::from_raw(0)));
+    assert_eq!(
+        app.message.as_deref(),
+        Some("apt remove curl zlib ran, but zlib is still installed")
+    );
+}
+
+#[test]
+fn dependency_tree_goes_to_installed_dependencies() {
+    let mock = Arc::new(
+        Mock::new()
+            .reply(
+                "pip show requests",
+                "Name: requests\nVersion: 2.32.3\nRequires: Urllib3, chardet\n",
+            )
+            .reply(
+                "pip show urllib3",
+                "Name: urllib3\nVersion: 2.2.2\nRequires: \n",
+            ),
+    );
+    let mut app = app(mock);
+    press(&mut app, KeyCode::Tab);
+    settle(&mut app);
+    app.package_list.select_package("requests");
+    press(&mut app, KeyCode::Char('t'));
+    // `show` runs in the background first
+    assert_eq!(
+        app.message.as_deref(),
+        Some("Loading the dependencies of requests…")
+    );
+    wait_until(&mut app, |app| app.dep_tree.is_some());
+    assert_eq!(app.focus(), Focus::DepTree);
+    assert_eq!(app.message, None);
+    let drawn = screen(&mut app, 100, 24);
+    assert!(drawn.contains("Dependencies of requests"), "{}", drawn);
+    assert!(drawn.contains("▸ Urllib3"), "{}", drawn);
+    assert!(drawn.contains("· chardet not installed"), "{}", drawn);
+
+    press(&mut app, KeyCode::Char('j'));
+    press(&mut app, KeyCode::Enter);
+    assert_eq!(app.message.as_deref(), Some("chardet is not installed"));
+    assert_eq!(app.focus(), Focus::DepTree);
+    // pip's spelling in Requires finds the listed package
+    press(&mut app, KeyCode::Char('k'));
+    press(&mut app, KeyCode::Char('l'));
+    wait_until(&mut app, |app| app.dep_wait.is_none());
+    assert!(screen(&mut app, 100, 24).contains("▾ Urllib3"));
+    press(&mut app, KeyCode::Enter);
+    assert!(app.dep_tree.is_none());
+    assert_eq!(selected_name(&app).as_deref(), Some("urllib3"));
+}
+
+#[test]
+fn sections_filter_column_and_grouping() {
+    let mock = Arc::new(Mock::new());
+    let mut app = app(mock);
+    press(&mut app, KeyCode::Char('#'));
+    assert_eq!(
+        app.message.as_deref(),
+        Some("No sections loaded from the dpkg status file")
+    );
+    // As the dpkg status file would give them
+    for pkg in &mut app.package_list.items {
+        pkg.section = match pkg.name.as_str() {
+            "bash" => Some("shells".to_string()),
+            "curl" => Some("net".to_string()),
+            "openssl" => Some("contrib/net".to_string()),
+            "libssl3" => Some("libs".to_string()),
+            _ => None,
+        };
+    }
+    app.package_list.rebuild_rows();
+
+    press(&mut app, KeyCode::Char('#'));
+    let drawn = screen(&mut app, 100, 24);
+    for line in [
+        "All sections",
+        "Network (net)",
+        "Libraries (libs)",
+        "Shells (shells)",
+        "(unsectioned)",
+        "Show section column",
+    ] {
+        assert!(drawn.contains(line), "{}\n{}", line, drawn);
+    }
+    press(&mut app, KeyCode::Down);
+    press(&mut app, KeyCode::Enter);
+    assert!(app.section_menu.is_none());
+    let shown = app.package_list.visible_names(None);
+    assert_eq!(shown, ["curl", "openssl"]);
+
+    // The column, from the menu's last entry; it opens on the current one
+    press(&mut app, KeyCode::Char('#'));
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Up);
+    press(&mut app, KeyCode::Enter);
+    assert!(app.show_section);
+    let drawn = screen(&mut app, 100, 24);
+    let row = drawn.lines().find(|line| line.contains("curl")).unwrap();
+    assert!(row.contains("curl 8.9.1 aarch64 net"), "{}", row);
+
+    app.package_list.filter = None;
+    app.package_list.group_by = GroupBy::Section;
+    app.package_list.rebuild_rows();
+    let drawn = screen(&mut app, 100, 24);
+    let first = |title: &str| drawn.find(title).unwrap_or(usize::MAX);
+    assert!(
+        first("Libraries (libs)") < first("Network (net)"),
+        "{}",
+        drawn
+    );
+    assert!(
+        first("Shells (shells)") < first("(unsectioned)"),
+        "{}",
+        drawn
+    );
+}
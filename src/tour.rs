@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ratatui::prelude::*;
+
+use crate::spotlight::Spotlight;
+use crate::{config, log};
+
+// The parts of the screen the tour stops at, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    List,
+    Details,
+    Tabs,
+    Status,
+}
+
+impl Stop {
+    pub const ALL: [Stop; 4] = [Stop::List, Stop::Details, Stop::Tabs, Stop::Status];
+
+    fn title(self) -> &'static str {
+        match self {
+            Stop::List => "The package list",
+            Stop::Details => "Package details",
+            Stop::Tabs => "Detail views",
+            Stop::Status => "The status line",
+        }
+    }
+
+    // Two or three keys worth knowing here.
+    fn keys(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Stop::List => &[
+                ("j/k", "move; Enter opens the actions for a package"),
+                ("Space", "marks packages; x, e, I and B then act on them"),
+                ("Tab", "switches to the next package manager"),
+            ],
+            Stop::Details => &[
+                ("J/K", "scroll the details, PgDn/PgUp by a page"),
+                ("+/-", "make this pane taller or shorter"),
+                ("/", "searches the list and this pane"),
+            ],
+            Stop::Tabs => &[
+                (
+                    "[ ]",
+                    "switch view: Raw, Fields, Files, Deps, Policy, Timeline",
+                ),
+                ("f", "the files a package installed"),
+            ],
+            Stop::Status => &[
+                (
+                    "",
+                    "counts, installed size and messages, which go after a few seconds",
+                ),
+                ("!", "diagnostics, when listing lines weren't understood"),
+                ("?", "lists every key; the right of this line names a few"),
+            ],
+        }
+    }
+}
+
+// The first-run tour: a spotlight on each part of the screen in turn. Enter
+// moves on, ← goes back and Esc ends it early; either way it isn't shown on
+// later starts.
+pub struct Tour {
+    step: usize,
+}
+
+impl Tour {
+    pub fn new() -> Self {
+        Self { step: 0 }
+    }
+
+    pub fn stop(&self) -> Stop {
+        Stop::ALL[self.step]
+    }
+
+    // False past the last stop.
+    pub fn next(&mut self) -> bool {
+        self.step += 1;
+        self.step < Stop::ALL.len()
+    }
+
+    pub fn back(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+
+    pub fn render(&self, f: &mut Frame, target: Rect) {
+        let stop = self.stop();
+        let key = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        let lines = stop
+            .keys()
+            .iter()
+            .map(|(keys, what)| {
+                Line::from(vec![
+                    Span::styled(format!("{:<6}", keys), key),
+                    Span::raw(*what),
+                ])
+            })
+            .collect();
+        let last = self.step + 1 == Stop::ALL.len();
+        Spotlight {
+            target,
+            title: format!(
+                "Tour {}/{}: {}",
+                self.step + 1,
+                Stop::ALL.len(),
+                stop.title()
+            ),
+            lines,
+            footer: format!(
+                "Enter {} · ← back · Esc ends the tour",
+                if last { "done" } else { "next" }
+            ),
+        }
+        .render(f);
+    }
+}
+
+// $XDG_STATE_HOME/tpi/tour-done (~/.local/state by default), created once the
+// tour is finished or ended.
+fn marker() -> Option<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| config::home().map(|home| home.join(".local/state")))?;
+    Some(state.join("tpi").join("tour-done"))
+}
+
+// Without a place to remember it, as if done, so it isn't shown on every start.
+pub fn completed() -> bool {
+    marker().is_none_or(|path| path.exists())
+}
+
+pub fn complete() {
+    let Some(path) = marker() else {
+        return;
+    };
+    let written = match path.parent() {
+        Some(dir) => fs::create_dir_all(dir).and_then(|()| fs::write(&path, "")),
+        None => fs::write(&path, ""),
+    };
+    if let Err(err) = written {
+        log::debug(&format!("tour: {}: {}", path.display(), err));
+    }
+}
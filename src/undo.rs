@@ -0,0 +1,237 @@
+use crate::{i18n, PackageManager};
+
+// How many changes `u` can go back; older ones are dropped.
+const DEPTH: usize = 100;
+
+// A change to tpi's own bookkeeping, with what it takes to go back. Only
+// what tpi keeps for itself is here: installs, removals and holds change
+// the system and are confirmed before they run instead.
+#[derive(Clone, Debug)]
+pub enum Change {
+    // Packages marked and unmarked, by identity like the marks themselves
+    Marks {
+        marked: Vec<(PackageManager, String)>,
+        unmarked: Vec<(PackageManager, String)>,
+    },
+    // Packages hidden (or shown again), only those whose state changed
+    Hidden {
+        manager: PackageManager,
+        names: Vec<String>,
+        hidden: bool,
+    },
+    // A pip package's do-not-upgrade entry: its reason before and after
+    NoUpgrade {
+        name: String,
+        before: Option<String>,
+        after: Option<String>,
+    },
+}
+
+impl Change {
+    // The change in the opposite direction.
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::Marks { marked, unmarked } => Self::Marks {
+                marked: unmarked,
+                unmarked: marked,
+            },
+            Self::Hidden {
+                manager,
+                names,
+                hidden,
+            } => Self::Hidden {
+                manager,
+                names,
+                hidden: !hidden,
+            },
+            Self::NoUpgrade {
+                name,
+                before,
+                after,
+            } => Self::NoUpgrade {
+                name,
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    // What the status bar says it did: "unmark 37 packages".
+    pub fn label(&self) -> String {
+        match self {
+            Self::Marks { marked, unmarked } => match (marked.as_slice(), unmarked.as_slice()) {
+                ([(_, name)], []) => i18n::fill("undo.mark", &[("name", name)]),
+                ([], [(_, name)]) => i18n::fill("undo.unmark", &[("name", name)]),
+                (marked, []) => i18n::fill("undo.mark_count", &[("count", &marked.len())]),
+                ([], unmarked) => i18n::fill("undo.unmark_count", &[("count", &unmarked.len())]),
+                (marked, unmarked) => i18n::fill(
+                    "undo.mark_and_unmark",
+                    &[("marked", &marked.len()), ("unmarked", &unmarked.len())],
+                ),
+            },
+            Self::Hidden { names, hidden, .. } => match (names.as_slice(), *hidden) {
+                ([name], true) => i18n::fill("undo.hide", &[("name", name)]),
+                ([name], false) => i18n::fill("undo.unhide", &[("name", name)]),
+                (names, true) => i18n::fill("undo.hide_count", &[("count", &names.len())]),
+                (names, false) => i18n::fill("undo.unhide_count", &[("count", &names.len())]),
+            },
+            Self::NoUpgrade {
+                name, after: None, ..
+            } => i18n::fill("undo.allow_upgrades", &[("name", name)]),
+            Self::NoUpgrade { name, .. } => i18n::fill("undo.stop_upgrades", &[("name", name)]),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Marks { marked, unmarked } => marked.is_empty() && unmarked.is_empty(),
+            Self::Hidden { names, .. } => names.is_empty(),
+            Self::NoUpgrade { before, after, .. } => before == after,
+        }
+    }
+}
+
+// The session's changes, for `u` and Ctrl+r. A new change drops what was
+// undone before it, as in an editor.
+#[derive(Default)]
+pub struct History {
+    done: Vec<Change>,
+    undone: Vec<Change>,
+}
+
+impl History {
+    pub fn record(&mut self, change: Change) {
+        if change.is_empty() {
+            return;
+        }
+        self.undone.clear();
+        self.done.push(change);
+        if self.done.len() > DEPTH {
+            self.done.remove(0);
+        }
+    }
+
+    // The last change, to be reversed; it can be redone afterwards.
+    pub fn undo(&mut self) -> Option<Change> {
+        let change = self.done.pop()?;
+        self.undone.push(change.clone());
+        Some(change)
+    }
+
+    pub fn redo(&mut self) -> Option<Change> {
+        let change = self.undone.pop()?;
+        self.done.push(change.clone());
+        Some(change)
+    }
+
+    pub fn last(&self) -> Option<&Change> {
+        self.done.last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PackageManager::*;
+
+    fn marks(marked: &[&str], unmarked: &[&str]) -> Change {
+        let keys = |names: &[&str]| names.iter().map(|n| (Apt, n.to_string())).collect();
+        Change::Marks {
+            marked: keys(marked),
+            unmarked: keys(unmarked),
+        }
+    }
+
+    fn labels(history: &History) -> Vec<String> {
+        history.done.iter().map(Change::label).collect()
+    }
+
+    #[test]
+    fn inverse_swaps_directions() {
+        assert_eq!(
+            marks(&["a"], &["b", "c"]).inverse().label(),
+            "mark 2, unmark 1"
+        );
+        let hidden = Change::Hidden {
+            manager: Pip,
+            names: vec!["pip".to_string()],
+            hidden: true,
+        };
+        assert_eq!(hidden.label(), "hide pip");
+        assert_eq!(hidden.inverse().label(), "unhide pip");
+        let held = Change::NoUpgrade {
+            name: "numpy".to_string(),
+            before: None,
+            after: Some("pinned by scipy".to_string()),
+        };
+        assert_eq!(held.label(), "stop pip upgrades of numpy");
+        assert_eq!(held.inverse().label(), "allow pip upgrades of numpy");
+        assert_eq!(held.inverse().inverse().label(), held.label());
+    }
+
+    #[test]
+    fn labels_count_bulk_changes() {
+        assert_eq!(marks(&["curl"], &[]).label(), "mark curl");
+        assert_eq!(marks(&[], &["curl"]).label(), "unmark curl");
+        assert_eq!(marks(&["a", "b", "c"], &[]).label(), "mark 3 packages");
+        assert_eq!(marks(&[], &["a", "b"]).label(), "unmark 2 packages");
+        let hidden = Change::Hidden {
+            manager: Apt,
+            names: vec!["a".to_string(), "b".to_string()],
+            hidden: false,
+        };
+        assert_eq!(hidden.label(), "unhide 2 packages");
+    }
+
+    #[test]
+    fn empty_changes_are_not_kept() {
+        let mut history = History::default();
+        history.record(marks(&[], &[]));
+        history.record(Change::NoUpgrade {
+            name: "numpy".to_string(),
+            before: Some("x".to_string()),
+            after: Some("x".to_string()),
+        });
+        assert!(history.last().is_none());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn undo_then_redo_in_order() {
+        let mut history = History::default();
+        history.record(marks(&["a"], &[]));
+        history.record(marks(&["b"], &[]));
+        assert_eq!(history.undo().unwrap().label(), "mark b");
+        assert_eq!(history.undo().unwrap().label(), "mark a");
+        assert!(history.undo().is_none());
+        assert_eq!(history.redo().unwrap().label(), "mark a");
+        assert_eq!(history.redo().unwrap().label(), "mark b");
+        assert!(history.redo().is_none());
+        assert_eq!(labels(&history), ["mark a", "mark b"]);
+    }
+
+    #[test]
+    fn a_new_change_drops_what_was_undone() {
+        let mut history = History::default();
+        history.record(marks(&["a"], &[]));
+        history.record(marks(&["b"], &[]));
+        history.undo();
+        history.record(marks(&["c"], &[]));
+        assert!(history.redo().is_none());
+        assert_eq!(labels(&history), ["mark a", "mark c"]);
+    }
+
+    #[test]
+    fn only_the_last_hundred_are_kept() {
+        let mut history = History::default();
+        for n in 0..DEPTH + 5 {
+            history.record(marks(&[&n.to_string()], &[]));
+        }
+        assert_eq!(history.done.len(), DEPTH);
+        assert_eq!(history.done[0].label(), "mark 5");
+        assert_eq!(
+            history.last().unwrap().label(),
+            format!("mark {}", DEPTH + 4)
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::details::Details;
+use crate::{format_size, Package};
+
+// Fields holding comma-separated package relations, compared item by item.
+const RELATIONS: [&str; 7] = [
+    "Depends",
+    "Pre-Depends",
+    "Recommends",
+    "Suggests",
+    "Provides",
+    "Requires",
+    "Required-by",
+];
+
+// Fields that differ on every upgrade and say nothing beyond the version.
+const NOISE: [&str; 4] = ["Version", "Installed-Size", "Filename", "SHA256"];
+
+// A package whose version changed between two listings.
+pub struct Upgrade {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub size: (Option<u64>, Option<u64>), // KiB before and after, when the manager reports sizes
+    pub before: Option<Details>,          // As shown before, when the package had been looked at
+}
+
+impl Upgrade {
+    pub fn size_delta(&self) -> Option<i64> {
+        match self.size {
+            (Some(before), Some(after)) => Some(after as i64 - before as i64),
+            _ => None,
+        }
+    }
+}
+
+// Packages in both listings whose version differs, with their sizes and the
+// details cached before (`shown`, by name and old version).
+pub fn collect(
+    old: &[Package],
+    old_sizes: &HashMap<String, u64>,
+    shown: &HashMap<(String, String), Details>,
+    new: &[Package],
+    new_sizes: &HashMap<String, u64>,
+) -> Vec<Upgrade> {
+    let old_versions: HashMap<&str, &str> = old
+        .iter()
+        .map(|pkg| (pkg.name.as_str(), pkg.version.as_str()))
+        .collect();
+    new.iter()
+        .filter_map(|pkg| {
+            let from = *old_versions.get(pkg.name.as_str())?;
+            (from != pkg.version).then(|| Upgrade {
+                name: pkg.name.clone(),
+                from: from.to_string(),
+                to: pkg.version.clone(),
+                size: (
+                    old_sizes.get(&pkg.name).copied(),
+                    new_sizes.get(&pkg.name).copied(),
+                ),
+                before: shown.get(&(pkg.name.clone(), from.to_string())).cloned(),
+            })
+        })
+        .collect()
+}
+
+fn signed_size(kib: i64) -> String {
+    format!(
+        "{}{}",
+        if kib < 0 { "-" } else { "+" },
+        format_size(kib.unsigned_abs())
+    )
+}
+
+fn relations(value: &str) -> BTreeSet<String> {
+    value
+        .split(',')
+        .map(|item| item.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Field by field: relations as added and removed items, other fields as the
+// old and new value. Fields that change with every upgrade are left out;
+// the version and size are in the summary line.
+fn field_diff(before: &Details, after: &Details) -> Vec<Line<'static>> {
+    let added = Style::default().fg(Color::Green);
+    let removed = Style::default().fg(Color::Red);
+    let mut keys: Vec<&str> = after.fields.iter().map(|(key, _)| key.as_str()).collect();
+    for (key, _) in &before.fields {
+        if !keys.iter().any(|known| known.eq_ignore_ascii_case(key)) {
+            keys.push(key);
+        }
+    }
+    let mut lines = Vec::new();
+    for key in keys {
+        if NOISE.iter().any(|noise| noise.eq_ignore_ascii_case(key)) {
+            continue;
+        }
+        let (old, new) = (before.get(key).unwrap_or(""), after.get(key).unwrap_or(""));
+        if old == new {
+            continue;
+        }
+        if RELATIONS
+            .iter()
+            .any(|relation| relation.eq_ignore_ascii_case(key))
+        {
+            let (old, new) = (relations(old), relations(new));
+            lines.push(Line::from(format!("{}:", key)).bold());
+            for item in new.difference(&old) {
+                lines.push(Line::styled(format!("  + {}", item), added));
+            }
+            for item in old.difference(&new) {
+                lines.push(Line::styled(format!("  - {}", item), removed));
+            }
+            continue;
+        }
+        lines.push(Line::from(format!("{}:", key)).bold());
+        if !old.is_empty() {
+            for line in old.lines() {
+                lines.push(Line::styled(format!("  - {}", line.trim()), removed));
+            }
+        }
+        if !new.is_empty() {
+            for line in new.lines() {
+                lines.push(Line::styled(format!("  + {}", line.trim()), added));
+            }
+        }
+    }
+    lines
+}
+
+// The popup after a refresh that found upgraded packages: each with its
+// version change and size delta, and on Enter what changed in its details.
+pub struct UpgradesView {
+    pub upgrades: Vec<Upgrade>,
+    pub state: ListState,
+    pub by_delta: bool, // Largest growth first, instead of by name
+    pub opened: Option<(usize, Details)>, // The package shown, with its details now
+    pub scroll: u16,
+}
+
+impl UpgradesView {
+    pub fn new(upgrades: Vec<Upgrade>) -> Self {
+        let mut view = Self {
+            upgrades,
+            state: ListState::default(),
+            by_delta: false,
+            opened: None,
+            scroll: 0,
+        };
+        view.sort();
+        view.state.select(Some(0));
+        view
+    }
+
+    pub fn toggle_sort(&mut self) {
+        self.by_delta = !self.by_delta;
+        self.sort();
+        self.state.select(Some(0));
+    }
+
+    // Unknown deltas sort last either way.
+    fn sort(&mut self) {
+        if self.by_delta {
+            self.upgrades.sort_by(|a, b| {
+                let key = |upgrade: &Upgrade| upgrade.size_delta().map(std::cmp::Reverse);
+                match (key(a), key(b)) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (a, b) => b.is_some().cmp(&a.is_some()),
+                }
+                .then_with(|| a.name.cmp(&b.name))
+            });
+        } else {
+            self.upgrades.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state
+                .select(Some((i + 1).min(self.upgrades.len().saturating_sub(1))));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Upgrade> {
+        self.upgrades.get(self.state.selected()?)
+    }
+
+    pub fn open(&mut self, after: Details) {
+        if let Some(i) = self.state.selected() {
+            self.opened = Some((i, after));
+            self.scroll = 0;
+        }
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        f.render_widget(Clear, area);
+        if let Some((i, after)) = &self.opened {
+            let upgrade = &self.upgrades[*i];
+            let block = Block::default()
+                .title(format!(
+                    "{} {} → {} — j/k scroll, Esc back",
+                    upgrade.name, upgrade.from, upgrade.to
+                ))
+                .borders(Borders::ALL);
+            let mut lines = vec![Line::from(match upgrade.size_delta() {
+                Some(kib) => format!("Installed size: {}", signed_size(kib)),
+                None => "Installed size: unknown".to_string(),
+            })];
+            match &upgrade.before {
+                Some(before) => {
+                    let diff = field_diff(before, after);
+                    if diff.is_empty() {
+                        lines.push(Line::from("No other field changed"));
+                    }
+                    lines.extend(diff);
+                }
+                None => lines.push(Line::styled(
+                    "Its details weren't loaded before the refresh, so only the version and size can be compared",
+                    Style::default().fg(Color::DarkGray),
+                )),
+            }
+            let paragraph = Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false })
+                .scroll((self.scroll, 0));
+            f.render_widget(paragraph, area);
+            return;
+        }
+
+        let growth: i64 = self.upgrades.iter().filter_map(Upgrade::size_delta).sum();
+        let title = format!(
+            "{} upgraded, {} — Enter what changed, s sort by {}, Esc close",
+            self.upgrades.len(),
+            signed_size(growth),
+            if self.by_delta { "name" } else { "size" }
+        );
+        let width = self
+            .upgrades
+            .iter()
+            .map(|upgrade| upgrade.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let items: Vec<ListItem> = self
+            .upgrades
+            .iter()
+            .map(|upgrade| {
+                let mut spans = vec![Span::raw(format!(
+                    "{:<width$}  {} → {}  ",
+                    upgrade.name,
+                    upgrade.from,
+                    upgrade.to,
+                    width = width
+                ))];
+                if let Some(kib) = upgrade.size_delta() {
+                    let color = if kib < 0 { Color::Green } else { Color::Red };
+                    spans.push(Span::styled(signed_size(kib), Style::default().fg(color)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::commands::Runner;
+use crate::{filelists, format_size, i18n, names, PackageList, PackageManager};
+
+// Columns of the proportional bar.
+const BAR: usize = 20;
+// Eighths of a column, for the bar's last cell.
+const EIGHTHS: [&str; 8] = [" ", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+// `%`: every package of the list by the space it takes, largest first.
+// pkg and apt have dpkg's Installed-Size. pip's is measured on disk, file by
+// file from each package's RECORD, in the background; until a package is
+// measured it shows `…`. Packages whose size can't be told show `?` and go
+// last instead of being left out.
+pub struct UsageView {
+    pub manager: PackageManager,
+    entries: Vec<(String, Option<u64>, bool)>, // KiB, and whether still to be measured
+    pending: usize,                            // pip packages not measured yet
+    state: ListState,
+    receiver: Option<Receiver<(String, Option<u64>)>>,
+    cancel: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl UsageView {
+    pub fn new(list: &PackageList) -> Self {
+        let manager = list.package_manager;
+        let names: Vec<String> = list.items.iter().map(|pkg| pkg.name.clone()).collect();
+        let mut view = Self {
+            manager,
+            entries: Vec::new(),
+            pending: 0,
+            state: ListState::default().with_selected(Some(0)),
+            receiver: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        };
+        if manager == PackageManager::Pip {
+            view.entries = names
+                .iter()
+                .map(|name| (name.clone(), None, true))
+                .collect();
+            view.pending = names.len();
+            view.measure(list.runner.clone(), names);
+        } else {
+            view.entries = names
+                .into_iter()
+                .map(|name| {
+                    let size = list.sizes.get(&name).copied();
+                    (name, size, false)
+                })
+                .collect();
+        }
+        view.sort();
+        view
+    }
+
+    // Sizes pip's files up on a worker thread, one package at a time.
+    fn measure(&mut self, runner: Arc<dyn Runner>, names: Vec<String>) {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = self.cancel.clone();
+        self.receiver = Some(receiver);
+        self.worker = Some(thread::spawn(move || {
+            let lists = filelists::pip(runner.as_ref()).ok();
+            // RECORDs spell names their own way; key both sides the same
+            let key = |name: &str| names::canonical(PackageManager::Pip, name);
+            let lists: HashMap<String, &Vec<String>> = lists
+                .iter()
+                .flat_map(|lists| lists.iter())
+                .map(|(name, paths)| (key(name), paths))
+                .collect();
+            for name in names {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let size = lists.get(&key(&name)).map(|paths| disk_usage(paths));
+                if sender.send((name, size)).is_err() {
+                    return;
+                }
+            }
+        }));
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.pending > 0
+    }
+
+    // Takes in what the worker measured. Returns whether anything changed.
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        let mut measured = HashMap::new();
+        while let Ok((name, size)) = receiver.try_recv() {
+            measured.insert(name, size);
+        }
+        if measured.is_empty() {
+            if self
+                .worker
+                .as_ref()
+                .is_some_and(|worker| worker.is_finished())
+            {
+                // Gone without a word for the rest: those stay unknown
+                self.pending = 0;
+                for (_, _, pending) in self.entries.iter_mut() {
+                    *pending = false;
+                }
+                self.receiver = None;
+                return true;
+            }
+            return false;
+        }
+        let selected = self.selected().map(str::to_string);
+        for (name, size, pending) in self.entries.iter_mut() {
+            if let Some(measured) = measured.remove(name.as_str()) {
+                *size = measured;
+                *pending = false;
+                self.pending = self.pending.saturating_sub(1);
+            }
+        }
+        self.sort();
+        // The selection stays on its package as the order changes
+        if let Some(selected) = selected {
+            let at = self
+                .entries
+                .iter()
+                .position(|(name, _, _)| *name == selected);
+            self.state.select(at.or(Some(0)));
+        }
+        true
+    }
+
+    // Stops the worker; returns it, to be joined on exit.
+    pub fn close(&mut self) -> Option<JoinHandle<()>> {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.receiver = None;
+        self.worker.take()
+    }
+
+    fn sort(&mut self) {
+        self.entries.sort_by(|(a, a_size, _), (b, b_size, _)| {
+            let unknown = |size: &Option<u64>| size.is_none();
+            unknown(a_size)
+                .cmp(&unknown(b_size))
+                .then(b_size.cmp(a_size))
+                .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        });
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        let i = self.state.selected()?;
+        self.entries.get(i).map(|(name, _, _)| name.as_str())
+    }
+
+    pub fn select_by(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        let at = self.state.selected().unwrap_or(0);
+        self.state
+            .select(Some(at.saturating_add_signed(delta).min(last)));
+    }
+
+    pub fn select_first(&mut self) {
+        self.state.select(Some(0));
+    }
+
+    pub fn select_last(&mut self) {
+        self.state
+            .select(Some(self.entries.len().saturating_sub(1)));
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let known: Vec<u64> = self
+            .entries
+            .iter()
+            .filter_map(|(_, size, _)| *size)
+            .collect();
+        let total: u64 = known.iter().sum();
+        let largest = known.iter().max().copied().unwrap_or(0);
+        let measured = self.entries.len() - self.pending;
+        let title = if self.is_running() {
+            i18n::fill(
+                "usage.measuring",
+                &[
+                    ("manager", &self.manager.name()),
+                    ("done", &measured),
+                    ("count", &self.entries.len()),
+                ],
+            )
+        } else {
+            i18n::fill("usage.title", &[("manager", &self.manager.name())])
+        };
+        let unknown = self.entries.len() - known.len() - self.pending;
+        let mut summary = i18n::fill(
+            "usage.total",
+            &[("size", &format_size(total)), ("count", &known.len())],
+        );
+        if unknown > 0 {
+            summary.push_str(&i18n::fill("usage.unknown", &[("count", &unknown)]));
+        }
+
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|(name, size, pending)| {
+                let (label, bar) = match size {
+                    Some(kib) => (format_size(*kib), bar(*kib, largest)),
+                    None if *pending => ("…".to_string(), " ".repeat(BAR)),
+                    None => ("?".to_string(), " ".repeat(BAR)),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:>10}  ", label)),
+                    Span::styled(bar, Style::default().fg(Color::Yellow)),
+                    Span::raw(format!("  {}", name)),
+                ]))
+            })
+            .collect();
+
+        let block = Block::default().title(title).borders(Borders::ALL);
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        let [summary_area, list_area] = *Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .split(inner)
+        else {
+            return;
+        };
+        f.render_widget(
+            Paragraph::new(summary).style(Style::default().add_modifier(Modifier::BOLD)),
+            summary_area,
+        );
+        let list =
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, list_area, &mut self.state);
+    }
+}
+
+// Bytes of the files that are there, rounded up to KiB like dpkg's sizes.
+fn disk_usage(paths: &[String]) -> u64 {
+    let bytes: u64 = paths
+        .iter()
+        .filter_map(|path| fs::symlink_metadata(path).ok())
+        .filter(|meta| !meta.is_dir())
+        .map(|meta| meta.len())
+        .sum();
+    bytes.div_ceil(1024)
+}
+
+// `kib` against the largest, in whole and eighth columns.
+fn bar(kib: u64, largest: u64) -> String {
+    if largest == 0 {
+        return " ".repeat(BAR);
+    }
+    let eighths = (kib.min(largest) * (BAR as u64) * 8 / largest) as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths / 8 < BAR {
+        bar.push_str(EIGHTHS[eighths % 8]);
+        bar.push_str(&" ".repeat(BAR - eighths / 8 - 1));
+    }
+    bar
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::Package;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+// How a package differs from the previous refresh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Added,
+    Upgraded, // Any version change, downgrades included
+    Removed,  // Kept in the list, struck through, until the next refresh
+}
+
+// Periodic re-listing of the active manager.
+pub struct Watch {
+    pub interval: Duration,
+    next: Instant,
+    pub last_refresh: Option<u64>, // Unix seconds
+}
+
+impl Watch {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Instant::now() + interval,
+            last_refresh: None,
+        }
+    }
+
+    // Time left until the next refresh is due.
+    pub fn timeout(&self) -> Duration {
+        self.next.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.next
+    }
+
+    pub fn refreshed(&mut self) {
+        self.next = Instant::now() + self.interval;
+        self.last_refresh = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+    }
+
+    // Skips the refresh that is due, e.g. while a popup is open.
+    pub fn postpone(&mut self) {
+        self.next = Instant::now() + self.interval;
+    }
+}
+
+// Compares a fresh listing with the previous one. Returns the new items, with
+// packages that disappeared kept in their old position for one more cycle, and
+// the change of every package that differs. Packages already shown as removed
+// are dropped for good.
+pub fn diff(
+    old: &[Package],
+    old_changes: &HashMap<String, Change>,
+    new: Vec<Package>,
+) -> (Vec<Package>, HashMap<String, Change>) {
+    let old_versions: HashMap<&str, &str> = old
+        .iter()
+        .filter(|pkg| old_changes.get(&pkg.name) != Some(&Change::Removed))
+        .map(|pkg| (pkg.name.as_str(), pkg.version.as_str()))
+        .collect();
+    let new_names: HashSet<&str> = new.iter().map(|pkg| pkg.name.as_str()).collect();
+
+    let mut changes = HashMap::new();
+    for pkg in &new {
+        match old_versions.get(pkg.name.as_str()) {
+            None => {
+                changes.insert(pkg.name.clone(), Change::Added);
+            }
+            Some(version) if *version != pkg.version => {
+                changes.insert(pkg.name.clone(), Change::Upgraded);
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Each removed package follows the last package before it that is still
+    // installed (None: it goes first).
+    let mut ghosts: Vec<(Option<String>, Package)> = Vec::new();
+    let mut anchor: Option<String> = None;
+    for pkg in old {
+        if old_changes.get(&pkg.name) == Some(&Change::Removed) {
+            continue;
+        }
+        if new_names.contains(pkg.name.as_str()) {
+            anchor = Some(pkg.name.clone());
+        } else {
+            changes.insert(pkg.name.clone(), Change::Removed);
+            ghosts.push((anchor.clone(), pkg.clone()));
+        }
+    }
+    if ghosts.is_empty() {
+        return (new, changes);
+    }
+
+    let mut items = Vec::with_capacity(new.len() + ghosts.len());
+    let take = |ghosts: &mut Vec<(Option<String>, Package)>, after: Option<&str>| {
+        let (matching, rest): (Vec<_>, Vec<_>) = std::mem::take(ghosts)
+            .into_iter()
+            .partition(|(anchor, _)| anchor.as_deref() == after);
+        *ghosts = rest;
+        matching.into_iter().map(|(_, pkg)| pkg).collect::<Vec<_>>()
+    };
+    items.extend(take(&mut ghosts, None));
+    for pkg in new {
+        let name = pkg.name.clone();
+        items.push(pkg);
+        items.extend(take(&mut ghosts, Some(&name)));
+    }
+    (items, changes)
+}
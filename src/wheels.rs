@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::names;
+
+// How a pip distribution got onto the device, judged by the platform tags in
+// its dist-info WHEEL file. pip tags wheels it builds from an sdist with the
+// bare `linux_<arch>` platform, which indexes don't accept for uploads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Build {
+    Pure,   // py3-none-any: nothing was compiled
+    Wheel,  // A binary wheel built elsewhere (manylinux, musllinux, android, ...)
+    Source, // Compiled here, or installed by setup.py without a WHEEL file
+}
+
+impl Build {
+    // Stable identifier for saved sessions.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Build::Pure => "pure",
+            Build::Wheel => "wheel",
+            Build::Source => "source",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [Build::Pure, Build::Wheel, Build::Source]
+            .into_iter()
+            .find(|build| build.id() == id)
+    }
+}
+
+pub struct BuildInfo {
+    pub build: Build,
+    pub tags: Vec<String>,
+    pub generator: Option<String>,
+}
+
+impl BuildInfo {
+    // "Build: from source (cp311-cp311-linux_aarch64, bdist_wheel (0.41.2))"
+    pub fn text(&self) -> String {
+        let how = match self.build {
+            Build::Pure => "pure Python wheel",
+            Build::Wheel => "binary wheel",
+            Build::Source => "from source",
+        };
+        let mut details = self.tags.clone();
+        details.extend(self.generator.clone());
+        if details.is_empty() {
+            return format!("Build: {} (no WHEEL file)\n", how);
+        }
+        format!("Build: {} ({})\n", how, details.join(", "))
+    }
+}
+
+// `name<TAB>wheel<TAB>line` entries of the METADATA command, one per line of
+// the WHEEL file, keyed by canonical name. Distributions printed without any
+// are legacy installs.
+pub fn parse(stdout: &str) -> HashMap<String, BuildInfo> {
+    let mut wheels: HashMap<String, Vec<&str>> = HashMap::new();
+    for line in stdout.lines().skip(1) {
+        let mut fields = line.splitn(3, '\t');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(name), Some("dist"), _) => {
+                wheels.entry(names::pip(name)).or_default();
+            }
+            (Some(name), Some("wheel"), Some(header)) => {
+                wheels.entry(names::pip(name)).or_default().push(header);
+            }
+            _ => {}
+        }
+    }
+    wheels
+        .into_iter()
+        .map(|(name, headers)| (name, classify(&headers)))
+        .collect()
+}
+
+fn classify(headers: &[&str]) -> BuildInfo {
+    let value = |line: &&str, key: &str| line.strip_prefix(key).map(|v| v.trim().to_string());
+    let tags: Vec<String> = headers
+        .iter()
+        .filter_map(|line| value(line, "Tag:"))
+        .collect();
+    let generator = headers.iter().find_map(|line| value(line, "Generator:"));
+    let platforms: Vec<&str> = tags
+        .iter()
+        .filter_map(|tag| tag.rsplit('-').next())
+        .collect();
+    let build = if headers.is_empty() || platforms.iter().any(|p| p.starts_with("linux_")) {
+        Build::Source
+    } else if platforms.iter().all(|p| *p == "any") {
+        Build::Pure
+    } else {
+        Build::Wheel
+    };
+    BuildInfo {
+        build,
+        tags,
+        generator,
+    }
+}
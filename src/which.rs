@@ -0,0 +1,77 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::commands::Runner;
+use crate::{filelists, prefix, PackageManager};
+
+// A command traced to the file that runs and the package that installed it.
+pub struct Found {
+    pub path: PathBuf,                           // What `command -v` would print
+    pub target: Option<PathBuf>,                 // Where its symlinks lead, when somewhere else
+    pub owner: Option<(PackageManager, String)>, // Apt stands for either dpkg manager
+}
+
+// The first executable called `command` on PATH ($PREFIX/bin without one).
+// Anything with a slash is taken as a path.
+pub fn resolve(command: &str) -> Result<PathBuf, String> {
+    if command.contains('/') {
+        let path = PathBuf::from(command);
+        return if path.exists() {
+            Ok(path)
+        } else {
+            Err(format!("{}: no such file", command))
+        };
+    }
+    path_dirs()
+        .iter()
+        .map(|dir| dir.join(command))
+        .find(|path| is_executable(path))
+        .ok_or_else(|| format!("{}: not found on PATH", command))
+}
+
+// The directories searched for commands, in order.
+pub fn path_dirs() -> Vec<PathBuf> {
+    match std::env::var_os("PATH").filter(|path| !path.is_empty()) {
+        Some(path) => std::env::split_paths(&path).collect(),
+        None => vec![Path::new(&prefix()).join("bin")],
+    }
+}
+
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+// Looks the command up in dpkg's file lists, then in pip's RECORDs. The path
+// on PATH is tried before its symlink target, since a package that ships the
+// link (python → python3.12) is the one the user asked about.
+pub fn find(runner: &dyn Runner, command: &str) -> Result<Found, String> {
+    let path = resolve(command)?;
+    let target = fs::canonicalize(&path)
+        .ok()
+        .filter(|target| *target != path);
+
+    let cancel = AtomicBool::new(false);
+    let lists: Vec<_> = [PackageManager::Apt, PackageManager::Pip]
+        .into_iter()
+        .filter_map(|manager| match filelists::load(manager, runner, &cancel) {
+            Some(Ok(lists)) => Some((manager, lists)),
+            _ => None,
+        })
+        .collect();
+    let owner = std::iter::once(&path).chain(&target).find_map(|candidate| {
+        let candidate = candidate.to_string_lossy();
+        lists.iter().find_map(|(manager, lists)| {
+            lists
+                .iter()
+                .find(|(_, paths)| paths.iter().any(|path| *path == candidate))
+                .map(|(name, _)| (*manager, name.clone()))
+        })
+    });
+    Ok(Found {
+        path,
+        target,
+        owner,
+    })
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::commands::{self, Action, Runner};
+use crate::{dpkglog, names, PackageManager};
+
+// Why a package is installed, in the spirit of `aptitude why`: the shortest
+// dependency chain from something installed on purpose down to it. For dpkg
+// the chains start at apt's manually installed set; for pip at distributions
+// nothing else requires.
+pub struct Explainer {
+    pub graph: Graph,
+    pub roots: HashSet<String>,
+    base: HashSet<String>, // Essential, required or important: the base system
+    parent: HashMap<String, String>, // Next package up the shortest chain to a root
+    installed_on: HashMap<String, String>, // Date of the last install per dpkg.log
+    manual: bool,          // Roots are apt's manual set
+}
+
+// Installed packages and what they need. `edges` is everything that keeps a
+// package installed (apt keeps recommended packages too); `clauses` are the
+// hard dependencies, each satisfied by any one of its installed alternatives.
+#[derive(Default)]
+pub struct Graph {
+    pub edges: HashMap<String, Vec<String>>,
+    pub clauses: HashMap<String, Vec<Vec<String>>>,
+}
+
+impl Explainer {
+    pub fn load(runner: &dyn Runner, manager: PackageManager) -> Option<Self> {
+        let (graph, base) = match manager {
+            PackageManager::Pkg | PackageManager::Apt => {
+                parse_depends(&stdout(runner, manager, Action::Depends)?)
+            }
+            PackageManager::Pip => (
+                parse_requires(&stdout(runner, manager, Action::Depends)?),
+                HashSet::new(),
+            ),
+            PackageManager::Luarocks
+            | PackageManager::Composer
+            | PackageManager::Opam
+            | PackageManager::Cargo
+            | PackageManager::Npm => return None,
+        };
+
+        let manual = manager != PackageManager::Pip;
+        let roots: HashSet<String> = if manual {
+            stdout(runner, manager, Action::Manual)?
+                .lines()
+                .map(|line| strip_arch(line.trim()).to_string())
+                .filter(|name| graph.edges.contains_key(name))
+                .chain(base.iter().cloned())
+                .collect()
+        } else {
+            let required: HashSet<&String> = graph.edges.values().flatten().collect();
+            graph
+                .edges
+                .keys()
+                .filter(|name| !required.contains(name))
+                .cloned()
+                .collect()
+        };
+
+        // Breadth-first from every root at once, so each package's parent lies
+        // on a shortest chain. Roots are sorted to keep the chosen chain stable.
+        let mut queue: VecDeque<&String> = {
+            let mut sorted: Vec<&String> = roots.iter().collect();
+            sorted.sort();
+            sorted.into()
+        };
+        let mut parent = HashMap::new();
+        let mut seen: HashSet<&String> = roots.iter().collect();
+        while let Some(name) = queue.pop_front() {
+            for dep in graph.edges.get(name).into_iter().flatten() {
+                if seen.insert(dep) {
+                    parent.insert(dep.clone(), name.clone());
+                    queue.push_back(dep);
+                }
+            }
+        }
+
+        let installed_on = if manual {
+            install_dates(runner, &roots)
+        } else {
+            HashMap::new()
+        };
+        Some(Self {
+            parent,
+            graph,
+            roots,
+            base,
+            installed_on,
+            manual,
+        })
+    }
+
+    // One line for the detail pane, or None when the graph doesn't know the
+    // package at all.
+    pub fn explain(&self, name: &str) -> Option<String> {
+        let name = self.key(name);
+        if !self.graph.edges.contains_key(&name) {
+            return None;
+        }
+        if self.base.contains(&name) {
+            return Some("part of the base system (essential, required or important)".to_string());
+        }
+        if self.roots.contains(&name) {
+            if !self.manual {
+                return Some("nothing else requires it, so it was installed directly".to_string());
+            }
+            return Some(match self.installed_on.get(&name) {
+                Some(date) => format!("manually installed by you (on {} per dpkg.log)", date),
+                None => "manually installed by you".to_string(),
+            });
+        }
+        let mut chain = vec![name.as_str()];
+        let mut current = name.as_str();
+        while let Some(up) = self.parent.get(current) {
+            chain.push(up);
+            current = up;
+        }
+        if chain.len() == 1 {
+            return Some("nothing explains this package — candidate for removal".to_string());
+        }
+        chain.reverse();
+        Some(chain.join(" → "))
+    }
+
+    // A listed package's name as the graph has it.
+    pub fn key(&self, name: &str) -> String {
+        if self.manual {
+            strip_arch(name).to_string()
+        } else {
+            names::pip(name)
+        }
+    }
+
+    // Whether removal frees what nothing needs anymore (apt's autoremove);
+    // pip leaves such packages behind.
+    pub fn autoremoves(&self) -> bool {
+        self.manual
+    }
+}
+
+fn stdout(runner: &dyn Runner, manager: PackageManager, action: Action) -> Option<String> {
+    let output = commands::run_with(runner, manager, action, "").ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn strip_arch(name: &str) -> &str {
+    name.split(':').next().unwrap_or(name)
+}
+
+// `package<TAB>status<TAB>pre-depends, depends<TAB>recommends<TAB>provides<TAB>
+// essential<TAB>priority` from dpkg-query, as the graph and the base system.
+// Apt keeps recommended packages, so they explain too.
+// Dependencies on virtual packages point at the installed packages providing
+// them, and every installed alternative of `a | b` counts.
+fn parse_depends(stdout: &str) -> (Graph, HashSet<String>) {
+    let mut entries = Vec::new();
+    let mut base = HashSet::new();
+    let mut provided: HashMap<String, Vec<String>> = HashMap::new();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [name, status, depends, recommends, provides, essential, priority] = fields[..] else {
+            continue;
+        };
+        // Removed packages with configuration left behind satisfy nothing
+        if matches!(status.chars().nth(1), Some('n' | 'c') | None) {
+            continue;
+        }
+        for virtual_name in relation_names(provides) {
+            provided
+                .entry(virtual_name.to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+        if essential == "yes" || matches!(priority, "required" | "important") {
+            base.insert(name.to_string());
+        }
+        entries.push((name, depends, recommends));
+    }
+
+    let installed: HashSet<&str> = entries.iter().map(|(name, _, _)| *name).collect();
+    // The installed packages that satisfy one relation
+    let targets = |name: &str, relation: &str| -> Vec<String> {
+        relation_names(relation)
+            .flat_map(|dep| {
+                if installed.contains(dep) {
+                    vec![dep.to_string()]
+                } else {
+                    provided.get(dep).cloned().unwrap_or_default()
+                }
+            })
+            .filter(|target| target != name)
+            .collect()
+    };
+    let mut graph = Graph::default();
+    for (name, depends, recommends) in &entries {
+        let clauses: Vec<Vec<String>> = depends
+            .split(',')
+            .map(|clause| targets(name, clause))
+            .filter(|clause| !clause.is_empty())
+            .collect();
+        let mut deps: Vec<String> = Vec::new();
+        for target in clauses
+            .iter()
+            .flatten()
+            .cloned()
+            .chain(targets(name, recommends))
+        {
+            if !deps.contains(&target) {
+                deps.push(target);
+            }
+        }
+        graph.edges.insert(name.to_string(), deps);
+        graph.clauses.insert(name.to_string(), clauses);
+    }
+    (graph, base)
+}
+
+// Package names in a dpkg relationship field such as
+// `libc (>= 2.0), libfoo | libbar, python3:any`.
+fn relation_names(field: &str) -> impl Iterator<Item = &str> {
+    field
+        .split([',', '|'])
+        .filter_map(|relation| relation.split_whitespace().next())
+        .map(|name| strip_arch(name.split('(').next().unwrap_or(name)))
+        .filter(|name| !name.is_empty())
+}
+
+// `name<TAB>requirement,requirement` lines, names already normalized. Every
+// requirement is a clause of its own.
+pub fn parse_requires(stdout: &str) -> Graph {
+    let mut edges: HashMap<String, Vec<String>> = stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, requires) = line.split_once('\t')?;
+            let requires = requires
+                .split(',')
+                .filter(|req| !req.is_empty())
+                .map(String::from)
+                .collect();
+            Some((name.to_string(), requires))
+        })
+        .collect();
+    // Requirements that aren't installed can't explain anything
+    let installed: HashSet<String> = edges.keys().cloned().collect();
+    for requires in edges.values_mut() {
+        requires.retain(|req| installed.contains(req));
+    }
+    let clauses = edges
+        .iter()
+        .map(|(name, requires)| {
+            let clauses = requires.iter().map(|req| vec![req.clone()]).collect();
+            (name.clone(), clauses)
+        })
+        .collect();
+    Graph { edges, clauses }
+}
+
+// Date of the most recent `install` of each root in dpkg's logs.
+fn install_dates(runner: &dyn Runner, roots: &HashSet<String>) -> HashMap<String, String> {
+    let log = dpkglog::Log::load(runner);
+    roots
+        .iter()
+        .filter_map(|root| Some((root.clone(), log.last_install(root)?.to_string())))
+        .collect()
+}
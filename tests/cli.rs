@@ -0,0 +1,235 @@
+// The headless commands run as a script would run them: the tpi binary,
+// with stub apt and pip programs as the only things on PATH, answering from
+// the fixtures. The stubs use shell builtins alone, so a real apt installed
+// on the machine running the tests can't be picked up instead.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+
+const APT: &str = r#"#!/bin/sh
+cat() { while IFS= read -r line; do printf '%s\n' "$line"; done < "$1"; }
+case "$*" in
+"list --installed") cat "$FIXTURES/apt-list.txt" ;;
+"show bash") printf 'Package: bash\nVersion: 5.2.26-1\n' ;;
+"show apt") echo "E: No packages found" >&2; exit 100 ;;
+*) echo "E: unexpected apt $*" >&2; exit 100 ;;
+esac
+"#;
+
+const PIP: &str = r#"#!/bin/sh
+cat() { while IFS= read -r line; do printf '%s\n' "$line"; done < "$1"; }
+[ -e "$HOME/pip-broken" ] && { echo "pip: bad interpreter" >&2; exit 1; }
+case "$*" in
+"list") cat "$FIXTURES/pip-list.txt" ;;
+*) echo "ERROR: unexpected pip $*" >&2; exit 1 ;;
+esac
+"#;
+
+// A home, PREFIX and PATH for one test, so nothing outside is read.
+fn sandbox(test: &str) -> PathBuf {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    let dir = DIR
+        .get_or_init(|| std::env::temp_dir().join(format!("tpi-cli-tests-{}", std::process::id())))
+        .join(test);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    for (name, script) in [("apt", APT), ("pip", PIP)] {
+        let path = dir.join("bin").join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    dir
+}
+
+fn tpi(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tpi"))
+        .args(args)
+        .env_clear()
+        .env("PATH", dir.join("bin"))
+        .env("HOME", dir)
+        .env("PREFIX", dir)
+        .env("TMPDIR", dir)
+        .env("TPI_MANAGERS", "apt,pip")
+        .env(
+            "FIXTURES",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"),
+        )
+        .stdin(Stdio::null())
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn list_in_each_format() {
+    let dir = sandbox("list");
+    let plain = tpi(&dir, &["list"]);
+    assert_eq!(plain.status.code(), Some(0), "{}", stderr(&plain));
+    assert_eq!(
+        stdout(&plain),
+        "apt 2.7.14\nbash 5.2.26-1\nca-certificates 1:2024.07.02\n\
+         libandroid-support 29-1\nxeyes 1.3.0-2\n"
+    );
+
+    let tsv = tpi(&dir, &["list", "--format", "tsv"]);
+    let lines: Vec<String> = stdout(&tsv).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[4], "xeyes\t1.3.0-2\taarch64\tx11\t");
+
+    let json = tpi(&dir, &["list", "--manager", "pip", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert!(json["schema_version"].is_u64());
+    assert_eq!(json["manager"], "pip");
+    let names: Vec<&str> = json["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pkg| pkg["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["certifi", "pip", "requests"]);
+}
+
+#[test]
+fn leading_manager_is_the_default() {
+    let dir = sandbox("leading");
+    let output = tpi(&dir, &["--manager", "pip", "list"]);
+    assert_eq!(
+        stdout(&output),
+        "certifi 2024.7.4\npip 24.2\nrequests 2.32.3\n"
+    );
+}
+
+#[test]
+fn show_prints_the_managers_output() {
+    let dir = sandbox("show");
+    let output = tpi(&dir, &["show", "bash"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Package: bash\nVersion: 5.2.26-1\n");
+
+    let missing = tpi(&dir, &["show", "nano"]);
+    assert_eq!(missing.status.code(), Some(1));
+    assert_eq!(
+        stderr(&missing),
+        "tpi show: 'nano' is not installed via apt\n"
+    );
+    assert!(missing.stdout.is_empty());
+
+    let failing = tpi(&dir, &["show", "apt"]);
+    assert_eq!(failing.status.code(), Some(1));
+    assert_eq!(stderr(&failing), "tpi show: E: No packages found\n");
+}
+
+#[test]
+fn count_every_manager_and_fail_with_one() {
+    let dir = sandbox("count");
+    let output = tpi(&dir, &["count"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "apt 5\npip 3\n");
+    let tsv = tpi(&dir, &["count", "--format", "tsv"]);
+    assert_eq!(stdout(&tsv), "apt\t5\npip\t3\n");
+
+    fs::write(dir.join("pip-broken"), "").unwrap();
+    let output = tpi(&dir, &["count"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "apt 5\n");
+    assert!(
+        stderr(&output).starts_with("tpi count: "),
+        "{}",
+        stderr(&output)
+    );
+    let json = tpi(&dir, &["count", "--format", "json"]);
+    assert_eq!(json.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(json["managers"][0]["count"], 5);
+    assert!(json["managers"][1]["count"].is_null());
+    assert!(json["managers"][1]["error"].is_string());
+}
+
+#[test]
+fn missing_manager_exits_1() {
+    let dir = sandbox("missing");
+    fs::remove_file(dir.join("bin/apt")).unwrap();
+    let output = tpi(&dir, &["list"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(
+        stderr(&output).starts_with("tpi list: "),
+        "{}",
+        stderr(&output)
+    );
+    // count leaves out what isn't installed
+    let count = tpi(&dir, &["count"]);
+    assert_eq!(count.status.code(), Some(0));
+    assert_eq!(stdout(&count), "pip 3\n");
+}
+
+#[test]
+fn bad_arguments_exit_2() {
+    let dir = sandbox("arguments");
+    for (args, message) in [
+        (
+            &["list", "--format", "xml"][..],
+            "tpi list: --format: expected plain, json or tsv",
+        ),
+        (&["list", "extra"], "tpi list: unexpected argument 'extra'"),
+        (
+            &["list", "--color"],
+            "tpi list: unexpected argument '--color'",
+        ),
+        (&["show"], "tpi show: expected one package name"),
+        (&["show", "a", "b"], "tpi show: expected one package name"),
+        (&["count", "--manager"], "tpi count: --manager: "),
+    ] {
+        let output = tpi(&dir, args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).starts_with(message), "{}", stderr(&output));
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn without_a_terminal_the_browser_points_at_the_commands() {
+    let dir = sandbox("no-terminal");
+    let output = tpi(&dir, &[]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("tpi list"), "{}", stderr(&output));
+    let listed = tpi(&dir, &["--list-if-not-tty"]);
+    assert_eq!(listed.status.code(), Some(0));
+    assert_eq!(stdout(&listed).lines().count(), 5);
+}
+
+#[test]
+fn stats_as_json() {
+    let dir = sandbox("stats");
+    let output = tpi(&dir, &["stats"]);
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["schema_version"].is_u64());
+    assert!(json["upgradable"].is_null());
+    assert_eq!(json["manager"], "apt");
+    let managers: Vec<(&str, u64)> = json["managers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| {
+            (
+                row["manager"].as_str().unwrap(),
+                row["count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(managers, [("apt", 5), ("pip", 3)]);
+    let bad = tpi(&dir, &["stats", "--format", "tsv"]);
+    assert_eq!(bad.status.code(), Some(2));
+    assert_eq!(stderr(&bad), "tpi stats: --format: only json\n");
+}